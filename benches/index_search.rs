@@ -0,0 +1,115 @@
+//! Benchmarks for indexing and searching a synthetic notes directory
+//!
+//! Run with `cargo bench`. The corpus has 2000 notes unless the
+//! NOTELOG_BENCH_NOTES environment variable asks for a different number.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+
+use notelog::db::{DB_FILENAME, Database, SearchQuery};
+use notelog::testsupport::corpus::{RARE_WORD, generate_corpus};
+
+/// Create a notes directory with the benchmark corpus and index it
+fn setup(rt: &Runtime) -> (TempDir, Database) {
+    let notes = env::var("NOTELOG_BENCH_NOTES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(2000);
+
+    let temp_dir = TempDir::new().unwrap();
+    generate_corpus(temp_dir.path(), notes, 42).unwrap();
+
+    let db = rt.block_on(async {
+        let db = Database::initialize(temp_dir.path()).await.unwrap();
+        db.index_notes().await.unwrap();
+        db
+    });
+
+    (temp_dir, db)
+}
+
+/// Remove the database, so that the next run indexes every note
+fn remove_database(notes_dir: &Path) {
+    for entry in fs::read_dir(notes_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(DB_FILENAME))
+        {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
+
+fn bench_indexing(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let (temp_dir, db) = setup(&rt);
+    drop(db);
+    let notes_dir = temp_dir.path();
+
+    let mut group = c.benchmark_group("indexing");
+    group.sample_size(10);
+
+    group.bench_function("index from scratch", |b| {
+        b.iter_batched(
+            || {
+                remove_database(notes_dir);
+                rt.block_on(Database::initialize(notes_dir)).unwrap()
+            },
+            |db| rt.block_on(db.index_notes()).unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+
+    // Nothing changed, so only the modification times are compared
+    let db = rt.block_on(Database::initialize(notes_dir)).unwrap();
+    rt.block_on(db.index_notes()).unwrap();
+    group.bench_function("reindex unchanged", |b| {
+        b.iter(|| rt.block_on(db.index_notes()).unwrap())
+    });
+
+    group.finish();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let (_temp_dir, db) = setup(&rt);
+
+    let mut group = c.benchmark_group("search");
+    for (name, query) in [
+        ("narrow", RARE_WORD),
+        ("broad", "the"),
+        ("tag", "+work"),
+        ("tag and words", "+project release plan"),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                rt.block_on(db.search_notes(&SearchQuery::new(query).limit(25)))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_id_prefix(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let (_temp_dir, db) = setup(&rt);
+
+    let (notes, _) = rt
+        .block_on(db.search_notes(&SearchQuery::new(RARE_WORD)))
+        .unwrap();
+    let id = notes[0].frontmatter().id().unwrap().clone();
+
+    c.bench_function("find_shortest_unique_id_prefix", |b| {
+        b.iter(|| rt.block_on(db.find_shortest_unique_id_prefix(&id)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_indexing, bench_search, bench_id_prefix);
+criterion_main!(benches);
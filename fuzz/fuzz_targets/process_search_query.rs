@@ -0,0 +1,14 @@
+//! Fuzz the search query processor
+//!
+//! Run with `cargo +nightly fuzz run process_search_query` from the
+//! repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use notelog::db::process_search_query;
+
+fuzz_target!(|query: &str| {
+    // Any query may be rejected, but none may panic
+    let _ = process_search_query(query);
+});
@@ -0,0 +1,5 @@
+-- Expose the optional source from the metadata as a column for filtering
+ALTER TABLE notes ADD COLUMN source TEXT
+    GENERATED ALWAYS AS (json_extract(metadata, '$.source')) VIRTUAL;
+
+CREATE INDEX note_source_idx ON notes(source);
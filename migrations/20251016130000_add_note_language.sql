@@ -0,0 +1,61 @@
+-- The dominant language of the note content (ISO 639-1 code), detected at index time
+ALTER TABLE notes ADD COLUMN lang TEXT;
+
+CREATE INDEX note_lang_idx ON notes(lang);
+
+-- Fulltext index with Porter stemming for English notes. Notes in other
+-- languages are only searched using the unstemmed notes_fts table.
+CREATE VIRTUAL TABLE notes_fts_stemmed USING fts5(
+    content,           -- The note content
+    tags,              -- Space-separated tags with + prefix
+    tokenize = 'porter unicode61'
+);
+
+-- Insert trigger for the stemmed FTS table
+CREATE TRIGGER notes_after_insert_fts_stemmed AFTER INSERT ON notes
+WHEN NEW.lang = 'en'
+BEGIN
+    INSERT INTO notes_fts_stemmed(rowid, content, tags)
+    VALUES (
+        NEW.id,
+        NEW.content,
+        (
+            SELECT group_concat('+' || value, ' ')
+            FROM json_each(NEW.metadata, '$.tags')
+        )
+    );
+END;
+
+-- Update trigger for content and language; the note may no longer be English
+CREATE TRIGGER notes_after_update_content_fts_stemmed AFTER UPDATE OF content, lang ON notes BEGIN
+    DELETE FROM notes_fts_stemmed WHERE rowid = OLD.id;
+
+    INSERT INTO notes_fts_stemmed(rowid, content, tags)
+    SELECT
+        NEW.id,
+        NEW.content,
+        (
+            SELECT group_concat('+' || value, ' ')
+            FROM json_each(NEW.metadata, '$.tags')
+        )
+    WHERE NEW.lang = 'en';
+END;
+
+-- Update trigger for metadata (tags)
+CREATE TRIGGER notes_after_update_metadata_fts_stemmed AFTER UPDATE OF metadata ON notes BEGIN
+    UPDATE notes_fts_stemmed
+    SET tags = (
+        SELECT group_concat('+' || value, ' ')
+        FROM json_each(NEW.metadata, '$.tags')
+    )
+    WHERE rowid = NEW.id;
+END;
+
+-- Delete trigger
+CREATE TRIGGER notes_after_delete_fts_stemmed AFTER DELETE ON notes BEGIN
+    DELETE FROM notes_fts_stemmed WHERE rowid = OLD.id;
+END;
+
+-- Reset the modification times so that all notes are re-indexed and their
+-- language is detected
+UPDATE notes SET mtime = '';
@@ -0,0 +1,8 @@
+-- Expose the optional due date from the metadata as a column for reminders
+ALTER TABLE notes ADD COLUMN due TEXT
+    GENERATED ALWAYS AS (json_extract(metadata, '$.due')) VIRTUAL;
+
+CREATE INDEX note_due_idx ON notes(due);
+
+-- The due date was not stored in the metadata before, so reindex all notes
+UPDATE notes SET mtime = '';
@@ -0,0 +1,5 @@
+-- The SHA-256 hash of the note file, to find edits that kept the modification time
+ALTER TABLE notes ADD COLUMN content_hash TEXT;
+
+-- Reindex all notes, so that their hash is stored
+UPDATE notes SET mtime = '';
@@ -0,0 +1,6 @@
+-- Note files that could not be indexed, so that they are not read again until they change
+CREATE TABLE IF NOT EXISTS failed_files (
+    filepath TEXT PRIMARY KEY,
+    mtime TEXT NOT NULL,
+    error TEXT NOT NULL
+);
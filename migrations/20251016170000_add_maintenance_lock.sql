@@ -0,0 +1,6 @@
+-- An advisory lock held by maintenance that rewrites the index (at most one row)
+CREATE TABLE IF NOT EXISTS maintenance_lock (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    holder TEXT NOT NULL,
+    acquired TEXT NOT NULL
+);
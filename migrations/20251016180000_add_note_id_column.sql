@@ -0,0 +1,10 @@
+-- The ID of the note, written at index time, so that lookups by ID prefix can
+-- use a range scan of an index instead of evaluating json_extract for every note
+ALTER TABLE notes ADD COLUMN note_id TEXT;
+
+UPDATE notes SET note_id = json_extract(metadata, '$.id');
+
+-- The expression index was not used by the LIKE queries for prefixes
+DROP INDEX IF EXISTS note_id_idx;
+
+CREATE INDEX note_note_id_idx ON notes(note_id);
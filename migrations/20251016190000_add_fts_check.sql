@@ -0,0 +1,6 @@
+-- When the fulltext indexes were last checked (at most one row), so that
+-- opening the database does not run the full check every time
+CREATE TABLE IF NOT EXISTS fts_check (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    checked TEXT NOT NULL
+);
@@ -0,0 +1,8 @@
+-- The creation time of the note in UTC, written at index time, so that listing
+-- the notes newest first can walk an index. The creation times in the metadata
+-- keep their UTC offsets, so they do not sort correctly as text.
+ALTER TABLE notes ADD COLUMN created_utc TEXT;
+
+UPDATE notes SET created_utc = strftime('%Y-%m-%dT%H:%M:%fZ', json_extract(metadata, '$.created'));
+
+CREATE INDEX note_created_utc_idx ON notes(created_utc);
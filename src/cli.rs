@@ -1,15 +1,216 @@
-use std::path::PathBuf;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 
-use clap::{Args, Parser, Subcommand};
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, TimeZone};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::config::expand_tilde;
+use crate::core::datetime::WeekStart;
+use crate::db::SearchOrder;
+
+/// How the content and tags of a new note are given, shown with `notelog --help`
+/// and `notelog add --help`
+const ADD_HELP: &str = "\
+Words starting with + are tags: `notelog Fixed the build +work +ci` adds the
+note \"Fixed the build\", tagged work and ci.
+
+The content of a new note comes from exactly one of these sources:
+  stdin, if it is not a terminal   echo \"Lorem ipsum\" | notelog +idea
+  a file                           notelog --file draft.md +draft
+  the remaining arguments          notelog Call the dentist +todo
+  the editor                       notelog +journal
+Tags can be given with any of them, but the sources cannot be combined. If the
+content starts with frontmatter, the tags are added to the ones listed there.
+
+Examples:
+  notelog add --title \"Wellerman\" There once was a ship +shanty
+  notelog -d ~/Work/Log Deployed the new API +release
+  notelog add --fetch-title https://example.com
+  notelog add --link abc123 Rolled back the deploy +ops";
+
+const LIST_HELP: &str = "\
+The notes are listed from the search index without updating it first, so
+notes edited outside of notelog show up once another command or a running
+daemon has indexed them.
+
+Examples:
+  notelog list
+  notelog list --plain --format tsv | fzf";
+
+const SEARCH_HELP: &str = "\
+Words starting with + (or tag:) match tags, and source:cli, source:mcp or
+lang:de restrict the results. The LINE column shows the first line of each note
+that contains a search term; --open puts the cursor there in editors that
+support it (vi, vim, nvim, nano, emacs). A query of - is read from stdin.
+
+Examples:
+  notelog search deploy +project
+  notelog search --query-file query.txt
+  cat query.txt | notelog search -
+  notelog search --open \"release notes\"
+  notelog search --open-n 2 +standup
+  notelog search --history
+  notelog search !2";
+
+const DUE_HELP: &str = "\
+Dates are YYYY-MM-DD or relative to today, like 3d or 2w.
+
+Examples:
+  notelog due
+  notelog due --overdue
+  notelog due --within 7d
+  notelog due set abc123 1w
+  notelog due clear abc123";
+
+const TAG_HELP: &str = "\
+Examples:
+  notelog tag stats deploy
+  notelog tag stats +deploy --json
+  notelog tag suggest-cleanup
+  notelog tag suggest-cleanup --apply";
+
+const EXEC_HELP: &str = "\
+Runs the command and saves a note titled with the command line, with its exit
+code, duration and output. The output is shown while the command runs; only
+the last 20 KiB of each of stdout and stderr are kept in the note. The note is
+tagged +exec and with the tags given before `--`. If the command fails, so
+does notelog, after saving the note.
+
+Examples:
+  notelog exec -- cargo test
+  notelog exec +deploy -- ./deploy.sh staging";
+
+const EXPORT_HELP: &str = "\
+Writes a page for each note with an ID, named after the ID, an index page
+with the notes by month (index.html) and a page for each tag. Links to other
+notes point to their pages if they are exported too. Private notes are never
+exported, since they are not in the search index.
+
+Examples:
+  notelog export --format html --output ~/shared-log
+  notelog export --format html --output ~/shared-log --tag project";
+
+const DAEMON_HELP: &str = "\
+Keeps the search index open and up to date, so that `search`, `list` and `cat`
+answer quickly, e.g. in an interactive picker on a large notes directory. The
+daemon runs in the foreground and listens on a socket in the cache directory;
+the commands use it if it is running for the same notes directory, and open
+the index themselves otherwise.
+
+Examples:
+  notelog daemon &
+  notelog daemon status
+  notelog daemon status --metrics
+  notelog daemon stop";
+
+const CONFIG_HELP: &str = "\
+Prints the effective value of each setting and where it was taken from: a
+flag, the selected profile, an environment variable, the configuration file or
+the built-in default. The notes directory is looked up in this order:
+--notes-dir, --profile/NOTELOG_PROFILE, NOTELOG_DIR, the configuration file,
+~/NoteLog.
+
+Examples:
+  notelog config
+  notelog --profile work config
+  notelog config --json";
+
+const CAT_HELP: &str = "\
+In a terminal, headings, emphasis, lists and code are shown with colors and
+long lines are wrapped to the width in $COLUMNS (or 80 characters). When the
+output is piped, the note is printed as plain markdown.
+
+Examples:
+  notelog cat abc123
+  notelog cat abc123 --plain | less";
+
+const TODOS_HELP: &str = "\
+Examples:
+  notelog Renew the passport +todo
+  notelog todos
+  notelog done abc123
+  notelog todos --all";
+
+const MV_HELP: &str = "\
+The note is moved into the year and month directories of its creation time.
+Times are RFC 3339, or local times like \"2025-05-06 23:30\" or dates.
+
+Examples:
+  notelog mv abc123 --created \"2025-05-06 23:30\"
+  notelog mv abc123 --title \"Quarterly planning\"";
+
+const SPLIT_HELP: &str = "\
+The section runs from its heading to the next heading of the same or a higher
+level, and becomes a new note with the tags of the original one (plus the tags
+given). Both notes link to each other.
+
+Examples:
+  notelog split abc123 --heading \"Follow-ups\"
+  notelog split abc123 --heading \"Follow-ups\" +todo --created \"2025-05-07 09:00\"";
+
+const REVIEW_HELP: &str = "\
+The review lists this week's notes grouped by tag, with empty Highlights,
+Lowlights and Next week sections, and is tagged +weekly-review. Weeks start on
+Monday, unless the week_start setting or --week-start say otherwise.
+
+Examples:
+  notelog review --week
+  notelog review --week --no-edit
+  notelog review --week --week-start sunday";
+
+const DIGEST_HELP: &str = "\
+Prints this week's notes grouped by tag, with the totals at the end. With
+--format email, the digest starts with a Subject header and can be piped to
+sendmail or mail.
+
+Examples:
+  notelog digest --week
+  notelog digest --week --format email | sendmail me@example.com";
+
+const PRUNE_HELP: &str = "\
+Deletes the notes that are older than the retention of their tags, set in the
+configuration file:
+
+  [retention]
+  scratch = 30
+
+Notes with a tag that has no retention are kept, e.g. a note tagged both
++scratch and +project.
+
+Examples:
+  notelog prune
+  notelog prune --yes";
+
+const INIT_HELP: &str = "\
+Examples:
+  notelog init
+  notelog init --git ~/Work/Log";
 
 #[derive(Parser)]
 #[command(author, version, about = "A command-line tool for recording notes")]
 #[command(propagate_version = true)]
+#[command(after_help = ADD_HELP)]
 pub struct Cli {
     /// Directory to store notes
     #[arg(short = 'd', long = "notes-dir", global = true)]
     pub notes_dir: Option<PathBuf>,
 
+    /// Profile from the configuration file to use
+    #[arg(short = 'P', long = "profile", global = true)]
+    pub profile: Option<String>,
+
+    /// Do not run the hooks in .notelog/hooks
+    #[arg(long = "no-hooks", global = true)]
+    pub no_hooks: bool,
+
+    /// Create the notes directory (and its parents) if it does not exist
+    #[arg(long = "create-dir", global = true)]
+    pub create_dir: bool,
+
+    /// Allow the home directory, a filesystem root or a directory with many other files as the notes directory
+    #[arg(long = "allow-large-dir", global = true)]
+    pub allow_large_dir: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 
@@ -21,22 +222,162 @@ pub struct Cli {
     #[arg(short = 'f', long = "file", global = true)]
     pub file: Option<PathBuf>,
 
+    /// If the note is just a link, fetch the page title (if no subcommand is provided)
+    #[arg(long = "fetch-title", global = true)]
+    pub fetch_title: bool,
+
+    /// Link to an earlier note by ID prefix; can be repeated (if no subcommand is provided)
+    #[arg(long = "link", value_name = "ID_PREFIX", global = true)]
+    pub link: Vec<String>,
+
+    /// Do not give the note an ID (if no subcommand is provided)
+    #[arg(long = "no-id", global = true)]
+    pub no_id: bool,
+
+    /// Turn tags of several words into dashed tags, e.g. +"code review" into +code-review (if no subcommand is provided)
+    #[arg(long = "fix-tags", global = true)]
+    pub fix_tags: bool,
+
+    /// Read a leading `---` block in the input as the frontmatter of the note (if no subcommand is provided)
+    #[arg(long = "parse-frontmatter", global = true)]
+    pub parse_frontmatter: bool,
+
     /// Note content (if no subcommand is provided, defaults to 'add')
     #[arg(trailing_var_arg = true)]
     pub args: Vec<String>,
 }
 
+impl Cli {
+    /// Get the command to run
+    ///
+    /// If no subcommand is provided, the top-level options and trailing
+    /// arguments are treated as an 'add' command.
+    pub fn into_command(mut self) -> Commands {
+        match self.command.take() {
+            Some(command) => command,
+            None => Commands::Add(self.into()),
+        }
+    }
+}
+
+impl Commands {
+    /// Whether the command only reads the notes
+    ///
+    /// Such commands also work on a read-only notes directory, e.g. a snapshot.
+    pub fn is_query_only(&self) -> bool {
+        match self {
+            Commands::List(_)
+            | Commands::Cat(_)
+            | Commands::Todos(_)
+            | Commands::Digest(_)
+            | Commands::Export(_)
+            | Commands::Profiles
+            | Commands::Config(_) => true,
+            Commands::Daemon(args) => args.action.is_some(),
+            Commands::Search(args) => !args.open && args.open_n.is_none(),
+            Commands::Last(args) => args.print,
+            Commands::Tag(args) => {
+                !matches!(args.action, TagAction::SuggestCleanup { apply: true })
+            }
+            Commands::Due(args) => args.action.is_none(),
+            Commands::Reindex(args) => args.check,
+            Commands::Add(_)
+            | Commands::Mcp(_)
+            | Commands::Backup(_)
+            | Commands::Doctor(_)
+            | Commands::Db(_)
+            | Commands::Init(_)
+            | Commands::Done(_)
+            | Commands::Exec(_)
+            | Commands::Review(_)
+            | Commands::Mv(_)
+            | Commands::Split(_)
+            | Commands::Prune(_)
+            | Commands::Selftest
+            | Commands::Bench(_) => false,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Add a new note
+    #[command(after_help = ADD_HELP)]
     Add(AddArgs),
     /// Starts the Model Context Protocol server
     Mcp(McpArgs),
     /// Find and open the newest note
     Last(LastArgs),
+    /// List the profiles from the configuration file
+    Profiles,
+    /// Show the effective settings and where they come from
+    #[command(after_help = CONFIG_HELP)]
+    Config(ConfigArgs),
+    /// Back up the notes database
+    Backup(BackupArgs),
+    /// Check the notes database for problems
+    Doctor(DoctorArgs),
+    /// Maintain the notes database
+    Db(DbArgs),
+    /// Update the search index with the notes on disk
+    Reindex(ReindexArgs),
+    /// Set up a new notes directory
+    #[command(after_help = INIT_HELP)]
+    Init(InitArgs),
+    /// List the notes, newest first
+    #[command(after_help = LIST_HELP)]
+    List(ListArgs),
+    /// Search the notes, optionally opening a result in the editor
+    #[command(after_help = SEARCH_HELP)]
+    Search(SearchArgs),
+    /// Print a note, formatted for the terminal
+    #[command(after_help = CAT_HELP)]
+    Cat(CatArgs),
+    /// List the notes with a due date, or set or clear the due date of a note
+    #[command(after_help = DUE_HELP)]
+    Due(DueArgs),
+    /// Show how a tag is used
+    #[command(after_help = TAG_HELP)]
+    Tag(TagArgs),
+    /// Mark a note as done, replacing its +todo tag with +done
+    #[command(after_help = TODOS_HELP)]
+    Done(DoneArgs),
+    /// List the notes tagged +todo, oldest first
+    #[command(after_help = TODOS_HELP)]
+    Todos(TodosArgs),
+    /// Change the creation time or filename of a note, moving its file
+    #[command(after_help = MV_HELP)]
+    Mv(MvArgs),
+    /// Move a section of a note into a new note
+    #[command(after_help = SPLIT_HELP)]
+    Split(SplitArgs),
+    /// Write a review of the week as a new note
+    #[command(after_help = REVIEW_HELP)]
+    Review(ReviewArgs),
+    /// Print a summary of this week's notes, e.g. to send by email
+    #[command(after_help = DIGEST_HELP)]
+    Digest(DigestArgs),
+    /// Run a command and save its output as a note
+    #[command(after_help = EXEC_HELP)]
+    Exec(ExecArgs),
+    /// Write the notes as a static HTML site, e.g. to share them
+    #[command(after_help = EXPORT_HELP)]
+    Export(ExportArgs),
+    /// Keep the search index open for faster queries
+    #[command(after_help = DAEMON_HELP)]
+    Daemon(DaemonArgs),
+    /// Delete the notes whose tags expired
+    #[command(after_help = PRUNE_HELP)]
+    Prune(PruneArgs),
+    /// Check the installation by adding, searching and deleting a note in a temporary directory
+    #[command(hide = true)]
+    Selftest,
+    /// Generate synthetic notes in a temporary directory and time indexing and searching them
+    #[command(hide = true)]
+    Bench(BenchArgs),
 }
 
-#[derive(Args)]
+#[derive(Args, Default)]
 pub struct AddArgs {
     /// Title of the note
     #[arg(short = 't', long = "title")]
@@ -46,14 +387,75 @@ pub struct AddArgs {
     #[arg(short = 'f', long = "file")]
     pub file: Option<PathBuf>,
 
+    /// If the note is just a link, fetch the page title and tag the note +link
+    #[arg(long = "fetch-title")]
+    pub fetch_title: bool,
+
+    /// Link to an earlier note by ID prefix in a "Related:" section; can be repeated
+    #[arg(long = "link", value_name = "ID_PREFIX")]
+    pub link: Vec<String>,
+
+    /// Do not give the note an ID; it can then only be found by its path
+    #[arg(long = "no-id")]
+    pub no_id: bool,
+
+    /// Turn tags of several words into dashed tags, e.g. +"code review" into +code-review
+    #[arg(long = "fix-tags")]
+    pub fix_tags: bool,
+
+    /// Read a leading `---` block from stdin or --file as the frontmatter of the note,
+    /// instead of keeping it as content
+    #[arg(long = "parse-frontmatter")]
+    pub parse_frontmatter: bool,
+
     /// Note content
     #[arg(trailing_var_arg = true)]
     pub args: Vec<String>,
 }
 
+impl From<Cli> for AddArgs {
+    fn from(cli: Cli) -> Self {
+        Self {
+            title: cli.title,
+            file: cli.file,
+            fetch_title: cli.fetch_title,
+            link: cli.link,
+            no_id: cli.no_id,
+            fix_tags: cli.fix_tags,
+            parse_frontmatter: cli.parse_frontmatter,
+            args: cli.args,
+        }
+    }
+}
+
 /// Arguments for the mcp command
 #[derive(Args)]
 pub struct McpArgs {
+    /// Print the server instructions, or the instructions of the given tool, and exit
+    #[arg(
+        long = "print-instructions",
+        visible_alias = "instructions",
+        value_name = "TOOL",
+        num_args = 0..=1
+    )]
+    pub print_instructions: Option<Option<String>>,
+
+    /// Serve an additional notes directory as a named store (can be repeated)
+    #[arg(long = "store", value_name = "NAME=DIR", value_parser = parse_store)]
+    pub stores: Vec<(String, PathBuf)>,
+
+    /// Back up each store's database once per day into .backups, keeping the given number of copies
+    #[arg(long = "daily-backups", value_name = "COPIES")]
+    pub daily_backups: Option<NonZeroUsize>,
+
+    /// Delete each store's notes whose tags expired once per day, see `notelog prune`
+    #[arg(long = "daily-prune")]
+    pub daily_prune: bool,
+
+    /// Update the query planner statistics of each store's database when the server shuts down
+    #[arg(long = "optimize-on-exit")]
+    pub optimize_on_exit: bool,
+
     // We need to capture global options to check if they were provided
     /// Title of the note (should not be used with mcp)
     #[arg(short = 't', long = "title", hide = true)]
@@ -68,6 +470,463 @@ pub struct McpArgs {
     pub args: Vec<String>,
 }
 
+/// Parse a `NAME=DIR` store argument
+fn parse_store(value: &str) -> Result<(String, PathBuf), String> {
+    let (name, dir) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=DIR, got '{}'", value))?;
+
+    if name.is_empty() || dir.is_empty() {
+        return Err(format!("expected NAME=DIR, got '{}'", value));
+    }
+
+    Ok((name.to_string(), expand_tilde(Path::new(dir))))
+}
+
+/// Arguments for the backup command
+#[derive(Args)]
+pub struct BackupArgs {
+    /// Directory to write the backup to (default: .backups in the notes directory)
+    #[arg(short = 'o', long = "output", value_name = "DIR")]
+    pub output: Option<PathBuf>,
+
+    /// Also archive the notes as a tarball
+    #[arg(long = "notes")]
+    pub notes: bool,
+}
+
+/// Arguments for the doctor command
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// Rebuild the fulltext index, even if it appears to be consistent
+    #[arg(long = "rebuild-fts")]
+    pub rebuild_fts: bool,
+
+    /// With --rebuild-fts, wait for other notelog processes to finish their maintenance
+    #[arg(long = "wait", requires = "rebuild_fts", overrides_with = "no_wait")]
+    pub wait: bool,
+
+    /// With --rebuild-fts, fail if another notelog process is doing maintenance (the default)
+    #[arg(long = "no-wait", overrides_with = "wait")]
+    pub no_wait: bool,
+}
+
+#[derive(Args)]
+pub struct DbArgs {
+    #[command(subcommand)]
+    pub action: DbAction,
+}
+
+/// Maintenance of the notes database
+#[derive(Subcommand)]
+pub enum DbAction {
+    /// Shrink the database file and optimize the fulltext index, reporting the size before and after
+    Optimize {
+        /// Wait for other notelog processes to finish their maintenance instead of failing
+        #[arg(long = "wait")]
+        wait: bool,
+    },
+}
+
+/// Arguments for the reindex command
+#[derive(Args)]
+pub struct ReindexArgs {
+    /// Only report what would be indexed, without writing to the database
+    #[arg(long = "check")]
+    pub check: bool,
+
+    /// With --check, list the status of every file
+    #[arg(short = 'v', long = "verbose", requires = "check")]
+    pub verbose: bool,
+
+    /// Also compare every indexed note with its file, to find edits that kept the modification time
+    #[arg(long = "verify", conflicts_with = "check")]
+    pub verify: bool,
+}
+
+/// Arguments for the init command
+#[derive(Args)]
+pub struct InitArgs {
+    /// Directory to set up (default: the notes directory)
+    #[arg(value_name = "PATH")]
+    pub path: Option<PathBuf>,
+
+    /// Also create a git repository
+    #[arg(long = "git")]
+    pub git: bool,
+
+    /// Initialize the directory even if it is not empty
+    #[arg(long = "force")]
+    pub force: bool,
+}
+
+/// Output format of the list command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// Aligned columns for reading
+    Text,
+    /// Tab-separated `short_id`, `created`, `title` and `tags` columns
+    Tsv,
+}
+
+/// Arguments for the list command
+#[derive(Args)]
+pub struct ListArgs {
+    /// Output format
+    #[arg(long = "format", value_enum, default_value_t = ListFormat::Text)]
+    pub format: ListFormat,
+
+    /// Omit the header line
+    #[arg(long = "plain")]
+    pub plain: bool,
+}
+
+/// Arguments for the search command
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Maximum number of results to show
+    #[arg(short = 'n', long = "limit", default_value_t = 25)]
+    pub limit: usize,
+
+    /// Skip this many results, e.g. `--offset 25` for the second page
+    #[arg(long = "offset", default_value_t = 0)]
+    pub offset: usize,
+
+    /// Order of the results: relevance, newest or oldest
+    #[arg(long = "order", default_value_t = SearchOrder::Relevance)]
+    pub order: SearchOrder,
+
+    /// Only search the notes below this directory of the notes directory, e.g. `2025/05_May`
+    #[arg(long = "path", value_name = "PREFIX")]
+    pub path: Option<String>,
+
+    /// Also show the number of words and tags of each result
+    #[arg(long = "long")]
+    pub long: bool,
+
+    /// Open the best match in the editor (asks if several match about equally well)
+    #[arg(long = "open", conflicts_with = "open_n")]
+    pub open: bool,
+
+    /// Open the K-th result in the editor
+    #[arg(long = "open-n", value_name = "K")]
+    pub open_n: Option<NonZeroUsize>,
+
+    /// List the recent queries, most recent first (re-run one with `notelog search !N`)
+    #[arg(long = "history", conflicts_with_all = ["open", "open_n", "query", "query_file"])]
+    pub history: bool,
+
+    /// Read the search query from a file (`-` for stdin)
+    #[arg(long = "query-file", value_name = "FILE", conflicts_with = "query")]
+    pub query_file: Option<PathBuf>,
+
+    /// Search query, e.g. `deploy +project source:cli`, `!N` to re-run the N-th most recent query, or `-` to read it from stdin
+    #[arg(required_unless_present_any = ["history", "query_file"], trailing_var_arg = true)]
+    pub query: Vec<String>,
+}
+
+/// Arguments for the due command
+#[derive(Args)]
+pub struct DueArgs {
+    #[command(subcommand)]
+    pub action: Option<DueAction>,
+
+    /// Only list notes that are past their due date
+    #[arg(long = "overdue", conflicts_with = "within")]
+    pub overdue: bool,
+
+    /// Only list notes due within the given time, e.g. `7d` or `2w` (including overdue notes)
+    #[arg(long = "within", value_name = "DURATION", value_parser = parse_days)]
+    pub within: Option<u32>,
+}
+
+/// Changes to the due date of a note
+#[derive(Subcommand)]
+pub enum DueAction {
+    /// Set the due date of a note
+    Set {
+        /// ID prefix of the note
+        id: String,
+        /// Due date as YYYY-MM-DD, or relative to today, e.g. `3d` or `1w`
+        #[arg(value_parser = parse_due_date)]
+        date: NaiveDate,
+    },
+    /// Remove the due date of a note
+    Clear {
+        /// ID prefix of the note
+        id: String,
+    },
+}
+
+/// Arguments for the daemon command
+#[derive(Args)]
+pub struct DaemonArgs {
+    #[command(subcommand)]
+    pub action: Option<DaemonAction>,
+}
+
+/// Managing a running daemon
+#[derive(Subcommand)]
+pub enum DaemonAction {
+    /// Stop the daemon for the notes directory
+    Stop,
+    /// Show whether a daemon is running for the notes directory
+    Status {
+        /// Also show how often searches and indexing ran and how long they took
+        #[arg(long = "metrics")]
+        metrics: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct ConfigArgs {
+    /// Print the settings as JSON
+    #[arg(long = "json")]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct TagArgs {
+    #[command(subcommand)]
+    pub action: TagAction,
+}
+
+/// Reports about a tag
+#[derive(Subcommand)]
+pub enum TagAction {
+    /// Show how often a tag was used per month, and which tags were used with it
+    Stats {
+        /// The tag, with or without the leading +
+        tag: String,
+        /// Print the statistics as JSON
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Suggest merging tags that are probably meant to be the same, e.g. +k8s and +kubernetes
+    SuggestCleanup {
+        /// Ask which of the suggested merges to apply, and retag the notes
+        #[arg(long = "apply")]
+        apply: bool,
+    },
+}
+
+/// Parse a number of days or weeks, e.g. `7d` or `2w`
+///
+/// A plain number is taken as days.
+fn parse_days(value: &str) -> Result<u32, String> {
+    let (number, factor) = match value.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (value.strip_suffix('d').unwrap_or(value), 1),
+    };
+
+    number
+        .parse::<u32>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .ok_or_else(|| format!("expected a duration like 7d or 2w, got '{}'", value))
+}
+
+/// Parse a due date, either as YYYY-MM-DD or relative to today
+fn parse_due_date(value: &str) -> Result<NaiveDate, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    let days = parse_days(value)
+        .map_err(|_| format!("expected YYYY-MM-DD or a duration like 3d, got '{}'", value))?;
+    Local::now()
+        .date_naive()
+        .checked_add_days(Days::new(days.into()))
+        .ok_or_else(|| format!("'{}' is too far in the future", value))
+}
+
+/// Arguments for the done command
+#[derive(Args)]
+pub struct DoneArgs {
+    /// ID prefix of the note
+    pub id: String,
+}
+
+/// Arguments for the todos command
+#[derive(Args)]
+pub struct TodosArgs {
+    /// List the notes tagged +done instead
+    #[arg(long = "done-only", conflicts_with = "all")]
+    pub done_only: bool,
+
+    /// List the notes tagged +todo or +done
+    #[arg(long = "all")]
+    pub all: bool,
+}
+
+/// Arguments for the mv command
+#[derive(Args)]
+#[command(group = clap::ArgGroup::new("change").required(true).multiple(true))]
+pub struct MvArgs {
+    /// ID prefix of the note
+    pub id: String,
+
+    /// New creation time of the note
+    #[arg(long = "created", value_name = "TIME", value_parser = parse_created_time, group = "change")]
+    pub created: Option<DateTime<Local>>,
+
+    /// New title for the filename of the note (the note itself is not changed)
+    #[arg(short = 't', long = "title", group = "change")]
+    pub title: Option<String>,
+}
+
+/// Arguments for the split command
+#[derive(Args)]
+pub struct SplitArgs {
+    /// ID prefix of the note
+    pub id: String,
+
+    /// Heading of the section to move into a new note, e.g. `Follow-ups`
+    #[arg(long = "heading", value_name = "HEADING")]
+    pub heading: String,
+
+    /// Creation time of the new note (default: now)
+    #[arg(long = "created", value_name = "TIME", value_parser = parse_created_time)]
+    pub created: Option<DateTime<Local>>,
+
+    /// Additional tags for the new note, e.g. +todo
+    pub tags: Vec<String>,
+}
+
+/// Parse a creation time as RFC 3339, or as a local time or date
+fn parse_created_time(value: &str) -> Result<DateTime<Local>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Local));
+    }
+
+    let naive = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_time(Default::default()))
+        })
+        .ok_or_else(|| {
+            format!(
+                "expected a time like 2025-05-06 23:30 or 2025-05-06, got '{}'",
+                value
+            )
+        })?;
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("'{}' does not exist in the local time zone", value))
+}
+
+/// Arguments for the review command
+#[derive(Args)]
+pub struct ReviewArgs {
+    /// Review the notes of this week
+    #[arg(long = "week", required = true)]
+    pub week: bool,
+
+    /// Save the review without opening it in the editor
+    #[arg(long = "no-edit")]
+    pub no_edit: bool,
+
+    /// The first day of the week, `monday` or `sunday` (default: the `week_start` setting)
+    #[arg(long = "week-start", value_name = "DAY")]
+    pub week_start: Option<WeekStart>,
+}
+
+/// Output format of the digest command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DigestFormat {
+    /// Plain text
+    Text,
+    /// Plain text with email headers, for sendmail
+    Email,
+}
+
+/// Arguments for the digest command
+#[derive(Args)]
+pub struct DigestArgs {
+    /// Summarize the notes of this week
+    #[arg(long = "week", required = true)]
+    pub week: bool,
+
+    /// Output format
+    #[arg(long = "format", value_enum, default_value_t = DigestFormat::Text)]
+    pub format: DigestFormat,
+
+    /// The first day of the week, `monday` or `sunday` (default: the `week_start` setting)
+    #[arg(long = "week-start", value_name = "DAY")]
+    pub week_start: Option<WeekStart>,
+}
+
+/// Arguments for the exec command
+#[derive(Args)]
+pub struct ExecArgs {
+    /// Tags for the note, e.g. `+ci`
+    #[arg(value_name = "TAGS")]
+    pub tags: Vec<String>,
+
+    /// The command to run and its arguments, after `--`
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    pub command: Vec<String>,
+}
+
+/// Output format of the export command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Static HTML pages
+    Html,
+}
+
+/// Arguments for the export command
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Output format
+    #[arg(long = "format", value_enum, default_value_t = ExportFormat::Html)]
+    pub format: ExportFormat,
+
+    /// Directory to write the pages to (created if it does not exist)
+    #[arg(short = 'o', long = "output", value_name = "DIR")]
+    pub output: PathBuf,
+
+    /// Only export the notes with this tag (can be given several times)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+}
+
+/// Arguments for the cat command
+#[derive(Args)]
+pub struct CatArgs {
+    /// ID prefix of the note
+    pub id: String,
+
+    /// Print the markdown as it is, even in a terminal
+    #[arg(long = "plain")]
+    pub plain: bool,
+}
+
+/// Arguments for the prune command
+#[derive(Args)]
+pub struct PruneArgs {
+    /// Delete the expired notes without asking
+    #[arg(short = 'y', long = "yes")]
+    pub yes: bool,
+}
+
+/// Arguments for the bench command
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Number of notes to generate
+    #[arg(long = "generate", value_name = "N")]
+    pub generate: usize,
+
+    /// Seed for the generated content, to reproduce a corpus
+    #[arg(long = "seed", default_value_t = 42)]
+    pub seed: u64,
+}
+
 /// Arguments for the last command
 #[derive(Args)]
 pub struct LastArgs {
@@ -75,6 +934,10 @@ pub struct LastArgs {
     #[arg(short = 'p', long = "print")]
     pub print: bool,
 
+    /// Find the newest note in the search index instead of the year and month directories
+    #[arg(long = "from-index")]
+    pub from_index: bool,
+
     // We need to capture global options to check if they were provided
     /// Title of the note (should not be used with last)
     #[arg(short = 't', long = "title", hide = true)]
@@ -88,3 +951,164 @@ pub struct LastArgs {
     #[arg(trailing_var_arg = true, hide = true)]
     pub args: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_args(argv: &[&str]) -> AddArgs {
+        match Cli::try_parse_from(argv).unwrap().into_command() {
+            Commands::Add(args) => args,
+            _ => panic!("Expected the add command"),
+        }
+    }
+
+    #[test]
+    fn test_default_command_carries_add_args() {
+        let args = add_args(&[
+            "notelog", "-t", "Title", "-f", "note.md", "some", "+tag", "words",
+        ]);
+
+        assert_eq!(args.title.as_deref(), Some("Title"));
+        assert_eq!(args.file, Some(PathBuf::from("note.md")));
+        assert_eq!(args.args, vec!["some", "+tag", "words"]);
+    }
+
+    #[test]
+    fn test_default_command_matches_explicit_add() {
+        let default = add_args(&["notelog", "--title", "Title", "content"]);
+        let explicit = add_args(&["notelog", "add", "--title", "Title", "content"]);
+
+        assert_eq!(default.title, explicit.title);
+        assert_eq!(default.file, explicit.file);
+        assert_eq!(default.args, explicit.args);
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        use clap::CommandFactory;
+
+        Cli::command().debug_assert();
+
+        // The bare invocation explains tags and the input sources
+        let help = Cli::command().render_help().to_string();
+        assert!(help.contains("Words starting with + are tags"));
+        assert!(help.contains("notelog --file draft.md +draft"));
+    }
+
+    #[test]
+    fn test_query_only_commands() {
+        let is_query_only = |argv: &[&str]| {
+            Cli::try_parse_from(argv)
+                .unwrap()
+                .into_command()
+                .is_query_only()
+        };
+
+        assert!(is_query_only(&["notelog", "search", "foo"]));
+        assert!(!is_query_only(&["notelog", "prune", "--yes"]));
+        assert!(is_query_only(&["notelog", "last", "--print"]));
+        assert!(is_query_only(&["notelog", "due"]));
+        assert!(is_query_only(&["notelog", "digest", "--week"]));
+        assert!(is_query_only(&["notelog", "tag", "suggest-cleanup"]));
+        assert!(!is_query_only(&[
+            "notelog",
+            "tag",
+            "suggest-cleanup",
+            "--apply"
+        ]));
+        assert!(is_query_only(&["notelog", "tag", "stats", "deploy"]));
+        assert!(!is_query_only(&["notelog", "search", "--open", "foo"]));
+        assert!(!is_query_only(&["notelog", "last"]));
+        assert!(!is_query_only(&["notelog", "due", "clear", "abc"]));
+        assert!(is_query_only(&["notelog", "daemon", "status"]));
+        assert!(is_query_only(&["notelog", "daemon", "status", "--metrics"]));
+        assert!(is_query_only(&["notelog", "config", "--json"]));
+        assert!(!is_query_only(&["notelog", "daemon"]));
+        assert!(!is_query_only(&["notelog", "some", "note"]));
+    }
+
+    #[test]
+    fn test_search_query_sources() {
+        let search_args = |argv: &[&str]| match Cli::try_parse_from(argv)?.into_command() {
+            Commands::Search(args) => Ok::<_, clap::Error>(args),
+            _ => panic!("Expected the search command"),
+        };
+
+        let args = search_args(&["notelog", "search", "--query-file", "q.txt"]).unwrap();
+        assert_eq!(args.query_file, Some(PathBuf::from("q.txt")));
+        assert!(args.query.is_empty());
+
+        let args = search_args(&["notelog", "search", "-"]).unwrap();
+        assert_eq!(args.query, vec!["-"]);
+        assert_eq!(args.query_file, None);
+
+        // A query file and a query on the command line are mutually exclusive
+        assert!(search_args(&["notelog", "search", "--query-file", "q.txt", "deploy"]).is_err());
+        assert!(search_args(&["notelog", "search", "--history", "--query-file", "q.txt"]).is_err());
+        assert!(search_args(&["notelog", "search"]).is_err());
+    }
+
+    #[test]
+    fn test_mcp_stores() {
+        let cli = Cli::try_parse_from([
+            "notelog",
+            "mcp",
+            "--store",
+            "work=/tmp/work",
+            "--store",
+            "personal=/tmp/notes",
+        ])
+        .unwrap();
+
+        let Commands::Mcp(args) = cli.into_command() else {
+            panic!("Expected the mcp command");
+        };
+        assert_eq!(
+            args.stores,
+            vec![
+                ("work".to_string(), PathBuf::from("/tmp/work")),
+                ("personal".to_string(), PathBuf::from("/tmp/notes")),
+            ]
+        );
+
+        assert!(Cli::try_parse_from(["notelog", "mcp", "--store", "work"]).is_err());
+        assert!(Cli::try_parse_from(["notelog", "mcp", "--store", "=/tmp"]).is_err());
+    }
+
+    #[test]
+    fn test_subcommand_is_kept() {
+        let cli = Cli::try_parse_from(["notelog", "last", "--print"]).unwrap();
+        assert!(matches!(cli.into_command(), Commands::Last(args) if args.print));
+    }
+
+    #[test]
+    fn test_parse_created_time() {
+        let expected = Local.with_ymd_and_hms(2025, 5, 6, 23, 30, 0).unwrap();
+        assert_eq!(parse_created_time("2025-05-06 23:30"), Ok(expected));
+        assert_eq!(parse_created_time("2025-05-06T23:30"), Ok(expected));
+        assert_eq!(parse_created_time(&expected.to_rfc3339()), Ok(expected));
+        assert_eq!(
+            parse_created_time("2025-05-06"),
+            Ok(Local.with_ymd_and_hms(2025, 5, 6, 0, 0, 0).unwrap())
+        );
+        assert!(parse_created_time("yesterday").is_err());
+
+        assert!(Cli::try_parse_from(["notelog", "mv", "abc123"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(parse_days("7d"), Ok(7));
+        assert_eq!(parse_days("2w"), Ok(14));
+        assert_eq!(parse_days("3"), Ok(3));
+        assert!(parse_days("d").is_err());
+        assert!(parse_days("1m").is_err());
+
+        assert_eq!(
+            parse_due_date("2025-04-08"),
+            Ok(NaiveDate::from_ymd_opt(2025, 4, 8).unwrap())
+        );
+        assert!(parse_due_date("next week").is_err());
+    }
+}
@@ -2,25 +2,147 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use crate::cli::AddArgs;
+use crate::commands::resolve::resolve_note;
+use crate::config;
+use crate::core::frontmatter::Frontmatter;
+use crate::core::id::Id;
+use crate::core::inline_tags::apply_inline_tags;
+use crate::core::input::{InputSource, resolve_input_source};
 use crate::core::note::Note;
 use crate::core::note_builder::NoteBuilder;
-use crate::core::tags::{Tag, extract_tags_from_args};
-use crate::error::{NotelogError, Result};
-use crate::utils::{open_editor, read_file_content, validate_content, wait_for_user_input};
+use crate::core::tags::{Tag, extract_tags_from_args, fix_multi_word_tags};
+use crate::db::Database;
+use crate::error::{FrontmatterError, NotelogError, Result, TextLocation};
+use crate::hooks::{Hook, run_hook};
+use crate::link::{HttpFetcher, PageFetcher, fetch_title, single_url};
+use crate::utils::{
+    decode_utf8, open_editor_at, read_file_content, validate_content, wait_for_user_input,
+};
 
 /// Create a note from various input sources and save it
 ///
 /// Returns the path to the created note file on success (relative to notes_dir)
 pub fn add_note(notes_dir: &Path, args: AddArgs, stdin_content: Vec<u8>) -> Result<PathBuf> {
-    let (note, title_override) = create_note_from_input(args, stdin_content)?;
+    // A given title makes fetching the page title pointless
+    let fetch_title = (args.fetch_title || config::get().fetch_link_titles) && args.title.is_none();
+
+    // Resolve the linked notes first, so that a typo does not cost a written note
+    let related = resolve_related_notes(notes_dir, &args.link)?;
+
+    let (mut note, title_override) = create_note_from_input(args, stdin_content)?;
+
+    if fetch_title {
+        note = add_link_title(note, &HttpFetcher)?;
+    }
+
+    if config::get().inline_tags {
+        note = apply_inline_tags(note, config::get().inline_tags_strip)?;
+    }
+
+    if !related.is_empty() {
+        note = Note::new(
+            note.frontmatter().clone(),
+            append_related_section(note.content(), &related),
+        );
+    }
+
+    // Record that the note was added from the command line
+    if note.frontmatter().source().is_none() {
+        note.frontmatter_mut().set_source("cli");
+    }
 
     let relative_path = note.save(notes_dir, title_override.as_deref())?;
     let absolute_path = notes_dir.join(&relative_path);
     println!("Note saved to: {}", absolute_path.display());
 
+    run_hook(notes_dir, Hook::PostAdd, &relative_path, &note);
+
     Ok(relative_path)
 }
 
+/// Look up the notes given with `--link`, in the order given and without duplicates
+///
+/// Returns the title and ID of each note. Fails if a prefix matches no note
+/// or several notes.
+fn resolve_related_notes(notes_dir: &Path, id_prefixes: &[String]) -> Result<Vec<(String, Id)>> {
+    if id_prefixes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let db = Database::open_for_query(notes_dir).await?;
+
+        let mut related: Vec<(String, Id)> = Vec::with_capacity(id_prefixes.len());
+        for id_prefix in id_prefixes {
+            let note = resolve_note(&db, id_prefix).await?;
+            let id = note
+                .frontmatter()
+                .id()
+                .cloned()
+                .ok_or_else(|| NotelogError::NoteNotFound(id_prefix.clone()))?;
+
+            if !related.iter().any(|(_, other)| *other == id) {
+                related.push((note.extract_title(), id));
+            }
+        }
+
+        Ok(related)
+    })
+}
+
+/// Append a "Related:" section that links to the given notes
+fn append_related_section(content: &str, related: &[(String, Id)]) -> String {
+    let mut content = format!("{}\n\nRelated:\n", content.trim_end());
+    for (title, id) in related {
+        content.push_str(&note_link(title, id));
+        content.push('\n');
+    }
+
+    content
+}
+
+/// A list item that links to a note by its `notelog://` URI
+pub(crate) fn note_link(title: &str, id: &Id) -> String {
+    // Brackets in the title would end the link text early
+    let title = title.replace('[', "\\[").replace(']', "\\]");
+    format!("- [{}](notelog://note/{})", title, id)
+}
+
+/// Use the page title as the title of a note that consists of a single link
+///
+/// The note becomes `# <page title>\n\n<url>` and is tagged `+link`, unless it
+/// has tags other than the default tags. If the title cannot be fetched, the
+/// note is left as it is.
+fn add_link_title(note: Note, fetcher: &impl PageFetcher) -> Result<Note> {
+    let Some(url) = single_url(note.content()) else {
+        return Ok(note);
+    };
+
+    let title = match fetch_title(fetcher, url) {
+        Ok(title) => title,
+        Err(e) => {
+            eprintln!("Warning: saving the link without a title: {}", e);
+            return Ok(note);
+        }
+    };
+
+    let content = format!("# {}\n\n{}", title, url);
+    let mut note = Note::new(note.frontmatter().clone(), content);
+
+    let default_tags = config::get().default_tags()?;
+    if note
+        .frontmatter()
+        .tags()
+        .iter()
+        .all(|tag| default_tags.contains(tag))
+    {
+        note.update_tags([Tag::new("link")?], [])?;
+    }
+
+    Ok(note)
+}
+
 /// Helper function to add a markdown header to content if a title is provided and content doesn't already have a header
 ///
 /// Returns a tuple of (content, title_override) where:
@@ -30,8 +152,11 @@ fn add_title_to_content(
     content: String,
     title: Option<&String>,
     tags: &[Tag],
+    generate_id: bool,
 ) -> Result<(Note, Option<String>)> {
-    let mut builder = NoteBuilder::new().tags(tags.to_vec());
+    let mut builder = NoteBuilder::new()
+        .tags(tags.to_vec())
+        .generate_id(generate_id);
 
     if let Some(title) = title {
         // Check if the content already has a markdown header
@@ -62,39 +187,58 @@ pub fn create_note_from_input(
     stdin_content: Vec<u8>,
 ) -> Result<(Note, Option<String>)> {
     // Extract tags from command line arguments
-    let (tags, non_tag_args) = extract_tags_from_args(&args.args)?;
+    let words = if args.fix_tags {
+        fix_multi_word_tags(&args.args)
+    } else {
+        args.args
+    };
+    let (mut tags, non_tag_args) = extract_tags_from_args(&words)?;
+
+    // Add the default tags from the configuration file
+    for tag in config::get().default_tags()? {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let generate_id = !args.no_id && config::get().generate_ids();
 
     // Determine the note content
-    let content = if !stdin_content.is_empty() {
-        // Content from stdin
-        if !non_tag_args.is_empty() {
-            return Err(NotelogError::ConflictingStdinAndArgs);
+    let content = match resolve_input_source(stdin_content, args.file, non_tag_args)? {
+        InputSource::Stdin(stdin_content) => {
+            validate_content(&stdin_content)?;
+            let content = decode_utf8(&stdin_content, "stdin")?.to_string();
+
+            // Pasted text that happens to start with a `---` block, like a
+            // YAML snippet, must not end up as the frontmatter of the note
+            if !args.parse_frontmatter && Frontmatter::starts_with_block(&content) {
+                eprintln!(
+                    "Warning: The input starts with a `---` block, which is kept as content. \
+                     Use --parse-frontmatter to read it as the frontmatter of the note."
+                );
+                return add_title_to_content(content, args.title.as_ref(), &tags, generate_id);
+            }
+            content
         }
-        if args.file.is_some() {
-            return Err(NotelogError::ConflictingInputMethods);
+        InputSource::File(file_path) => {
+            let content = read_file_content(&file_path)?;
+            if args.parse_frontmatter {
+                content
+            } else {
+                // Use the helper function to add a title if needed
+                return add_title_to_content(content, args.title.as_ref(), &tags, generate_id);
+            }
         }
+        InputSource::Args(non_tag_args) => {
+            let content = non_tag_args.join(" ");
 
-        validate_content(&stdin_content)?;
-        String::from_utf8(stdin_content).map_err(|_| NotelogError::InvalidUtf8Content)?
-    } else if let Some(file_path) = &args.file {
-        // Content from file
-        if !non_tag_args.is_empty() {
-            return Err(NotelogError::ConflictingInputMethods);
+            // Use the helper function to add a title if needed
+            return add_title_to_content(content, args.title.as_ref(), &tags, generate_id);
+        }
+        InputSource::Editor => {
+            // Open an editor with frontmatter and any provided tags
+            create_note_from_editor(args.title.as_ref(), &tags, generate_id)?
         }
-
-        let content = read_file_content(file_path)?;
-
-        // Use the helper function to add a title if needed
-        return add_title_to_content(content, args.title.as_ref(), &tags);
-    } else if !non_tag_args.is_empty() {
-        // Content from command line arguments
-        let content = non_tag_args.join(" ");
-
-        // Use the helper function to add a title if needed
-        return add_title_to_content(content, args.title.as_ref(), &tags);
-    } else {
-        // Open an editor with frontmatter and any provided tags
-        create_note_from_editor(args.title.as_ref(), &tags)?
     };
 
     // Get the title override if provided
@@ -107,6 +251,7 @@ pub fn create_note_from_input(
     let builder = NoteBuilder::new()
         .content(content)
         .tags(tags)
+        .generate_id(generate_id)
         .validate(true);
 
     // Try to parse the content as a note, or create a new one if parsing fails
@@ -114,7 +259,12 @@ pub fn create_note_from_input(
         Ok(note) => note,
         Err(_) => {
             // If there was an error, try the helper function as a fallback
-            return add_title_to_content(content_clone, args.title.as_ref(), &tags_clone);
+            return add_title_to_content(
+                content_clone,
+                args.title.as_ref(),
+                &tags_clone,
+                generate_id,
+            );
         }
     };
 
@@ -124,38 +274,48 @@ pub fn create_note_from_input(
 /// Opens an editor for the user to create a note, with optional title and tags
 ///
 /// Handles the editor loop, validation, and user interaction for creating a note
-fn create_note_from_editor(title: Option<&String>, tags: &[Tag]) -> Result<String> {
-    let mut content;
-    let mut initial_content: Option<String> = None;
-
-    loop {
-        // For the first iteration, use the default initial content
-        // For subsequent iterations, use the user's content (even if it has invalid YAML)
-        let editor_content = if let Some(ref user_content) = initial_content {
-            user_content.clone()
-        } else {
-            let base_content = title.map(|t| format!("# {}", t)).unwrap_or_default();
+fn create_note_from_editor(
+    title: Option<&String>,
+    tags: &[Tag],
+    generate_id: bool,
+) -> Result<String> {
+    let base_content = title.map(|t| format!("# {}", t)).unwrap_or_default();
+
+    // Create a builder with the provided tags
+    let mut builder = NoteBuilder::new()
+        .content(base_content)
+        .tags(tags.to_vec())
+        .generate_id(generate_id);
+
+    // Only add the 'edit-me' tag if no tags were provided
+    if tags.is_empty()
+        && let Ok(tag) = Tag::new("edit-me")
+    {
+        builder = builder.tag(tag);
+    }
 
-            // Create a builder with the provided tags
-            let mut builder = NoteBuilder::new().content(base_content).tags(tags.to_vec());
+    // Build the note and get its formatted content
+    // Disable validation for the initial template since it will be empty
+    let initial_content = builder
+        .validate(false)
+        .build()
+        .map(|note| note.formatted_content())
+        .unwrap_or_default();
 
-            // Only add the 'edit-me' tag if no tags were provided
-            if tags.is_empty() {
-                if let Ok(tag) = Tag::new("edit-me") {
-                    builder = builder.tag(tag);
-                }
-            }
+    edit_until_valid(initial_content, title)
+}
 
-            // Build the note and get its formatted content
-            // Disable validation for the initial template since it will be empty
-            builder
-                .validate(false)
-                .build()
-                .map(|note| note.formatted_content())
-                .unwrap_or_default()
-        };
+/// Opens an editor with the given content until the user saves a valid note
+///
+/// Content that is empty, or only the given title, cancels the note. If the
+/// frontmatter is invalid, the user can fix it in the editor again.
+pub(crate) fn edit_until_valid(initial_content: String, title: Option<&String>) -> Result<String> {
+    let mut content;
+    let mut editor_content = initial_content;
+    let mut error_line = None;
 
-        content = open_editor(Some(&editor_content))?;
+    loop {
+        content = open_editor_at(Some(&editor_content), error_line)?;
         content = content.trim().to_string();
 
         // Check if the content is completely blank or just the default title
@@ -176,8 +336,20 @@ fn create_note_from_editor(title: Option<&String>, tags: &[Tag]) -> Result<Strin
             Err(e) => {
                 eprintln!("Error in YAML frontmatter: {}", e);
 
-                // Save the user's content for the next iteration
-                initial_content = Some(content.clone());
+                // Point at the offending line, and start the editor there next time
+                error_line = None;
+                if let NotelogError::FrontmatterError(FrontmatterError::InvalidYaml {
+                    location: Some(location),
+                    ..
+                }) = &e
+                    && let Some(context) = error_context(&content, *location)
+                {
+                    eprintln!("{}", context);
+                    error_line = Some(location.line);
+                }
+
+                // Reopen the editor with the user's content, even though it has invalid YAML
+                editor_content = content.clone();
 
                 // Wait for user to press Enter or Ctrl+C
                 match wait_for_user_input() {
@@ -199,21 +371,29 @@ fn create_note_from_editor(title: Option<&String>, tags: &[Tag]) -> Result<Strin
     Ok(content)
 }
 
+/// Show the line of the content at the location, with a caret under the column
+fn error_context(content: &str, location: TextLocation) -> Option<String> {
+    let line = content.lines().nth(location.line.checked_sub(1)?)?;
+    let indent = " ".repeat(location.column.saturating_sub(1));
+
+    Some(format!(
+        "{:>5} | {}\n      | {}^",
+        location.line, line, indent
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::cli::AddArgs;
+    use chrono::Datelike;
     use std::path::PathBuf;
-    use tempfile::NamedTempFile;
+    use tempfile::{NamedTempFile, TempDir};
 
     #[test]
     fn test_create_note_from_stdin() {
         // Test with content from stdin
-        let args = AddArgs {
-            args: vec![],
-            file: None,
-            title: None,
-        };
+        let args = AddArgs::default();
         let stdin_content = "This is a test note from stdin".as_bytes().to_vec();
 
         let result = create_note_from_input(args, stdin_content).unwrap();
@@ -229,8 +409,7 @@ mod tests {
         // Test with content from stdin and tags in args
         let args = AddArgs {
             args: vec!["+test".to_string(), "+tag2".to_string()],
-            file: None,
-            title: None,
+            ..Default::default()
         };
         let stdin_content = "This is a test note with tags".as_bytes().to_vec();
 
@@ -251,17 +430,41 @@ mod tests {
     fn test_create_note_from_stdin_with_file() {
         // Test with content from stdin and file (should error)
         let args = AddArgs {
-            args: vec![],
             file: Some(PathBuf::from("test.txt")),
-            title: None,
+            ..Default::default()
         };
         let stdin_content = "This is a test note".as_bytes().to_vec();
 
-        let result = create_note_from_input(args, stdin_content);
-        assert!(result.is_err());
+        let err = create_note_from_input(args, stdin_content).unwrap_err();
         assert!(matches!(
-            result.unwrap_err(),
-            NotelogError::ConflictingInputMethods
+            &err,
+            NotelogError::ConflictingInputMethods {
+                a: InputSource::Stdin(_),
+                b: InputSource::File(_),
+            }
+        ));
+        assert!(
+            err.to_string()
+                .contains("provided both via stdin and --file test.txt")
+        );
+    }
+
+    #[test]
+    fn test_create_note_from_stdin_with_args() {
+        // Tags can be combined with stdin, other words cannot
+        let args = AddArgs {
+            args: vec!["+idea".to_string(), "more".to_string(), "words".to_string()],
+            ..Default::default()
+        };
+        let stdin_content = "This is a test note".as_bytes().to_vec();
+
+        let err = create_note_from_input(args, stdin_content).unwrap_err();
+        assert!(matches!(
+            &err,
+            NotelogError::ConflictingInputMethods {
+                a: InputSource::Stdin(_),
+                b: InputSource::Args(args),
+            } if args == &["more", "words"]
         ));
     }
 
@@ -273,9 +476,8 @@ mod tests {
         writeln!(temp_file, "This is a test note from a file")?;
 
         let args = AddArgs {
-            args: vec![],
             file: Some(temp_file.path().to_path_buf()),
-            title: None,
+            ..Default::default()
         };
         let stdin_content = vec![];
 
@@ -297,9 +499,9 @@ mod tests {
         writeln!(temp_file, "This is a test note from a file")?;
 
         let args = AddArgs {
-            args: vec![],
             file: Some(temp_file.path().to_path_buf()),
             title: Some("File Title".to_string()),
+            ..Default::default()
         };
         let stdin_content = vec![];
 
@@ -325,9 +527,9 @@ mod tests {
         writeln!(temp_file, "This is a test note with an existing header")?;
 
         let args = AddArgs {
-            args: vec![],
             file: Some(temp_file.path().to_path_buf()),
             title: Some("File Title".to_string()),
+            ..Default::default()
         };
         let stdin_content = vec![];
 
@@ -351,15 +553,20 @@ mod tests {
         let args = AddArgs {
             args: vec!["some".to_string(), "args".to_string()],
             file: Some(PathBuf::from("test.txt")),
-            title: None,
+            ..Default::default()
         };
         let stdin_content = vec![];
 
-        let result = create_note_from_input(args, stdin_content);
-        assert!(result.is_err());
+        let err = create_note_from_input(args, stdin_content).unwrap_err();
         assert!(matches!(
-            result.unwrap_err(),
-            NotelogError::ConflictingInputMethods
+            &err,
+            NotelogError::ConflictingInputMethods {
+                a: InputSource::File(_),
+                b: InputSource::Args(_),
+            }
+        ));
+        assert!(err.to_string().contains(
+            "provided both via --file test.txt and the command line arguments \"some args\""
         ));
     }
 
@@ -374,8 +581,7 @@ mod tests {
                 "test".to_string(),
                 "note".to_string(),
             ],
-            file: None,
-            title: None,
+            ..Default::default()
         };
         let stdin_content = vec![];
 
@@ -399,8 +605,7 @@ mod tests {
                 "note".to_string(),
                 "+tag2".to_string(),
             ],
-            file: None,
-            title: None,
+            ..Default::default()
         };
         let stdin_content = vec![];
 
@@ -427,8 +632,8 @@ mod tests {
                 "a".to_string(),
                 "test".to_string(),
             ],
-            file: None,
             title: Some("Custom Title".to_string()),
+            ..Default::default()
         };
         let stdin_content = vec![];
 
@@ -450,8 +655,8 @@ mod tests {
                 "Header".to_string(),
                 "content".to_string(),
             ],
-            file: None,
             title: Some("Custom Title".to_string()),
+            ..Default::default()
         };
         let stdin_content = vec![];
 
@@ -475,9 +680,8 @@ tags:
 # Note with existing frontmatter"#;
 
         let args = AddArgs {
-            args: vec![],
-            file: None,
-            title: None,
+            parse_frontmatter: true,
+            ..Default::default()
         };
         let stdin_content = content.as_bytes().to_vec();
 
@@ -505,8 +709,8 @@ tags:
 
         let args = AddArgs {
             args: vec!["+cli-tag".to_string()],
-            file: None,
-            title: None,
+            parse_frontmatter: true,
+            ..Default::default()
         };
         let stdin_content = content.as_bytes().to_vec();
 
@@ -523,6 +727,25 @@ tags:
         assert!(tags.iter().any(|t| t.as_str() == "cli-tag"));
     }
 
+    #[test]
+    fn test_create_note_with_frontmatter_and_duplicate_command_line_tags() {
+        let content = "---\ncreated: 2025-04-01T12:00:00+00:00\ntags:\n  - existing\n  - shared\n---\n\n# Note";
+
+        let args = AddArgs {
+            args: vec!["+shared".to_string(), "+cli-tag".to_string()],
+            parse_frontmatter: true,
+            ..Default::default()
+        };
+
+        let (note, _) = create_note_from_input(args, content.as_bytes().to_vec()).unwrap();
+
+        // The frontmatter tags come first, and a tag given twice is kept once
+        assert_eq!(
+            note.tags_as_strings(),
+            vec!["existing", "shared", "cli-tag"]
+        );
+    }
+
     #[test]
     fn test_create_note_with_empty_frontmatter_and_command_line_tags() {
         // Test with content that has empty frontmatter and tags from command line
@@ -535,8 +758,8 @@ tags: []
 
         let args = AddArgs {
             args: vec!["+cli-tag".to_string()],
-            file: None,
-            title: None,
+            parse_frontmatter: true,
+            ..Default::default()
         };
         let stdin_content = content.as_bytes().to_vec();
 
@@ -551,4 +774,145 @@ tags: []
         assert_eq!(tags.len(), 1);
         assert_eq!(tags[0].as_str(), "cli-tag");
     }
+
+    #[test]
+    fn test_create_note_with_pasted_yaml() {
+        // A pasted YAML snippet that would be valid frontmatter
+        let content =
+            "---\ncreated: 2020-01-01T00:00:00+00:00\ntags: [ci]\n---\n\nThe deploy workflow";
+        let args = |parse_frontmatter| AddArgs {
+            args: vec!["+snippet".to_string()],
+            file: None,
+            title: None,
+            fetch_title: false,
+            link: vec![],
+            no_id: false,
+            fix_tags: false,
+            parse_frontmatter,
+        };
+
+        // Without --parse-frontmatter, the YAML stays part of the content
+        let (note, _) = create_note_from_input(args(false), content.as_bytes().to_vec()).unwrap();
+        assert_eq!(note.content(), content);
+        assert_eq!(note.tags_as_strings(), vec!["snippet"]);
+        assert_ne!(note.frontmatter().created().year(), 2020);
+
+        // ... also after saving and reading the note again
+        let temp_dir = TempDir::new().unwrap();
+        let relative_path = note.save(temp_dir.path(), None).unwrap();
+        let saved = std::fs::read_to_string(temp_dir.path().join(relative_path)).unwrap();
+        let saved = Note::from_str(&saved).unwrap();
+        assert_eq!(saved.content().trim_end(), content);
+        assert_eq!(saved.tags_as_strings(), vec!["snippet"]);
+
+        // With it, the YAML is the frontmatter
+        let (note, _) = create_note_from_input(args(true), content.as_bytes().to_vec()).unwrap();
+        assert_eq!(note.content(), "The deploy workflow");
+        assert_eq!(note.tags_as_strings(), vec!["ci", "snippet"]);
+        assert_eq!(note.frontmatter().created().year(), 2020);
+    }
+
+    /// Serves a fixed page, or fails if there is none
+    struct StubFetcher(Option<&'static str>);
+
+    impl PageFetcher for StubFetcher {
+        fn fetch_page(&self, url: &str) -> Result<String> {
+            self.0
+                .map(str::to_string)
+                .ok_or_else(|| NotelogError::FetchFailed(url.to_string()))
+        }
+    }
+
+    fn link_note(content: &str, tags: &[&str]) -> Note {
+        let args = AddArgs {
+            args: tags.iter().map(|tag| tag.to_string()).collect(),
+            fetch_title: true,
+            ..Default::default()
+        };
+        create_note_from_input(args, content.as_bytes().to_vec())
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_add_link_title() {
+        let fetcher = StubFetcher(Some("<html><title>Example Domain</title></html>"));
+
+        let note = add_link_title(link_note("https://example.com\n", &[]), &fetcher).unwrap();
+        assert_eq!(note.content(), "# Example Domain\n\nhttps://example.com");
+        assert_eq!(note.extract_title(), "Example Domain");
+        assert_eq!(note.tags_as_strings(), vec!["link"]);
+
+        // Given tags replace the +link tag
+        let note = add_link_title(link_note("https://example.com", &["+read"]), &fetcher).unwrap();
+        assert_eq!(note.tags_as_strings(), vec!["read"]);
+
+        // Other content is left alone
+        let note = add_link_title(link_note("see https://example.com", &[]), &fetcher).unwrap();
+        assert_eq!(note.content(), "see https://example.com");
+    }
+
+    #[test]
+    fn test_add_link_title_keeps_url_on_failure() {
+        let note =
+            add_link_title(link_note("https://example.com", &[]), &StubFetcher(None)).unwrap();
+
+        assert_eq!(note.content(), "https://example.com");
+        assert!(note.frontmatter().tags().is_empty());
+    }
+
+    #[test]
+    fn test_error_context() {
+        let content = "---\ncreated: 2025-04-01T12:00:00+00:00\ntags: [foo\n---";
+        let context = error_context(content, TextLocation { line: 3, column: 7 }).unwrap();
+        assert_eq!(context, "    3 | tags: [foo\n      |       ^");
+
+        assert!(error_context(content, TextLocation { line: 9, column: 1 }).is_none());
+        assert!(error_context(content, TextLocation { line: 0, column: 1 }).is_none());
+    }
+
+    #[test]
+    fn test_append_related_section() {
+        let first = Id::new("0123456789abcdef").unwrap();
+        let second = Id::new("fedcba9876543210").unwrap();
+        let related = vec![
+            ("Deploy [staging]".to_string(), first),
+            ("Rollback plan".to_string(), second),
+        ];
+
+        let content = append_related_section("# Incident\n\nThe deploy failed.\n\n", &related);
+        assert_eq!(
+            content,
+            "# Incident\n\nThe deploy failed.\n\nRelated:\n\
+             - [Deploy \\[staging\\]](notelog://note/0123456789abcdef)\n\
+             - [Rollback plan](notelog://note/fedcba9876543210)\n"
+        );
+    }
+    #[test]
+    fn test_resolve_related_notes_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        let mut ids = Vec::new();
+        for title in ["First", "Second"] {
+            let note = Note::new(Frontmatter::default(), format!("# {}\n\nBody", title));
+            note.save(notes_dir, None).unwrap();
+            ids.push(note.frontmatter().id().unwrap().clone());
+        }
+
+        // Command line order, with duplicates dropped
+        let prefixes: Vec<String> = [&ids[1], &ids[0], &ids[1]]
+            .iter()
+            .map(|id| id.as_str()[..6].to_string())
+            .collect();
+        let related = resolve_related_notes(notes_dir, &prefixes).unwrap();
+        let titles: Vec<&str> = related.iter().map(|(title, _)| title.as_str()).collect();
+        assert_eq!(titles, vec!["Second", "First"]);
+        assert_eq!(related[0].1, ids[1]);
+
+        assert!(matches!(
+            resolve_related_notes(notes_dir, &["zzzzzz".to_string()]),
+            Err(NotelogError::NoteNotFound(_))
+        ));
+    }
 }
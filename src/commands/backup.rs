@@ -0,0 +1,125 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::cli::BackupArgs;
+use crate::db::{BACKUPS_DIR, DB_FILENAME, Database};
+use crate::error::Result;
+
+/// Back up the notes database, and optionally the notes
+pub fn backup_notes(notes_dir: &Path, args: BackupArgs) -> Result<()> {
+    let output_dir = args.output.unwrap_or_else(|| notes_dir.join(BACKUPS_DIR));
+    fs::create_dir_all(&output_dir)?;
+
+    let timestamp = Local::now().format("%Y-%m-%dT%H-%M-%S");
+
+    let db_path = output_dir.join(format!("notes-{}.db", timestamp));
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await?;
+        db.backup_to(&db_path).await
+    })?;
+    println!("Database backed up to {}", db_path.display());
+
+    if args.notes {
+        let archive_path = output_dir.join(format!("notes-{}.tar", timestamp));
+        archive_notes(notes_dir, &archive_path)?;
+        println!("Notes archived to {}", archive_path.display());
+    }
+
+    Ok(())
+}
+
+/// Write the notes directory to a tar archive
+///
+/// The database and the backups directory are not included.
+fn archive_notes(notes_dir: &Path, archive_path: &Path) -> Result<()> {
+    let mut builder = tar::Builder::new(File::create(archive_path)?);
+
+    for path in archive_entries(notes_dir, archive_path)? {
+        let name = path.strip_prefix(notes_dir).unwrap_or(&path);
+        if path.is_dir() {
+            builder.append_dir_all(name, &path)?;
+        } else {
+            builder.append_path_with_name(&path, name)?;
+        }
+    }
+
+    builder.finish()?;
+
+    Ok(())
+}
+
+/// Get the top-level entries of the notes directory to archive
+fn archive_entries(notes_dir: &Path, archive_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(notes_dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        // Skip the database (including its -wal and -shm files), the backups
+        // and the archive itself, in case it is written into the notes directory
+        if name.starts_with(DB_FILENAME) || name == BACKUPS_DIR || path == archive_path {
+            continue;
+        }
+
+        entries.push(path);
+    }
+
+    entries.sort();
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_archive_notes_skips_database_and_backups() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        fs::create_dir_all(notes_dir.join("2025").join("01_January")).unwrap();
+        fs::write(
+            notes_dir
+                .join("2025")
+                .join("01_January")
+                .join("2025-01-01T10-00 Note.md"),
+            "note",
+        )
+        .unwrap();
+        fs::write(notes_dir.join(DB_FILENAME), "db").unwrap();
+        fs::create_dir_all(notes_dir.join(BACKUPS_DIR)).unwrap();
+        fs::write(
+            notes_dir.join(BACKUPS_DIR).join("daily-2025-01-01.db"),
+            "db",
+        )
+        .unwrap();
+
+        let archive_path = notes_dir.join(BACKUPS_DIR).join("notes.tar");
+        archive_notes(notes_dir, &archive_path).unwrap();
+
+        let mut archive = tar::Archive::new(File::open(&archive_path).unwrap());
+        let mut names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+
+        assert!(
+            names
+                .iter()
+                .any(|name| name.ends_with("2025-01-01T10-00 Note.md"))
+        );
+        assert!(!names.iter().any(|name| name.contains(DB_FILENAME)));
+        assert!(!names.iter().any(|name| name.contains(BACKUPS_DIR)));
+    }
+}
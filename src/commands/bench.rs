@@ -0,0 +1,47 @@
+use std::time::Instant;
+
+use crate::cli::BenchArgs;
+use crate::db::{Database, SearchQuery};
+use crate::error::Result;
+use notelog::testsupport::corpus::{RARE_WORD, generate_corpus};
+
+/// Generate a synthetic notes directory and time indexing and searching it
+///
+/// The directory is kept, so that the numbers can be reproduced with other
+/// commands, e.g. `notelog -d <dir> search +work`.
+pub fn bench(args: &BenchArgs) -> Result<()> {
+    let notes_dir = tempfile::Builder::new()
+        .prefix("notelog-bench-")
+        .tempdir()?
+        .keep();
+
+    let start = Instant::now();
+    generate_corpus(&notes_dir, args.generate, args.seed)?;
+    println!(
+        "Generated {} notes in {} ({:.2?})",
+        args.generate,
+        notes_dir.display(),
+        start.elapsed()
+    );
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let start = Instant::now();
+        let db = Database::initialize(&notes_dir).await?;
+        db.index_notes().await?;
+        println!("Indexed the notes in {:.2?}", start.elapsed());
+
+        for query in [RARE_WORD, "the", "+work", "+project release plan"] {
+            let start = Instant::now();
+            let (_, total) = db.search_notes(&SearchQuery::new(query).limit(25)).await?;
+            println!(
+                "Searched for {:?}: {} matches in {:.2?}",
+                query,
+                total,
+                start.elapsed()
+            );
+        }
+
+        Ok(())
+    })
+}
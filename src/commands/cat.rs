@@ -0,0 +1,118 @@
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::cli::CatArgs;
+use crate::commands::resolve::resolve_note_path;
+use crate::core::datetime::format_display_date;
+use crate::core::note::Note;
+use crate::daemon::client;
+use crate::db::Database;
+use crate::error::Result;
+use crate::render::{render_markdown, terminal_width};
+
+/// Print a note, rendered with ANSI styles if stdout is a terminal
+///
+/// The frontmatter is replaced by a line with the ID, creation time and tags
+/// of the note. With `--plain`, or if stdout is not a terminal, the content is
+/// printed as it is.
+pub fn cat(notes_dir: &Path, args: CatArgs) -> Result<()> {
+    // A running daemon has the index open already
+    let (path, short_id) = match client::show(notes_dir, &args.id) {
+        Some(found) => found?,
+        None => {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(find_note(notes_dir, &args.id))?
+        }
+    };
+    let note = Note::from_str(&fs::read_to_string(notes_dir.join(&path))?)?;
+    let header = format_header(short_id.as_deref(), &note);
+
+    if args.plain || !io::stdout().is_terminal() {
+        print!("{}\n\n{}", header, with_newline(note.content()));
+    } else {
+        print!(
+            "\x1b[2m{}\x1b[0m\n\n{}",
+            header,
+            render_markdown(note.content(), terminal_width())
+        );
+    }
+
+    Ok(())
+}
+
+/// Find the file of a note in the index, with the shortest unique prefix of its ID
+async fn find_note(notes_dir: &Path, id: &str) -> Result<(PathBuf, Option<String>)> {
+    let db = Database::open_for_query(notes_dir).await?;
+    let path = resolve_note_path(&db, id).await?;
+    let note = Note::from_str(&fs::read_to_string(notes_dir.join(&path))?)?;
+
+    let short_id = match note.frontmatter().id() {
+        Some(id) => Some(db.find_shortest_unique_id_prefix(id).await?),
+        None => None,
+    };
+
+    Ok((path, short_id))
+}
+
+/// Format the ID, creation time and tags of a note as a single line
+fn format_header(short_id: Option<&str>, note: &Note) -> String {
+    let mut parts = Vec::new();
+    if let Some(short_id) = short_id {
+        parts.push(short_id.to_string());
+    }
+    let created = note.frontmatter().created();
+    parts.push(format!(
+        "{} {}",
+        format_display_date(created.date_naive()),
+        created.format("%H:%M")
+    ));
+
+    let tags: Vec<String> = note
+        .frontmatter()
+        .tags()
+        .iter()
+        .map(|tag| format!("+{}", tag.as_str()))
+        .collect();
+    if !tags.is_empty() {
+        parts.push(tags.join(" "));
+    }
+
+    parts.join("  ")
+}
+
+/// The text with a trailing newline
+fn with_newline(text: &str) -> String {
+    if text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frontmatter::Frontmatter;
+    use crate::core::tags::Tag;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_format_header() {
+        let created = Local.with_ymd_and_hms(2025, 5, 6, 23, 30, 0).unwrap();
+        let tags = vec![Tag::new("deploy").unwrap(), Tag::new("ops").unwrap()];
+        let note = Note::new(Frontmatter::new(created, tags), "# Deploy".to_string());
+
+        assert_eq!(
+            format_header(Some("ab12"), &note),
+            "ab12  2025-05-06 23:30  +deploy +ops"
+        );
+
+        let note = Note::new(
+            Frontmatter::new(created, Vec::new()),
+            "# Deploy".to_string(),
+        );
+        assert_eq!(format_header(None, &note), "2025-05-06 23:30");
+    }
+}
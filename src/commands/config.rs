@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::cli::ConfigArgs;
+use crate::config::{self, Resolved, SettingSource, resolved_config_path};
+use crate::db::{DB_FILENAME, WATCH_POLL_INTERVAL};
+use crate::error::{NotelogError, Result};
+use crate::utils::get_notes_dir;
+
+/// The effective settings, with where each was taken from
+#[derive(Debug, Serialize)]
+struct Settings {
+    config_file: Option<Resolved<PathBuf>>,
+    profile: Option<Resolved<String>>,
+    notes_dir: Resolved<PathBuf>,
+    database: Resolved<PathBuf>,
+    editor: Resolved<String>,
+    default_tags: Resolved<Vec<String>>,
+    note_extensions: Resolved<Vec<String>>,
+    watch_poll_interval_secs: Resolved<u64>,
+}
+
+/// Print the effective settings and where they were taken from
+///
+/// This works without a notes directory, so that it can tell why one is not
+/// found.
+pub fn show_config(notes_dir: Option<PathBuf>, args: &ConfigArgs) -> Result<()> {
+    let config = config::get();
+    let notes_dir = get_notes_dir(notes_dir)?;
+
+    let settings = Settings {
+        config_file: resolved_config_path(),
+        profile: config
+            .resolved_profile()
+            .map(|profile| Resolved::new(profile.value.to_string(), profile.source)),
+        database: Resolved::new(notes_dir.value.join(DB_FILENAME), notes_dir.source),
+        notes_dir,
+        editor: config.resolved_editor(),
+        default_tags: config.resolved_default_tags(),
+        note_extensions: config.resolved_note_extensions(),
+        watch_poll_interval_secs: Resolved::new(
+            WATCH_POLL_INTERVAL.as_secs(),
+            SettingSource::Default,
+        ),
+    };
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&settings)
+            .map_err(|e| NotelogError::ConfigError(e.to_string()))?;
+        println!("{}", json);
+    } else {
+        print!("{}", render_settings(&settings));
+    }
+
+    Ok(())
+}
+
+/// Format the settings as aligned lines of name, value and source
+fn render_settings(settings: &Settings) -> String {
+    let config_file = match &settings.config_file {
+        Some(path) if !path.value.exists() => {
+            let value = format!("{} (does not exist)", path.value.display());
+            Some(Resolved::new(value, path.source))
+        }
+        Some(path) => Some(Resolved::new(path.value.display().to_string(), path.source)),
+        None => None,
+    };
+    let path =
+        |path: &Resolved<PathBuf>| Resolved::new(path.value.display().to_string(), path.source);
+    let list = |items: &Resolved<Vec<String>>, prefix: &str| {
+        let value = if items.value.is_empty() {
+            "none".to_string()
+        } else {
+            items
+                .value
+                .iter()
+                .map(|item| format!("{}{}", prefix, item))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        Resolved::new(value, items.source)
+    };
+
+    let rows = [
+        ("config_file", config_file),
+        ("profile", settings.profile.clone()),
+        ("notes_dir", Some(path(&settings.notes_dir))),
+        ("database", Some(path(&settings.database))),
+        ("editor", Some(settings.editor.clone())),
+        ("default_tags", Some(list(&settings.default_tags, "+"))),
+        (
+            "note_extensions",
+            Some(list(&settings.note_extensions, ".")),
+        ),
+        (
+            "watch_poll_interval",
+            Some(Resolved::new(
+                format!("{}s", settings.watch_poll_interval_secs.value),
+                settings.watch_poll_interval_secs.source,
+            )),
+        ),
+    ];
+
+    let mut output = String::new();
+    for (name, setting) in rows {
+        let line = match setting {
+            Some(setting) => format!("{:<20} {} ({})", name, setting.value, setting.source),
+            None => format!("{:<20} none", name),
+        };
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_settings() {
+        let settings = Settings {
+            config_file: None,
+            profile: Some(Resolved::new("work".to_string(), SettingSource::Flag)),
+            notes_dir: Resolved::new(PathBuf::from("/home/me/Work"), SettingSource::Profile),
+            database: Resolved::new(
+                PathBuf::from("/home/me/Work/.notes.db"),
+                SettingSource::Profile,
+            ),
+            editor: Resolved::new("hx".to_string(), SettingSource::Profile),
+            default_tags: Resolved::new(Vec::new(), SettingSource::Default),
+            note_extensions: Resolved::new(
+                vec!["md".to_string(), "txt".to_string()],
+                SettingSource::Environment,
+            ),
+            watch_poll_interval_secs: Resolved::new(20, SettingSource::Default),
+        };
+
+        assert_eq!(
+            render_settings(&settings),
+            "config_file          none\n\
+             profile              work (flag)\n\
+             notes_dir            /home/me/Work (profile)\n\
+             database             /home/me/Work/.notes.db (profile)\n\
+             editor               hx (profile)\n\
+             default_tags         none (default)\n\
+             note_extensions      .md .txt (env)\n\
+             watch_poll_interval  20s (default)\n"
+        );
+
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["notes_dir"]["value"], "/home/me/Work");
+        assert_eq!(json["notes_dir"]["source"], "profile");
+        assert_eq!(json["note_extensions"]["source"], "env");
+        assert_eq!(json["watch_poll_interval_secs"]["value"], 20);
+    }
+}
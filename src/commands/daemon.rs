@@ -0,0 +1,54 @@
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::cli::{DaemonAction, DaemonArgs};
+use crate::daemon::{client, run_daemon, socket_path};
+use crate::error::Result;
+
+/// How long `daemon stop` waits for the daemon to remove its socket
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Run the daemon, or stop or report on the one that is running
+pub fn daemon_command(notes_dir: &Path, args: DaemonArgs) -> Result<()> {
+    match args.action {
+        None => run_daemon(notes_dir),
+        Some(DaemonAction::Stop) => {
+            let Some(stopped) = client::stop(notes_dir) else {
+                println!("No daemon is running for {}", notes_dir.display());
+                return Ok(());
+            };
+            stopped?;
+
+            // Wait until a new daemon can be started
+            let step = Duration::from_millis(20);
+            let mut waited = Duration::ZERO;
+            while waited < STOP_TIMEOUT && socket_path(notes_dir).is_some_and(|path| path.exists())
+            {
+                thread::sleep(step);
+                waited += step;
+            }
+
+            println!("Stopped the daemon for {}", notes_dir.display());
+            Ok(())
+        }
+        Some(DaemonAction::Status { metrics }) => {
+            match client::status(notes_dir).transpose()? {
+                Some(status) => {
+                    println!(
+                        "A daemon is running for {} (pid {}): {} notes indexed, {} files could not be read",
+                        status.notes_dir.display(),
+                        status.pid,
+                        status.notes,
+                        status.failed_files
+                    );
+                    if metrics {
+                        print!("\n{}", status.metrics.report());
+                    }
+                }
+                None => println!("No daemon is running for {}", notes_dir.display()),
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::Path;
+
+use crate::cli::{DbAction, DbArgs};
+use crate::db::{Database, format_size};
+use crate::error::Result;
+
+/// Maintain the notes database
+pub fn db_command(notes_dir: &Path, args: DbArgs) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await?;
+
+        match args.action {
+            DbAction::Optimize { wait } => {
+                // VACUUM rewrites the database, so keep MCP servers from
+                // updating it meanwhile
+                let lock = db.lock_maintenance(wait).await?;
+                let stats = db.maintenance().await;
+                lock.release().await?;
+                let stats = stats?;
+
+                println!(
+                    "Optimized the database in {:.1}s: {} before, {} after",
+                    stats.duration.as_secs_f64(),
+                    format_size(stats.size_before),
+                    format_size(stats.size_after)
+                );
+            }
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,26 @@
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::cli::{DigestArgs, DigestFormat};
+use crate::commands::review::notes_created_between;
+use crate::config;
+use crate::core::datetime::week_bounds;
+use crate::error::Result;
+use crate::output::{Digest, format_digest_email, format_digest_text};
+
+/// Print a summary of this week's notes
+pub fn digest(notes_dir: &Path, args: DigestArgs) -> Result<()> {
+    let week_start = args.week_start.unwrap_or(config::get().week_start);
+    let (start, end) = week_bounds(Local::now().date_naive(), week_start);
+
+    let notes = notes_created_between(notes_dir, start, end)?;
+    let digest = Digest::new(start.date_naive(), &notes);
+
+    match args.format {
+        DigestFormat::Text => print!("{}", format_digest_text(&digest)),
+        DigestFormat::Email => print!("{}", format_digest_email(&digest, Local::now())),
+    }
+
+    Ok(())
+}
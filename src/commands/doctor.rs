@@ -0,0 +1,47 @@
+use std::path::Path;
+
+use crate::cli::DoctorArgs;
+use crate::db::{Database, format_size};
+use crate::error::Result;
+
+/// Check the notes database and repair the fulltext index if requested
+///
+/// Also lists the note files that could not be indexed.
+pub fn doctor(notes_dir: &Path, args: DoctorArgs) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        // Opening the database only checks the fulltext index once in a while,
+        // so check it here unless that just rebuilt it
+        let mut db = Database::initialize(notes_dir).await?;
+
+        if db.fts_rebuilt() || db.repair_fts().await? {
+            println!("Fulltext index: inconsistent, rebuilt");
+        } else if args.rebuild_fts {
+            // Keep running MCP servers from updating the index meanwhile
+            let lock = db.lock_maintenance(args.wait).await?;
+            let rebuilt = db.rebuild_fts().await;
+            lock.release().await?;
+            rebuilt?;
+            println!("Fulltext index: consistent, rebuilt anyway");
+        } else {
+            println!("Fulltext index: OK");
+        }
+
+        println!("Database size: {}", format_size(db.size()?));
+
+        // Index the notes first, so that new problems are reported as well
+        db.index_notes().await?;
+        let failed = db.failed_files().await?;
+        if failed.is_empty() {
+            println!("Unreadable notes: none");
+        } else {
+            println!("Unreadable notes: {}", failed.len());
+            for file in failed {
+                println!("  {}: {}", file.filepath, file.error);
+            }
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use chrono::{Local, NaiveDate};
+
+use crate::cli::{DueAction, DueArgs};
+use crate::commands::resolve::{resolve_note_path, rewrite_note};
+use crate::core::note::Note;
+use crate::db::{Database, due_until};
+use crate::error::Result;
+
+/// List the notes with a due date, or set or clear the due date of a note
+pub fn due(notes_dir: &Path, args: DueArgs) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        let db = Database::open_for_query(notes_dir).await?;
+
+        match args.action {
+            Some(DueAction::Set { id, date }) => set_due(&db, notes_dir, &id, Some(date)).await,
+            Some(DueAction::Clear { id }) => set_due(&db, notes_dir, &id, None).await,
+            None => {
+                let today = Local::now().date_naive();
+                let until = due_until(today, args.overdue, args.within);
+                for note in db.due_notes(until).await? {
+                    println!("{}", due_row(&db, &note).await?);
+                }
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Set or clear the due date of a note
+async fn set_due(db: &Database, notes_dir: &Path, id: &str, due: Option<NaiveDate>) -> Result<()> {
+    let relative_path = resolve_note_path(db, id).await?;
+
+    let note = rewrite_note(notes_dir, &relative_path, |note| {
+        note.frontmatter_mut().set_due(due);
+        Ok(())
+    })?;
+
+    match due {
+        Some(due) => println!("\"{}\" is due on {}", note.extract_title(), due),
+        None => println!("\"{}\" is no longer due", note.extract_title()),
+    }
+
+    Ok(())
+}
+
+/// Format a note as a line of the due list
+async fn due_row(db: &Database, note: &Note) -> Result<String> {
+    let short_id = match note.frontmatter().id() {
+        Some(id) => db.find_shortest_unique_id_prefix(id).await?,
+        None => String::new(),
+    };
+    let due = note
+        .frontmatter()
+        .due()
+        .map(|due| due.to_string())
+        .unwrap_or_default();
+    let tags = note
+        .frontmatter()
+        .tags()
+        .iter()
+        .map(|tag| format!("+{}", tag.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(format!(
+        "{}  {:<8}  {} {}",
+        due,
+        short_id,
+        note.extract_title(),
+        tags
+    )
+    .trim_end()
+    .to_string())
+}
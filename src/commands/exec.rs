@@ -0,0 +1,312 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cli::ExecArgs;
+use crate::config;
+use crate::core::note_builder::NoteBuilder;
+use crate::core::tags::{Tag, extract_tags_from_args};
+use crate::error::{NotelogError, Result};
+use crate::hooks::{Hook, run_hook};
+
+/// The tag of the notes with command runs
+const EXEC_TAG: &str = "exec";
+
+/// How much of each output stream is kept in the note, in bytes
+///
+/// Both streams together stay well below the maximum note size.
+const MAX_CAPTURED_BYTES: usize = 20 * 1024;
+
+/// Run a command, and save its output, exit code and duration as a note
+///
+/// The output still goes to the terminal while the command runs. If the
+/// command fails, the note is saved before the failure is reported.
+pub fn exec(notes_dir: &Path, args: ExecArgs) -> Result<()> {
+    let (given_tags, other) = extract_tags_from_args(&args.tags)?;
+    if let Some(arg) = other.first() {
+        return Err(NotelogError::ExecFailed(format!(
+            "'{}' is not a tag; put the command after `--`",
+            arg
+        )));
+    }
+
+    let mut tags = config::get().default_tags()?;
+    tags.push(Tag::new(EXEC_TAG)?);
+    tags.extend(given_tags);
+
+    let (program, program_args) = args
+        .command
+        .split_first()
+        .ok_or_else(|| NotelogError::ExecFailed("no command given".to_string()))?;
+
+    let started = Instant::now();
+    let mut child = Command::new(program)
+        .args(program_args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| NotelogError::ExecFailed(format!("{}: {}", program, e)))?;
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let (stdout, stderr) = thread::scope(|scope| {
+        let stdout = scope.spawn(|| tee(stdout, io::stdout(), MAX_CAPTURED_BYTES));
+        let stderr = scope.spawn(|| tee(stderr, io::stderr(), MAX_CAPTURED_BYTES));
+        (
+            stdout.join().expect("stdout capture panicked"),
+            stderr.join().expect("stderr capture panicked"),
+        )
+    });
+    let status = child.wait()?;
+    let duration = started.elapsed();
+
+    let command_line = args
+        .command
+        .iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let content = exec_note_content(
+        &command_line,
+        &describe_status(status),
+        duration,
+        &stdout?,
+        &stderr?,
+    );
+
+    let note = NoteBuilder::new()
+        .content(content)
+        .tags(tags)
+        .source("cli")
+        .generate_id(config::get().generate_ids())
+        .build()?;
+
+    let relative_path = note.save(notes_dir, None)?;
+    println!(
+        "Note saved to: {}",
+        notes_dir.join(&relative_path).display()
+    );
+    run_hook(notes_dir, Hook::PostAdd, &relative_path, &note);
+
+    if !status.success() {
+        return Err(NotelogError::ExecFailed(format!(
+            "{} ({})",
+            command_line,
+            describe_status(status)
+        )));
+    }
+
+    Ok(())
+}
+
+/// The end of an output stream
+#[derive(Debug, Default)]
+struct Captured {
+    /// The last bytes of the output
+    bytes: Vec<u8>,
+    /// The number of bytes before them that were dropped
+    dropped: usize,
+}
+
+impl Captured {
+    /// Add output, dropping the oldest bytes beyond `max`
+    fn push(&mut self, chunk: &[u8], max: usize) {
+        self.bytes.extend_from_slice(chunk);
+        if self.bytes.len() > max {
+            let excess = self.bytes.len() - max;
+            self.bytes.drain(..excess);
+            self.dropped += excess;
+        }
+    }
+
+    /// The output as text that can be part of a note
+    ///
+    /// Invalid UTF-8 is replaced, and null bytes, which notes cannot contain,
+    /// are removed. Since the replacement characters are longer than the bytes
+    /// they replace, the start of the text is cut off again if needed.
+    fn text(&self) -> String {
+        let text = String::from_utf8_lossy(&self.bytes).replace('\0', "");
+        let mut start = text.len().saturating_sub(MAX_CAPTURED_BYTES);
+        while !text.is_char_boundary(start) {
+            start += 1;
+        }
+        text[start..].to_string()
+    }
+}
+
+/// Copy everything from `reader` to `writer`, keeping the last `max` bytes
+///
+/// The output is captured even if it cannot be written, e.g. because the
+/// terminal was closed.
+fn tee(mut reader: impl Read, mut writer: impl Write, max: usize) -> io::Result<Captured> {
+    let mut captured = Captured::default();
+    let mut chunk = [0; 8192];
+
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let _ = writer.write_all(&chunk[..n]).and_then(|_| writer.flush());
+        captured.push(&chunk[..n], max);
+    }
+
+    Ok(captured)
+}
+
+/// Describe how a command exited, e.g. "exit code 0"
+fn describe_status(status: ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("exit code {}", code);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return format!("killed by signal {}", signal);
+        }
+    }
+
+    "unknown exit status".to_string()
+}
+
+/// Quote an argument for the command line in the note, if it needs quoting
+fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./:=+,@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Format the note of a command run
+fn exec_note_content(
+    command_line: &str,
+    status: &str,
+    duration: Duration,
+    stdout: &Captured,
+    stderr: &Captured,
+) -> String {
+    let mut content = format!(
+        "# {}\n\n- Result: {}\n- Duration: {:.2}s\n",
+        command_line,
+        status,
+        duration.as_secs_f64()
+    );
+
+    for (name, captured) in [("Output", stdout), ("Errors", stderr)] {
+        if captured.bytes.is_empty() {
+            continue;
+        }
+
+        content.push_str(&format!("\n## {}\n\n", name));
+        if captured.dropped > 0 {
+            content.push_str(&format!(
+                "(truncated: the first {} bytes are not included)\n\n",
+                captured.dropped
+            ));
+        }
+
+        // The fence must be longer than any run of backticks in the output
+        let text = captured.text();
+        let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        content.push_str(&format!(
+            "{}text\n{}\n{}\n",
+            fence,
+            text.trim_end_matches('\n'),
+            fence
+        ));
+    }
+
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captured(text: &str) -> Captured {
+        let mut captured = Captured::default();
+        captured.push(text.as_bytes(), MAX_CAPTURED_BYTES);
+        captured
+    }
+
+    #[test]
+    fn test_tee_keeps_the_end() {
+        let mut written = Vec::new();
+        let captured = tee(io::Cursor::new("0123456789"), &mut written, 4).unwrap();
+
+        assert_eq!(written, b"0123456789");
+        assert_eq!(captured.bytes, b"6789");
+        assert_eq!(captured.dropped, 6);
+    }
+
+    #[test]
+    fn test_exec_note_content() {
+        let content = exec_note_content(
+            "cargo test",
+            "exit code 101",
+            Duration::from_millis(1500),
+            &captured("running 2 tests\n```\n"),
+            &Captured::default(),
+        );
+
+        assert_eq!(
+            content,
+            "# cargo test\n\n\
+             - Result: exit code 101\n\
+             - Duration: 1.50s\n\n\
+             ## Output\n\n\
+             ````text\nrunning 2 tests\n```\n````\n"
+        );
+
+        // Truncated output says so
+        let mut stderr = captured("warning: unused\n");
+        stderr.dropped = 100;
+        let content = exec_note_content(
+            "make",
+            "exit code 0",
+            Duration::ZERO,
+            &Captured::default(),
+            &stderr,
+        );
+        assert!(!content.contains("## Output"));
+        assert!(content.contains(
+            "## Errors\n\n(truncated: the first 100 bytes are not included)\n\n```text\nwarning: unused\n```\n"
+        ));
+    }
+
+    #[test]
+    fn test_captured_text() {
+        let captured = captured("ok\0 \u{1b}[32mgreen\u{1b}[0m");
+        assert_eq!(captured.text(), "ok \u{1b}[32mgreen\u{1b}[0m");
+
+        let mut invalid = Captured::default();
+        invalid.push(b"caf\xe9", 10);
+        assert_eq!(invalid.text(), "caf\u{fffd}");
+
+        // Binary output stays within the limit after replacing invalid bytes
+        let mut binary = Captured::default();
+        binary.push(&[0xff; MAX_CAPTURED_BYTES], MAX_CAPTURED_BYTES);
+        assert!(binary.text().len() <= MAX_CAPTURED_BYTES);
+    }
+
+    #[test]
+    fn test_quote_arg() {
+        assert_eq!(quote_arg("--release"), "--release");
+        assert_eq!(quote_arg("src/main.rs"), "src/main.rs");
+        assert_eq!(quote_arg("two words"), "'two words'");
+        assert_eq!(quote_arg("it's"), r"'it'\''s'");
+        assert_eq!(quote_arg(""), "''");
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cli::{ExportArgs, ExportFormat};
+use crate::core::note::Note;
+use crate::core::tags::Tag;
+use crate::db::Database;
+use crate::error::Result;
+use crate::html::render_site;
+
+/// Write the notes (or those with one of the given tags) as a static site
+pub fn export(notes_dir: &Path, args: ExportArgs) -> Result<()> {
+    let tags = args
+        .tags
+        .iter()
+        .map(|tag| Tag::new(tag.trim_start_matches('+')))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let notes = rt.block_on(async {
+        let db = Database::open_for_query(notes_dir).await?;
+        if !tags.is_empty() {
+            return db.notes_with_any_tag(&tags).await;
+        }
+
+        let mut notes: Vec<Note> = Vec::new();
+        db.list_notes(|_, note| {
+            notes.push(note);
+            Ok(())
+        })
+        .await?;
+        Ok(notes)
+    })?;
+
+    let pages = match args.format {
+        ExportFormat::Html => render_site(&notes),
+    };
+
+    fs::create_dir_all(&args.output)?;
+    for (filename, html) in &pages {
+        fs::write(args.output.join(filename), html)?;
+    }
+
+    let without_id = notes
+        .iter()
+        .filter(|note| note.frontmatter().id().is_none())
+        .count();
+    println!(
+        "Exported {} notes to {}",
+        notes.len() - without_id,
+        args.output.display()
+    );
+    if without_id > 0 {
+        eprintln!(
+            "Skipped {} notes without an ID, since their pages are named after it",
+            without_id
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use chrono::Local;
+
+use crate::cli::InitArgs;
+use crate::config::config_path;
+use crate::db::{BACKUPS_DIR, DB_FILENAME, Database, IGNORE_FILENAME};
+use crate::error::{NotelogError, Result};
+use crate::utils::create_date_directories;
+
+/// The initial contents of the ignore file
+const IGNORE_TEMPLATE: &str = "\
+# Files and directories Notelog should leave alone, one pattern per line.
+# Only files named like notes (e.g. `2025-01-01T17-45 Title.md`) are indexed
+# in the first place, and .trash, .archive, .backups, attachments and .notelog
+# are always left alone, so this is rarely needed.
+#
+# `*` matches within a name, a trailing `/` matches only directories, and a
+# pattern with a `/` matches from the top of the notes directory, e.g.:
+# drafts/
+# 2019/old-*
+";
+
+/// The initial contents of the configuration file, without the notes directory
+const CONFIG_TEMPLATE: &str = "
+# Tags added to every note created from the command line
+# default_tags = [\"log\"]
+
+# Editor to use instead of $VISUAL/$EDITOR
+# editor = \"vim\"
+
+# How to name month directories: \"english\", \"numeric\" or \"locale\"
+# month_dir_style = \"english\"
+";
+
+/// Set up a new notes directory
+///
+/// Creates the directory with the folders for the current month, an ignore
+/// file, optionally a git repository, the search database and a starter
+/// configuration file (unless one exists already).
+pub fn init_notes_dir(notes_dir: &Path, args: &InitArgs) -> Result<()> {
+    if is_non_empty_dir(notes_dir)? && !args.force {
+        return Err(NotelogError::NotesDirectoryNotEmpty(
+            notes_dir.display().to_string(),
+        ));
+    }
+
+    fs::create_dir_all(notes_dir)?;
+    let notes_dir = notes_dir.canonicalize()?;
+
+    create_date_directories(&notes_dir, &Local::now())?;
+
+    let ignore_path = notes_dir.join(IGNORE_FILENAME);
+    if !ignore_path.exists() {
+        fs::write(&ignore_path, IGNORE_TEMPLATE)?;
+    }
+
+    if args.git {
+        init_git_repository(&notes_dir)?;
+    }
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(Database::initialize(&notes_dir))?;
+
+    println!("Initialized notes directory {}", notes_dir.display());
+
+    if let Some(path) = config_path() {
+        if path.exists() {
+            println!(
+                "Configuration file {} exists, leaving it unchanged",
+                path.display()
+            );
+        } else {
+            write_starter_config(&path, &notes_dir)?;
+            println!("Wrote configuration file {}", path.display());
+        }
+    }
+
+    println!();
+    println!("What next?");
+    println!("  notelog add \"My first note\" +hello   add a note");
+    println!("  notelog last --print                 show the newest note");
+    println!("  notelog mcp                          start the MCP server for your AI assistant");
+
+    Ok(())
+}
+
+/// Check whether the path is a directory with at least one entry
+fn is_non_empty_dir(path: &Path) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    Ok(fs::read_dir(path)?.next().is_some())
+}
+
+/// Create a git repository that ignores the database and its backups
+fn init_git_repository(notes_dir: &Path) -> Result<()> {
+    let status = Command::new("git")
+        .arg("init")
+        .arg("--quiet")
+        .arg(notes_dir)
+        .status()
+        .map_err(|e| NotelogError::GitFailed(e.to_string()))?;
+
+    if !status.success() {
+        return Err(NotelogError::GitFailed(format!(
+            "git init exited with {}",
+            status
+        )));
+    }
+
+    let gitignore_path = notes_dir.join(".gitignore");
+    if !gitignore_path.exists() {
+        fs::write(
+            gitignore_path,
+            format!("{}*\n{}/\n", DB_FILENAME, BACKUPS_DIR),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Write a configuration file that points to the notes directory
+fn write_starter_config(path: &Path, notes_dir: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let notes_dir = toml::Value::String(notes_dir.display().to_string());
+    fs::write(
+        path,
+        format!("notes_dir = {}\n{}", notes_dir, CONFIG_TEMPLATE),
+    )?;
+
+    Ok(())
+}
@@ -1,12 +1,11 @@
 use std::collections::BinaryHeap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
 
 use crate::cli::LastArgs;
-use crate::core::note::Note;
+use crate::db::Database;
 use crate::error::{NotelogError, Result};
-use crate::utils::{is_valid_note_file, open_editor, read_file_content};
+use crate::utils::{edit_note_file, is_valid_note_file, read_file_content};
 
 /// Find and open the newest note
 pub fn last_note(notes_dir: &Path, args: LastArgs) -> Result<()> {
@@ -15,8 +14,17 @@ pub fn last_note(notes_dir: &Path, args: LastArgs) -> Result<()> {
         return Err(NotelogError::InvalidLastOptions);
     }
 
-    // Find the newest note.
-    let newest_note_path = find_newest_note(notes_dir)?;
+    // Find the newest note. Notes outside of the year and month directories
+    // are only found in the index, which is slower to bring up to date.
+    let newest = if args.from_index {
+        None
+    } else {
+        find_newest_note(notes_dir)?
+    };
+    let newest_note_path = match newest {
+        Some(path) => path,
+        None => find_newest_indexed_note(notes_dir)?,
+    };
 
     // Either print the note or open it in the editor
     if args.print {
@@ -24,20 +32,7 @@ pub fn last_note(notes_dir: &Path, args: LastArgs) -> Result<()> {
         let content = read_file_content(&newest_note_path)?;
         println!("{}", content);
     } else {
-        // Read the note content
-        let content = read_file_content(&newest_note_path)?;
-
-        // Parse the note to validate it
-        let _note = Note::from_str(&content)?;
-
-        // Open the note in the editor
-        let new_content = open_editor(Some(&content))?;
-
-        // If the content has changed, save it back to the file
-        if new_content != content {
-            fs::write(&newest_note_path, new_content)?;
-            println!("Note updated: {}", newest_note_path.display());
-        }
+        edit_note_file(notes_dir, &newest_note_path)?;
     }
 
     Ok(())
@@ -48,24 +43,47 @@ pub fn last_note(notes_dir: &Path, args: LastArgs) -> Result<()> {
 /// Searches for the last year in the notes directory, then the last month in
 /// that directory, and then the last note in that directory. Usually it should
 /// return quickly, because it does not descend deeper into the tree than
-/// necessary.
-fn find_newest_note(notes_dir: &Path) -> Result<PathBuf> {
+/// necessary. Notes in other directories are not found.
+fn find_newest_note(notes_dir: &Path) -> Result<Option<PathBuf>> {
     let mut year_dirs = get_year_dirs(notes_dir)?;
 
     while let Some(year_dir) = year_dirs.pop() {
         let mut month_dirs = get_month_dirs(&year_dir)?;
 
-        while let Some(month_dir) = month_dirs.pop() {
-            let note_file = get_last_note_file(&month_dir)?;
+        while let Some((month, month_dir)) = month_dirs.pop() {
+            let mut newest = get_last_note_file(&month_dir)?;
+
+            // Month directories of different naming styles (e.g. `05` and `05_May`)
+            // can exist side by side, so compare the notes of all of them
+            while month_dirs.peek().is_some_and(|(other, _)| *other == month) {
+                let (_, other_dir) = month_dirs.pop().expect("peeked month directory");
+                newest = newer_note_file(newest, get_last_note_file(&other_dir)?);
+            }
 
-            if note_file.is_some() {
-                return Ok(note_file.unwrap());
+            if newest.is_some() {
+                return Ok(newest);
             }
         }
     }
 
-    // If we get here, no valid note was found
-    Err(NotelogError::NoValidNoteFound)
+    Ok(None)
+}
+
+/// Find the note with the latest creation time in the search index
+///
+/// This also finds notes outside of the year and month directories, e.g. in
+/// directories of other tools.
+fn find_newest_indexed_note(notes_dir: &Path) -> Result<PathBuf> {
+    let rt = tokio::runtime::Runtime::new()?;
+    let filepath = rt.block_on(async {
+        let db = Database::open_for_query(notes_dir).await?;
+        db.newest_note_filepath().await
+    })?;
+
+    filepath
+        .map(|filepath| notes_dir.join(filepath))
+        .filter(|path| path.is_file())
+        .ok_or(NotelogError::NoValidNoteFound)
 }
 
 /// Get all year directories sorted by name
@@ -78,13 +96,13 @@ fn get_year_dirs(notes_dir: &Path) -> Result<BinaryHeap<PathBuf>> {
     // Filter for year directories (4-digit numbers)
     for entry in entries.flatten() {
         let path = entry.path();
-        if path.is_dir() {
-            if let Some(name) = path.file_name() {
-                let name_str = name.to_string_lossy();
-                // Check if the name is a 4-digit year
-                if name_str.len() == 4 && name_str.chars().all(|c| c.is_ascii_digit()) {
-                    year_dirs.push(path);
-                }
+        if path.is_dir()
+            && let Some(name) = path.file_name()
+        {
+            let name_str = name.to_string_lossy();
+            // Check if the name is a 4-digit year
+            if name_str.len() == 4 && name_str.chars().all(|c| c.is_ascii_digit()) {
+                year_dirs.push(path);
             }
         }
     }
@@ -92,8 +110,12 @@ fn get_year_dirs(notes_dir: &Path) -> Result<BinaryHeap<PathBuf>> {
     Ok(year_dirs)
 }
 
-/// Get all month directories sorted by name
-fn get_month_dirs(year_dir: &Path) -> Result<BinaryHeap<PathBuf>> {
+/// Get all month directories sorted by month number
+///
+/// Month directories start with the two-digit month number, followed by an
+/// optional suffix such as `_January`. Other tools also leave out the leading
+/// zero, e.g. `5` for May.
+fn get_month_dirs(year_dir: &Path) -> Result<BinaryHeap<(u32, PathBuf)>> {
     let mut month_dirs = BinaryHeap::new();
 
     // Read the year directory
@@ -102,18 +124,23 @@ fn get_month_dirs(year_dir: &Path) -> Result<BinaryHeap<PathBuf>> {
     // Filter for month directories (starting with 01-12)
     for entry in entries.flatten() {
         let path = entry.path();
-        if path.is_dir() {
-            if let Some(name) = path.file_name() {
-                let name_str = name.to_string_lossy();
-                // Check if the name starts with a valid month number (01-12)
-                if name_str.len() >= 2 {
-                    let month_prefix = &name_str[..2];
-                    if let Ok(month_num) = month_prefix.parse::<u32>() {
-                        if (1..=12).contains(&month_num) {
-                            month_dirs.push(path);
-                        }
-                    }
-                }
+        if path.is_dir()
+            && let Some(name) = path.file_name()
+        {
+            let name_str = name.to_string_lossy();
+            // Check if the name starts with a valid month number (01-12), or
+            // is a single digit (1-9)
+            let month_digits = if name_str.len() == 1 {
+                Some(name_str.as_ref())
+            } else {
+                name_str.get(..2)
+            };
+            if let Some(month_digits) = month_digits
+                && month_digits.chars().all(|c| c.is_ascii_digit())
+                && let Ok(month_num) = month_digits.parse::<u32>()
+                && (1..=12).contains(&month_num)
+            {
+                month_dirs.push((month_num, path));
             }
         }
     }
@@ -121,6 +148,15 @@ fn get_month_dirs(year_dir: &Path) -> Result<BinaryHeap<PathBuf>> {
     Ok(month_dirs)
 }
 
+/// Pick the newer of two note files by comparing their file names, which start
+/// with the date and time
+fn newer_note_file(a: Option<PathBuf>, b: Option<PathBuf>) -> Option<PathBuf> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.file_name() > a.file_name() { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
 /// Get the note file with the largest path (which should be the newest one,
 /// since note paths include the date and time)
 fn get_last_note_file(month_dir: &Path) -> Result<Option<PathBuf>> {
@@ -142,3 +178,88 @@ fn get_last_note_file(month_dir: &Path) -> Result<Option<PathBuf>> {
 
     Ok(note_file)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MonthDirStyle;
+    use crate::utils::{create_date_directories_with_style, generate_filename};
+    use chrono::{Local, TimeZone};
+    use tempfile::TempDir;
+
+    fn write_note(notes_dir: &Path, style: MonthDirStyle, day: u32, hour: u32) -> PathBuf {
+        let date = Local.with_ymd_and_hms(2025, 5, day, hour, 0, 0).unwrap();
+        let month_dir = create_date_directories_with_style(notes_dir, &date, style).unwrap();
+        let path = month_dir.join(generate_filename(&date, "Note", None));
+        fs::write(&path, "# Note\n").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_find_newest_note_across_month_dir_styles() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        // An older note in an earlier month
+        let april = Local.with_ymd_and_hms(2025, 4, 30, 12, 0, 0).unwrap();
+        let april_dir =
+            create_date_directories_with_style(notes_dir, &april, MonthDirStyle::Numeric).unwrap();
+        fs::write(
+            april_dir.join(generate_filename(&april, "April", None)),
+            "# April\n",
+        )
+        .unwrap();
+
+        // The same month with both naming styles
+        write_note(notes_dir, MonthDirStyle::English, 1, 9);
+        let newest = write_note(notes_dir, MonthDirStyle::Numeric, 2, 9);
+        write_note(notes_dir, MonthDirStyle::English, 1, 18);
+
+        assert!(notes_dir.join("2025").join("05").is_dir());
+        assert!(notes_dir.join("2025").join("05_May").is_dir());
+        assert_eq!(find_newest_note(notes_dir).unwrap(), Some(newest));
+
+        // A newer note in the other style wins
+        let newest = write_note(notes_dir, MonthDirStyle::English, 3, 8);
+        assert_eq!(find_newest_note(notes_dir).unwrap(), Some(newest));
+    }
+
+    #[test]
+    fn test_get_month_dirs_ignores_other_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in [
+            "05",
+            "06_June",
+            "13_Smarch",
+            "Mär",
+            "attachments",
+            "1",
+            "13",
+            "007",
+        ] {
+            fs::create_dir(temp_dir.path().join(name)).unwrap();
+        }
+
+        let months: Vec<u32> = get_month_dirs(temp_dir.path())
+            .unwrap()
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(month, _)| month)
+            .collect();
+        assert_eq!(months, vec![1, 5, 6]);
+    }
+
+    #[test]
+    fn test_find_newest_note_in_numeric_month_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        // Month directories without a leading zero, as other tools create them
+        let month_dir = notes_dir.join("2025").join("5");
+        fs::create_dir_all(&month_dir).unwrap();
+        let path = month_dir.join("2025-05-01T12-00 Note.md");
+        fs::write(&path, "# Note\n").unwrap();
+
+        assert_eq!(find_newest_note(notes_dir).unwrap(), Some(path));
+    }
+}
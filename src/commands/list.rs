@@ -0,0 +1,140 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::cli::{ListArgs, ListFormat};
+use crate::core::datetime::format_display_date;
+use crate::core::note::Note;
+use crate::daemon::client;
+use crate::db::Database;
+use crate::error::{NotelogError, Result};
+use crate::output::{fit_row, table_width};
+
+/// List the notes, newest first
+///
+/// The notes are written as they are read from the database, so that the
+/// first lines appear immediately even for large notes directories.
+pub fn list_notes(notes_dir: &Path, args: ListArgs) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    if !args.plain {
+        writeln!(stdout, "{}", header(args.format))?;
+    }
+    // TSV rows are for programs, which need them in full
+    let width = match args.format {
+        ListFormat::Text => table_width(),
+        ListFormat::Tsv => None,
+    };
+    let mut write_row = |short_id: &str, note: Note| {
+        let row = format_row(short_id, &note, args.format);
+        writeln!(stdout, "{}", fit_row(row, width))?;
+        Ok(())
+    };
+
+    // A running daemon has the index open already
+    let result = match client::list(notes_dir, &mut write_row) {
+        Some(result) => result,
+        None => {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(async {
+                // List the index as it is, so that the first lines appear right away
+                let db = Database::open_for_listing(notes_dir).await?;
+                db.list_notes(&mut write_row).await
+            })
+        }
+    };
+
+    match result {
+        // The reader (e.g. a picker) may exit before reading everything
+        Err(NotelogError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// The column names for the given format
+pub fn header(format: ListFormat) -> String {
+    match format {
+        ListFormat::Text => format!("{:<8}  {:<16}  {}", "ID", "CREATED", "TITLE"),
+        ListFormat::Tsv => "short_id\tcreated\ttitle\ttags".to_string(),
+    }
+}
+
+/// Format a note as a line of the listing
+pub fn format_row(short_id: &str, note: &Note, format: ListFormat) -> String {
+    match format {
+        ListFormat::Text => format!("{:<8}  {}", short_id, format_summary(note)),
+        ListFormat::Tsv => format!(
+            "{}\t{}\t{}\t{}",
+            short_id,
+            note.frontmatter().created().format("%Y-%m-%dT%H:%M:%S%:z"),
+            tab_safe(&note.extract_title()),
+            tags(note)
+        ),
+    }
+}
+
+/// Format the creation time, title and tags of a note for the text listing
+pub fn format_summary(note: &Note) -> String {
+    let created = note.frontmatter().created();
+    format!(
+        "{} {}  {} {}",
+        format_display_date(created.date_naive()),
+        created.format("%H:%M"),
+        tab_safe(&note.extract_title()),
+        tags(note)
+    )
+    .trim_end()
+    .to_string()
+}
+
+/// The tags of a note, each with its `+` prefix
+fn tags(note: &Note) -> String {
+    note.frontmatter()
+        .tags()
+        .iter()
+        .map(|tag| format!("+{}", tag.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Replace tabs and line breaks, which would break up a TSV row
+fn tab_safe(s: &str) -> String {
+    s.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frontmatter::Frontmatter;
+    use crate::core::tags::Tag;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_format_row() {
+        let created = Local.with_ymd_and_hms(2025, 5, 1, 12, 30, 0).unwrap();
+        let tags = vec![Tag::new("todo").unwrap(), Tag::new("work").unwrap()];
+        let note = Note::new(
+            Frontmatter::new(created, tags),
+            "# Fix\tthe build\n\nDetails".to_string(),
+        );
+
+        let row = format_row("ab12", &note, ListFormat::Tsv);
+        let fields: Vec<&str> = row.split('\t').collect();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[0], "ab12");
+        assert_eq!(
+            fields[1],
+            created.format("%Y-%m-%dT%H:%M:%S%:z").to_string()
+        );
+        assert_eq!(fields[2], "Fix the build");
+        assert_eq!(fields[3], "+todo +work");
+
+        assert_eq!(
+            format_row("ab12", &note, ListFormat::Text),
+            "ab12      2025-05-01 12:30  Fix the build +todo +work"
+        );
+    }
+
+    #[test]
+    fn test_tab_safe() {
+        assert_eq!(tab_safe("a\tb\r\nc"), "a b  c");
+    }
+}
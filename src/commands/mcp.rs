@@ -1,8 +1,10 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::cli::McpArgs;
+use crate::config;
 use crate::error::{NotelogError, Result};
 use crate::mcp;
+use crate::utils::{check_notes_dir_scope, ensure_notes_dir_writable};
 
 /// Handle the mcp command
 pub fn mcp_command(notes_dir: &Path, args: McpArgs) -> Result<()> {
@@ -11,10 +13,54 @@ pub fn mcp_command(notes_dir: &Path, args: McpArgs) -> Result<()> {
         return Err(NotelogError::InvalidMcpOptions);
     }
 
+    let mut stores: Vec<(String, PathBuf)> = Vec::new();
+    for (name, dir) in args.stores {
+        if stores.iter().any(|(existing, _)| *existing == name) {
+            return Err(NotelogError::DuplicateStore(name));
+        }
+
+        ensure_notes_dir_writable(&dir)?;
+        let dir = dir.canonicalize()?;
+        if !config::get().allow_large_dir {
+            check_notes_dir_scope(&dir)?;
+        }
+        stores.push((name, dir));
+    }
+
+    // The notes directory is the default store, unless a store is explicitly named "default"
+    if !stores.iter().any(|(name, _)| name == mcp::DEFAULT_STORE) {
+        stores.insert(0, (mcp::DEFAULT_STORE.to_string(), notes_dir.to_path_buf()));
+    }
+
     // Run the MCP server with database initialization
     // This uses a single Tokio runtime for both database initialization and the MCP server
-    match mcp::run_mcp_server_with_db(notes_dir) {
+    let daily_backups = args.daily_backups.map(|keep| keep.get());
+    let retention = if args.daily_prune {
+        Some(config::get().retention()?)
+    } else {
+        None
+    };
+    match mcp::run_mcp_server_with_db(stores, daily_backups, retention, args.optimize_on_exit) {
         Ok(_) => Ok(()),
         Err(e) => Err(NotelogError::McpServerError(e.to_string())),
     }
 }
+
+/// Print the server instructions, or the instructions of a specific tool
+pub fn print_instructions(tool: Option<&str>) -> Result<()> {
+    let instructions = match tool {
+        None => mcp::SERVER_INSTRUCTIONS,
+        Some(name) => mcp::tool_instructions(name).ok_or_else(|| {
+            let available = mcp::TOOL_INSTRUCTIONS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            NotelogError::UnknownTool(name.to_string(), available)
+        })?,
+    };
+
+    print!("{}", instructions);
+
+    Ok(())
+}
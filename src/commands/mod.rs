@@ -1,7 +1,54 @@
 pub mod add;
+pub mod backup;
+pub mod bench;
+pub mod cat;
+pub mod config;
+pub mod daemon;
+pub mod database;
+pub mod digest;
+pub mod doctor;
+pub mod due;
+pub mod exec;
+pub mod export;
+pub mod init;
 pub mod last;
+pub mod list;
 pub mod mcp;
+pub mod mv;
+pub mod profiles;
+pub mod prune;
+pub mod reindex;
+pub mod resolve;
+pub mod review;
+pub mod search;
+pub mod selftest;
+pub mod split;
+pub mod tag;
+pub mod todos;
 
 pub use add::add_note;
+pub use backup::backup_notes;
+pub use bench::bench;
+pub use cat::cat;
+pub use config::show_config;
+pub use daemon::daemon_command;
+pub use database::db_command;
+pub use digest::digest;
+pub use doctor::doctor;
+pub use due::due;
+pub use exec::exec;
+pub use export::export;
+pub use init::init_notes_dir;
 pub use last::last_note;
-pub use mcp::mcp_command;
+pub use list::list_notes;
+pub use mcp::{mcp_command, print_instructions};
+pub use mv::move_note;
+pub use profiles::list_profiles;
+pub use prune::prune;
+pub use reindex::reindex;
+pub use review::review;
+pub use search::search_notes;
+pub use selftest::selftest;
+pub use split::split_note;
+pub use tag::tag;
+pub use todos::{done, todos};
@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::Local;
+
+use crate::cli::MvArgs;
+use crate::commands::resolve::resolve_note_path;
+use crate::core::note::Note;
+use crate::db::Database;
+use crate::error::Result;
+use crate::hooks::{Hook, run_hook};
+use crate::utils::{create_date_directories, generate_filename};
+
+/// Give a note a new creation time or filename title, moving its file
+///
+/// The file is moved into the year and month directories of the new creation
+/// time, and the index is updated to the new path.
+pub fn move_note(notes_dir: &Path, args: MvArgs) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await?;
+        db.index_notes().await?;
+
+        let old_path = resolve_note_path(&db, &args.id).await?;
+        let mut note = Note::from_str(&fs::read_to_string(notes_dir.join(&old_path))?)?;
+
+        if let Some(created) = args.created {
+            note.frontmatter_mut().set_created(created);
+        }
+
+        // Keep the title of the filename, which can differ from the one in the note
+        let title = args
+            .title
+            .or_else(|| filename_title(&old_path))
+            .unwrap_or_else(|| note.extract_title());
+
+        let new_path = if is_current_path(notes_dir, &old_path, &note, &title)? {
+            fs::write(notes_dir.join(&old_path), note.formatted_content())?;
+            old_path.clone()
+        } else {
+            let new_path = note.save(notes_dir, Some(&title))?;
+            // Otherwise the note would exist twice, with the same ID
+            if let Err(e) = fs::remove_file(notes_dir.join(&old_path)) {
+                let _ = fs::remove_file(notes_dir.join(&new_path));
+                return Err(e.into());
+            }
+            new_path
+        };
+
+        db.move_note(&old_path, &new_path).await?;
+        println!("Moved {} to {}", old_path.display(), new_path.display());
+
+        if new_path != old_path && notes_dir.join(".git").exists() {
+            eprintln!(
+                "Warning: the notes directory is a git repository; stage both paths (e.g. `git add -A`) so that git records the move as a rename"
+            );
+        }
+
+        run_hook(notes_dir, Hook::PostEdit, &new_path, &note);
+
+        Ok(())
+    })
+}
+
+/// Whether the note would be saved at the path it already has
+fn is_current_path(notes_dir: &Path, path: &Path, note: &Note, title: &str) -> Result<bool> {
+    let created = note.frontmatter().created().with_timezone(&Local);
+    let month_dir = create_date_directories(notes_dir, &created)?;
+
+    Ok(notes_dir.join(path) == month_dir.join(generate_filename(&created, title, None)))
+}
+
+/// The title in the filename of a note, without the timestamp and counter
+fn filename_title(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let (_, title) = stem.split_once(' ')?;
+
+    // Remove the counter added to the filenames of notes with the same title
+    let title = match title.rsplit_once(" (") {
+        Some((title, counter))
+            if counter
+                .strip_suffix(')')
+                .is_some_and(|n| n.parse::<usize>().is_ok()) =>
+        {
+            title
+        }
+        _ => title,
+    };
+
+    Some(title.to_string()).filter(|title| !title.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filename_title() {
+        let title = |path: &str| filename_title(Path::new(path));
+
+        assert_eq!(
+            title("2025/05_May/2025-05-06T12-00 Weekly sync.md"),
+            Some("Weekly sync".to_string())
+        );
+        assert_eq!(
+            title("2025/05_May/2025-05-06T12-00 Weekly sync (2).md"),
+            Some("Weekly sync".to_string())
+        );
+        assert_eq!(
+            title("2025/05_May/2025-05-06T12-00 Chapter (one).md"),
+            Some("Chapter (one)".to_string())
+        );
+        assert_eq!(title("2025/05_May/2025-05-06T12-00.md"), None);
+    }
+}
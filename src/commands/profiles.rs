@@ -0,0 +1,46 @@
+use crate::config::{Config, config_path, expand_tilde};
+use crate::error::Result;
+
+/// List the profiles from the configuration file
+///
+/// The active profile is marked with `*`, the default profile with `(default)`.
+pub fn list_profiles(config: &Config) -> Result<()> {
+    if let Some(path) = config_path() {
+        println!("Configuration file: {}", path.display());
+    }
+
+    if config.profiles.is_empty() {
+        println!("No profiles configured.");
+        return Ok(());
+    }
+
+    let width = config
+        .profiles
+        .keys()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0);
+
+    for (name, profile) in &config.profiles {
+        let marker = if config.active_profile() == Some(name.as_str()) {
+            '*'
+        } else {
+            ' '
+        };
+        let default = if config.default_profile.as_deref() == Some(name.as_str()) {
+            " (default)"
+        } else {
+            ""
+        };
+
+        println!(
+            "{} {:width$}  {}{}",
+            marker,
+            name,
+            expand_tilde(&profile.notes_dir).display(),
+            default,
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::cli::PruneArgs;
+use crate::config;
+use crate::core::datetime::format_display_date;
+use crate::db::Database;
+use crate::error::Result;
+
+/// Delete the notes whose tags expired, after listing them
+///
+/// Without `--yes`, the user is asked to confirm; if stdin is not a terminal,
+/// the notes are only listed.
+pub fn prune(notes_dir: &Path, args: PruneArgs) -> Result<()> {
+    let policy = config::get().retention()?;
+    if policy.is_empty() {
+        println!("No tags expire; add a [retention] table to the configuration file.");
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await?;
+        db.index_notes().await?;
+
+        let now = Local::now();
+        let expired = db.expired_notes(&policy, now).await?;
+        if expired.is_empty() {
+            println!("No notes expired.");
+            return Ok(());
+        }
+
+        for note in &expired {
+            println!(
+                "{}  {}",
+                format_display_date(note.created.date_naive()),
+                note.filepath
+            );
+        }
+
+        if !args.yes {
+            if !io::stdin().is_terminal() {
+                println!(
+                    "\n{} notes expired; run with --yes to delete them.",
+                    expired.len()
+                );
+                return Ok(());
+            }
+
+            if !confirm_prune(&mut io::stdin().lock(), &mut io::stderr(), expired.len())? {
+                return Ok(());
+            }
+        }
+
+        let pruned = db.prune_notes(&policy, now, &expired).await?;
+        println!("Deleted {} expired notes.", pruned.len());
+        if pruned.len() < expired.len() {
+            println!(
+                "Kept {} notes that changed since they were indexed.",
+                expired.len() - pruned.len()
+            );
+        }
+
+        Ok(())
+    })
+}
+
+/// Ask whether the expired notes should be deleted
+///
+/// Anything but `y` or `yes` (in any case) counts as no.
+fn confirm_prune(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    count: usize,
+) -> io::Result<bool> {
+    write!(output, "\nDelete these {} notes? [y/N] ", count)?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm_prune() {
+        let mut output = Vec::new();
+        assert!(confirm_prune(&mut "Yes\n".as_bytes(), &mut output, 3).unwrap());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\nDelete these 3 notes? [y/N] "
+        );
+
+        for answer in ["n\n", "\n", ""] {
+            assert!(!confirm_prune(&mut answer.as_bytes(), &mut Vec::new(), 3).unwrap());
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use crate::cli::ReindexArgs;
+use crate::db::{Database, FileStatus, check_notes_dir};
+use crate::error::Result;
+use crate::utils::SkipReason;
+
+/// The statuses in the order they are listed in the summary
+const SUMMARY_ORDER: [FileStatus; 7] = [
+    FileStatus::WillIndex,
+    FileStatus::Unchanged,
+    FileStatus::Skipped(SkipReason::WrongExtension),
+    FileStatus::Skipped(SkipReason::NotANoteFilename),
+    FileStatus::Skipped(SkipReason::TooLarge),
+    FileStatus::Private,
+    FileStatus::Invalid(String::new()),
+];
+
+/// Update the search index, or report what would be indexed with --check
+pub fn reindex(notes_dir: &Path, args: ReindexArgs) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    let mut stats = None;
+    let mut verify_stats = None;
+    let files = rt.block_on(async {
+        if args.check {
+            // Only read from the database
+            let indexed_mtimes = Database::read_indexed_mtimes(notes_dir).await?;
+            check_notes_dir(notes_dir, &indexed_mtimes).await
+        } else {
+            let db = Database::initialize(notes_dir).await?;
+            let indexed_mtimes = Database::read_indexed_mtimes(notes_dir).await?;
+            let files = check_notes_dir(notes_dir, &indexed_mtimes).await?;
+            stats = Some(db.index_notes().await?);
+            if args.verify {
+                verify_stats = Some(db.verify_content_hashes().await?);
+            }
+            Ok(files)
+        }
+    })?;
+
+    if args.verbose {
+        for (path, status) in &files {
+            match status {
+                FileStatus::Invalid(error) => println!("{}: {} ({})", status.label(), path, error),
+                _ => println!("{}: {}", status.label(), path),
+            }
+        }
+        println!();
+    }
+
+    print!("{}", summary(&files));
+
+    if let Some(stats) = stats {
+        println!(
+            "\nThe search index is up to date ({} of {} files were note files).",
+            stats.files_sent, stats.files_seen
+        );
+    }
+
+    if let Some(verify_stats) = verify_stats {
+        println!(
+            "Verified {} notes; {} had changed without a new modification time and were indexed again.",
+            verify_stats.checked, verify_stats.reindexed
+        );
+    }
+
+    Ok(())
+}
+
+/// Format a table with the number of files per status
+fn summary(files: &[(String, FileStatus)]) -> String {
+    let rows: Vec<(String, usize)> = SUMMARY_ORDER
+        .iter()
+        .map(|status| {
+            let count = files
+                .iter()
+                .filter(|(_, file_status)| same_kind(file_status, status))
+                .count();
+            (status.label(), count)
+        })
+        .collect();
+
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+
+    let mut table = String::new();
+    for (label, count) in rows {
+        table.push_str(&format!("{:width$}  {:>6}\n", label, count));
+    }
+    table.push_str(&format!("{:width$}  {:>6}\n", "total", files.len()));
+
+    table
+}
+
+/// Check if two statuses are the same, ignoring the error of invalid files
+fn same_kind(a: &FileStatus, b: &FileStatus) -> bool {
+    match (a, b) {
+        (FileStatus::Invalid(_), FileStatus::Invalid(_)) => true,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let files = vec![
+            ("2025/01_January/a.md".to_string(), FileStatus::WillIndex),
+            ("2025/01_January/b.md".to_string(), FileStatus::WillIndex),
+            (
+                "2025/01_January/c.md".to_string(),
+                FileStatus::Invalid("missing frontmatter".to_string()),
+            ),
+            (
+                "README.md".to_string(),
+                FileStatus::Skipped(SkipReason::NotANoteFilename),
+            ),
+        ];
+
+        assert_eq!(
+            summary(&files),
+            "will index                         2\n\
+             already indexed, unchanged         0\n\
+             skipped: wrong extension           0\n\
+             skipped: not a note filename       1\n\
+             skipped: too large                 0\n\
+             skipped: private                   0\n\
+             skipped: invalid frontmatter       1\n\
+             total                              4\n"
+        );
+    }
+}
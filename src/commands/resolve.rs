@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::core::id::normalize_id_prefix;
+use crate::core::note::Note;
+use crate::db::Database;
+use crate::error::{NotelogError, Result};
+use crate::hooks::{Hook, run_hook};
+
+/// Find the file of the note with the given ID prefix
+///
+/// Returns the path relative to the notes directory.
+pub async fn resolve_note_path(db: &Database, id: &str) -> Result<PathBuf> {
+    let id_prefix = normalize_id_prefix(id)?;
+
+    db.get_filepath_by_id_prefix(&id_prefix)
+        .await?
+        .map(PathBuf::from)
+        .ok_or(NotelogError::NoteNotFound(id_prefix))
+}
+
+/// Find the note with the given ID prefix in the index
+pub async fn resolve_note(db: &Database, id: &str) -> Result<Note> {
+    let id_prefix = normalize_id_prefix(id)?;
+
+    db.fetch_note_by_id(&id_prefix)
+        .await?
+        .ok_or(NotelogError::NoteNotFound(id_prefix))
+}
+
+/// Change the frontmatter of a note file and write it back
+///
+/// Like the `edit_tags` MCP tool, the note is parsed and written out again
+/// with the updated frontmatter; the content is left as it is. Runs the
+/// post-edit hook and returns the updated note.
+pub fn rewrite_note<F>(notes_dir: &Path, relative_path: &Path, update: F) -> Result<Note>
+where
+    F: FnOnce(&mut Note) -> Result<()>,
+{
+    let path = notes_dir.join(relative_path);
+    let mut note = Note::from_str(&fs::read_to_string(&path)?)?;
+
+    update(&mut note)?;
+
+    fs::write(&path, note.formatted_content())?;
+    run_hook(notes_dir, Hook::PostEdit, relative_path, &note);
+
+    Ok(note)
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::cli::ReviewArgs;
+use crate::commands::add::{edit_until_valid, note_link};
+use crate::config;
+use crate::core::datetime::{week_bounds, week_name};
+use crate::core::note::Note;
+use crate::core::note_builder::NoteBuilder;
+use crate::core::tags::Tag;
+use crate::db::Database;
+use crate::error::{NotelogError, Result};
+use crate::hooks::{Hook, run_hook};
+
+/// The tag of the review notes, whose notes are left out of later reviews
+const REVIEW_TAG: &str = "weekly-review";
+
+/// Write a review of this week's notes as a new note
+///
+/// The review lists the notes created this week, grouped by tag, and has
+/// empty sections to fill in. Unless `--no-edit` is given, it is opened in the
+/// editor before it is saved.
+pub fn review(notes_dir: &Path, args: ReviewArgs) -> Result<()> {
+    let week_start = args.week_start.unwrap_or(config::get().week_start);
+    let (start, end) = week_bounds(Local::now().date_naive(), week_start);
+    let review_tag = Tag::new(REVIEW_TAG)?;
+
+    let mut notes = notes_created_between(notes_dir, start, end)?;
+    notes.retain(|note| !note.frontmatter().tags().contains(&review_tag));
+
+    let mut tags = config::get().default_tags()?;
+    if !tags.contains(&review_tag) {
+        tags.push(review_tag);
+    }
+    let generate_id = config::get().generate_ids();
+
+    let mut note = NoteBuilder::new()
+        .content(review_skeleton(start.date_naive(), &notes))
+        .tags(tags)
+        .generate_id(generate_id)
+        .build()?;
+
+    if !args.no_edit {
+        let content = edit_until_valid(note.formatted_content(), None)?;
+        note = NoteBuilder::new()
+            .content(content)
+            .generate_id(generate_id)
+            .parse_or_create()?;
+    }
+
+    // Record that the note was added from the command line
+    if note.frontmatter().source().is_none() {
+        note.frontmatter_mut().set_source("cli");
+    }
+
+    let relative_path = note.save(notes_dir, None)?;
+    println!(
+        "Note saved to: {}",
+        notes_dir.join(&relative_path).display()
+    );
+
+    run_hook(notes_dir, Hook::PostAdd, &relative_path, &note);
+
+    Ok(())
+}
+
+/// The notes created from `start` (inclusive) to `end` (exclusive), oldest first
+pub fn notes_created_between(
+    notes_dir: &Path,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+) -> Result<Vec<Note>> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let db = Database::open_for_query(notes_dir).await?;
+
+        let mut notes = Vec::new();
+        db.list_notes(|_, note| {
+            let created = *note.frontmatter().created();
+            if start <= created && created < end {
+                notes.push(note);
+            }
+            Ok(())
+        })
+        .await?;
+
+        // Oldest first
+        notes.reverse();
+        Ok::<_, NotelogError>(notes)
+    })
+}
+
+/// The content of a review of the week starting on `week_start`
+///
+/// Notes with several tags are listed under each of them. Notes with an ID
+/// are linked, so that the review shows up as a backlink.
+fn review_skeleton(week_start: NaiveDate, notes: &[Note]) -> String {
+    let mut by_tag: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut untagged = Vec::new();
+    for note in notes {
+        let title = note.extract_title();
+        let entry = match note.frontmatter().id() {
+            Some(id) => note_link(&title, id),
+            None => format!("- {}", title),
+        };
+
+        for tag in note.frontmatter().tags() {
+            by_tag.entry(tag.as_str()).or_default().push(entry.clone());
+        }
+        if note.frontmatter().tags().is_empty() {
+            untagged.push(entry);
+        }
+    }
+
+    let mut content = format!("# Weekly review {}\n\n## Notes\n\n", week_name(week_start));
+    if notes.is_empty() {
+        content.push_str("No notes this week.\n\n");
+    }
+
+    let sections = by_tag
+        .into_iter()
+        .map(|(tag, entries)| (format!("+{}", tag), entries))
+        .chain((!untagged.is_empty()).then(|| ("Untagged".to_string(), untagged)));
+    for (heading, entries) in sections {
+        content.push_str(&format!("### {}\n\n{}\n\n", heading, entries.join("\n")));
+    }
+
+    content.push_str("## Highlights\n\n\n\n## Lowlights\n\n\n\n## Next week\n");
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frontmatter::Frontmatter;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_review_skeleton() {
+        let note = |title: &str, tags: &[&str], id: bool| {
+            let created = Local.with_ymd_and_hms(2025, 5, 6, 12, 0, 0).unwrap();
+            let tags = tags.iter().map(|tag| Tag::new(tag).unwrap()).collect();
+            let mut frontmatter = Frontmatter::new(created, tags);
+            if !id {
+                frontmatter.clear_id();
+            }
+            Note::new(frontmatter, format!("# {}", title))
+        };
+        let notes = vec![
+            note("Deployed the API", &["work", "ops"], true),
+            note("Groceries", &[], false),
+        ];
+        let id = notes[0].frontmatter().id().unwrap();
+
+        let week_start = NaiveDate::from_ymd_opt(2025, 5, 5).unwrap();
+        let skeleton = review_skeleton(week_start, &notes);
+        let link = format!("- [Deployed the API](notelog://note/{})", id);
+        assert_eq!(
+            skeleton,
+            format!(
+                "# Weekly review 2025-W19\n\n## Notes\n\n\
+                 ### +ops\n\n{link}\n\n\
+                 ### +work\n\n{link}\n\n\
+                 ### Untagged\n\n- Groceries\n\n\
+                 ## Highlights\n\n\n\n## Lowlights\n\n\n\n## Next week\n"
+            )
+        );
+
+        // Weeks that start on Sunday are named after the ISO week they overlap
+        let sunday = NaiveDate::from_ymd_opt(2025, 5, 4).unwrap();
+        assert!(review_skeleton(sunday, &[]).starts_with("# Weekly review 2025-W19\n"));
+
+        let skeleton = review_skeleton(week_start, &[]);
+        assert!(skeleton.contains("## Notes\n\nNo notes this week.\n\n## Highlights"));
+    }
+}
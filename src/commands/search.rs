@@ -0,0 +1,337 @@
+use std::fs::File;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+use crate::cli::SearchArgs;
+use crate::commands::list::format_summary;
+use crate::core::datetime::format_display_date;
+use crate::core::note::Note;
+use crate::daemon::client;
+use crate::daemon::protocol::{SearchRequest, SearchResults};
+use crate::db::{
+    Database, SearchOrder, SearchQuery, check_query_size, first_match_line, search_terms,
+};
+use crate::error::{NotelogError, Result};
+use crate::history::{self, HistoryEntry, expand_rerun, history_path};
+use crate::output::{fit_row, table_width};
+use crate::utils::{decode_utf8, edit_note_file_at, read_bounded, read_file_content};
+
+/// Results whose rank differs from the top rank by less than this fraction are
+/// considered tied with it
+const RANK_TIE_THRESHOLD: f64 = 0.05;
+
+/// The maximum number of tied results offered to choose from
+const MAX_CHOICES: usize = 5;
+
+/// Search the notes, and optionally open one of the results in the editor
+pub fn search_notes(notes_dir: &Path, args: SearchArgs) -> Result<()> {
+    let history_path = history_path();
+    if args.history {
+        return print_history(history_path.as_deref(), args.limit);
+    }
+
+    let open = args.open || args.open_n.is_some();
+
+    // Opening a note needs a terminal, both for the prompt and the editor
+    if open && !io::stdin().is_terminal() {
+        return Err(NotelogError::OpenRequiresTerminal);
+    }
+
+    // `!N` re-runs the N-th most recent query
+    let mut query = match query_file(&args) {
+        Some(path) => read_query(path)?,
+        None => args.query.join(" "),
+    };
+    let history = match &history_path {
+        Some(path) => history::load(path)?,
+        None => Vec::new(),
+    };
+    if let Some(previous) = expand_rerun(&query, &history)? {
+        eprintln!("Searching for: {}", previous);
+        query = previous;
+    }
+    let open_n = args.open_n.map(NonZeroUsize::get);
+    let limit = open_n.map_or(args.limit, |n| args.limit.max(n));
+
+    let request = SearchRequest {
+        query: query.clone(),
+        limit,
+        offset: args.offset,
+        order: args.order.to_string(),
+        path: args.path.clone(),
+    };
+    let search = request.to_query()?;
+
+    // A running daemon has the index open already
+    let (results, total_count) = match client::search(notes_dir, &request) {
+        Some(results) => results?,
+        None => {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(search_index(notes_dir, &search))?
+        }
+    };
+
+    // A search works without history, so a failure to record it is only a warning
+    if let Some(path) = &history_path
+        && let Err(e) = history::append(path, HistoryEntry::new(&query, total_count))
+    {
+        eprintln!("Warning: could not update the search history: {}", e);
+    }
+
+    let terms = search_terms(&query);
+    let rows: Vec<String> = results
+        .iter()
+        .map(|(short_id, hit)| result_row(short_id, &hit.note, &terms, args.long))
+        .collect();
+
+    let index = if let Some(n) = open_n {
+        if n > results.len() {
+            return Err(NotelogError::NoSearchResult(format!(
+                "result {} requested, but only {} notes match '{}'",
+                n,
+                results.len(),
+                query
+            )));
+        }
+        n - 1
+    } else if args.open {
+        if results.is_empty() {
+            return Err(NotelogError::NoSearchResult(format!(
+                "no notes match '{}'",
+                query
+            )));
+        }
+
+        // Only the best matches can be tied; in date order, the first one is opened
+        let ranks: Vec<f64> = results.iter().map(|(_, hit)| hit.rank).collect();
+        let tied = if args.order == SearchOrder::Relevance {
+            tied_with_top(&ranks).min(MAX_CHOICES)
+        } else {
+            1
+        };
+        if tied > 1 {
+            let choice = choose_result(&mut io::stdin().lock(), &mut io::stderr(), &rows[..tied])?;
+            match choice {
+                Some(index) => index,
+                None => return Err(NotelogError::UserCancelled),
+            }
+        } else {
+            0
+        }
+    } else {
+        println!("{}", header(args.long));
+        let width = table_width();
+        for row in rows {
+            println!("{}", fit_row(row, width));
+        }
+        if total_count > args.offset + results.len() {
+            println!(
+                "({} of {} matching notes shown)",
+                results.len(),
+                total_count
+            );
+        }
+        return Ok(());
+    };
+
+    // The file may differ from the indexed note, so look for the match again
+    let path = notes_dir.join(&results[index].1.filepath);
+    let line = first_match_line(&read_file_content(&path)?, &terms);
+    edit_note_file_at(notes_dir, &path, line)?;
+
+    Ok(())
+}
+
+/// Search the index without a daemon, returning the results with the
+/// shortest unique prefixes of their IDs and the number of matching notes
+async fn search_index(notes_dir: &Path, search: &SearchQuery) -> Result<SearchResults> {
+    let db = Database::open_for_query(notes_dir).await?;
+    let (hits, total_count) = db.search_hits(search).await?;
+
+    let mut results = Vec::with_capacity(hits.len());
+    for hit in hits {
+        let short_id = match hit.note.frontmatter().id() {
+            Some(id) => db.find_shortest_unique_id_prefix(id).await?,
+            None => String::new(),
+        };
+        results.push((short_id, hit));
+    }
+
+    Ok((results, total_count))
+}
+
+/// The file to read the query from, with `-` for stdin
+fn query_file(args: &SearchArgs) -> Option<&Path> {
+    match (&args.query_file, args.query.as_slice()) {
+        (Some(path), _) => Some(path),
+        (None, [dash]) if dash == "-" => Some(Path::new("-")),
+        _ => None,
+    }
+}
+
+/// Read a search query from a file, or from stdin if the path is `-`
+fn read_query(path: &Path) -> Result<String> {
+    let (bytes, source) = if path == Path::new("-") {
+        (read_bounded(io::stdin().lock())?, "stdin".to_string())
+    } else {
+        (read_bounded(File::open(path)?)?, path.display().to_string())
+    };
+
+    parse_query(&bytes, &source)
+}
+
+/// Decode a query that was read from `source`, without its trailing newlines
+fn parse_query(bytes: &[u8], source: &str) -> Result<String> {
+    let query = decode_utf8(bytes, source)?.trim_end_matches(['\n', '\r']);
+    check_query_size(query)?;
+    Ok(query.to_string())
+}
+
+/// Print the most recent queries from the search history, most recent first
+///
+/// The numbers are the ones to re-run the queries with, e.g. `notelog search !2`.
+fn print_history(path: Option<&Path>, limit: usize) -> Result<()> {
+    let history = match path {
+        Some(path) => history::load(path)?,
+        None => Vec::new(),
+    };
+
+    for (n, entry) in history.iter().rev().take(limit).enumerate() {
+        println!(
+            "{:>3}  {} {}  {:>4} {}  {}",
+            n + 1,
+            format_display_date(entry.time.date_naive()),
+            entry.time.format("%H:%M"),
+            entry.hits,
+            if entry.hits == 1 { "hit " } else { "hits" },
+            entry.query
+        );
+    }
+
+    Ok(())
+}
+
+/// The column names of the search results, with the WORDS and TAGS columns if `long`
+fn header(long: bool) -> String {
+    if long {
+        format!(
+            "{:<8}  {:>4}  {:>5}  {:>4}  {:<16}  {}",
+            "ID", "LINE", "WORDS", "TAGS", "CREATED", "TITLE"
+        )
+    } else {
+        format!("{:<8}  {:>4}  {:<16}  {}", "ID", "LINE", "CREATED", "TITLE")
+    }
+}
+
+/// Format a search result like a line of `notelog list`, with the line of the
+/// first match (or `-` if the note only matched through its tags or stemming)
+///
+/// With `long`, the row also has the number of words and tags of the note.
+fn result_row(short_id: &str, note: &Note, terms: &[Vec<String>], long: bool) -> String {
+    let line = first_match_line(&note.to_string(), terms)
+        .map_or_else(|| "-".to_string(), |line| line.to_string());
+
+    if long {
+        return format!(
+            "{:<8}  {:>4}  {:>5}  {:>4}  {}",
+            short_id,
+            line,
+            note.word_count(),
+            note.frontmatter().tags().len(),
+            format_summary(note)
+        );
+    }
+
+    format!("{:<8}  {:>4}  {}", short_id, line, format_summary(note))
+}
+
+/// Count the results that are ranked about as well as the first one
+///
+/// The ranks must be sorted from best to worst, as returned by the search.
+fn tied_with_top(ranks: &[f64]) -> usize {
+    let Some(&top) = ranks.first() else {
+        return 0;
+    };
+
+    ranks
+        .iter()
+        .take_while(|rank| (*rank - top).abs() <= top.abs() * RANK_TIE_THRESHOLD)
+        .count()
+}
+
+/// Ask which of the closely ranked results to open
+///
+/// Returns the index of the chosen result, or `None` if the user entered
+/// nothing or something other than one of the listed numbers.
+fn choose_result(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    rows: &[String],
+) -> io::Result<Option<usize>> {
+    writeln!(output, "Several notes match about equally well:")?;
+    for (i, row) in rows.iter().enumerate() {
+        writeln!(output, "{:>3}) {}", i + 1, row)?;
+    }
+    write!(output, "Open which note? [1-{}] ", rows.len())?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    Ok(answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=rows.len()).contains(n))
+        .map(|n| n - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query(b"deploy +project\r\n\n", "stdin").unwrap(),
+            "deploy +project"
+        );
+        assert_eq!(
+            parse_query(b"(a OR b)\nNOT c\n", "stdin").unwrap(),
+            "(a OR b)\nNOT c"
+        );
+
+        let too_long = "a".repeat(crate::constants::MAX_SEARCH_QUERY_CHARS + 1);
+        assert!(parse_query(too_long.as_bytes(), "stdin").is_err());
+        assert!(parse_query(b"\xff", "stdin").is_err());
+    }
+
+    #[test]
+    fn test_tied_with_top() {
+        assert_eq!(tied_with_top(&[]), 0);
+        assert_eq!(tied_with_top(&[-3.0, -1.0]), 1);
+        assert_eq!(tied_with_top(&[-3.0, -2.95, -2.9, -1.0]), 3);
+
+        // Queries consisting only of filters are not ranked
+        assert_eq!(tied_with_top(&[0.0, 0.0]), 2);
+    }
+
+    #[test]
+    fn test_choose_result() {
+        let rows = vec!["first".to_string(), "second".to_string()];
+
+        let mut output = Vec::new();
+        let choice = choose_result(&mut io::Cursor::new("2\n"), &mut output, &rows).unwrap();
+        assert_eq!(choice, Some(1));
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("  1) first\n"));
+        assert!(output.ends_with("Open which note? [1-2] "));
+
+        for answer in ["\n", "3\n", "0\n", "first\n"] {
+            let choice = choose_result(&mut io::Cursor::new(answer), &mut Vec::new(), &rows);
+            assert_eq!(choice.unwrap(), None, "answer {:?}", answer);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::AddArgs;
+use crate::commands::add::add_note;
+use crate::commands::resolve::{resolve_note_path, rewrite_note};
+use crate::core::tags::Tag;
+use crate::db::{Database, SearchQuery};
+use crate::error::{NotelogError, Result};
+
+/// Check that notelog works, using a throwaway notes directory
+///
+/// Adds a note, indexes and searches it, edits its tags and deletes it again,
+/// printing the outcome of each step. Stops at the first failing step.
+pub fn selftest() -> Result<()> {
+    let temp_dir = tempfile::Builder::new()
+        .prefix("notelog-selftest-")
+        .tempdir()?;
+    let notes_dir = temp_dir.path();
+    println!("Running the self test in {}", notes_dir.display());
+
+    let relative_path = report("add a note", add(notes_dir))?;
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let db = report("index the notes", index(notes_dir).await)?;
+        report("search the notes", search(&db).await)?;
+        report("edit the tags", edit_tags(notes_dir, &db).await)?;
+        report(
+            "delete the note",
+            delete(notes_dir, &db, &relative_path).await,
+        )
+    })?;
+
+    println!("Self test passed");
+    Ok(())
+}
+
+/// Print the outcome of a step and pass its result on
+fn report<T>(step: &str, result: Result<T>) -> Result<T> {
+    match &result {
+        Ok(_) => println!("  ok    {}", step),
+        Err(e) => println!("  FAIL  {}: {}", step, e),
+    }
+
+    result.map_err(|e| NotelogError::SelftestFailed(format!("{}: {}", step, e)))
+}
+
+/// Fail with the given message unless the condition holds
+fn ensure(condition: bool, message: &str) -> Result<()> {
+    if condition {
+        Ok(())
+    } else {
+        Err(NotelogError::SelftestFailed(message.to_string()))
+    }
+}
+
+/// Count the notes matching a search query
+async fn count(db: &Database, query: &str) -> Result<usize> {
+    let (_, total) = db.search_notes(&SearchQuery::new(query)).await?;
+    Ok(total)
+}
+
+/// Add a note like `notelog add` does
+fn add(notes_dir: &Path) -> Result<PathBuf> {
+    let args = AddArgs {
+        title: Some("Selftest".to_string()),
+        args: vec![
+            "Checking".to_string(),
+            "the".to_string(),
+            "installation".to_string(),
+            "+selftest".to_string(),
+        ],
+        ..Default::default()
+    };
+
+    let relative_path = add_note(notes_dir, args, Vec::new())?;
+    ensure(
+        notes_dir.join(&relative_path).is_file(),
+        "the note file was not written",
+    )?;
+
+    Ok(relative_path)
+}
+
+/// Create the database and index the new note
+async fn index(notes_dir: &Path) -> Result<Database> {
+    let db = Database::initialize(notes_dir).await?;
+    db.index_notes().await?;
+    ensure(
+        count(&db, "+selftest").await? == 1,
+        "the note was not indexed",
+    )?;
+
+    Ok(db)
+}
+
+/// Search for a word that is in the note and one that is not
+async fn search(db: &Database) -> Result<()> {
+    ensure(
+        count(db, "installation").await? == 1,
+        "a search for a word of the note found nothing",
+    )?;
+    ensure(
+        count(db, "uninstalled").await? == 0,
+        "a search for a missing word found a note",
+    )
+}
+
+/// Replace the tag of the note and check that the index follows
+async fn edit_tags(notes_dir: &Path, db: &Database) -> Result<()> {
+    let relative_path = resolve_note_path(db, &note_id(db).await?).await?;
+    rewrite_note(notes_dir, &relative_path, |note| {
+        note.update_tags([Tag::new("checked")?], [Tag::new("selftest")?])
+    })?;
+
+    db.index_notes().await?;
+    ensure(
+        count(db, "+checked").await? == 1,
+        "the new tag was not indexed",
+    )?;
+    ensure(
+        count(db, "+selftest").await? == 0,
+        "the removed tag is still indexed",
+    )
+}
+
+/// Delete the note file and check that it leaves the index
+async fn delete(notes_dir: &Path, db: &Database, relative_path: &Path) -> Result<()> {
+    fs::remove_file(notes_dir.join(relative_path))?;
+
+    db.index_notes().await?;
+    ensure(
+        count(db, "+checked").await? == 0,
+        "the deleted note is still indexed",
+    )
+}
+
+/// Get the ID of the only note
+async fn note_id(db: &Database) -> Result<String> {
+    let (notes, _) = db.search_notes(&SearchQuery::new("+selftest")).await?;
+    notes
+        .first()
+        .and_then(|note| note.frontmatter().id())
+        .map(|id| id.as_str().to_string())
+        .ok_or_else(|| NotelogError::SelftestFailed("the note has no ID".to_string()))
+}
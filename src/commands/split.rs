@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::cli::SplitArgs;
+use crate::commands::add::note_link;
+use crate::commands::resolve::resolve_note_path;
+use crate::config;
+use crate::core::id::Id;
+use crate::core::note::Note;
+use crate::core::note_builder::NoteBuilder;
+use crate::core::sections::{find_section, promote_headings, replace_section};
+use crate::core::tags::Tag;
+use crate::db::Database;
+use crate::error::{NotelogError, Result};
+use crate::hooks::{Hook, run_hook};
+use crate::utils::write_file_atomically;
+
+/// Move a section of a note into a new note
+///
+/// The new note gets the tags of the original one plus the given ones, and
+/// the section is replaced by a link to it; the new note links back. The
+/// original note is replaced atomically, and both notes are indexed right away.
+pub fn split_note(notes_dir: &Path, args: SplitArgs) -> Result<()> {
+    let extra_tags = args
+        .tags
+        .iter()
+        .map(|tag| Tag::new(tag))
+        .collect::<Result<Vec<_>>>()?;
+    let generate_id = config::get().generate_ids();
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await?;
+        db.index_notes().await?;
+
+        let path = resolve_note_path(&db, &args.id).await?;
+        let mut note = Note::from_str(&fs::read_to_string(notes_dir.join(&path))?)?;
+        let section = find_section(note.content(), &args.heading)
+            .ok_or_else(|| NotelogError::SectionNotFound(args.heading.clone()))?;
+
+        // The notes can only link to each other by ID
+        if note.frontmatter().id().is_none() && generate_id {
+            note.frontmatter_mut().set_id(Id::default());
+        }
+
+        let mut content = promote_headings(&note.content()[section.range.clone()])
+            .trim_end()
+            .to_string();
+        if let Some(id) = note.frontmatter().id() {
+            content.push_str(&format!(
+                "\n\nSplit from:\n\n{}\n",
+                note_link(&note.extract_title(), id)
+            ));
+        }
+
+        let mut builder = NoteBuilder::new()
+            .content(content)
+            .tags(note.frontmatter().tags().iter().cloned().chain(extra_tags))
+            .source("cli")
+            .generate_id(generate_id);
+        if let Some(created) = args.created {
+            builder = builder.created(created);
+        }
+        let new_note = builder.build()?;
+
+        let link = new_note
+            .frontmatter()
+            .id()
+            .map(|id| note_link(&section.heading.title, id))
+            .unwrap_or_default();
+        let original = Note::new(
+            note.frontmatter().clone(),
+            replace_section(note.content(), &section, &link),
+        );
+
+        // Without the section in the original note, the new note must exist
+        let new_path = new_note.save(notes_dir, None)?;
+        if let Err(e) = write_file_atomically(&notes_dir.join(&path), &original.formatted_content())
+        {
+            let _ = fs::remove_file(notes_dir.join(&new_path));
+            return Err(e);
+        }
+
+        db.index_file(&new_path).await?;
+        db.index_file(&path).await?;
+        println!("Note saved to: {}", notes_dir.join(&new_path).display());
+        println!("Note updated: {}", notes_dir.join(&path).display());
+
+        run_hook(notes_dir, Hook::PostAdd, &new_path, &new_note);
+        run_hook(notes_dir, Hook::PostEdit, &path, &original);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::cli::{TagAction, TagArgs};
+use crate::commands::resolve::rewrite_note;
+use crate::core::datetime::format_display_date;
+use crate::core::tag_clusters::{TagCluster, find_tag_clusters};
+use crate::core::tags::Tag;
+use crate::db::{Database, TagTimeline};
+use crate::error::{DatabaseError, NotelogError, Result};
+
+/// The width of the longest bar of the chart, in characters
+const BAR_WIDTH: usize = 40;
+
+/// Show reports about a tag
+pub fn tag(notes_dir: &Path, args: TagArgs) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        match args.action {
+            TagAction::Stats { tag, json } => {
+                let db = Database::open_for_query(notes_dir).await?;
+                let timeline = db.tag_timeline(&Tag::new(&tag)?).await?;
+                if json {
+                    let json = serde_json::to_string_pretty(&timeline)
+                        .map_err(|e| DatabaseError::Serialization(e.to_string()))?;
+                    println!("{}", json);
+                } else {
+                    print!("{}", render_timeline(&timeline));
+                }
+                Ok(())
+            }
+            TagAction::SuggestCleanup { apply } => suggest_cleanup(notes_dir, apply).await,
+        }
+    })
+}
+
+/// List the clusters of near-duplicate tags, and optionally merge one of them
+async fn suggest_cleanup(notes_dir: &Path, apply: bool) -> Result<()> {
+    // Merging rewrites notes, so the index must be up to date
+    let db = if apply {
+        let db = Database::initialize(notes_dir).await?;
+        db.index_notes().await?;
+        db
+    } else {
+        Database::open_for_query(notes_dir).await?
+    };
+
+    let clusters = find_tag_clusters(&tag_counts(&db).await?);
+    if clusters.is_empty() {
+        println!("No near-duplicate tags found.");
+        return Ok(());
+    }
+
+    for (i, cluster) in clusters.iter().enumerate() {
+        println!("{:>3}) {}", i + 1, format_cluster(cluster));
+    }
+    if !apply {
+        return Ok(());
+    }
+
+    let Some(index) = choose_cluster(&mut io::stdin().lock(), &mut io::stderr(), clusters.len())?
+    else {
+        return Err(NotelogError::UserCancelled);
+    };
+    let cluster = &clusters[index];
+
+    // The notes may have changed while the user was choosing
+    db.index_notes().await?;
+    let counts = tag_counts(&db).await?;
+    for (tag, listed) in &cluster.tags {
+        let now = counts
+            .iter()
+            .find(|(name, _)| name == tag)
+            .map_or(0, |(_, count)| *count);
+        if now != *listed {
+            return Err(NotelogError::TagCountChanged {
+                tag: tag.clone(),
+                listed: *listed,
+                now,
+            });
+        }
+    }
+
+    let merged = merge_cluster(notes_dir, &db, cluster).await?;
+    println!("Retagged {} notes with +{}", merged.len(), cluster.target());
+
+    Ok(())
+}
+
+/// The tags with the number of notes that have each of them
+async fn tag_counts(db: &Database) -> Result<Vec<(String, usize)>> {
+    Ok(db
+        .tag_counts()
+        .await?
+        .into_iter()
+        .map(|count| (count.tag, count.count))
+        .collect())
+}
+
+/// Replace the other tags of a cluster with its target in each note that has them
+///
+/// Returns the paths of the rewritten notes, relative to the notes directory.
+async fn merge_cluster(
+    notes_dir: &Path,
+    db: &Database,
+    cluster: &TagCluster,
+) -> Result<Vec<String>> {
+    let target = Tag::new(cluster.target())?;
+    let sources = cluster
+        .sources()
+        .iter()
+        .map(|(tag, _)| Tag::new(tag))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut merged = Vec::new();
+    let mut seen = HashSet::new();
+    for source in &sources {
+        for filepath in db.filepaths_with_tag(source).await? {
+            // Notes with several of the tags are only rewritten once
+            if !seen.insert(filepath.clone()) {
+                continue;
+            }
+
+            let relative_path = Path::new(&filepath);
+            rewrite_note(notes_dir, relative_path, |note| {
+                note.update_tags([target.clone()], sources.iter().cloned())
+            })?;
+            db.index_file(relative_path).await?;
+            merged.push(filepath);
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Format a cluster as the tag to keep and the tags to merge into it, with
+/// the number of notes with each tag
+fn format_cluster(cluster: &TagCluster) -> String {
+    let sources = cluster
+        .sources()
+        .iter()
+        .map(|(tag, count)| format!("+{} ({})", tag, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "+{} ({})  <-  {}",
+        cluster.target(),
+        cluster.tags[0].1,
+        sources
+    )
+}
+
+/// Ask which of the listed clusters to merge
+///
+/// Returns the index of the chosen cluster, or `None` if the user entered
+/// nothing or something other than one of the listed numbers.
+fn choose_cluster(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    count: usize,
+) -> io::Result<Option<usize>> {
+    write!(output, "Merge which tags? [1-{}] ", count)?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    Ok(answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=count).contains(n))
+        .map(|n| n - 1))
+}
+
+/// Format a timeline as a summary, a bar chart of the months and the
+/// co-occurring tags
+fn render_timeline(timeline: &TagTimeline) -> String {
+    let (Some(first), Some(last)) = (timeline.first, timeline.last) else {
+        return format!("No notes are tagged +{}.\n", timeline.tag);
+    };
+
+    let mut out = format!(
+        "+{}: {} notes, first used on {}, last used on {}\n\n",
+        timeline.tag,
+        timeline.total(),
+        format_display_date(first.date_naive()),
+        format_display_date(last.date_naive())
+    );
+
+    let max = timeline
+        .months
+        .iter()
+        .map(|month| month.count)
+        .max()
+        .unwrap_or(0);
+    let count_width = max.to_string().len();
+    for month in &timeline.months {
+        // Months with notes get at least one character
+        let bar = (month.count * BAR_WIDTH).div_ceil(max.max(1));
+        let line = format!(
+            "{}  {:>count_width$}  {}",
+            month.month,
+            month.count,
+            "#".repeat(bar)
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    if !timeline.co_tags.is_empty() {
+        let co_tags = timeline
+            .co_tags
+            .iter()
+            .map(|tag| format!("+{} ({})", tag.tag, tag.count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!("\nUsed together with: {}\n", co_tags));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{MonthCount, TagCount};
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_render_timeline() {
+        let month = |month: &str, count| MonthCount {
+            month: month.to_string(),
+            count,
+        };
+        let timeline = TagTimeline {
+            tag: "deploy".to_string(),
+            months: vec![
+                month("2025-01", 10),
+                month("2025-02", 0),
+                month("2025-03", 1),
+            ],
+            first: Some(Local.with_ymd_and_hms(2025, 1, 3, 12, 0, 0).unwrap()),
+            last: Some(Local.with_ymd_and_hms(2025, 3, 20, 12, 0, 0).unwrap()),
+            co_tags: vec![TagCount {
+                tag: "ops".to_string(),
+                count: 7,
+            }],
+        };
+
+        let rendered = render_timeline(&timeline);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines[0],
+            "+deploy: 11 notes, first used on 2025-01-03, last used on 2025-03-20"
+        );
+        assert_eq!(lines[2], format!("2025-01  10  {}", "#".repeat(BAR_WIDTH)));
+        assert_eq!(lines[3], "2025-02   0");
+        assert_eq!(lines[4], "2025-03   1  ####");
+        assert_eq!(lines[6], "Used together with: +ops (7)");
+
+        let empty = TagTimeline {
+            tag: "unused".to_string(),
+            ..TagTimeline::default()
+        };
+        assert_eq!(render_timeline(&empty), "No notes are tagged +unused.\n");
+    }
+
+    #[test]
+    fn test_format_cluster() {
+        let cluster = TagCluster {
+            tags: vec![
+                ("kubernetes".to_string(), 12),
+                ("k8s".to_string(), 5),
+                ("kube".to_string(), 2),
+            ],
+        };
+        assert_eq!(
+            format_cluster(&cluster),
+            "+kubernetes (12)  <-  +k8s (5), +kube (2)"
+        );
+    }
+
+    #[test]
+    fn test_choose_cluster() {
+        let choice = choose_cluster(&mut io::Cursor::new("2\n"), &mut Vec::new(), 3).unwrap();
+        assert_eq!(choice, Some(1));
+
+        for answer in ["\n", "4\n", "0\n", "all\n"] {
+            let choice = choose_cluster(&mut io::Cursor::new(answer), &mut Vec::new(), 3);
+            assert_eq!(choice.unwrap(), None, "answer {:?}", answer);
+        }
+    }
+}
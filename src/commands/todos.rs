@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::cli::{DoneArgs, TodosArgs};
+use crate::commands::resolve::{resolve_note_path, rewrite_note};
+use crate::core::todo::{TodoFilter, age_in_days, format_age, is_done, mark_done};
+use crate::db::Database;
+use crate::error::Result;
+
+/// Mark a note as done
+pub fn done(notes_dir: &Path, args: DoneArgs) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await?;
+        db.index_notes().await?;
+
+        let relative_path = resolve_note_path(&db, &args.id).await?;
+        let note = rewrite_note(notes_dir, &relative_path, mark_done)?;
+        println!("Done: {}", note.extract_title());
+
+        Ok(())
+    })
+}
+
+/// List the notes tagged +todo (or +done), oldest first
+pub fn todos(notes_dir: &Path, args: TodosArgs) -> Result<()> {
+    let filter = TodoFilter::from_flags(args.done_only, args.all);
+    let rt = tokio::runtime::Runtime::new()?;
+
+    rt.block_on(async {
+        let db = Database::open_for_query(notes_dir).await?;
+
+        let now = Local::now();
+        for note in db.notes_with_any_tag(&filter.tags()).await? {
+            let short_id = match note.frontmatter().id() {
+                Some(id) => db.find_shortest_unique_id_prefix(id).await?,
+                None => String::new(),
+            };
+            let checkbox = if is_done(&note) { "[x]" } else { "[ ]" };
+            let age = format_age(age_in_days(note.frontmatter().created(), &now));
+
+            println!(
+                "{} {:<8}  {:>12}  {}",
+                checkbox,
+                short_id,
+                age,
+                note.extract_title()
+            );
+        }
+
+        Ok(())
+    })
+}
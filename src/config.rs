@@ -0,0 +1,766 @@
+//! Configuration file handling
+//!
+//! Notelog reads an optional TOML configuration file from
+//! `~/.config/notelog/config.toml` (or the path given by the `NOTELOG_CONFIG`
+//! environment variable). All settings are optional.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use dirs::{config_dir, home_dir};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{
+    DEFAULT_DATE_FORMAT, DEFAULT_MAX_FILENAME_BYTES, DEFAULT_MAX_FOREIGN_ENTRIES,
+    DEFAULT_MIN_CREATED_YEAR, DEFAULT_NOTE_EXTENSIONS, MIN_FILENAME_BYTES,
+};
+use crate::core::datetime::{WeekStart, check_date_format};
+use crate::core::retention::RetentionPolicy;
+use crate::core::tags::Tag;
+use crate::error::{NotelogError, Result};
+
+/// The effective configuration, set once at startup
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Where the effective value of a setting was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingSource {
+    /// A command-line flag, such as --notes-dir
+    Flag,
+    /// An explicitly selected profile
+    Profile,
+    /// An environment variable
+    #[serde(rename = "env")]
+    Environment,
+    /// The configuration file (or its default profile)
+    #[serde(rename = "config")]
+    ConfigFile,
+    /// None of the above, so the built-in default is used
+    Default,
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SettingSource::Flag => "flag",
+            SettingSource::Profile => "profile",
+            SettingSource::Environment => "env",
+            SettingSource::ConfigFile => "config",
+            SettingSource::Default => "default",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The effective value of a setting, with where it was taken from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: SettingSource,
+}
+
+impl<T> Resolved<T> {
+    pub fn new(value: T, source: SettingSource) -> Self {
+        Self { value, source }
+    }
+}
+
+/// How month directories are named
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MonthDirStyle {
+    /// `01` … `12`
+    Numeric,
+    /// `01_January` … `12_December`
+    #[default]
+    English,
+    /// Like `English`, but with month names in the language of the current locale
+    Locale,
+}
+
+/// A named profile from the configuration file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[non_exhaustive]
+pub struct Profile {
+    /// Directory to store the notes of this profile
+    pub notes_dir: PathBuf,
+    /// Tags added to every note created from the command line
+    pub default_tags: Option<Vec<String>>,
+    /// Editor command to use instead of $VISUAL/$EDITOR
+    pub editor: Option<String>,
+}
+
+/// The contents of the configuration file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[non_exhaustive]
+pub struct Config {
+    /// Directory to store notes if no other directory was specified
+    pub notes_dir: Option<PathBuf>,
+    /// Tags added to every note created from the command line
+    #[serde(default)]
+    pub default_tags: Vec<String>,
+    /// Editor command to use instead of $VISUAL/$EDITOR
+    pub editor: Option<String>,
+    /// How to name newly created month directories
+    #[serde(default)]
+    pub month_dir_style: MonthDirStyle,
+    /// The first day of the week, e.g. for weekly reviews
+    #[serde(default)]
+    pub week_start: WeekStart,
+    /// Fetch the page title for notes that consist of a single link
+    #[serde(default)]
+    pub fetch_link_titles: bool,
+    /// Add `#hashtag` words in the body of new notes to their tags
+    #[serde(default)]
+    pub inline_tags: bool,
+    /// Remove the `#hashtag` words from the body when adding them as tags
+    #[serde(default)]
+    pub inline_tags_strip: bool,
+    /// Make new notes, directories and the database accessible only by their owner
+    #[serde(default)]
+    pub restrict_permissions: bool,
+    /// Maximum length of the filename of a new note in bytes
+    pub max_filename_bytes: Option<usize>,
+    /// Give notes created from the command line an `id` (the default)
+    pub generate_ids: Option<bool>,
+    /// The earliest year that a note added via MCP can be backdated to
+    pub min_created_year: Option<i32>,
+    /// Do not run the hooks in `.notelog/hooks`
+    #[serde(default)]
+    pub disable_hooks: bool,
+    /// Create the notes directory if it does not exist, instead of failing
+    #[serde(default)]
+    pub create_missing_dir: bool,
+    /// Use the home directory, a filesystem root or a directory with many
+    /// other files as the notes directory
+    #[serde(default)]
+    pub allow_large_dir: bool,
+    /// How many entries at the top level of the notes directory can be
+    /// neither notes nor year directories
+    pub max_foreign_entries: Option<usize>,
+    /// Allow lowercase Unicode letters and digits in tags, not only a-z and 0-9
+    #[serde(default)]
+    pub unicode_tags: bool,
+    /// The strftime format of dates in command output, e.g. `%d.%m.%Y`
+    pub date_format: Option<String>,
+    /// Extensions of the files that are notes, without the dot
+    pub note_extensions: Option<Vec<String>>,
+    /// Days to keep notes with each tag before `notelog prune` deletes them
+    #[serde(default)]
+    pub retention: BTreeMap<String, NonZeroU32>,
+    /// Profile to use if neither --profile nor NOTELOG_PROFILE is given
+    pub default_profile: Option<String>,
+    /// Named profiles
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// The name of the active profile, if any
+    #[serde(skip)]
+    active_profile: Option<String>,
+    /// Where the active profile was selected: the --profile flag, the
+    /// environment or the `default_profile` setting
+    #[serde(skip)]
+    profile_source: Option<SettingSource>,
+}
+
+impl Config {
+    /// Load the configuration file
+    ///
+    /// A missing configuration file is not an error; the default configuration
+    /// is returned in that case.
+    pub fn load() -> Result<Self> {
+        match config_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Load the configuration from the given file
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        content
+            .parse()
+            .map_err(|e| NotelogError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Select the active profile
+    ///
+    /// The profile is taken from `name` (the --profile flag), the
+    /// NOTELOG_PROFILE environment variable, or the `default_profile` setting,
+    /// in that order. The settings of the selected profile override the
+    /// top-level settings.
+    pub fn with_profile(mut self, name: Option<&str>) -> Result<Self> {
+        let selected = name
+            .map(|name| (name.to_string(), SettingSource::Flag))
+            .or_else(|| {
+                env::var("NOTELOG_PROFILE")
+                    .ok()
+                    .map(|name| (name, SettingSource::Environment))
+            })
+            .filter(|(name, _)| !name.is_empty())
+            .or_else(|| {
+                self.default_profile
+                    .clone()
+                    .map(|name| (name, SettingSource::ConfigFile))
+            });
+
+        let Some((name, source)) = selected else {
+            return Ok(self);
+        };
+
+        let Some(profile) = self.profiles.get(&name).cloned() else {
+            return Err(NotelogError::UnknownProfile(
+                name,
+                self.available_profiles(),
+            ));
+        };
+
+        self.notes_dir = Some(profile.notes_dir);
+        if let Some(default_tags) = profile.default_tags {
+            self.default_tags = default_tags;
+        }
+        if profile.editor.is_some() {
+            self.editor = profile.editor;
+        }
+        self.active_profile = Some(name);
+        self.profile_source = Some(source);
+
+        Ok(self)
+    }
+
+    /// Get the name of the active profile, if any
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
+
+    /// Get the name of the active profile and where it was selected
+    pub fn resolved_profile(&self) -> Option<Resolved<&str>> {
+        Some(Resolved::new(
+            self.active_profile.as_deref()?,
+            self.profile_source?,
+        ))
+    }
+
+    /// The active profile, if it was selected with the flag or the environment
+    fn explicit_profile(&self) -> Option<&Profile> {
+        match self.profile_source? {
+            SettingSource::Flag | SettingSource::Environment => {
+                self.profiles.get(self.active_profile.as_deref()?)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a profile was selected with the flag or the environment
+    fn profile_explicit(&self) -> bool {
+        matches!(
+            self.profile_source,
+            Some(SettingSource::Flag | SettingSource::Environment)
+        )
+    }
+
+    /// Get the notes directory of an explicitly selected profile
+    pub fn profile_notes_dir(&self) -> Option<PathBuf> {
+        if self.profile_explicit() {
+            self.notes_dir.as_deref().map(expand_tilde)
+        } else {
+            None
+        }
+    }
+
+    /// Get the notes directory configured in the file (or by the default profile)
+    pub fn file_notes_dir(&self) -> Option<PathBuf> {
+        if self.profile_explicit() {
+            None
+        } else {
+            self.notes_dir.as_deref().map(expand_tilde)
+        }
+    }
+
+    /// Get the editor command and where it was configured
+    ///
+    /// The editor is taken from the active profile or the configuration file,
+    /// the VISUAL or EDITOR environment variables, or is `nano`.
+    pub fn resolved_editor(&self) -> Resolved<String> {
+        if let Some(editor) = &self.editor {
+            return Resolved::new(editor.clone(), self.profile_setting_source(|p| &p.editor));
+        }
+
+        env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .map(|editor| Resolved::new(editor, SettingSource::Environment))
+            .unwrap_or_else(|_| Resolved::new("nano".to_string(), SettingSource::Default))
+    }
+
+    /// Get the default tags, unvalidated, and where they were configured
+    pub fn resolved_default_tags(&self) -> Resolved<Vec<String>> {
+        let source = if self.default_tags.is_empty() {
+            SettingSource::Default
+        } else {
+            self.profile_setting_source(|p| &p.default_tags)
+        };
+        Resolved::new(self.default_tags.clone(), source)
+    }
+
+    /// Where a setting that a profile can override was taken from, given that
+    /// it is set
+    ///
+    /// A default profile is part of the configuration file.
+    fn profile_setting_source<T>(&self, setting: impl Fn(&Profile) -> &Option<T>) -> SettingSource {
+        if self
+            .explicit_profile()
+            .is_some_and(|profile| setting(profile).is_some())
+        {
+            SettingSource::Profile
+        } else {
+            SettingSource::ConfigFile
+        }
+    }
+
+    /// Get the validated default tags
+    pub fn default_tags(&self) -> Result<Vec<Tag>> {
+        self.default_tags
+            .iter()
+            .map(|tag| Tag::with_unicode(tag, self.unicode_tags))
+            .collect()
+    }
+
+    /// Whether new notes, directories and the database should be accessible only by their owner
+    ///
+    /// Set with `restrict_permissions = true` or the NOTELOG_RESTRICT_PERMISSIONS
+    /// environment variable.
+    pub fn restrict_permissions(&self) -> bool {
+        self.restrict_permissions
+            || env::var("NOTELOG_RESTRICT_PERMISSIONS")
+                .is_ok_and(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+    }
+
+    /// Get the maximum length of the filename of a new note in bytes
+    pub fn max_filename_bytes(&self) -> usize {
+        self.max_filename_bytes
+            .unwrap_or(DEFAULT_MAX_FILENAME_BYTES)
+    }
+
+    /// Whether notes created from the command line get an `id`
+    pub fn generate_ids(&self) -> bool {
+        self.generate_ids.unwrap_or(true)
+    }
+
+    /// Get the earliest year that a note added via MCP can be backdated to
+    pub fn min_created_year(&self) -> i32 {
+        self.min_created_year.unwrap_or(DEFAULT_MIN_CREATED_YEAR)
+    }
+
+    /// Get how many entries at the top level of the notes directory can be
+    /// neither notes nor year directories before notelog refuses to use it
+    pub fn max_foreign_entries(&self) -> usize {
+        self.max_foreign_entries
+            .unwrap_or(DEFAULT_MAX_FOREIGN_ENTRIES)
+    }
+
+    /// Get the strftime format of dates in command output
+    ///
+    /// This only changes how dates are shown; dates in filenames, frontmatter
+    /// and arguments are always ISO 8601.
+    pub fn date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT)
+    }
+
+    /// Get the extensions of the files that are notes, without the dot
+    ///
+    /// Set with `note_extensions = ["md", "markdown"]` or a comma-separated
+    /// list in the NOTELOG_NOTE_EXTENSIONS environment variable, which takes
+    /// precedence. New notes are always saved as `.md`.
+    pub fn note_extensions(&self) -> Vec<String> {
+        self.resolved_note_extensions().value
+    }
+
+    /// Get the extensions of the files that are notes and where they were configured
+    pub fn resolved_note_extensions(&self) -> Resolved<Vec<String>> {
+        let normalize = |extensions: Vec<String>| -> Vec<String> {
+            extensions
+                .iter()
+                .map(|ext| ext.trim().trim_start_matches('.').to_string())
+                .filter(|ext| !ext.is_empty())
+                .collect()
+        };
+
+        let configured = env::var("NOTELOG_NOTE_EXTENSIONS")
+            .ok()
+            .map(|value| {
+                let extensions = value.split(',').map(str::to_string).collect();
+                (extensions, SettingSource::Environment)
+            })
+            .or_else(|| {
+                self.note_extensions
+                    .clone()
+                    .map(|extensions| (extensions, SettingSource::ConfigFile))
+            })
+            .map(|(extensions, source)| Resolved::new(normalize(extensions), source))
+            .filter(|extensions| !extensions.value.is_empty());
+
+        configured.unwrap_or_else(|| {
+            let extensions = DEFAULT_NOTE_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect();
+            Resolved::new(extensions, SettingSource::Default)
+        })
+    }
+
+    /// Get the retention policy for notes with expiring tags
+    pub fn retention(&self) -> Result<RetentionPolicy> {
+        let rules = self
+            .retention
+            .iter()
+            .map(|(tag, days)| Ok((Tag::with_unicode(tag, self.unicode_tags)?, *days)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RetentionPolicy::new(rules))
+    }
+
+    /// Get a comma-separated list of the configured profile names
+    pub fn available_profiles(&self) -> String {
+        if self.profiles.is_empty() {
+            "(none configured)".to_string()
+        } else {
+            self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = NotelogError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let config: Config =
+            toml::from_str(s).map_err(|e| NotelogError::ConfigError(e.to_string()))?;
+
+        // Validate the default tags early so that typos are reported at startup
+        config.default_tags()?;
+        config.retention()?;
+        for profile in config.profiles.values() {
+            for tag in profile.default_tags.iter().flatten() {
+                Tag::with_unicode(tag, config.unicode_tags)?;
+            }
+        }
+
+        check_date_format(config.date_format())?;
+
+        if let Some(max) = config.max_filename_bytes
+            && max < MIN_FILENAME_BYTES
+        {
+            return Err(NotelogError::ConfigError(format!(
+                "max_filename_bytes must be at least {}, got {}",
+                MIN_FILENAME_BYTES, max
+            )));
+        }
+
+        Ok(config)
+    }
+}
+
+/// Get the path of the configuration file
+pub fn config_path() -> Option<PathBuf> {
+    resolved_config_path().map(|path| path.value)
+}
+
+/// Get the path of the configuration file and where it was set
+pub fn resolved_config_path() -> Option<Resolved<PathBuf>> {
+    env::var("NOTELOG_CONFIG")
+        .map(|path| Resolved::new(PathBuf::from(path), SettingSource::Environment))
+        .ok()
+        .or_else(|| {
+            config_dir().map(|p| {
+                Resolved::new(
+                    p.join("notelog").join("config.toml"),
+                    SettingSource::Default,
+                )
+            })
+        })
+}
+
+/// Set the effective configuration
+///
+/// This should be called once at startup, before any command runs.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// Get the effective configuration
+///
+/// Returns the default configuration if `init` was never called (e.g. in tests).
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Expand a leading `~` in a path to the home directory
+pub fn expand_tilde(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| path.to_path_buf()),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default_tags = ["log"]
+editor = "vim"
+default_profile = "personal"
+
+[profiles.work]
+notes_dir = "~/Work/Log"
+default_tags = ["work"]
+editor = "hx"
+
+[profiles.personal]
+notes_dir = "/home/me/Notes"
+"#;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = CONFIG.parse().unwrap();
+        assert_eq!(config.default_tags, vec!["log"]);
+        assert_eq!(config.editor.as_deref(), Some("vim"));
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(config.available_profiles(), "personal, work");
+    }
+
+    #[test]
+    fn test_explicit_profile_overrides_settings() {
+        let config = CONFIG
+            .parse::<Config>()
+            .unwrap()
+            .with_profile(Some("work"))
+            .unwrap();
+
+        assert_eq!(config.active_profile(), Some("work"));
+        assert_eq!(config.editor.as_deref(), Some("hx"));
+        assert_eq!(config.default_tags().unwrap()[0].as_str(), "work");
+        assert!(config.file_notes_dir().is_none());
+        assert!(
+            config
+                .profile_notes_dir()
+                .unwrap()
+                .ends_with(Path::new("Work").join("Log"))
+        );
+    }
+
+    #[test]
+    fn test_default_profile() {
+        let config = CONFIG
+            .parse::<Config>()
+            .unwrap()
+            .with_profile(None)
+            .unwrap();
+
+        // The personal profile has no overrides for tags and editor
+        assert_eq!(config.active_profile(), Some("personal"));
+        assert_eq!(config.editor.as_deref(), Some("vim"));
+        assert_eq!(config.default_tags, vec!["log"]);
+
+        // A default profile has lower precedence than NOTELOG_DIR
+        assert!(config.profile_notes_dir().is_none());
+        assert_eq!(
+            config.file_notes_dir(),
+            Some(PathBuf::from("/home/me/Notes"))
+        );
+    }
+
+    #[test]
+    fn test_setting_sources() {
+        let config = CONFIG
+            .parse::<Config>()
+            .unwrap()
+            .with_profile(Some("work"))
+            .unwrap();
+        let profile = config.resolved_profile().unwrap();
+        assert_eq!(profile, Resolved::new("work", SettingSource::Flag));
+        assert_eq!(
+            config.resolved_editor(),
+            Resolved::new("hx".to_string(), SettingSource::Profile)
+        );
+        assert_eq!(
+            config.resolved_default_tags().source,
+            SettingSource::Profile
+        );
+
+        // The settings of a default profile are part of the configuration file
+        let config = CONFIG
+            .parse::<Config>()
+            .unwrap()
+            .with_profile(None)
+            .unwrap();
+        let profile = config.resolved_profile().unwrap();
+        assert_eq!(
+            profile,
+            Resolved::new("personal", SettingSource::ConfigFile)
+        );
+        assert_eq!(
+            config.resolved_editor(),
+            Resolved::new("vim".to_string(), SettingSource::ConfigFile)
+        );
+
+        let config: Config = "".parse().unwrap();
+        assert!(config.resolved_profile().is_none());
+        assert_eq!(
+            config.resolved_default_tags(),
+            Resolved::new(Vec::new(), SettingSource::Default)
+        );
+    }
+
+    #[test]
+    fn test_unknown_profile_lists_available_profiles() {
+        let err = CONFIG
+            .parse::<Config>()
+            .unwrap()
+            .with_profile(Some("hobby"))
+            .unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("hobby"));
+        assert!(message.contains("personal, work"));
+    }
+
+    #[test]
+    fn test_week_start() {
+        let config: Config = "week_start = \"sunday\"".parse().unwrap();
+        assert_eq!(config.week_start, WeekStart::Sunday);
+
+        // Weeks start on Monday by default
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config.week_start, WeekStart::Monday);
+
+        assert!("week_start = \"friday\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_month_dir_style() {
+        let config: Config = "month_dir_style = \"numeric\"".parse().unwrap();
+        assert_eq!(config.month_dir_style, MonthDirStyle::Numeric);
+
+        // English names are the default
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config.month_dir_style, MonthDirStyle::English);
+
+        assert!("month_dir_style = \"roman\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_generate_ids() {
+        assert!(Config::default().generate_ids());
+
+        let config: Config = "generate_ids = false".parse().unwrap();
+        assert!(!config.generate_ids());
+    }
+
+    #[test]
+    fn test_min_created_year() {
+        let config: Config = "min_created_year = 2010".parse().unwrap();
+        assert_eq!(config.min_created_year(), 2010);
+
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config.min_created_year(), DEFAULT_MIN_CREATED_YEAR);
+    }
+
+    #[test]
+    fn test_max_filename_bytes() {
+        let config: Config = "max_filename_bytes = 120".parse().unwrap();
+        assert_eq!(config.max_filename_bytes(), 120);
+
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config.max_filename_bytes(), DEFAULT_MAX_FILENAME_BYTES);
+
+        let result = "max_filename_bytes = 10".parse::<Config>();
+        assert!(matches!(result, Err(NotelogError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_retention() {
+        let config: Config = "[retention]\nscratch = 30\ntmp = 7".parse().unwrap();
+        let tags: Vec<String> = config
+            .retention()
+            .unwrap()
+            .tags()
+            .iter()
+            .map(|tag| tag.as_str().to_string())
+            .collect();
+        assert_eq!(tags, vec!["scratch", "tmp"]);
+
+        let config: Config = "".parse().unwrap();
+        assert!(config.retention().unwrap().is_empty());
+
+        // Notes must be kept for at least a day
+        let result = "[retention]\nscratch = 0".parse::<Config>();
+        assert!(matches!(result, Err(NotelogError::ConfigError(_))));
+
+        let result = "[retention]\n\"not valid\" = 30".parse::<Config>();
+        assert!(matches!(result, Err(NotelogError::TagError(_))));
+    }
+
+    #[test]
+    fn test_unicode_tags() {
+        let toml = "default_tags = [\"kundengespräch\"]";
+        assert!(toml.parse::<Config>().is_err());
+
+        let config: Config = format!("unicode_tags = true\n{}", toml).parse().unwrap();
+        assert_eq!(config.default_tags().unwrap()[0].as_str(), "kundengespräch");
+    }
+
+    #[test]
+    fn test_date_format() {
+        let config = Config::default();
+        assert_eq!(config.date_format(), "%Y-%m-%d");
+
+        let config: Config = "date_format = \"%d.%m.%Y\"".parse().unwrap();
+        assert_eq!(config.date_format(), "%d.%m.%Y");
+
+        let err = "date_format = \"%d.%m.%Q\""
+            .parse::<Config>()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("%d.%m.%Q"), "{}", err);
+    }
+
+    #[test]
+    fn test_note_extensions() {
+        let config: Config = "note_extensions = [\"md\", \".markdown\", \"txt\"]"
+            .parse()
+            .unwrap();
+        assert_eq!(config.note_extensions(), vec!["md", "markdown", "txt"]);
+
+        let config: Config = "".parse().unwrap();
+        assert_eq!(config.note_extensions(), vec!["md"]);
+
+        // An empty list would make every note disappear from the index
+        let config: Config = "note_extensions = []".parse().unwrap();
+        assert_eq!(config.note_extensions(), vec!["md"]);
+    }
+
+    #[test]
+    fn test_invalid_default_tag() {
+        let result = "default_tags = [\"not valid\"]".parse::<Config>();
+        assert!(matches!(result, Err(NotelogError::TagError(_))));
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let home = home_dir().unwrap();
+        assert_eq!(expand_tilde(Path::new("~/Notes")), home.join("Notes"));
+        assert_eq!(
+            expand_tilde(Path::new("/tmp/Notes")),
+            PathBuf::from("/tmp/Notes")
+        );
+    }
+}
@@ -9,5 +9,74 @@ pub const MAX_SEARCH_RESULTS: usize = 25;
 /// Default number of search results to return (10)
 pub const DEFAULT_SEARCH_RESULTS: usize = 10;
 
+/// Default number of notes returned by the get_recent_notes MCP tool (5)
+pub const DEFAULT_RECENT_NOTES: usize = 5;
+
+/// Maximum number of notes fetched at once by the fetch_notes MCP tool (10)
+pub const MAX_FETCH_NOTES: usize = 10;
+
+/// Maximum number of notes returned by the get_recent_notes MCP tool (25)
+pub const MAX_RECENT_NOTES: usize = 25;
+
+/// Maximum number of matching notes for which a search includes the content (3)
+pub const MAX_CONTENT_SEARCH_RESULTS: usize = 3;
+
+/// Maximum combined size of the note contents in a search result in KiB (64 KiB)
+pub const MAX_SEARCH_CONTENT_KIB: usize = 64;
+
+/// Maximum length of a search query in characters (1000)
+pub const MAX_SEARCH_QUERY_CHARS: usize = 1000;
+
+/// Maximum nesting depth of parentheses in a search query (8)
+pub const MAX_SEARCH_QUERY_DEPTH: usize = 8;
+
+/// Maximum number of terms (words, phrases and tags) in a search query (64)
+pub const MAX_SEARCH_QUERY_TERMS: usize = 64;
+
+/// Maximum number of queries kept in the search history (200)
+pub const MAX_SEARCH_HISTORY: usize = 200;
+
+/// Maximum length of a note title in characters (100)
+pub const MAX_TITLE_CHARS: usize = 100;
+
+/// Number of non-empty lines at the start of a note that are searched for a
+/// title before a generated one is used (10)
+pub const MAX_TITLE_SEARCH_LINES: usize = 10;
+
+/// Number of non-space characters above which the line a title is taken from
+/// may be pasted output rather than prose (1000)
+pub const MAX_TITLE_LINE_CHARS: usize = 1000;
+
+/// Maximum average length of the words on a line longer than
+/// `MAX_TITLE_LINE_CHARS`; lines of longer words are pasted output, and the
+/// title is generated instead (8)
+pub const MAX_TITLE_LINE_WORD_CHARS: usize = 8;
+
+/// Default maximum length of a note filename in bytes (200)
+pub const DEFAULT_MAX_FILENAME_BYTES: usize = 200;
+
+/// Smallest allowed `max_filename_bytes` setting, which leaves room for the
+/// timestamp and a counter (32)
+pub const MIN_FILENAME_BYTES: usize = 32;
+
+/// How far in the future the creation time of a new note can be, in minutes (5)
+pub const MAX_CREATED_IN_FUTURE_MINUTES: i64 = 5;
+
+/// Default earliest year for the creation time of a new note (1970)
+pub const DEFAULT_MIN_CREATED_YEAR: i32 = 1970;
+
+/// Default strftime format of dates shown in command output (ISO 8601)
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Default maximum number of entries at the top level of the notes directory
+/// that are neither notes nor year directories (50)
+pub const DEFAULT_MAX_FOREIGN_ENTRIES: usize = 50;
+
+/// Default extensions of note files (md)
+pub const DEFAULT_NOTE_EXTENSIONS: &[&str] = &["md"];
+
+/// Maximum number of tags a note can have (10)
+pub const MAX_TAGS_PER_NOTE: usize = 10;
+
 /// Maximum file size in bytes (MAX_FILE_SIZE_KIB * 1024)
 pub const MAX_FILE_SIZE_BYTES: usize = MAX_FILE_SIZE_KIB * 1024;
@@ -0,0 +1,254 @@
+//! Calendar helpers, e.g. for the weeks of weekly reviews
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime, TimeZone, Weekday};
+use serde::Deserialize;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::config;
+use crate::constants::DEFAULT_DATE_FORMAT;
+use crate::error::NotelogError;
+
+/// The first day of the week
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    /// Weeks run from Monday to Sunday, as in ISO 8601
+    #[default]
+    Monday,
+    /// Weeks run from Sunday to Saturday
+    Sunday,
+}
+
+impl WeekStart {
+    /// The first day of the week as a weekday
+    pub fn weekday(self) -> Weekday {
+        match self {
+            WeekStart::Monday => Weekday::Mon,
+            WeekStart::Sunday => Weekday::Sun,
+        }
+    }
+}
+
+impl FromStr for WeekStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "monday" => Ok(WeekStart::Monday),
+            "sunday" => Ok(WeekStart::Sunday),
+            _ => Err(format!("expected 'monday' or 'sunday', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for WeekStart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeekStart::Monday => write!(f, "monday"),
+            WeekStart::Sunday => write!(f, "sunday"),
+        }
+    }
+}
+
+/// The first day of the week that contains `date`
+pub fn week_start_date(date: NaiveDate, week_start: WeekStart) -> NaiveDate {
+    date.week(week_start.weekday()).first_day()
+}
+
+/// The start of the day in local time
+///
+/// In time zones where a DST change skips midnight, the day starts at the
+/// first moment that exists.
+pub fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            // Skipped times are at most a few hours long
+            (1..=4).find_map(|hours| {
+                let later = midnight + chrono::Duration::hours(hours);
+                Local.from_local_datetime(&later).earliest()
+            })
+        })
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
+
+/// The week that contains `date`, from the start of its first day (inclusive)
+/// to the start of the next week (exclusive), in local time
+///
+/// The weeks of consecutive dates are contiguous and never overlap, also
+/// across year boundaries and DST changes.
+pub fn week_bounds(date: NaiveDate, week_start: WeekStart) -> (DateTime<Local>, DateTime<Local>) {
+    let first_day = week_start_date(date, week_start);
+    let next_week = first_day
+        .checked_add_days(Days::new(7))
+        .unwrap_or(NaiveDate::MAX);
+
+    (start_of_day(first_day), start_of_day(next_week))
+}
+
+/// The name of the week starting on `week_start`, e.g. `2025-W23`
+///
+/// This is the ISO week of the middle of the week, which also names weeks
+/// that start on Sunday after the ISO week that they mostly overlap.
+pub fn week_name(week_start: NaiveDate) -> String {
+    let middle = week_start
+        .checked_add_days(Days::new(3))
+        .unwrap_or(week_start);
+    middle.format("%G-W%V").to_string()
+}
+
+/// Format a date for command output with the `date_format` setting
+pub fn format_display_date(date: NaiveDate) -> String {
+    format_date_with(date, config::get().date_format())
+}
+
+/// Format a date with a strftime format
+///
+/// Formats that `check_date_format` rejects fall back to ISO 8601.
+pub fn format_date_with(date: NaiveDate, format: &str) -> String {
+    let mut out = String::new();
+    match write!(out, "{}", date.format(format)) {
+        Ok(()) => out,
+        Err(_) => date.format(DEFAULT_DATE_FORMAT).to_string(),
+    }
+}
+
+/// Check that a strftime format can format a date
+///
+/// Unknown specifiers and specifiers for times (like `%H`) are errors.
+pub fn check_date_format(format: &str) -> crate::error::Result<()> {
+    // After an error the items are only errors, so stop at the first one
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(NotelogError::ConfigError(format!(
+            "date_format {:?} contains an unknown strftime specifier",
+            format
+        )));
+    }
+
+    let mut out = String::new();
+    if write!(out, "{}", NaiveDate::MIN.format(format)).is_err() {
+        return Err(NotelogError::ConfigError(format!(
+            "date_format {:?} can only contain date specifiers like %d, %m and %Y",
+            format
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Datelike;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_week_start_date() {
+        // 2025-01-01 is a Wednesday
+        assert_eq!(
+            week_start_date(date(2025, 1, 1), WeekStart::Monday),
+            date(2024, 12, 30)
+        );
+        assert_eq!(
+            week_start_date(date(2025, 1, 1), WeekStart::Sunday),
+            date(2024, 12, 29)
+        );
+
+        // A week's first day starts the week
+        assert_eq!(
+            week_start_date(date(2024, 12, 29), WeekStart::Sunday),
+            date(2024, 12, 29)
+        );
+        assert_eq!(
+            week_start_date(date(2024, 12, 29), WeekStart::Monday),
+            date(2024, 12, 23)
+        );
+        assert_eq!(
+            week_start_date(date(2025, 1, 5), WeekStart::Monday),
+            date(2024, 12, 30)
+        );
+        assert_eq!(
+            week_start_date(date(2025, 1, 5), WeekStart::Sunday),
+            date(2025, 1, 5)
+        );
+    }
+
+    #[test]
+    fn test_week_name() {
+        assert_eq!(week_name(date(2025, 6, 2)), "2025-W23");
+        assert_eq!(week_name(date(2025, 6, 1)), "2025-W23");
+        assert_eq!(week_name(date(2024, 12, 30)), "2025-W01");
+    }
+
+    #[test]
+    fn test_format_date_with() {
+        assert_eq!(format_date_with(date(2025, 6, 2), "%Y-%m-%d"), "2025-06-02");
+        assert_eq!(format_date_with(date(2025, 6, 2), "%d.%m.%Y"), "02.06.2025");
+        assert_eq!(
+            format_date_with(date(2025, 6, 2), "%e %b %Y"),
+            " 2 Jun 2025"
+        );
+
+        // Formats that cannot format a date fall back to ISO 8601
+        assert_eq!(format_date_with(date(2025, 6, 2), "%d %H:%M"), "2025-06-02");
+    }
+
+    #[test]
+    fn test_check_date_format() {
+        assert!(check_date_format("%d.%m.%Y").is_ok());
+        assert!(check_date_format("%A, %B %-d").is_ok());
+
+        let err = check_date_format("%d.%m.%Q").unwrap_err().to_string();
+        assert!(err.contains("unknown strftime specifier"), "{}", err);
+        let err = check_date_format("%d %H:%M").unwrap_err().to_string();
+        assert!(err.contains("only contain date specifiers"), "{}", err);
+    }
+
+    #[test]
+    fn test_week_bounds_are_contiguous() {
+        // Across a year boundary and the DST changes of most time zones
+        let ranges = [
+            (date(2024, 12, 20), 21),
+            (date(2025, 3, 20), 21),
+            (date(2025, 10, 20), 21),
+        ];
+
+        for week_start in [WeekStart::Monday, WeekStart::Sunday] {
+            for (first, days) in ranges {
+                let mut previous: Option<(DateTime<Local>, DateTime<Local>)> = None;
+                for offset in 0..days {
+                    let day = first + Days::new(offset);
+                    let (start, end) = week_bounds(day, week_start);
+
+                    assert_eq!(start.date_naive().weekday(), week_start.weekday());
+                    assert!(start <= start_of_day(day) && start_of_day(day) < end);
+                    assert_eq!(
+                        end.date_naive() - start.date_naive(),
+                        chrono::Duration::days(7)
+                    );
+
+                    // The same week, or the one right after the previous week
+                    if let Some((previous_start, previous_end)) = previous {
+                        assert!(start == previous_start || start == previous_end);
+                    }
+                    previous = Some((start, end));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_week_start_from_str() {
+        assert_eq!("monday".parse(), Ok(WeekStart::Monday));
+        assert_eq!("sunday".parse(), Ok(WeekStart::Sunday));
+        assert!("friday".parse::<WeekStart>().is_err());
+        assert_eq!(WeekStart::Sunday.to_string(), "sunday");
+    }
+}
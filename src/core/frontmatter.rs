@@ -1,13 +1,14 @@
 //! Frontmatter implementation for notelog
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 
+use crate::constants::MAX_TAGS_PER_NOTE;
 use crate::core::id::Id;
 use crate::core::tags::Tag;
-use crate::error::{FrontmatterError, NotelogError, Result};
+use crate::error::{FrontmatterError, NotelogError, Result, TagError, TextLocation};
 
 /// Represents the frontmatter of a note
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,8 +18,26 @@ pub struct Frontmatter {
     id: Option<Id>,
     /// The creation timestamp
     created: DateTime<Local>,
+    /// The creation timestamp as written in the file, e.g. in UTC or with
+    /// fractional seconds, which is kept as is when the note is rewritten
+    #[serde(skip)]
+    created_raw: Option<String>,
     /// The tags associated with the note
     tags: Vec<Tag>,
+    /// Where the note came from, e.g. `cli` or `mcp:claude-desktop` (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// Whether the note is private, i.e. excluded from the search index
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    private: bool,
+    /// When the note needs to be followed up on (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<NaiveDate>,
+    /// The due date as written in the file if it is not a valid date, e.g.
+    /// `next week` typed by hand. The note counts as not due, but the value is
+    /// kept as is when the note is rewritten.
+    #[serde(skip)]
+    invalid_due: Option<String>,
 }
 
 impl Frontmatter {
@@ -27,16 +46,16 @@ impl Frontmatter {
     pub fn new(created: DateTime<Local>, tags: Vec<Tag>) -> Self {
         Self {
             created,
+            created_raw: None,
             tags,
             id: Some(Id::default()),
+            source: None,
+            private: false,
+            due: None,
+            invalid_due: None,
         }
     }
 
-    /// Create a new frontmatter with the current timestamp and no tags
-    pub fn default() -> Self {
-        Self::with_tags(vec![])
-    }
-
     /// Create a new frontmatter with the current timestamp and given tags
     pub fn with_tags(tags: Vec<Tag>) -> Self {
         Self::new(Local::now(), tags)
@@ -47,6 +66,12 @@ impl Frontmatter {
         &self.created
     }
 
+    /// Set the creation timestamp
+    pub fn set_created(&mut self, created: DateTime<Local>) {
+        self.created = created;
+        self.created_raw = None;
+    }
+
     /// Get the tags
     pub fn tags(&self) -> &[Tag] {
         &self.tags
@@ -62,13 +87,60 @@ impl Frontmatter {
         self.id = Some(id);
     }
 
+    /// Remove the id, for notes that are only identified by their path
+    pub fn clear_id(&mut self) {
+        self.id = None;
+    }
+
+    /// Get the source if present
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Set the source
+    pub fn set_source<S: Into<String>>(&mut self, source: S) {
+        self.source = Some(source.into());
+    }
+
+    /// Check if the note is private
+    ///
+    /// Private notes are not indexed, so they are never returned by the MCP
+    /// server, but can still be read by commands that access the files directly.
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// Get the due date if present
+    pub fn due(&self) -> Option<NaiveDate> {
+        self.due
+    }
+
+    /// Get the due date as written in the file, if it is not a valid date
+    pub fn invalid_due(&self) -> Option<&str> {
+        self.invalid_due.as_deref()
+    }
+
+    /// Set or clear the due date
+    pub fn set_due(&mut self, due: Option<NaiveDate>) {
+        self.due = due;
+        self.invalid_due = None;
+    }
+
     /// Add a tag to the frontmatter
-    pub fn add_tag(&mut self, tag: Tag) {
+    ///
+    /// Adding a tag that is already present is a no-op. Returns an error if the
+    /// frontmatter would end up with more than MAX_TAGS_PER_NOTE tags.
+    pub fn add_tag(&mut self, tag: Tag) -> Result<()> {
         if self.tags.contains(&tag) {
-            return;
+            return Ok(());
+        }
+
+        if self.tags.len() >= MAX_TAGS_PER_NOTE {
+            return Err(TagError::TooMany(self.tags.len() + 1).into());
         }
 
         self.tags.push(tag);
+        Ok(())
     }
 
     /// Remove a tag from the frontmatter
@@ -79,25 +151,51 @@ impl Frontmatter {
     }
 
     /// Update tags by adding and removing the specified tags
-    pub fn update_tags<I, J>(&mut self, tags_to_add: I, tags_to_remove: J)
+    ///
+    /// If the update would leave the frontmatter with more than MAX_TAGS_PER_NOTE
+    /// tags, an error is returned and the tags are left unchanged.
+    pub fn update_tags<I, J>(&mut self, tags_to_add: I, tags_to_remove: J) -> Result<()>
     where
         I: IntoIterator<Item = Tag>,
         J: IntoIterator<Item = Tag>,
     {
+        let original_tags = self.tags.clone();
+
         for tag in tags_to_remove {
             self.remove_tag(&tag);
         }
 
         for tag in tags_to_add {
-            self.add_tag(tag);
+            if let Err(e) = self.add_tag(tag) {
+                self.tags = original_tags;
+                return Err(e);
+            }
         }
+
+        Ok(())
     }
 
     /// Extract frontmatter from content if present
     pub fn extract_from_content(content: &str) -> Result<(Option<Self>, String)> {
         match Self::extract_yaml_and_content(content) {
-            Ok((Some(yaml), content_without_frontmatter)) => match Self::from_str(&yaml) {
+            Ok((Some((yaml, start)), content_without_frontmatter)) => match Self::from_str(&yaml) {
                 Ok(frontmatter) => Ok((Some(frontmatter), content_without_frontmatter)),
+                Err(NotelogError::FrontmatterError(FrontmatterError::InvalidYaml {
+                    message,
+                    location,
+                })) => Err(FrontmatterError::InvalidYaml {
+                    message,
+                    // Report the location relative to the note instead of the YAML block
+                    location: location.map(|location| TextLocation {
+                        line: start.line + location.line - 1,
+                        column: if location.line == 1 {
+                            start.column + location.column - 1
+                        } else {
+                            location.column
+                        },
+                    }),
+                }
+                .into()),
                 Err(e) => Err(e),
             },
             Ok((None, content_without_frontmatter)) => {
@@ -108,6 +206,14 @@ impl Frontmatter {
         }
     }
 
+    /// Whether the content starts with a `---` block that would be read as frontmatter
+    pub fn starts_with_block(content: &str) -> bool {
+        content
+            .trim_start()
+            .strip_prefix("---")
+            .is_some_and(|rest| rest.contains("\n---"))
+    }
+
     /// Format the frontmatter as a YAML string
     pub fn to_yaml(&self) -> String {
         let id_yaml = if let Some(id) = &self.id {
@@ -116,7 +222,38 @@ impl Frontmatter {
             String::new()
         };
 
-        let created_yaml = self.created.format("created: %Y-%m-%dT%H:%M:%S%:z\n");
+        // Rewriting a note should not change how its timestamp is written
+        let created_yaml = match &self.created_raw {
+            Some(raw) if DateTime::parse_from_rfc3339(raw).is_ok_and(|raw| raw == self.created) => {
+                format!("created: {}\n", raw)
+            }
+            _ => self
+                .created
+                .format("created: %Y-%m-%dT%H:%M:%S%:z\n")
+                .to_string(),
+        };
+
+        // Serialize the source with serde_yaml so that it is quoted if necessary
+        let source_yaml = match &self.source {
+            Some(source) => format!(
+                "source: {}\n",
+                serde_yaml::to_string(source).unwrap_or_default().trim_end()
+            ),
+            None => String::new(),
+        };
+
+        let private_yaml = if self.private { "private: true\n" } else { "" };
+
+        let due_yaml = match (self.due, &self.invalid_due) {
+            (Some(due), _) => due.format("due: %Y-%m-%d\n").to_string(),
+            (None, Some(invalid_due)) => format!(
+                "due: {}\n",
+                serde_yaml::to_string(invalid_due)
+                    .unwrap_or_default()
+                    .trim_end()
+            ),
+            (None, None) => String::new(),
+        };
 
         let tags_yaml = if !self.tags.is_empty() {
             let mut yaml = String::from("tags:");
@@ -128,11 +265,16 @@ impl Frontmatter {
             String::new()
         };
 
-        format!("---\n{}{}{}\n---", id_yaml, created_yaml, tags_yaml)
+        format!(
+            "---\n{}{}{}{}{}{}\n---",
+            id_yaml, created_yaml, source_yaml, private_yaml, due_yaml, tags_yaml
+        )
     }
 
     /// Helper function to extract YAML frontmatter and content from a document
-    fn extract_yaml_and_content(content: &str) -> Result<(Option<String>, String)> {
+    ///
+    /// The YAML is returned together with the location where it starts in the content.
+    fn extract_yaml_and_content(content: &str) -> Result<(Option<(String, TextLocation)>, String)> {
         // Check if the content starts with frontmatter
         let trimmed = content.trim_start();
         if !trimmed.starts_with("---") {
@@ -152,8 +294,13 @@ impl Frontmatter {
 
                 // Extract the frontmatter and content
                 let yaml = frontmatter_content.trim().to_string();
+                let start = content.len() - rest.len() + frontmatter_content.len()
+                    - frontmatter_content.trim_start().len();
                 let after_frontmatter = &rest[end_index + 4..]; // +4 to skip "\n---"
-                return Ok((Some(yaml), after_frontmatter.trim_start().to_string()));
+                return Ok((
+                    Some((yaml, text_location(content, start))),
+                    after_frontmatter.trim_start().to_string(),
+                ));
             } else {
                 // No closing delimiter, not valid frontmatter
                 return Ok((None, content.to_string()));
@@ -165,6 +312,63 @@ impl Frontmatter {
     }
 }
 
+/// Get the line and column of a byte offset in a string
+fn text_location(s: &str, offset: usize) -> TextLocation {
+    let before = &s[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    TextLocation {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+/// Convert a serde_yaml error, keeping its location separate from the message
+fn invalid_yaml(e: serde_yaml::Error) -> FrontmatterError {
+    let location = e.location().map(|location| TextLocation {
+        line: location.line(),
+        column: location.column(),
+    });
+
+    FrontmatterError::InvalidYaml {
+        message: strip_yaml_positions(&e.to_string()),
+        location,
+    }
+}
+
+/// Remove the " at line N column M" positions from a serde_yaml error message
+///
+/// They are relative to the YAML block and would be misleading for the note.
+fn strip_yaml_positions(message: &str) -> String {
+    const MARKER: &str = " at line ";
+
+    let mut result = String::new();
+    let mut rest = message;
+    while let Some(start) = rest.find(MARKER) {
+        let after = &rest[start + MARKER.len()..];
+        let line_len = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let Some(column) = after[line_len..].strip_prefix(" column ") else {
+            result.push_str(&rest[..start + MARKER.len()]);
+            rest = after;
+            continue;
+        };
+        let column_rest = column.trim_start_matches(|c: char| c.is_ascii_digit());
+
+        result.push_str(&rest[..start]);
+        rest = column_rest;
+    }
+    result.push_str(rest);
+
+    result
+}
+
+impl Default for Frontmatter {
+    /// Create a new frontmatter with the current timestamp and no tags
+    fn default() -> Self {
+        Self::with_tags(vec![])
+    }
+}
+
 impl fmt::Display for Frontmatter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.to_yaml())
@@ -179,6 +383,15 @@ struct FrontmatterData {
     created: String,
     #[serde(default)]
     tags: Vec<String>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    private: bool,
+    /// `notelog: ignore` is an alias of `private: true`
+    #[serde(default)]
+    notelog: Option<String>,
+    #[serde(default)]
+    due: Option<String>,
 }
 
 impl FromStr for Frontmatter {
@@ -187,7 +400,7 @@ impl FromStr for Frontmatter {
     fn from_str(yaml: &str) -> Result<Self> {
         let frontmatter_data: FrontmatterData = match serde_yaml::from_str(yaml) {
             Ok(data) => data,
-            Err(e) => return Err(FrontmatterError::InvalidYaml(e.to_string()).into()),
+            Err(e) => return Err(invalid_yaml(e).into()),
         };
 
         let id = if let Some(id_str) = frontmatter_data.id {
@@ -199,7 +412,8 @@ impl FromStr for Frontmatter {
             None
         };
 
-        let created = match chrono::DateTime::parse_from_rfc3339(&frontmatter_data.created) {
+        let created_raw = frontmatter_data.created.trim().to_string();
+        let created = match chrono::DateTime::parse_from_rfc3339(&created_raw) {
             Ok(dt) => dt.with_timezone(&Local),
             Err(e) => return Err(FrontmatterError::InvalidTimestamp(e.to_string()).into()),
         };
@@ -212,7 +426,37 @@ impl FromStr for Frontmatter {
             }
         }
 
-        Ok(Self { created, tags, id })
+        // Treat an empty source like a missing one
+        let source = frontmatter_data
+            .source
+            .map(|source| source.trim().to_string())
+            .filter(|source| !source.is_empty());
+
+        let private = frontmatter_data.private
+            || frontmatter_data
+                .notelog
+                .is_some_and(|notelog| notelog.trim() == "ignore");
+
+        // An invalid due date, e.g. typed by hand, must not make the whole
+        // note unreadable; the note is treated as not due instead
+        let (due, invalid_due) = match frontmatter_data.due {
+            Some(due) => match NaiveDate::parse_from_str(due.trim(), "%Y-%m-%d") {
+                Ok(due) => (Some(due), None),
+                Err(_) => (None, Some(due)),
+            },
+            None => (None, None),
+        };
+
+        Ok(Self {
+            created,
+            created_raw: Some(created_raw),
+            tags,
+            id,
+            source,
+            private,
+            due,
+            invalid_due,
+        })
     }
 }
 
@@ -230,7 +474,7 @@ mod tests {
         let tags = vec![tag1.clone(), tag2.clone()];
 
         // Test new constructor
-        let frontmatter = Frontmatter::new(date.clone(), tags.clone());
+        let frontmatter = Frontmatter::new(date, tags.clone());
 
         assert_eq!(frontmatter.created(), &date);
         assert_eq!(frontmatter.tags().len(), 2);
@@ -264,19 +508,29 @@ mod tests {
         assert_ne!(frontmatter1.id(), frontmatter2.id()); // Ids should be different
     }
 
+    #[test]
+    fn test_starts_with_block() {
+        assert!(Frontmatter::starts_with_block("---\nname: deploy\n---\n"));
+        assert!(Frontmatter::starts_with_block("\n---\n---\nBody"));
+        assert!(!Frontmatter::starts_with_block("---\nNo closing line"));
+        assert!(!Frontmatter::starts_with_block(
+            "Body\n---\nname: deploy\n---\n"
+        ));
+    }
+
     #[test]
     fn test_frontmatter_add_tag() {
         // Test adding a tag to an empty frontmatter
         let mut frontmatter = Frontmatter::default();
         let tag = Tag::new("test").unwrap();
-        frontmatter.add_tag(tag.clone());
+        frontmatter.add_tag(tag.clone()).unwrap();
 
         assert_eq!(frontmatter.tags().len(), 1);
         assert_eq!(frontmatter.tags()[0], tag);
 
         // Test adding a second tag
         let tag2 = Tag::new("another").unwrap();
-        frontmatter.add_tag(tag2.clone());
+        frontmatter.add_tag(tag2.clone()).unwrap();
 
         assert_eq!(frontmatter.tags().len(), 2);
         assert_eq!(frontmatter.tags()[0], tag);
@@ -296,11 +550,11 @@ mod tests {
         let tag_c = Tag::new("c").unwrap();
 
         // Add all tags
-        frontmatter.add_tag(tag_a1.clone());
-        frontmatter.add_tag(tag_b1.clone());
-        frontmatter.add_tag(tag_a2.clone()); // Should be ignored as duplicate
-        frontmatter.add_tag(tag_b2.clone()); // Should be ignored as duplicate
-        frontmatter.add_tag(tag_c.clone());
+        frontmatter.add_tag(tag_a1.clone()).unwrap();
+        frontmatter.add_tag(tag_b1.clone()).unwrap();
+        frontmatter.add_tag(tag_a2.clone()).unwrap(); // Should be ignored as duplicate
+        frontmatter.add_tag(tag_b2.clone()).unwrap(); // Should be ignored as duplicate
+        frontmatter.add_tag(tag_c.clone()).unwrap();
 
         // Verify we only have 3 unique tags: a, b, c
         assert_eq!(frontmatter.tags().len(), 3);
@@ -309,7 +563,7 @@ mod tests {
         assert_eq!(frontmatter.tags()[2].as_str(), "c");
 
         // Try adding a duplicate again
-        frontmatter.add_tag(tag_a1.clone());
+        frontmatter.add_tag(tag_a1.clone()).unwrap();
 
         // Verify count still remains at 3
         assert_eq!(frontmatter.tags().len(), 3);
@@ -325,9 +579,14 @@ mod tests {
         // Create a frontmatter with a specific ID for testing
         let id = Id::new("0123456789abcdef").unwrap();
         let frontmatter = Frontmatter {
-            created: date.clone(),
+            created: date,
+            created_raw: None,
             tags: tags.clone(),
             id: Some(id.clone()),
+            source: None,
+            private: false,
+            due: None,
+            invalid_due: None,
         };
 
         let yaml = frontmatter.to_yaml();
@@ -340,9 +599,14 @@ mod tests {
 
         // Test with no tags
         let frontmatter = Frontmatter {
-            created: date.clone(),
+            created: date,
+            created_raw: None,
             tags: vec![],
             id: Some(id.clone()),
+            source: None,
+            private: false,
+            due: None,
+            invalid_due: None,
         };
         let yaml = frontmatter.to_yaml();
 
@@ -481,7 +745,9 @@ mod tests {
 
         // Add and remove tags
         let tag3 = Tag::new("new").unwrap();
-        frontmatter.update_tags(vec![tag3.clone()], vec![tag1.clone()]);
+        frontmatter
+            .update_tags(vec![tag3.clone()], vec![tag1.clone()])
+            .unwrap();
 
         // Should have tag2 and tag3, but not tag1
         assert_eq!(frontmatter.tags().len(), 2);
@@ -489,4 +755,205 @@ mod tests {
         assert!(frontmatter.tags().iter().any(|t| t.as_str() == "example"));
         assert!(frontmatter.tags().iter().any(|t| t.as_str() == "new"));
     }
+
+    #[test]
+    fn test_frontmatter_tag_limit() {
+        let mut frontmatter = Frontmatter::default();
+        for i in 0..MAX_TAGS_PER_NOTE {
+            frontmatter
+                .add_tag(Tag::new(&format!("tag{}", i)).unwrap())
+                .unwrap();
+        }
+
+        // Adding a duplicate at the limit is fine
+        assert!(frontmatter.add_tag(Tag::new("tag0").unwrap()).is_ok());
+
+        // Adding one more tag exceeds the limit
+        assert!(matches!(
+            frontmatter.add_tag(Tag::new("extra").unwrap()).unwrap_err(),
+            NotelogError::TagError(TagError::TooMany(n)) if n == MAX_TAGS_PER_NOTE + 1
+        ));
+        assert_eq!(frontmatter.tags().len(), MAX_TAGS_PER_NOTE);
+
+        // Replacing a tag keeps the count within the limit
+        frontmatter
+            .update_tags(
+                vec![Tag::new("extra").unwrap()],
+                vec![Tag::new("tag0").unwrap()],
+            )
+            .unwrap();
+        assert_eq!(frontmatter.tags().len(), MAX_TAGS_PER_NOTE);
+
+        // An update that exceeds the limit leaves the tags unchanged
+        let before = frontmatter.tags().to_vec();
+        assert!(
+            frontmatter
+                .update_tags(vec![Tag::new("one-more").unwrap()], vec![])
+                .is_err()
+        );
+        assert_eq!(frontmatter.tags(), before.as_slice());
+    }
+
+    #[test]
+    fn test_created_keeps_its_format_after_tag_edit() {
+        for created in [
+            "2025-04-01T12:00:00Z",
+            "2025-04-01T17:30:00+05:30",
+            "2025-04-01T12:00:00.123456Z",
+        ] {
+            let yaml = format!(
+                "---\nid: 0123456789abcdef\ncreated: {}\ntags:\n  - draft\n---",
+                created
+            );
+            let mut frontmatter: Frontmatter = yaml
+                .trim_start_matches("---\n")
+                .trim_end_matches("\n---")
+                .parse()
+                .unwrap();
+
+            frontmatter
+                .update_tags(vec![Tag::new("todo").unwrap()], vec![])
+                .unwrap();
+            frontmatter
+                .update_tags(vec![], vec![Tag::new("todo").unwrap()])
+                .unwrap();
+            assert_eq!(frontmatter.to_yaml(), yaml);
+        }
+    }
+
+    #[test]
+    fn test_frontmatter_source() {
+        // Notes without a source stay without one
+        let content = "---\ncreated: 2025-04-01T12:00:00+00:00\n---\n\n# Content";
+        let (frontmatter, _) = Frontmatter::extract_from_content(content).unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert!(frontmatter.source().is_none());
+        assert!(!frontmatter.to_yaml().contains("source:"));
+
+        // The source round-trips through YAML
+        let mut frontmatter = Frontmatter::with_tags(vec![Tag::new("foo").unwrap()]);
+        frontmatter.set_source("mcp:claude-desktop");
+        let yaml = frontmatter.to_yaml();
+        assert!(yaml.contains("\nsource: mcp:claude-desktop\ntags:"));
+
+        let (parsed, _) = Frontmatter::extract_from_content(&yaml).unwrap();
+        assert_eq!(parsed.unwrap().source(), Some("mcp:claude-desktop"));
+
+        // Sources that are not plain YAML scalars are quoted
+        frontmatter.set_source("mcp: weird # client");
+        let (parsed, _) = Frontmatter::extract_from_content(&frontmatter.to_yaml()).unwrap();
+        assert_eq!(parsed.unwrap().source(), Some("mcp: weird # client"));
+    }
+
+    #[test]
+    fn test_frontmatter_private() {
+        // Notes are not private by default, and the field is left out
+        let content = "---\ncreated: 2025-04-01T12:00:00+00:00\n---\n\n# Content";
+        let (frontmatter, _) = Frontmatter::extract_from_content(content).unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert!(!frontmatter.is_private());
+        assert!(!frontmatter.to_yaml().contains("private:"));
+
+        // The flag round-trips through YAML
+        let content = "---\ncreated: 2025-04-01T12:00:00+00:00\nprivate: true\ntags:\n  - foo\n---\n\n# Content";
+        let (frontmatter, _) = Frontmatter::extract_from_content(content).unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert!(frontmatter.is_private());
+
+        let yaml = frontmatter.to_yaml();
+        assert!(yaml.contains("\nprivate: true\ntags:"));
+        let (parsed, _) = Frontmatter::extract_from_content(&yaml).unwrap();
+        assert!(parsed.unwrap().is_private());
+
+        // `notelog: ignore` is an alias, written back as `private: true`
+        let content = "---\ncreated: 2025-04-01T12:00:00+00:00\nnotelog: ignore\n---\n\n# Content";
+        let (frontmatter, _) = Frontmatter::extract_from_content(content).unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert!(frontmatter.is_private());
+        assert!(frontmatter.to_yaml().contains("\nprivate: true\n"));
+
+        // Other values of the field do not make a note private
+        let content = "---\ncreated: 2025-04-01T12:00:00+00:00\nnotelog: index\n---\n\n# Content";
+        let (frontmatter, _) = Frontmatter::extract_from_content(content).unwrap();
+        assert!(!frontmatter.unwrap().is_private());
+    }
+
+    #[test]
+    fn test_frontmatter_due() {
+        let content =
+            "---\ncreated: 2025-04-01T12:00:00+00:00\ndue: 2025-04-08\n---\n\n# Ping legal";
+        let (frontmatter, _) = Frontmatter::extract_from_content(content).unwrap();
+        let mut frontmatter = frontmatter.unwrap();
+        assert_eq!(frontmatter.due(), NaiveDate::from_ymd_opt(2025, 4, 8));
+
+        // The due date round-trips through YAML and JSON
+        let yaml = frontmatter.to_yaml();
+        assert!(yaml.contains("\ndue: 2025-04-08\n"));
+        let (parsed, _) = Frontmatter::extract_from_content(&yaml).unwrap();
+        assert_eq!(parsed.unwrap().due(), frontmatter.due());
+
+        let json = serde_json::to_string(&frontmatter).unwrap();
+        assert!(json.contains("\"due\":\"2025-04-08\""));
+
+        frontmatter.set_due(None);
+        assert!(!frontmatter.to_yaml().contains("due:"));
+
+        // An invalid due date is ignored, but kept when the note is rewritten
+        let content =
+            "---\ncreated: 2025-04-01T12:00:00+00:00\ndue: next week\n---\n\n# Ping legal";
+        let (frontmatter, _) = Frontmatter::extract_from_content(content).unwrap();
+        let mut frontmatter = frontmatter.unwrap();
+        assert_eq!(frontmatter.due(), None);
+        assert_eq!(frontmatter.invalid_due(), Some("next week"));
+        assert!(frontmatter.to_yaml().contains("\ndue: next week\n"));
+        assert!(!serde_json::to_string(&frontmatter).unwrap().contains("due"));
+
+        // Setting a due date replaces it
+        frontmatter.set_due(NaiveDate::from_ymd_opt(2025, 4, 8));
+        assert_eq!(frontmatter.invalid_due(), None);
+        assert!(frontmatter.to_yaml().contains("\ndue: 2025-04-08\n"));
+    }
+
+    #[test]
+    fn test_strip_yaml_positions() {
+        assert_eq!(
+            strip_yaml_positions(
+                "did not find expected ',' or ']' at line 3 column 7, while parsing a flow sequence at line 2 column 7"
+            ),
+            "did not find expected ',' or ']', while parsing a flow sequence"
+        );
+        assert_eq!(
+            strip_yaml_positions("nothing to see at line two"),
+            "nothing to see at line two"
+        );
+    }
+
+    #[test]
+    fn test_invalid_yaml_location() {
+        // The unclosed bracket is reported where the YAML parser gives up
+        let content =
+            "---\ncreated: 2025-04-01T12:00:00+00:00\ntags: [foo\nsource: cli\n---\n\n# Oops";
+        let Err(NotelogError::FrontmatterError(FrontmatterError::InvalidYaml {
+            message,
+            location,
+        })) = Frontmatter::extract_from_content(content)
+        else {
+            panic!("Expected a YAML error");
+        };
+
+        // The location is relative to the note, not to the YAML block
+        let location = location.unwrap();
+        assert_eq!(location.line, 4);
+        assert!(!message.contains(" at line "));
+
+        // Leading whitespace before the frontmatter shifts the location
+        let (_, location) = match Frontmatter::extract_from_content(&format!("\n\n{}", content)) {
+            Err(NotelogError::FrontmatterError(FrontmatterError::InvalidYaml {
+                message,
+                location,
+            })) => (message, location.unwrap()),
+            _ => panic!("Expected a YAML error"),
+        };
+        assert_eq!(location.line, 6);
+    }
 }
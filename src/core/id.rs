@@ -7,6 +7,14 @@ use std::str::FromStr;
 
 use crate::error::{IdError, NotelogError, Result};
 
+/// The length of a full Id
+pub(crate) const ID_LENGTH: usize = 16;
+
+/// Check if a character is valid in an Id (0-9 and lowercase a-z)
+fn is_valid_id_char(c: char) -> bool {
+    c.is_ascii_digit() || c.is_ascii_lowercase()
+}
+
 /// An opaque wrapper type that represents a valid Id
 ///
 /// An Id is a base36 string of length 16 (using characters 0-9 and a-z).
@@ -23,16 +31,13 @@ impl Id {
             return Err(NotelogError::IdError(IdError::Empty));
         }
 
-        if processed_input.len() != 16 {
+        if processed_input.len() != ID_LENGTH {
             return Err(NotelogError::IdError(IdError::InvalidLength(
                 processed_input.len(),
             )));
         }
 
-        if !processed_input
-            .chars()
-            .all(|c| c.is_ascii_digit() || (c.is_ascii_lowercase() && c.is_ascii_alphabetic()))
-        {
+        if !processed_input.chars().all(is_valid_id_char) {
             return Err(NotelogError::IdError(IdError::InvalidCharacters(
                 processed_input.to_string(),
             )));
@@ -44,7 +49,6 @@ impl Id {
     /// Generate a random base36 Id
     fn generate_random() -> Self {
         const CHARSET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
-        const ID_LENGTH: usize = 16;
 
         let mut rng = rng();
         let id: String = (0..ID_LENGTH)
@@ -63,6 +67,29 @@ impl Id {
     }
 }
 
+/// Normalize a user-provided Id prefix
+///
+/// Ids are stored in lowercase, so prefixes are trimmed and lowercased before
+/// they are used for lookups. The prefix must not be empty, may only contain
+/// the characters 0-9 and a-z, and cannot be longer than a full Id.
+pub fn normalize_id_prefix(input: &str) -> Result<String> {
+    let prefix = input.trim().to_lowercase();
+
+    if prefix.is_empty() {
+        return Err(NotelogError::IdError(IdError::Empty));
+    }
+
+    if !prefix.chars().all(is_valid_id_char) {
+        return Err(NotelogError::IdError(IdError::InvalidCharacters(prefix)));
+    }
+
+    if prefix.len() > ID_LENGTH {
+        return Err(NotelogError::IdError(IdError::PrefixTooLong(prefix.len())));
+    }
+
+    Ok(prefix)
+}
+
 impl Default for Id {
     fn default() -> Self {
         Self::generate_random()
@@ -203,4 +230,45 @@ mod tests {
         let err3 = "0123456789abcde!".parse::<Id>();
         assert!(err3.is_err());
     }
+
+    #[test]
+    fn test_normalize_id_prefix() {
+        // Uppercase prefixes are lowercased
+        assert_eq!(normalize_id_prefix("ABCD").unwrap(), "abcd");
+        assert_eq!(normalize_id_prefix("AbC1").unwrap(), "abc1");
+
+        // Surrounding whitespace is trimmed
+        assert_eq!(normalize_id_prefix("  abcd\n").unwrap(), "abcd");
+        assert_eq!(normalize_id_prefix("\tAB12 ").unwrap(), "ab12");
+
+        // A full Id is a valid prefix
+        assert_eq!(
+            normalize_id_prefix("0123456789ABCDEF").unwrap(),
+            "0123456789abcdef"
+        );
+
+        // Empty prefixes are rejected
+        assert!(matches!(
+            normalize_id_prefix("   ").unwrap_err(),
+            NotelogError::IdError(IdError::Empty)
+        ));
+
+        // Invalid characters produce an error that names the prefix
+        let err = normalize_id_prefix("_no_id").unwrap_err();
+        assert!(matches!(
+            err,
+            NotelogError::IdError(IdError::InvalidCharacters(_))
+        ));
+        assert!(err.to_string().contains("_no_id"));
+        assert!(matches!(
+            normalize_id_prefix("ab-cd").unwrap_err(),
+            NotelogError::IdError(IdError::InvalidCharacters(_))
+        ));
+
+        // Prefixes longer than a full Id are rejected
+        assert!(matches!(
+            normalize_id_prefix("0123456789abcdef0").unwrap_err(),
+            NotelogError::IdError(IdError::PrefixTooLong(17))
+        ));
+    }
 }
@@ -0,0 +1,195 @@
+//! Inline `#hashtag` tags in the note body
+//!
+//! With the `inline_tags` setting, words like `#standup` in the body of a new
+//! note are added to its tags. The scanner knows just enough markdown to skip
+//! headings, fenced code blocks and inline code spans.
+
+use std::ops::Range;
+
+use crate::core::note::Note;
+use crate::core::sections::{for_each_line_outside_code, heading_level};
+use crate::core::tags::Tag;
+use crate::error::Result;
+
+/// A `#tag` in the body of a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InlineTag {
+    tag: Tag,
+    /// The byte range of `#tag` in the content
+    range: Range<usize>,
+}
+
+/// Add the inline tags in the body of a note to its frontmatter
+///
+/// If `strip` is set, the `#tag` words are also removed from the body.
+pub fn apply_inline_tags(note: Note, strip: bool) -> Result<Note> {
+    let inline_tags = find_inline_tags(note.content());
+    if inline_tags.is_empty() {
+        return Ok(note);
+    }
+
+    let content = if strip {
+        strip_inline_tags(note.content(), &inline_tags)
+    } else {
+        note.content().to_string()
+    };
+
+    let mut note = Note::new(note.frontmatter().clone(), content);
+    note.update_tags(inline_tags.into_iter().map(|inline| inline.tag), [])?;
+
+    Ok(note)
+}
+
+/// Find the `#tag` words outside of headings and code
+fn find_inline_tags(content: &str) -> Vec<InlineTag> {
+    let mut inline_tags = Vec::new();
+
+    for_each_line_outside_code(content, |offset, text| {
+        let indented = text.trim_start_matches(' ');
+        if text.len() - indented.len() <= 3 && heading_level(indented).is_some() {
+            return;
+        }
+
+        scan_line(text, offset, &mut inline_tags);
+    });
+
+    inline_tags
+}
+
+/// Find the `#tag` words in a line, skipping inline code spans
+fn scan_line(line: &str, offset: usize, inline_tags: &mut Vec<InlineTag>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => {
+                // A code span ends with a backtick run of the same length;
+                // without one, the backticks are literal
+                let run = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                let delimiter = &line[i..i + run];
+                i += run;
+
+                let mut search = i;
+                while let Some(found) = line[search..].find(delimiter) {
+                    let end = search + found;
+                    let end_run = bytes[end..].iter().take_while(|b| **b == b'`').count();
+                    if end_run == run {
+                        i = end + run;
+                        break;
+                    }
+                    search = end + end_run;
+                }
+            }
+            // A tag starts a word, so that e.g. `C#` or URL fragments are not tags
+            b'#' if i == 0 || (bytes[i - 1] as char).is_whitespace() || bytes[i - 1] == b'(' => {
+                // Take the whole word, so that e.g. `#snake_case` is not a tag at all
+                let word_len = line[i + 1..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                    .unwrap_or(line.len() - i - 1);
+                let word = &line[i + 1..i + 1 + word_len];
+
+                // Words without letters are more likely issue numbers than tags
+                if word.chars().any(|c| c.is_ascii_alphabetic())
+                    && let Ok(tag) = Tag::new(word)
+                {
+                    inline_tags.push(InlineTag {
+                        tag,
+                        range: offset + i..offset + i + 1 + word_len,
+                    });
+                }
+                i += 1 + word_len;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/// Remove the `#tag` words from the content, along with the space before them
+fn strip_inline_tags(content: &str, inline_tags: &[InlineTag]) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+
+    for inline in inline_tags {
+        let mut start = inline.range.start;
+        let mut end = inline.range.end;
+        if content[..start].ends_with(' ') {
+            start -= 1;
+        } else if content[end..].starts_with(' ') {
+            end += 1;
+        }
+
+        result.push_str(&content[last..start.max(last)]);
+        last = end;
+    }
+    result.push_str(&content[last..]);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frontmatter::Frontmatter;
+
+    fn tags(content: &str) -> Vec<String> {
+        find_inline_tags(content)
+            .into_iter()
+            .map(|inline| inline.tag.as_str().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_inline_tags_in_text() {
+        assert_eq!(
+            tags("#standup notes\n\nTalked about #deploy-plan (#ops), see #42."),
+            vec!["standup", "deploy-plan", "ops"]
+        );
+
+        // Not at the start of a word, or not a valid tag
+        assert!(tags("C# and https://example.com/#anchor").is_empty());
+        assert!(tags("#-dash #under_score #").is_empty());
+
+        // Tags are lowercased, like on the command line
+        assert_eq!(tags("#Standup"), vec!["standup"]);
+    }
+
+    #[test]
+    fn test_inline_tags_skip_headings() {
+        assert_eq!(
+            tags("# Weekly sync\n## #notatag\n#tag-line\n####### seven"),
+            vec!["tag-line"]
+        );
+    }
+
+    #[test]
+    fn test_inline_tags_skip_fenced_code() {
+        let content =
+            "Before #one\n```sh\n#comment\n```\n~~~~\n#two\n~~~\n#three\n~~~~\nAfter #four";
+        assert_eq!(tags(content), vec!["one", "four"]);
+    }
+
+    #[test]
+    fn test_inline_tags_skip_code_spans() {
+        assert_eq!(
+            tags("Use `#[derive]` or ``a ` #b`` but #yes, and ` #unclosed"),
+            vec!["yes", "unclosed"]
+        );
+    }
+
+    #[test]
+    fn test_apply_inline_tags() {
+        let note = Note::new(
+            Frontmatter::default(),
+            "# Standup\n\nShipped the release #work\n#done".to_string(),
+        );
+
+        let kept = apply_inline_tags(note.clone(), false).unwrap();
+        assert_eq!(kept.tags_as_strings(), vec!["work", "done"]);
+        assert_eq!(kept.content(), note.content());
+
+        let stripped = apply_inline_tags(note, true).unwrap();
+        assert_eq!(stripped.tags_as_strings(), vec!["work", "done"]);
+        assert_eq!(stripped.content(), "# Standup\n\nShipped the release\n");
+    }
+}
@@ -0,0 +1,129 @@
+//! Where the content of a new note comes from
+//!
+//! A note added from the command line takes its content from exactly one
+//! source. The sources are resolved up front, so that conflicting sources are
+//! always reported the same way.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::{NotelogError, Result};
+
+/// The source of the content of a new note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    /// Content piped into stdin
+    Stdin(Vec<u8>),
+    /// A file given with --file
+    File(PathBuf),
+    /// The command line arguments that are not tags
+    Args(Vec<String>),
+    /// Nothing was given, so the note is written in the editor
+    Editor,
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSource::Stdin(_) => write!(f, "stdin"),
+            InputSource::File(path) => write!(f, "--file {}", path.display()),
+            InputSource::Args(args) => {
+                write!(f, "the command line arguments \"{}\"", args.join(" "))
+            }
+            InputSource::Editor => write!(f, "the editor"),
+        }
+    }
+}
+
+/// Resolve the source of the content of a new note
+///
+/// Stdin, --file and the non-tag arguments are mutually exclusive; if none of
+/// them is given, the note is written in the editor. If several are given, the
+/// error names the first two in the order stdin, file, arguments.
+pub fn resolve_input_source(
+    stdin_content: Vec<u8>,
+    file: Option<PathBuf>,
+    non_tag_args: Vec<String>,
+) -> Result<InputSource> {
+    let mut sources = Vec::with_capacity(3);
+    if !stdin_content.is_empty() {
+        sources.push(InputSource::Stdin(stdin_content));
+    }
+    if let Some(path) = file {
+        sources.push(InputSource::File(path));
+    }
+    if !non_tag_args.is_empty() {
+        sources.push(InputSource::Args(non_tag_args));
+    }
+
+    let mut sources = sources.into_iter();
+    match (sources.next(), sources.next()) {
+        (None, _) => Ok(InputSource::Editor),
+        (Some(source), None) => Ok(source),
+        (Some(a), Some(b)) => Err(NotelogError::ConflictingInputMethods { a, b }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A short name for each kind of source, to keep the table readable
+    fn kind(source: &InputSource) -> &'static str {
+        match source {
+            InputSource::Stdin(_) => "stdin",
+            InputSource::File(_) => "file",
+            InputSource::Args(_) => "args",
+            InputSource::Editor => "editor",
+        }
+    }
+
+    #[test]
+    fn test_resolve_input_source() {
+        // (stdin, file, args) => the resolved source, or "a+b" for a conflict
+        let table = [
+            (false, false, false, "editor"),
+            (true, false, false, "stdin"),
+            (false, true, false, "file"),
+            (false, false, true, "args"),
+            (true, true, false, "stdin+file"),
+            (true, false, true, "stdin+args"),
+            (false, true, true, "file+args"),
+            (true, true, true, "stdin+file"),
+        ];
+
+        for (stdin, file, args, expected) in table {
+            let result = resolve_input_source(
+                if stdin { b"piped".to_vec() } else { Vec::new() },
+                file.then(|| PathBuf::from("note.md")),
+                if args {
+                    vec!["words".to_string()]
+                } else {
+                    Vec::new()
+                },
+            );
+
+            let actual = match &result {
+                Ok(source) => kind(source).to_string(),
+                Err(NotelogError::ConflictingInputMethods { a, b }) => {
+                    format!("{}+{}", kind(a), kind(b))
+                }
+                Err(e) => panic!("Unexpected error: {}", e),
+            };
+            assert_eq!(actual, expected, "stdin={stdin}, file={file}, args={args}");
+        }
+    }
+
+    #[test]
+    fn test_input_source_display() {
+        assert_eq!(InputSource::Stdin(b"x".to_vec()).to_string(), "stdin");
+        assert_eq!(
+            InputSource::File(PathBuf::from("notes.md")).to_string(),
+            "--file notes.md"
+        );
+        assert_eq!(
+            InputSource::Args(vec!["foo".to_string(), "bar".to_string()]).to_string(),
+            "the command line arguments \"foo bar\""
+        );
+    }
+}
@@ -3,8 +3,15 @@
 //! This module contains the core data structures and functionality for notelog,
 //! including Note, Frontmatter, and Tag implementations.
 
+pub mod datetime;
 pub mod frontmatter;
 pub mod id;
+pub mod inline_tags;
+pub mod input;
 pub mod note;
 pub mod note_builder;
+pub mod retention;
+pub mod sections;
+pub mod tag_clusters;
 pub mod tags;
+pub mod todo;
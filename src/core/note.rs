@@ -3,13 +3,22 @@
 use chrono::Local;
 use std::fmt;
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use crate::config;
+use crate::constants::{
+    MAX_TITLE_CHARS, MAX_TITLE_LINE_CHARS, MAX_TITLE_LINE_WORD_CHARS, MAX_TITLE_SEARCH_LINES,
+};
 use crate::core::frontmatter::Frontmatter;
+use crate::core::sections::{code_fence, for_each_line_outside_code};
 use crate::core::tags::Tag;
 use crate::error::{NotelogError, Result};
-use crate::utils::{create_date_directories, generate_filename, validate_content};
+use crate::utils::{
+    create_date_directories, create_new_file, generate_filename, relative_to_notes_dir,
+    truncate_chars, validate_content,
+};
 
 /// Represents a complete note with frontmatter and content
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -53,12 +62,17 @@ impl Note {
 
     /// Save the note to disk in the appropriate directory
     ///
+    /// The year and month directories and the timestamp in the filename are
+    /// those of the `created` time of the note, converted to local time, so
+    /// that the path always agrees with the frontmatter no matter which offset
+    /// the timestamp was written with.
+    ///
     /// Returns the path to the saved note file, relative to the notes_dir
     pub fn save(&self, notes_dir: &Path, title_override: Option<&str>) -> Result<PathBuf> {
-        let now = Local::now();
+        let created = self.frontmatter.created().with_timezone(&Local);
 
         // Create the year and month directories
-        let month_dir = create_date_directories(notes_dir, &now)?;
+        let month_dir = create_date_directories(notes_dir, &created)?;
 
         // Determine the title to use for the filename
         let title = match title_override {
@@ -71,28 +85,42 @@ impl Note {
             return Err(NotelogError::EmptyContent);
         }
 
-        // Generate the filename
-        let mut filename = generate_filename(&now, &title, None);
-        let mut counter = 2;
-
-        // Check for filename collisions
-        while month_dir.join(&filename).exists() {
-            filename = generate_filename(&now, &title, Some(counter));
-            counter += 1;
-        }
-
         // Get the full content with frontmatter
         let final_content = self.formatted_content();
 
-        // Write the note to the file
-        let absolute_note_path = month_dir.join(&filename);
-        fs::write(&absolute_note_path, final_content)?;
+        // Write the note to a new file, adding a counter to the filename on collisions.
+        // Creating the file with create_new makes the collision check atomic, so
+        // concurrent saves of notes with the same title never overwrite each other.
+        let mut counter = None;
+        let absolute_note_path = loop {
+            let filename = generate_filename(&created, &title, counter);
+            let path = month_dir.join(&filename);
+
+            match create_new_file(&path, config::get().restrict_permissions()) {
+                Ok(mut file) => {
+                    if let Err(e) = file.write_all(final_content.as_bytes()) {
+                        // Don't leave a truncated note behind
+                        let _ = fs::remove_file(&path);
+                        return Err(e.into());
+                    }
+                    break path;
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    counter = Some(counter.map_or(2, |c| c + 1));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
 
         // Convert the absolute path to a path relative to notes_dir
-        let relative_path = absolute_note_path
-            .strip_prefix(notes_dir)
-            .map_err(|e| NotelogError::PathError(format!("Failed to create relative path: {}", e)))?
-            .to_path_buf();
+        let relative_path =
+            relative_to_notes_dir(&absolute_note_path, notes_dir).ok_or_else(|| {
+                NotelogError::PathError(format!(
+                    "{} is not in the notes directory {}",
+                    absolute_note_path.display(),
+                    notes_dir.display()
+                ))
+            })?;
 
         // Return the relative path
         Ok(relative_path)
@@ -107,58 +135,142 @@ impl Note {
             .collect()
     }
 
+    /// Count the words in the content of the note
+    ///
+    /// Fenced code blocks are not counted, and neither are markdown markers
+    /// like `#` or `-` that do not contain a letter or digit. Inline code
+    /// counts as part of the prose around it.
+    pub fn word_count(&self) -> usize {
+        let mut words = 0;
+        for_each_line_outside_code(&self.content, |_, text| {
+            words += text
+                .split_whitespace()
+                .filter(|word| word.chars().any(char::is_alphanumeric))
+                .count();
+        });
+        words
+    }
+
     /// Update the note's tags by adding and removing specified tags
-    pub fn update_tags<I, J>(&mut self, tags_to_add: I, tags_to_remove: J)
+    pub fn update_tags<I, J>(&mut self, tags_to_add: I, tags_to_remove: J) -> Result<()>
     where
         I: IntoIterator<Item = Tag>,
         J: IntoIterator<Item = Tag>,
     {
         self.frontmatter_mut()
-            .update_tags(tags_to_add, tags_to_remove);
+            .update_tags(tags_to_add, tags_to_remove)
     }
 
     /// Extract title from the note content
+    ///
+    /// The title is the first line with text, among the first
+    /// `MAX_TITLE_SEARCH_LINES` non-empty lines. If all of those are Markdown
+    /// syntax, like the fence of a code block, the title is generated from the
+    /// creation time instead, as it is if the line is pasted output rather
+    /// than prose, like a log pasted as a single line. Only a note without
+    /// content has an empty title.
     pub fn extract_title(&self) -> String {
-        // Find the first non-empty line in the content
-        let mut title = self
+        let title = self
             .content
             .lines()
-            .find(|line| !line.trim().is_empty())
-            .unwrap_or("")
-            .trim()
-            .to_string();
-
-        // Remove leading '#' characters (indicating a Markdown header) from the
-        // title. If the note starts with a Markdown list indicated by "- " or
-        // "* ", remove that as well.
-        if title.starts_with('#') {
-            title = title.trim_start_matches('#').trim().to_string();
-        } else if title.starts_with("- ") {
-            title = title
-                .strip_prefix("- ")
-                .unwrap_or(&title)
-                .trim()
-                .to_string();
-        } else if title.starts_with("* ") {
-            title = title
-                .strip_prefix("* ")
-                .unwrap_or(&title)
-                .trim()
-                .to_string();
+            .filter(|line| !line.trim().is_empty())
+            .take(MAX_TITLE_SEARCH_LINES)
+            .map(|line| (line, title_text(line)))
+            .find(|(_, title)| !title.is_empty());
+
+        match title {
+            Some((line, _)) if is_pasted_output(line) => self.generated_title(),
+            Some((_, title)) => title,
+            None if self.content.trim().is_empty() => String::new(),
+            None => self.generated_title(),
         }
+    }
 
-        // Truncate to 100 characters maximum
-        if title.len() > 100 {
-            title.truncate(100);
-        }
+    /// A title for notes without a line that could be used as one, like
+    /// `Note 2025-04-01 12:00`, from the creation time in local time
+    pub fn generated_title(&self) -> String {
+        let created = self.frontmatter.created().with_timezone(&Local);
+        format!("Note {}", created.format("%Y-%m-%d %H:%M"))
+    }
+}
 
-        // Remove any trailing periods (so we don't end up with "Title..md")
-        while title.ends_with('.') {
-            title.pop();
-        }
+/// Whether a line is too long to be prose, and its words are too long as well
+///
+/// Logs and encoded data run to long "words", like timestamps, key-value
+/// pairs and paths, while a paragraph of prose on a single line does not.
+fn is_pasted_output(line: &str) -> bool {
+    let (words, chars) = line
+        .split_whitespace()
+        .fold((0, 0), |(words, chars), word| {
+            (words + 1, chars + word.chars().count())
+        });
+
+    chars > MAX_TITLE_LINE_CHARS && chars > words * MAX_TITLE_LINE_WORD_CHARS
+}
+
+/// The title text of a line, or an empty string for lines that are only
+/// Markdown syntax
+fn title_text(line: &str) -> String {
+    let line = line.trim();
+
+    // Horizontal rules (like the `---` that starts a pasted YAML block), the
+    // fences of code blocks, the rules under table headers and empty quotes
+    if is_horizontal_rule(line)
+        || code_fence(line).is_some()
+        || is_table_rule(line)
+        || line.chars().all(|c| c == '>' || c.is_whitespace())
+    {
+        return String::new();
+    }
 
-        title
+    // The cells of a table row, without the pipes around them
+    let mut title = line.to_string();
+    if title.starts_with('|') {
+        title = title.trim_matches('|').trim().to_string();
+    }
+
+    // Remove leading '#' characters (indicating a Markdown header) from the
+    // title. If the line starts a Markdown list indicated by "- " or "* ",
+    // remove that as well.
+    if title.starts_with('#') {
+        title = title.trim_start_matches('#').trim().to_string();
+    } else if let Some(item) = title
+        .strip_prefix("- ")
+        .or_else(|| title.strip_prefix("* "))
+    {
+        title = item.trim().to_string();
     }
+
+    // Truncate to 100 characters maximum
+    let mut title = truncate_chars(&title, MAX_TITLE_CHARS)
+        .trim_end()
+        .to_string();
+
+    // Remove any trailing periods (so we don't end up with "Title..md")
+    while title.ends_with('.') {
+        title.pop();
+    }
+
+    title
+}
+
+/// Whether a line is the rule under the header of a Markdown table, like
+/// `|---|:---:|`
+fn is_table_rule(line: &str) -> bool {
+    line.contains('|')
+        && line.contains('-')
+        && line
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':') || c.is_whitespace())
+}
+
+/// Whether a line is a Markdown horizontal rule, like `---` or `* * *`
+fn is_horizontal_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&mark| marks.iter().all(|&c| c == mark))
 }
 
 impl fmt::Display for Note {
@@ -197,8 +309,32 @@ impl FromStr for Note {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::DateTime;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_word_count() {
+        let note = Note::from_str(
+            "---\ncreated: 2025-04-01T12:00:00+00:00\ntags:\n  - test\n---\n\n# A title\n\n- one item\n- `cargo build` works\n",
+        )
+        .unwrap();
+        assert_eq!(note.word_count(), 7);
+
+        // Fenced code blocks are skipped, along with their fences
+        let note = Note::from_str(
+            "Before the fence\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\n~~~~\nmore code here\n~~~~\nafter it",
+        )
+        .unwrap();
+        assert_eq!(note.word_count(), 5);
+
+        // An unclosed fence runs to the end of the note
+        let note = Note::from_str("Two words\n```\nnot counted\n").unwrap();
+        assert_eq!(note.word_count(), 2);
+
+        let note = Note::new(Frontmatter::default(), String::new());
+        assert_eq!(note.word_count(), 0);
+    }
+
     #[test]
     fn test_note_from_str() {
         // Valid note with frontmatter
@@ -268,6 +404,11 @@ mod tests {
         let note = Note::new(frontmatter.clone(), content.to_string());
         assert_eq!(note.extract_title(), "This is a title");
 
+        // Horizontal rules are skipped
+        let content = "---\nname: deploy\n---\nThis is the content";
+        let note = Note::new(frontmatter.clone(), content.to_string());
+        assert_eq!(note.extract_title(), "name: deploy");
+
         // Long title truncation
         let long_title = "A".repeat(150);
         let content = format!("# {}\nThis is the content", long_title);
@@ -276,6 +417,20 @@ mod tests {
         assert_eq!(extracted.len(), 100);
         assert_eq!(extracted, "A".repeat(100));
 
+        // Multi-byte titles are truncated to 100 characters, not bytes
+        let content = format!("# {}\nThis is the content", "🦀".repeat(150));
+        let note = Note::new(frontmatter.clone(), content);
+        assert_eq!(note.extract_title(), "🦀".repeat(100));
+
+        // Byte 100 falls inside a character
+        let cjk_title = format!("a{}", "日本語".repeat(50));
+        let content = format!("# {}\nThis is the content", cjk_title);
+        let note = Note::new(frontmatter.clone(), content);
+        assert_eq!(
+            note.extract_title(),
+            cjk_title.chars().take(100).collect::<String>()
+        );
+
         // Single trailing period
         let content = "This title has a period.\nThis is the content";
         let note = Note::new(frontmatter.clone(), content.to_string());
@@ -292,6 +447,76 @@ mod tests {
         assert_eq!(note.extract_title(), "This is a header with period");
     }
 
+    #[test]
+    fn test_extract_title_skips_markdown_syntax() {
+        let frontmatter = "created: 2025-04-01T12:30:00+00:00"
+            .parse::<Frontmatter>()
+            .unwrap();
+        let title =
+            |content: &str| Note::new(frontmatter.clone(), content.to_string()).extract_title();
+        let generated = format!(
+            "Note {}",
+            frontmatter
+                .created()
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+        );
+
+        // Code block first
+        assert_eq!(
+            title("```rust\nfn main() {}\n```\nIt compiles."),
+            "fn main() {}"
+        );
+        assert_eq!(title("~~~\n\n$ cargo build\n~~~"), "$ cargo build");
+
+        // Table first
+        let table = "| Host | Status |\n|------|:------:|\n| web | up |";
+        assert_eq!(title(table), "Host | Status");
+        assert_eq!(title("|---|---|\n| web | up |"), "web | up");
+
+        // Empty quotes and headings
+        assert_eq!(title(">\n> > \n#\n> Quoted"), "> Quoted");
+
+        // Without a line of text, the title is generated
+        assert_eq!(title("```\n```"), generated);
+        let syntax = "---\n".repeat(MAX_TITLE_SEARCH_LINES);
+        assert_eq!(title(&format!("{}Too late", syntax)), generated);
+        assert_eq!(title(" \n\n"), "");
+    }
+
+    #[test]
+    fn test_extract_title_of_a_log_dump() {
+        let frontmatter = "created: 2025-04-01T12:30:00+00:00"
+            .parse::<Frontmatter>()
+            .unwrap();
+        let generated = Note::new(frontmatter.clone(), String::new()).generated_title();
+        assert!(generated.starts_with("Note 2025-04-0"));
+
+        // About 40 KB of log output, pasted as a single line
+        let entry = "2025-04-01T12:00:00Z INFO request_id=4f2a9c status=200 path=/api/v1/notes ";
+        let dump = entry.repeat(40 * 1024 / entry.len());
+        let note = Note::new(frontmatter.clone(), format!("{}\n\nFrom the proxy", dump));
+        assert_eq!(note.extract_title(), generated);
+
+        // Long lines of prose are still titles
+        let prose = "word ".repeat(MAX_TITLE_LINE_CHARS / 4);
+        let note = Note::new(frontmatter.clone(), prose);
+        assert_eq!(note.extract_title(), "word ".repeat(20).trim_end());
+
+        // Also a paragraph of about 200 words on a single line
+        let sentence = "The committee discussed the proposed budget for the coming year, \
+                        and eventually agreed to postpone the final decision until the \
+                        spring meeting. ";
+        let paragraph = sentence.repeat(9);
+        assert!(paragraph.split_whitespace().map(str::len).sum::<usize>() > MAX_TITLE_LINE_CHARS);
+        let note = Note::new(frontmatter.clone(), paragraph);
+        assert!(note.extract_title().starts_with("The committee discussed"));
+
+        // Encoded data without any spaces is not
+        let note = Note::new(frontmatter, "QUJD".repeat(MAX_TITLE_LINE_CHARS));
+        assert_eq!(note.extract_title(), generated);
+    }
+
     #[test]
     fn test_save() {
         // Create a temporary directory for testing
@@ -323,6 +548,82 @@ mod tests {
         assert!(!saved_content.contains("tags:"));
     }
 
+    #[test]
+    fn test_save_without_a_title_line() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        let frontmatter = "created: 2025-04-01T12:30:00+00:00"
+            .parse::<Frontmatter>()
+            .unwrap();
+        let created = frontmatter.created().with_timezone(&Local);
+
+        // A note with content always has a title to name the file after
+        let note = Note::new(frontmatter.clone(), "```\n```".to_string());
+        let relative_path = note.save(notes_dir, None).unwrap();
+        assert_eq!(
+            relative_path.file_name().unwrap().to_string_lossy(),
+            format!(
+                "{} Note {}.md",
+                created.format("%Y-%m-%dT%H-%M"),
+                created.format("%Y-%m-%d %H-%M")
+            )
+        );
+
+        let note = Note::new(frontmatter, "\n \n".to_string());
+        assert!(matches!(
+            note.save(notes_dir, None),
+            Err(NotelogError::EmptyContent)
+        ));
+    }
+
+    #[test]
+    fn test_save_uses_created_time_in_local_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        for (i, timestamp) in [
+            "2021-12-31T23:30:00-11:00",
+            "2022-01-01T00:15:00+00:00",
+            "2022-06-30T23:59:00+14:00",
+            "2023-03-01T00:00:00+05:30",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let frontmatter = format!("created: {}", timestamp)
+                .parse::<Frontmatter>()
+                .unwrap();
+            let local = DateTime::parse_from_rfc3339(timestamp)
+                .unwrap()
+                .with_timezone(&Local);
+            let note = Note::new(frontmatter, format!("# Note {}", i));
+
+            let relative_path = note.save(notes_dir, None).unwrap();
+
+            // The directories and the filename follow the frontmatter, in local time
+            let components: Vec<String> = relative_path
+                .iter()
+                .map(|c| c.to_string_lossy().to_string())
+                .collect();
+            assert_eq!(
+                components[0],
+                local.format("%Y").to_string(),
+                "{}",
+                timestamp
+            );
+            assert!(
+                components[1].starts_with(&local.format("%m").to_string()),
+                "{}",
+                timestamp
+            );
+            assert_eq!(
+                components[2],
+                format!("{} Note {}.md", local.format("%Y-%m-%dT%H-%M"), i)
+            );
+        }
+    }
+
     #[test]
     fn test_save_with_title_override() {
         // Create a temporary directory for testing
@@ -383,7 +684,8 @@ mod tests {
 
         // Add and remove tags
         let tag3 = Tag::new("+new").unwrap();
-        note.update_tags(vec![tag3.clone()], vec![tag1.clone()]);
+        note.update_tags(vec![tag3.clone()], vec![tag1.clone()])
+            .unwrap();
 
         // Verify the tags
         let tags = note.tags_as_strings();
@@ -392,4 +694,35 @@ mod tests {
         assert!(tags.contains(&"example".to_string()));
         assert!(tags.contains(&"new".to_string()));
     }
+
+    #[test]
+    fn test_concurrent_saves_with_same_title() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path().to_path_buf();
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let notes_dir = notes_dir.clone();
+                std::thread::spawn(move || {
+                    let note = Note::new(Frontmatter::default(), format!("# Same\n\nNote {}", i));
+                    note.save(&notes_dir, None).unwrap()
+                })
+            })
+            .collect();
+
+        let mut paths: Vec<PathBuf> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        // Every note was written to its own file
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), 8);
+
+        let mut contents: Vec<String> = paths
+            .iter()
+            .map(|path| fs::read_to_string(notes_dir.join(path)).unwrap())
+            .collect();
+        contents.sort();
+        contents.dedup();
+        assert_eq!(contents.len(), 8);
+    }
 }
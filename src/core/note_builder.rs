@@ -24,6 +24,10 @@ pub struct NoteBuilder {
     validate: bool,
     /// Title override for saving
     title_override: Option<String>,
+    /// Where the note came from
+    source: Option<String>,
+    /// Whether a new frontmatter gets an ID
+    generate_id: bool,
 }
 
 impl NoteBuilder {
@@ -36,11 +40,12 @@ impl NoteBuilder {
             created: None,
             validate: true,
             title_override: None,
+            source: None,
+            generate_id: true,
         }
     }
 
-    /// Set the creation timestamp
-    #[cfg(test)]
+    /// Set the creation timestamp (the current time by default)
     pub fn created(mut self, created: DateTime<Local>) -> Self {
         self.created = Some(created);
         self
@@ -86,6 +91,20 @@ impl NoteBuilder {
         self
     }
 
+    /// Set where the note came from, unless the frontmatter already names a source
+    pub fn source<S: Into<String>>(mut self, source: S) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Set whether a new frontmatter gets an ID (the default)
+    ///
+    /// Frontmatter parsed from the content keeps its ID, or lack thereof.
+    pub fn generate_id(mut self, generate_id: bool) -> Self {
+        self.generate_id = generate_id;
+        self
+    }
+
     /// Build the Note object
     pub fn build(self) -> Result<Note> {
         // Validate the content if requested
@@ -93,23 +112,30 @@ impl NoteBuilder {
             validate_content(self.content.as_bytes())?;
         }
 
-        // Create the frontmatter
-        let frontmatter = match self.frontmatter {
-            Some(mut fm) => {
-                // If we have frontmatter but also have tags, add them to the frontmatter
-                for tag in self.tags {
-                    fm.add_tag(tag);
-                }
-
-                fm
-            }
+        // Create the frontmatter, or use the one that was provided
+        let mut frontmatter = match self.frontmatter {
+            Some(fm) => fm,
             None => {
-                // Create new frontmatter with the provided tags and timestamp
-                let created = self.created.unwrap_or_else(Local::now);
-                Frontmatter::new(created, self.tags)
+                let mut frontmatter =
+                    Frontmatter::new(self.created.unwrap_or_else(Local::now), Vec::new());
+                if !self.generate_id {
+                    frontmatter.clear_id();
+                }
+                frontmatter
             }
         };
 
+        // Add the tags one by one to deduplicate them and enforce the tag limit
+        for tag in self.tags {
+            frontmatter.add_tag(tag)?;
+        }
+
+        if let Some(source) = self.source
+            && frontmatter.source().is_none()
+        {
+            frontmatter.set_source(source);
+        }
+
         Ok(Note::new(frontmatter, self.content))
     }
 
@@ -117,9 +143,24 @@ impl NoteBuilder {
     pub fn parse_or_create(self) -> Result<Note> {
         match Note::from_str(&self.content) {
             Ok(mut note) => {
+                // Content without frontmatter got a new one, which follows `generate_id`
+                if !self.generate_id
+                    && matches!(
+                        Frontmatter::extract_from_content(&self.content),
+                        Ok((None, _))
+                    )
+                {
+                    note.frontmatter_mut().clear_id();
+                }
+
                 // If we have tags, add them to the note
                 for tag in self.tags {
-                    note.frontmatter_mut().add_tag(tag);
+                    note.frontmatter_mut().add_tag(tag)?;
+                }
+                if let Some(source) = self.source
+                    && note.frontmatter().source().is_none()
+                {
+                    note.frontmatter_mut().set_source(source);
                 }
                 Ok(note)
             }
@@ -268,4 +309,86 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_note_builder_tag_limit() {
+        use crate::constants::MAX_TAGS_PER_NOTE;
+        use crate::error::{NotelogError, TagError};
+
+        let tags = |n: usize| (0..n).map(|i| Tag::new(&format!("tag{}", i)).unwrap());
+
+        // Exactly MAX_TAGS_PER_NOTE tags are fine
+        let note = NoteBuilder::new()
+            .content("Content")
+            .tags(tags(MAX_TAGS_PER_NOTE))
+            .build()
+            .unwrap();
+        assert_eq!(note.frontmatter().tags().len(), MAX_TAGS_PER_NOTE);
+
+        // Duplicates do not count towards the limit
+        let note = NoteBuilder::new()
+            .content("Content")
+            .tags(tags(MAX_TAGS_PER_NOTE))
+            .tags(tags(MAX_TAGS_PER_NOTE))
+            .build()
+            .unwrap();
+        assert_eq!(note.frontmatter().tags().len(), MAX_TAGS_PER_NOTE);
+
+        // One more tag is an error
+        let result = NoteBuilder::new()
+            .content("Content")
+            .tags(tags(MAX_TAGS_PER_NOTE + 1))
+            .build();
+        assert!(matches!(
+            result.unwrap_err(),
+            NotelogError::TagError(TagError::TooMany(_))
+        ));
+    }
+
+    #[test]
+    fn test_note_builder_source() {
+        let note = NoteBuilder::new()
+            .content("Content")
+            .source("mcp")
+            .build()
+            .unwrap();
+        assert_eq!(note.frontmatter().source(), Some("mcp"));
+
+        // An existing source in the frontmatter is kept
+        let note = NoteBuilder::new()
+            .content("---\ncreated: 2025-04-01T12:00:00+00:00\nsource: import\n---\n\n# Content")
+            .source("cli")
+            .parse_or_create()
+            .unwrap();
+        assert_eq!(note.frontmatter().source(), Some("import"));
+    }
+
+    #[test]
+    fn test_note_builder_without_id() {
+        let note = NoteBuilder::new()
+            .content("Content")
+            .generate_id(false)
+            .build()
+            .unwrap();
+        assert!(note.frontmatter().id().is_none());
+        assert!(!note.to_string().contains("id:"));
+
+        // An ID in the frontmatter of the content is kept
+        let note = NoteBuilder::new()
+            .content(
+                "---\nid: 0123456789abcdef\ncreated: 2025-04-01T12:00:00+00:00\n---\n\n# Content",
+            )
+            .generate_id(false)
+            .parse_or_create()
+            .unwrap();
+        assert!(note.frontmatter().id().is_some());
+
+        // Content without frontmatter gets a new one without an ID
+        let note = NoteBuilder::new()
+            .content("# Content")
+            .generate_id(false)
+            .parse_or_create()
+            .unwrap();
+        assert!(note.frontmatter().id().is_none());
+    }
 }
@@ -0,0 +1,118 @@
+//! Retention of notes with expiring tags, for `notelog prune`
+
+use chrono::{DateTime, Duration, Local};
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+
+use crate::core::note::Note;
+use crate::core::tags::Tag;
+
+/// How many days notes with each expiring tag are kept
+///
+/// A note expires once it is older than the retention of each of its tags.
+/// Notes with a tag that does not expire are kept forever, so that e.g. a
+/// note tagged both `+scratch` and `+project` is never deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// The days to keep notes, by tag name
+    days: BTreeMap<String, NonZeroU32>,
+}
+
+impl RetentionPolicy {
+    /// Create a policy that keeps notes with each tag for the given number of days
+    pub fn new(rules: impl IntoIterator<Item = (Tag, NonZeroU32)>) -> Self {
+        Self {
+            days: rules
+                .into_iter()
+                .map(|(tag, days)| (tag.as_str().to_string(), days))
+                .collect(),
+        }
+    }
+
+    /// Whether no tags expire
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty()
+    }
+
+    /// The tags that expire
+    pub fn tags(&self) -> Vec<Tag> {
+        self.days
+            .keys()
+            .filter_map(|tag| Tag::new(tag).ok())
+            .collect()
+    }
+
+    /// When a note expires
+    ///
+    /// Returns `None` for notes that are kept forever, i.e. notes without an
+    /// expiring tag and notes with a tag that does not expire.
+    pub fn expires(&self, note: &Note) -> Option<DateTime<Local>> {
+        let tags = note.frontmatter().tags();
+        if tags.is_empty() {
+            return None;
+        }
+
+        // The longest retention of the tags wins
+        let mut days = 0;
+        for tag in tags {
+            days = days.max(self.days.get(tag.as_str())?.get());
+        }
+
+        Some(*note.frontmatter().created() + Duration::days(days.into()))
+    }
+
+    /// Whether a note has expired at `now`
+    pub fn is_expired(&self, note: &Note, now: DateTime<Local>) -> bool {
+        self.expires(note).is_some_and(|expires| expires <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frontmatter::Frontmatter;
+
+    fn policy() -> RetentionPolicy {
+        RetentionPolicy::new([
+            (Tag::new("scratch").unwrap(), NonZeroU32::new(30).unwrap()),
+            (Tag::new("tmp").unwrap(), NonZeroU32::new(7).unwrap()),
+        ])
+    }
+
+    fn note(days_old: i64, tags: &[&str]) -> Note {
+        let created = Local::now() - Duration::days(days_old);
+        let tags = tags.iter().map(|tag| Tag::new(tag).unwrap()).collect();
+        Note::new(Frontmatter::new(created, tags), "# Note".to_string())
+    }
+
+    #[test]
+    fn test_expiring_tags() {
+        let policy = policy();
+        let now = Local::now();
+
+        assert!(policy.is_expired(&note(31, &["scratch"]), now));
+        assert!(!policy.is_expired(&note(29, &["scratch"]), now));
+        assert!(policy.is_expired(&note(8, &["tmp"]), now));
+        assert!(!policy.is_expired(&note(6, &["tmp"]), now));
+    }
+
+    #[test]
+    fn test_longest_retention_wins() {
+        let policy = policy();
+        let now = Local::now();
+
+        assert!(!policy.is_expired(&note(8, &["tmp", "scratch"]), now));
+        assert!(policy.is_expired(&note(31, &["tmp", "scratch"]), now));
+    }
+
+    #[test]
+    fn test_tags_without_expiry_protect_notes() {
+        let policy = policy();
+        let now = Local::now();
+
+        assert_eq!(policy.expires(&note(365, &["scratch", "project"])), None);
+        assert!(!policy.is_expired(&note(365, &["project"]), now));
+        assert!(!policy.is_expired(&note(365, &[]), now));
+        assert!(!RetentionPolicy::default().is_expired(&note(365, &["scratch"]), now));
+    }
+}
@@ -0,0 +1,288 @@
+//! Finding the sections of a Markdown note by their heading
+//!
+//! A section starts with an ATX heading (`## Title`) and ends before the next
+//! heading of the same or a higher level, so it includes its subsections.
+//! Lines in fenced code blocks are never headings, even if they start with `#`.
+
+use std::ops::Range;
+
+/// A heading in a Markdown document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// The number of `#` characters, from 1 to 6
+    pub level: usize,
+    /// The text of the heading, without the `#` characters
+    pub title: String,
+    /// The byte range of the heading line, including its line break
+    pub line: Range<usize>,
+}
+
+/// A section of a Markdown document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// The heading that starts the section
+    pub heading: Heading,
+    /// The byte range of the section, from its heading line to the start of
+    /// the next heading of the same or a higher level (or the end)
+    pub range: Range<usize>,
+}
+
+/// Get the marker and length of a code fence line (```` ``` ```` or `~~~`)
+pub(crate) fn code_fence(line: &str) -> Option<(char, usize)> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.len() - line.trim_start_matches(marker).len();
+
+    (len >= 3).then_some((marker, len))
+}
+
+/// Get the level of an ATX heading line, e.g. 2 for `## Title`
+pub(crate) fn heading_level(line: &str) -> Option<usize> {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[hashes..];
+
+    ((1..=6).contains(&hashes) && (rest.is_empty() || rest.starts_with([' ', '\t'])))
+        .then_some(hashes)
+}
+
+/// Call `f` with the byte offset and text of each line outside fenced code
+/// blocks, without the line break
+///
+/// The fence lines themselves are skipped as well.
+pub(crate) fn for_each_line_outside_code(content: &str, mut f: impl FnMut(usize, &str)) {
+    let mut fence: Option<(char, usize)> = None;
+    let mut line_start = 0;
+
+    for line in content.split_inclusive('\n') {
+        let offset = line_start;
+        line_start += line.len();
+
+        let text = line.trim_end_matches(['\n', '\r']);
+        let indented = text.trim_start_matches(' ');
+        let indent = text.len() - indented.len();
+
+        // Code fences can be indented by up to three spaces
+        if indent <= 3
+            && let Some((marker, len)) = code_fence(indented)
+        {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open_marker, open_len))
+                    if marker == open_marker
+                        && len >= open_len
+                        && indented.trim_start_matches(marker).trim().is_empty() =>
+                {
+                    fence = None
+                }
+                Some(_) => {}
+            }
+            continue;
+        }
+
+        if fence.is_none() {
+            f(offset, text);
+        }
+    }
+}
+
+/// Find the headings of a Markdown document, outside of fenced code blocks
+pub fn headings(content: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+
+    for_each_line_outside_code(content, |offset, text| {
+        let indented = text.trim_start_matches(' ');
+        if text.len() - indented.len() > 3 {
+            return;
+        }
+
+        if let Some(level) = heading_level(indented) {
+            let line_len = content[offset..]
+                .find('\n')
+                .map_or(content.len() - offset, |i| i + 1);
+            headings.push(Heading {
+                level,
+                title: heading_title(&indented[level..]),
+                line: offset..offset + line_len,
+            });
+        }
+    });
+
+    headings
+}
+
+/// The text of a heading, without surrounding space and closing `#` characters
+fn heading_title(rest: &str) -> String {
+    let title = rest.trim();
+    let without_closing = title.trim_end_matches('#');
+    if without_closing.is_empty() || without_closing.ends_with([' ', '\t']) {
+        without_closing.trim_end().to_string()
+    } else {
+        title.to_string()
+    }
+}
+
+/// Find the first section whose heading has the given title
+///
+/// The title is compared without case, and may be given with its `#`
+/// characters, e.g. `## Follow-ups`.
+pub fn find_section(content: &str, title: &str) -> Option<Section> {
+    let wanted = title.trim().trim_start_matches('#').trim().to_lowercase();
+    let headings = headings(content);
+
+    let index = headings
+        .iter()
+        .position(|heading| heading.title.to_lowercase() == wanted)?;
+    let heading = headings[index].clone();
+    let end = headings[index + 1..]
+        .iter()
+        .find(|next| next.level <= heading.level)
+        .map_or(content.len(), |next| next.line.start);
+
+    Some(Section {
+        range: heading.line.start..end,
+        heading,
+    })
+}
+
+/// Replace a section of a document with the given text
+///
+/// The replacement is followed by an empty line if more of the document
+/// follows it; an empty replacement removes the section.
+pub fn replace_section(content: &str, section: &Section, replacement: &str) -> String {
+    let before = &content[..section.range.start];
+    let after = &content[section.range.end..];
+
+    match (replacement.trim_end(), after.is_empty()) {
+        ("", _) => format!("{}{}", before, after),
+        (replacement, true) => format!("{}{}\n", before, replacement),
+        (replacement, false) => format!("{}{}\n\n{}", before, replacement, after),
+    }
+}
+
+/// Raise the headings of a section so that its own heading has level 1
+///
+/// Subsections keep their level relative to the section, e.g. the `###`
+/// headings in a `##` section become `##` headings.
+pub fn promote_headings(section: &str) -> String {
+    let headings = headings(section);
+    let Some(shift) = headings.first().map(|heading| heading.level - 1) else {
+        return section.to_string();
+    };
+
+    let mut out = String::with_capacity(section.len());
+    let mut last = 0;
+    for heading in &headings {
+        let line = &section[heading.line.clone()];
+        let hashes_start = line.len() - line.trim_start_matches(' ').len();
+        let remove = shift.min(heading.level - 1);
+
+        out.push_str(&section[last..heading.line.start]);
+        out.push_str(&line[..hashes_start]);
+        out.push_str(&line[hashes_start + remove..]);
+        last = heading.line.end;
+    }
+    out.push_str(&section[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "\
+# Meeting
+
+Notes.
+
+## Decisions
+
+- Ship it
+
+### Details
+
+```sh
+# not a heading
+echo hi
+```
+
+## Follow-ups
+
+- Call Bob
+
+### Open questions ###
+
+~~~
+## also not a heading
+~~~
+
+## Attendees
+
+Alice
+";
+
+    #[test]
+    fn test_headings_skip_code_fences() {
+        let titles: Vec<(usize, String)> = headings(NOTE)
+            .into_iter()
+            .map(|heading| (heading.level, heading.title))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                (1, "Meeting".to_string()),
+                (2, "Decisions".to_string()),
+                (3, "Details".to_string()),
+                (2, "Follow-ups".to_string()),
+                (3, "Open questions".to_string()),
+                (2, "Attendees".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_section_includes_subsections() {
+        let section = find_section(NOTE, "follow-ups").unwrap();
+        assert_eq!(section.heading.level, 2);
+        assert_eq!(
+            &NOTE[section.range.clone()],
+            "## Follow-ups\n\n- Call Bob\n\n### Open questions ###\n\n~~~\n## also not a heading\n~~~\n\n"
+        );
+
+        // A section ends at a heading of a higher level, not only the same one
+        let section = find_section(NOTE, "## Details").unwrap();
+        assert!(NOTE[section.range.clone()].ends_with("echo hi\n```\n\n"));
+
+        // The last section ends at the end of the document
+        let section = find_section(NOTE, "Attendees").unwrap();
+        assert_eq!(&NOTE[section.range], "## Attendees\n\nAlice\n");
+
+        assert_eq!(find_section(NOTE, "not a heading"), None);
+        assert_eq!(find_section(NOTE, "also not a heading"), None);
+    }
+
+    #[test]
+    fn test_replace_section() {
+        let section = find_section(NOTE, "Follow-ups").unwrap();
+        let replaced = replace_section(NOTE, &section, "- [Follow-ups](notelog://note/abc)");
+        assert!(
+            replaced
+                .contains("echo hi\n```\n\n- [Follow-ups](notelog://note/abc)\n\n## Attendees\n")
+        );
+        assert!(!replaced.contains("Call Bob"));
+
+        let removed = replace_section(NOTE, &section, "");
+        assert!(removed.contains("echo hi\n```\n\n## Attendees\n"));
+
+        let section = find_section(NOTE, "Attendees").unwrap();
+        assert!(replace_section(NOTE, &section, "- link").ends_with("\n\n- link\n"));
+    }
+
+    #[test]
+    fn test_promote_headings() {
+        let section = find_section(NOTE, "Follow-ups").unwrap();
+        assert_eq!(
+            promote_headings(&NOTE[section.range]),
+            "# Follow-ups\n\n- Call Bob\n\n## Open questions ###\n\n~~~\n## also not a heading\n~~~\n\n"
+        );
+        assert_eq!(promote_headings("No headings\n"), "No headings\n");
+    }
+}
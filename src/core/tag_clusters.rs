@@ -0,0 +1,237 @@
+//! Near-duplicate tags, for `notelog tag suggest-cleanup`
+//!
+//! Tags are near-duplicates if they are the same without dashes (`+standup`
+//! and `+stand-up`), if one is a numeronym of the other (`+k8s` and
+//! `+kubernetes`), if one starts with the other (`+kube` and `+kubernetes`),
+//! or if they differ in a few letters relative to their length (`+meeting`
+//! and `+meetings`). Near-duplicates of near-duplicates end up in the same
+//! cluster.
+
+/// The largest edit distance, relative to the length of the longer tag, at
+/// which two tags are near-duplicates
+const MAX_RELATIVE_DISTANCE: f64 = 0.25;
+
+/// Tags shorter than this (without dashes) are not matched by prefix or edit
+/// distance, since e.g. `+ai` and `+ui` differ in one letter but mean different things
+const MIN_FUZZY_LEN: usize = 4;
+
+/// A group of tags that are probably meant to be the same
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagCluster {
+    /// The tags and the number of notes with each, most used first
+    pub tags: Vec<(String, usize)>,
+}
+
+impl TagCluster {
+    /// The tag to merge the others into, which is the most used one
+    pub fn target(&self) -> &str {
+        &self.tags[0].0
+    }
+
+    /// The tags to replace with the target
+    pub fn sources(&self) -> &[(String, usize)] {
+        &self.tags[1..]
+    }
+
+    /// The number of notes with any of the tags
+    ///
+    /// Notes with several of the tags are counted once for each of them.
+    pub fn total(&self) -> usize {
+        self.tags.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Find the clusters of near-duplicate tags, most used first
+///
+/// `tags` are the tag names with the number of notes that have each of them.
+/// Tags without near-duplicates are not part of any cluster.
+pub fn find_tag_clusters(tags: &[(String, usize)]) -> Vec<TagCluster> {
+    // Union-find over the indices of the tags
+    let mut parent: Vec<usize> = (0..tags.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for i in 0..tags.len() {
+        for j in i + 1..tags.len() {
+            if are_near_duplicates(&tags[i].0, &tags[j].0) {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<(String, usize)>> = vec![Vec::new(); tags.len()];
+    for (i, tag) in tags.iter().enumerate() {
+        let r = root(&mut parent, i);
+        groups[r].push(tag.clone());
+    }
+
+    let mut clusters: Vec<TagCluster> = groups
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .map(|mut tags| {
+            // Ties go to the shorter, then alphabetically first tag
+            tags.sort_by(|(a, a_count), (b, b_count)| {
+                b_count
+                    .cmp(a_count)
+                    .then(a.len().cmp(&b.len()))
+                    .then(a.cmp(b))
+            });
+            TagCluster { tags }
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| {
+        b.total()
+            .cmp(&a.total())
+            .then_with(|| a.target().cmp(b.target()))
+    });
+    clusters
+}
+
+/// Whether two tags are probably meant to be the same
+fn are_near_duplicates(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().filter(|c| *c != '-').collect();
+    let b: Vec<char> = b.chars().filter(|c| *c != '-').collect();
+    if a == b {
+        return true;
+    }
+
+    if is_numeronym_of(&a, &b) || is_numeronym_of(&b, &a) {
+        return true;
+    }
+
+    let (shorter, longer) = if a.len() <= b.len() {
+        (&a, &b)
+    } else {
+        (&b, &a)
+    };
+    if shorter.len() < MIN_FUZZY_LEN {
+        return false;
+    }
+    if longer.starts_with(shorter) {
+        return true;
+    }
+
+    let distance = levenshtein(&a, &b);
+    distance as f64 / longer.len() as f64 <= MAX_RELATIVE_DISTANCE
+}
+
+/// Whether `short` abbreviates `long` with the number of letters in between,
+/// like `k8s` for `kubernetes` or `i18n` for `internationalization`
+fn is_numeronym_of(short: &[char], long: &[char]) -> bool {
+    let [first, digits @ .., last] = short else {
+        return false;
+    };
+    if digits.is_empty() || !digits.iter().all(char::is_ascii_digit) {
+        return false;
+    }
+
+    let digits: String = digits.iter().collect();
+    long.len() > 2
+        && long.first() == Some(first)
+        && long.last() == Some(last)
+        && digits.parse::<usize>().ok() == Some(long.len() - 2)
+}
+
+/// The number of single-character insertions, deletions and substitutions
+/// to turn `a` into `b`
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(tags: &[(&str, usize)]) -> Vec<(String, usize)> {
+        tags.iter()
+            .map(|(tag, count)| (tag.to_string(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_tag_clusters() {
+        let tags = counts(&[
+            ("k8s", 5),
+            ("kubernetes", 12),
+            ("kube", 2),
+            ("standup", 3),
+            ("stand-up", 7),
+            ("meeting", 4),
+            ("meetings", 1),
+            ("ai", 6),
+            ("ui", 6),
+            ("work", 20),
+            ("rust", 9),
+            ("i18n", 1),
+        ]);
+
+        let clusters = find_tag_clusters(&tags);
+        assert_eq!(
+            clusters,
+            vec![
+                TagCluster {
+                    tags: counts(&[("kubernetes", 12), ("k8s", 5), ("kube", 2)])
+                },
+                TagCluster {
+                    tags: counts(&[("stand-up", 7), ("standup", 3)])
+                },
+                TagCluster {
+                    tags: counts(&[("meeting", 4), ("meetings", 1)])
+                },
+            ]
+        );
+
+        assert_eq!(clusters[0].target(), "kubernetes");
+        assert_eq!(
+            clusters[0].sources(),
+            &counts(&[("k8s", 5), ("kube", 2)])[..]
+        );
+        assert_eq!(clusters[0].total(), 19);
+
+        assert!(find_tag_clusters(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_are_near_duplicates() {
+        assert!(are_near_duplicates("stand-up", "standup"));
+        assert!(are_near_duplicates("k8s", "kubernetes"));
+        assert!(are_near_duplicates("i18n", "internationalization"));
+        assert!(are_near_duplicates("kube", "kubernetes"));
+        assert!(are_near_duplicates("deploy", "deploys"));
+        assert!(are_near_duplicates("postgres", "postgre"));
+
+        // Short tags must match exactly without dashes
+        assert!(!are_near_duplicates("ai", "ui"));
+        assert!(!are_near_duplicates("bug", "bugs"));
+        assert!(!are_near_duplicates("k9s", "kubernetes"));
+        assert!(!are_near_duplicates("rust", "trust-me"));
+        assert!(!are_near_duplicates("todo", "done"));
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+        assert_eq!(levenshtein(&chars("same"), &chars("same")), 0);
+    }
+}
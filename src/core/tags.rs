@@ -1,8 +1,11 @@
 //! Tag implementation for notelog
 
+use crate::config;
+use crate::constants::MAX_TAGS_PER_NOTE;
 use crate::error::{NotelogError, Result, TagError};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use unicode_normalization::UnicodeNormalization;
 
 /// An opaque wrapper type that represents a valid tag
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -10,9 +13,21 @@ pub struct Tag(String);
 
 impl Tag {
     /// Create a new tag from a string, validating it in the process
+    ///
+    /// Tags may contain Unicode letters if the `unicode_tags` setting is on.
     pub fn new(input: &str) -> Result<Self> {
+        Self::with_unicode(input, config::get().unicode_tags)
+    }
+
+    /// Create a new tag from a string, allowing Unicode letters and digits or
+    /// only a-z and 0-9
+    ///
+    /// Unicode tags are lowercased and normalized to NFC, so that composed
+    /// and decomposed forms of the same letter make the same tag.
+    pub fn with_unicode(input: &str, unicode: bool) -> Result<Self> {
         // Remove the '+' prefix if present
         let tag = input.strip_prefix('+').unwrap_or(input).to_lowercase();
+        let tag = if unicode { tag.nfc().collect() } else { tag };
 
         if tag.is_empty() {
             return Err(NotelogError::TagError(TagError::Empty));
@@ -22,11 +37,16 @@ impl Tag {
             return Err(NotelogError::TagError(TagError::InvalidDashPosition(tag)));
         }
 
-        // Check if tag contains only valid characters (a-z, 0-9, -)
-        if !tag
-            .chars()
-            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
-        {
+        // Check if tag contains only valid characters (a-z, 0-9, -), or
+        // Unicode letters and digits without an uppercase form
+        let valid_char = |c: char| {
+            if unicode {
+                (c.is_alphanumeric() && !c.is_uppercase()) || c == '-'
+            } else {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'
+            }
+        };
+        if !tag.chars().all(valid_char) {
             return Err(NotelogError::TagError(TagError::InvalidCharacters(tag)));
         }
 
@@ -44,15 +64,99 @@ impl fmt::Display for Tag {
     }
 }
 
+/// Suggest a tag for a tag of several words, such as `+"code review"`
+///
+/// The words are joined with dashes, e.g. `code-review`. Returns `None` if the
+/// input, without the `+` and surrounding quotes, is not several words, or if
+/// the words do not make a valid tag.
+pub fn multi_word_tag_suggestion(input: &str) -> Option<String> {
+    let phrase = tag_phrase(input);
+    if phrase.split_whitespace().count() < 2 {
+        return None;
+    }
+
+    let suggestion = phrase
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    Tag::new(&suggestion).ok().map(|tag| tag.0)
+}
+
+/// The words of a tag, without the `+` and surrounding quotes
+fn tag_phrase(input: &str) -> &str {
+    let phrase = input.strip_prefix('+').unwrap_or(input).trim();
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| phrase.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(phrase)
+}
+
+/// Find a tag of several words in quotes, such as `+"code review"`, in a text
+pub fn find_multi_word_tag(text: &str) -> Option<&str> {
+    text.match_indices('+').find_map(|(i, _)| {
+        // The tag must start a word
+        if text[..i]
+            .chars()
+            .next_back()
+            .is_some_and(|c| !c.is_whitespace() && c != '(')
+        {
+            return None;
+        }
+
+        let rest = &text[i + 1..];
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = rest[1..].find(quote)? + 2;
+        let tag = &text[i..i + 1 + end];
+        multi_word_tag_suggestion(tag).map(|_| tag)
+    })
+}
+
+/// Replace tags of several words in command line arguments with their dashed form
+///
+/// For example, the argument `+code review` (from `+"code review"` in the
+/// shell) becomes `+code-review`. Other arguments are kept as they are.
+pub fn fix_multi_word_tags(args: &[String]) -> Vec<String> {
+    args.iter()
+        .map(|arg| {
+            multi_word_tag_suggestion(arg)
+                .filter(|_| arg.starts_with('+'))
+                .map(|suggestion| format!("+{}", suggestion))
+                .unwrap_or_else(|| arg.clone())
+        })
+        .collect()
+}
+
+/// The error for a tag of several words, with the dashed form as suggestion
+pub(crate) fn multi_word_tag_error(input: &str) -> Option<TagError> {
+    multi_word_tag_suggestion(input).map(|suggestion| TagError::MultiWord {
+        tag: tag_phrase(input).to_string(),
+        suggestion,
+    })
+}
+
 /// Extract tags from command line arguments
+///
+/// Duplicate tags are only returned once. Returns an error if more than
+/// MAX_TAGS_PER_NOTE distinct tags are given, or if a tag has several words
+/// (see [`fix_multi_word_tags`]).
 pub fn extract_tags_from_args(args: &[String]) -> Result<(Vec<Tag>, Vec<String>)> {
     let mut tags = Vec::new();
     let mut non_tag_args = Vec::new();
 
     for arg in args {
         if arg.starts_with('+') {
+            if let Some(e) = multi_word_tag_error(arg) {
+                return Err(e.into());
+            }
             match Tag::new(arg) {
-                Ok(tag) => tags.push(tag),
+                Ok(tag) => {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
                 Err(e) => return Err(e),
             }
         } else {
@@ -60,6 +164,10 @@ pub fn extract_tags_from_args(args: &[String]) -> Result<(Vec<Tag>, Vec<String>)
         }
     }
 
+    if tags.len() > MAX_TAGS_PER_NOTE {
+        return Err(TagError::TooMany(tags.len()).into());
+    }
+
     Ok((tags, non_tag_args))
 }
 
@@ -99,6 +207,39 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_unicode_tags() {
+        // Composed and decomposed input make the same tag
+        let composed = Tag::with_unicode("+kundengespr\u{e4}ch", true).unwrap();
+        let decomposed = Tag::with_unicode("+kundengespra\u{308}ch", true).unwrap();
+        assert_eq!(composed, decomposed);
+        assert_eq!(composed.as_str(), "kundengespräch");
+
+        assert_eq!(
+            Tag::with_unicode("+ÜBERSTUNDEN", true).unwrap().as_str(),
+            "überstunden"
+        );
+        assert_eq!(
+            Tag::with_unicode("+日本語", true).unwrap().as_str(),
+            "日本語"
+        );
+        assert_eq!(
+            Tag::with_unicode("+foo-bar", true).unwrap().as_str(),
+            "foo-bar"
+        );
+
+        // Whitespace and punctuation other than dashes are still not allowed
+        for invalid in ["+foo bar", "+foo_bar", "+foo.bar", "+foo\u{2014}bar"] {
+            assert!(matches!(
+                Tag::with_unicode(invalid, true).unwrap_err(),
+                NotelogError::TagError(TagError::InvalidCharacters(_))
+            ));
+        }
+
+        // Without the setting, tags are ASCII only
+        assert!(Tag::with_unicode("+kundengespräch", false).is_err());
+    }
+
     #[test]
     fn test_tag_display() {
         let tag = Tag::new("+foo").unwrap();
@@ -125,4 +266,52 @@ mod tests {
         let args = vec!["+foo".to_string(), "+foo-".to_string()];
         assert!(extract_tags_from_args(&args).is_err());
     }
+
+    #[test]
+    fn test_multi_word_tags() {
+        assert_eq!(
+            multi_word_tag_suggestion("+\"Code Review\"").as_deref(),
+            Some("code-review")
+        );
+        assert_eq!(
+            multi_word_tag_suggestion("+code  review, part 2").as_deref(),
+            Some("code-review-part-2")
+        );
+        assert_eq!(multi_word_tag_suggestion("+\"review\""), None);
+        assert_eq!(multi_word_tag_suggestion("+review"), None);
+
+        // The shell removes the quotes of `+"code review"`
+        let args = vec!["Met".to_string(), "+code review".to_string()];
+        let e = extract_tags_from_args(&args).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Tag validation error: Tag 'code review' cannot contain spaces; use dashes instead, e.g. +code-review"
+        );
+
+        let (tags, non_tags) = extract_tags_from_args(&fix_multi_word_tags(&args)).unwrap();
+        assert_eq!(tags, vec![Tag::new("code-review").unwrap()]);
+        assert_eq!(non_tags, vec!["Met"]);
+
+        assert_eq!(
+            find_multi_word_tag("deploy +\"code review\" +ops"),
+            Some("+\"code review\"")
+        );
+        assert_eq!(find_multi_word_tag("deploy +\"ops\" a+\"b c\""), None);
+    }
+
+    #[test]
+    fn test_extract_tags_from_args_limit() {
+        // Duplicate tags are collapsed and don't count towards the limit
+        let mut args: Vec<String> = (0..MAX_TAGS_PER_NOTE).map(|i| format!("+t{}", i)).collect();
+        args.push("+t0".to_string());
+        let (tags, _) = extract_tags_from_args(&args).unwrap();
+        assert_eq!(tags.len(), MAX_TAGS_PER_NOTE);
+
+        // One tag too many is an error
+        args.push("+one-too-many".to_string());
+        assert!(matches!(
+            extract_tags_from_args(&args).unwrap_err(),
+            NotelogError::TagError(TagError::TooMany(n)) if n == MAX_TAGS_PER_NOTE + 1
+        ));
+    }
 }
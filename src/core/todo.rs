@@ -0,0 +1,121 @@
+//! Tasks tracked with the +todo and +done tags
+
+use chrono::{DateTime, Local};
+
+use crate::core::note::Note;
+use crate::core::tags::Tag;
+use crate::error::Result;
+
+/// The tag of open tasks
+pub const TODO_TAG: &str = "todo";
+
+/// The tag of finished tasks
+pub const DONE_TAG: &str = "done";
+
+/// Which tasks to list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoFilter {
+    /// Notes tagged +todo
+    Open,
+    /// Notes tagged +done
+    Done,
+    /// Both
+    All,
+}
+
+impl TodoFilter {
+    /// Get the filter for the `--done-only` and `--all` options
+    pub fn from_flags(done_only: bool, all: bool) -> Self {
+        if all {
+            TodoFilter::All
+        } else if done_only {
+            TodoFilter::Done
+        } else {
+            TodoFilter::Open
+        }
+    }
+
+    /// The tags a note must have one of to be listed
+    pub fn tags(&self) -> Vec<Tag> {
+        let names: &[&str] = match self {
+            TodoFilter::Open => &[TODO_TAG],
+            TodoFilter::Done => &[DONE_TAG],
+            TodoFilter::All => &[TODO_TAG, DONE_TAG],
+        };
+
+        names
+            .iter()
+            .map(|name| Tag::new(name).expect("valid tag"))
+            .collect()
+    }
+}
+
+/// Mark a note as done by replacing its +todo tag with +done
+pub fn mark_done(note: &mut Note) -> Result<()> {
+    note.update_tags([Tag::new(DONE_TAG)?], [Tag::new(TODO_TAG)?])
+}
+
+/// Check whether a note is tagged +done
+pub fn is_done(note: &Note) -> bool {
+    note.frontmatter()
+        .tags()
+        .iter()
+        .any(|tag| tag.as_str() == DONE_TAG)
+}
+
+/// Get the number of whole days since the note was created
+pub fn age_in_days(created: &DateTime<Local>, now: &DateTime<Local>) -> i64 {
+    (now.date_naive() - created.date_naive()).num_days().max(0)
+}
+
+/// Describe the age of a note, e.g. "14 days old"
+pub fn format_age(days: i64) -> String {
+    match days {
+        0 => "today".to_string(),
+        1 => "1 day old".to_string(),
+        days => format!("{} days old", days),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frontmatter::Frontmatter;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_mark_done() {
+        let tags = vec![Tag::new("todo").unwrap(), Tag::new("work").unwrap()];
+        let mut note = Note::new(Frontmatter::with_tags(tags), "# Task".to_string());
+        assert!(!is_done(&note));
+
+        mark_done(&mut note).unwrap();
+        assert!(is_done(&note));
+        assert_eq!(note.tags_as_strings(), vec!["work", "done"]);
+
+        // Marking a note twice does nothing
+        mark_done(&mut note).unwrap();
+        assert_eq!(note.tags_as_strings(), vec!["work", "done"]);
+    }
+
+    #[test]
+    fn test_age() {
+        let created = Local.with_ymd_and_hms(2025, 5, 1, 23, 0, 0).unwrap();
+
+        let now = Local.with_ymd_and_hms(2025, 5, 15, 1, 0, 0).unwrap();
+        assert_eq!(age_in_days(&created, &now), 14);
+        assert_eq!(format_age(14), "14 days old");
+
+        assert_eq!(age_in_days(&created, &created), 0);
+        assert_eq!(format_age(0), "today");
+        assert_eq!(format_age(1), "1 day old");
+    }
+
+    #[test]
+    fn test_todo_filter() {
+        assert_eq!(TodoFilter::from_flags(false, false), TodoFilter::Open);
+        assert_eq!(TodoFilter::from_flags(true, false), TodoFilter::Done);
+        assert_eq!(TodoFilter::from_flags(false, true), TodoFilter::All);
+        assert_eq!(TodoFilter::All.tags().len(), 2);
+    }
+}
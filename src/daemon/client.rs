@@ -0,0 +1,220 @@
+//! Sending the requests of the commands to a running daemon
+//!
+//! Each function returns `None` if no daemon is running for the notes
+//! directory, and the command then opens the database itself. On platforms
+//! other than Unix, there is never a daemon.
+
+#[cfg(unix)]
+pub use unix::{Client, list, search, show, status, stop};
+
+#[cfg(not(unix))]
+pub use other::{list, search, show, status, stop};
+
+#[cfg(not(unix))]
+mod other {
+    use std::path::{Path, PathBuf};
+
+    use crate::core::note::Note;
+    use crate::daemon::protocol::{DaemonStatus, SearchRequest, SearchResults};
+    use crate::error::Result;
+
+    pub fn search(_notes_dir: &Path, _request: &SearchRequest) -> Option<Result<SearchResults>> {
+        None
+    }
+
+    pub fn list<F>(_notes_dir: &Path, _f: F) -> Option<Result<()>>
+    where
+        F: FnMut(&str, Note) -> Result<()>,
+    {
+        None
+    }
+
+    pub fn show(_notes_dir: &Path, _id: &str) -> Option<Result<(PathBuf, Option<String>)>> {
+        None
+    }
+
+    pub fn status(_notes_dir: &Path) -> Option<Result<DaemonStatus>> {
+        None
+    }
+
+    pub fn stop(_notes_dir: &Path) -> Option<Result<()>> {
+        None
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use crate::core::note::Note;
+    use crate::daemon::protocol::{DaemonStatus, Request, Response, SearchRequest, SearchResults};
+    use crate::daemon::socket_path;
+    use crate::db::SearchHit;
+    use crate::error::{NotelogError, Result};
+
+    /// How long to wait for each line of a response before giving up on the daemon
+    const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Search the notes, returning the results with their shortest unique ID
+    /// prefixes and the total number of matching notes
+    pub fn search(notes_dir: &Path, request: &SearchRequest) -> Option<Result<SearchResults>> {
+        Client::connect(notes_dir).map(|client| client.search(request))
+    }
+
+    /// List the notes, newest first, calling `f` for each like `Database::list_notes`
+    pub fn list<F>(notes_dir: &Path, f: F) -> Option<Result<()>>
+    where
+        F: FnMut(&str, Note) -> Result<()>,
+    {
+        Client::connect(notes_dir).map(|client| client.list(f))
+    }
+
+    /// Find the file of the note with an ID prefix, relative to the notes
+    /// directory, and the shortest unique prefix of its ID
+    pub fn show(notes_dir: &Path, id: &str) -> Option<Result<(PathBuf, Option<String>)>> {
+        Client::connect(notes_dir).map(|client| client.show(id))
+    }
+
+    /// Get the state of the daemon
+    pub fn status(notes_dir: &Path) -> Option<Result<DaemonStatus>> {
+        Client::connect(notes_dir).map(|client| client.status())
+    }
+
+    /// Ask the daemon to shut down
+    pub fn stop(notes_dir: &Path) -> Option<Result<()>> {
+        Client::connect(notes_dir).map(|client| client.stop())
+    }
+
+    /// A connection to a daemon, for a single request
+    pub struct Client {
+        stream: BufReader<UnixStream>,
+    }
+
+    impl Client {
+        /// Connect to the daemon for the notes directory, if one is running
+        pub fn connect(notes_dir: &Path) -> Option<Self> {
+            Self::connect_to(&socket_path(notes_dir)?)
+        }
+
+        /// Connect to the daemon listening on a socket
+        ///
+        /// A socket that nobody listens on is left over from a daemon that
+        /// did not shut down cleanly, and is removed.
+        pub fn connect_to(path: &Path) -> Option<Self> {
+            let stream = match UnixStream::connect(path) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::ConnectionRefused {
+                        let _ = fs::remove_file(path);
+                    }
+                    return None;
+                }
+            };
+            stream.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+
+            Some(Self {
+                stream: BufReader::new(stream),
+            })
+        }
+
+        pub fn search(mut self, request: &SearchRequest) -> Result<SearchResults> {
+            match self.request(&Request::Search(request.clone()))? {
+                Response::Search { hits, total } => {
+                    let hits = hits
+                        .into_iter()
+                        .map(|hit| {
+                            let note = Note::from_str(&hit.note)?;
+                            Ok((
+                                hit.short_id,
+                                SearchHit {
+                                    note,
+                                    rank: hit.rank,
+                                    filepath: hit.filepath,
+                                },
+                            ))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok((hits, total))
+                }
+                response => Err(unexpected(response)),
+            }
+        }
+
+        pub fn list<F>(mut self, mut f: F) -> Result<()>
+        where
+            F: FnMut(&str, Note) -> Result<()>,
+        {
+            self.send(&Request::List)?;
+            loop {
+                match self.receive()? {
+                    Response::Row { short_id, note } => f(&short_id, Note::from_str(&note)?)?,
+                    Response::End => return Ok(()),
+                    response => return Err(unexpected(response)),
+                }
+            }
+        }
+
+        pub fn show(mut self, id: &str) -> Result<(PathBuf, Option<String>)> {
+            match self.request(&Request::Show { id: id.to_string() })? {
+                Response::Show { filepath, short_id } => Ok((filepath, short_id)),
+                response => Err(unexpected(response)),
+            }
+        }
+
+        pub fn status(mut self) -> Result<DaemonStatus> {
+            match self.request(&Request::Status)? {
+                Response::Status(status) => Ok(status),
+                response => Err(unexpected(response)),
+            }
+        }
+
+        pub fn stop(mut self) -> Result<()> {
+            match self.request(&Request::Stop)? {
+                Response::Stopping => Ok(()),
+                response => Err(unexpected(response)),
+            }
+        }
+
+        /// Send a request and read the single response to it
+        fn request(&mut self, request: &Request) -> Result<Response> {
+            self.send(request)?;
+            self.receive()
+        }
+
+        fn send(&mut self, request: &Request) -> Result<()> {
+            let mut line = serde_json::to_string(request)
+                .map_err(|e| NotelogError::DaemonFailed(e.to_string()))?;
+            line.push('\n');
+            self.stream.get_mut().write_all(line.as_bytes())?;
+            Ok(())
+        }
+
+        /// Read the next response, turning an error response into an error
+        fn receive(&mut self) -> Result<Response> {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(NotelogError::DaemonFailed(
+                    "the daemon closed the connection".to_string(),
+                ));
+            }
+
+            match serde_json::from_str(&line) {
+                Ok(Response::Error { message }) => Err(NotelogError::FromDaemon(message)),
+                Ok(response) => Ok(response),
+                Err(e) => Err(NotelogError::DaemonFailed(format!(
+                    "invalid response: {}",
+                    e
+                ))),
+            }
+        }
+    }
+
+    fn unexpected(response: Response) -> NotelogError {
+        NotelogError::DaemonFailed(format!("unexpected response: {:?}", response))
+    }
+}
@@ -0,0 +1,237 @@
+//! A long-running process that keeps the search index open
+//!
+//! `notelog daemon` opens the database, keeps the index up to date like the
+//! MCP server does, and answers the queries of `search`, `list` and `cat`
+//! on a Unix socket. This saves the commands from opening the database and
+//! checking the index on every run, which is slow on large notes directories
+//! (especially on network shares).
+
+pub mod client;
+pub mod protocol;
+#[cfg(unix)]
+mod server;
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use dirs::cache_dir;
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+use protocol::PROTOCOL_VERSION;
+
+/// Get the path of the socket of the daemon for a notes directory
+///
+/// The sockets are in `NOTELOG_DAEMON_DIR` or the cache directory, named
+/// after the protocol version and a hash of the notes directory.
+pub fn socket_path(notes_dir: &Path) -> Option<PathBuf> {
+    let dir = env::var("NOTELOG_DAEMON_DIR")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| cache_dir().map(|p| p.join("notelog")))?;
+
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(notes_dir.as_os_str().as_encoded_bytes())
+    );
+    Some(dir.join(format!("daemon-v{}-{}.sock", PROTOCOL_VERSION, &hash[..16])))
+}
+
+/// Run the daemon for a notes directory until it is stopped
+#[cfg(unix)]
+pub fn run_daemon(notes_dir: &Path) -> Result<()> {
+    use std::fs;
+
+    use tokio::net::UnixListener;
+    use tokio::signal::unix::{SignalKind, signal};
+
+    use crate::db::Database;
+    use crate::error::NotelogError;
+    use crate::utils::{create_dir_all_with_permissions, restrict_file_permissions};
+
+    let path = socket_path(notes_dir).ok_or_else(|| {
+        NotelogError::DaemonFailed("there is no cache directory for the socket".to_string())
+    })?;
+    if let Some(dir) = path.parent() {
+        create_dir_all_with_permissions(dir, true)?;
+    }
+
+    // A socket that nobody answers on is left over from a crash
+    if client::Client::connect_to(&path).is_some() {
+        return Err(NotelogError::DaemonFailed(format!(
+            "a daemon is already running for {}",
+            notes_dir.display()
+        )));
+    }
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await?;
+        db.start_indexing_task().await?;
+        db.start_monitoring_task().await?;
+        db.start_verification_task().await?;
+
+        // Only the owner may connect, since the daemon answers with the notes
+        let listener = UnixListener::bind(&path)?;
+        restrict_file_permissions(&path)?;
+        eprintln!("Serving {} on {}", notes_dir.display(), path.display());
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        let result = tokio::select! {
+            result = server::serve(listener, db, notes_dir.to_path_buf()) => result,
+            _ = tokio::signal::ctrl_c() => Ok(()),
+            _ = terminate.recv() => Ok(()),
+        };
+
+        let _ = fs::remove_file(&path);
+        result
+    })
+}
+
+/// Run the daemon for a notes directory until it is stopped
+#[cfg(not(unix))]
+pub fn run_daemon(_notes_dir: &Path) -> Result<()> {
+    Err(crate::error::NotelogError::DaemonFailed(
+        "the daemon is only available on Unix".to_string(),
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use crate::core::frontmatter::Frontmatter;
+    use crate::core::note::Note;
+    use crate::daemon::client::Client;
+    use crate::daemon::protocol::SearchRequest;
+    use crate::db::Database;
+    use crate::error::NotelogError;
+    use tempfile::TempDir;
+    use tokio::net::UnixListener;
+
+    #[test]
+    fn test_socket_path() {
+        let path = socket_path(Path::new("/home/user/NoteLog")).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with(&format!("daemon-v{}-", PROTOCOL_VERSION)));
+        assert!(name.ends_with(".sock"));
+
+        // Each notes directory has its own daemon
+        assert_ne!(path, socket_path(Path::new("/home/user/Work")).unwrap());
+    }
+
+    #[test]
+    fn test_daemon_answers_requests() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path().join("notes");
+        fs::create_dir(&notes_dir).unwrap();
+        let socket = temp_dir.path().join("daemon.sock");
+
+        for (id, content) in [
+            ("abcd1234efgh0000", "# Airships\n\nThe zeppelin landed."),
+            ("abcd5678efgh0000", "# Trains\n\nThe train was late."),
+        ] {
+            let yaml = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id);
+            let frontmatter = yaml.parse::<Frontmatter>().unwrap();
+            Note::new(frontmatter, content.to_string())
+                .save(&notes_dir, None)
+                .unwrap();
+        }
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let db = rt.block_on(async {
+            let db = Database::initialize(&notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            db
+        });
+        let listener = rt.block_on(async { UnixListener::bind(&socket).unwrap() });
+        let server = rt.spawn(server::serve(listener, db, notes_dir.clone()));
+        let connect = || Client::connect_to(&socket).unwrap();
+
+        // Search
+        let request = SearchRequest {
+            query: "zeppelin".to_string(),
+            limit: 25,
+            offset: 0,
+            order: "relevance".to_string(),
+            path: None,
+        };
+        let (hits, total) = connect().search(&request).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(hits[0].0, "abcd1");
+        assert_eq!(hits[0].1.note.extract_title(), "Airships");
+        assert!(hits[0].1.filepath.ends_with("Airships.md"));
+
+        // Invalid searches fail as they would without the daemon
+        let request = SearchRequest {
+            order: "random".to_string(),
+            ..request
+        };
+        let err = connect().search(&request).unwrap_err();
+        assert!(matches!(err, NotelogError::FromDaemon(_)));
+        assert!(err.to_string().contains("Unknown order 'random'"));
+
+        // List
+        let mut listed = Vec::new();
+        connect()
+            .list(|short_id, note| {
+                listed.push((short_id.to_string(), note.extract_title()));
+                Ok(())
+            })
+            .unwrap();
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![
+                ("abcd1".to_string(), "Airships".to_string()),
+                ("abcd5".to_string(), "Trains".to_string())
+            ]
+        );
+
+        // Show
+        let (filepath, short_id) = connect().show("ABCD5").unwrap();
+        assert!(filepath.to_string_lossy().ends_with("Trains.md"));
+        assert_eq!(short_id.as_deref(), Some("abcd5"));
+        let err = connect().show("zz").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("No note found with ID prefix 'zz'")
+        );
+
+        // Status
+        let status = connect().status().unwrap();
+        assert_eq!(status.pid, std::process::id());
+        assert_eq!(status.notes, 2);
+        assert_eq!(status.notes_dir, notes_dir);
+        // The invalid search was rejected before it reached the database
+        assert_eq!(status.metrics.search.count, 1);
+        assert_eq!(status.metrics.indexing_pass.count, 1);
+
+        // Stop; as in `run_daemon`, the runtime is shut down once the server
+        // returns, which must not cut off the response
+        let stopping = std::thread::spawn(move || Client::connect_to(&socket).unwrap().stop());
+        rt.block_on(server).unwrap().unwrap();
+        drop(rt);
+        stopping.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_stale_socket_is_removed() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket = temp_dir.path().join("daemon.sock");
+
+        // A socket that was bound, but is no longer listened on
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+
+        assert!(Client::connect_to(&socket).is_none());
+        assert!(!socket.exists());
+
+        // Without a socket, there is no daemon
+        assert!(Client::connect_to(&socket).is_none());
+    }
+}
@@ -0,0 +1,155 @@
+//! The messages between the daemon and the commands that use it
+//!
+//! Each message is a JSON object on a line of its own. A client sends one
+//! request per connection; the daemon answers with one response, or for
+//! `list` with a `row` per note followed by `end`.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Metrics, SearchHit, SearchOrder, SearchQuery};
+use crate::error::Result;
+
+/// The version of the protocol, which is part of the socket name so that
+/// commands never talk to a daemon of another version
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The results of a search with the shortest unique prefixes of their IDs,
+/// and the total number of matching notes
+pub type SearchResults = (Vec<(String, SearchHit)>, usize);
+
+/// A request to the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Search the notes
+    Search(SearchRequest),
+    /// List all notes, newest first
+    List,
+    /// Find the file of the note with an ID prefix
+    Show { id: String },
+    /// Report the state of the daemon and its index
+    Status,
+    /// Shut the daemon down
+    Stop,
+}
+
+/// The parameters of a search, as given on the command line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    pub limit: usize,
+    pub offset: usize,
+    pub order: String,
+    pub path: Option<String>,
+}
+
+impl SearchRequest {
+    /// The search to run on the database, after checking it
+    pub fn to_query(&self) -> Result<SearchQuery> {
+        let mut search = SearchQuery::new(self.query.as_str())
+            .limit(self.limit)
+            .offset(self.offset)
+            .order(self.order.parse::<SearchOrder>()?);
+        if let Some(prefix) = &self.path {
+            search = search.path_prefix(prefix.as_str());
+        }
+        search.validate()?;
+
+        Ok(search)
+    }
+}
+
+/// A response of the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    /// The results of a search, with the total number of matching notes
+    Search { hits: Vec<Hit>, total: usize },
+    /// A note of a list
+    Row { short_id: String, note: String },
+    /// The end of a list
+    End,
+    /// The file of a note, relative to the notes directory
+    Show {
+        filepath: PathBuf,
+        short_id: Option<String>,
+    },
+    /// The state of the daemon
+    Status(DaemonStatus),
+    /// The daemon is shutting down
+    Stopping,
+    /// The request failed
+    Error { message: String },
+}
+
+/// A search result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hit {
+    /// The shortest unique ID prefix, empty for notes without an ID
+    pub short_id: String,
+    pub filepath: String,
+    pub rank: f64,
+    /// The note as indexed, with its frontmatter
+    pub note: String,
+}
+
+/// The state of a running daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub notes_dir: PathBuf,
+    pub notes: usize,
+    pub failed_files: usize,
+    /// The durations of the searches and indexing since the daemon started
+    pub metrics: Metrics,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_single_lines() {
+        let request = Request::Search(SearchRequest {
+            query: "zeppelin\n+airships".to_string(),
+            limit: 25,
+            offset: 0,
+            order: "newest".to_string(),
+            path: None,
+        });
+        let json = serde_json::to_string(&request).unwrap();
+        assert!(!json.contains('\n'));
+        assert!(json.starts_with(r#"{"type":"search","query":"#));
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+
+        let json = serde_json::to_string(&Request::Show {
+            id: "abc".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"type":"show","id":"abc"}"#);
+        assert_eq!(
+            serde_json::to_string(&Response::End).unwrap(),
+            r#"{"type":"end"}"#
+        );
+    }
+
+    #[test]
+    fn test_search_request_to_query() {
+        let request = SearchRequest {
+            query: "zeppelin".to_string(),
+            limit: 25,
+            offset: 0,
+            order: "random".to_string(),
+            path: None,
+        };
+        assert!(request.to_query().is_err());
+
+        let request = SearchRequest {
+            order: "oldest".to_string(),
+            ..request
+        };
+        assert!(request.to_query().is_ok());
+    }
+}
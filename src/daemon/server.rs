@@ -0,0 +1,177 @@
+//! Answering the requests of the commands
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{Notify, mpsc};
+
+use crate::commands::resolve::resolve_note_path;
+use crate::core::note::Note;
+use crate::daemon::protocol::{DaemonStatus, Hit, Request, Response};
+use crate::db::Database;
+use crate::error::{NotelogError, Result};
+
+/// Answer the requests on `listener` until a client asks the daemon to stop
+///
+/// Each connection is answered in a task of its own, so that a slow client
+/// does not hold up the others.
+pub async fn serve(listener: UnixListener, db: Database, notes_dir: PathBuf) -> Result<()> {
+    let stop = Arc::new(Notify::new());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let db = db.clone();
+                let notes_dir = notes_dir.clone();
+                let stop = stop.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &db, &notes_dir, &stop).await {
+                        eprintln!("Error answering a request: {}", e);
+                    }
+                });
+            }
+            _ = stop.notified() => return Ok(()),
+        }
+    }
+}
+
+/// Read the request of a connection and write the response
+async fn handle_connection(
+    stream: UnixStream,
+    db: &Database,
+    notes_dir: &Path,
+    stop: &Notify,
+) -> Result<()> {
+    let (reader, writer) = stream.into_split();
+    let mut writer = BufWriter::new(writer);
+
+    let mut line = String::new();
+    BufReader::new(reader).read_line(&mut line).await?;
+    let request = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => request,
+        Err(e) => {
+            let message = format!("Invalid request: {}", e);
+            return write_response(&mut writer, &Response::Error { message }).await;
+        }
+    };
+
+    let response = match request {
+        Request::List => return list(&mut writer, db).await,
+        Request::Stop => {
+            // The runtime shuts down once notified, so the response goes first
+            let result = write_response(&mut writer, &Response::Stopping).await;
+            stop.notify_one();
+            return result;
+        }
+        request => answer(request, db, notes_dir)
+            .await
+            .unwrap_or_else(|e| Response::Error {
+                message: e.to_string(),
+            }),
+    };
+
+    write_response(&mut writer, &response).await
+}
+
+/// Answer a request that has a single response
+async fn answer(request: Request, db: &Database, notes_dir: &Path) -> Result<Response> {
+    match request {
+        Request::Search(request) => {
+            let (hits, total) = db.search_hits(&request.to_query()?).await?;
+
+            let mut results = Vec::with_capacity(hits.len());
+            for hit in hits {
+                results.push(Hit {
+                    short_id: short_id(db, &hit.note).await?.unwrap_or_default(),
+                    filepath: hit.filepath,
+                    rank: hit.rank,
+                    note: hit.note.to_string(),
+                });
+            }
+
+            Ok(Response::Search {
+                hits: results,
+                total,
+            })
+        }
+        Request::Show { id } => {
+            let filepath = resolve_note_path(db, &id).await?;
+            let note = Note::from_str(&fs::read_to_string(notes_dir.join(&filepath))?)?;
+
+            Ok(Response::Show {
+                filepath,
+                short_id: short_id(db, &note).await?,
+            })
+        }
+        Request::Status => Ok(Response::Status(DaemonStatus {
+            pid: std::process::id(),
+            notes_dir: notes_dir.to_path_buf(),
+            notes: db.note_count().await?,
+            failed_files: db.failed_files().await?.len(),
+            metrics: db.metrics(),
+        })),
+        Request::List | Request::Stop => unreachable!("answered by handle_connection"),
+    }
+}
+
+/// Write a row for each note as it is read from the database, then `end`
+async fn list<W: AsyncWrite + Unpin>(writer: &mut W, db: &Database) -> Result<()> {
+    // The rows are read in a callback that cannot wait for the client
+    let (rows, mut received) = mpsc::unbounded_channel();
+    let listed = async move {
+        db.list_notes(|short_id, note| {
+            let row = Response::Row {
+                short_id: short_id.to_string(),
+                note: note.to_string(),
+            };
+            // The client hung up, so the remaining rows are not needed
+            rows.send(row)
+                .map_err(|_| NotelogError::DaemonFailed("the client hung up".to_string()))
+        })
+        .await
+    };
+    let written = async {
+        while let Some(row) = received.recv().await {
+            write_message(writer, &row).await?;
+        }
+        Ok::<_, NotelogError>(())
+    };
+
+    let (listed, written) = tokio::join!(listed, written);
+    written?;
+    let end = match listed {
+        Ok(()) => Response::End,
+        Err(e) => Response::Error {
+            message: e.to_string(),
+        },
+    };
+    write_response(writer, &end).await
+}
+
+/// The shortest unique prefix of the ID of a note
+async fn short_id(db: &Database, note: &Note) -> Result<Option<String>> {
+    match note.frontmatter().id() {
+        Some(id) => Ok(Some(db.find_shortest_unique_id_prefix(id).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Write the last message of a response
+async fn write_response<W: AsyncWrite + Unpin>(writer: &mut W, response: &Response) -> Result<()> {
+    write_message(writer, response).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, response: &Response) -> Result<()> {
+    let mut line =
+        serde_json::to_string(response).map_err(|e| NotelogError::DaemonFailed(e.to_string()))?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Database backups
+
+use chrono::Local;
+use sqlx::{Pool, Sqlite};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::{DatabaseError, Result};
+
+/// Directory in the notes directory that daily backups are written to
+pub const BACKUPS_DIR: &str = ".backups";
+
+/// Filename prefix of daily backups; only these are pruned
+const DAILY_BACKUP_PREFIX: &str = "daily-";
+
+/// How often the daily backup task checks whether a new backup is due
+const DAILY_BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Write a snapshot of the database to a new SQLite file
+///
+/// Uses `VACUUM INTO`, which produces a consistent copy even while other
+/// connections write to the database. The target file must not exist yet.
+pub async fn backup_database(pool: &Pool<Sqlite>, path: &Path) -> Result<()> {
+    if path.exists() {
+        return Err(DatabaseError::Backup(format!("{} already exists", path.display())).into());
+    }
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy())
+        .execute(pool)
+        .await
+        .map_err(|e| DatabaseError::Backup(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Take today's daily backup in `backups_dir`, unless it already exists
+///
+/// Afterwards, old daily backups are pruned so that at most `keep` remain.
+///
+/// # Returns
+///
+/// The path of the new backup, or `None` if today's backup already existed.
+pub async fn create_daily_backup(
+    pool: &Pool<Sqlite>,
+    backups_dir: &Path,
+    keep: usize,
+) -> Result<Option<PathBuf>> {
+    let filename = format!(
+        "{}{}.db",
+        DAILY_BACKUP_PREFIX,
+        Local::now().format("%Y-%m-%d")
+    );
+    let path = backups_dir.join(filename);
+
+    if path.exists() {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(backups_dir)?;
+    backup_database(pool, &path).await?;
+    prune_backups(backups_dir, keep)?;
+
+    Ok(Some(path))
+}
+
+/// Delete the oldest daily backups in `backups_dir`, keeping the newest `keep`
+///
+/// Other files in the directory (e.g. backups made with `notelog backup`) are
+/// left alone.
+///
+/// # Returns
+///
+/// The paths of the deleted backups.
+pub fn prune_backups(backups_dir: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let mut backups: Vec<PathBuf> = fs::read_dir(backups_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "db")
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(DAILY_BACKUP_PREFIX))
+        })
+        .collect();
+
+    // The date in the filename sorts chronologically
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    let pruned: Vec<PathBuf> = backups.drain(..excess).collect();
+    for path in &pruned {
+        fs::remove_file(path)?;
+    }
+
+    Ok(pruned)
+}
+
+/// Take a daily backup for as long as the program runs
+pub async fn run_daily_backups(pool: Pool<Sqlite>, backups_dir: PathBuf, keep: usize) {
+    loop {
+        if let Err(e) = create_daily_backup(&pool, &backups_dir, keep).await {
+            eprintln!("Error backing up the database: {}", e);
+        }
+
+        tokio::time::sleep(DAILY_BACKUP_CHECK_INTERVAL).await;
+    }
+}
@@ -1,12 +1,12 @@
 //! Helper functions for database operations
 
-use chrono::{DateTime, Local};
-use rmcp::serde_json;
+use chrono::{DateTime, Days, Local, NaiveDate};
 use sqlx::{Pool, Sqlite, query_scalar};
 
+use crate::constants::{MAX_SEARCH_QUERY_CHARS, MAX_SEARCH_QUERY_DEPTH, MAX_SEARCH_QUERY_TERMS};
 use crate::core::frontmatter::Frontmatter;
 use crate::core::note::Note;
-use crate::core::tags::Tag;
+use crate::core::tags::{Tag, find_multi_word_tag, multi_word_tag_error};
 use crate::error::{DatabaseError, Result};
 
 /// Add date conditions to a SQL query string
@@ -49,29 +49,24 @@ pub fn add_date_conditions(
     query
 }
 
-/// Check if a date range is valid
+/// Get the end of the range of the IDs that start with a prefix
 ///
-/// A date range is valid if either:
-/// - Both before and after are None
-/// - Only one of before or after is Some
-/// - Both before and after are Some, and before >= after
-///
-/// # Parameters
-///
-/// * `before` - Optional DateTime to filter notes created before this time
-/// * `after` - Optional DateTime to filter notes created after this time
-///
-/// # Returns
-///
-/// true if the date range is valid, false otherwise
-pub fn is_valid_date_range(
-    before: Option<&DateTime<Local>>,
-    after: Option<&DateTime<Local>>,
-) -> bool {
-    match (before, after) {
-        (Some(before_date), Some(after_date)) => before_date >= after_date,
-        _ => true,
+/// All IDs that start with `prefix` are at least `prefix` and less than the
+/// returned string, so prefix lookups can scan a range of the `note_id` index.
+/// The last character is incremented: `ab` becomes `ac`, and `az` becomes
+/// `a{`, which sorts after every ID starting with `az` (IDs only contain 0-9
+/// and a-z), instead of rolling over to `b0`.
+pub fn id_prefix_end(prefix: &str) -> String {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    while let Some(last) = chars.pop() {
+        if let Some(next) = char::from_u32(last as u32 + 1) {
+            chars.push(next);
+            return chars.into_iter().collect();
+        }
     }
+
+    // Every ID starts with the empty prefix
+    char::MAX.to_string()
 }
 
 /// Count notes with an ID prefix
@@ -92,10 +87,11 @@ pub async fn count_notes_with_id_prefix(pool: &Pool<Sqlite>, id_prefix: &str) ->
         r#"
         SELECT COUNT(*)
         FROM notes
-        WHERE json_extract(metadata, '$.id') LIKE ? || '%'
+        WHERE note_id >= ? AND note_id < ?
         "#,
     )
     .bind(id_prefix)
+    .bind(id_prefix_end(id_prefix))
     .fetch_one(pool)
     .await
     .map_err(|e| DatabaseError::Query(e.to_string()).into())
@@ -148,6 +144,203 @@ pub fn json_to_note(metadata_json: &str, content: &str) -> Result<Note> {
     Ok(Note::new(frontmatter, content.to_string()))
 }
 
+/// Get the shortest unique prefix of an ID from its neighbours in sorted order
+///
+/// An ID prefix is unique if it is longer than the prefix the ID shares with
+/// the previous and the next ID. Like `find_shortest_unique_id_prefix`, at
+/// least 2 characters are returned.
+pub fn unique_id_prefix<'a>(id: &'a str, prev: Option<&str>, next: Option<&str>) -> &'a str {
+    const MIN_PREFIX_LENGTH: usize = 2;
+
+    let common_prefix_len = |other: Option<&str>| {
+        other.map_or(0, |other| {
+            id.bytes()
+                .zip(other.bytes())
+                .take_while(|(a, b)| a == b)
+                .count()
+        })
+    };
+
+    let len = (common_prefix_len(prev).max(common_prefix_len(next)) + 1)
+        .max(MIN_PREFIX_LENGTH)
+        .min(id.len());
+
+    &id[..len]
+}
+
+/// Get the last due date to list
+///
+/// Overdue notes are due before today; notes due within `n` days include the
+/// overdue ones.
+pub fn due_until(today: NaiveDate, overdue: bool, within_days: Option<u32>) -> Option<NaiveDate> {
+    if overdue {
+        today.pred_opt()
+    } else {
+        within_days.and_then(|days| today.checked_add_days(Days::new(days.into())))
+    }
+}
+
+/// Prefix of search terms that filter notes by their source
+const SOURCE_FILTER_PREFIX: &str = "source:";
+
+/// Prefix of search terms that filter notes by their language
+const LANG_FILTER_PREFIX: &str = "lang:";
+
+/// Extract `source:<value>` filters from a search query
+///
+/// Only top-level words are considered; a `source:` inside quotes or parentheses
+/// is left in the query. Returns the query without the filters and the list of
+/// source values. A note matches a source value if its source is equal to the
+/// value or starts with the value followed by a colon, so `source:mcp` also
+/// matches `mcp:claude-desktop`.
+///
+/// The filters always narrow the search down, so a filter after NOT or next to
+/// OR is an error; an AND next to a filter is dropped with it.
+pub fn extract_source_filters(query: &str) -> Result<(String, Vec<String>)> {
+    extract_prefixed_filters(query, SOURCE_FILTER_PREFIX)
+}
+
+/// Extract `lang:<code>` filters from a search query
+///
+/// Works like `extract_source_filters`. The language codes are lowercased,
+/// since the detected languages are stored as lowercase ISO 639-1 codes
+/// (e.g. `en`, `de`).
+pub fn extract_lang_filters(query: &str) -> Result<(String, Vec<String>)> {
+    let (query, languages) = extract_prefixed_filters(query, LANG_FILTER_PREFIX)?;
+    let languages = languages.iter().map(|lang| lang.to_lowercase()).collect();
+    Ok((query, languages))
+}
+
+/// Extract the values of top-level words starting with `prefix` from a query
+fn extract_prefixed_filters(query: &str, prefix: &str) -> Result<(String, Vec<String>)> {
+    let words = split_top_level_words(query);
+    let is_filter = |word: &str| word.strip_prefix(prefix).is_some_and(|v| !v.is_empty());
+
+    let mut values = Vec::new();
+    let mut remaining: Vec<&str> = Vec::new();
+    let mut after_filter = false;
+    for (i, &word) in words.iter().enumerate() {
+        if is_filter(word) {
+            let previous = i.checked_sub(1).map(|i| words[i]);
+            let next = words.get(i + 1).copied();
+            if previous == Some("NOT") {
+                return Err(DatabaseError::InvalidSearchQuery(format!(
+                    "'{}' cannot follow NOT; filters only narrow the search down",
+                    word
+                ))
+                .into());
+            }
+            if previous == Some("OR") || next == Some("OR") {
+                return Err(DatabaseError::InvalidSearchQuery(format!(
+                    "'{}' cannot be combined with OR; filters only narrow the search down",
+                    word
+                ))
+                .into());
+            }
+
+            values.push(word[prefix.len()..].to_string());
+            after_filter = true;
+            continue;
+        }
+
+        // An operator right after a removed filter has lost its left operand if
+        // the query starts there or another operator precedes it
+        let dangling = after_filter && remaining.last().is_none_or(|last| *last == "AND");
+        if dangling && word == "AND" {
+            after_filter = false;
+            continue;
+        }
+        if dangling && word == "NOT" {
+            return Err(DatabaseError::InvalidSearchQuery(
+                "NOT needs a search term before it, besides filters".to_string(),
+            )
+            .into());
+        }
+        remaining.push(word);
+        after_filter = false;
+    }
+
+    if values.is_empty() {
+        // Leave the query untouched if there are no filters
+        return Ok((query.to_string(), values));
+    }
+
+    // An AND before a filter at the end of the query has lost its right operand
+    if remaining.last() == Some(&"AND") && words.last().copied().is_some_and(is_filter) {
+        remaining.pop();
+    }
+
+    Ok((remaining.join(" "), values))
+}
+
+/// Split a query into whitespace-separated words
+///
+/// Whitespace inside quoted phrases and parentheses does not split words, so
+/// `"a b"` and `(a OR b)` are returned as single words.
+fn split_top_level_words(query: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut in_quotes = false;
+    let mut paren_depth = 0usize;
+    let mut word_start = None;
+    let mut escape_next = false;
+
+    for (i, c) in query.char_indices() {
+        if c.is_whitespace() && !in_quotes && paren_depth == 0 {
+            if let Some(start) = word_start.take() {
+                words.push(&query[start..i]);
+            }
+            continue;
+        }
+
+        word_start.get_or_insert(i);
+
+        if escape_next {
+            escape_next = false;
+        } else if c == '\\' {
+            escape_next = true;
+        } else if c == '"' && is_phrase_quote(query, i, in_quotes) {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && c == '(' {
+            paren_depth += 1;
+        } else if !in_quotes && c == ')' {
+            paren_depth = paren_depth.saturating_sub(1);
+        }
+    }
+
+    if let Some(start) = word_start {
+        words.push(&query[start..]);
+    }
+
+    words
+}
+
+/// Build the SQL condition for source filters
+///
+/// Each source value binds two parameters: the value itself and the value
+/// followed by a colon (see `source_filter_params`).
+pub fn source_filter_condition(source_count: usize) -> String {
+    let conditions = vec!["(n.source = ? OR instr(n.source, ?) = 1)"; source_count];
+    format!("({})", conditions.join(" OR "))
+}
+
+/// Build the SQL condition for language filters, binding one parameter per language
+pub fn lang_filter_condition(lang_count: usize) -> String {
+    format!("n.lang IN ({})", vec!["?"; lang_count].join(", "))
+}
+
+/// Build the SQL condition for a path prefix, binding the prefix as parameter
+pub fn path_prefix_condition() -> String {
+    "instr(n.filepath, ?) = 1".to_string()
+}
+
+/// Get the parameters to bind for the source filter condition
+pub fn source_filter_params(sources: &[String]) -> Vec<String> {
+    sources
+        .iter()
+        .flat_map(|source| [source.clone(), format!("{}:", source)])
+        .collect()
+}
+
 /// Process a search query to handle tag prefixes (+ signs) and parentheses
 ///
 /// In FTS5, + is a special character that means "required term", so we need to
@@ -160,30 +353,37 @@ pub fn json_to_note(metadata_json: &str, content: &str) -> Result<Note> {
 ///
 /// * `Ok(String)` - The processed query string
 /// * `Err(DatabaseError)` - If the query is invalid (e.g., unbalanced quotes or parentheses)
+///   or too long or complex (see `check_query_size`)
 pub fn process_search_query(query: &str) -> Result<String> {
     // If the query is empty, return an empty string
     if query.trim().is_empty() {
         return Ok(String::new());
     }
 
+    check_query_size(query)?;
+
     // Check for balanced quotes
-    let quote_count = query.chars().filter(|&c| c == '"').count();
-    if quote_count % 2 != 0 {
-        return Err(DatabaseError::InvalidSearchQuery(
-            "Unbalanced quotes in search query".to_string(),
-        )
-        .into());
-    }
+    check_balanced_quotes(query)?;
 
-    // Check for balanced parentheses
+    // Check for balanced parentheses, and that they are not nested too deeply
     check_balanced_parentheses(query)?;
 
+    let terms = count_query_terms(query);
+    if terms > MAX_SEARCH_QUERY_TERMS {
+        return Err(DatabaseError::InvalidSearchQuery(format!(
+            "Search query has too many terms: {} (at most {})",
+            terms, MAX_SEARCH_QUERY_TERMS
+        ))
+        .into());
+    }
+
     // Split the query into quoted, parenthesized, and unquoted sections
     let mut result = Vec::new();
     let mut in_quotes = false;
     let mut paren_depth = 0;
     let mut section_start = 0;
     let mut escape_next = false;
+    let mut in_near_group = false;
 
     for (i, c) in query.char_indices() {
         if escape_next {
@@ -196,30 +396,37 @@ pub fn process_search_query(query: &str) -> Result<String> {
             continue;
         }
 
-        if c == '"' && paren_depth == 0 {
-            if !in_quotes {
-                // Process any unquoted text before this quote
-                if i > section_start {
-                    let unquoted_section = &query[section_start..i];
-                    process_unquoted_section(unquoted_section, &mut result)?;
+        if c == '"' && is_phrase_quote(query, i, in_quotes) {
+            if paren_depth == 0 {
+                if !in_quotes {
+                    // Process any unquoted text before this quote
+                    if i > section_start {
+                        let unquoted_section = &query[section_start..i];
+                        process_unquoted_section(unquoted_section, &mut result)?;
+                    }
+                    // Start of quoted section
+                    section_start = i;
+                } else {
+                    // End of quoted section
+                    let quoted_section = &query[section_start + 1..i];
+                    result.push(quote_fts_string(quoted_section));
+                    section_start = i + 1;
                 }
-                // Start of quoted section
-                section_start = i;
-            } else {
-                // End of quoted section
-                let quoted_section = &query[section_start..=i];
-                result.push(quoted_section.to_string());
-                section_start = i + 1;
             }
             in_quotes = !in_quotes;
         } else if !in_quotes {
             if c == '(' {
                 if paren_depth == 0 {
-                    // Process any unquoted text before this parenthesis
-                    if i > section_start {
-                        let unquoted_section = &query[section_start..i];
-                        process_unquoted_section(unquoted_section, &mut result)?;
+                    // A NEAR group is written as `NEAR(` without a space
+                    let mut unquoted_section = &query[section_start..i];
+                    in_near_group = ends_with_near_keyword(unquoted_section);
+                    if in_near_group {
+                        unquoted_section = &unquoted_section[..unquoted_section.len() - 4];
                     }
+
+                    // Process any unquoted text before this parenthesis
+                    process_unquoted_section(unquoted_section, &mut result)?;
+
                     // Start of parenthesized section
                     section_start = i;
                 }
@@ -229,7 +436,12 @@ pub fn process_search_query(query: &str) -> Result<String> {
                 if paren_depth == 0 {
                     // End of parenthesized section
                     let paren_section = &query[section_start..=i];
-                    process_parentheses_section(paren_section, &mut result)?;
+                    if in_near_group {
+                        process_near_group(paren_section, &mut result)?;
+                        in_near_group = false;
+                    } else {
+                        process_parentheses_section(paren_section, &mut result)?;
+                    }
                     section_start = i + 1;
                 }
             }
@@ -246,6 +458,82 @@ pub fn process_search_query(query: &str) -> Result<String> {
     Ok(result.join(" "))
 }
 
+/// Check if the quote at byte index `i` delimits a phrase
+///
+/// Quotes only start a phrase at the beginning of a word (after whitespace or an
+/// opening parenthesis) and only end a phrase at the end of a word (before
+/// whitespace or a closing parenthesis, or before the distance of a NEAR
+/// group as in `NEAR(a "b c", 5)`). Quotes elsewhere, such as the inch mark in
+/// `5"6`, are treated as part of the word.
+fn is_phrase_quote(s: &str, i: usize, in_quotes: bool) -> bool {
+    if in_quotes {
+        let rest = &s[i + 1..];
+        rest.chars()
+            .next()
+            .is_none_or(|c| c.is_whitespace() || c == ')')
+            || is_near_distance(rest)
+    } else {
+        s[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || c == '(')
+    }
+}
+
+/// Check if a string starts with the distance of a NEAR group: a comma and
+/// the text up to the closing parenthesis (or the end of the group's content)
+fn is_near_distance(s: &str) -> bool {
+    s.strip_prefix(',').is_some_and(|rest| {
+        let end = rest.find(['"', '(', ')']);
+        end.is_none_or(|end| rest[end..].starts_with(')'))
+    })
+}
+
+/// Wrap a string in quotes for FTS5, escaping embedded quotes by doubling them
+fn quote_fts_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+/// Check if the phrase quotes in a string are balanced
+///
+/// # Returns
+///
+/// * `Ok(())` - If every opening quote has a matching closing quote
+/// * `Err(DatabaseError)` - If a quote is never closed, naming its position
+fn check_balanced_quotes(s: &str) -> Result<()> {
+    let mut open_quote = None;
+    let mut escape_next = false;
+
+    for (position, (i, c)) in s.char_indices().enumerate() {
+        if escape_next {
+            escape_next = false;
+            continue;
+        }
+
+        if c == '\\' {
+            escape_next = true;
+            continue;
+        }
+
+        if c == '"' && is_phrase_quote(s, i, open_quote.is_some()) {
+            open_quote = match open_quote {
+                Some(_) => None,
+                None => Some(position + 1),
+            };
+        }
+    }
+
+    if let Some(position) = open_quote {
+        return Err(DatabaseError::InvalidSearchQuery(format!(
+            "Unbalanced quotes in search query: the quote at position {} is never closed",
+            position
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
 /// Check if parentheses in a string are balanced and properly ordered
 ///
 /// This function checks if all opening parentheses have matching closing parentheses
@@ -260,7 +548,7 @@ fn check_balanced_parentheses(s: &str) -> Result<()> {
     let mut in_quotes = false;
     let mut escape_next = false;
 
-    for c in s.chars() {
+    for (i, c) in s.char_indices() {
         if escape_next {
             escape_next = false;
             continue;
@@ -271,11 +559,18 @@ fn check_balanced_parentheses(s: &str) -> Result<()> {
             continue;
         }
 
-        if c == '"' {
+        if c == '"' && is_phrase_quote(s, i, in_quotes) {
             in_quotes = !in_quotes;
         } else if !in_quotes {
             if c == '(' {
                 stack.push(c);
+                if stack.len() > MAX_SEARCH_QUERY_DEPTH {
+                    return Err(DatabaseError::InvalidSearchQuery(format!(
+                        "Parentheses in search query are nested too deeply (at most {} levels)",
+                        MAX_SEARCH_QUERY_DEPTH
+                    ))
+                    .into());
+                }
             } else if c == ')' && stack.pop().is_none() {
                 return Err(DatabaseError::InvalidSearchQuery(
                     "Unbalanced parentheses in search query: too many closing parentheses"
@@ -296,6 +591,66 @@ fn check_balanced_parentheses(s: &str) -> Result<()> {
     Ok(())
 }
 
+/// Check that a search query is not too long
+///
+/// Very long queries make FTS5 slow for no good reason. The nesting depth and
+/// the number of terms are checked while processing the query; raw queries,
+/// which are not processed, are only checked for their length.
+pub fn check_query_size(query: &str) -> Result<()> {
+    let chars = query.chars().count();
+    if chars > MAX_SEARCH_QUERY_CHARS {
+        return Err(DatabaseError::InvalidSearchQuery(format!(
+            "Search query is too long: {} characters (at most {})",
+            chars, MAX_SEARCH_QUERY_CHARS
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Count the terms of a search query
+///
+/// A term is a word, tag or quoted phrase; operators and parentheses are not
+/// terms. A filter like `tags:"+project"` counts as one term.
+fn count_query_terms(query: &str) -> usize {
+    let is_term = |word: &str| !matches!(word, "AND" | "OR" | "NOT" | "NEAR");
+
+    let mut count = 0;
+    let mut in_quotes = false;
+    let mut escape_next = false;
+    let mut word_start = None;
+
+    for (i, c) in query.char_indices() {
+        if !in_quotes && !escape_next && (c.is_whitespace() || c == '(' || c == ')') {
+            if let Some(start) = word_start.take()
+                && is_term(&query[start..i])
+            {
+                count += 1;
+            }
+            continue;
+        }
+
+        word_start.get_or_insert(i);
+
+        if escape_next {
+            escape_next = false;
+        } else if c == '\\' {
+            escape_next = true;
+        } else if c == '"' && is_phrase_quote(query, i, in_quotes) {
+            in_quotes = !in_quotes;
+        }
+    }
+
+    if let Some(start) = word_start
+        && is_term(&query[start..])
+    {
+        count += 1;
+    }
+
+    count
+}
+
 /// Process a parenthesized section of the search query
 ///
 /// This function processes the content inside parentheses, preserving the parentheses
@@ -318,6 +673,88 @@ fn process_parentheses_section(section: &str, result: &mut Vec<String>) -> Resul
     Ok(())
 }
 
+/// Check if an unquoted section ends with the NEAR keyword, i.e. whether a
+/// parenthesis directly following it opens a NEAR group
+fn ends_with_near_keyword(section: &str) -> bool {
+    section
+        .strip_suffix("NEAR")
+        .is_some_and(|before| before.is_empty() || before.ends_with(char::is_whitespace))
+}
+
+/// Process a NEAR group of the search query
+///
+/// FTS5 supports proximity queries of the form `NEAR(a b, N)`, which match if
+/// the phrases occur within N tokens of each other. The phrases inside the group
+/// are quoted like elsewhere, while the NEAR wrapper and the distance are passed
+/// through. Tags, boolean operators and nested parentheses are not allowed
+/// inside a NEAR group.
+///
+/// # Parameters
+///
+/// * `section` - The parenthesized section following the NEAR keyword, including the parentheses
+/// * `result` - The vector to append the processed group to
+fn process_near_group(section: &str, result: &mut Vec<String>) -> Result<()> {
+    const BOOLEAN_OPERATORS: [&str; 3] = ["AND", "OR", "NOT"];
+
+    let content = &section[1..section.len() - 1];
+
+    // Split off the distance after the last comma outside of quotes
+    let mut in_quotes = false;
+    let mut comma = None;
+    for (i, c) in content.char_indices() {
+        if c == '"' && is_phrase_quote(content, i, in_quotes) {
+            in_quotes = !in_quotes;
+        } else if c == ',' && !in_quotes {
+            comma = Some(i);
+        }
+    }
+
+    let (terms, distance) = match comma {
+        Some(i) => (&content[..i], Some(content[i + 1..].trim())),
+        None => (content, None),
+    };
+
+    if let Some(distance) = distance
+        && (distance.is_empty() || !distance.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err(DatabaseError::InvalidSearchQuery(format!(
+            "NEAR distance must be a number, got '{}'",
+            distance
+        ))
+        .into());
+    }
+
+    let words = split_top_level_words(terms);
+    if words.is_empty() {
+        return Err(DatabaseError::InvalidSearchQuery(
+            "NEAR requires at least one search term".to_string(),
+        )
+        .into());
+    }
+
+    for word in &words {
+        if word.starts_with('+')
+            || tag_filter_name(word).is_some()
+            || word.starts_with('(')
+            || BOOLEAN_OPERATORS.contains(word)
+        {
+            return Err(DatabaseError::InvalidSearchQuery(format!(
+                "NEAR can only contain search terms and phrases, got '{}'",
+                word
+            ))
+            .into());
+        }
+    }
+
+    let processed_terms = process_search_query(terms)?;
+    match distance {
+        Some(distance) => result.push(format!("NEAR({}, {})", processed_terms, distance)),
+        None => result.push(format!("NEAR({})", processed_terms)),
+    }
+
+    Ok(())
+}
+
 /// Process an unquoted section of the search query
 ///
 /// This function splits the unquoted section into words and processes each word
@@ -326,19 +763,30 @@ fn process_unquoted_section(section: &str, result: &mut Vec<String>) -> Result<(
     // Define boolean operators that should not be wrapped in quotes
     const BOOLEAN_OPERATORS: [&str; 3] = ["AND", "OR", "NOT"];
 
+    // A quoted tag like `+"code review"` would otherwise be split into words
+    if let Some(tag) = find_multi_word_tag(section)
+        && let Some(e) = multi_word_tag_error(tag)
+    {
+        return Err(
+            DatabaseError::InvalidSearchQuery(format!("Invalid tag '{}': {}", tag, e)).into(),
+        );
+    }
+
     // Split the section into words
     for word in section.split_whitespace() {
         if word == "+" {
             // If the word is a verbatim '+', leave it as is
             result.push(word.to_string());
-        } else if word.starts_with('+') {
-            // If the word is a tag (starts with a '+'), validate it and map it to 'tags:"+<word>"'
+        } else if let Some(name) = word.strip_prefix('+').or_else(|| tag_filter_name(word)) {
+            // If the word is a tag (`+tag`, or `tag:tag` and `tags:tag`, which
+            // models tend to write), validate it and map it to 'tags:"+<tag>"'
             // First, validate the tag
-            match Tag::new(word) {
-                Ok(_) => {
-                    // Format as a column-specific search for tags
+            match Tag::new(name) {
+                Ok(tag) => {
+                    // Format as a column-specific search for tags, with the
+                    // tag in the normalized form in which it is stored
                     // Use the SQLite FTS5 column filter syntax without parentheses
-                    result.push(format!("tags:\"{}\"", word));
+                    result.push(format!("tags:\"+{}\"", tag));
                 }
                 Err(e) => {
                     return Err(DatabaseError::InvalidSearchQuery(format!(
@@ -351,18 +799,96 @@ fn process_unquoted_section(section: &str, result: &mut Vec<String>) -> Result<(
         } else if BOOLEAN_OPERATORS.contains(&word) {
             // If the word is a boolean operator, leave it as is
             result.push(word.to_string());
+        } else if let Some(phrase) = hyphenated_word_to_phrase(word) {
+            // If the word is hyphenated, search for its parts as a phrase
+            result.push(phrase);
         } else {
             // Otherwise, wrap the word in quotes
-            result.push(format!("\"{}\"", word));
+            result.push(quote_fts_string(word));
         }
     }
     Ok(())
 }
 
+/// Get the tag name of a `tag:NAME` or `tags:NAME` filter
+///
+/// These are accepted as aliases of `+NAME`. Without the alias, the word
+/// would be searched as a literal term and silently match nothing.
+fn tag_filter_name(word: &str) -> Option<&str> {
+    word.strip_prefix("tag:")
+        .or_else(|| word.strip_prefix("tags:"))
+        .filter(|name| !name.is_empty())
+}
+
+/// Convert a hyphenated word into an FTS5 phrase of its parts
+///
+/// The FTS tokenizer splits stored text on dashes, so `on-call` is indexed as
+/// the two tokens `on` and `call`. Searching for the phrase `"on call"` matches
+/// the same notes regardless of whether the term appears in the content or in a
+/// hyphenated tag.
+///
+/// Returns `None` if the word does not contain a dash between other characters.
+fn hyphenated_word_to_phrase(word: &str) -> Option<String> {
+    if !word.contains('-') {
+        return None;
+    }
+
+    let parts: Vec<&str> = word.split('-').filter(|part| !part.is_empty()).collect();
+    if parts.is_empty() {
+        return None;
+    }
+
+    Some(quote_fts_string(&parts.join(" ")))
+}
+
 #[cfg(test)]
 mod query_tests {
-    use super::process_search_query;
+    use super::{
+        MAX_SEARCH_QUERY_CHARS, MAX_SEARCH_QUERY_DEPTH, MAX_SEARCH_QUERY_TERMS, count_query_terms,
+        due_until, extract_lang_filters, extract_source_filters, hyphenated_word_to_phrase,
+        id_prefix_end, process_search_query, unique_id_prefix,
+    };
     use crate::error::DatabaseError;
+    use chrono::NaiveDate;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_id_prefix_end() {
+        assert_eq!(id_prefix_end("ab"), "ac");
+        assert_eq!(id_prefix_end("a9"), "a:");
+        // No rollover to the next letter: "az" ends before "a{", not "b0"
+        assert_eq!(id_prefix_end("az"), "a{");
+        assert_eq!(id_prefix_end("zz"), "z{");
+        assert!("azzzzzzzzzzzzzzz" < id_prefix_end("az").as_str());
+        assert!("b0000000000000000" >= id_prefix_end("az").as_str());
+    }
+
+    #[test]
+    fn test_due_until() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+
+        assert_eq!(due_until(today, false, None), None);
+        assert_eq!(
+            due_until(today, true, None),
+            NaiveDate::from_ymd_opt(2025, 2, 28)
+        );
+        assert_eq!(
+            due_until(today, false, Some(7)),
+            NaiveDate::from_ymd_opt(2025, 3, 8)
+        );
+        assert_eq!(due_until(today, false, Some(0)), Some(today));
+    }
+
+    #[test]
+    fn test_unique_id_prefix() {
+        assert_eq!(unique_id_prefix("abcdef", None, None), "ab");
+        assert_eq!(unique_id_prefix("abcdef", Some("abcxyz"), None), "abcd");
+        assert_eq!(
+            unique_id_prefix("abcdef", Some("a0"), Some("abcdeg")),
+            "abcdef"
+        );
+        assert_eq!(unique_id_prefix("b1", Some("abcdef"), Some("c2")), "b1");
+    }
 
     #[test]
     fn test_process_search_query_basic() {
@@ -382,6 +908,33 @@ mod query_tests {
         );
     }
 
+    #[test]
+    fn test_process_search_query_with_tag_aliases() {
+        // `tag:` and `tags:` are aliases of the `+` prefix
+        assert_eq!(
+            process_search_query("meeting tag:project notes tags:on-call").unwrap(),
+            r#""meeting" tags:"+project" "notes" tags:"+on-call""#
+        );
+        assert_eq!(
+            process_search_query("(tag:ops OR +deploy) NOT tags:draft").unwrap(),
+            r#"(tags:"+ops" OR tags:"+deploy") NOT tags:"+draft""#
+        );
+
+        // An empty alias is an ordinary word
+        assert_eq!(process_search_query("tag:").unwrap(), r#""tag:""#);
+
+        // Invalid tags are reported like with the `+` prefix
+        for (alias, plus) in [("tag:Tag_x", "+Tag_x"), ("tags:-ops", "+-ops")] {
+            let alias_error = process_search_query(alias).unwrap_err().to_string();
+            let plus_error = process_search_query(plus).unwrap_err().to_string();
+            assert!(alias_error.contains(&format!("Invalid tag '{}'", alias)));
+            assert_eq!(
+                alias_error.split_once("': ").unwrap().1,
+                plus_error.split_once("': ").unwrap().1
+            );
+        }
+    }
+
     #[test]
     fn test_process_search_query_with_quotes() {
         // Test query with quotes
@@ -436,6 +989,19 @@ mod query_tests {
         }
     }
 
+    #[test]
+    fn test_process_search_query_with_multi_word_tag() {
+        for query in ["deploy +\"code review\"", "(+\"code review\" OR +ops)"] {
+            let e = process_search_query(query).unwrap_err();
+            assert_eq!(
+                e.to_string(),
+                "Database error: Invalid search query: Invalid tag '+\"code review\"': Tag 'code review' cannot contain spaces; use dashes instead, e.g. +code-review",
+                "{}",
+                query
+            );
+        }
+    }
+
     #[test]
     fn test_process_search_query_with_empty_query() {
         // Test empty query
@@ -472,10 +1038,10 @@ mod query_tests {
 
     #[test]
     fn test_process_search_query_with_backslash_escape() {
-        // Test query with backslash escaping a quote
+        // Test query with backslash escaping a quote (embedded quotes are doubled for FTS5)
         assert_eq!(
             process_search_query(r#"text with \"escaped quotes\""#).unwrap(),
-            r#""text" "with" "\"escaped" "quotes\"""#
+            r#""text" "with" "\""escaped" "quotes\""""#
         );
     }
 
@@ -601,4 +1167,359 @@ mod query_tests {
             r#""AND OR NOT" tags:"+tag""#
         );
     }
+
+    #[test]
+    fn test_process_search_query_with_hyphenated_terms() {
+        // A hyphenated content term becomes a phrase of its parts
+        assert_eq!(process_search_query("on-call").unwrap(), r#""on call""#);
+
+        // Terms with multiple hyphens keep all their parts in order
+        assert_eq!(
+            process_search_query("state-of-the-art design").unwrap(),
+            r#""state of the art" "design""#
+        );
+
+        // Doubled or trailing dashes do not produce empty tokens
+        assert_eq!(process_search_query("on--call-").unwrap(), r#""on call""#);
+
+        // A lone dash is quoted as before
+        assert_eq!(
+            process_search_query("foo - bar").unwrap(),
+            r#""foo" "-" "bar""#
+        );
+    }
+
+    #[test]
+    fn test_process_search_query_with_hyphenated_tags() {
+        // Hyphenated tags are still handled as tags
+        assert_eq!(
+            process_search_query("+on-call incident-review").unwrap(),
+            r#"tags:"+on-call" "incident review""#
+        );
+    }
+
+    #[test]
+    fn test_hyphenated_word_to_phrase_escapes_quotes() {
+        // Embedded quotes are doubled, so that the phrase stays terminated
+        assert_eq!(
+            hyphenated_word_to_phrase("5\"-wide").unwrap(),
+            "\"5\"\" wide\""
+        );
+        assert_eq!(hyphenated_word_to_phrase("plain"), None);
+    }
+
+    #[test]
+    fn test_process_search_query_with_apostrophes() {
+        // Apostrophes are ordinary characters inside words
+        assert_eq!(
+            process_search_query("don't O'Brien").unwrap(),
+            r#""don't" "O'Brien""#
+        );
+
+        // Phrases can contain apostrophes
+        assert_eq!(
+            process_search_query(r#""don't panic" +todo"#).unwrap(),
+            r#""don't panic" tags:"+todo""#
+        );
+    }
+
+    #[test]
+    fn test_process_search_query_with_inch_marks() {
+        // A quote inside a word is not a phrase delimiter and gets escaped
+        assert_eq!(process_search_query(r#"5"6"#).unwrap(), r#""5""6""#);
+        assert_eq!(
+            process_search_query(r#"shelf 5"6 tall"#).unwrap(),
+            r#""shelf" "5""6" "tall""#
+        );
+
+        // Trailing inch marks are embedded quotes as well
+        assert_eq!(
+            process_search_query(r#"a 12" pipe"#).unwrap(),
+            r#""a" "12""" "pipe""#
+        );
+
+        // Inch marks inside a phrase are escaped
+        assert_eq!(
+            process_search_query(r#""a 5"6 shelf""#).unwrap(),
+            r#""a 5""6 shelf""#
+        );
+    }
+
+    #[test]
+    fn test_process_search_query_unbalanced_quote_position() {
+        let result = process_search_query(r#"shelf "5 feet"#);
+        match result {
+            Err(crate::error::NotelogError::DatabaseError(DatabaseError::InvalidSearchQuery(
+                msg,
+            ))) => {
+                assert!(msg.contains("Unbalanced quotes"));
+                assert!(msg.contains("position 7"), "unexpected message: {}", msg);
+            }
+            _ => panic!("Expected InvalidSearchQuery error"),
+        }
+    }
+
+    #[test]
+    fn test_extract_source_filters() {
+        let (query, sources) = extract_source_filters("meeting source:mcp +work").unwrap();
+        assert_eq!(query, "meeting +work");
+        assert_eq!(sources, vec!["mcp"]);
+
+        let (query, sources) =
+            extract_source_filters("source:cli source:mcp:claude-desktop").unwrap();
+        assert_eq!(query, "");
+        assert_eq!(sources, vec!["cli", "mcp:claude-desktop"]);
+
+        // Filters inside quotes or parentheses and empty filters are kept as search terms
+        let query = r#""source:mcp" (a OR source:cli) source:"#;
+        let (processed, sources) = extract_source_filters(query).unwrap();
+        assert_eq!(processed, query);
+        assert!(sources.is_empty());
+    }
+
+    #[test]
+    fn test_extract_source_filters_with_operators() {
+        // An AND next to a filter is dropped with it
+        let (query, sources) = extract_source_filters("foo AND source:cli").unwrap();
+        assert_eq!(query, "foo");
+        assert_eq!(sources, vec!["cli"]);
+        let (query, _) = extract_source_filters("source:cli AND foo").unwrap();
+        assert_eq!(query, "foo");
+        let (query, _) = extract_source_filters("foo AND source:cli AND bar").unwrap();
+        assert_eq!(query, "foo AND bar");
+
+        // Operators between other terms are kept
+        let (query, _) = extract_source_filters("foo source:cli bar NOT baz").unwrap();
+        assert_eq!(query, "foo bar NOT baz");
+        assert!(process_search_query(&query).is_ok());
+
+        // Filters cannot be negated or be alternatives
+        for query in [
+            "+jp NOT source:mcp",
+            "deploy OR source:cli",
+            "source:cli OR deploy",
+            "source:cli NOT deploy",
+        ] {
+            let err = extract_source_filters(query).unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    crate::error::NotelogError::DatabaseError(DatabaseError::InvalidSearchQuery(_))
+                ),
+                "{}: {}",
+                query,
+                err
+            );
+        }
+        assert!(extract_lang_filters("Treiber OR lang:de").is_err());
+    }
+
+    #[test]
+    fn test_extract_lang_filters() {
+        let (query, languages) = extract_lang_filters("Treiber lang:DE source:cli").unwrap();
+        assert_eq!(query, "Treiber source:cli");
+        assert_eq!(languages, vec!["de"]);
+
+        let (query, languages) = extract_lang_filters("lang:").unwrap();
+        assert_eq!(query, "lang:");
+        assert!(languages.is_empty());
+    }
+
+    #[test]
+    fn test_process_search_query_with_near() {
+        assert_eq!(
+            process_search_query("NEAR(deploy failure, 5)").unwrap(),
+            r#"NEAR("deploy" "failure", 5)"#
+        );
+
+        // Without a distance
+        assert_eq!(
+            process_search_query("NEAR(deploy failure)").unwrap(),
+            r#"NEAR("deploy" "failure")"#
+        );
+
+        // Combined with tags and phrases
+        assert_eq!(
+            process_search_query(r#"+ops NEAR(deploy failure, 5) "root cause""#).unwrap(),
+            r#"tags:"+ops" NEAR("deploy" "failure", 5) "root cause""#
+        );
+
+        // Phrases and hyphenated terms inside the group
+        assert_eq!(
+            process_search_query(r#"NEAR("deploy script" on-call,10)"#).unwrap(),
+            r#"NEAR("deploy script" "on call", 10)"#
+        );
+
+        // A phrase right before the distance
+        assert_eq!(
+            process_search_query(r#"NEAR(on "deploy failure", 5)"#).unwrap(),
+            r#"NEAR("on" "deploy failure", 5)"#
+        );
+        assert_eq!(
+            process_search_query(r#"+ops NEAR("deploy failure",5) bar"#).unwrap(),
+            r#"tags:"+ops" NEAR("deploy failure", 5) "bar""#
+        );
+
+        // NEAR followed by a space is a regular term and parenthesized group
+        assert_eq!(
+            process_search_query("NEAR (a b)").unwrap(),
+            r#""NEAR" ("a" "b")"#
+        );
+    }
+
+    #[test]
+    fn test_process_search_query_with_invalid_near() {
+        for query in [
+            "NEAR(deploy failure, five)",
+            "NEAR(deploy failure, )",
+            "NEAR(+ops failure, 5)",
+            "NEAR(deploy OR failure, 5)",
+            "NEAR(, 5)",
+            "NEAR(deploy (failure), 5)",
+        ] {
+            let result = process_search_query(query);
+            assert!(
+                matches!(
+                    result,
+                    Err(crate::error::NotelogError::DatabaseError(
+                        DatabaseError::InvalidSearchQuery(_)
+                    ))
+                ),
+                "expected an error for {}",
+                query
+            );
+        }
+
+        // Parentheses must balance
+        assert!(process_search_query("NEAR(deploy failure, 5").is_err());
+    }
+
+    #[test]
+    fn test_process_search_query_multibyte() {
+        assert_eq!(process_search_query("(日本語)").unwrap(), "(\"日本語\")");
+        assert_eq!(
+            process_search_query("NEAR(日本 語, 2) OR (é)").unwrap(),
+            "NEAR(\"日本\" \"語\", 2) OR (\"é\")"
+        );
+        assert!(process_search_query("(日本語").is_err());
+    }
+
+    #[test]
+    fn test_process_search_query_hyphenated_word_with_quote() {
+        // The quote must be escaped inside the phrase, like in other words
+        assert_eq!(process_search_query("A\"-").unwrap(), "\"A\"\"\"");
+        assert_eq!(process_search_query("5\"-wide").unwrap(), "\"5\"\" wide\"");
+    }
+
+    /// Check that the quotes and parentheses of an FTS5 query are balanced
+    ///
+    /// Quotes are doubled inside strings, and parentheses inside strings do not count.
+    fn is_balanced_fts_query(query: &str) -> bool {
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut chars = query.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if in_string && chars.peek() == Some(&'"') => {
+                    chars.next();
+                }
+                '"' => in_string = !in_string,
+                '(' if !in_string => depth += 1,
+                ')' if !in_string => {
+                    if depth == 0 {
+                        return false;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+        }
+
+        !in_string && depth == 0
+    }
+
+    #[test]
+    fn test_process_search_query_length_limit() {
+        let at_limit = "a".repeat(MAX_SEARCH_QUERY_CHARS);
+        assert!(process_search_query(&at_limit[1..]).is_ok());
+        assert!(process_search_query(&at_limit).is_ok());
+
+        let error = process_search_query(&format!("{}a", at_limit)).unwrap_err();
+        assert!(
+            error.to_string().contains("too long: 1001 characters"),
+            "{}",
+            error
+        );
+
+        // Characters are counted, not bytes
+        assert!(process_search_query(&"é".repeat(MAX_SEARCH_QUERY_CHARS)).is_ok());
+    }
+
+    #[test]
+    fn test_process_search_query_depth_limit() {
+        let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(process_search_query(&nested(MAX_SEARCH_QUERY_DEPTH - 1)).is_ok());
+        assert!(process_search_query(&nested(MAX_SEARCH_QUERY_DEPTH)).is_ok());
+
+        let error = process_search_query(&nested(MAX_SEARCH_QUERY_DEPTH + 1)).unwrap_err();
+        assert!(error.to_string().contains("nested too deeply"), "{}", error);
+
+        // Parentheses in phrases do not count
+        let quoted = format!("\"{}\"", nested(MAX_SEARCH_QUERY_DEPTH + 1));
+        assert!(process_search_query(&quoted).is_ok());
+    }
+
+    #[test]
+    fn test_process_search_query_term_limit() {
+        let terms = |count: usize| vec!["a"; count].join(" OR ");
+
+        assert!(process_search_query(&terms(MAX_SEARCH_QUERY_TERMS - 1)).is_ok());
+        assert!(process_search_query(&terms(MAX_SEARCH_QUERY_TERMS)).is_ok());
+
+        let error = process_search_query(&terms(MAX_SEARCH_QUERY_TERMS + 1)).unwrap_err();
+        assert!(
+            error.to_string().contains("too many terms: 65"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_count_query_terms() {
+        assert_eq!(count_query_terms("deploy +ops"), 2);
+        assert_eq!(count_query_terms("\"project plan\" AND (a OR b)"), 3);
+        assert_eq!(count_query_terms("tags:\"+project\" NOT draft"), 2);
+        assert_eq!(count_query_terms("NEAR(a b)"), 2);
+        assert_eq!(count_query_terms("  "), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn test_id_prefix_end_bounds_the_ids_with_the_prefix(
+            id in "[0-9a-z]{16}",
+            prefix in "[0-9a-z]{1,4}",
+        ) {
+            let end = id_prefix_end(&prefix);
+            let in_range = prefix.as_str() <= id.as_str() && id.as_str() < end.as_str();
+            prop_assert_eq!(in_range, id.starts_with(&prefix));
+        }
+
+        #[test]
+        fn test_process_search_query_any_input(query in ".{0,32}") {
+            if let Ok(processed) = process_search_query(&query) {
+                prop_assert!(is_balanced_fts_query(&processed), "{:?} -> {:?}", query, processed);
+            }
+        }
+
+        #[test]
+        fn test_process_search_query_query_syntax(
+            query in "([ a-zé日ß\\\\\"()+:,-]|NEAR|AND|OR|NOT){0,16}"
+        ) {
+            if let Ok(processed) = process_search_query(&query) {
+                prop_assert!(is_balanced_fts_query(&processed), "{:?} -> {:?}", query, processed);
+            }
+        }
+    }
 }
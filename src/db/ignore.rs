@@ -0,0 +1,211 @@
+//! Directories and files that indexing and file monitoring leave alone
+//!
+//! Besides the database, the reserved top-level directories (trash, archive,
+//! backups, attachments and notelog's data) never contain notes to index.
+//! Users can add their own patterns to `.notelogignore` in the notes directory.
+
+use std::fs;
+use std::path::{Component, Path};
+
+use crate::db::BACKUPS_DIR;
+use crate::hooks::DATA_DIR;
+
+/// The name of the file listing files that should not be indexed
+pub const IGNORE_FILENAME: &str = ".notelogignore";
+
+/// Top-level directories of the notes directory whose files are never indexed
+pub const RESERVED_DIRS: &[&str] = &[".trash", ".archive", BACKUPS_DIR, "attachments", DATA_DIR];
+
+/// A pattern of `.notelogignore`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    /// The path components to match, which may contain `*` and `?`
+    components: Vec<String>,
+    /// Whether the pattern only matches from the top of the notes directory
+    anchored: bool,
+    /// Whether the pattern only matches directories (it ended with `/`)
+    dir_only: bool,
+}
+
+/// The paths that indexing skips: the reserved directories and the patterns
+/// of `.notelogignore`
+///
+/// Patterns work like a simple `.gitignore`: one per line, `#` starts a
+/// comment, `*` and `?` match within a path component, a pattern ending in
+/// `/` only matches directories, and a pattern containing `/` matches from the
+/// top of the notes directory instead of at any depth. Ignoring a directory
+/// ignores everything in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// Parse the content of an ignore file
+    pub fn parse(content: &str) -> Self {
+        let patterns = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                let components: Vec<String> = line
+                    .split('/')
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect();
+
+                (!components.is_empty()).then_some(Pattern {
+                    components,
+                    anchored,
+                    dir_only,
+                })
+            })
+            .collect();
+
+        Self { patterns }
+    }
+
+    /// Load `.notelogignore` from the notes directory
+    ///
+    /// A missing or unreadable ignore file ignores only the reserved directories.
+    pub fn load(notes_dir: &Path) -> Self {
+        fs::read_to_string(notes_dir.join(IGNORE_FILENAME))
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    /// Whether a path relative to the notes directory is ignored
+    ///
+    /// `is_dir` says whether the path itself is a directory; the components
+    /// before the last one are always directories.
+    pub fn is_ignored(&self, relative_path: &Path, is_dir: bool) -> bool {
+        let components: Vec<String> = relative_path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+
+        if components
+            .first()
+            .is_some_and(|first| RESERVED_DIRS.contains(&first.as_str()))
+            && (components.len() > 1 || is_dir)
+        {
+            return true;
+        }
+
+        self.patterns.iter().any(|pattern| {
+            // Try each directory of the path, and the path itself
+            (1..=components.len()).any(|len| {
+                let names = &components[..len];
+                let names_dir = len < components.len() || is_dir;
+                if pattern.dir_only && !names_dir {
+                    return false;
+                }
+
+                if pattern.anchored {
+                    names.len() == pattern.components.len()
+                        && names
+                            .iter()
+                            .zip(&pattern.components)
+                            .all(|(name, glob)| glob_match(glob, name))
+                } else {
+                    glob_match(&pattern.components[0], &names[len - 1])
+                }
+            })
+        })
+    }
+}
+
+/// Match a name against a glob with `*` (any characters) and `?` (one character)
+fn glob_match(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut g, mut n) = (0, 0);
+    // The positions to go back to after a mismatch following the last `*`
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if g < glob.len() && (glob[g] == '?' || glob[g] == name[n]) {
+            g += 1;
+            n += 1;
+        } else if g < glob.len() && glob[g] == '*' {
+            star = Some((g, n));
+            g += 1;
+        } else if let Some((star_g, star_n)) = star {
+            g = star_g + 1;
+            n = star_n + 1;
+            star = Some((star_g, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    glob[g..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.md", "note.md"));
+        assert!(glob_match("draft-?", "draft-1"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("*.md", "note.txt"));
+        assert!(!glob_match("draft-?", "draft-12"));
+    }
+
+    #[test]
+    fn test_reserved_dirs() {
+        let rules = IgnoreRules::default();
+        for dir in RESERVED_DIRS {
+            let path = Path::new(dir).join("2025-01-01T12-00 Note.md");
+            assert!(rules.is_ignored(&path, false), "{}", path.display());
+            assert!(rules.is_ignored(Path::new(dir), true));
+        }
+
+        // Only at the top of the notes directory
+        assert!(!rules.is_ignored(
+            Path::new("2025/attachments/2025-01-01T12-00 Note.md"),
+            false
+        ));
+        assert!(!rules.is_ignored(Path::new("2025/01_January/2025-01-01T12-00 Note.md"), false));
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let rules =
+            IgnoreRules::parse("# Comment\n\nscratch\n*.tmp.md\ndrafts/\n2024/old-*\n/exports\n");
+        let ignored = |path: &str, is_dir: bool| rules.is_ignored(Path::new(path), is_dir);
+
+        // Unanchored patterns match at any depth
+        assert!(ignored("scratch/2025-01-01T12-00 Note.md", false));
+        assert!(ignored("2025/scratch/2025-01-01T12-00 Note.md", false));
+        assert!(ignored(
+            "2025/01_January/2025-01-01T12-00 Note.tmp.md",
+            false
+        ));
+
+        // Patterns ending in / only match directories
+        assert!(ignored("drafts/2025-01-01T12-00 Note.md", false));
+        assert!(!ignored("2025/drafts", false));
+
+        // Patterns with a / match from the top
+        assert!(ignored("2024/old-notes/2024-01-01T12-00 Note.md", false));
+        assert!(!ignored(
+            "archive/2024/old-notes/2024-01-01T12-00 Note.md",
+            false
+        ));
+        assert!(ignored("exports", true));
+        assert!(!ignored("2025/exports", true));
+
+        assert!(!ignored("2025/01_January/2025-01-01T12-00 Note.md", false));
+    }
+}
@@ -1,19 +1,36 @@
 //! Indexing functionality for the database
 
-use rmcp::serde_json;
-use sqlx::{Pool, Sqlite};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Acquire, Pool, Sqlite};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 use tokio::fs;
 
+use crate::constants::MAX_TAGS_PER_NOTE;
 use crate::core::note::Note;
+use crate::db::lock::wait_for_maintenance;
+use crate::db::metrics::{MetricsRecorder, Operation};
+use crate::db::{DB_FILENAME, IgnoreRules};
 use crate::error::{DatabaseError, NotelogError, Result};
-use crate::utils;
+use crate::utils::{self, SkipReason, decode_utf8, relative_to_notes_dir};
+
+/// The progress of the indexing started by `Database::start_indexing_task`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum IndexingState {
+    #[default]
+    NotStarted,
+    Running,
+    Finished,
+    /// The indexing returned an error or panicked
+    Failed(String),
+}
 
 /// Async version of is_valid_note_file
 ///
 /// A valid note file must:
-/// - Have a .md extension
+/// - Have one of the note extensions (.md by default)
 /// - Have a filename that starts with '1' or '2' (for year 1xxx or 2xxx)
 ///   to filter out non-note files like README.md or monthly rollups
 /// - Be less than MAX_FILE_SIZE_BYTES in size
@@ -21,6 +38,110 @@ pub async fn is_valid_note_file(path: &Path) -> bool {
     utils::is_valid_note_file(path).unwrap_or(false)
 }
 
+/// Whether indexing leaves a file or directory in the notes directory alone
+///
+/// These are the database files and the paths ignored by the rules, such as
+/// the reserved directories.
+pub fn is_skipped(notes_dir: &Path, path: &Path, is_dir: bool, ignore_rules: &IgnoreRules) -> bool {
+    if path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(DB_FILENAME))
+    {
+        return true;
+    }
+
+    path.strip_prefix(notes_dir)
+        .is_ok_and(|relative| ignore_rules.is_ignored(relative, is_dir))
+}
+
+/// Common function words of the languages that are detected, by ISO 639-1 code
+const LANGUAGE_STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "was", "were", "to", "of", "in", "with", "for", "that",
+            "this", "it", "we", "not", "be", "have", "on", "about",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "sind", "nicht", "mit", "ein", "eine", "wir", "ich",
+            "zu", "den", "von", "auf", "für", "auch", "im", "werden", "wurde",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "sont", "un", "une", "des", "pas", "pour", "avec",
+            "dans", "que", "qui", "nous", "je", "sur", "du", "au", "ce",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "y", "es", "son", "un", "una", "que", "de", "con", "para",
+            "por", "no", "en", "del", "se", "lo", "pero",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "la", "di", "che", "e", "è", "un", "una", "per", "con", "non", "sono", "del",
+            "della", "gli", "le", "ma", "nel", "si",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "van", "niet", "met", "voor", "op", "dat", "die", "ik",
+            "we", "zijn", "te", "ook", "maar", "naar", "wordt",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "a", "os", "as", "e", "é", "um", "uma", "que", "de", "com", "para", "por", "não",
+            "em", "do", "da", "se", "mas",
+        ],
+    ),
+];
+
+/// Minimum number of function words needed to detect a language
+const MIN_LANGUAGE_STOPWORDS: usize = 2;
+
+/// Detect the dominant language of a note's content
+///
+/// This is a light heuristic that counts common function words of each
+/// language in `LANGUAGE_STOPWORDS`. Returns `None` if the language cannot
+/// be determined, e.g. for very short notes or other languages.
+pub fn detect_language(content: &str) -> Option<String> {
+    let words: Vec<String> = content
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut scores: Vec<(usize, &str)> = LANGUAGE_STOPWORDS
+        .iter()
+        .map(|(lang, stopwords)| {
+            let score = words
+                .iter()
+                .filter(|word| stopwords.contains(&word.as_str()))
+                .count();
+            (score, *lang)
+        })
+        .collect();
+    scores.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+
+    match scores.as_slice() {
+        [(best, lang), (second, _), ..] if *best >= MIN_LANGUAGE_STOPWORDS && best > second => {
+            Some(lang.to_string())
+        }
+        _ => None,
+    }
+}
+
 /// Get all note filepaths from the database
 pub async fn get_all_note_filepaths(pool: &Pool<Sqlite>) -> Result<Vec<String>> {
     let filepaths = sqlx::query_scalar::<_, String>(
@@ -35,14 +156,73 @@ pub async fn get_all_note_filepaths(pool: &Pool<Sqlite>) -> Result<Vec<String>>
     Ok(filepaths)
 }
 
+/// A note file that could not be indexed, e.g. because of broken frontmatter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailedFile {
+    /// The path of the file, relative to the notes directory
+    pub filepath: String,
+    /// Why the file could not be indexed
+    pub error: String,
+}
+
+/// Get the note files that could not be indexed, by path
+pub async fn get_failed_files(pool: &Pool<Sqlite>) -> Result<Vec<FailedFile>> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT filepath, error FROM failed_files ORDER BY filepath
+    "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(filepath, error)| FailedFile { filepath, error })
+        .collect())
+}
+
+/// The number of files the indexer came across
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexStats {
+    /// Files found in the notes directory, notes or not
+    pub files_seen: usize,
+    /// Note files passed on to be indexed
+    pub files_sent: usize,
+}
+
 /// Index all notes in the notes directory using channels
-pub async fn index_notes_with_channel(pool: Pool<Sqlite>, notes_dir: &Path) -> Result<()> {
+///
+/// Indexing waits while another process holds the maintenance lock.
+pub async fn index_notes_with_channel(pool: Pool<Sqlite>, notes_dir: &Path) -> Result<IndexStats> {
+    index_notes_with_metrics(pool, notes_dir, &MetricsRecorder::default()).await
+}
+
+/// Index all notes in the notes directory using channels, and record the
+/// durations of the pass and of processing each file in `metrics`
+pub async fn index_notes_with_metrics(
+    pool: Pool<Sqlite>,
+    notes_dir: &Path,
+    metrics: &MetricsRecorder,
+) -> Result<IndexStats> {
+    // Maintenance by another process goes first
+    wait_for_maintenance(&pool).await?;
+
+    let start = Instant::now();
+
     // First, get all existing note filepaths from the database
     let existing_filepaths = get_all_note_filepaths(&pool).await?;
 
-    // Create a HashSet to track which notes still exist on disk
+    // Create a HashSet to track which notes still exist on disk, including
+    // the ones that could not be indexed
     let mut filepaths_to_delete = existing_filepaths
         .into_iter()
+        .chain(
+            get_failed_files(&pool)
+                .await?
+                .into_iter()
+                .map(|failed| failed.filepath),
+        )
         .collect::<std::collections::HashSet<String>>();
 
     // Create a channel for sending file paths
@@ -51,9 +231,11 @@ pub async fn index_notes_with_channel(pool: Pool<Sqlite>, notes_dir: &Path) -> R
     // Spawn a task to collect note files and send them to the channel
     let notes_dir_clone = notes_dir.to_path_buf();
     let collector_task = tokio::spawn(async move {
-        if let Err(e) = collect_note_files_with_channel(&notes_dir_clone, tx).await {
+        let mut stats = IndexStats::default();
+        if let Err(e) = collect_note_files_with_channel(&notes_dir_clone, tx, &mut stats).await {
             eprintln!("Error collecting note files: {}", e);
         }
+        stats
     });
 
     // Process notes as they arrive through the channel
@@ -63,24 +245,28 @@ pub async fn index_notes_with_channel(pool: Pool<Sqlite>, notes_dir: &Path) -> R
     // Process files as they come in
     while let Some(file_path) = rx.recv().await {
         // Get the relative path from the notes directory
-        if let Ok(relative_path) = file_path
-            .strip_prefix(&notes_dir_clone)
+        if let Some(relative_path) = relative_to_notes_dir(&file_path, &notes_dir_clone)
             .map(|p| p.to_string_lossy().to_string())
         {
             // Remove this filepath from the set of files to delete
             filepaths_to_delete.remove(&relative_path);
 
             // Process the note file
-            if let Err(e) = process_note_file(&pool_clone, &notes_dir_clone, &file_path).await {
+            let processed = metrics.time(
+                Operation::ProcessNoteFile,
+                process_note_file(&pool_clone, &notes_dir_clone, &file_path),
+            );
+            if let Err(e) = processed.await {
                 eprintln!("Error processing note file {}: {}", file_path.display(), e);
             }
         }
     }
 
     // Wait for the collector task to complete
-    if let Err(e) = collector_task.await {
+    let stats = collector_task.await.unwrap_or_else(|e| {
         eprintln!("Error in collector task: {}", e);
-    }
+        IndexStats::default()
+    });
 
     // Delete notes that no longer exist on disk
     if !filepaths_to_delete.is_empty() {
@@ -90,141 +276,467 @@ pub async fn index_notes_with_channel(pool: Pool<Sqlite>, notes_dir: &Path) -> R
         }
     }
 
-    Ok(())
+    metrics.record(Operation::IndexingPass, start.elapsed());
+    Ok(stats)
 }
 
-/// Process a single note file
-pub async fn process_note_file(
-    pool: &Pool<Sqlite>,
-    notes_dir: &Path,
+/// Format the modification time of a file as stored in the database
+///
+/// The time is formatted as ISO8601 with millisecond precision.
+fn format_mtime(metadata: &std::fs::Metadata) -> String {
+    let mtime = metadata.modified().unwrap_or(SystemTime::now());
+    let datetime = chrono::DateTime::<chrono::Local>::from(mtime);
+    datetime.format("%Y-%m-%d %H:%M:%S.%3f").to_string()
+}
+
+/// Format the creation time of a note as stored in the database
+///
+/// The time is converted to UTC, so that the stored times sort correctly as
+/// text, and formatted like SQLite's `strftime('%Y-%m-%dT%H:%M:%fZ', ...)`.
+fn format_created_utc(created: &chrono::DateTime<chrono::Local>) -> String {
+    created
+        .with_timezone(&chrono::Utc)
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+/// The SHA-256 hash of a note file, as stored in the database
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// How indexing handles a file in the notes directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    /// The note is new or changed and will be indexed
+    WillIndex,
+    /// The note is already indexed and unchanged
+    Unchanged,
+    /// The file is not a note file
+    Skipped(SkipReason),
+    /// The note is marked as private
+    Private,
+    /// The file looks like a note, but cannot be parsed
+    Invalid(String),
+}
+
+impl FileStatus {
+    /// A short description of the status for reports
+    pub fn label(&self) -> String {
+        match self {
+            FileStatus::WillIndex => "will index".to_string(),
+            FileStatus::Unchanged => "already indexed, unchanged".to_string(),
+            FileStatus::Skipped(reason) => format!("skipped: {}", reason),
+            FileStatus::Private => "skipped: private".to_string(),
+            FileStatus::Invalid(_) => "skipped: invalid frontmatter".to_string(),
+        }
+    }
+}
+
+/// Classify a file the way indexing would, without writing to the database
+///
+/// # Parameters
+///
+/// * `file_path` - The file to classify
+/// * `indexed_mtime` - The modification time stored in the database for this file, if it is indexed
+pub async fn classify_note_file(
     file_path: &Path,
-) -> Result<()> {
-    // Get the file's modification time
+    indexed_mtime: Option<&str>,
+) -> Result<FileStatus> {
     let metadata = fs::metadata(file_path).await?;
-    let mtime = metadata.modified().unwrap_or(SystemTime::now());
 
-    // Convert SystemTime to DateTime<Local> and format as ISO8601 with millisecond precision
-    let datetime = chrono::DateTime::<chrono::Local>::from(mtime);
-    let mtime_str = datetime.format("%Y-%m-%d %H:%M:%S.%3f").to_string();
+    if let Some(reason) = utils::note_file_skip_reason(file_path, metadata.len()) {
+        return Ok(FileStatus::Skipped(reason));
+    }
+
+    if indexed_mtime == Some(format_mtime(&metadata).as_str()) {
+        return Ok(FileStatus::Unchanged);
+    }
+
+    let bytes = fs::read(file_path).await?;
+    let content = match decode_utf8(&bytes, &file_path.display().to_string()) {
+        Ok(content) => content,
+        Err(e) => return Ok(FileStatus::Invalid(e.to_string())),
+    };
+    match content.parse::<Note>() {
+        Ok(note) if note.frontmatter().is_private() => Ok(FileStatus::Private),
+        Ok(_) => Ok(FileStatus::WillIndex),
+        Err(e) => Ok(FileStatus::Invalid(e.to_string())),
+    }
+}
+
+/// Classify all files in the notes directory, without writing to the database
+///
+/// The database and the ignored directories and files are left out.
+///
+/// # Parameters
+///
+/// * `notes_dir` - The notes directory
+/// * `indexed_mtimes` - The modification times stored in the database, by relative filepath
+///
+/// # Returns
+///
+/// The relative filepaths and their status, sorted by filepath.
+pub async fn check_notes_dir(
+    notes_dir: &Path,
+    indexed_mtimes: &HashMap<String, String>,
+) -> Result<Vec<(String, FileStatus)>> {
+    let mut results = Vec::new();
+    let mut dirs = vec![notes_dir.to_path_buf()];
+    let ignore_rules = IgnoreRules::load(notes_dir);
+
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_dir = fs::metadata(&path).await?.is_dir();
+
+            // Notelog's own files are not worth reporting
+            if is_skipped(notes_dir, &path, is_dir, &ignore_rules) {
+                continue;
+            }
+
+            if is_dir {
+                dirs.push(path);
+                continue;
+            }
+
+            let relative_path = path
+                .strip_prefix(notes_dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+            let indexed_mtime = indexed_mtimes.get(&relative_path).map(String::as_str);
+            let status = classify_note_file(&path, indexed_mtime).await?;
+
+            results.push((relative_path, status));
+        }
+    }
+
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(results)
+}
+
+/// Process a single note file
+///
+/// All queries run on one connection, so the note can be (re-)indexed as part
+/// of a larger transaction.
+pub async fn process_note_file<'a, A>(db: A, notes_dir: &Path, file_path: &Path) -> Result<()>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    // Get the file's modification time
+    let metadata = fs::metadata(file_path).await?;
+    let mtime_str = format_mtime(&metadata);
 
     // Get the relative path from the notes directory
-    let relative_path = file_path
-        .strip_prefix(notes_dir)
-        .map_err(|e| NotelogError::PathError(format!("Failed to create relative path: {}", e)))?
+    let relative_path = relative_to_notes_dir(file_path, notes_dir)
+        .ok_or_else(|| {
+            NotelogError::PathError(format!(
+                "{} is not in the notes directory {}",
+                file_path.display(),
+                notes_dir.display()
+            ))
+        })?
         .to_string_lossy()
         .to_string();
 
+    let mut conn = db
+        .acquire()
+        .await
+        .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+
     // Check if the note already exists in the database with the same mtime
-    let existing = sqlx::query_as::<_, (i64, String)>(
+    let existing = sqlx::query_as::<_, (i64, String, Option<String>)>(
         r#"
             SELECT
                 id,
-                mtime
+                mtime,
+                note_id
             FROM notes
             WHERE filepath = ?
         "#,
     )
     .bind(&relative_path)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await
     .map_err(|e| DatabaseError::Query(e.to_string()))?;
 
     // If the note exists and has the same mtime, skip processing
-    if let Some((_, db_mtime)) = &existing {
-        if db_mtime == &mtime_str {
-            return Ok(());
-        }
+    if let Some((_, db_mtime, _)) = &existing
+        && db_mtime == &mtime_str
+    {
+        return Ok(());
+    }
+
+    // Files that could not be indexed are not read again until they change
+    let failed_mtime =
+        sqlx::query_scalar::<_, String>("SELECT mtime FROM failed_files WHERE filepath = ?")
+            .bind(&relative_path)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+    if failed_mtime.as_deref() == Some(mtime_str.as_str()) {
+        return Ok(());
     }
 
     // Read the file content
-    let content = fs::read_to_string(file_path).await?;
+    let bytes = fs::read(file_path).await?;
+    let hash = content_hash(&bytes);
+
+    // Parse the note, recording the error if that fails. Invalid UTF-8 is
+    // replaced, so that one bad byte does not hide the whole note from search,
+    // but the file is still reported.
+    let (parsed, warning) = match decode_utf8(&bytes, &relative_path) {
+        Ok(content) => (content.parse::<Note>(), None),
+        Err(e) => (String::from_utf8_lossy(&bytes).parse::<Note>(), Some(e)),
+    };
+    let note = match (parsed, &warning) {
+        (Ok(note), Some(e)) => {
+            let error = format!("indexed with the invalid bytes replaced: {}", e);
+            record_failed_file(&mut *conn, &relative_path, &mtime_str, &error).await?;
+            note
+        }
+        (Ok(note), None) => note,
+        (Err(e), _) => {
+            // The invalid bytes are the more likely cause of the problem
+            let e = warning.unwrap_or(e);
+            record_failed_file(&mut *conn, &relative_path, &mtime_str, &e.to_string()).await?;
+            return Err(e);
+        }
+    };
+
+    if failed_mtime.is_some() && warning.is_none() {
+        sqlx::query("DELETE FROM failed_files WHERE filepath = ?")
+            .bind(&relative_path)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+    }
 
-    // Parse the note
-    let note = content.parse::<Note>()?;
+    // Private notes are not indexed; remove the note if it was indexed before
+    // it was marked as private
+    if note.frontmatter().is_private() {
+        if existing.is_some() {
+            delete_notes_by_filepaths(&mut *conn, &[relative_path]).await?;
+        }
+        return Ok(());
+    }
+
+    // Notes edited by hand can exceed the tag limit; index them anyway, but warn
+    let tag_count = note.frontmatter().tags().len();
+    if tag_count > MAX_TAGS_PER_NOTE {
+        eprintln!(
+            "Warning: note {} has {} tags (maximum is {})",
+            relative_path, tag_count, MAX_TAGS_PER_NOTE
+        );
+    }
+    if let Some(invalid_due) = note.frontmatter().invalid_due() {
+        eprintln!(
+            "Warning: note {} has an invalid due date '{}' (expected YYYY-MM-DD); it is indexed as not due",
+            relative_path, invalid_due
+        );
+    }
 
     // Convert frontmatter to JSON
     let metadata_json = serde_json::to_string(note.frontmatter())
         .map_err(|e| DatabaseError::Serialization(e.to_string()))?;
 
+    // English notes are additionally indexed with stemming
+    let lang = detect_language(note.content());
+
+    let created_utc = format_created_utc(note.frontmatter().created());
+    let row = NoteRow {
+        mtime: &mtime_str,
+        content_hash: &hash,
+        note_id: note.frontmatter().id().map(|id| id.as_str()),
+        created_utc: &created_utc,
+        metadata_json: &metadata_json,
+        content: note.content(),
+        lang: lang.as_deref(),
+    };
+
+    // A file that was replaced by a different note (e.g. deleted and recreated
+    // while the MCP server was not running) gets a new row, so that the old
+    // note's ID no longer resolves to the new note
+    let new_note_id = note.frontmatter().id().map(|id| id.as_str());
+    if let Some((id, _, Some(old_note_id))) = &existing
+        && new_note_id != Some(old_note_id.as_str())
+    {
+        eprintln!(
+            "Note {} changed its ID from {} to {}",
+            relative_path,
+            old_note_id,
+            new_note_id.unwrap_or("(none)")
+        );
+
+        return replace_note(&mut *conn, id, &relative_path, &row).await;
+    }
+
     // Insert or update the note in the database
-    if let Some((id, _)) = &existing {
-        update_note(pool, id, &mtime_str, &metadata_json, note.content()).await?;
+    if let Some((id, _, _)) = &existing {
+        update_note(&mut *conn, id, &row).await?;
     } else {
-        insert_note(
-            pool,
-            &relative_path,
-            &mtime_str,
-            &metadata_json,
-            note.content(),
-        )
-        .await?;
+        insert_note(&mut *conn, &relative_path, &row).await?;
     }
 
     Ok(())
 }
 
+/// The columns of a note that indexing writes
+struct NoteRow<'a> {
+    /// The modification time of the file, as formatted by `format_mtime`
+    mtime: &'a str,
+    /// The hash of the file, from `content_hash`
+    content_hash: &'a str,
+    /// The ID from the frontmatter, if any
+    note_id: Option<&'a str>,
+    /// The creation time, as formatted by `format_created_utc`
+    created_utc: &'a str,
+    /// The frontmatter as JSON
+    metadata_json: &'a str,
+    /// The content below the frontmatter
+    content: &'a str,
+    /// The detected language, if any
+    lang: Option<&'a str>,
+}
+
 /// Update an existing note in the database
-async fn update_note(
-    pool: &Pool<Sqlite>,
-    id: &i64,
-    mtime: &str,
-    metadata_json: &str,
-    content: &str,
-) -> Result<()> {
+async fn update_note<'e, E>(executor: E, id: &i64, row: &NoteRow<'_>) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
     sqlx::query(
         r#"
         UPDATE notes
         SET
             mtime = ?,
+            content_hash = ?,
+            note_id = ?,
+            created_utc = ?,
             metadata = ?,
-            content = ?
+            content = ?,
+            lang = ?
+        WHERE id = ?
+    "#,
+    )
+    .bind(row.mtime)
+    .bind(row.content_hash)
+    .bind(row.note_id)
+    .bind(row.created_utc)
+    .bind(row.metadata_json)
+    .bind(row.content)
+    .bind(row.lang)
+    .bind(id)
+    .execute(executor)
+    .await
+    .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Replace an existing note with a new one under the same filepath
+///
+/// Deleting and re-inserting the row (instead of updating it) lets the triggers
+/// update the tag usage counts and the fulltext index as for any other deletion
+/// and insertion.
+async fn replace_note<'a, A>(db: A, id: &i64, filepath: &str, row: &NoteRow<'_>) -> Result<()>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut tx = db
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM notes
         WHERE id = ?
     "#,
     )
-    .bind(mtime)
-    .bind(metadata_json)
-    .bind(content)
     .bind(id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| DatabaseError::Query(e.to_string()))?;
 
+    insert_note(&mut *tx, filepath, row).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
     Ok(())
 }
 
 /// Insert a new note into the database
-async fn insert_note(
-    pool: &Pool<Sqlite>,
-    filepath: &str,
-    mtime: &str,
-    metadata_json: &str,
-    content: &str,
-) -> Result<()> {
+async fn insert_note<'e, E>(executor: E, filepath: &str, row: &NoteRow<'_>) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
     sqlx::query(
         r#"
         INSERT INTO notes (
             filepath,
             mtime,
+            content_hash,
+            note_id,
+            created_utc,
             metadata,
-            content
-        ) VALUES (?, ?, ?, ?)
+            content,
+            lang
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
     "#,
     )
     .bind(filepath)
-    .bind(mtime)
-    .bind(metadata_json)
-    .bind(content)
-    .execute(pool)
+    .bind(row.mtime)
+    .bind(row.content_hash)
+    .bind(row.note_id)
+    .bind(row.created_utc)
+    .bind(row.metadata_json)
+    .bind(row.content)
+    .bind(row.lang)
+    .execute(executor)
     .await
     .map_err(|e| DatabaseError::Query(e.to_string()))?;
 
     Ok(())
 }
 
+/// Record that a note file could not be indexed (completely)
+///
+/// The file is not read again until its modification time changes.
+async fn record_failed_file<'e, E>(
+    executor: E,
+    filepath: &str,
+    mtime: &str,
+    error: &str,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    sqlx::query("INSERT OR REPLACE INTO failed_files (filepath, mtime, error) VALUES (?, ?, ?)")
+        .bind(filepath)
+        .bind(mtime)
+        .bind(error)
+        .execute(executor)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    Ok(())
+}
+
 /// Delete notes from the database by their filepaths
-pub async fn delete_notes_by_filepaths(pool: &Pool<Sqlite>, filepaths: &[String]) -> Result<()> {
+///
+/// Files that could not be indexed are forgotten as well.
+pub async fn delete_notes_by_filepaths<'a, A>(db: A, filepaths: &[String]) -> Result<()>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
     // Use a transaction to ensure all deletions are atomic
-    let mut tx = pool
+    let mut tx = db
         .begin()
         .await
         .map_err(|e| DatabaseError::Query(e.to_string()))?;
@@ -242,6 +754,12 @@ pub async fn delete_notes_by_filepaths(pool: &Pool<Sqlite>, filepaths: &[String]
         .execute(&mut *tx)
         .await
         .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        sqlx::query("DELETE FROM failed_files WHERE filepath = ?")
+            .bind(filepath)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
     }
 
     // Commit the transaction
@@ -253,27 +771,51 @@ pub async fn delete_notes_by_filepaths(pool: &Pool<Sqlite>, filepaths: &[String]
 }
 
 /// Collect note files and send them to a channel
+///
+/// The directories are walked with an explicit stack instead of recursion, and
+/// one at a time, so that neither deep trees nor a slow consumer of the channel
+/// pile up stack frames or open directory handles.
 async fn collect_note_files_with_channel(
     notes_dir: &Path,
     tx: tokio::sync::mpsc::Sender<PathBuf>,
+    stats: &mut IndexStats,
 ) -> Result<()> {
-    // Process the current directory
-    let mut entries = fs::read_dir(notes_dir).await?;
-
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
-        let metadata = fs::metadata(&path).await?;
+    let mut dirs = vec![notes_dir.to_path_buf()];
+    let ignore_rules = IgnoreRules::load(notes_dir);
+
+    while let Some(dir) = dirs.pop() {
+        // Read the whole directory first, so that its handle is closed before
+        // waiting on the channel
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_dir = fs::metadata(&path).await?.is_dir();
+
+            // Notelog's own files are not notes, and ignored directories are
+            // not even read
+            if is_skipped(notes_dir, &path, is_dir, &ignore_rules) {
+                continue;
+            }
 
-        if metadata.is_dir() {
-            // Process subdirectories recursively
-            Box::pin(collect_note_files_with_channel(&path, tx.clone())).await?;
-            continue;
+            if is_dir {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
         }
-
-        if is_valid_note_file(&path).await {
-            // Send valid note files to the channel
-            if let Err(e) = tx.send(path).await {
-                eprintln!("Error sending file path to channel: {}", e);
+        drop(entries);
+
+        for path in files {
+            stats.files_seen += 1;
+
+            if is_valid_note_file(&path).await {
+                // Send valid note files to the channel
+                if let Err(e) = tx.send(path).await {
+                    eprintln!("Error sending file path to channel: {}", e);
+                    continue;
+                }
+                stats.files_sent += 1;
             }
         }
     }
@@ -0,0 +1,133 @@
+//! Consistency checks and repair of the fulltext indexes
+
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+
+use super::lock::timestamp;
+use crate::error::{DatabaseError, Result};
+
+/// How long opening the database skips the check after one has run
+///
+/// The check reads the whole fulltext index, which takes a while for large
+/// notes directories, so it does not run on every command.
+const FTS_CHECK_INTERVAL: chrono::Duration = chrono::Duration::hours(24);
+
+/// The fulltext tables and the condition selecting the notes each one indexes
+const FTS_TABLES: &[(&str, &str)] = &[("notes_fts", "1"), ("notes_fts_stemmed", "n.lang = 'en'")];
+
+/// Check whether the fulltext indexes are consistent with the notes table
+///
+/// Runs the FTS5 integrity check on each fulltext table and verifies that it
+/// contains exactly the notes it should index.
+pub async fn check_fts_integrity(pool: &Pool<Sqlite>) -> Result<bool> {
+    for (table, condition) in FTS_TABLES {
+        // The integrity check fails with an error if the index is corrupt. It
+        // runs in a transaction that is rolled back, so that a failed check
+        // does not leave a stale read snapshot on the pooled connection.
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+        let integrity_check = format!("INSERT INTO {table}({table}) VALUES('integrity-check')");
+        let result = sqlx::query(&integrity_check).execute(&mut *tx).await;
+        tx.rollback()
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        if result.is_err() {
+            return Ok(false);
+        }
+
+        let mismatch_query = format!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM notes n
+                 WHERE {condition} AND n.id NOT IN (SELECT rowid FROM {table}))
+                +
+                (SELECT COUNT(*) FROM {table}
+                 WHERE rowid NOT IN (SELECT n.id FROM notes n WHERE {condition}))
+            "#
+        );
+        let mismatches = sqlx::query_scalar::<_, i64>(&mismatch_query)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        if mismatches > 0 {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Whether the fulltext indexes were last checked longer than FTS_CHECK_INTERVAL ago
+pub async fn fts_check_due(pool: &Pool<Sqlite>) -> Result<bool> {
+    let recent = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM fts_check WHERE id = 1 AND checked >= ?",
+    )
+    .bind(timestamp(Utc::now() - FTS_CHECK_INTERVAL))
+    .fetch_one(pool)
+    .await
+    .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    Ok(recent == 0)
+}
+
+/// Record that the fulltext indexes were checked just now
+pub async fn record_fts_check(pool: &Pool<Sqlite>) -> Result<()> {
+    sqlx::query("INSERT OR REPLACE INTO fts_check (id, checked) VALUES (1, ?)")
+        .bind(timestamp(Utc::now()))
+        .execute(pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Rebuild the fulltext indexes from the notes table
+pub async fn rebuild_fts(pool: &Pool<Sqlite>) -> Result<()> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    for (table, condition) in FTS_TABLES {
+        // Rebuild the index structures first, so that deleting the rows works
+        // even if the index was corrupt
+        let statements = [
+            format!("INSERT INTO {table}({table}) VALUES('rebuild')"),
+            format!("DELETE FROM {table}"),
+            format!(
+                r#"
+                INSERT INTO {table}(rowid, content, tags)
+                SELECT
+                    n.id,
+                    n.content,
+                    COALESCE(
+                        (
+                            SELECT group_concat('+' || value, ' ')
+                            FROM json_each(n.metadata, '$.tags')
+                        ),
+                        ''
+                    )
+                FROM notes n
+                WHERE {condition}
+                "#
+            ),
+        ];
+
+        for statement in &statements {
+            sqlx::query(statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| DatabaseError::Query(e.to_string()))?;
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! An advisory lock for maintenance that rewrites the index
+//!
+//! Maintenance such as rebuilding the fulltext index must not interleave with
+//! the updates of a running MCP server. The lock is a row in the database, so
+//! that it is shared by all processes using the notes directory; the file
+//! monitor and the background verification pause while it is held.
+
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::error::{DatabaseError, Result};
+
+/// How often a waiting process checks whether the lock was released
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long a lock is honored, in case its holder crashed without releasing it
+const LOCK_STALE_AFTER: chrono::Duration = chrono::Duration::hours(1);
+
+/// Distinguishes the holders within one process
+static NEXT_HOLDER: AtomicU64 = AtomicU64::new(0);
+
+/// The maintenance lock, held until it is released
+#[derive(Debug)]
+#[must_use = "the lock must be released"]
+pub struct MaintenanceLock {
+    pool: Pool<Sqlite>,
+    holder: String,
+}
+
+impl MaintenanceLock {
+    /// Release the lock
+    pub async fn release(self) -> Result<()> {
+        sqlx::query("DELETE FROM maintenance_lock WHERE id = 1 AND holder = ?")
+            .bind(&self.holder)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// A time as stored, in UTC so that the times sort as text
+pub(super) fn timestamp(time: chrono::DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// When the current lock was acquired, unless it is free or stale
+async fn lock_acquired(pool: &Pool<Sqlite>) -> Result<Option<String>> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT acquired FROM maintenance_lock WHERE id = 1 AND acquired >= ?",
+    )
+    .bind(timestamp(Utc::now() - LOCK_STALE_AFTER))
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| DatabaseError::Query(e.to_string()).into())
+}
+
+/// Acquire the maintenance lock
+///
+/// If another process holds the lock, this waits for it with `wait`, and
+/// fails with `DatabaseError::MaintenanceLocked` otherwise.
+pub async fn acquire_maintenance_lock(pool: &Pool<Sqlite>, wait: bool) -> Result<MaintenanceLock> {
+    let holder = format!(
+        "{}:{}",
+        std::process::id(),
+        NEXT_HOLDER.fetch_add(1, Ordering::Relaxed)
+    );
+
+    loop {
+        let now = Utc::now();
+
+        // Take over locks whose holder is gone
+        sqlx::query("DELETE FROM maintenance_lock WHERE id = 1 AND acquired < ?")
+            .bind(timestamp(now - LOCK_STALE_AFTER))
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO maintenance_lock (id, holder, acquired) VALUES (1, ?, ?)",
+        )
+        .bind(&holder)
+        .bind(timestamp(now))
+        .execute(pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        if inserted.rows_affected() == 1 {
+            return Ok(MaintenanceLock {
+                pool: pool.clone(),
+                holder,
+            });
+        }
+
+        if !wait {
+            let since = lock_acquired(pool).await?.unwrap_or_default();
+            return Err(DatabaseError::MaintenanceLocked(since).into());
+        }
+
+        tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+    }
+}
+
+/// Wait until no process holds the maintenance lock
+pub async fn wait_for_maintenance(pool: &Pool<Sqlite>) -> Result<()> {
+    while lock_acquired(pool).await?.is_some() {
+        tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Shrinking and optimizing the database file
+
+use sqlx::{Pool, Sqlite};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::db::DB_FILENAME;
+use crate::error::{DatabaseError, Result};
+
+/// The fulltext tables, whose index segments are merged by the maintenance
+const FTS_TABLES: &[&str] = &["notes_fts", "notes_fts_stemmed"];
+
+/// The result of optimizing the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceStats {
+    /// The size of the database files before, in bytes
+    pub size_before: u64,
+    /// The size of the database files afterwards, in bytes
+    pub size_after: u64,
+    /// How long the maintenance took
+    pub duration: Duration,
+}
+
+/// The size of the database in the notes directory, in bytes
+///
+/// Includes the write-ahead log, which holds changes that were not yet
+/// written to the database file.
+pub fn database_size(notes_dir: &Path) -> Result<u64> {
+    let mut size = fs::metadata(notes_dir.join(DB_FILENAME))?.len();
+    if let Ok(wal) = fs::metadata(notes_dir.join(format!("{}-wal", DB_FILENAME))) {
+        size += wal.len();
+    }
+    Ok(size)
+}
+
+/// Merge the fulltext index segments, rebuild the database file without
+/// unused pages and update the statistics of the query planner
+///
+/// `VACUUM` rewrites the whole database, so this takes a while for large
+/// databases.
+pub async fn optimize_database(pool: &Pool<Sqlite>, notes_dir: &Path) -> Result<MaintenanceStats> {
+    let started = Instant::now();
+    let size_before = database_size(notes_dir)?;
+
+    let mut statements: Vec<String> = FTS_TABLES
+        .iter()
+        .map(|table| format!("INSERT INTO {table}({table}) VALUES('optimize')"))
+        .collect();
+    statements.push("VACUUM".to_string());
+    statements.push("PRAGMA optimize".to_string());
+    // Write the changes into the database file, so that the log is emptied
+    statements.push("PRAGMA wal_checkpoint(TRUNCATE)".to_string());
+
+    for statement in &statements {
+        sqlx::query(statement)
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::Query(format!("{}: {}", statement, e)))?;
+    }
+
+    Ok(MaintenanceStats {
+        size_before,
+        size_after: database_size(notes_dir)?,
+        duration: started.elapsed(),
+    })
+}
+
+/// Update the statistics of the query planner where they are out of date
+///
+/// This is cheap, and meant to be run before closing long-lived connections.
+pub async fn pragma_optimize(pool: &Pool<Sqlite>) -> Result<()> {
+    sqlx::query("PRAGMA optimize")
+        .execute(pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Format a size in bytes for people, e.g. `1.5 MiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 bytes");
+        assert_eq!(format_size(1023), "1023 bytes");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(350 * 1024 * 1024), "350.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -0,0 +1,171 @@
+//! Locating search matches inside a note
+//!
+//! The full-text index only tells which notes match a query, not where. To
+//! open a note at the match, the search terms are looked up again in the text
+//! of the note, splitting both into words the way the FTS tokenizer does.
+//! This is a best-effort approximation: matches that only exist through
+//! stemming (e.g. `deploying` finding `deployed`) or through tags have no line.
+
+/// Operators of the FTS5 query syntax, which are not search terms
+const OPERATORS: [&str; 4] = ["AND", "OR", "NOT", "NEAR"];
+
+/// Extract the search terms of a query, as lowercase words
+///
+/// Each term is a list of words: one for a plain word, several for a phrase or
+/// a hyphenated word. Tags (`+tag`), `source:` and `lang:` filters, column
+/// filters other than `content:`, operators and `NEAR` distances are skipped.
+pub fn search_terms(query: &str) -> Vec<Vec<String>> {
+    let mut terms = Vec::new();
+    let mut after_comma = false;
+
+    for word in split_query_words(query) {
+        let word = word.trim_start_matches('(');
+        let word = word.strip_prefix("NEAR(").unwrap_or(word);
+        let word = word.trim_end_matches(')');
+
+        // The distance of a `NEAR(a b, 5)` group follows a comma
+        let is_distance = after_comma || word.starts_with(',');
+        after_comma = word.ends_with(',');
+        if is_distance || word.is_empty() || word.starts_with('+') || OPERATORS.contains(&word) {
+            continue;
+        }
+
+        // Column and other filters, such as `tags:"+project"` or `source:cli`
+        let word = match word.split_once(':') {
+            Some(("content", rest)) => rest,
+            Some((prefix, _)) if prefix.chars().all(|c| c.is_ascii_alphabetic()) => continue,
+            _ => word,
+        };
+
+        let words = tokenize(word);
+        if !words.is_empty() {
+            terms.push(words);
+        }
+    }
+
+    terms
+}
+
+/// Find the first line of a note that contains one of the search terms
+///
+/// The text is the whole note file; the frontmatter is skipped, but counted, so
+/// that the returned 1-based line number can be passed to an editor.
+pub fn first_match_line(text: &str, terms: &[Vec<String>]) -> Option<usize> {
+    let mut lines = text.lines().enumerate().peekable();
+
+    // The frontmatter holds the tags, not the content of the note
+    if lines
+        .next_if(|(_, line)| line.trim_end() == "---")
+        .is_some()
+    {
+        lines.by_ref().find(|(_, line)| line.trim_end() == "---")?;
+    }
+
+    lines
+        .find(|(_, line)| {
+            let words = tokenize(line);
+            terms.iter().any(|term| {
+                words
+                    .windows(term.len())
+                    .any(|window| window == term.as_slice())
+            })
+        })
+        .map(|(i, _)| i + 1)
+}
+
+/// Split a query at whitespace, keeping quoted phrases together
+fn split_query_words(query: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut in_quotes = false;
+
+    for (i, c) in query.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+
+        if c.is_whitespace() && !in_quotes {
+            if let Some(start) = start.take() {
+                words.push(&query[start..i]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+
+    if let Some(start) = start {
+        words.push(&query[start..]);
+    }
+
+    words
+}
+
+/// Split text into lowercase words of letters and digits
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(query: &str) -> Vec<String> {
+        search_terms(query)
+            .into_iter()
+            .map(|term| term.join(" "))
+            .collect()
+    }
+
+    #[test]
+    fn test_search_terms() {
+        assert_eq!(terms("Deploy +project"), ["deploy"]);
+        assert_eq!(
+            terms("\"project plan\" on-call"),
+            ["project plan", "on call"]
+        );
+        assert_eq!(
+            terms("(alpha OR beta) NOT gamma source:cli lang:de"),
+            ["alpha", "beta", "gamma"]
+        );
+        assert_eq!(terms("NEAR(deploy failure, 5)"), ["deploy", "failure"]);
+        assert_eq!(terms("tags:\"+project\" AND content:deploy"), ["deploy"]);
+        assert!(terms("+todo").is_empty());
+    }
+
+    #[test]
+    fn test_first_match_line() {
+        let note = "---\nid: abc123\ncreated: 2025-04-01T12:00:00+00:00\ntags:\n  - deploy\n---\n\n# Release\n\nThe Deploy failed\n";
+        let terms = search_terms("deploy");
+
+        // The tag in the frontmatter is not a match
+        assert_eq!(first_match_line(note, &terms), Some(10));
+
+        // Phrases must match as a whole
+        assert_eq!(
+            first_match_line(note, &search_terms("\"deploy failed\"")),
+            Some(10)
+        );
+        assert_eq!(
+            first_match_line(note, &search_terms("\"failed deploy\"")),
+            None
+        );
+
+        // Words only match as a whole
+        assert_eq!(first_match_line(note, &search_terms("ploy")), None);
+    }
+
+    #[test]
+    fn test_first_match_line_on_line_7() {
+        let note = "---\ncreated: 2025-04-01T12:00:00+00:00\n---\n\n# Notes\n\nThe zeppelin landed\nzeppelins are not counted\n";
+        assert_eq!(first_match_line(note, &search_terms("zeppelin")), Some(7));
+
+        // Without frontmatter, the lines are counted from the start
+        assert_eq!(
+            first_match_line("a\nb\nc\nd\ne\nf\nzeppelin", &search_terms("zeppelin")),
+            Some(7)
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Timing of the database operations, for `Database::metrics`
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// An operation whose durations are recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// A search, from checking the query to parsing the found notes
+    Search,
+    /// Reading, parsing and storing a single note file
+    ProcessNoteFile,
+    /// Indexing the whole notes directory
+    IndexingPass,
+}
+
+impl Operation {
+    /// All operations, in the order they are reported
+    pub const ALL: [Operation; 3] = [
+        Operation::Search,
+        Operation::ProcessNoteFile,
+        Operation::IndexingPass,
+    ];
+
+    /// The name of the operation in reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Search => "search",
+            Operation::ProcessNoteFile => "process_note_file",
+            Operation::IndexingPass => "indexing_pass",
+        }
+    }
+}
+
+/// How often an operation ran and how long it took
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationMetrics {
+    pub count: u64,
+    #[serde(rename = "total_ms", with = "millis")]
+    pub total: Duration,
+    #[serde(rename = "max_ms", with = "millis")]
+    pub max: Duration,
+}
+
+impl OperationMetrics {
+    /// The mean duration, zero if the operation never ran
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.count) {
+            Ok(0) => Duration::ZERO,
+            Ok(count) => self.total / count,
+            Err(_) => self.total.div_f64(self.count as f64),
+        }
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+}
+
+/// The durations of the operations of a database since it was opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub search: OperationMetrics,
+    pub process_note_file: OperationMetrics,
+    pub indexing_pass: OperationMetrics,
+}
+
+impl Metrics {
+    /// The metrics of an operation
+    pub fn get(&self, operation: Operation) -> &OperationMetrics {
+        match operation {
+            Operation::Search => &self.search,
+            Operation::ProcessNoteFile => &self.process_note_file,
+            Operation::IndexingPass => &self.indexing_pass,
+        }
+    }
+
+    fn get_mut(&mut self, operation: Operation) -> &mut OperationMetrics {
+        match operation {
+            Operation::Search => &mut self.search,
+            Operation::ProcessNoteFile => &mut self.process_note_file,
+            Operation::IndexingPass => &mut self.indexing_pass,
+        }
+    }
+
+    /// Format the metrics as a line per operation, e.g.
+    /// `search  12 runs, mean 3.4 ms, max 10.2 ms, total 40.8 ms`
+    pub fn report(&self) -> String {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+        let mut report = String::new();
+        for operation in Operation::ALL {
+            let metrics = self.get(operation);
+            let line = if metrics.count == 0 {
+                format!("{:<18} no runs", operation.name())
+            } else {
+                format!(
+                    "{:<18} {} {}, mean {:.1} ms, max {:.1} ms, total {:.1} ms",
+                    operation.name(),
+                    metrics.count,
+                    if metrics.count == 1 { "run" } else { "runs" },
+                    ms(metrics.mean()),
+                    ms(metrics.max),
+                    ms(metrics.total)
+                )
+            };
+            report.push_str(&line);
+            report.push('\n');
+        }
+
+        report
+    }
+}
+
+/// Where the operations of a database record their durations
+///
+/// The clones of a recorder share the metrics, like the clones of a
+/// `Database` share the connection pool.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRecorder(Arc<Mutex<Metrics>>);
+
+impl MetricsRecorder {
+    /// Record a run of an operation
+    pub fn record(&self, operation: Operation, elapsed: Duration) {
+        // The metrics are only counters, so those of a panicked thread are fine
+        let mut metrics = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        metrics.get_mut(operation).record(elapsed);
+    }
+
+    /// Run an operation and record how long it took
+    pub async fn time<T>(&self, operation: Operation, future: impl Future<Output = T>) -> T {
+        let start = Instant::now();
+        let output = future.await;
+        self.record(operation, start.elapsed());
+        output
+    }
+
+    /// The metrics recorded so far
+    pub fn snapshot(&self) -> Metrics {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// (De)serialize durations as fractional milliseconds
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        // Rounded to whole nanoseconds, so that durations survive a round trip
+        let ms = f64::deserialize(deserializer)?;
+        Ok(Duration::from_nanos((ms * 1_000_000.0).round() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_report() {
+        let recorder = MetricsRecorder::default();
+        let clone = recorder.clone();
+        recorder.record(Operation::Search, Duration::from_millis(3));
+        clone.record(Operation::Search, Duration::from_micros(10_400));
+        recorder.record(Operation::IndexingPass, Duration::from_secs(2));
+
+        let metrics = recorder.snapshot();
+        assert_eq!(metrics.search.count, 2);
+        assert_eq!(metrics.search.total, Duration::from_micros(13_400));
+        assert_eq!(metrics.search.max, Duration::from_micros(10_400));
+        assert_eq!(metrics.search.mean(), Duration::from_micros(6_700));
+        assert_eq!(metrics.process_note_file.mean(), Duration::ZERO);
+
+        assert_eq!(
+            metrics.report(),
+            "search             2 runs, mean 6.7 ms, max 10.4 ms, total 13.4 ms\n\
+             process_note_file  no runs\n\
+             indexing_pass      1 run, mean 2000.0 ms, max 2000.0 ms, total 2000.0 ms\n"
+        );
+
+        let json = serde_json::to_value(metrics).unwrap();
+        assert_eq!(json["search"]["count"], 2);
+        assert!((json["search"]["total_ms"].as_f64().unwrap() - 13.4).abs() < 1e-9);
+        assert_eq!(json["indexing_pass"]["max_ms"], 2000.0);
+        assert_eq!(serde_json::from_value::<Metrics>(json).unwrap(), metrics);
+    }
+
+    #[test]
+    fn test_time() {
+        let recorder = MetricsRecorder::default();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let output = rt.block_on(recorder.time(Operation::ProcessNoteFile, async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            42
+        }));
+        assert_eq!(output, 42);
+
+        let metrics = recorder.snapshot().process_note_file;
+        assert_eq!(metrics.count, 1);
+        assert!(metrics.max >= Duration::from_millis(5));
+    }
+}
@@ -1,39 +1,111 @@
 //! Database implementation for notelog
 
+mod backup;
 mod helpers;
+mod ignore;
 mod indexing;
+mod integrity;
+mod lock;
+mod maintenance;
+mod matches;
+mod metrics;
 mod monitoring;
+mod prune;
+mod search_query;
 #[cfg(test)]
 mod tests;
+mod timeline;
+mod verify;
 
 #[cfg(test)]
-pub use indexing::{delete_notes_by_filepaths, get_all_note_filepaths};
+pub use indexing::{delete_notes_by_filepaths, detect_language, get_all_note_filepaths};
 
+pub use backup::BACKUPS_DIR;
+pub use ignore::{IGNORE_FILENAME, IgnoreRules, RESERVED_DIRS};
 // Re-export indexing functions
-pub use indexing::{index_notes_with_channel, is_valid_note_file, process_note_file};
+pub use indexing::{
+    FailedFile, FileStatus, IndexStats, check_notes_dir, index_notes_with_channel,
+    is_valid_note_file, process_note_file,
+};
+pub use lock::MaintenanceLock;
+pub use maintenance::{MaintenanceStats, format_size};
+pub use matches::{first_match_line, search_terms};
+pub use metrics::{Metrics, Operation, OperationMetrics};
+pub use prune::ExpiredNote;
+pub use search_query::{SearchOrder, SearchQuery};
+pub use timeline::{MonthCount, TIMELINE_CO_TAGS, TagCount, TagTimeline};
+pub use verify::VerifyStats;
 // Re-export monitoring functions
-pub use monitoring::start_file_monitoring;
+pub use monitoring::{WATCH_POLL_INTERVAL, start_file_monitoring};
 // Re-export helper functions
 pub use helpers::{
-    add_date_conditions, check_multiple_id_matches, count_notes_with_id_prefix,
-    is_valid_date_range, json_to_note, process_search_query,
+    add_date_conditions, check_multiple_id_matches, check_query_size, count_notes_with_id_prefix,
+    due_until, extract_lang_filters, extract_source_filters, id_prefix_end, json_to_note,
+    lang_filter_condition, path_prefix_condition, process_search_query, source_filter_condition,
+    source_filter_params, unique_id_prefix,
 };
+
+use chrono::{DateTime, Local, NaiveDate};
+use futures_util::TryStreamExt;
+use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::{Pool, Sqlite, SqlitePool, migrate::MigrateDatabase};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+
+use indexing::{IndexingState, index_notes_with_metrics};
+use metrics::MetricsRecorder;
 
 use crate::core::note::Note;
+use crate::core::retention::RetentionPolicy;
+use crate::core::tags::Tag;
 
+use crate::config;
 use crate::error::{DatabaseError, Result};
+use crate::utils::{check_dir_writable, restrict_file_permissions};
 
-const DB_FILENAME: &str = ".notes.db";
+pub const DB_FILENAME: &str = ".notes.db";
+
+/// A note found by a search
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// The note as indexed
+    pub note: Note,
+    /// The FTS5 bm25 score, where lower (more negative) is better
+    pub rank: f64,
+    /// The path of the note file, relative to the notes directory
+    pub filepath: String,
+}
+
+/// The result of looking up a note by an ID prefix
+#[derive(Debug, Clone)]
+pub enum PrefixMatch {
+    /// Exactly one note has an ID with the prefix
+    Found(Note),
+    /// No note has an ID with the prefix
+    NotFound,
+    /// The given number of notes have an ID with the prefix
+    Ambiguous(usize),
+}
 
 /// Database connection pool
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Database {
     /// The SQLite connection pool
     pool: Pool<Sqlite>,
     /// The path to the notes directory
     notes_dir: PathBuf,
+    /// Whether the fulltext index was found inconsistent and rebuilt during initialization
+    fts_rebuilt: bool,
+    /// Whether the notes directory could be read at the last check of the monitoring
+    notes_dir_available: Arc<AtomicBool>,
+    /// The progress of the indexing started by `start_indexing_task`
+    indexing: Arc<watch::Sender<IndexingState>>,
+    /// The durations of searches and indexing, shared by the background tasks
+    metrics: MetricsRecorder,
 }
 
 impl Database {
@@ -49,6 +121,12 @@ impl Database {
             Sqlite::create_database(&db_url)
                 .await
                 .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+
+            // The index holds the content of every note, so it is at least as
+            // sensitive as the notes themselves
+            if config::get().restrict_permissions() {
+                restrict_file_permissions(&db_path)?;
+            }
         }
 
         let pool = SqlitePool::connect(&db_url)
@@ -61,12 +139,122 @@ impl Database {
             .await
             .map_err(|e| DatabaseError::Migration(e.to_string()))?;
 
+        let mut db = Self {
+            pool,
+            notes_dir: notes_dir.to_path_buf(),
+            fts_rebuilt: false,
+            notes_dir_available: Arc::new(AtomicBool::new(true)),
+            indexing: Arc::new(watch::Sender::new(IndexingState::NotStarted)),
+            metrics: MetricsRecorder::default(),
+        };
+
+        // Repair the fulltext index if it got out of sync, e.g. after a crash.
+        // The check reads the whole index, so it only runs once in a while.
+        if integrity::fts_check_due(&db.pool).await? && db.repair_fts().await? {
+            eprintln!(
+                "The fulltext index of {} was inconsistent and has been rebuilt.",
+                db_path.display()
+            );
+        }
+
+        Ok(db)
+    }
+
+    /// Open the database for a command that only queries the notes
+    ///
+    /// If the notes directory is writable, this initializes the database and
+    /// brings the index up to date. Otherwise, e.g. on a read-only snapshot,
+    /// the existing database is opened read-only and used as is.
+    pub async fn open_for_query(notes_dir: &Path) -> Result<Self> {
+        if check_dir_writable(notes_dir).is_ok() {
+            let db = Self::initialize(notes_dir).await?;
+            db.index_notes().await?;
+            return Ok(db);
+        }
+
+        Self::open_read_only(notes_dir).await
+    }
+
+    /// Open the database for a command that lists the index as it is
+    ///
+    /// Like `open_for_query`, but the notes are only indexed if the database
+    /// did not exist yet, so that a listing of a large notes directory starts
+    /// right away. Notes changed outside of notelog show up once a running
+    /// daemon or another command has indexed them.
+    pub async fn open_for_listing(notes_dir: &Path) -> Result<Self> {
+        if check_dir_writable(notes_dir).is_ok() {
+            let is_new = !notes_dir.join(DB_FILENAME).exists();
+            let db = Self::initialize(notes_dir).await?;
+            if is_new {
+                db.index_notes().await?;
+            }
+            return Ok(db);
+        }
+
+        Self::open_read_only(notes_dir).await
+    }
+
+    /// Open an existing database read-only
+    ///
+    /// Neither migrations nor the fulltext index check are run, and the notes
+    /// are not indexed.
+    pub async fn open_read_only(notes_dir: &Path) -> Result<Self> {
+        let db_path = notes_dir.join(DB_FILENAME);
+        if !db_path.exists() {
+            return Err(DatabaseError::Connection(format!(
+                "{} does not exist and cannot be created in a read-only directory",
+                db_path.display()
+            ))
+            .into());
+        }
+
+        // Reading a database in rollback journal mode only takes a shared lock
+        // on the file, so this works without write access to the directory
+        let options = SqliteConnectOptions::new()
+            .filename(&db_path)
+            .read_only(true);
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+
         Ok(Self {
             pool,
             notes_dir: notes_dir.to_path_buf(),
+            fts_rebuilt: false,
+            notes_dir_available: Arc::new(AtomicBool::new(true)),
+            indexing: Arc::new(watch::Sender::new(IndexingState::NotStarted)),
+            metrics: MetricsRecorder::default(),
         })
     }
 
+    /// Read the indexed filepaths and their modification times without
+    /// modifying the database
+    ///
+    /// Unlike `initialize`, this neither creates the database nor runs
+    /// migrations. Returns an empty map if the database does not exist yet.
+    pub async fn read_indexed_mtimes(notes_dir: &Path) -> Result<HashMap<String, String>> {
+        let db_path = notes_dir.join(DB_FILENAME);
+        if !db_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(&db_path)
+            .read_only(true);
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+
+        let mtimes = sqlx::query_as::<_, (String, String)>("SELECT filepath, mtime FROM notes")
+            .fetch_all(&pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        pool.close().await;
+
+        Ok(mtimes.into_iter().collect())
+    }
+
     /// Get the database connection pool
     #[cfg(test)]
     pub fn pool(&self) -> &Pool<Sqlite> {
@@ -75,62 +263,91 @@ impl Database {
 
     /// Search for notes using fulltext search
     ///
-    /// Returns a Vec of Notes that match the search query, ordered by relevance.
-    ///
-    /// # Parameters
-    ///
-    /// * `query` - The search query string
-    /// * `before` - Optional DateTime to filter notes created before this time
-    /// * `after` - Optional DateTime to filter notes created after this time
-    /// * `limit` - Optional limit on the number of results to return
+    /// Returns the matching notes, in the order of the query (by default, the
+    /// most relevant first), and the total number of matches regardless of the
+    /// limit and offset. The query is validated first, so that invalid
+    /// parameters are reported as `SearchQueryError`s and syntax errors in the
+    /// query text as `DatabaseError::InvalidSearchQuery`.
+    pub async fn search_notes(&self, query: &SearchQuery) -> Result<(Vec<Note>, usize)> {
+        let (hits, total_count) = self.search_hits(query).await?;
+        Ok((hits.into_iter().map(|hit| hit.note).collect(), total_count))
+    }
+
+    /// Search for notes using fulltext search, returning the rank and path of each note
     ///
-    /// The query can include tag prefixes (e.g., "+project") to search for specific tags.
-    /// If both `before` and `after` are provided and `before` is less than `after`,
-    /// an empty result will be returned as this represents a non-overlapping date range.
-    pub async fn search_notes(
+    /// Works like `search_notes`. The ranks are 0.0 for queries consisting
+    /// only of filters. The paths also identify notes without an ID.
+    pub async fn search_hits(&self, query: &SearchQuery) -> Result<(Vec<SearchHit>, usize)> {
+        self.metrics
+            .time(Operation::Search, async {
+                let (processed_query, sources, languages) = self.prepare_search(query).await?;
+                self.run_search(&processed_query, &sources, &languages, query)
+                    .await
+            })
+            .await
+    }
+
+    /// Validate a search and split its text into the FTS5 query and the
+    /// source and language filters
+    async fn prepare_search(
         &self,
-        query: &str,
-        before: Option<chrono::DateTime<chrono::Local>>,
-        after: Option<chrono::DateTime<chrono::Local>>,
-        limit: Option<usize>,
-    ) -> Result<(Vec<Note>, usize)> {
-        if query.trim().is_empty() {
-            return Ok((Vec::new(), 0));
-        }
+        query: &SearchQuery,
+    ) -> Result<(String, Vec<String>, Vec<String>)> {
+        query.validate()?;
 
-        if !is_valid_date_range(before.as_ref(), after.as_ref()) {
-            return Ok((Vec::new(), 0));
+        // Raw queries are FTS5 MATCH expressions, which only FTS5 can check
+        if query.raw {
+            check_query_size(&query.text)?;
+            self.validate_raw_query(&query.text).await?;
+            return Ok((query.text.clone(), Vec::new(), Vec::new()));
         }
 
-        let base_count_query = String::from(
-            r#"
-            SELECT COUNT(*)
-            FROM notes_fts fts
-            JOIN notes n ON fts.rowid = n.id
-            WHERE notes_fts MATCH ?
-            "#,
-        );
-
-        let count_query =
-            add_date_conditions(base_count_query, before.as_ref(), after.as_ref(), true);
-
-        let mut count_query_builder = sqlx::query_scalar::<_, i64>(&count_query);
+        // Split off source and language filters, which are applied as SQL conditions
+        let (fts_query, sources) = extract_source_filters(&query.text)?;
+        let (fts_query, languages) = extract_lang_filters(&fts_query)?;
 
         // Process the query to handle tag prefixes (+ signs)
         // In FTS5, + is a special character, so we need to escape it or transform the query
-        let processed_query = process_search_query(query)?;
+        let processed_query = process_search_query(&fts_query)?;
 
-        count_query_builder = count_query_builder.bind(&processed_query);
+        Ok((processed_query, sources, languages))
+    }
 
-        // Bind date parameters if provided
-        if let Some(before_date) = before.as_ref() {
-            let before_str = before_date.format("%Y-%m-%dT%H:%M:%S%:z").to_string();
-            count_query_builder = count_query_builder.bind(before_str);
-        }
+    /// Check that FTS5 accepts a raw MATCH expression
+    async fn validate_raw_query(&self, query: &str) -> Result<()> {
+        // LIMIT 1 stops after the first match, so this is cheap. LIMIT 0 would not
+        // do, because SQLite then skips the scan and FTS5 never parses the expression.
+        sqlx::query("SELECT rowid FROM notes_fts WHERE notes_fts MATCH ? LIMIT 1")
+            .bind(query)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e.as_database_error() {
+                Some(db_error) => DatabaseError::InvalidSearchQuery(db_error.message().to_string()),
+                None => DatabaseError::Query(e.to_string()),
+            })?;
+
+        Ok(())
+    }
 
-        if let Some(after_date) = after.as_ref() {
-            let after_str = after_date.format("%Y-%m-%dT%H:%M:%S%:z").to_string();
-            count_query_builder = count_query_builder.bind(after_str);
+    /// Run a search with an already processed FTS5 query and filters
+    ///
+    /// English notes are matched against the stemmed fulltext index, all other
+    /// notes against the unstemmed one, so that e.g. "deploying" also finds
+    /// "deployed" in English notes without stemming notes in other languages
+    /// with English rules.
+    async fn run_search(
+        &self,
+        processed_query: &str,
+        sources: &[String],
+        languages: &[String],
+        query: &SearchQuery,
+    ) -> Result<(Vec<SearchHit>, usize)> {
+        let clauses = SearchClauses::new(processed_query, sources, languages, query);
+
+        let count_query = format!("SELECT COUNT(*) {}", clauses.sql);
+        let mut count_query_builder = sqlx::query_scalar::<_, i64>(&count_query);
+        for param in &clauses.params {
+            count_query_builder = count_query_builder.bind(param);
         }
 
         let total_count = count_query_builder
@@ -139,51 +356,38 @@ impl Database {
             .map_err(|e| DatabaseError::Query(e.to_string()))?;
 
         // If limit is 0, only return the count
-        if let Some(limit_val) = limit {
-            if limit_val == 0 {
-                return Ok((Vec::new(), total_count as usize));
-            }
+        if query.limit == Some(0) {
+            return Ok((Vec::new(), total_count as usize));
         }
 
         // Build the main query
-        let base_main_query = String::from(
-            r#"
-            SELECT
-                n.id,
-                n.metadata,
-                n.content,
-                rank
-            FROM notes_fts fts
-            JOIN notes n ON fts.rowid = n.id
-            WHERE notes_fts MATCH ?
-            "#,
+        let rank_column = if clauses.use_fts {
+            "fts.rank"
+        } else {
+            "0.0 AS rank"
+        };
+        let mut main_query = format!(
+            "SELECT n.filepath, n.metadata, n.content, {} {}",
+            rank_column, clauses.sql
         );
 
-        let mut main_query =
-            add_date_conditions(base_main_query, before.as_ref(), after.as_ref(), true);
-
         // Add ORDER BY clause
-        main_query.push_str(" ORDER BY rank, json_extract(n.metadata, '$.created') DESC");
+        main_query.push_str(&format!(" ORDER BY {}", query.order.order_by()));
 
-        // Add LIMIT clause if provided
-        if let Some(limit_val) = limit {
-            main_query.push_str(&format!(" LIMIT {}", limit_val));
+        // Add LIMIT and OFFSET clauses; SQLite needs a LIMIT for an OFFSET
+        match query.limit {
+            Some(limit_val) => main_query.push_str(&format!(" LIMIT {}", limit_val)),
+            None if query.offset > 0 => main_query.push_str(" LIMIT -1"),
+            None => {}
         }
-
-        let mut main_query_builder = sqlx::query_as::<_, (i64, String, String, f64)>(&main_query);
-
-        // Bind the processed search query parameter
-        main_query_builder = main_query_builder.bind(&processed_query);
-
-        // Bind date parameters if provided
-        if let Some(before_date) = before.as_ref() {
-            let before_str = before_date.format("%Y-%m-%dT%H:%M:%S%:z").to_string();
-            main_query_builder = main_query_builder.bind(before_str);
+        if query.offset > 0 {
+            main_query.push_str(&format!(" OFFSET {}", query.offset));
         }
 
-        if let Some(after_date) = after.as_ref() {
-            let after_str = after_date.format("%Y-%m-%dT%H:%M:%S%:z").to_string();
-            main_query_builder = main_query_builder.bind(after_str);
+        let mut main_query_builder =
+            sqlx::query_as::<_, (String, String, String, f64)>(&main_query);
+        for param in &clauses.params {
+            main_query_builder = main_query_builder.bind(param);
         }
 
         // Execute the query
@@ -192,16 +396,53 @@ impl Database {
             .await
             .map_err(|e| DatabaseError::Query(e.to_string()))?;
 
-        // Convert the results to a Vec of Notes, preserving the order from the database query
-        let mut notes = Vec::with_capacity(notes_data.len());
-        for (_db_id, metadata_json, content, _rank) in notes_data {
+        // Convert the results to a Vec of hits, preserving the order from the database query
+        let mut hits = Vec::with_capacity(notes_data.len());
+        for (filepath, metadata_json, content, rank) in notes_data {
             match json_to_note(&metadata_json, &content) {
-                Ok(note) => notes.push(note),
+                Ok(note) => hits.push(SearchHit {
+                    note,
+                    rank,
+                    filepath,
+                }),
                 Err(e) => eprintln!("Error parsing note: {}", e),
             }
         }
 
-        Ok((notes, total_count as usize))
+        Ok((hits, total_count as usize))
+    }
+
+    /// Count the notes matching a search by the month they were created in
+    ///
+    /// The limit and offset of the query are ignored, so the counts add up to
+    /// the total number of matches. The months are `YYYY-MM` in local time,
+    /// oldest first; months without matching notes are left out.
+    pub async fn search_month_counts(&self, query: &SearchQuery) -> Result<Vec<MonthCount>> {
+        let (processed_query, sources, languages) = self.prepare_search(query).await?;
+        let clauses = SearchClauses::new(&processed_query, &sources, &languages, query);
+
+        let month_query = format!(
+            "SELECT strftime('%Y-%m', json_extract(n.metadata, '$.created'), 'localtime') AS month, \
+             COUNT(*) {} GROUP BY month HAVING month IS NOT NULL ORDER BY month",
+            clauses.sql
+        );
+        let mut month_query_builder = sqlx::query_as::<_, (String, i64)>(&month_query);
+        for param in &clauses.params {
+            month_query_builder = month_query_builder.bind(param);
+        }
+
+        let counts = month_query_builder
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        Ok(counts
+            .into_iter()
+            .map(|(month, count)| MonthCount {
+                month,
+                count: count as usize,
+            })
+            .collect())
     }
 
     /// Fetch a note by its ID prefix
@@ -230,10 +471,11 @@ impl Database {
                 metadata,
                 content
             FROM notes
-            WHERE json_extract(metadata, '$.id') LIKE ? || '%'
+            WHERE note_id >= ? AND note_id < ?
             "#,
         )
         .bind(id_prefix)
+        .bind(id_prefix_end(id_prefix))
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| DatabaseError::Query(e.to_string()))?;
@@ -246,6 +488,98 @@ impl Database {
         Ok(None)
     }
 
+    /// Fetch a note by the path of its file, relative to the notes directory
+    ///
+    /// This finds notes without an ID. Only indexed notes are found, so the
+    /// path cannot point outside of the notes directory.
+    pub async fn fetch_note_by_filepath(&self, filepath: &str) -> Result<Option<Note>> {
+        let note_data = sqlx::query_as::<_, (String, String)>(
+            "SELECT metadata, content FROM notes WHERE filepath = ?",
+        )
+        .bind(filepath)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        note_data
+            .map(|(metadata_json, content)| json_to_note(&metadata_json, &content))
+            .transpose()
+    }
+
+    /// Fetch the notes with several ID prefixes at once
+    ///
+    /// Each prefix is resolved on its own, and the result has one entry per
+    /// prefix, in the same order. The prefixes should be normalized with
+    /// `normalize_id_prefix`. This needs two queries however many prefixes
+    /// there are: one to find the matching IDs, and one for the notes that
+    /// were matched by a prefix alone.
+    pub async fn fetch_notes_by_prefixes(&self, prefixes: &[String]) -> Result<Vec<PrefixMatch>> {
+        let ranges: Vec<(&str, String)> = prefixes
+            .iter()
+            .map(|prefix| (prefix.as_str(), id_prefix_end(prefix)))
+            .collect();
+        let ranges_json = serde_json::to_string(&ranges)
+            .map_err(|e| DatabaseError::Serialization(e.to_string()))?;
+
+        let matches = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT p.key, n.id
+            FROM json_each(?) AS p
+            JOIN notes AS n
+                ON n.note_id >= json_extract(p.value, '$[0]')
+                AND n.note_id < json_extract(p.value, '$[1]')
+            "#,
+        )
+        .bind(&ranges_json)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        let mut matched: Vec<Vec<i64>> = vec![Vec::new(); prefixes.len()];
+        for (index, note_id) in matches {
+            if let Some(note_ids) = usize::try_from(index)
+                .ok()
+                .and_then(|index| matched.get_mut(index))
+            {
+                note_ids.push(note_id);
+            }
+        }
+
+        let unique: Vec<i64> = matched
+            .iter()
+            .filter(|note_ids| note_ids.len() == 1)
+            .map(|note_ids| note_ids[0])
+            .collect();
+        let unique_json = serde_json::to_string(&unique)
+            .map_err(|e| DatabaseError::Serialization(e.to_string()))?;
+
+        let rows = sqlx::query_as::<_, (i64, String, String)>(
+            "SELECT id, metadata, content FROM notes WHERE id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(&unique_json)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        let mut notes = HashMap::new();
+        for (note_id, metadata_json, content) in rows {
+            notes.insert(note_id, json_to_note(&metadata_json, &content)?);
+        }
+
+        Ok(matched
+            .into_iter()
+            .map(|note_ids| match note_ids.as_slice() {
+                [] => PrefixMatch::NotFound,
+                // The note could have been removed between the two queries
+                [note_id] => notes
+                    .get(note_id)
+                    .cloned()
+                    .map_or(PrefixMatch::NotFound, PrefixMatch::Found),
+                _ => PrefixMatch::Ambiguous(note_ids.len()),
+            })
+            .collect())
+    }
+
     /// Get the filepath of a note by its ID prefix
     ///
     /// This function searches for notes with IDs that start with the provided prefix
@@ -271,10 +605,11 @@ impl Database {
             r#"
             SELECT filepath
             FROM notes
-            WHERE json_extract(metadata, '$.id') LIKE ? || '%'
+            WHERE note_id >= ? AND note_id < ?
             "#,
         )
         .bind(id_prefix)
+        .bind(id_prefix_end(id_prefix))
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| DatabaseError::Query(e.to_string()))?;
@@ -284,7 +619,7 @@ impl Database {
 
     /// Find the shortest unique prefix of a given ID
     ///
-    /// This function uses the note_id_idx index to find the shortest prefix of the given ID
+    /// This function uses the index of the note_id column to find the shortest prefix of the given ID
     /// that uniquely identifies a note in the database. It will always return at least
     /// 2 characters, even if a shorter prefix would be unique.
     ///
@@ -305,7 +640,7 @@ impl Database {
             r#"
             SELECT COUNT(*)
             FROM notes
-            WHERE json_extract(metadata, '$.id') = ?
+            WHERE note_id = ?
             "#,
         )
         .bind(id_str)
@@ -335,22 +670,553 @@ impl Database {
         Ok(id_str.to_string())
     }
 
+    /// Get the notes with a due date, soonest first
+    ///
+    /// If `until` is given, only notes due on or before that day are returned.
+    pub async fn due_notes(&self, until: Option<NaiveDate>) -> Result<Vec<Note>> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT metadata, content
+            FROM notes
+            WHERE due IS NOT NULL AND (?1 IS NULL OR due <= ?1)
+            ORDER BY due, json_extract(metadata, '$.created')
+            "#,
+        )
+        .bind(until.map(|until| until.format("%Y-%m-%d").to_string()))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        rows.iter()
+            .map(|(metadata, content)| json_to_note(metadata, content))
+            .collect()
+    }
+
+    /// Get the notes that have at least one of the given tags, oldest first
+    pub async fn notes_with_any_tag(&self, tags: &[Tag]) -> Result<Vec<Note>> {
+        if tags.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; tags.len()].join(", ");
+        let query = format!(
+            r#"
+            SELECT n.metadata, n.content
+            FROM notes n
+            WHERE n.id IN (
+                SELECT nt.note_id
+                FROM note_tags nt JOIN tags t ON t.tag_id = nt.tag_id
+                WHERE t.tag_name IN ({})
+            )
+            ORDER BY json_extract(n.metadata, '$.created')
+            "#,
+            placeholders
+        );
+
+        let mut query_builder = sqlx::query_as::<_, (String, String)>(&query);
+        for tag in tags {
+            query_builder = query_builder.bind(tag.as_str());
+        }
+
+        let rows = query_builder
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        rows.iter()
+            .map(|(metadata, content)| json_to_note(metadata, content))
+            .collect()
+    }
+
+    /// Count the notes with each tag, most used first
+    pub async fn tag_counts(&self) -> Result<Vec<TagCount>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT t.tag_name, COUNT(*) AS count
+            FROM tags t
+            JOIN note_tags nt ON nt.tag_id = t.tag_id
+            GROUP BY t.tag_name
+            ORDER BY count DESC, t.tag_name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(tag, count)| TagCount {
+                tag,
+                count: count.max(0) as usize,
+            })
+            .collect())
+    }
+
+    /// Get the paths of the notes with a tag, relative to the notes directory
+    pub async fn filepaths_with_tag(&self, tag: &Tag) -> Result<Vec<String>> {
+        sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT n.filepath
+            FROM notes n
+            JOIN note_tags nt ON nt.note_id = n.id
+            JOIN tags t ON t.tag_id = nt.tag_id
+            WHERE t.tag_name = ?
+            ORDER BY n.filepath
+            "#,
+        )
+        .bind(tag.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()).into())
+    }
+
+    /// Get the most recently created notes, newest first
+    ///
+    /// With a `tag`, only the notes with that tag are returned.
+    pub async fn recent_notes(&self, limit: usize, tag: Option<&Tag>) -> Result<Vec<Note>> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT n.metadata, n.content
+            FROM notes n
+            WHERE ?1 IS NULL OR n.id IN (
+                SELECT nt.note_id
+                FROM note_tags nt JOIN tags t ON t.tag_id = nt.tag_id
+                WHERE t.tag_name = ?1
+            )
+            ORDER BY json_extract(n.metadata, '$.created') DESC
+            LIMIT ?2
+            "#,
+        )
+        .bind(tag.map(|tag| tag.as_str()))
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        rows.iter()
+            .map(|(metadata, content)| json_to_note(metadata, content))
+            .collect()
+    }
+
+    /// Get the path of the most recently created note, relative to the notes directory
+    pub async fn newest_note_filepath(&self) -> Result<Option<String>> {
+        sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT filepath
+            FROM notes
+            ORDER BY json_extract(metadata, '$.created') DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()).into())
+    }
+
+    /// Count the notes with a tag per month, and find the tags used with it
+    ///
+    /// The months are those of the creation times in local time, like the
+    /// month directories of the notes.
+    pub async fn tag_timeline(&self, tag: &Tag) -> Result<TagTimeline> {
+        let counts = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT strftime('%Y-%m', json_extract(n.metadata, '$.created'), 'localtime') AS month,
+                   COUNT(*)
+            FROM notes n
+            JOIN note_tags nt ON nt.note_id = n.id
+            JOIN tags t ON t.tag_id = nt.tag_id
+            WHERE t.tag_name = ? AND month IS NOT NULL
+            GROUP BY month
+            ORDER BY month
+            "#,
+        )
+        .bind(tag.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        // Timestamps with different offsets do not sort as strings
+        let (first, last) = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+            r#"
+            WITH created AS (
+                SELECT json_extract(n.metadata, '$.created') AS created
+                FROM notes n
+                JOIN note_tags nt ON nt.note_id = n.id
+                JOIN tags t ON t.tag_id = nt.tag_id
+                WHERE t.tag_name = ?
+            )
+            SELECT (SELECT created FROM created ORDER BY julianday(created) ASC LIMIT 1),
+                   (SELECT created FROM created ORDER BY julianday(created) DESC LIMIT 1)
+            "#,
+        )
+        .bind(tag.as_str())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        let co_tags = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT other.tag_name, COUNT(*) AS count
+            FROM tags t
+            JOIN note_tags nt ON nt.tag_id = t.tag_id
+            JOIN note_tags other_nt ON other_nt.note_id = nt.note_id AND other_nt.tag_id != t.tag_id
+            JOIN tags other ON other.tag_id = other_nt.tag_id
+            WHERE t.tag_name = ?
+            GROUP BY other.tag_name
+            ORDER BY count DESC, other.tag_name
+            LIMIT ?
+            "#,
+        )
+        .bind(tag.as_str())
+        .bind(TIMELINE_CO_TAGS as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        let parse = |created: Option<String>| {
+            created
+                .and_then(|created| DateTime::parse_from_rfc3339(&created).ok())
+                .map(|created| created.with_timezone(&Local))
+        };
+
+        Ok(TagTimeline {
+            tag: tag.as_str().to_string(),
+            months: timeline::fill_months(counts),
+            first: parse(first),
+            last: parse(last),
+            co_tags: co_tags
+                .into_iter()
+                .map(|(tag, count)| TagCount {
+                    tag,
+                    count: count.max(0) as usize,
+                })
+                .collect(),
+        })
+    }
+
+    /// List all notes, newest first
+    ///
+    /// The notes are streamed from the database, and `f` is called with the
+    /// shortest unique ID prefix (empty for notes without an ID) and the note
+    /// as soon as each row arrives. The rows are read along the index of the
+    /// creation times, and the neighbouring IDs that determine the unique
+    /// prefix are looked up in the index of the IDs, so no row waits for the
+    /// whole table to be sorted.
+    pub async fn list_notes<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&str, Note) -> Result<()>,
+    {
+        let mut rows = sqlx::query_as::<
+            _,
+            (
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                String,
+                String,
+            ),
+        >(
+            r#"
+            SELECT
+                n.note_id,
+                (SELECT p.note_id FROM notes p
+                 WHERE p.note_id <= n.note_id AND p.id <> n.id
+                 ORDER BY p.note_id DESC LIMIT 1),
+                (SELECT s.note_id FROM notes s
+                 WHERE s.note_id >= n.note_id AND s.id <> n.id
+                 ORDER BY s.note_id ASC LIMIT 1),
+                n.metadata,
+                n.content
+            FROM notes n
+            ORDER BY n.created_utc DESC, n.id DESC
+            "#,
+        )
+        .fetch(&self.pool);
+
+        while let Some((id, prev_id, next_id, metadata, content)) = rows
+            .try_next()
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?
+        {
+            let short_id = id
+                .as_deref()
+                .map(|id| unique_id_prefix(id, prev_id.as_deref(), next_id.as_deref()))
+                .unwrap_or_default();
+
+            f(short_id, json_to_note(&metadata, &content)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Acquire the maintenance lock, for maintenance that rewrites the index
+    ///
+    /// While the lock is held, the file monitor and the background
+    /// verification of other processes pause. With `wait`, this waits for
+    /// another holder to release the lock; otherwise it fails with
+    /// `DatabaseError::MaintenanceLocked`.
+    pub async fn lock_maintenance(&self, wait: bool) -> Result<MaintenanceLock> {
+        lock::acquire_maintenance_lock(&self.pool, wait).await
+    }
+
+    /// Record that a note file was moved, and index it at its new path
+    ///
+    /// Paths are relative to the notes directory. The note keeps its row in
+    /// the index, so that it is not briefly missing from searches. Both steps
+    /// run in one transaction.
+    pub async fn move_note(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        sqlx::query("UPDATE notes SET filepath = ? WHERE filepath = ?")
+            .bind(to.to_string_lossy().as_ref())
+            .bind(from.to_string_lossy().as_ref())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        self.metrics
+            .time(
+                Operation::ProcessNoteFile,
+                process_note_file(&mut *tx, &self.notes_dir, &self.notes_dir.join(to)),
+            )
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Index a note file right away, e.g. after changing it
+    ///
+    /// The path is relative to the notes directory.
+    pub async fn index_file(&self, path: &Path) -> Result<()> {
+        self.metrics
+            .time(
+                Operation::ProcessNoteFile,
+                process_note_file(&self.pool, &self.notes_dir, &self.notes_dir.join(path)),
+            )
+            .await
+    }
+
+    /// Count the indexed notes
+    pub async fn note_count(&self) -> Result<usize> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM notes")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        Ok(count.max(0) as usize)
+    }
+
+    /// Get the note files that could not be indexed, e.g. because of broken
+    /// frontmatter, by path
+    ///
+    /// These files are not read again until they change.
+    pub async fn failed_files(&self) -> Result<Vec<FailedFile>> {
+        indexing::get_failed_files(&self.pool).await
+    }
+
+    /// Index all notes in the notes directory and wait for it to finish
+    pub async fn index_notes(&self) -> Result<IndexStats> {
+        index_notes_with_metrics(self.pool.clone(), &self.notes_dir, &self.metrics).await
+    }
+
     /// Start a background task to index all notes in the notes directory
+    ///
+    /// How the indexing ends is recorded for `indexing_failure` and `wait_for_indexing`.
     pub async fn start_indexing_task(&self) -> Result<()> {
         // Clone the pool and notes_dir for the background task
         let pool = self.pool.clone();
         let notes_dir = self.notes_dir.clone();
+        let state = self.indexing.clone();
+        let metrics = self.metrics.clone();
+        state.send_replace(IndexingState::Running);
 
-        // Spawn a background task to index notes using channels
+        // Spawn a background task to index notes using channels, and await it
+        // in another one, so that a panic is recorded like an error
+        let indexing =
+            tokio::spawn(async move { index_notes_with_metrics(pool, &notes_dir, &metrics).await });
         tokio::spawn(async move {
-            if let Err(e) = index_notes_with_channel(pool, &notes_dir).await {
-                eprintln!("Error indexing notes: {}", e);
+            let outcome = match indexing.await {
+                Ok(Ok(_)) => IndexingState::Finished,
+                Ok(Err(e)) => IndexingState::Failed(e.to_string()),
+                Err(e) => IndexingState::Failed(format!("the indexing task failed: {}", e)),
+            };
+            if let IndexingState::Failed(message) = &outcome {
+                eprintln!("Error indexing notes: {}", message);
             }
+            state.send_replace(outcome);
         });
 
         Ok(())
     }
 
+    /// How often searches and indexing ran since the database was opened,
+    /// and how long they took
+    ///
+    /// The clones of a database and its background tasks share the metrics.
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.snapshot()
+    }
+
+    /// Why the indexing started by `start_indexing_task` failed, if it did
+    pub fn indexing_failure(&self) -> Option<String> {
+        match &*self.indexing.borrow() {
+            IndexingState::Failed(message) => Some(message.clone()),
+            _ => None,
+        }
+    }
+
+    /// Wait up to `timeout` for the indexing started by `start_indexing_task`
+    /// to finish
+    ///
+    /// Returns an error if the indexing failed within that time. Indexing that
+    /// is still running, or was never started, is not an error.
+    pub async fn wait_for_indexing(&self, timeout: Duration) -> Result<()> {
+        let mut state = self.indexing.subscribe();
+        let finished = state.wait_for(|state| *state != IndexingState::Running);
+        if let Ok(Ok(state)) = tokio::time::timeout(timeout, finished).await
+            && let IndexingState::Failed(message) = &*state
+        {
+            return Err(DatabaseError::Indexing(message.clone()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Index the notes again whose file changed without a new modification time
+    pub async fn verify_content_hashes(&self) -> Result<VerifyStats> {
+        let (stats, _) =
+            verify::verify_content_hashes(&self.pool, &self.notes_dir, 0, None).await?;
+        Ok(stats)
+    }
+
+    /// Start a background task that verifies a batch of notes every few minutes
+    pub async fn start_verification_task(&self) -> Result<()> {
+        // Clone the pool and notes_dir for the background task
+        let pool = self.pool.clone();
+        let notes_dir = self.notes_dir.clone();
+
+        tokio::spawn(verify::run_verification(
+            pool,
+            notes_dir,
+            self.metrics.clone(),
+        ));
+
+        Ok(())
+    }
+
+    /// Check whether the fulltext index is consistent with the notes
+    pub async fn check_fts_integrity(&self) -> Result<bool> {
+        integrity::check_fts_integrity(&self.pool).await
+    }
+
+    /// Rebuild the fulltext index from the notes
+    pub async fn rebuild_fts(&self) -> Result<()> {
+        integrity::rebuild_fts(&self.pool).await
+    }
+
+    /// Check the fulltext index and rebuild it if it is inconsistent
+    ///
+    /// Returns whether the index was rebuilt. The check is recorded, so that
+    /// opening the database skips it for a while.
+    pub async fn repair_fts(&mut self) -> Result<bool> {
+        let consistent = self.check_fts_integrity().await?;
+        if !consistent {
+            self.rebuild_fts().await?;
+            self.fts_rebuilt = true;
+        }
+        integrity::record_fts_check(&self.pool).await?;
+
+        Ok(!consistent)
+    }
+
+    /// Whether the fulltext index was inconsistent and rebuilt since opening the database
+    pub fn fts_rebuilt(&self) -> bool {
+        self.fts_rebuilt
+    }
+
+    /// Whether the notes directory is available
+    ///
+    /// This is false while the monitoring finds that the notes directory
+    /// cannot be read, e.g. because the share it is on was unmounted.
+    pub fn notes_dir_available(&self) -> bool {
+        self.notes_dir_available.load(Ordering::Relaxed)
+    }
+
+    /// The size of the database files, in bytes
+    pub fn size(&self) -> Result<u64> {
+        maintenance::database_size(&self.notes_dir)
+    }
+
+    /// Shrink the database file and optimize the fulltext index and queries
+    ///
+    /// Runs the FTS5 `optimize` command, `VACUUM` and `PRAGMA optimize`. Take
+    /// the maintenance lock first, as `VACUUM` rewrites the whole database.
+    pub async fn maintenance(&self) -> Result<MaintenanceStats> {
+        maintenance::optimize_database(&self.pool, &self.notes_dir).await
+    }
+
+    /// Update the statistics of the query planner, e.g. before shutting down
+    pub async fn optimize_queries(&self) -> Result<()> {
+        maintenance::pragma_optimize(&self.pool).await
+    }
+
+    /// Write a snapshot of the database to `path`, which must not exist yet
+    pub async fn backup_to(&self, path: &Path) -> Result<()> {
+        backup::backup_database(&self.pool, path).await
+    }
+
+    /// Start a background task that takes one backup per day, keeping at most `keep`
+    pub async fn start_daily_backup_task(&self, keep: usize) -> Result<()> {
+        // Clone the pool and notes_dir for the background task
+        let pool = self.pool.clone();
+        let backups_dir = self.notes_dir.join(BACKUPS_DIR);
+
+        tokio::spawn(backup::run_daily_backups(pool, backups_dir, keep));
+
+        Ok(())
+    }
+
+    /// Find the notes that expired at `now` under the retention policy, oldest first
+    pub async fn expired_notes(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Local>,
+    ) -> Result<Vec<ExpiredNote>> {
+        prune::find_expired_notes(&self.pool, policy, now).await
+    }
+
+    /// Delete the files and rows of expired notes that still expired at `now`
+    ///
+    /// Returns the paths of the deleted notes; notes whose file changed so
+    /// that they no longer expire are kept.
+    pub async fn prune_notes(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Local>,
+        notes: &[ExpiredNote],
+    ) -> Result<Vec<String>> {
+        prune::prune_notes(&self.pool, &self.notes_dir, policy, now, notes).await
+    }
+
+    /// Start a background task that deletes the expired notes once per day
+    pub async fn start_daily_pruning_task(&self, policy: RetentionPolicy) -> Result<()> {
+        // Clone the pool and notes_dir for the background task
+        let pool = self.pool.clone();
+        let notes_dir = self.notes_dir.clone();
+
+        tokio::spawn(prune::run_daily_pruning(pool, notes_dir, policy));
+
+        Ok(())
+    }
+
     /// Start a background task to monitor the notes directory for changes
     pub async fn start_monitoring_task(&self) -> Result<()> {
         // Clone the pool and notes_dir for the background task
@@ -358,6 +1224,92 @@ impl Database {
         let notes_dir = self.notes_dir.clone();
 
         // Start the file monitoring task
-        start_file_monitoring(pool, &notes_dir).await
+        start_file_monitoring(
+            pool,
+            &notes_dir,
+            self.notes_dir_available.clone(),
+            self.metrics.clone(),
+        )
+        .await
+    }
+}
+
+/// The FROM and WHERE clauses of a search, shared by its queries
+struct SearchClauses {
+    sql: String,
+    /// The parameters of the clauses, in the order they are bound
+    params: Vec<String>,
+    /// Whether the notes are matched against the fulltext indexes, which
+    /// provide the `fts.rank` column
+    use_fts: bool,
+}
+
+impl SearchClauses {
+    fn new(
+        processed_query: &str,
+        sources: &[String],
+        languages: &[String],
+        query: &SearchQuery,
+    ) -> Self {
+        // A query consisting only of filters does not use the fulltext index
+        let use_fts = !processed_query.is_empty();
+
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        if use_fts {
+            params.push(processed_query.to_string());
+            params.push(processed_query.to_string());
+        }
+        if !sources.is_empty() {
+            conditions.push(source_filter_condition(sources.len()));
+            params.extend(source_filter_params(sources));
+        }
+        if !languages.is_empty() {
+            conditions.push(lang_filter_condition(languages.len()));
+            params.extend(languages.iter().cloned());
+        }
+        if let Some(prefix) = query.normalized_path_prefix() {
+            conditions.push(path_prefix_condition());
+            params.push(prefix.to_string());
+        }
+
+        // The ranks of both fulltext indexes are not strictly comparable, but
+        // close enough for ordering the results
+        let from_clause = if use_fts {
+            r#"FROM (
+                SELECT rowid AS note_id, rank FROM notes_fts
+                WHERE notes_fts MATCH ? AND rowid NOT IN (SELECT rowid FROM notes_fts_stemmed)
+                UNION ALL
+                SELECT rowid AS note_id, rank FROM notes_fts_stemmed
+                WHERE notes_fts_stemmed MATCH ?
+            ) fts JOIN notes n ON fts.note_id = n.id"#
+        } else {
+            "FROM notes n"
+        };
+
+        let base_query = if conditions.is_empty() {
+            from_clause.to_string()
+        } else {
+            format!("{} WHERE {}", from_clause, conditions.join(" AND "))
+        };
+
+        let sql = add_date_conditions(
+            base_query,
+            query.before.as_ref(),
+            query.after.as_ref(),
+            !conditions.is_empty(),
+        );
+        for date in [query.before.as_ref(), query.after.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            params.push(date.format("%Y-%m-%dT%H:%M:%S%:z").to_string());
+        }
+
+        Self {
+            sql,
+            params,
+            use_fts,
+        }
     }
 }
@@ -8,22 +8,46 @@ use sqlx::Pool;
 use sqlx::Sqlite;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::{Mutex, mpsc};
 
-use crate::db::is_valid_note_file;
+use crate::db::indexing::{index_notes_with_metrics, is_skipped};
+use crate::db::lock::wait_for_maintenance;
+use crate::db::metrics::{MetricsRecorder, Operation};
+use crate::db::{IGNORE_FILENAME, IgnoreRules, is_valid_note_file};
 use crate::error::{DatabaseError, Result};
 
+/// How many file events can wait to be processed
+///
+/// Mass operations, such as restoring a backup into the notes directory,
+/// produce more events than this; the events that do not fit are dropped and
+/// the whole notes directory is indexed again instead.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// How often the notes directory is scanned for changes where it cannot be
+/// watched for events, and the watcher falls back to polling
+pub const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(20);
+
+/// How often the monitoring checks that the notes directory can still be read
+const AVAILABILITY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 /// File monitoring handler that sends events to a channel
-struct FileMonitoringHandler {
+pub(super) struct FileMonitoringHandler {
     /// Channel sender for file events
-    sender: mpsc::UnboundedSender<Event>,
+    sender: mpsc::Sender<Event>,
+    /// Set when an event was dropped because the channel was full
+    resync_needed: Arc<AtomicBool>,
 }
 
 impl FileMonitoringHandler {
     /// Create a new file monitoring handler with a channel sender
-    fn new(sender: mpsc::UnboundedSender<Event>) -> Self {
-        Self { sender }
+    pub(super) fn new(sender: mpsc::Sender<Event>, resync_needed: Arc<AtomicBool>) -> Self {
+        Self {
+            sender,
+            resync_needed,
+        }
     }
 }
 
@@ -32,9 +56,13 @@ impl EventHandler for FileMonitoringHandler {
     fn handle_event(&mut self, result: NotifyResult<Event>) {
         match result {
             Ok(event) => {
-                // Send the event to the channel
-                if let Err(e) = self.sender.send(event) {
-                    eprintln!("Error sending file event to channel: {}", e);
+                // Send the event to the channel, without blocking the watcher thread
+                match self.sender.try_send(event) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        self.resync_needed.store(true, Ordering::Relaxed);
+                    }
+                    Err(e) => eprintln!("Error sending file event to channel: {}", e),
                 }
             }
             Err(e) => {
@@ -45,22 +73,49 @@ impl EventHandler for FileMonitoringHandler {
 }
 
 /// Process file events from the channel
-async fn process_events(
-    mut receiver: mpsc::UnboundedReceiver<Event>,
+///
+/// When events were dropped, the events still in the channel are discarded
+/// and the whole notes directory is indexed again, which covers them all.
+pub(super) async fn process_events(
+    mut receiver: mpsc::Receiver<Event>,
+    resync_needed: Arc<AtomicBool>,
     pool: Pool<Sqlite>,
     notes_dir: PathBuf,
+    metrics: MetricsRecorder,
 ) {
     // Create a mutex to prevent concurrent processing of the same file
     let processing = Arc::new(Mutex::new(()));
+    let mut ignore_rules = IgnoreRules::load(&notes_dir);
 
     while let Some(event) = receiver.recv().await {
+        // Maintenance by another process goes first
+        if let Err(e) = wait_for_maintenance(&pool).await {
+            eprintln!("Error checking the maintenance lock: {}", e);
+        }
+
+        if resync_needed.swap(false, Ordering::Relaxed) {
+            while receiver.try_recv().is_ok() {}
+
+            let _lock = processing.lock().await;
+            if let Err(e) = index_notes_with_metrics(pool.clone(), &notes_dir, &metrics).await {
+                eprintln!("Error indexing the notes after dropped file events: {}", e);
+            }
+            continue;
+        }
+
         // Only process events that are related to file modifications
         match event.kind {
             EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
                 // Process each path in the event
                 for path in event.paths {
-                    // Skip directories
-                    if path.is_dir() {
+                    // Pick up changes to the ignore patterns right away
+                    if path == notes_dir.join(IGNORE_FILENAME) {
+                        ignore_rules = IgnoreRules::load(&notes_dir);
+                        continue;
+                    }
+
+                    // Skip directories and the ignored files
+                    if path.is_dir() || is_skipped(&notes_dir, &path, false, &ignore_rules) {
                         continue;
                     }
 
@@ -70,8 +125,11 @@ async fn process_events(
                         let _lock = processing.lock().await;
 
                         // Process the note file
-                        if let Err(e) = crate::db::process_note_file(&pool, &notes_dir, &path).await
-                        {
+                        let processed = metrics.time(
+                            Operation::ProcessNoteFile,
+                            crate::db::process_note_file(&pool, &notes_dir, &path),
+                        );
+                        if let Err(e) = processed.await {
                             eprintln!("Error processing note file {}: {}", path.display(), e);
                         }
                     }
@@ -82,14 +140,22 @@ async fn process_events(
     }
 }
 
-/// Start a file monitoring task for the notes directory
-pub async fn start_file_monitoring(pool: Pool<Sqlite>, notes_dir: &Path) -> Result<()> {
-    let (sender, receiver) = mpsc::unbounded_channel();
+/// Watch the notes directory and process its file events in a new task
+///
+/// The task ends when the returned watcher is dropped, as that closes the
+/// channel of the events.
+fn watch_notes_dir(
+    pool: Pool<Sqlite>,
+    notes_dir: &Path,
+    metrics: MetricsRecorder,
+) -> Result<RecommendedWatcher> {
+    let (sender, receiver) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+    let resync_needed = Arc::new(AtomicBool::new(false));
 
-    let handler = FileMonitoringHandler::new(sender);
+    let handler = FileMonitoringHandler::new(sender, resync_needed.clone());
 
     let config = Config::default()
-        .with_poll_interval(Duration::from_secs(20))
+        .with_poll_interval(WATCH_POLL_INTERVAL)
         .with_compare_contents(false);
 
     let mut watcher = RecommendedWatcher::new(handler, config)
@@ -102,14 +168,100 @@ pub async fn start_file_monitoring(pool: Pool<Sqlite>, notes_dir: &Path) -> Resu
     let notes_dir_clone = notes_dir.to_path_buf();
 
     tokio::spawn(async move {
-        process_events(receiver, pool, notes_dir_clone).await;
+        process_events(receiver, resync_needed, pool, notes_dir_clone, metrics).await;
     });
 
-    // Keep the watcher alive by moving it into a tokio task
+    Ok(watcher)
+}
+
+/// The file monitoring of a notes directory
+///
+/// If the notes directory disappears, e.g. because a network share was
+/// unmounted, the watcher stops working without an error. The monitor notices
+/// this when it checks the directory, marks it unavailable and drops the
+/// watcher; once the directory is back, it watches it again and indexes the
+/// notes to catch up on the changes it missed.
+pub(super) struct Monitor {
+    pool: Pool<Sqlite>,
+    notes_dir: PathBuf,
+    /// The watcher, while the notes directory is available
+    watcher: Option<RecommendedWatcher>,
+    /// Whether the notes directory was readable at the last check
+    available: Arc<AtomicBool>,
+    metrics: MetricsRecorder,
+}
+
+impl Monitor {
+    /// Start watching the notes directory
+    pub(super) fn start(
+        pool: Pool<Sqlite>,
+        notes_dir: &Path,
+        available: Arc<AtomicBool>,
+        metrics: MetricsRecorder,
+    ) -> Result<Self> {
+        let watcher = watch_notes_dir(pool.clone(), notes_dir, metrics.clone())?;
+        available.store(true, Ordering::Relaxed);
+
+        Ok(Self {
+            pool,
+            notes_dir: notes_dir.to_path_buf(),
+            watcher: Some(watcher),
+            available,
+            metrics,
+        })
+    }
+
+    /// Check whether the notes directory can still be read, and stop or
+    /// resume watching it if that changed
+    pub(super) async fn check_availability(&mut self) {
+        let readable = std::fs::read_dir(&self.notes_dir).is_ok();
+
+        if !readable && self.watcher.is_some() {
+            eprintln!(
+                "The notes directory {} is unavailable; waiting for it to return.",
+                self.notes_dir.display()
+            );
+            self.watcher = None;
+            self.available.store(false, Ordering::Relaxed);
+        } else if readable && self.watcher.is_none() {
+            match watch_notes_dir(self.pool.clone(), &self.notes_dir, self.metrics.clone()) {
+                Ok(watcher) => self.watcher = Some(watcher),
+                Err(e) => {
+                    eprintln!("Error watching the notes directory again: {}", e);
+                    return;
+                }
+            }
+
+            let indexed =
+                index_notes_with_metrics(self.pool.clone(), &self.notes_dir, &self.metrics).await;
+            if let Err(e) = indexed {
+                eprintln!(
+                    "Error indexing the notes after the notes directory returned: {}",
+                    e
+                );
+            }
+            self.available.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Start a file monitoring task for the notes directory
+///
+/// `available` is kept up to date with whether the notes directory can be
+/// read, which is checked every minute.
+pub async fn start_file_monitoring(
+    pool: Pool<Sqlite>,
+    notes_dir: &Path,
+    available: Arc<AtomicBool>,
+    metrics: MetricsRecorder,
+) -> Result<()> {
+    let mut monitor = Monitor::start(pool, notes_dir, available, metrics)?;
+
+    // The task owns the watcher, which keeps it alive
     tokio::spawn(async move {
-        let _watcher = watcher;
         loop {
-            tokio::time::sleep(Duration::from_secs(3600)).await;
+            tokio::time::sleep(AVAILABILITY_CHECK_INTERVAL).await;
+            monitor.check_availability().await;
         }
     });
 
@@ -0,0 +1,159 @@
+//! Deleting notes whose tags expired, according to a `RetentionPolicy`
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::fs;
+
+use crate::core::note::Note;
+use crate::core::retention::RetentionPolicy;
+use crate::db::helpers::json_to_note;
+use crate::db::indexing::delete_notes_by_filepaths;
+use crate::db::lock::wait_for_maintenance;
+use crate::error::{DatabaseError, Result};
+
+/// How often the daily pruning runs
+const PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A note that expired
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExpiredNote {
+    /// The path of the note, relative to the notes directory
+    pub filepath: String,
+    /// When the note was created
+    pub created: DateTime<Local>,
+}
+
+/// Find the indexed notes that expired at `now`, oldest first
+pub async fn find_expired_notes(
+    pool: &Pool<Sqlite>,
+    policy: &RetentionPolicy,
+    now: DateTime<Local>,
+) -> Result<Vec<ExpiredNote>> {
+    let tags = policy.tags();
+    if tags.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; tags.len()].join(", ");
+    let query = format!(
+        r#"
+        SELECT n.filepath, n.metadata, n.content
+        FROM notes n
+        WHERE n.id IN (
+            SELECT nt.note_id
+            FROM note_tags nt JOIN tags t ON t.tag_id = nt.tag_id
+            WHERE t.tag_name IN ({})
+        )
+        ORDER BY json_extract(n.metadata, '$.created')
+        "#,
+        placeholders
+    );
+
+    let mut query_builder = sqlx::query_as::<_, (String, String, String)>(&query);
+    for tag in &tags {
+        query_builder = query_builder.bind(tag.as_str());
+    }
+
+    let rows = query_builder
+        .fetch_all(pool)
+        .await
+        .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    let mut expired = Vec::new();
+    for (filepath, metadata, content) in rows {
+        let note = json_to_note(&metadata, &content)?;
+        if policy.is_expired(&note, now) {
+            expired.push(ExpiredNote {
+                filepath,
+                created: *note.frontmatter().created(),
+            });
+        }
+    }
+
+    Ok(expired)
+}
+
+/// Delete the files and rows of expired notes
+///
+/// Each file is read again first, and only deleted if the note in it still
+/// expired at `now`; notes that were changed since they were indexed (e.g. to
+/// remove the expiring tag) are kept. Files that no longer exist only lose
+/// their row.
+///
+/// A file that cannot be read or deleted does not stop the others; the rows of
+/// the deleted files are removed before the first such error is returned.
+///
+/// # Returns
+///
+/// The paths of the deleted notes, relative to the notes directory.
+pub async fn prune_notes(
+    pool: &Pool<Sqlite>,
+    notes_dir: &Path,
+    policy: &RetentionPolicy,
+    now: DateTime<Local>,
+    notes: &[ExpiredNote],
+) -> Result<Vec<String>> {
+    let mut pruned = Vec::new();
+    let mut first_error = None;
+    for expired in notes {
+        let path = notes_dir.join(&expired.filepath);
+        let result = match fs::read_to_string(&path).await {
+            Ok(text) => {
+                let still_expired =
+                    Note::from_str(&text).is_ok_and(|note| policy.is_expired(&note, now));
+                if !still_expired {
+                    continue;
+                }
+                fs::remove_file(&path).await
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => pruned.push(expired.filepath.clone()),
+            Err(e) if first_error.is_none() => first_error = Some(e),
+            Err(e) => eprintln!(
+                "Warning: could not delete expired note {}: {}",
+                expired.filepath, e
+            ),
+        }
+    }
+
+    delete_notes_by_filepaths(pool, &pruned).await?;
+
+    match first_error {
+        Some(e) => Err(e.into()),
+        None => Ok(pruned),
+    }
+}
+
+/// Delete the expired notes once per day, for as long as the program runs
+pub async fn run_daily_pruning(pool: Pool<Sqlite>, notes_dir: PathBuf, policy: RetentionPolicy) {
+    loop {
+        // Maintenance by another process goes first
+        if let Err(e) = wait_for_maintenance(&pool).await {
+            eprintln!("Error checking the maintenance lock: {}", e);
+        }
+
+        let now = Local::now();
+        let pruned = match find_expired_notes(&pool, &policy, now).await {
+            Ok(expired) => prune_notes(&pool, &notes_dir, &policy, now, &expired).await,
+            Err(e) => Err(e),
+        };
+        match pruned {
+            Ok(pruned) => {
+                for filepath in pruned {
+                    eprintln!("Pruned expired note {}", filepath);
+                }
+            }
+            Err(e) => eprintln!("Error pruning expired notes: {}", e),
+        }
+
+        tokio::time::sleep(PRUNE_INTERVAL).await;
+    }
+}
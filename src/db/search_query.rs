@@ -0,0 +1,338 @@
+//! Search parameters for `Database::search_notes`
+
+use chrono::{DateTime, Local};
+use std::fmt;
+use std::path::{Component, Path};
+use std::str::FromStr;
+
+use crate::error::SearchQueryError;
+
+/// The order of search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchOrder {
+    /// Best matches first, newest first among equally good matches
+    #[default]
+    Relevance,
+    /// Most recently created notes first
+    Newest,
+    /// Least recently created notes first
+    Oldest,
+}
+
+impl SearchOrder {
+    /// The SQL ORDER BY clause for this order
+    pub(super) fn order_by(self) -> &'static str {
+        match self {
+            SearchOrder::Relevance => "rank, json_extract(n.metadata, '$.created') DESC",
+            SearchOrder::Newest => "json_extract(n.metadata, '$.created') DESC",
+            SearchOrder::Oldest => "json_extract(n.metadata, '$.created') ASC",
+        }
+    }
+}
+
+impl FromStr for SearchOrder {
+    type Err = SearchQueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relevance" => Ok(SearchOrder::Relevance),
+            "newest" => Ok(SearchOrder::Newest),
+            "oldest" => Ok(SearchOrder::Oldest),
+            _ => Err(SearchQueryError::InvalidOrder(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for SearchOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SearchOrder::Relevance => "relevance",
+            SearchOrder::Newest => "newest",
+            SearchOrder::Oldest => "oldest",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A search of the notes, with its filters and paging
+///
+/// The query text can include tag prefixes (e.g. `+project`), `source:`
+/// filters (e.g. `source:mcp`) and `lang:` filters (e.g. `lang:de`). With
+/// `raw`, the text is passed to the FTS5 MATCH operator as-is instead.
+///
+/// # Example
+///
+/// ```
+/// use notelog::db::{SearchOrder, SearchQuery};
+///
+/// let query = SearchQuery::new("deploy +ops")
+///     .order(SearchOrder::Newest)
+///     .limit(10)
+///     .offset(10);
+/// assert!(query.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// The query text
+    pub(super) text: String,
+    /// Only find notes created at or before this time
+    pub(super) before: Option<DateTime<Local>>,
+    /// Only find notes created at or after this time
+    pub(super) after: Option<DateTime<Local>>,
+    /// The maximum number of notes to return; 0 only counts the matches
+    pub(super) limit: Option<usize>,
+    /// The largest limit the caller allows
+    max_limit: Option<usize>,
+    /// The number of matching notes to skip
+    pub(super) offset: usize,
+    /// The order of the results
+    pub(super) order: SearchOrder,
+    /// Only find notes whose path, relative to the notes directory, starts with this
+    pub(super) path_prefix: Option<String>,
+    /// Whether the caller shows the content of the notes
+    include_content: bool,
+    /// Whether to pass the text to FTS5 without preprocessing
+    pub(super) raw: bool,
+}
+
+impl SearchQuery {
+    /// Create a search for the given query text
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Only find notes created at or before this time
+    pub fn before(mut self, before: impl Into<Option<DateTime<Local>>>) -> Self {
+        self.before = before.into();
+        self
+    }
+
+    /// Only find notes created at or after this time
+    pub fn after(mut self, after: impl Into<Option<DateTime<Local>>>) -> Self {
+        self.after = after.into();
+        self
+    }
+
+    /// Return at most this many notes; with 0, only the matches are counted
+    pub fn limit(mut self, limit: impl Into<Option<usize>>) -> Self {
+        self.limit = limit.into();
+        self
+    }
+
+    /// Reject limits above this one when validating
+    pub fn max_limit(mut self, max_limit: usize) -> Self {
+        self.max_limit = Some(max_limit);
+        self
+    }
+
+    /// Skip this many matching notes, e.g. for the next page of results
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Set the order of the results
+    pub fn order(mut self, order: SearchOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Only find notes below this path, relative to the notes directory
+    pub fn path_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.path_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Set whether the caller shows the content of the notes
+    ///
+    /// The notes are always returned with their content; this only records
+    /// whether the caller wants it, e.g. to include it in MCP results.
+    pub fn include_content(mut self, include_content: bool) -> Self {
+        self.include_content = include_content;
+        self
+    }
+
+    /// Set whether to pass the text to the FTS5 MATCH operator as-is
+    pub fn raw(mut self, raw: bool) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    /// The query text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the caller shows the content of the notes
+    pub fn includes_content(&self) -> bool {
+        self.include_content
+    }
+
+    /// Check the parameters of the search
+    ///
+    /// The syntax of the query text is checked when the search runs, as raw
+    /// queries can only be checked by the database.
+    pub fn validate(&self) -> Result<(), SearchQueryError> {
+        if self.text.trim().is_empty() {
+            return Err(SearchQueryError::Empty);
+        }
+
+        if let (Some(before), Some(after)) = (self.before, self.after)
+            && before < after
+        {
+            return Err(SearchQueryError::InvalidDateRange(before, after));
+        }
+
+        if let (Some(limit), Some(max)) = (self.limit, self.max_limit)
+            && limit > max
+        {
+            return Err(SearchQueryError::LimitTooLarge { limit, max });
+        }
+
+        if let Some(prefix) = &self.path_prefix {
+            let path = Path::new(prefix);
+            if path.has_root()
+                || path
+                    .components()
+                    .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(SearchQueryError::InvalidPathPrefix(prefix.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The path prefix as stored in the database, without a leading `./`
+    pub(super) fn normalized_path_prefix(&self) -> Option<&str> {
+        self.path_prefix
+            .as_deref()
+            .map(|prefix| prefix.trim_start_matches("./"))
+            .filter(|prefix| !prefix.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(day: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_validate_accepts_defaults() {
+        assert!(SearchQuery::new("deploy").validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_query() {
+        for text in ["", "  \t"] {
+            assert!(matches!(
+                SearchQuery::new(text).validate(),
+                Err(SearchQueryError::Empty)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate_date_range() {
+        let query = SearchQuery::new("deploy").before(date(1)).after(date(2));
+        assert!(matches!(
+            query.validate(),
+            Err(SearchQueryError::InvalidDateRange(..))
+        ));
+
+        // A range of a single moment, and open ranges, are fine
+        assert!(
+            SearchQuery::new("deploy")
+                .before(date(1))
+                .after(date(1))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            SearchQuery::new("deploy")
+                .before(None)
+                .after(date(2))
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_validate_limit() {
+        let query = SearchQuery::new("deploy").limit(26).max_limit(25);
+        assert!(matches!(
+            query.validate(),
+            Err(SearchQueryError::LimitTooLarge { limit: 26, max: 25 })
+        ));
+
+        assert!(
+            SearchQuery::new("deploy")
+                .limit(25)
+                .max_limit(25)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            SearchQuery::new("deploy")
+                .limit(0)
+                .max_limit(25)
+                .validate()
+                .is_ok()
+        );
+
+        // Without a maximum, any limit is fine
+        assert!(SearchQuery::new("deploy").limit(1000).validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_path_prefix() {
+        for prefix in ["2025", "2025/05_May", "./2025/", "2025/05_May/2025-05-01"] {
+            let query = SearchQuery::new("deploy").path_prefix(prefix);
+            assert!(query.validate().is_ok(), "{}", prefix);
+        }
+
+        for prefix in ["/etc", "../other", "2025/../../other"] {
+            let query = SearchQuery::new("deploy").path_prefix(prefix);
+            assert!(
+                matches!(
+                    query.validate(),
+                    Err(SearchQueryError::InvalidPathPrefix(_))
+                ),
+                "{}",
+                prefix
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalized_path_prefix() {
+        let query = SearchQuery::new("deploy").path_prefix("./2025/");
+        assert_eq!(query.normalized_path_prefix(), Some("2025/"));
+
+        let query = SearchQuery::new("deploy").path_prefix("./");
+        assert_eq!(query.normalized_path_prefix(), None);
+    }
+
+    #[test]
+    fn test_search_order() {
+        for order in [
+            SearchOrder::Relevance,
+            SearchOrder::Newest,
+            SearchOrder::Oldest,
+        ] {
+            assert_eq!(order.to_string().parse::<SearchOrder>().unwrap(), order);
+        }
+
+        assert!(matches!(
+            "best".parse::<SearchOrder>(),
+            Err(SearchQueryError::InvalidOrder(_))
+        ));
+    }
+}
@@ -1,448 +1,2600 @@
-#[cfg(test)]
-mod tests {
-    use crate::core::frontmatter::Frontmatter;
-    use crate::core::note::Note;
-    use crate::core::tags::Tag;
-    use crate::db::{
-        DB_FILENAME, Database, delete_notes_by_filepaths, get_all_note_filepaths,
-        index_notes_with_channel,
-    };
-    use chrono::{Local, TimeZone};
-    use std::fs;
-    use std::str::FromStr;
-    use tempfile::TempDir;
-    use tokio::runtime::Runtime;
+use crate::core::frontmatter::Frontmatter;
+use crate::core::note::Note;
+use crate::core::retention::RetentionPolicy;
+use crate::core::tags::Tag;
+use crate::db::backup::{create_daily_backup, prune_backups};
+use crate::db::lock::{acquire_maintenance_lock, wait_for_maintenance};
+use crate::db::metrics::MetricsRecorder;
+use crate::db::monitoring::{FileMonitoringHandler, Monitor, process_events};
+use crate::db::verify::verify_content_hashes;
+use crate::db::{
+    DB_FILENAME, Database, FileStatus, IGNORE_FILENAME, Metrics, MonthCount, PrefixMatch,
+    RESERVED_DIRS, SearchOrder, SearchQuery, check_notes_dir, count_notes_with_id_prefix,
+    delete_notes_by_filepaths, detect_language, get_all_note_filepaths, index_notes_with_channel,
+    process_note_file,
+};
+use crate::error::{DatabaseError, NotelogError, SearchQueryError};
+use crate::utils::SkipReason;
+use chrono::{Local, NaiveDate, TimeZone};
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+
+#[test]
+fn test_database_initialization() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    // Initialize the database
+    let _db = rt.block_on(async { Database::initialize(notes_dir).await.unwrap() });
+
+    // Verify the database file was created
+    let db_path = notes_dir.join(DB_FILENAME);
+    assert!(db_path.exists());
+}
+
+#[test]
+fn test_note_indexing() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
 
-    #[test]
-    fn test_database_initialization() {
-        // Create a temporary directory for testing
-        let temp_dir = TempDir::new().unwrap();
-        let notes_dir = temp_dir.path();
+    // Create a test note
+    rt.block_on(async {
+        // Create year/month directories
+        let year_dir = notes_dir.join("2025");
+        let month_dir = year_dir.join("05");
+        fs::create_dir_all(&month_dir).unwrap();
 
-        // Create a tokio runtime for testing
-        let rt = Runtime::new().unwrap();
+        // Create a test note with tags
+        let mut frontmatter = Frontmatter::default();
+        let tag1 = Tag::new("test").unwrap();
+        let tag2 = Tag::new("example").unwrap();
+        frontmatter.add_tag(tag1).unwrap();
+        frontmatter.add_tag(tag2).unwrap();
+
+        let content = "# Test Note\nThis is a test note for database indexing.";
+        let note = Note::new(frontmatter, content.to_string());
+
+        // Save the note to disk
+        let note_path = note.save(notes_dir, Some("Test Note")).unwrap();
+        assert!(notes_dir.join(&note_path).exists());
 
         // Initialize the database
-        let _db = rt.block_on(async { Database::initialize(notes_dir).await.unwrap() });
-
-        // Verify the database file was created
-        let db_path = notes_dir.join(DB_FILENAME);
-        assert!(db_path.exists());
-    }
-
-    #[test]
-    fn test_note_indexing() {
-        // Create a temporary directory for testing
-        let temp_dir = TempDir::new().unwrap();
-        let notes_dir = temp_dir.path();
-
-        // Create a tokio runtime for testing
-        let rt = Runtime::new().unwrap();
-
-        // Create a test note
-        rt.block_on(async {
-            // Create year/month directories
-            let year_dir = notes_dir.join("2025");
-            let month_dir = year_dir.join("05");
-            fs::create_dir_all(&month_dir).unwrap();
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        // Run the indexing task
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Search for notes by tag using fulltext search
+        let (notes, total_count) = db.search_notes(&SearchQuery::new("+test")).await.unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(total_count, 1);
+
+        // Search for notes by multiple tags using fulltext search
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("+test +example"))
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(total_count, 1);
+
+        // Search for non-existent tag using fulltext search
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("+nonexistent"))
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 0);
+        assert_eq!(total_count, 0);
+    });
+}
 
-            // Create a test note with tags
-            let mut frontmatter = Frontmatter::default();
-            let tag1 = Tag::new("test").unwrap();
-            let tag2 = Tag::new("example").unwrap();
-            frontmatter.add_tag(tag1);
-            frontmatter.add_tag(tag2);
+#[test]
+fn test_note_deletion() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
 
-            let content = "# Test Note\nThis is a test note for database indexing.";
-            let note = Note::new(frontmatter, content.to_string());
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
 
-            // Save the note to disk
-            let note_path = note.save(notes_dir, Some("Test Note")).unwrap();
-            assert!(notes_dir.join(&note_path).exists());
+    rt.block_on(async {
+        // Create year/month directories
+        let year_dir = notes_dir.join("2025");
+        let month_dir = year_dir.join("05");
+        fs::create_dir_all(&month_dir).unwrap();
 
-            // Initialize the database
-            let db = Database::initialize(notes_dir).await.unwrap();
+        // Create two test notes with tags
+        let mut frontmatter1 = Frontmatter::default();
+        let tag1 = Tag::new("test").unwrap();
+        let tag2 = Tag::new("example").unwrap();
+        frontmatter1.add_tag(tag1.clone()).unwrap();
+        frontmatter1.add_tag(tag2.clone()).unwrap();
 
-            // Run the indexing task
-            index_notes_with_channel(db.pool().clone(), notes_dir)
-                .await
-                .unwrap();
+        let content1 = "# Test Note 1\nThis is the first test note.";
+        let note1 = Note::new(frontmatter1, content1.to_string());
 
-            // Search for notes by tag using fulltext search
-            let (notes, total_count) = db.search_notes("+test", None, None, None).await.unwrap();
-            assert_eq!(notes.len(), 1);
-            assert_eq!(total_count, 1);
+        let mut frontmatter2 = Frontmatter::default();
+        frontmatter2.add_tag(tag1).unwrap();
+        frontmatter2.add_tag(tag2).unwrap();
 
-            // Search for notes by multiple tags using fulltext search
-            let (notes, total_count) = db
-                .search_notes("+test +example", None, None, None)
-                .await
-                .unwrap();
-            assert_eq!(notes.len(), 1);
-            assert_eq!(total_count, 1);
+        let content2 = "# Test Note 2\nThis is the second test note.";
+        let note2 = Note::new(frontmatter2, content2.to_string());
 
-            // Search for non-existent tag using fulltext search
-            let (notes, total_count) = db
-                .search_notes("+nonexistent", None, None, None)
-                .await
-                .unwrap();
-            assert_eq!(notes.len(), 0);
-            assert_eq!(total_count, 0);
-        });
-    }
-
-    #[test]
-    fn test_note_deletion() {
-        // Create a temporary directory for testing
-        let temp_dir = TempDir::new().unwrap();
-        let notes_dir = temp_dir.path();
-
-        // Create a tokio runtime for testing
-        let rt = Runtime::new().unwrap();
-
-        rt.block_on(async {
-            // Create year/month directories
-            let year_dir = notes_dir.join("2025");
-            let month_dir = year_dir.join("05");
-            fs::create_dir_all(&month_dir).unwrap();
+        // Save the notes to disk
+        let note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
+        let note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
 
-            // Create two test notes with tags
-            let mut frontmatter1 = Frontmatter::default();
-            let tag1 = Tag::new("test").unwrap();
-            let tag2 = Tag::new("example").unwrap();
-            frontmatter1.add_tag(tag1.clone());
-            frontmatter1.add_tag(tag2.clone());
+        assert!(notes_dir.join(&note_path1).exists());
+        assert!(notes_dir.join(&note_path2).exists());
 
-            let content1 = "# Test Note 1\nThis is the first test note.";
-            let note1 = Note::new(frontmatter1, content1.to_string());
+        // Initialize the database
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        // Run the indexing task
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Verify both notes are in the database
+        let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
+        assert_eq!(filepaths.len(), 2);
+        assert!(filepaths.contains(&note_path1.to_string_lossy().to_string()));
+        assert!(filepaths.contains(&note_path2.to_string_lossy().to_string()));
+
+        // Delete the first note from disk
+        fs::remove_file(notes_dir.join(&note_path1)).unwrap();
+
+        // Run the indexing task again
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Verify only the second note remains in the database
+        let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
+        assert_eq!(filepaths.len(), 1);
+        assert!(!filepaths.contains(&note_path1.to_string_lossy().to_string()));
+        assert!(filepaths.contains(&note_path2.to_string_lossy().to_string()));
+
+        // Test direct deletion using delete_notes_by_filepaths
+        let to_delete = vec![note_path2.to_string_lossy().to_string()];
+        delete_notes_by_filepaths(db.pool(), &to_delete)
+            .await
+            .unwrap();
+
+        // Verify no notes remain in the database
+        let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
+        assert_eq!(filepaths.len(), 0);
+    });
+}
 
-            let mut frontmatter2 = Frontmatter::default();
-            frontmatter2.add_tag(tag1);
-            frontmatter2.add_tag(tag2);
+#[test]
+fn test_find_shortest_unique_id_prefix() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // Create year/month directories
+        let year_dir = notes_dir.join("2025");
+        let month_dir = year_dir.join("05");
+        fs::create_dir_all(&month_dir).unwrap();
+
+        // Create three test notes with different IDs
+        // We'll create the notes with YAML frontmatter that includes the IDs
+        // Note 1: ID starts with "abcd1234"
+        let id1 = "abcd1234efgh0000";
+        let yaml1 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id1);
+        let frontmatter1 = yaml1.parse::<Frontmatter>().unwrap();
+        let content1 = "# Test Note 1\nThis is the first test note.";
+        let note1 = Note::new(frontmatter1.clone(), content1.to_string());
+
+        // Note 2: ID starts with "abcd5678"
+        let id2 = "abcd5678efgh0000";
+        let yaml2 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id2);
+        let frontmatter2 = yaml2.parse::<Frontmatter>().unwrap();
+        let content2 = "# Test Note 2\nThis is the second test note.";
+        let note2 = Note::new(frontmatter2.clone(), content2.to_string());
+
+        // Note 3: ID starts with "wxyz"
+        let id3 = "wxyz1234efgh0000";
+        let yaml3 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id3);
+        let frontmatter3 = yaml3.parse::<Frontmatter>().unwrap();
+        let content3 = "# Test Note 3\nThis is the third test note.";
+        let note3 = Note::new(frontmatter3.clone(), content3.to_string());
+
+        // Save the notes to disk
+        let _note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
+        let _note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
+        let _note_path3 = note3.save(notes_dir, Some("Test Note 3")).unwrap();
 
-            let content2 = "# Test Note 2\nThis is the second test note.";
-            let note2 = Note::new(frontmatter2, content2.to_string());
+        // Initialize the database
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        // Run the indexing task
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Test 1: Find shortest unique prefix for id1
+        // Since id1 and id2 both start with "abcd", we need more characters to distinguish them
+        let id1_obj = frontmatter1.id().unwrap();
+        let prefix1 = db.find_shortest_unique_id_prefix(id1_obj).await.unwrap();
+        assert_eq!(prefix1.len(), 5); // "abcd1" should be unique
+        assert_eq!(&prefix1, "abcd1");
+
+        // Test 2: Find shortest unique prefix for id2
+        // Since id1 and id2 both start with "abcd", we need more characters to distinguish them
+        let id2_obj = frontmatter2.id().unwrap();
+        let prefix2 = db.find_shortest_unique_id_prefix(id2_obj).await.unwrap();
+        assert_eq!(prefix2.len(), 5); // "abcd5" should be unique
+        assert_eq!(&prefix2, "abcd5");
+
+        // Test 3: Find shortest unique prefix for id3
+        // Since id3 starts with "wxyz" which is unique, we should get the minimum length (4)
+        let id3_obj = frontmatter3.id().unwrap();
+        let prefix3 = db.find_shortest_unique_id_prefix(id3_obj).await.unwrap();
+        assert_eq!(prefix3.len(), 2); // "wxyz" should be unique, but we return at least 4 chars
+        assert_eq!(&prefix3, "wx");
+
+        // Test 4: Try to find prefix for non-existent ID
+        let nonexistent_id = crate::core::id::Id::from_str("nonexistent00000").unwrap();
+        let result = db.find_shortest_unique_id_prefix(&nonexistent_id).await;
+        assert!(result.is_err());
+    });
+}
 
-            // Save the notes to disk
-            let note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
-            let note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
+#[test]
+fn test_fetch_note_by_id_prefix() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // Create year/month directories
+        let year_dir = notes_dir.join("2025");
+        let month_dir = year_dir.join("05");
+        fs::create_dir_all(&month_dir).unwrap();
+
+        // Create three test notes with different IDs
+        // Note 1: ID starts with "abcd1234"
+        let id1 = "abcd1234efgh0000";
+        let yaml1 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id1);
+        let frontmatter1 = yaml1.parse::<Frontmatter>().unwrap();
+        let content1 = "# Test Note 1\nThis is the first test note.";
+        let note1 = Note::new(frontmatter1.clone(), content1.to_string());
+
+        // Note 2: ID starts with "abcd5678"
+        let id2 = "abcd5678efgh0000";
+        let yaml2 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id2);
+        let frontmatter2 = yaml2.parse::<Frontmatter>().unwrap();
+        let content2 = "# Test Note 2\nThis is the second test note.";
+        let note2 = Note::new(frontmatter2.clone(), content2.to_string());
+
+        // Note 3: ID starts with "wxyz"
+        let id3 = "wxyz1234efgh0000";
+        let yaml3 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id3);
+        let frontmatter3 = yaml3.parse::<Frontmatter>().unwrap();
+        let content3 = "# Test Note 3\nThis is the third test note.";
+        let note3 = Note::new(frontmatter3.clone(), content3.to_string());
+
+        // Save the notes to disk
+        let _note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
+        let _note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
+        let _note_path3 = note3.save(notes_dir, Some("Test Note 3")).unwrap();
 
-            assert!(notes_dir.join(&note_path1).exists());
-            assert!(notes_dir.join(&note_path2).exists());
+        // Initialize the database
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        // Run the indexing task
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Test 1: Fetch note with a unique prefix
+        let result = db.fetch_note_by_id("wx").await;
+        assert!(result.is_ok());
+        let note_option = result.unwrap();
+        assert!(note_option.is_some());
+        let note = note_option.unwrap();
+        assert_eq!(note.frontmatter().id().unwrap().as_str(), id3);
+        // The content might have newlines added, so just check that it contains the original content
+        assert!(note.content().contains(content3));
+
+        // Test 2: Fetch note with a longer unique prefix
+        let result = db.fetch_note_by_id("abcd1").await;
+        assert!(result.is_ok());
+        let note_option = result.unwrap();
+        assert!(note_option.is_some());
+        let note = note_option.unwrap();
+        assert_eq!(note.frontmatter().id().unwrap().as_str(), id1);
+        // The content might have newlines added, so just check that it contains the original content
+        assert!(note.content().contains(content1));
+
+        // Test 3: Fetch note with a non-unique prefix (should return an error)
+        let result = db.fetch_note_by_id("abcd").await;
+        assert!(result.is_err());
+        let error = result.unwrap_err();
+        let error_string = error.to_string();
+        assert!(
+            error_string.contains("Multiple notes found with ID prefix 'abcd': 2 matches"),
+            "Expected error message about multiple matches, got: {}",
+            error_string
+        );
+
+        // Test 4: Fetch note with a non-existent prefix
+        let result = db.fetch_note_by_id("nonexistent").await;
+        assert!(result.is_ok());
+        let note_option = result.unwrap();
+        assert!(note_option.is_none());
+    });
+}
 
-            // Initialize the database
-            let db = Database::initialize(notes_dir).await.unwrap();
+#[test]
+fn test_id_prefix_lookups_use_the_note_id_index() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // IDs around the end of the range of the prefix "az"
+        for (id, title) in [
+            ("ayzzzzzzzzzzzzzz", "Before"),
+            ("az00000000000000", "First"),
+            ("azzzzzzzzzzzzzzz", "Last"),
+            ("b000000000000000", "After"),
+        ] {
+            let yaml = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id);
+            let note = Note::new(yaml.parse::<Frontmatter>().unwrap(), format!("# {}", title));
+            note.save(notes_dir, Some(title)).unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        assert_eq!(
+            count_notes_with_id_prefix(db.pool(), "az").await.unwrap(),
+            2
+        );
+        assert_eq!(
+            count_notes_with_id_prefix(db.pool(), "azz").await.unwrap(),
+            1
+        );
+        assert_eq!(
+            count_notes_with_id_prefix(db.pool(), "zz").await.unwrap(),
+            0
+        );
+        let note = db.fetch_note_by_id("azz").await.unwrap().unwrap();
+        assert_eq!(
+            note.frontmatter().id().unwrap().as_str(),
+            "azzzzzzzzzzzzzzz"
+        );
+
+        let prefix = db
+            .find_shortest_unique_id_prefix(&"b000000000000000".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(prefix, "b0");
+
+        // The prefix range is a search of the index, not a scan of all notes
+        let plan = sqlx::query_as::<_, (i64, i64, i64, String)>(
+            "EXPLAIN QUERY PLAN SELECT COUNT(*) FROM notes WHERE note_id >= ? AND note_id < ?",
+        )
+        .bind("az")
+        .bind("a{")
+        .fetch_all(db.pool())
+        .await
+        .unwrap();
+        assert!(
+            plan.iter()
+                .any(|(_, _, _, detail)| detail.contains("note_note_id_idx")),
+            "{:?}",
+            plan
+        );
+    });
+}
 
-            // Run the indexing task
-            index_notes_with_channel(db.pool().clone(), notes_dir)
-                .await
-                .unwrap();
+#[test]
+fn test_fetch_notes_by_prefixes() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for (id, title) in [
+            ("abcd1234efgh0000", "First"),
+            ("abcd5678efgh0000", "Second"),
+            ("wxyz1234efgh0000", "Third"),
+        ] {
+            let yaml = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id);
+            let note = Note::new(
+                yaml.parse::<Frontmatter>().unwrap(),
+                format!("# {}\n\nBody of the {} note.", title, title),
+            );
+            note.save(notes_dir, Some(title)).unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        let prefixes: Vec<String> = ["wx", "abcd", "zz", "abcd5", "wxyz"]
+            .iter()
+            .map(|prefix| prefix.to_string())
+            .collect();
+        let results = db.fetch_notes_by_prefixes(&prefixes).await.unwrap();
+        assert_eq!(results.len(), 5);
+
+        let id = |result: &PrefixMatch| match result {
+            PrefixMatch::Found(note) => note.frontmatter().id().unwrap().to_string(),
+            other => panic!("Expected a note, got {:?}", other),
+        };
+        assert_eq!(id(&results[0]), "wxyz1234efgh0000");
+        assert!(matches!(results[1], PrefixMatch::Ambiguous(2)));
+        assert!(matches!(results[2], PrefixMatch::NotFound));
+        assert_eq!(id(&results[3]), "abcd5678efgh0000");
+        assert_eq!(id(&results[4]), "wxyz1234efgh0000");
+        if let PrefixMatch::Found(note) = &results[3] {
+            assert!(note.content().contains("Body of the Second note."));
+        }
+
+        assert!(db.fetch_notes_by_prefixes(&[]).await.unwrap().is_empty());
+    });
+}
 
-            // Verify both notes are in the database
-            let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
-            assert_eq!(filepaths.len(), 2);
-            assert!(filepaths.contains(&note_path1.to_string_lossy().to_string()));
-            assert!(filepaths.contains(&note_path2.to_string_lossy().to_string()));
+#[test]
+fn test_search_notes_with_date_range() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // Create year/month directories
+        let year_dir = notes_dir.join("2025");
+        let month_dir = year_dir.join("05");
+        fs::create_dir_all(&month_dir).unwrap();
+
+        // Create three test notes with different creation dates
+        // Note 1: Created 2025-05-01
+        let date1 = Local.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap();
+        let mut frontmatter1 = Frontmatter::new(date1, vec![]);
+        let tag1 = Tag::new("test").unwrap();
+        frontmatter1.add_tag(tag1.clone()).unwrap();
+        let content1 = "# Test Note 1\nThis is the first test note.";
+        let note1 = Note::new(frontmatter1, content1.to_string());
+
+        // Note 2: Created 2025-05-15
+        let date2 = Local.with_ymd_and_hms(2025, 5, 15, 12, 0, 0).unwrap();
+        let mut frontmatter2 = Frontmatter::new(date2, vec![]);
+        frontmatter2.add_tag(tag1.clone()).unwrap();
+        let content2 = "# Test Note 2\nThis is the second test note.";
+        let note2 = Note::new(frontmatter2, content2.to_string());
+
+        // Note 3: Created 2025-05-30
+        let date3 = Local.with_ymd_and_hms(2025, 5, 30, 12, 0, 0).unwrap();
+        let mut frontmatter3 = Frontmatter::new(date3, vec![]);
+        frontmatter3.add_tag(tag1).unwrap();
+        let content3 = "# Test Note 3\nThis is the third test note.";
+        let note3 = Note::new(frontmatter3, content3.to_string());
+
+        // Save the notes to disk
+        let _note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
+        let _note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
+        let _note_path3 = note3.save(notes_dir, Some("Test Note 3")).unwrap();
 
-            // Delete the first note from disk
-            fs::remove_file(notes_dir.join(&note_path1)).unwrap();
+        // Initialize the database
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        // Run the indexing task
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Test 1: Search with no date filters (should return all 3 notes)
+        let (notes, total_count) = db.search_notes(&SearchQuery::new("+test")).await.unwrap();
+        assert_eq!(notes.len(), 3);
+        assert_eq!(total_count, 3);
+
+        // Just verify that we have 3 notes in the results
+        assert_eq!(notes.len(), 3, "Should have 3 notes in the results");
+
+        // Test 2: Search for notes before 2025-05-20
+        let before_date = Local.with_ymd_and_hms(2025, 5, 20, 0, 0, 0).unwrap();
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("+test").before(before_date))
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(total_count, 2);
+
+        // Just verify that we have 2 notes in the results
+        assert_eq!(notes.len(), 2, "Should have 2 notes in the results");
+
+        // Test 3: Search for notes after 2025-05-10
+        let after_date = Local.with_ymd_and_hms(2025, 5, 10, 0, 0, 0).unwrap();
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("+test").after(after_date))
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(total_count, 2);
+
+        // Just verify that we have 2 notes in the results
+        assert_eq!(notes.len(), 2, "Should have 2 notes in the results");
+
+        // Test 4: Search with both before and after filters
+        let before_date = Local.with_ymd_and_hms(2025, 5, 25, 0, 0, 0).unwrap();
+        let after_date = Local.with_ymd_and_hms(2025, 5, 10, 0, 0, 0).unwrap();
+        let (notes, total_count) = db
+            .search_notes(
+                &SearchQuery::new("+test")
+                    .before(before_date)
+                    .after(after_date),
+            )
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(total_count, 1);
+
+        // Just verify that we have 1 note in the results
+        assert_eq!(notes.len(), 1, "Should have 1 note in the results");
+
+        // Test 5: Non-overlapping date range (before < after) is an error
+        let before_date = Local.with_ymd_and_hms(2025, 5, 5, 0, 0, 0).unwrap();
+        let after_date = Local.with_ymd_and_hms(2025, 5, 10, 0, 0, 0).unwrap();
+        let result = db
+            .search_notes(
+                &SearchQuery::new("+test")
+                    .before(before_date)
+                    .after(after_date),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(NotelogError::SearchQueryError(
+                SearchQueryError::InvalidDateRange(..)
+            ))
+        ));
+    });
+}
 
-            // Run the indexing task again
-            index_notes_with_channel(db.pool().clone(), notes_dir)
-                .await
+#[test]
+fn test_search_notes_order_offset_and_path_prefix() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // One note per month, each in its month directory
+        for month in 1..=3 {
+            let created = Local.with_ymd_and_hms(2025, month, 10, 12, 0, 0).unwrap();
+            let note = Note::new(
+                Frontmatter::new(created, vec![]),
+                format!("# Report {}\nMonthly report.", month),
+            );
+            let month_dir = notes_dir.join(format!("2025/{:02}", month));
+            fs::create_dir_all(&month_dir).unwrap();
+            fs::write(
+                month_dir.join(format!("2025-{:02}-10T12-00 Report.md", month)),
+                note.to_string(),
+            )
+            .unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let titles = |notes: Vec<Note>| -> Vec<String> {
+            notes.iter().map(|note| note.extract_title()).collect()
+        };
+
+        let query = SearchQuery::new("report").order(SearchOrder::Oldest);
+        let (notes, _) = db.search_notes(&query).await.unwrap();
+        assert_eq!(titles(notes), ["Report 1", "Report 2", "Report 3"]);
+
+        // The total counts all matches, regardless of the page
+        let query = SearchQuery::new("report")
+            .order(SearchOrder::Newest)
+            .limit(1)
+            .offset(1);
+        let (notes, total_count) = db.search_notes(&query).await.unwrap();
+        assert_eq!(titles(notes), ["Report 2"]);
+        assert_eq!(total_count, 3);
+
+        // An offset without a limit skips the first notes
+        let query = SearchQuery::new("report")
+            .order(SearchOrder::Oldest)
+            .offset(2);
+        let (notes, _) = db.search_notes(&query).await.unwrap();
+        assert_eq!(titles(notes), ["Report 3"]);
+
+        let query = SearchQuery::new("report").path_prefix("./2025/02");
+        let (notes, total_count) = db.search_notes(&query).await.unwrap();
+        assert_eq!(titles(notes), ["Report 2"]);
+        assert_eq!(total_count, 1);
+
+        // Invalid parameters are reported before the search runs
+        let result = db
+            .search_notes(&SearchQuery::new("report").path_prefix("../2025"))
+            .await;
+        assert!(matches!(
+            result,
+            Err(NotelogError::SearchQueryError(
+                SearchQueryError::InvalidPathPrefix(_)
+            ))
+        ));
+    });
+}
+
+#[test]
+fn test_search_month_counts() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for (month, day, title) in [
+            (1, 10, "Report"),
+            (1, 20, "Report"),
+            (3, 10, "Report"),
+            (3, 11, "Memo"),
+        ] {
+            let created = Local.with_ymd_and_hms(2025, month, day, 12, 0, 0).unwrap();
+            let note = Note::new(Frontmatter::new(created, vec![]), format!("# {}", title));
+            note.save(notes_dir, Some(&format!("{} {}", title, day)))
                 .unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let month = |month: &str, count| MonthCount {
+            month: month.to_string(),
+            count,
+        };
+
+        // The limit does not apply, so the counts add up to the total
+        let query = SearchQuery::new("report").limit(0);
+        let counts = db.search_month_counts(&query).await.unwrap();
+        assert_eq!(counts, vec![month("2025-01", 2), month("2025-03", 1)]);
+
+        // The filters do
+        let after = Local.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap();
+        let query = SearchQuery::new("report").after(Some(after)).limit(1);
+        let counts = db.search_month_counts(&query).await.unwrap();
+        assert_eq!(counts, vec![month("2025-01", 1), month("2025-03", 1)]);
+
+        let counts = db
+            .search_month_counts(&SearchQuery::new("zeppelin"))
+            .await
+            .unwrap();
+        assert!(counts.is_empty());
+    });
+}
 
-            // Verify only the second note remains in the database
-            let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
-            assert_eq!(filepaths.len(), 1);
-            assert!(!filepaths.contains(&note_path1.to_string_lossy().to_string()));
-            assert!(filepaths.contains(&note_path2.to_string_lossy().to_string()));
+#[test]
+fn test_metrics_record_searches_and_indexing() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
 
-            // Test direct deletion using delete_notes_by_filepaths
-            let to_delete = vec![note_path2.to_string_lossy().to_string()];
-            delete_notes_by_filepaths(db.pool(), &to_delete)
-                .await
+    rt.block_on(async {
+        for title in ["Airships", "Trains"] {
+            Note::new(Frontmatter::default(), format!("# {}", title))
+                .save(notes_dir, None)
                 .unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        assert_eq!(db.metrics(), Metrics::default());
+
+        db.index_notes().await.unwrap();
+        db.search_notes(&SearchQuery::new("airships"))
+            .await
+            .unwrap();
+        // Failed searches take time as well
+        let _ = db.search_notes(&SearchQuery::new("\"unbalanced")).await;
+
+        // The clones share the metrics
+        let metrics = db.clone().metrics();
+        assert_eq!(metrics.indexing_pass.count, 1);
+        assert_eq!(metrics.process_note_file.count, 2);
+        assert_eq!(metrics.search.count, 2);
+        assert!(metrics.search.max <= metrics.search.total);
+        assert!(metrics.process_note_file.total <= metrics.indexing_pass.total);
+    });
+}
 
-            // Verify no notes remain in the database
-            let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
-            assert_eq!(filepaths.len(), 0);
-        });
-    }
-
-    #[test]
-    fn test_find_shortest_unique_id_prefix() {
-        // Create a temporary directory for testing
-        let temp_dir = TempDir::new().unwrap();
-        let notes_dir = temp_dir.path();
-
-        // Create a tokio runtime for testing
-        let rt = Runtime::new().unwrap();
-
-        rt.block_on(async {
-            // Create year/month directories
-            let year_dir = notes_dir.join("2025");
-            let month_dir = year_dir.join("05");
-            fs::create_dir_all(&month_dir).unwrap();
+#[test]
+fn test_search_hyphenated_terms_and_tags() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // Note 1: Hyphenated term in the content
+        let content1 = "# Rotation\nI was on-call for the state-of-the-art cluster.";
+        let note1 = Note::new(Frontmatter::default(), content1.to_string());
+
+        // Note 2: Hyphenated tag only
+        let frontmatter2 = Frontmatter::with_tags(vec![Tag::new("on-call").unwrap()]);
+        let content2 = "# Handover\nNothing happened.";
+        let note2 = Note::new(frontmatter2, content2.to_string());
+
+        // Note 3: Unrelated note
+        let content3 = "# Unrelated\nCall me maybe.";
+        let note3 = Note::new(Frontmatter::default(), content3.to_string());
+
+        note1.save(notes_dir, Some("Rotation")).unwrap();
+        note2.save(notes_dir, Some("Handover")).unwrap();
+        note3.save(notes_dir, Some("Unrelated")).unwrap();
+
+        // Initialize the database and index the notes
+        let db = Database::initialize(notes_dir).await.unwrap();
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // A bare hyphenated term matches both the hyphenated text and the hyphenated tag,
+        // just like any other bare term matches content and tags
+        let (_, total_count) = db.search_notes(&SearchQuery::new("on-call")).await.unwrap();
+        assert_eq!(total_count, 2);
+
+        // The same hyphenated word as a tag matches the tagged note
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("+on-call"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+        assert_eq!(notes[0].tags_as_strings(), vec!["on-call".to_string()]);
+
+        // Terms with multiple hyphens work as well
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("state-of-the-art"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+    });
+}
 
-            // Create three test notes with different IDs
-            // We'll create the notes with YAML frontmatter that includes the IDs
-            // Note 1: ID starts with "abcd1234"
-            let id1 = "abcd1234efgh0000";
-            let yaml1 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id1);
-            let frontmatter1 = yaml1.parse::<Frontmatter>().unwrap();
-            let content1 = "# Test Note 1\nThis is the first test note.";
-            let note1 = Note::new(frontmatter1.clone(), content1.to_string());
-
-            // Note 2: ID starts with "abcd5678"
-            let id2 = "abcd5678efgh0000";
-            let yaml2 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id2);
-            let frontmatter2 = yaml2.parse::<Frontmatter>().unwrap();
-            let content2 = "# Test Note 2\nThis is the second test note.";
-            let note2 = Note::new(frontmatter2.clone(), content2.to_string());
-
-            // Note 3: ID starts with "wxyz"
-            let id3 = "wxyz1234efgh0000";
-            let yaml3 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id3);
-            let frontmatter3 = yaml3.parse::<Frontmatter>().unwrap();
-            let content3 = "# Test Note 3\nThis is the third test note.";
-            let note3 = Note::new(frontmatter3.clone(), content3.to_string());
-
-            // Save the notes to disk
-            let _note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
-            let _note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
-            let _note_path3 = note3.save(notes_dir, Some("Test Note 3")).unwrap();
-
-            // Initialize the database
-            let db = Database::initialize(notes_dir).await.unwrap();
-
-            // Run the indexing task
-            index_notes_with_channel(db.pool().clone(), notes_dir)
-                .await
+#[test]
+fn test_search_tags_match_whole_words() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for tag in ["project", "projects", "project-x"] {
+            let frontmatter = Frontmatter::with_tags(vec![Tag::new(tag).unwrap()]);
+            Note::new(frontmatter, format!("# Tagged {}", tag))
+                .save(notes_dir, Some(tag))
                 .unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let tags = |query: &'static str| {
+            let db = &db;
+            async move {
+                let (notes, _) = db.search_notes(&SearchQuery::new(query)).await.unwrap();
+                let mut tags: Vec<String> = notes
+                    .iter()
+                    .flat_map(|note| note.tags_as_strings())
+                    .collect();
+                tags.sort();
+                tags
+            }
+        };
+
+        // A tag is not a prefix of longer tags, but matches a whole
+        // dash-separated part of one; the aliases behave the same
+        for query in ["+project", "tag:project", "tags:project"] {
+            assert_eq!(tags(query).await, vec!["project", "project-x"], "{}", query);
+        }
+        assert_eq!(tags("+projects").await, vec!["projects"]);
+        assert_eq!(tags("tag:project-x").await, vec!["project-x"]);
+    });
+}
 
-            // Test 1: Find shortest unique prefix for id1
-            // Since id1 and id2 both start with "abcd", we need more characters to distinguish them
-            let id1_obj = frontmatter1.id().unwrap();
-            let prefix1 = db.find_shortest_unique_id_prefix(id1_obj).await.unwrap();
-            assert_eq!(prefix1.len(), 5); // "abcd1" should be unique
-            assert_eq!(&prefix1, "abcd1");
-
-            // Test 2: Find shortest unique prefix for id2
-            // Since id1 and id2 both start with "abcd", we need more characters to distinguish them
-            let id2_obj = frontmatter2.id().unwrap();
-            let prefix2 = db.find_shortest_unique_id_prefix(id2_obj).await.unwrap();
-            assert_eq!(prefix2.len(), 5); // "abcd5" should be unique
-            assert_eq!(&prefix2, "abcd5");
-
-            // Test 3: Find shortest unique prefix for id3
-            // Since id3 starts with "wxyz" which is unique, we should get the minimum length (4)
-            let id3_obj = frontmatter3.id().unwrap();
-            let prefix3 = db.find_shortest_unique_id_prefix(id3_obj).await.unwrap();
-            assert_eq!(prefix3.len(), 2); // "wxyz" should be unique, but we return at least 4 chars
-            assert_eq!(&prefix3, "wx");
-
-            // Test 4: Try to find prefix for non-existent ID
-            let nonexistent_id = crate::core::id::Id::from_str("nonexistent00000").unwrap();
-            let result = db.find_shortest_unique_id_prefix(&nonexistent_id).await;
-            assert!(result.is_err());
-        });
-    }
-
-    #[test]
-    fn test_fetch_note_by_id_prefix() {
-        // Create a temporary directory for testing
-        let temp_dir = TempDir::new().unwrap();
-        let notes_dir = temp_dir.path();
-
-        // Create a tokio runtime for testing
-        let rt = Runtime::new().unwrap();
-
-        rt.block_on(async {
-            // Create year/month directories
-            let year_dir = notes_dir.join("2025");
-            let month_dir = year_dir.join("05");
-            fs::create_dir_all(&month_dir).unwrap();
+#[test]
+fn test_search_with_embedded_quotes() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let content = "# Shelf\nThe shelf is 5\"6 tall, don't bump into it.";
+        let note = Note::new(Frontmatter::default(), content.to_string());
+        note.save(notes_dir, Some("Shelf")).unwrap();
+
+        // Initialize the database and index the notes
+        let db = Database::initialize(notes_dir).await.unwrap();
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Inch marks and apostrophes are valid FTS5 queries after processing
+        let (_, total_count) = db.search_notes(&SearchQuery::new(r#"5"6"#)).await.unwrap();
+        assert_eq!(total_count, 1);
+
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new(r#""don't bump""#))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+    });
+}
 
-            // Create three test notes with different IDs
-            // Note 1: ID starts with "abcd1234"
-            let id1 = "abcd1234efgh0000";
-            let yaml1 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id1);
-            let frontmatter1 = yaml1.parse::<Frontmatter>().unwrap();
-            let content1 = "# Test Note 1\nThis is the first test note.";
-            let note1 = Note::new(frontmatter1.clone(), content1.to_string());
-
-            // Note 2: ID starts with "abcd5678"
-            let id2 = "abcd5678efgh0000";
-            let yaml2 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id2);
-            let frontmatter2 = yaml2.parse::<Frontmatter>().unwrap();
-            let content2 = "# Test Note 2\nThis is the second test note.";
-            let note2 = Note::new(frontmatter2.clone(), content2.to_string());
-
-            // Note 3: ID starts with "wxyz"
-            let id3 = "wxyz1234efgh0000";
-            let yaml3 = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id3);
-            let frontmatter3 = yaml3.parse::<Frontmatter>().unwrap();
-            let content3 = "# Test Note 3\nThis is the third test note.";
-            let note3 = Note::new(frontmatter3.clone(), content3.to_string());
-
-            // Save the notes to disk
-            let _note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
-            let _note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
-            let _note_path3 = note3.save(notes_dir, Some("Test Note 3")).unwrap();
-
-            // Initialize the database
-            let db = Database::initialize(notes_dir).await.unwrap();
-
-            // Run the indexing task
-            index_notes_with_channel(db.pool().clone(), notes_dir)
-                .await
-                .unwrap();
+#[test]
+fn test_search_by_source() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // Note 1: Added from the command line
+        let mut frontmatter1 = Frontmatter::default();
+        frontmatter1.set_source("cli");
+        let note1 = Note::new(
+            frontmatter1,
+            "# Standup\nDiscussed the release.".to_string(),
+        );
+
+        // Note 2: Added by an MCP client
+        let mut frontmatter2 = Frontmatter::default();
+        frontmatter2.set_source("mcp:claude-desktop");
+        let note2 = Note::new(
+            frontmatter2,
+            "# Summary\nThe release is on track.".to_string(),
+        );
+
+        // Note 3: Written before sources were recorded
+        let note3 = Note::new(
+            Frontmatter::default(),
+            "# Old note\nAn old release.".to_string(),
+        );
+
+        note1.save(notes_dir, Some("Standup")).unwrap();
+        note2.save(notes_dir, Some("Summary")).unwrap();
+        note3.save(notes_dir, Some("Old note")).unwrap();
+
+        // Initialize the database and index the notes
+        let db = Database::initialize(notes_dir).await.unwrap();
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // Without a filter, all notes match
+        let (_, total_count) = db.search_notes(&SearchQuery::new("release")).await.unwrap();
+        assert_eq!(total_count, 3);
+
+        // A source filter matches the source and its more specific variants
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("release source:mcp"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+        assert_eq!(notes[0].frontmatter().source(), Some("mcp:claude-desktop"));
+
+        // A source filter alone lists all notes from that source
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("source:cli"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+        assert_eq!(notes[0].frontmatter().source(), Some("cli"));
+
+        // Multiple source filters match any of the sources
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("source:cli source:mcp"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 2);
+
+        // A source prefix must end at a colon
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("source:mc"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 0);
+    });
+}
 
-            // Test 1: Fetch note with a unique prefix
-            let result = db.fetch_note_by_id("wx").await;
-            assert!(result.is_ok());
-            let note_option = result.unwrap();
-            assert!(note_option.is_some());
-            let note = note_option.unwrap();
-            assert_eq!(note.frontmatter().id().unwrap().as_str(), id3);
-            // The content might have newlines added, so just check that it contains the original content
-            assert!(note.content().contains(content3));
-
-            // Test 2: Fetch note with a longer unique prefix
-            let result = db.fetch_note_by_id("abcd1").await;
-            assert!(result.is_ok());
-            let note_option = result.unwrap();
-            assert!(note_option.is_some());
-            let note = note_option.unwrap();
-            assert_eq!(note.frontmatter().id().unwrap().as_str(), id1);
-            // The content might have newlines added, so just check that it contains the original content
-            assert!(note.content().contains(content1));
-
-            // Test 3: Fetch note with a non-unique prefix (should return an error)
-            let result = db.fetch_note_by_id("abcd").await;
-            assert!(result.is_err());
-            let error = result.unwrap_err();
-            let error_string = error.to_string();
+#[test]
+fn test_raw_search() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mut frontmatter1 = Frontmatter::default();
+        frontmatter1.add_tag(Tag::new("project").unwrap()).unwrap();
+        let note1 = Note::new(
+            frontmatter1,
+            "# Kickoff\nThe project starts today.".to_string(),
+        );
+
+        let note2 = Note::new(
+            Frontmatter::default(),
+            "# Idea\nA project for the weekend.".to_string(),
+        );
+
+        note1.save(notes_dir, Some("Kickoff")).unwrap();
+        note2.save(notes_dir, Some("Idea")).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // A column filter restricts the match to the tags
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new(r#"tags:"+project""#).raw(true))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+        assert_eq!(notes[0].extract_title(), "Kickoff");
+
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("content:project").raw(true))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 2);
+
+        // Syntax errors are reported as invalid search queries
+        for query in ["project AND", "\"unterminated", "author:me"] {
+            let result = db.search_notes(&SearchQuery::new(query).raw(true)).await;
             assert!(
-                error_string.contains("Multiple notes found with ID prefix 'abcd': 2 matches"),
-                "Expected error message about multiple matches, got: {}",
-                error_string
+                matches!(
+                    result,
+                    Err(NotelogError::DatabaseError(
+                        DatabaseError::InvalidSearchQuery(_)
+                    ))
+                ),
+                "expected an invalid search query error for {}",
+                query
             );
+        }
+
+        // Raw queries are limited in length, too
+        let long_query = vec!["project"; 200].join(" OR ");
+        let result = db
+            .search_notes(&SearchQuery::new(long_query).raw(true))
+            .await;
+        assert!(matches!(
+            result,
+            Err(NotelogError::DatabaseError(
+                DatabaseError::InvalidSearchQuery(message)
+            )) if message.contains("too long")
+        ));
+    });
+}
 
-            // Test 4: Fetch note with a non-existent prefix
-            let result = db.fetch_note_by_id("nonexistent").await;
-            assert!(result.is_ok());
-            let note_option = result.unwrap();
-            assert!(note_option.is_none());
-        });
-    }
-
-    #[test]
-    fn test_search_notes_with_date_range() {
-        // Create a temporary directory for testing
-        let temp_dir = TempDir::new().unwrap();
-        let notes_dir = temp_dir.path();
-
-        // Create a tokio runtime for testing
-        let rt = Runtime::new().unwrap();
-
-        rt.block_on(async {
-            // Create year/month directories
-            let year_dir = notes_dir.join("2025");
-            let month_dir = year_dir.join("05");
-            fs::create_dir_all(&month_dir).unwrap();
+#[test]
+fn test_detect_language() {
+    assert_eq!(
+        detect_language(
+            "We deployed the new release to production yesterday and the connection issues are gone."
+        )
+        .as_deref(),
+        Some("en")
+    );
+    assert_eq!(
+        detect_language(
+            "Wir haben gestern die neue Version ausgeliefert und die Verbindungsprobleme sind verschwunden."
+        )
+        .as_deref(),
+        Some("de")
+    );
+
+    // Too short to detect reliably
+    assert_eq!(detect_language("ok"), None);
+}
+
+#[test]
+fn test_search_with_stemming() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // English notes are indexed with stemming
+        let note1 = Note::new(
+            Frontmatter::default(),
+            "# Outage\nThe database connection was dropped while we were deploying the new release."
+                .to_string(),
+        );
+
+        // German notes are not stemmed, even if they contain English words
+        let note2 = Note::new(
+            Frontmatter::default(),
+            "# Treiber\nDie connect Funktion im Treiber muss repariert werden, bevor wir morgen das neue System ausliefern."
+                .to_string(),
+        );
+
+        let outage_path = notes_dir.join(note1.save(notes_dir, Some("Outage")).unwrap());
+        note2.save(notes_dir, Some("Treiber")).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        // A different inflection matches the English note, but not the German one
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("connecting releases"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+        assert_eq!(notes[0].extract_title(), "Outage");
+
+        let (notes, total_count) = db.search_notes(&SearchQuery::new("connects")).await.unwrap();
+        assert_eq!(total_count, 1);
+        assert_eq!(notes[0].extract_title(), "Outage");
+
+        // Exact terms still match notes in other languages
+        let (notes, total_count) = db.search_notes(&SearchQuery::new("connect")).await.unwrap();
+        assert_eq!(total_count, 2);
+        assert_eq!(notes.len(), 2);
+
+        // Filter by language
+        let (notes, total_count) = db
+            .search_notes(&SearchQuery::new("connect lang:de"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+        assert_eq!(notes[0].extract_title(), "Treiber");
+
+        let (_, total_count) = db.search_notes(&SearchQuery::new("lang:EN")).await.unwrap();
+        assert_eq!(total_count, 1);
+
+        // A note that changes its language moves to the unstemmed index
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(
+            &outage_path,
+            "# Ausfall\nDie Datenbank connection wurde getrennt, während wir die neue Version ausgeliefert haben.",
+        )
+        .unwrap();
+        process_note_file(db.pool(), notes_dir, &outage_path)
+            .await
+            .unwrap();
+
+        let (_, total_count) = db.search_notes(&SearchQuery::new("connects")).await.unwrap();
+        assert_eq!(total_count, 0);
+
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("connection lang:de"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+    });
+}
+
+#[test]
+fn test_backup_to() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let note = Note::new(
+            Frontmatter::default(),
+            "# Backup\nThis note should be in the backup.".to_string(),
+        );
+        note.save(notes_dir, Some("Backup")).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+
+        let backup_path = temp_dir.path().join("backup.db");
+        db.backup_to(&backup_path).await.unwrap();
+
+        // The backup is a readable SQLite database with the indexed notes
+        let backup_pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", backup_path.display()))
+            .await
+            .unwrap();
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM notes")
+            .fetch_one(&backup_pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Existing files are not overwritten
+        let result = db.backup_to(&backup_path).await;
+        assert!(matches!(
+            result,
+            Err(NotelogError::DatabaseError(DatabaseError::Backup(_)))
+        ));
+    });
+}
 
-            // Create three test notes with different creation dates
-            // Note 1: Created 2025-05-01
-            let date1 = Local.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap();
-            let mut frontmatter1 = Frontmatter::new(date1, vec![]);
-            let tag1 = Tag::new("test").unwrap();
-            frontmatter1.add_tag(tag1.clone());
-            let content1 = "# Test Note 1\nThis is the first test note.";
-            let note1 = Note::new(frontmatter1, content1.to_string());
-
-            // Note 2: Created 2025-05-15
-            let date2 = Local.with_ymd_and_hms(2025, 5, 15, 12, 0, 0).unwrap();
-            let mut frontmatter2 = Frontmatter::new(date2, vec![]);
-            frontmatter2.add_tag(tag1.clone());
-            let content2 = "# Test Note 2\nThis is the second test note.";
-            let note2 = Note::new(frontmatter2, content2.to_string());
-
-            // Note 3: Created 2025-05-30
-            let date3 = Local.with_ymd_and_hms(2025, 5, 30, 12, 0, 0).unwrap();
-            let mut frontmatter3 = Frontmatter::new(date3, vec![]);
-            frontmatter3.add_tag(tag1);
-            let content3 = "# Test Note 3\nThis is the third test note.";
-            let note3 = Note::new(frontmatter3, content3.to_string());
-
-            // Save the notes to disk
-            let _note_path1 = note1.save(notes_dir, Some("Test Note 1")).unwrap();
-            let _note_path2 = note2.save(notes_dir, Some("Test Note 2")).unwrap();
-            let _note_path3 = note3.save(notes_dir, Some("Test Note 3")).unwrap();
-
-            // Initialize the database
-            let db = Database::initialize(notes_dir).await.unwrap();
-
-            // Run the indexing task
-            index_notes_with_channel(db.pool().clone(), notes_dir)
+#[test]
+fn test_daily_backup_and_pruning() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let backups_dir = notes_dir.join(".backups");
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        // Older daily backups and a manual backup
+        fs::create_dir_all(&backups_dir).unwrap();
+        for name in [
+            "daily-2025-01-01.db",
+            "daily-2025-01-02.db",
+            "daily-2025-01-03.db",
+            "notes-2024-12-31T12-00-00.db",
+        ] {
+            fs::write(backups_dir.join(name), "").unwrap();
+        }
+
+        // Only one backup is taken per day
+        let backup_path = create_daily_backup(db.pool(), &backups_dir, 2)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(backup_path.exists());
+        assert!(
+            create_daily_backup(db.pool(), &backups_dir, 2)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // The oldest daily backups were pruned, the manual backup was kept
+        let mut remaining: Vec<String> = fs::read_dir(&backups_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            vec![
+                "daily-2025-01-03.db".to_string(),
+                backup_path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                "notes-2024-12-31T12-00-00.db".to_string(),
+            ]
+        );
+
+        // Pruning again does nothing
+        assert!(prune_backups(&backups_dir, 2).unwrap().is_empty());
+    });
+}
+
+#[test]
+fn test_fts_integrity_check_and_rebuild() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mut frontmatter = Frontmatter::default();
+        frontmatter.add_tag(Tag::new("ops").unwrap()).unwrap();
+        let note1 = Note::new(
+            frontmatter,
+            "# Outage\nThe deploy failed and we rolled back the release.".to_string(),
+        );
+        let note2 = Note::new(
+            Frontmatter::default(),
+            "# Groceries\nBuy milk and bread.".to_string(),
+        );
+        note1.save(notes_dir, Some("Outage")).unwrap();
+        note2.save(notes_dir, Some("Groceries")).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        assert!(!db.fts_rebuilt());
+        index_notes_with_channel(db.pool().clone(), notes_dir)
+            .await
+            .unwrap();
+        assert!(db.check_fts_integrity().await.unwrap());
+
+        // A note that is missing from the fulltext index is not found
+        sqlx::query("DELETE FROM notes_fts WHERE rowid = (SELECT MIN(rowid) FROM notes_fts)")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM notes_fts_stemmed")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert!(!db.check_fts_integrity().await.unwrap());
+
+        db.rebuild_fts().await.unwrap();
+        assert!(db.check_fts_integrity().await.unwrap());
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("deploy +ops"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+
+        // Corrupt the index by deleting from a shadow table
+        sqlx::query("DELETE FROM notes_fts_content")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert!(!db.check_fts_integrity().await.unwrap());
+        drop(db);
+
+        // The index was checked when the database was opened, so opening it
+        // again right away skips the check
+        let db = Database::initialize(notes_dir).await.unwrap();
+        assert!(!db.fts_rebuilt());
+        assert!(!db.check_fts_integrity().await.unwrap());
+        sqlx::query("UPDATE fts_check SET checked = '2000-01-01T00:00:00Z'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        drop(db);
+
+        // Once the last check is old enough, opening the database repairs the index
+        let mut db = Database::initialize(notes_dir).await.unwrap();
+        assert!(db.fts_rebuilt());
+        assert!(!db.repair_fts().await.unwrap());
+        assert!(db.check_fts_integrity().await.unwrap());
+
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("deploy +ops"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+        let (_, total_count) = db.search_notes(&SearchQuery::new("milk")).await.unwrap();
+        assert_eq!(total_count, 1);
+    });
+}
+
+#[test]
+fn test_replaced_note_file_gets_new_row() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let old_note = Note::new(
+            Frontmatter::with_tags(vec![Tag::new("alpha").unwrap(), Tag::new("beta").unwrap()]),
+            "# Plan\nThe old plan.".to_string(),
+        );
+        let old_id = old_note.frontmatter().id().unwrap().as_str().to_string();
+        let filepath = notes_dir.join(old_note.save(notes_dir, Some("Plan")).unwrap());
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        process_note_file(db.pool(), notes_dir, &filepath)
+            .await
+            .unwrap();
+
+        // Another note, indexed afterwards, so that the replacement cannot reuse the row ID
+        let other_note = Note::new(
+            Frontmatter::default(),
+            "# Other\nSomething else.".to_string(),
+        );
+        let other_path = notes_dir.join(other_note.save(notes_dir, Some("Other")).unwrap());
+        process_note_file(db.pool(), notes_dir, &other_path)
+            .await
+            .unwrap();
+
+        let old_row =
+            sqlx::query_scalar::<_, i64>("SELECT id FROM notes WHERE content LIKE '%old plan%'")
+                .fetch_one(db.pool())
                 .await
                 .unwrap();
 
-            // Test 1: Search with no date filters (should return all 3 notes)
-            let (notes, total_count) = db.search_notes("+test", None, None, None).await.unwrap();
-            assert_eq!(notes.len(), 3);
-            assert_eq!(total_count, 3);
+        // Replace the file with a different note
+        let new_note = Note::new(
+            Frontmatter::with_tags(vec![Tag::new("beta").unwrap(), Tag::new("gamma").unwrap()]),
+            "# Plan\nThe new plan.".to_string(),
+        );
+        let new_id = new_note.frontmatter().id().unwrap().as_str().to_string();
+        assert_ne!(old_id, new_id);
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(&filepath, new_note.to_string()).unwrap();
+        process_note_file(db.pool(), notes_dir, &filepath)
+            .await
+            .unwrap();
+
+        // The note has a new row, and the old ID no longer resolves
+        let (new_row, count) = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT MAX(id), COUNT(*) FROM notes WHERE content LIKE '%plan%'",
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap();
+        assert_eq!(count, 1);
+        assert!(new_row > old_row);
+        assert!(db.fetch_note_by_id(&old_id).await.unwrap().is_none());
+        let note = db.fetch_note_by_id(&new_id).await.unwrap().unwrap();
+        assert!(note.content().contains("The new plan."));
+
+        // The tag usage counts were updated, and the unused tag was removed
+        let tag_counts = sqlx::query_as::<_, (String, i64)>(
+            "SELECT tag_name, usage_count FROM tags ORDER BY tag_name",
+        )
+        .fetch_all(db.pool())
+        .await
+        .unwrap();
+        assert_eq!(
+            tag_counts,
+            vec![("beta".to_string(), 1), ("gamma".to_string(), 1)]
+        );
+
+        let (_, total_count) = db.search_notes(&SearchQuery::new("+gamma")).await.unwrap();
+        assert_eq!(total_count, 1);
+    });
+}
+
+#[test]
+fn test_verify_finds_edits_that_kept_the_mtime() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mut paths = Vec::new();
+        for title in ["Zeppelin", "Airship"] {
+            let note = Note::new(Frontmatter::default(), format!("# {}\n\nThe plan.", title));
+            paths.push(note.save(notes_dir, None).unwrap());
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        // Edit the first note the way `git checkout` would, keeping its mtime
+        let path = notes_dir.join(&paths[0]);
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace("The plan.", "The revised plan.")).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        // Indexing does not notice the change
+        db.index_notes().await.unwrap();
+        let (_, total) = db.search_notes(&SearchQuery::new("revised")).await.unwrap();
+        assert_eq!(total, 0);
+
+        // A batch with only the other note finds nothing
+        let other_row: i64 = sqlx::query_scalar("SELECT id FROM notes WHERE filepath = ?")
+            .bind(paths[1].to_string_lossy())
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        let (stats, last_row) = verify_content_hashes(db.pool(), notes_dir, other_row - 1, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(stats.checked, 1);
+        assert_eq!(stats.reindexed, 0);
+        assert_eq!(last_row, Some(other_row));
+
+        // Verification does
+        let stats = db.verify_content_hashes().await.unwrap();
+        assert_eq!(stats.checked, 2);
+        assert_eq!(stats.reindexed, 1);
+        let (_, total) = db.search_notes(&SearchQuery::new("revised")).await.unwrap();
+        assert_eq!(total, 1);
+
+        // And the hash is up to date afterwards
+        assert_eq!(db.verify_content_hashes().await.unwrap().reindexed, 0);
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinked_notes_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let target = temp_dir.path().join("synced");
+    fs::create_dir(&target).unwrap();
+    let notes_dir = temp_dir.path().join("NoteLog");
+    std::os::unix::fs::symlink(&target, &notes_dir).unwrap();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let note = Note::new(Frontmatter::default(), "# Symlinked\n\nFirst".to_string());
+        let relative_path = note.save(&notes_dir, None).unwrap();
+
+        let db = Database::initialize(&notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        // File watchers report the paths with the symlink resolved
+        let resolved_path = target.canonicalize().unwrap().join(&relative_path);
+        fs::write(
+            &resolved_path,
+            fs::read_to_string(&resolved_path)
+                .unwrap()
+                .replace("First", "Second"),
+        )
+        .unwrap();
+        process_note_file(db.pool(), &notes_dir, &resolved_path)
+            .await
+            .unwrap();
+
+        // The note was updated in place instead of failing or being added again
+        let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
+        assert_eq!(filepaths, vec![relative_path.to_string_lossy().to_string()]);
+        let (_, total) = db.search_notes(&SearchQuery::new("second")).await.unwrap();
+        assert_eq!(total, 1);
+
+        // The same for a file that was just removed
+        fs::remove_file(&resolved_path).unwrap();
+        assert_eq!(
+            crate::utils::relative_to_notes_dir(&resolved_path, &notes_dir),
+            Some(relative_path)
+        );
+    });
+}
+
+#[test]
+fn test_check_notes_dir() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let note = Note::new(Frontmatter::default(), "# Valid\nA valid note.".to_string());
+        let valid_path = note.save(notes_dir, Some("Valid")).unwrap();
+        let month_dir = notes_dir.join(valid_path.parent().unwrap());
+
+        fs::write(
+            month_dir.join("2025-01-01T10-00 Broken.md"),
+            "---\ncreated: yesterday\n---\nBroken frontmatter",
+        )
+        .unwrap();
+        fs::write(
+            month_dir.join("2025-01-01T11-00 Huge.md"),
+            "x".repeat(crate::constants::MAX_FILE_SIZE_BYTES + 1),
+        )
+        .unwrap();
+        fs::write(month_dir.join("2025-01-01T12-00 Image.png"), "png").unwrap();
+        fs::write(notes_dir.join("README.md"), "# Readme").unwrap();
+
+        let statuses = |files: Vec<(String, FileStatus)>| {
+            files
+                .into_iter()
+                .map(|(path, status)| {
+                    let name = Path::new(&path)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string();
+                    (name, status)
+                })
+                .collect::<std::collections::HashMap<_, _>>()
+        };
+
+        // Without a database, all notes will be indexed
+        let indexed_mtimes = Database::read_indexed_mtimes(notes_dir).await.unwrap();
+        assert!(indexed_mtimes.is_empty());
+        assert!(!notes_dir.join(DB_FILENAME).exists());
+
+        let files = statuses(check_notes_dir(notes_dir, &indexed_mtimes).await.unwrap());
+        assert_eq!(files.len(), 5);
+        let valid_name = valid_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(files[&valid_name], FileStatus::WillIndex);
+        assert!(matches!(
+            files["2025-01-01T10-00 Broken.md"],
+            FileStatus::Invalid(_)
+        ));
+        assert_eq!(
+            files["2025-01-01T11-00 Huge.md"],
+            FileStatus::Skipped(SkipReason::TooLarge)
+        );
+        assert_eq!(
+            files["2025-01-01T12-00 Image.png"],
+            FileStatus::Skipped(SkipReason::WrongExtension)
+        );
+        assert_eq!(
+            files["README.md"],
+            FileStatus::Skipped(SkipReason::NotANoteFilename)
+        );
+
+        // After indexing, the valid note is unchanged
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let indexed_mtimes = Database::read_indexed_mtimes(notes_dir).await.unwrap();
+        assert_eq!(indexed_mtimes.len(), 1);
+
+        let files = statuses(check_notes_dir(notes_dir, &indexed_mtimes).await.unwrap());
+        assert_eq!(files[&valid_name], FileStatus::Unchanged);
+        assert!(matches!(
+            files["2025-01-01T10-00 Broken.md"],
+            FileStatus::Invalid(_)
+        ));
+    });
+}
 
-            // Just verify that we have 3 notes in the results
-            assert_eq!(notes.len(), 3, "Should have 3 notes in the results");
+#[test]
+fn test_private_note_is_removed_from_index() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let note = Note::new(
+            Frontmatter::default(),
+            "# Scratchpad\nSome private thoughts.".to_string(),
+        );
+        let id = note.frontmatter().id().unwrap().as_str().to_string();
+        let filepath = notes_dir.join(note.save(notes_dir, Some("Scratchpad")).unwrap());
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("thoughts"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 1);
+
+        // Mark the note as private
+        let content = fs::read_to_string(&filepath).unwrap();
+        let content = content.replacen("\ncreated:", "\nprivate: true\ncreated:", 1);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(&filepath, content).unwrap();
+        process_note_file(db.pool(), notes_dir, &filepath)
+            .await
+            .unwrap();
+
+        // The note is no longer found, but the file is still there
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("thoughts"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 0);
+        assert!(db.fetch_note_by_id(&id).await.unwrap().is_none());
+        assert!(filepath.exists());
+
+        // Reindexing does not add it again
+        db.index_notes().await.unwrap();
+        let (_, total_count) = db
+            .search_notes(&SearchQuery::new("thoughts"))
+            .await
+            .unwrap();
+        assert_eq!(total_count, 0);
+    });
+}
 
-            // Test 2: Search for notes before 2025-05-20
-            let before_date = Local.with_ymd_and_hms(2025, 5, 20, 0, 0, 0).unwrap();
-            let (notes, total_count) = db
-                .search_notes("+test", Some(before_date), None, None)
+#[test]
+fn test_list_notes_newest_first() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for (day, title) in [(1, "Oldest"), (3, "Newest"), (2, "Middle")] {
+            let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+            let note = Note::new(
+                Frontmatter::new(created, vec![]),
+                format!("# {}\nContent", title),
+            );
+            note.save(notes_dir, None).unwrap();
+        }
+
+        // Times with different UTC offsets are compared as times, not as text:
+        // 20:00 UTC comes before 22:00 UTC, although its text sorts after it
+        for (id, created, title) in [
+            (
+                "abcd000000000001",
+                "2025-05-04T01:00:00+05:00",
+                "Earlier evening",
+            ),
+            (
+                "abcd000000000002",
+                "2025-05-03T22:00:00+00:00",
+                "Later evening",
+            ),
+        ] {
+            let yaml = format!("id: {}\ncreated: {}", id, created);
+            let frontmatter = yaml.parse::<Frontmatter>().unwrap();
+            let note = Note::new(frontmatter, format!("# {}\nContent", title));
+            note.save(notes_dir, None).unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let mut listed = Vec::new();
+        db.list_notes(|short_id, note| {
+            listed.push((short_id.to_string(), note));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let titles: Vec<String> = listed
+            .iter()
+            .map(|(_, note)| note.extract_title())
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Later evening",
+                "Earlier evening",
+                "Newest",
+                "Middle",
+                "Oldest"
+            ]
+        );
+        assert_eq!(listed[0].0, "abcd000000000002");
+
+        // The short IDs are the same as the ones the MCP server reports
+        for (short_id, note) in &listed {
+            let id = note.frontmatter().id().unwrap();
+            assert_eq!(
+                short_id,
+                &db.find_shortest_unique_id_prefix(id).await.unwrap()
+            );
+        }
+    });
+}
+
+#[test]
+fn test_due_notes() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for (title, due) in [
+            ("Later", Some(20)),
+            ("No due date", None),
+            ("Sooner", Some(10)),
+        ] {
+            let mut note = Note::new(Frontmatter::default(), format!("# {}", title));
+            note.frontmatter_mut()
+                .set_due(due.and_then(|day| NaiveDate::from_ymd_opt(2025, 5, day)));
+            note.save(notes_dir, None).unwrap();
+        }
+
+        // A due date typed by hand that is not a date
+        let yaml = "created: 2025-05-01T12:00:00+00:00\ndue: next week";
+        let note = Note::new(
+            yaml.parse::<Frontmatter>().unwrap(),
+            "# Zeppelin\nBook the flight".to_string(),
+        );
+        note.save(notes_dir, None).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        // The note is indexed anyway, as a note that is not due
+        let (found, _) = db
+            .search_notes(&SearchQuery::new("zeppelin"))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        let titles = |notes: Vec<Note>| -> Vec<String> {
+            notes.iter().map(|note| note.extract_title()).collect()
+        };
+
+        assert_eq!(
+            titles(db.due_notes(None).await.unwrap()),
+            vec!["Sooner", "Later"]
+        );
+        assert_eq!(
+            titles(
+                db.due_notes(NaiveDate::from_ymd_opt(2025, 5, 10))
+                    .await
+                    .unwrap()
+            ),
+            vec!["Sooner"]
+        );
+        assert!(
+            db.due_notes(NaiveDate::from_ymd_opt(2025, 5, 9))
                 .await
-                .unwrap();
-            assert_eq!(notes.len(), 2);
-            assert_eq!(total_count, 2);
+                .unwrap()
+                .is_empty()
+        );
+    });
+}
 
-            // Just verify that we have 2 notes in the results
-            assert_eq!(notes.len(), 2, "Should have 2 notes in the results");
+#[test]
+fn test_notes_with_any_tag() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for (title, day, tag) in [
+            ("Newer todo", 20, "todo"),
+            ("Finished", 15, "done"),
+            ("Older todo", 10, "todo"),
+            ("Unrelated", 5, "misc"),
+        ] {
+            let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+            let frontmatter = Frontmatter::new(created, vec![Tag::new(tag).unwrap()]);
+            let note = Note::new(frontmatter, format!("# {}", title));
+            note.save(notes_dir, None).unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let titles = |notes: Vec<Note>| -> Vec<String> {
+            notes.iter().map(|note| note.extract_title()).collect()
+        };
+
+        let todo = Tag::new("todo").unwrap();
+        let done = Tag::new("done").unwrap();
+
+        // Oldest first
+        assert_eq!(
+            titles(
+                db.notes_with_any_tag(std::slice::from_ref(&todo))
+                    .await
+                    .unwrap()
+            ),
+            vec!["Older todo", "Newer todo"]
+        );
+        assert_eq!(
+            titles(db.notes_with_any_tag(&[todo, done]).await.unwrap()),
+            vec!["Older todo", "Finished", "Newer todo"]
+        );
+        assert!(db.notes_with_any_tag(&[]).await.unwrap().is_empty());
+    });
+}
 
-            // Test 3: Search for notes after 2025-05-10
-            let after_date = Local.with_ymd_and_hms(2025, 5, 10, 0, 0, 0).unwrap();
-            let (notes, total_count) = db
-                .search_notes("+test", None, Some(after_date), None)
+#[test]
+fn test_recent_notes() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for (title, day, tag) in [
+            ("Second", 10, "work"),
+            ("Newest", 20, "home"),
+            ("Oldest", 5, "work"),
+        ] {
+            let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+            let frontmatter = Frontmatter::new(created, vec![Tag::new(tag).unwrap()]);
+            let note = Note::new(frontmatter, format!("# {}", title));
+            note.save(notes_dir, None).unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let titles = |notes: Vec<Note>| -> Vec<String> {
+            notes.iter().map(|note| note.extract_title()).collect()
+        };
+
+        // Newest first, by creation time rather than file order
+        assert_eq!(
+            titles(db.recent_notes(2, None).await.unwrap()),
+            vec!["Newest", "Second"]
+        );
+        assert_eq!(
+            titles(
+                db.recent_notes(5, Some(&Tag::new("work").unwrap()))
+                    .await
+                    .unwrap()
+            ),
+            vec!["Second", "Oldest"]
+        );
+        assert!(
+            db.recent_notes(5, Some(&Tag::new("unused").unwrap()))
                 .await
-                .unwrap();
-            assert_eq!(notes.len(), 2);
-            assert_eq!(total_count, 2);
+                .unwrap()
+                .is_empty()
+        );
+    });
+}
 
-            // Just verify that we have 2 notes in the results
-            assert_eq!(notes.len(), 2, "Should have 2 notes in the results");
+#[test]
+fn test_tag_timeline() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for (title, month, day, tags) in [
+            ("Deploy one", 1, 10, vec!["deploy", "ops"]),
+            ("Deploy two", 1, 20, vec!["deploy", "ops", "work"]),
+            ("Deploy three", 4, 5, vec!["deploy", "work"]),
+            ("Deploy four", 4, 6, vec!["deploy", "ops"]),
+            ("Unrelated", 2, 1, vec!["ops"]),
+        ] {
+            let created = Local.with_ymd_and_hms(2025, month, day, 12, 0, 0).unwrap();
+            let tags = tags.into_iter().map(|tag| Tag::new(tag).unwrap()).collect();
+            let note = Note::new(Frontmatter::new(created, tags), format!("# {}", title));
+            note.save(notes_dir, None).unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let timeline = db.tag_timeline(&Tag::new("deploy").unwrap()).await.unwrap();
+        assert_eq!(timeline.tag, "deploy");
+        assert_eq!(timeline.total(), 4);
+
+        // The months without notes are included
+        let months: Vec<(&str, usize)> = timeline
+            .months
+            .iter()
+            .map(|month| (month.month.as_str(), month.count))
+            .collect();
+        assert_eq!(
+            months,
+            vec![
+                ("2025-01", 2),
+                ("2025-02", 0),
+                ("2025-03", 0),
+                ("2025-04", 2)
+            ]
+        );
+
+        assert_eq!(
+            timeline.first,
+            Some(Local.with_ymd_and_hms(2025, 1, 10, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            timeline.last,
+            Some(Local.with_ymd_and_hms(2025, 4, 6, 12, 0, 0).unwrap())
+        );
+
+        let co_tags: Vec<(&str, usize)> = timeline
+            .co_tags
+            .iter()
+            .map(|tag| (tag.tag.as_str(), tag.count))
+            .collect();
+        assert_eq!(co_tags, vec![("ops", 3), ("work", 2)]);
+
+        // An unused tag has an empty timeline
+        let timeline = db.tag_timeline(&Tag::new("unused").unwrap()).await.unwrap();
+        assert_eq!(timeline.total(), 0);
+        assert!(timeline.months.is_empty());
+        assert!(timeline.first.is_none() && timeline.co_tags.is_empty());
+
+        // All tags with their counts, most used first
+        let counts: Vec<(String, usize)> = db
+            .tag_counts()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tag| (tag.tag, tag.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("deploy".to_string(), 4),
+                ("ops".to_string(), 4),
+                ("work".to_string(), 2)
+            ]
+        );
+
+        let paths = db
+            .filepaths_with_tag(&Tag::new("work").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|path| path.starts_with("2025/")));
+    });
+}
+
+#[test]
+fn test_open_read_only() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // Without a database, there is nothing to open
+        assert!(Database::open_read_only(notes_dir).await.is_err());
 
-            // Test 4: Search with both before and after filters
-            let before_date = Local.with_ymd_and_hms(2025, 5, 25, 0, 0, 0).unwrap();
-            let after_date = Local.with_ymd_and_hms(2025, 5, 10, 0, 0, 0).unwrap();
-            let (notes, total_count) = db
-                .search_notes("+test", Some(before_date), Some(after_date), None)
+        let note = Note::new(Frontmatter::default(), "# Snapshot\n\nRead me".to_string());
+        note.save(notes_dir, None).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+        db.pool().close().await;
+
+        let db = Database::open_read_only(notes_dir).await.unwrap();
+        let (notes, _) = db.search_notes(&SearchQuery::new("read")).await.unwrap();
+        assert_eq!(notes.len(), 1);
+
+        // Writes are rejected
+        assert!(
+            sqlx::query("DELETE FROM notes")
+                .execute(db.pool())
                 .await
-                .unwrap();
-            assert_eq!(notes.len(), 1);
-            assert_eq!(total_count, 1);
+                .is_err()
+        );
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn test_open_for_query_in_read_only_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let note = Note::new(Frontmatter::default(), "# Snapshot\n\nRead me".to_string());
+        note.save(notes_dir, None).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+        db.pool().close().await;
+
+        fs::set_permissions(notes_dir, fs::Permissions::from_mode(0o555)).unwrap();
+        let result = Database::open_for_query(notes_dir).await;
+        fs::set_permissions(notes_dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let (notes, _) = result
+            .unwrap()
+            .search_notes(&SearchQuery::new("read"))
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+    });
+}
 
-            // Just verify that we have 1 note in the results
-            assert_eq!(notes.len(), 1, "Should have 1 note in the results");
+#[test]
+fn test_index_deeply_nested_notes() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        // 1000 levels of directories, with a note at the bottom and a stray file halfway
+        let mut dir = notes_dir.to_path_buf();
+        for level in 0..1000 {
+            dir.push("d");
+            if level == 500 {
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("README.md"), "Not a note").unwrap();
+            }
+        }
+        fs::create_dir_all(&dir).unwrap();
+
+        let note = Note::new(
+            Frontmatter::with_tags(vec![Tag::new("deep").unwrap()]),
+            "# Deep\nAt the bottom.".to_string(),
+        );
+        fs::write(dir.join("2025-01-01T00-00 Deep.md"), note.to_string()).unwrap();
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        let stats = db.index_notes().await.unwrap();
+        assert_eq!(stats.files_seen, 2);
+        assert_eq!(stats.files_sent, 1);
+
+        let (notes, total) = db.search_notes(&SearchQuery::new("+deep")).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(notes[0].extract_title(), "Deep");
+    });
+}
 
-            // Test 5: Non-overlapping date range (before < after)
-            let before_date = Local.with_ymd_and_hms(2025, 5, 5, 0, 0, 0).unwrap();
-            let after_date = Local.with_ymd_and_hms(2025, 5, 10, 0, 0, 0).unwrap();
-            let (notes, total_count) = db
-                .search_notes("+test", Some(before_date), Some(after_date), None)
+#[test]
+fn test_monitor_resumes_when_notes_dir_returns() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path().join("notes");
+    let away_dir = temp_dir.path().join("unmounted");
+    fs::create_dir(&notes_dir).unwrap();
+    let rt = Runtime::new().unwrap();
+
+    let note = |day: u32| {
+        let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+        Note::new(Frontmatter::new(created, vec![]), format!("# Note {}", day))
+    };
+
+    rt.block_on(async {
+        note(1).save(&notes_dir, None).unwrap();
+        let db = Database::initialize(&notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let available = Arc::new(AtomicBool::new(false));
+        let mut monitor = Monitor::start(
+            db.pool().clone(),
+            &notes_dir,
+            available.clone(),
+            MetricsRecorder::default(),
+        )
+        .unwrap();
+        assert!(available.load(Ordering::Relaxed));
+
+        // The directory disappears, and a note is added while it is away
+        fs::rename(&notes_dir, &away_dir).unwrap();
+        monitor.check_availability().await;
+        assert!(!available.load(Ordering::Relaxed));
+
+        note(2).save(&away_dir, None).unwrap();
+        fs::rename(&away_dir, &notes_dir).unwrap();
+
+        // Its return is caught up on by indexing
+        monitor.check_availability().await;
+        assert!(available.load(Ordering::Relaxed));
+        assert_eq!(db.note_count().await.unwrap(), 2);
+
+        // The directory is watched again
+        note(3).save(&notes_dir, None).unwrap();
+        for _ in 0..100 {
+            if db.note_count().await.unwrap() == 3 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(db.note_count().await.unwrap(), 3);
+    });
+}
+
+#[test]
+fn test_dropped_file_events_trigger_a_resync() {
+    use notify::event::CreateKind;
+    use notify::{Event, EventHandler, EventKind};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        let (sender, receiver) = tokio::sync::mpsc::channel(2);
+        let resync_needed = Arc::new(AtomicBool::new(false));
+        let mut handler = FileMonitoringHandler::new(sender, resync_needed.clone());
+
+        // Flood the channel with more events than it can hold
+        for day in 1..=5 {
+            let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+            let note = Note::new(Frontmatter::new(created, vec![]), format!("# Note {}", day));
+            let path = notes_dir.join(note.save(notes_dir, None).unwrap());
+            handler.handle_event(Ok(
+                Event::new(EventKind::Create(CreateKind::File)).add_path(path)
+            ));
+        }
+        assert!(resync_needed.load(Ordering::Relaxed));
+
+        // Closing the channel lets the processing finish
+        drop(handler);
+        process_events(
+            receiver,
+            resync_needed.clone(),
+            db.pool().clone(),
+            notes_dir.to_path_buf(),
+            MetricsRecorder::default(),
+        )
+        .await;
+        assert!(!resync_needed.load(Ordering::Relaxed));
+
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM notes")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(count, 5);
+    });
+}
+
+/// Write a valid note file into each reserved directory and one ignored by
+/// `.notelogignore`, returning their paths
+fn write_ignored_notes(notes_dir: &Path) -> Vec<std::path::PathBuf> {
+    fs::write(notes_dir.join(IGNORE_FILENAME), "# Not notes\nscratch/\n").unwrap();
+
+    let dirs = RESERVED_DIRS
+        .iter()
+        .map(|dir| notes_dir.join(dir).join("2025/05_May"))
+        .chain([notes_dir.join("2025/scratch")]);
+    dirs.map(|dir| {
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("2025-05-01T12-00 Copy.md");
+        fs::write(
+            &path,
+            "---\ncreated: 2025-05-01T12:00:00+00:00\ntags:\n- copy\n---\n\n# Copy\n",
+        )
+        .unwrap();
+        path
+    })
+    .collect()
+}
+
+#[test]
+fn test_indexing_skips_reserved_and_ignored_dirs() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await.unwrap();
+        write_ignored_notes(notes_dir);
+
+        let created = Local.with_ymd_and_hms(2025, 5, 2, 12, 0, 0).unwrap();
+        let tags = vec![Tag::new("copy").unwrap()];
+        Note::new(Frontmatter::new(created, tags), "# Original".to_string())
+            .save(notes_dir, None)
+            .unwrap();
+
+        let stats = db.index_notes().await.unwrap();
+        assert_eq!(stats.files_sent, 1);
+        let (notes, total) = db.search_notes(&SearchQuery::new("+copy")).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(notes[0].extract_title(), "Original");
+    });
+}
+
+#[test]
+fn test_file_events_in_reserved_and_ignored_dirs_are_skipped() {
+    use notify::event::CreateKind;
+    use notify::{Event, EventHandler, EventKind};
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await.unwrap();
+        let paths = write_ignored_notes(notes_dir);
+
+        let (sender, receiver) = tokio::sync::mpsc::channel(16);
+        let resync_needed = Arc::new(AtomicBool::new(false));
+        let mut handler = FileMonitoringHandler::new(sender, resync_needed.clone());
+        for path in paths {
+            handler.handle_event(Ok(
+                Event::new(EventKind::Create(CreateKind::File)).add_path(path)
+            ));
+        }
+
+        drop(handler);
+        process_events(
+            receiver,
+            resync_needed,
+            db.pool().clone(),
+            notes_dir.to_path_buf(),
+            MetricsRecorder::default(),
+        )
+        .await;
+        assert_eq!(db.note_count().await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn test_notes_that_fail_to_parse_are_recorded() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        let month_dir = notes_dir.join("2025/05_May");
+        fs::create_dir_all(&month_dir).unwrap();
+        let path = month_dir.join("2025-05-02T12-00 Broken.md");
+        fs::write(&path, "---\ncreated: [not a date\n---\n\n# Broken\n").unwrap();
+
+        // The first attempt reports the error and records it
+        assert!(
+            process_note_file(db.pool(), notes_dir, &path)
                 .await
-                .unwrap();
-            assert_eq!(notes.len(), 0);
-            assert_eq!(total_count, 0);
-        });
-    }
+                .is_err()
+        );
+        let failed = db.failed_files().await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].filepath, "2025/05_May/2025-05-02T12-00 Broken.md");
+
+        // Until the file changes, it is skipped
+        assert!(process_note_file(db.pool(), notes_dir, &path).await.is_ok());
+        assert_eq!(db.note_count().await.unwrap(), 0);
+
+        // Once it is fixed, it is indexed and no longer reported
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(
+            &path,
+            "---\ncreated: 2025-05-02T12:00:00+00:00\n---\n\n# Fixed\n",
+        )
+        .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime + std::time::Duration::from_secs(1))
+            .unwrap();
+        process_note_file(db.pool(), notes_dir, &path)
+            .await
+            .unwrap();
+        assert_eq!(db.note_count().await.unwrap(), 1);
+        assert!(db.failed_files().await.unwrap().is_empty());
+
+        // Broken files that are deleted are forgotten as well
+        let other = month_dir.join("2025-05-03T12-00 Broken too.md");
+        fs::write(&other, "---\ncreated: [\n---\n").unwrap();
+        db.index_notes().await.unwrap();
+        assert_eq!(db.failed_files().await.unwrap().len(), 1);
+        fs::remove_file(&other).unwrap();
+        db.index_notes().await.unwrap();
+        assert!(db.failed_files().await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn test_notes_with_invalid_utf8_are_indexed_and_reported() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await.unwrap();
+
+        let month_dir = notes_dir.join("2025/05_May");
+        fs::create_dir_all(&month_dir).unwrap();
+        let path = month_dir.join("2025-05-02T12-00 Latin-1.md");
+        let mut bytes = b"---\ncreated: 2025-05-02T12:00:00+00:00\n---\n\n# Caf".to_vec();
+        let offset = bytes.len();
+        bytes.extend_from_slice(b"\xe9 menu\n\nCroissants and espresso\n");
+        fs::write(&path, &bytes).unwrap();
+
+        // The rest of the note is searchable
+        process_note_file(db.pool(), notes_dir, &path)
+            .await
+            .unwrap();
+        let (notes, _) = db
+            .search_notes(&SearchQuery::new("croissants"))
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].extract_title(), "Caf\u{fffd} menu");
+
+        // The file is reported with the position of the invalid byte
+        let failed = db.failed_files().await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(
+            failed[0].filepath,
+            "2025/05_May/2025-05-02T12-00 Latin-1.md"
+        );
+        assert!(
+            failed[0].error.contains(&format!(
+                "2025/05_May/2025-05-02T12-00 Latin-1.md contains invalid UTF-8 at byte {}",
+                offset
+            )),
+            "{}",
+            failed[0].error
+        );
+        assert!(failed[0].error.contains("[e9]"), "{}", failed[0].error);
+
+        // Indexing again keeps both the note and the report
+        db.index_notes().await.unwrap();
+        assert_eq!(db.note_count().await.unwrap(), 1);
+        assert_eq!(db.failed_files().await.unwrap().len(), 1);
+    });
+}
+
+#[test]
+fn test_database_maintenance() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        for i in 0..50 {
+            let created = Local.with_ymd_and_hms(2025, 5, 1, 12, i, 0).unwrap();
+            let note = Note::new(
+                Frontmatter::new(created, vec![Tag::new("bulk").unwrap()]),
+                format!("# Note {}\n\n{}", i, "Some filler text. ".repeat(200)),
+            );
+            note.save(notes_dir, None).unwrap();
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        // Deleting the notes leaves free pages behind
+        let filepaths = get_all_note_filepaths(db.pool()).await.unwrap();
+        delete_notes_by_filepaths(db.pool(), &filepaths)
+            .await
+            .unwrap();
+
+        let stats = db.maintenance().await.unwrap();
+        assert!(stats.size_before > 0);
+        assert!(stats.size_after > 0);
+        assert!(stats.size_after < stats.size_before);
+        assert_eq!(db.size().unwrap(), stats.size_after);
+
+        // The database still works afterwards
+        db.optimize_queries().await.unwrap();
+        db.index_notes().await.unwrap();
+        assert!(db.check_fts_integrity().await.unwrap());
+    });
+}
+
+#[test]
+fn test_maintenance_lock() {
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(temp_dir.path()).await.unwrap();
+        let lock = db.lock_maintenance(false).await.unwrap();
+
+        // A second holder is turned away
+        assert!(matches!(
+            db.lock_maintenance(false).await,
+            Err(NotelogError::DatabaseError(
+                DatabaseError::MaintenanceLocked(_)
+            ))
+        ));
+
+        // Background work, indexing and waiting holders wait for the release
+        let pool = db.pool().clone();
+        let background = tokio::spawn(async move { wait_for_maintenance(&pool).await });
+        let indexer = db.clone();
+        let indexing = tokio::spawn(async move { indexer.index_notes().await });
+        let pool = db.pool().clone();
+        let waiting = tokio::spawn(async move { acquire_maintenance_lock(&pool, true).await });
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!background.is_finished());
+        assert!(!indexing.is_finished());
+        assert!(!waiting.is_finished());
+
+        // The waiting holder may get the lock before the others see the
+        // release, so it has to let go before they can go on
+        lock.release().await.unwrap();
+        let lock = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        lock.release().await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), background)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), indexing)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        db.lock_maintenance(false)
+            .await
+            .unwrap()
+            .release()
+            .await
+            .unwrap();
+    });
+}
+
+#[test]
+fn test_prune_expired_notes() {
+    // Create a temporary directory for testing
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // Create a tokio runtime for testing
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let now = Local.with_ymd_and_hms(2025, 6, 10, 12, 0, 0).unwrap();
+        let mut paths = Vec::new();
+        for (title, day, tags) in [
+            ("Old scratch", 1, vec!["scratch"]),
+            ("New scratch", 20, vec!["scratch"]),
+            ("Old scratch project", 2, vec!["scratch", "project"]),
+            ("Old project", 3, vec!["project"]),
+            ("Old scratch tmp", 4, vec!["scratch", "tmp"]),
+            ("Changed scratch", 5, vec!["scratch"]),
+        ] {
+            let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+            let tags = tags.iter().map(|tag| Tag::new(tag).unwrap()).collect();
+            let note = Note::new(Frontmatter::new(created, tags), format!("# {}", title));
+            paths.push(note.save(notes_dir, None).unwrap());
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let policy = RetentionPolicy::new([
+            (Tag::new("scratch").unwrap(), NonZeroU32::new(30).unwrap()),
+            (Tag::new("tmp").unwrap(), NonZeroU32::new(7).unwrap()),
+        ]);
+
+        // Notes with a tag that does not expire are kept
+        let expired = db.expired_notes(&policy, now).await.unwrap();
+        let expired_paths: Vec<&str> = expired.iter().map(|note| note.filepath.as_str()).collect();
+        let path = |i: usize| paths[i].to_string_lossy().to_string();
+        assert_eq!(expired_paths, vec![path(0), path(4), path(5)]);
+
+        // A note that lost its expiring tag since it was indexed is kept
+        let changed = notes_dir.join(&paths[5]);
+        let text = fs::read_to_string(&changed).unwrap();
+        fs::write(&changed, text.replace("- scratch", "- keep")).unwrap();
+
+        let pruned = db.prune_notes(&policy, now, &expired).await.unwrap();
+        assert_eq!(pruned, vec![path(0), path(4)]);
+        assert!(!notes_dir.join(&paths[0]).exists());
+        assert!(!notes_dir.join(&paths[4]).exists());
+        assert!(changed.exists());
+        assert_eq!(db.note_count().await.unwrap(), 4);
+
+        // Nothing expires without expiring tags
+        assert!(
+            db.expired_notes(&RetentionPolicy::default(), now)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    });
+}
+
+#[test]
+fn test_prune_continues_after_a_failed_deletion() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let now = Local.with_ymd_and_hms(2025, 6, 10, 12, 0, 0).unwrap();
+        let mut paths = Vec::new();
+        for (title, day) in [("Unreadable scratch", 1), ("Old scratch", 2)] {
+            let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+            let tags = vec![Tag::new("scratch").unwrap()];
+            let note = Note::new(Frontmatter::new(created, tags), format!("# {}", title));
+            paths.push(note.save(notes_dir, None).unwrap());
+        }
+
+        let db = Database::initialize(notes_dir).await.unwrap();
+        db.index_notes().await.unwrap();
+
+        let policy =
+            RetentionPolicy::new([(Tag::new("scratch").unwrap(), NonZeroU32::new(30).unwrap())]);
+        let expired = db.expired_notes(&policy, now).await.unwrap();
+        assert_eq!(expired.len(), 2);
+
+        // The first note can no longer be read
+        let unreadable = notes_dir.join(&paths[0]);
+        fs::remove_file(&unreadable).unwrap();
+        fs::create_dir(&unreadable).unwrap();
+
+        // The second note is deleted anyway, and loses its row
+        assert!(db.prune_notes(&policy, now, &expired).await.is_err());
+        assert!(!notes_dir.join(&paths[1]).exists());
+        assert_eq!(db.note_count().await.unwrap(), 1);
+    });
+}
+
+#[test]
+fn test_indexing_failure_is_recorded() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let db = Database::initialize(notes_dir).await.unwrap();
+        assert!(db.wait_for_indexing(Duration::ZERO).await.is_ok());
+
+        db.start_indexing_task().await.unwrap();
+        db.wait_for_indexing(Duration::from_secs(10)).await.unwrap();
+        assert_eq!(db.indexing_failure(), None);
+
+        // A broken schema makes the indexing fail right away
+        sqlx::query("ALTER TABLE notes RENAME TO broken_notes")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        db.start_indexing_task().await.unwrap();
+        let err = db
+            .wait_for_indexing(Duration::from_secs(10))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            NotelogError::DatabaseError(DatabaseError::Indexing(_))
+        ));
+        assert!(
+            db.indexing_failure()
+                .unwrap()
+                .contains("no such table: notes")
+        );
+    });
 }
@@ -0,0 +1,117 @@
+//! The use of a single tag over time, for `Database::tag_timeline`
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// The number of co-occurring tags that a timeline lists
+pub const TIMELINE_CO_TAGS: usize = 10;
+
+/// How often a tag was used, month by month
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TagTimeline {
+    /// The tag, without the leading `+`
+    pub tag: String,
+    /// The number of notes created in each month, oldest first, from the
+    /// month of the first to the month of the last use (including empty months)
+    pub months: Vec<MonthCount>,
+    /// When the oldest note with the tag was created
+    pub first: Option<DateTime<Local>>,
+    /// When the newest note with the tag was created
+    pub last: Option<DateTime<Local>>,
+    /// The tags used most often together with this one, most frequent first
+    pub co_tags: Vec<TagCount>,
+}
+
+impl TagTimeline {
+    /// The number of notes with the tag
+    pub fn total(&self) -> usize {
+        self.months.iter().map(|month| month.count).sum()
+    }
+}
+
+/// The number of notes with a tag that were created in a month
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MonthCount {
+    /// The month as `YYYY-MM`, in local time
+    pub month: String,
+    /// The number of notes
+    pub count: usize,
+}
+
+/// The number of notes with a tag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagCount {
+    /// The tag, without the leading `+`
+    pub tag: String,
+    /// The number of notes
+    pub count: usize,
+}
+
+/// Fill in the months without notes between the given `YYYY-MM` months
+///
+/// The counts must be sorted by month; months that cannot be parsed are kept
+/// as they are.
+pub(super) fn fill_months(counts: Vec<(String, i64)>) -> Vec<MonthCount> {
+    let parse = |month: &str| -> Option<(i32, u32)> {
+        let (year, month) = month.split_once('-')?;
+        Some((year.parse().ok()?, month.parse().ok()?))
+    };
+
+    let mut months: Vec<MonthCount> = Vec::with_capacity(counts.len());
+    for (month, count) in counts {
+        if let (Some(previous), Some((year, number))) = (
+            months.last().and_then(|last| parse(&last.month)),
+            parse(&month),
+        ) {
+            let (mut y, mut m) = previous;
+            loop {
+                (y, m) = if m == 12 { (y + 1, 1) } else { (y, m + 1) };
+                if (y, m) >= (year, number) {
+                    break;
+                }
+                months.push(MonthCount {
+                    month: format!("{:04}-{:02}", y, m),
+                    count: 0,
+                });
+            }
+        }
+
+        months.push(MonthCount {
+            month,
+            count: count.max(0) as usize,
+        });
+    }
+
+    months
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_months() {
+        let months = fill_months(vec![
+            ("2024-11".to_string(), 2),
+            ("2025-02".to_string(), 1),
+            ("2025-03".to_string(), 4),
+        ]);
+
+        let months: Vec<(&str, usize)> = months
+            .iter()
+            .map(|month| (month.month.as_str(), month.count))
+            .collect();
+        assert_eq!(
+            months,
+            vec![
+                ("2024-11", 2),
+                ("2024-12", 0),
+                ("2025-01", 0),
+                ("2025-02", 1),
+                ("2025-03", 4),
+            ]
+        );
+
+        assert!(fill_months(Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Finding notes whose file changed without a new modification time
+//!
+//! Indexing skips files whose modification time matches the one stored in the
+//! database, so edits by tools that keep the modification time (e.g. `git
+//! checkout` or some sync clients) would never reach the index. Verification
+//! compares the hash of each file with the stored one instead.
+
+use sqlx::{Pool, Sqlite};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs;
+
+use crate::db::indexing::{content_hash, process_note_file};
+use crate::db::lock::wait_for_maintenance;
+use crate::db::metrics::{MetricsRecorder, Operation};
+use crate::error::{DatabaseError, Result};
+
+/// How often the background verification checks the next batch of notes
+const VERIFY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How many notes the background verification checks at a time
+const VERIFY_BATCH_SIZE: i64 = 200;
+
+/// The result of verifying the indexed notes against their files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyStats {
+    /// Notes whose file was read and hashed
+    pub checked: usize,
+    /// Notes that were indexed again because their file had changed
+    pub reindexed: usize,
+}
+
+/// Index the notes again whose file no longer matches the stored hash
+///
+/// The notes are checked in the order of their rows, starting after the row
+/// `after_row`; with a `limit`, at most that many are checked. Files that no
+/// longer exist are left to indexing, which removes their rows.
+///
+/// # Returns
+///
+/// The statistics and the last row that was checked, if any.
+pub async fn verify_content_hashes(
+    pool: &Pool<Sqlite>,
+    notes_dir: &Path,
+    after_row: i64,
+    limit: Option<i64>,
+) -> Result<(VerifyStats, Option<i64>)> {
+    verify_content_hashes_with_metrics(
+        pool,
+        notes_dir,
+        after_row,
+        limit,
+        &MetricsRecorder::default(),
+    )
+    .await
+}
+
+/// Works like `verify_content_hashes`, and records the durations of
+/// processing the changed files in `metrics`
+pub async fn verify_content_hashes_with_metrics(
+    pool: &Pool<Sqlite>,
+    notes_dir: &Path,
+    after_row: i64,
+    limit: Option<i64>,
+    metrics: &MetricsRecorder,
+) -> Result<(VerifyStats, Option<i64>)> {
+    let rows = sqlx::query_as::<_, (i64, String, Option<String>)>(
+        r#"
+        SELECT id, filepath, content_hash
+        FROM notes
+        WHERE id > ?
+        ORDER BY id
+        LIMIT ?
+        "#,
+    )
+    .bind(after_row)
+    .bind(limit.unwrap_or(-1))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    let mut stats = VerifyStats::default();
+    for (_, filepath, stored_hash) in &rows {
+        let path = notes_dir.join(filepath);
+        let Ok(bytes) = fs::read(&path).await else {
+            continue;
+        };
+        stats.checked += 1;
+
+        if stored_hash.as_deref() == Some(content_hash(&bytes).as_str()) {
+            continue;
+        }
+
+        // Forget the modification time, so that the file is indexed again
+        sqlx::query("UPDATE notes SET mtime = '' WHERE filepath = ?")
+            .bind(filepath)
+            .execute(pool)
+            .await
+            .map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+        let processed = metrics.time(
+            Operation::ProcessNoteFile,
+            process_note_file(pool, notes_dir, &path),
+        );
+        match processed.await {
+            Ok(()) => stats.reindexed += 1,
+            Err(e) => eprintln!("Error processing note file {}: {}", path.display(), e),
+        }
+    }
+
+    Ok((stats, rows.last().map(|(id, ..)| *id)))
+}
+
+/// Verify a batch of notes every few minutes, for as long as the program runs
+///
+/// Going through the notes in batches keeps the verification from reading
+/// the whole notes directory at once.
+pub async fn run_verification(pool: Pool<Sqlite>, notes_dir: PathBuf, metrics: MetricsRecorder) {
+    let mut after_row = 0;
+
+    loop {
+        tokio::time::sleep(VERIFY_INTERVAL).await;
+
+        // Maintenance by another process goes first
+        if let Err(e) = wait_for_maintenance(&pool).await {
+            eprintln!("Error checking the maintenance lock: {}", e);
+        }
+
+        match verify_content_hashes_with_metrics(
+            &pool,
+            &notes_dir,
+            after_row,
+            Some(VERIFY_BATCH_SIZE),
+            &metrics,
+        )
+        .await
+        {
+            Ok((stats, last_row)) => {
+                if stats.reindexed > 0 {
+                    eprintln!(
+                        "Re-indexed {} notes that changed without a new modification time",
+                        stats.reindexed
+                    );
+                }
+
+                // Start over after the last batch
+                after_row = last_row.unwrap_or(0);
+            }
+            Err(e) => eprintln!("Error verifying the indexed notes: {}", e),
+        }
+    }
+}
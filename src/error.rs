@@ -1,10 +1,14 @@
+use chrono::{DateTime, Local};
 use std::io;
 use thiserror::Error;
 
-use crate::constants::MAX_FILE_SIZE_KIB;
+use crate::constants::{MAX_FILE_SIZE_KIB, MAX_TAGS_PER_NOTE};
+use crate::core::id::ID_LENGTH;
+use crate::core::input::InputSource;
 
 /// Specific error type for tag validation errors
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum TagError {
     #[error("Tag cannot be empty")]
     Empty,
@@ -14,10 +18,17 @@ pub enum TagError {
 
     #[error("Tag '{0}' can only contain lowercase letters, numbers, and dashes")]
     InvalidCharacters(String),
+
+    #[error("Tag '{tag}' cannot contain spaces; use dashes instead, e.g. +{suggestion}")]
+    MultiWord { tag: String, suggestion: String },
+
+    #[error("Too many tags: {0} (maximum is {MAX_TAGS_PER_NOTE})")]
+    TooMany(usize),
 }
 
 /// Specific error type for Id validation errors
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum IdError {
     #[error("Id cannot be empty")]
     Empty,
@@ -27,13 +38,34 @@ pub enum IdError {
 
     #[error("Id '{0}' can only contain lowercase letters and numbers")]
     InvalidCharacters(String),
+
+    #[error("Id prefix can be at most {ID_LENGTH} characters long, got {0}")]
+    PrefixTooLong(usize),
+}
+
+/// A 1-based line and column in a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for TextLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
 }
 
 /// Specific error type for frontmatter validation errors
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum FrontmatterError {
-    #[error("Invalid YAML format: {0}")]
-    InvalidYaml(String),
+    #[error("Invalid YAML format: {message}{}", location.map(|l| format!(" at {}", l)).unwrap_or_default())]
+    InvalidYaml {
+        message: String,
+        /// Where the error is, relative to the start of the note if known
+        location: Option<TextLocation>,
+    },
 
     #[error("Invalid timestamp format: {0}")]
     InvalidTimestamp(String),
@@ -41,6 +73,7 @@ pub enum FrontmatterError {
 
 /// Specific error type for database operations
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum DatabaseError {
     #[error("Database connection error: {0}")]
     Connection(String),
@@ -57,14 +90,46 @@ pub enum DatabaseError {
     #[error("File monitoring error: {0}")]
     Monitoring(String),
 
+    #[error("Indexing error: {0}")]
+    Indexing(String),
+
     #[error("Multiple notes found with ID prefix '{0}': {1} matches")]
     MultipleMatches(String, usize),
 
     #[error("Invalid search query: {0}")]
     InvalidSearchQuery(String),
+
+    #[error("Database backup error: {0}")]
+    Backup(String),
+
+    #[error(
+        "Another notelog process holds the maintenance lock (since {0}); try again later or pass --wait"
+    )]
+    MaintenanceLocked(String),
 }
 
+/// Specific error type for invalid search parameters
 #[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SearchQueryError {
+    #[error("A search query must be provided")]
+    Empty,
+
+    #[error("'before' date ({0}) must be greater than or equal to 'after' date ({1})")]
+    InvalidDateRange(DateTime<Local>, DateTime<Local>),
+
+    #[error("Limit cannot exceed {max}, got {limit}")]
+    LimitTooLarge { limit: usize, max: usize },
+
+    #[error("Path prefix '{0}' must be relative to the notes directory, without '..'")]
+    InvalidPathPrefix(String),
+
+    #[error("Unknown order '{0}' (expected relevance, newest or oldest)")]
+    InvalidOrder(String),
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum NotelogError {
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
@@ -72,9 +137,20 @@ pub enum NotelogError {
     #[error("Notes directory does not exist or is not a directory: {0}")]
     NotesDirectoryNotFound(String),
 
+    #[error("Notes directory is not readable: {0}")]
+    NotesDirectoryNotReadable(String),
+
     #[error("Notes directory is not writable: {0}")]
     NotesDirectoryNotWritable(String),
 
+    #[error(
+        "Refusing to use {0} as the notes directory: notelog indexes and watches every file below it, which is slow and puts unrelated files into the search index (pass --allow-large-dir to use it anyway)"
+    )]
+    NotesDirectoryTooLarge(String),
+
+    #[error("Directory is not empty: {0} (use --force to initialize it anyway)")]
+    NotesDirectoryNotEmpty(String),
+
     #[error("Note content is empty")]
     EmptyContent,
 
@@ -84,19 +160,36 @@ pub enum NotelogError {
     #[error("Note content contains null bytes")]
     ContentContainsNullBytes,
 
-    #[error("Note content contains invalid UTF-8")]
-    InvalidUtf8Content,
+    #[error("{path} contains invalid UTF-8 at byte {offset} (bytes around it: {context})")]
+    InvalidUtf8Content {
+        /// The file, or `stdin`
+        path: String,
+        /// The offset of the first invalid byte
+        offset: usize,
+        /// The bytes around the invalid one in hex, with the invalid one in brackets
+        context: String,
+    },
 
-    #[error("Cannot use both stdin and file input")]
-    ConflictingInputMethods,
-
-    #[error("Cannot use both stdin and command line arguments")]
-    ConflictingStdinAndArgs,
+    #[error(
+        "Content was provided both via {a} and {b}; use only one of them (words starting with + are tags and can be combined with either)"
+    )]
+    ConflictingInputMethods { a: InputSource, b: InputSource },
 
     #[error("Failed to launch editor: {0}")]
     EditorLaunchFailed(String),
 
-    #[error("Invalid options for 'mcp' command: only the global --notes-dir option is allowed.")]
+    #[error("Failed to fetch the page title: {0}")]
+    FetchFailed(String),
+
+    #[error("Failed to run the command: {0}")]
+    ExecFailed(String),
+
+    #[error("Failed to run git: {0}")]
+    GitFailed(String),
+
+    #[error(
+        "Invalid options for 'mcp' command: only the global --notes-dir and the --store and --print-instructions options are allowed."
+    )]
     InvalidMcpOptions,
 
     #[error(
@@ -107,9 +200,37 @@ pub enum NotelogError {
     #[error("No valid note found")]
     NoValidNoteFound,
 
+    #[error("No note found with ID prefix '{0}'")]
+    NoteNotFound(String),
+
+    #[error("No section with the heading '{0}' in the note")]
+    SectionNotFound(String),
+
+    #[error("Cannot open search result: {0}")]
+    NoSearchResult(String),
+
+    #[error("No query !{0} in the search history ({1} queries recorded)")]
+    HistoryEntryNotFound(usize, usize),
+
+    #[error("Opening a search result requires an interactive terminal")]
+    OpenRequiresTerminal,
+
     #[error("MCP server error: {0}")]
     McpServerError(String),
 
+    #[error("Daemon error: {0}")]
+    DaemonFailed(String),
+
+    /// An error that the daemon reported for a request, with its message as is
+    #[error("{0}")]
+    FromDaemon(String),
+
+    #[error("Unknown tool '{0}'. Available tools: {1}")]
+    UnknownTool(String, String),
+
+    #[error("Store '{0}' was given more than once")]
+    DuplicateStore(String),
+
     #[error("Tag validation error: {0}")]
     TagError(#[from] TagError),
 
@@ -122,11 +243,32 @@ pub enum NotelogError {
     #[error("Database error: {0}")]
     DatabaseError(#[from] DatabaseError),
 
+    #[error("Invalid search: {0}")]
+    SearchQueryError(#[from] SearchQueryError),
+
+    #[error(
+        "+{tag} is now used by {now} notes instead of {listed}; run `notelog tag suggest-cleanup` again"
+    )]
+    TagCountChanged {
+        tag: String,
+        listed: usize,
+        now: usize,
+    },
+
     #[error("Operation cancelled by user")]
     UserCancelled,
 
+    #[error("Self test failed: {0}")]
+    SelftestFailed(String),
+
     #[error("Path error: {0}")]
     PathError(String),
+
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
+    #[error("Unknown profile '{0}'. Available profiles: {1}")]
+    UnknownProfile(String, String),
 }
 
 pub type Result<T> = std::result::Result<T, NotelogError>;
@@ -0,0 +1,208 @@
+//! Search query history for the command line
+//!
+//! Every query run with `notelog search` is appended to a history file, one
+//! tab-separated line with the time, the number of hits and the query. The
+//! file lives in `~/.local/share/notelog/search_history` unless the
+//! `NOTELOG_SEARCH_HISTORY` environment variable points elsewhere.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use dirs::data_dir;
+
+use crate::constants::MAX_SEARCH_HISTORY;
+use crate::error::{NotelogError, Result};
+
+/// A query from the search history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// When the query was run
+    pub time: DateTime<Local>,
+    /// How many notes matched
+    pub hits: usize,
+    /// The query as entered
+    pub query: String,
+}
+
+impl HistoryEntry {
+    /// Create a history entry for a query that was just run
+    pub fn new(query: &str, hits: usize) -> Self {
+        // Tabs and newlines would break the line format
+        let query = query
+            .split(['\t', '\n', '\r'])
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Self {
+            time: Local::now(),
+            hits,
+            query,
+        }
+    }
+
+    /// Format the entry as a line of the history file
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}", self.time.to_rfc3339(), self.hits, self.query)
+    }
+
+    /// Parse a line of the history file
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let time = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+        let hits = fields.next()?.parse().ok()?;
+        let query = fields.next()?.to_string();
+
+        Some(Self {
+            time: time.with_timezone(&Local),
+            hits,
+            query,
+        })
+    }
+}
+
+/// Get the path of the search history file
+pub fn history_path() -> Option<PathBuf> {
+    env::var("NOTELOG_SEARCH_HISTORY")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| data_dir().map(|p| p.join("notelog").join("search_history")))
+}
+
+/// Load the search history, oldest first
+///
+/// A missing file is an empty history, and lines that cannot be parsed are skipped.
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(HistoryEntry::from_line)
+        .collect())
+}
+
+/// Append an entry to the search history, keeping at most `MAX_SEARCH_HISTORY` entries
+pub fn append(path: &Path, entry: HistoryEntry) -> Result<()> {
+    let mut entries = load(path)?;
+    entries.push(entry);
+    prune(&mut entries, MAX_SEARCH_HISTORY);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut content = String::new();
+    for entry in &entries {
+        content.push_str(&entry.to_line());
+        content.push('\n');
+    }
+    fs::write(path, content)?;
+
+    Ok(())
+}
+
+/// Drop the oldest entries so that at most `max` remain
+pub fn prune(entries: &mut Vec<HistoryEntry>, max: usize) {
+    let excess = entries.len().saturating_sub(max);
+    entries.drain(..excess);
+}
+
+/// Expand a `!N` query to the N-th most recent query in the history
+///
+/// Returns `None` if the query is not of the form `!N`.
+pub fn expand_rerun(query: &str, entries: &[HistoryEntry]) -> Result<Option<String>> {
+    let Some(n) = query
+        .strip_prefix('!')
+        .and_then(|n| n.parse::<usize>().ok())
+    else {
+        return Ok(None);
+    };
+
+    n.checked_sub(1)
+        .and_then(|index| entries.iter().rev().nth(index))
+        .map(|entry| Some(entry.query.clone()))
+        .ok_or(NotelogError::HistoryEntryNotFound(n, entries.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entries(queries: &[&str]) -> Vec<HistoryEntry> {
+        queries
+            .iter()
+            .map(|query| HistoryEntry::new(query, 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_rerun() {
+        let history = entries(&["oldest", "deploy +project", "newest"]);
+
+        assert_eq!(
+            expand_rerun("!1", &history).unwrap().as_deref(),
+            Some("newest")
+        );
+        assert_eq!(
+            expand_rerun("!3", &history).unwrap().as_deref(),
+            Some("oldest")
+        );
+
+        // Not a re-run
+        assert_eq!(expand_rerun("deploy", &history).unwrap(), None);
+        assert_eq!(expand_rerun("!important", &history).unwrap(), None);
+
+        // Out of range
+        assert!(matches!(
+            expand_rerun("!4", &history),
+            Err(NotelogError::HistoryEntryNotFound(4, 3))
+        ));
+        assert!(expand_rerun("!0", &history).is_err());
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut history = entries(&["a", "b", "c", "d"]);
+        prune(&mut history, 2);
+        let queries: Vec<&str> = history.iter().map(|entry| entry.query.as_str()).collect();
+        assert_eq!(queries, vec!["c", "d"]);
+
+        prune(&mut history, 5);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notelog").join("search_history");
+
+        assert!(load(&path).unwrap().is_empty());
+
+        append(&path, HistoryEntry::new("deploy\t+project", 3)).unwrap();
+        append(&path, HistoryEntry::new("standup", 0)).unwrap();
+
+        let history = load(&path).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].query, "deploy +project");
+        assert_eq!(history[0].hits, 3);
+        assert_eq!(history[1].query, "standup");
+
+        // The history is capped
+        for i in 0..MAX_SEARCH_HISTORY {
+            append(&path, HistoryEntry::new(&format!("query {}", i), 1)).unwrap();
+        }
+        let history = load(&path).unwrap();
+        assert_eq!(history.len(), MAX_SEARCH_HISTORY);
+        assert_eq!(
+            history.last().unwrap().query,
+            format!("query {}", MAX_SEARCH_HISTORY - 1)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! User hooks
+//!
+//! Executable files in `<notes_dir>/.notelog/hooks` are run after Notelog
+//! writes a note. A hook receives the path of the note (relative to the notes
+//! directory), its ID and its title, both as arguments and as the
+//! `NOTELOG_NOTE_PATH`, `NOTELOG_NOTE_ID` and `NOTELOG_NOTE_TITLE` environment
+//! variables. Hooks run in the notes directory, with `NOTELOG_DIR` set to it.
+//!
+//! Hook failures are reported as warnings; they never fail the operation.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config;
+use crate::core::note::Note;
+
+/// Directory for Notelog's own files inside the notes directory
+pub const DATA_DIR: &str = ".notelog";
+
+/// The operations hooks can run after
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// A note was added
+    PostAdd,
+    /// A note was modified
+    PostEdit,
+}
+
+impl Hook {
+    /// The filename of the hook
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::PostAdd => "post-add",
+            Hook::PostEdit => "post-edit",
+        }
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Get the path of a hook in the notes directory
+pub fn hook_path(notes_dir: &Path, hook: Hook) -> PathBuf {
+    notes_dir.join(DATA_DIR).join("hooks").join(hook.name())
+}
+
+/// Run a hook for a note, unless hooks are disabled
+///
+/// Waits for the hook to finish. Inside the MCP server, use `spawn_hook`
+/// instead, so that a slow hook does not block the server.
+pub fn run_hook(notes_dir: &Path, hook: Hook, relative_path: &Path, note: &Note) {
+    if config::get().disable_hooks {
+        return;
+    }
+
+    let id = note
+        .frontmatter()
+        .id()
+        .map(|id| id.as_str().to_string())
+        .unwrap_or_default();
+
+    if let Err(e) = run_hook_script(notes_dir, hook, relative_path, &id, &note.extract_title()) {
+        eprintln!("Warning: {} hook failed: {}", hook, e);
+    }
+}
+
+/// Run a hook for a note in the background, unless hooks are disabled
+///
+/// Falls back to running the hook directly outside of a tokio runtime.
+pub fn spawn_hook(notes_dir: &Path, hook: Hook, relative_path: &Path, note: &Note) {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        run_hook(notes_dir, hook, relative_path, note);
+        return;
+    };
+
+    let notes_dir = notes_dir.to_path_buf();
+    let relative_path = relative_path.to_path_buf();
+    let note = note.clone();
+    handle.spawn_blocking(move || run_hook(&notes_dir, hook, &relative_path, &note));
+}
+
+/// Run the hook script, if it exists
+///
+/// The output of the hook goes to stderr, since stdout carries the protocol
+/// messages of the MCP server.
+fn run_hook_script(
+    notes_dir: &Path,
+    hook: Hook,
+    relative_path: &Path,
+    id: &str,
+    title: &str,
+) -> io::Result<()> {
+    let path = hook_path(notes_dir, hook);
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    if !is_executable(&path)? {
+        return Err(io::Error::other(format!(
+            "{} is not executable",
+            path.display()
+        )));
+    }
+
+    let status = Command::new(&path)
+        .arg(relative_path)
+        .arg(id)
+        .arg(title)
+        .env("NOTELOG_DIR", notes_dir)
+        .env("NOTELOG_NOTE_PATH", relative_path)
+        .env("NOTELOG_NOTE_ID", id)
+        .env("NOTELOG_NOTE_TITLE", title)
+        .current_dir(notes_dir)
+        .stdin(Stdio::null())
+        .stdout(io::stderr())
+        .status()?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!("exited with {}", status)));
+    }
+
+    Ok(())
+}
+
+/// Check whether a file can be executed
+#[cfg(unix)]
+fn is_executable(path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Ok(path.metadata()?.permissions().mode() & 0o111 != 0)
+}
+
+/// Check whether a file can be executed
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn write_hook(notes_dir: &Path, hook: Hook, script: &str) {
+        let path = hook_path(notes_dir, hook);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_hook_receives_note_details() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        write_hook(
+            notes_dir,
+            Hook::PostAdd,
+            "#!/bin/sh\necho \"$1|$2|$3|$NOTELOG_NOTE_TITLE\" > marker\n",
+        );
+
+        run_hook_script(
+            notes_dir,
+            Hook::PostAdd,
+            Path::new("2025/05_May/note.md"),
+            "abc123",
+            "My note",
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(notes_dir.join("marker")).unwrap(),
+            "2025/05_May/note.md|abc123|My note|My note\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_and_failing_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let note_path = Path::new("note.md");
+
+        // A missing hook is not an error
+        run_hook_script(notes_dir, Hook::PostEdit, note_path, "", "").unwrap();
+
+        write_hook(notes_dir, Hook::PostEdit, "#!/bin/sh\nexit 3\n");
+        let err = run_hook_script(notes_dir, Hook::PostEdit, note_path, "", "").unwrap_err();
+        assert!(err.to_string().contains("exited"));
+
+        fs::set_permissions(
+            hook_path(notes_dir, Hook::PostEdit),
+            fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+        let err = run_hook_script(notes_dir, Hook::PostEdit, note_path, "", "").unwrap_err();
+        assert!(err.to_string().contains("not executable"));
+    }
+}
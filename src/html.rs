@@ -0,0 +1,489 @@
+//! Rendering the notes as a static HTML site, for `notelog export`
+//!
+//! Each note becomes a page named after its ID, e.g. `0123456789abcdef.html`.
+//! The index page lists the notes by month, and each tag has a page listing
+//! its notes. Links to other notes (`notelog://note/ID` or `[[ID]]`) point to
+//! their pages if they are part of the export; otherwise only the link text
+//! is kept. So is the text of links to anything but web pages, email
+//! addresses and relative paths, e.g. `javascript:` URLs. Raw HTML in the
+//! notes is shown as text.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Bound;
+
+use pulldown_cmark::{CowStr, Event, LinkType, Options, Parser, Tag, TagEnd, html};
+
+use notelog::core::datetime::format_display_date;
+use notelog::core::note::Note;
+
+/// The filename of the index page
+pub const INDEX_PAGE: &str = "index.html";
+
+/// The filename of the page that lists the tags
+pub const TAGS_PAGE: &str = "tags.html";
+
+/// The scheme and path of links to other notes
+const NOTE_URI_PREFIX: &str = "notelog://note/";
+
+/// The URL schemes that other links can have on the exported pages
+const SAFE_URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+const STYLE: &str = "\
+body { max-width: 46em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }
+nav, .meta { color: #666; font-size: 0.9em; }
+.meta a, nav a { color: inherit; }
+pre { background: #f4f4f4; padding: 0.5em; overflow-x: auto; }
+code { background: #f4f4f4; }
+ul.notes { list-style: none; padding-left: 0; }
+ul.notes time { color: #666; margin-right: 0.5em; }";
+
+/// The filename of the page of a note with the given ID
+pub fn note_page(id: &str) -> String {
+    format!("{}.html", id)
+}
+
+/// The filename of the page of a tag
+pub fn tag_page(tag: &str) -> String {
+    format!("tag-{}.html", tag)
+}
+
+/// Turns links to other notes into links to their pages
+pub struct LinkResolver<'a> {
+    /// The IDs of the exported notes
+    ids: BTreeSet<&'a str>,
+    /// The titles of the exported notes, by ID
+    titles: BTreeMap<&'a str, String>,
+}
+
+impl<'a> LinkResolver<'a> {
+    /// Create a resolver for links to the given notes
+    pub fn new(notes: &[&'a Note]) -> Self {
+        let mut ids = BTreeSet::new();
+        let mut titles = BTreeMap::new();
+        for note in notes {
+            if let Some(id) = note.frontmatter().id() {
+                ids.insert(id.as_str());
+                titles.insert(id.as_str(), note.extract_title());
+            }
+        }
+
+        Self { ids, titles }
+    }
+
+    /// Find the ID of the exported note that a link points to
+    ///
+    /// `notelog://` URIs must have the full ID; wikilinks can use any unique
+    /// prefix of it.
+    fn resolve(&self, dest: &str, link_type: LinkType) -> Option<&'a str> {
+        let prefix = match (dest.strip_prefix(NOTE_URI_PREFIX), link_type) {
+            (Some(id), _) => return self.ids.get(id).copied(),
+            (None, LinkType::WikiLink { .. }) => dest.trim(),
+            (None, _) => return None,
+        };
+        if prefix.is_empty() {
+            return None;
+        }
+
+        let mut matches = self
+            .ids
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|id| id.starts_with(prefix));
+        match (matches.next(), matches.next()) {
+            (Some(id), None) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// Render the markdown of a note as HTML, with links to other notes resolved
+fn render_markdown(content: &str, links: &LinkResolver) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_WIKILINKS;
+
+    // Whether each open link is kept, and whether its text is being replaced
+    let mut open_links: Vec<bool> = Vec::new();
+    let mut replacing_text = false;
+    let mut events = Vec::new();
+
+    for event in Parser::new_ext(content, options) {
+        match event {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let is_note_link = dest_url.starts_with(NOTE_URI_PREFIX)
+                    || matches!(link_type, LinkType::WikiLink { .. });
+                if !is_note_link {
+                    let keep = link_type == LinkType::Email || is_safe_url(&dest_url);
+                    open_links.push(keep);
+                    if keep {
+                        events.push(Event::Start(Tag::Link {
+                            link_type,
+                            dest_url,
+                            title,
+                            id,
+                        }));
+                    }
+                    continue;
+                }
+
+                let Some(target) = links.resolve(&dest_url, link_type) else {
+                    open_links.push(false);
+                    continue;
+                };
+                open_links.push(true);
+                events.push(Event::Start(Tag::Link {
+                    link_type,
+                    dest_url: CowStr::from(note_page(target)),
+                    title,
+                    id,
+                }));
+
+                // `[[ID]]` shows the title of the note instead of its ID
+                if link_type == (LinkType::WikiLink { has_pothole: false }) {
+                    events.push(Event::Text(CowStr::from(links.titles[target].clone())));
+                    replacing_text = true;
+                }
+            }
+            Event::End(TagEnd::Link) => {
+                replacing_text = false;
+                if open_links.pop().unwrap_or(true) {
+                    events.push(Event::End(TagEnd::Link));
+                }
+            }
+            _ if replacing_text => {}
+            Event::Html(text) | Event::InlineHtml(text) => events.push(Event::Text(text)),
+            event => events.push(event),
+        }
+    }
+
+    let mut out = String::new();
+    html::push_html(&mut out, events.into_iter());
+    out
+}
+
+/// Whether a link destination is a relative URL or has one of the
+/// `SAFE_URL_SCHEMES`
+///
+/// Anything that could be a scheme counts as one, so that browsers cannot
+/// read e.g. `java\tscript:` as a scheme that is not allowed.
+fn is_safe_url(dest: &str) -> bool {
+    match dest.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => SAFE_URL_SCHEMES
+            .iter()
+            .any(|safe| scheme.eq_ignore_ascii_case(safe)),
+        _ => true,
+    }
+}
+
+/// Escape text for use in HTML, also in attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Wrap the body of a page in a complete HTML document
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n\
+         <nav><a href=\"{}\">All notes</a> · <a href=\"{}\">Tags</a></nav>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        INDEX_PAGE,
+        TAGS_PAGE,
+        body
+    )
+}
+
+/// The creation time of a note, for the lists and the header block
+fn created(note: &Note) -> String {
+    let created = note.frontmatter().created();
+    format!(
+        "<time datetime=\"{}\">{} {}</time>",
+        created.to_rfc3339(),
+        escape(&format_display_date(created.date_naive())),
+        created.format("%H:%M")
+    )
+}
+
+/// Render the page of a note, with its frontmatter as a header block
+pub fn render_note(note: &Note, links: &LinkResolver) -> String {
+    let frontmatter = note.frontmatter();
+
+    let mut meta = created(note);
+    for tag in frontmatter.tags() {
+        let _ = write!(
+            meta,
+            " <a href=\"{}\">+{}</a>",
+            escape(&tag_page(tag.as_str())),
+            escape(tag.as_str())
+        );
+    }
+    if let Some(due) = frontmatter.due() {
+        let _ = write!(meta, " · due {}", escape(&format_display_date(due)));
+    }
+
+    let body = format!(
+        "<header class=\"meta\">{}</header>\n<article>\n{}</article>\n",
+        meta,
+        render_markdown(note.content(), links)
+    );
+    page(&note.extract_title(), &body)
+}
+
+/// Render a list of notes, each with its creation time and a link to its page
+fn note_list(notes: &[&Note]) -> String {
+    let mut out = String::from("<ul class=\"notes\">\n");
+    for note in notes {
+        if let Some(id) = note.frontmatter().id() {
+            let _ = writeln!(
+                out,
+                "<li>{}<a href=\"{}\">{}</a></li>",
+                created(note),
+                note_page(id.as_str()),
+                escape(&note.extract_title())
+            );
+        }
+    }
+    out.push_str("</ul>\n");
+    out
+}
+
+/// Render the index page, with the notes grouped by month, newest first
+///
+/// The notes must be sorted newest first.
+pub fn render_index(notes: &[&Note]) -> String {
+    let mut body = String::from("<h1>Notes</h1>\n");
+    for month in notes.chunk_by(|a, b| {
+        a.frontmatter().created().format("%Y-%m").to_string()
+            == b.frontmatter().created().format("%Y-%m").to_string()
+    }) {
+        let _ = writeln!(
+            body,
+            "<h2>{}</h2>",
+            month[0].frontmatter().created().format("%B %Y")
+        );
+        body.push_str(&note_list(month));
+    }
+    page("Notes", &body)
+}
+
+/// Render the page that lists the tags, with the number of notes with each
+pub fn render_tags(tags: &BTreeMap<&str, Vec<&Note>>) -> String {
+    let mut body = String::from("<h1>Tags</h1>\n<ul>\n");
+    for (tag, notes) in tags {
+        let _ = writeln!(
+            body,
+            "<li><a href=\"{}\">+{}</a> ({})</li>",
+            escape(&tag_page(tag)),
+            escape(tag),
+            notes.len()
+        );
+    }
+    body.push_str("</ul>\n");
+    page("Tags", &body)
+}
+
+/// Render the page of a tag, with its notes newest first
+pub fn render_tag(tag: &str, notes: &[&Note]) -> String {
+    let title = format!("+{}", tag);
+    let body = format!("<h1>{}</h1>\n{}", escape(&title), note_list(notes));
+    page(&title, &body)
+}
+
+/// Render the pages of the site, as filenames with their HTML
+///
+/// Only notes with an ID are exported, since their pages are named after it.
+pub fn render_site(notes: &[Note]) -> Vec<(String, String)> {
+    let mut notes: Vec<&Note> = notes
+        .iter()
+        .filter(|note| note.frontmatter().id().is_some())
+        .collect();
+    notes.sort_by(|a, b| b.frontmatter().created().cmp(a.frontmatter().created()));
+
+    let links = LinkResolver::new(&notes);
+    let mut pages = vec![(INDEX_PAGE.to_string(), render_index(&notes))];
+
+    let mut tags: BTreeMap<&str, Vec<&Note>> = BTreeMap::new();
+    for note in &notes {
+        for tag in note.frontmatter().tags() {
+            tags.entry(tag.as_str()).or_default().push(note);
+        }
+    }
+    pages.push((TAGS_PAGE.to_string(), render_tags(&tags)));
+    for (tag, tagged) in &tags {
+        pages.push((tag_page(tag), render_tag(tag, tagged)));
+    }
+
+    for note in &notes {
+        if let Some(id) = note.frontmatter().id() {
+            pages.push((note_page(id.as_str()), render_note(note, &links)));
+        }
+    }
+
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const FIRST: &str = "0123456789abcdef";
+    const SECOND: &str = "fedcba9876543210";
+    const OUTSIDE: &str = "aaaaaaaaaaaaaaaa";
+
+    fn note(id: &str, created: &str, tags: &str, content: &str) -> Note {
+        Note::from_str(&format!(
+            "---\ncreated: {}\nid: {}\ntags: [{}]\n---\n\n{}",
+            created, id, tags, content
+        ))
+        .unwrap()
+    }
+
+    fn corpus() -> Vec<Note> {
+        vec![
+            note(
+                FIRST,
+                "2025-04-10T09:00:00+00:00",
+                "project",
+                "# Kickoff\n\nWe started <b>today</b>.",
+            ),
+            note(
+                SECOND,
+                "2025-05-02T09:00:00+00:00",
+                "project, deploy",
+                &format!(
+                    "# Deploy\n\nSee [the kickoff](notelog://note/{FIRST}), [[0123]] and \
+                     [[fedc|this note]].\n\nNot exported: [old](notelog://note/{OUTSIDE}) \
+                     and [[{OUTSIDE}]]. [Docs](https://example.com) \
+                     [Click](javascript:alert(1)) [Mail](mailto:me@example.com) \
+                     [Guide](guide.html)"
+                ),
+            ),
+            Note::from_str("---\ncreated: 2025-05-03T09:00:00+00:00\n---\n\n# Without an ID")
+                .unwrap(),
+        ]
+    }
+
+    fn find<'a>(pages: &'a [(String, String)], filename: &str) -> &'a str {
+        pages
+            .iter()
+            .find(|(name, _)| name == filename)
+            .map(|(_, html)| html.as_str())
+            .unwrap_or_else(|| panic!("no page {}", filename))
+    }
+
+    #[test]
+    fn test_render_site_pages() {
+        let pages = render_site(&corpus());
+        let mut filenames: Vec<&str> = pages.iter().map(|(name, _)| name.as_str()).collect();
+        filenames.sort();
+        assert_eq!(
+            filenames,
+            vec![
+                "0123456789abcdef.html",
+                "fedcba9876543210.html",
+                "index.html",
+                "tag-deploy.html",
+                "tag-project.html",
+                "tags.html",
+            ]
+        );
+
+        // The index groups the notes by month, newest first
+        let index = find(&pages, INDEX_PAGE);
+        let may = index.find("<h2>May 2025</h2>").unwrap();
+        let april = index.find("<h2>April 2025</h2>").unwrap();
+        assert!(may < april);
+        assert!(index.contains("<a href=\"fedcba9876543210.html\">Deploy</a>"));
+        assert!(!index.contains("Without an ID"));
+
+        let tags = find(&pages, TAGS_PAGE);
+        assert!(tags.contains("<a href=\"tag-project.html\">+project</a> (2)"));
+        let deploy = find(&pages, "tag-deploy.html");
+        assert!(deploy.contains("fedcba9876543210.html"));
+        assert!(!deploy.contains("0123456789abcdef.html"));
+    }
+
+    #[test]
+    fn test_render_site_links() {
+        let pages = render_site(&corpus());
+        let html = find(&pages, "fedcba9876543210.html");
+
+        // The header block links to the tag pages
+        assert!(html.contains("<a href=\"tag-deploy.html\">+deploy</a>"));
+
+        // Links to exported notes point to their pages
+        assert!(html.contains("<a href=\"0123456789abcdef.html\">the kickoff</a>"));
+        assert!(html.contains("<a href=\"0123456789abcdef.html\">Kickoff</a>"));
+        assert!(html.contains("<a href=\"fedcba9876543210.html\">this note</a>"));
+
+        // Links to other notes keep only their text
+        assert!(html.contains("Not exported: old and aaaaaaaaaaaaaaaa."));
+        assert!(!html.contains("notelog://"));
+        assert!(html.contains("<a href=\"https://example.com\">Docs</a>"));
+
+        // Other links keep only their text unless they are safe to follow
+        assert!(html.contains(" Click "));
+        assert!(!html.contains("javascript:"));
+        assert!(html.contains("<a href=\"mailto:me@example.com\">Mail</a>"));
+        assert!(html.contains("<a href=\"guide.html\">Guide</a>"));
+
+        // Raw HTML is shown as text
+        let html = find(&pages, "0123456789abcdef.html");
+        assert!(html.contains("We started &lt;b&gt;today&lt;/b&gt;."));
+        assert!(html.contains("<title>Kickoff</title>"));
+    }
+
+    #[test]
+    fn test_link_resolver() {
+        let mut notes = corpus();
+        notes.push(note(
+            "0123aaaaaaaaaaaa",
+            "2025-05-03T09:00:00+00:00",
+            "",
+            "# Other",
+        ));
+        let refs: Vec<&Note> = notes.iter().collect();
+        let links = LinkResolver::new(&refs);
+        let wikilink = LinkType::WikiLink { has_pothole: false };
+
+        assert_eq!(links.resolve("01234", wikilink), Some(FIRST));
+        assert_eq!(links.resolve(SECOND, wikilink), Some(SECOND));
+
+        // Ambiguous prefixes and other links are not resolved
+        assert_eq!(links.resolve("0123", wikilink), None);
+        assert_eq!(links.resolve("", wikilink), None);
+        assert_eq!(links.resolve("0123", LinkType::Inline), None);
+
+        // IDs in notelog URIs must be complete
+        let uri = format!("{}0123", NOTE_URI_PREFIX);
+        assert_eq!(links.resolve(&uri, LinkType::Inline), None);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&'"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;"
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Notelog as a library
+//!
+//! The `notelog` binary is built on top of this library, which other Rust
+//! tools can use to read and write the same notes directory:
+//!
+//! - [`core`] has the note itself: [`Note`](core::note::Note),
+//!   [`Frontmatter`](core::frontmatter::Frontmatter), [`Tag`](core::tags::Tag)
+//!   and [`Id`](core::id::Id).
+//! - [`db`] has the search index, [`Database`](db::Database).
+//! - [`error`] has the error types shared by both.
+//!
+//! The MCP server is only part of the binary; build with
+//! `default-features = false` to leave out its dependencies.
+//!
+//! # Example
+//!
+//! ```
+//! use notelog::core::frontmatter::Frontmatter;
+//! use notelog::core::note::Note;
+//! use notelog::core::tags::Tag;
+//! use notelog::db::{Database, SearchQuery};
+//!
+//! # fn main() -> notelog::error::Result<()> {
+//! let notes_dir = tempfile::tempdir()?;
+//!
+//! // Write a note into the year and month directories
+//! let frontmatter = Frontmatter::with_tags(vec![Tag::new("idea")?]);
+//! let note = Note::new(frontmatter, "# Embed notelog\n\nIn the build tool".to_string());
+//! let path = note.save(notes_dir.path(), None)?;
+//! assert!(notes_dir.path().join(path).exists());
+//!
+//! // Index the notes directory and search it
+//! let rt = tokio::runtime::Runtime::new()?;
+//! let (notes, total) = rt.block_on(async {
+//!     let db = Database::initialize(notes_dir.path()).await?;
+//!     db.index_notes().await?;
+//!     db.search_notes(&SearchQuery::new("+idea")).await
+//! })?;
+//! assert_eq!(total, 1);
+//! assert_eq!(notes[0].extract_title(), "Embed notelog");
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod config;
+pub mod constants;
+pub mod core;
+pub mod db;
+pub mod error;
+pub mod hooks;
+
+/// Synthetic notes for benchmarks; not part of the stable API
+#[doc(hidden)]
+pub mod testsupport;
+
+/// Helpers shared with the binary; only the re-exports below are stable
+#[doc(hidden)]
+pub mod utils;
+
+pub use utils::{
+    create_date_directories, generate_filename, is_valid_note_file, read_file_content,
+    truncate_chars, validate_content,
+};
@@ -0,0 +1,129 @@
+//! Page titles for notes that consist of a single link
+
+use std::io::Read;
+use std::time::Duration;
+
+use crate::error::{NotelogError, Result};
+
+/// How long to wait for a page before giving up
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How much of a page to read; the title is usually near the start
+const MAX_PAGE_BYTES: u64 = 256 * 1024;
+
+/// Something that can download a web page
+///
+/// This is a trait so that tests do not need network access.
+pub trait PageFetcher {
+    /// Fetch the (possibly truncated) HTML of the page at `url`
+    fn fetch_page(&self, url: &str) -> Result<String>;
+}
+
+/// Fetches pages over HTTP(S)
+pub struct HttpFetcher;
+
+impl PageFetcher for HttpFetcher {
+    fn fetch_page(&self, url: &str) -> Result<String> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(FETCH_TIMEOUT))
+            .build()
+            .into();
+
+        let mut response = agent
+            .get(url)
+            .call()
+            .map_err(|e| NotelogError::FetchFailed(format!("{}: {}", url, e)))?;
+
+        let mut page = Vec::new();
+        response
+            .body_mut()
+            .as_reader()
+            .take(MAX_PAGE_BYTES)
+            .read_to_end(&mut page)
+            .map_err(|e| NotelogError::FetchFailed(format!("{}: {}", url, e)))?;
+
+        Ok(String::from_utf8_lossy(&page).into_owned())
+    }
+}
+
+/// Get the URL if the content consists of nothing but a single http(s) URL
+pub fn single_url(content: &str) -> Option<&str> {
+    let content = content.trim();
+
+    let is_url = (content.starts_with("http://") || content.starts_with("https://"))
+        && !content.contains(char::is_whitespace);
+
+    is_url.then_some(content)
+}
+
+/// Fetch the page at `url` and get its title
+pub fn fetch_title(fetcher: &impl PageFetcher, url: &str) -> Result<String> {
+    let page = fetcher.fetch_page(url)?;
+
+    extract_title(&page)
+        .ok_or_else(|| NotelogError::FetchFailed(format!("{}: the page has no title", url)))
+}
+
+/// Extract the contents of the `<title>` element of an HTML page
+///
+/// Whitespace is collapsed and the most common character references are
+/// decoded. Returns `None` if there is no (non-empty) title.
+pub fn extract_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps the byte offsets valid for `html`
+    let lower = html.to_ascii_lowercase();
+
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title = html[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let title = decode_entities(&title);
+
+    (!title.is_empty()).then_some(title)
+}
+
+/// Decode the character references that commonly appear in page titles
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_url() {
+        assert_eq!(
+            single_url("  https://example.com/a?b=c\n"),
+            Some("https://example.com/a?b=c")
+        );
+        assert_eq!(single_url("http://example.com"), Some("http://example.com"));
+        assert_eq!(single_url("see https://example.com"), None);
+        assert_eq!(single_url("ftp://example.com"), None);
+        assert_eq!(single_url("example.com"), None);
+    }
+
+    #[test]
+    fn test_extract_title() {
+        assert_eq!(
+            extract_title("<html><head><TITLE lang=\"en\">\n  Rust &amp; Cargo\n</TITLE>"),
+            Some("Rust & Cargo".to_string())
+        );
+        assert_eq!(
+            extract_title("<title>Ärger &lt;3</title>"),
+            Some("Ärger <3".to_string())
+        );
+        assert_eq!(extract_title("<title>  </title>"), None);
+        assert_eq!(extract_title("<title>Unterminated"), None);
+        assert_eq!(extract_title("<h1>No title</h1>"), None);
+    }
+}
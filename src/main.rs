@@ -1,18 +1,24 @@
 mod cli;
 mod commands;
-mod constants;
-mod core;
-mod db;
-mod error;
+mod daemon;
+mod history;
+mod html;
+mod link;
 mod mcp;
-mod utils;
+mod output;
+mod render;
+
+// The commands use these modules of the library as if they were part of the binary
+use notelog::{config, constants, core, db, error, hooks, utils};
 
 use clap::Parser;
-use std::io::{self, IsTerminal, Read};
 
-use cli::{AddArgs, Cli, Commands};
+use cli::{Cli, Commands, McpArgs};
 use error::Result;
-use utils::{ensure_notes_dir_exists, get_notes_dir};
+use utils::{
+    ensure_default_notes_dir, ensure_notes_dir_exists, ensure_notes_dir_readable,
+    ensure_notes_dir_writable, get_notes_dir, read_piped_stdin,
+};
 
 fn main() {
     if let Err(e) = run() {
@@ -25,44 +31,91 @@ fn main() {
 fn run() -> Result<()> {
     let cli = Cli::parse();
 
+    // Load the configuration file and select the active profile
+    let mut config = config::Config::load()?.with_profile(cli.profile.as_deref())?;
+    config.disable_hooks |= cli.no_hooks;
+    config.create_missing_dir |= cli.create_dir;
+    config.allow_large_dir |= cli.allow_large_dir;
+    config::init(config);
+
+    // Some commands do not need a notes directory
+    match &cli.command {
+        Some(Commands::Profiles) => return commands::list_profiles(config::get()),
+        Some(Commands::Config(args)) => return commands::show_config(cli.notes_dir.clone(), args),
+        Some(Commands::Selftest) => return commands::selftest(),
+        Some(Commands::Bench(args)) => return commands::bench(args),
+        Some(Commands::Mcp(McpArgs {
+            print_instructions: Some(tool),
+            ..
+        })) => return commands::print_instructions(tool.as_deref()),
+        Some(Commands::Init(args)) => {
+            // The directory to initialize usually does not exist yet
+            let notes_dir = get_notes_dir(args.path.clone().or(cli.notes_dir.clone()))?;
+            return commands::init_notes_dir(&notes_dir.value, args);
+        }
+        _ => {}
+    }
+
     // Determine the notes directory
-    let notes_dir = get_notes_dir(cli.notes_dir)?;
+    let notes_dir = get_notes_dir(cli.notes_dir.clone())?;
 
-    // Ensure the notes directory exists and is writable
-    ensure_notes_dir_exists(&notes_dir)?;
+    // On first run, offer to create the default notes directory
+    let create_missing_dir = config::get().create_missing_dir;
+    if !create_missing_dir {
+        ensure_default_notes_dir(&notes_dir.value, notes_dir.source)?;
+    }
+
+    // Create a missing notes directory if asked to
+    // Resolve symlinks once, so that the paths from file watchers match
+    let notes_dir = ensure_notes_dir_exists(
+        &notes_dir,
+        create_missing_dir,
+        config::get().allow_large_dir,
+    )?;
 
     // Handle the command (or default to 'add')
-    match cli.command {
-        Some(Commands::Add(args)) => {
+    let command = cli.into_command();
+
+    // Ensure the notes directory exists, and is writable unless the command
+    // only queries the notes
+    if command.is_query_only() {
+        ensure_notes_dir_readable(&notes_dir)?;
+    } else {
+        ensure_notes_dir_writable(&notes_dir)?;
+    }
+
+    match command {
+        Commands::Add(args) => {
             // Only check stdin for the add command
-            let stdin_content = if !io::stdin().is_terminal() {
-                let mut buffer = Vec::new();
-                io::stdin().read_to_end(&mut buffer)?;
-                buffer
-            } else {
-                Vec::new()
-            };
-            commands::add_note(&notes_dir, args, stdin_content).map(|_| ())
+            commands::add_note(&notes_dir, args, read_piped_stdin()?).map(|_| ())
         }
-        Some(Commands::Mcp(args)) => commands::mcp_command(&notes_dir, args),
-        Some(Commands::Last(args)) => commands::last_note(&notes_dir, args),
-        None => {
-            // If no subcommand is provided, treat trailing args as 'add' command
-            let add_args = AddArgs {
-                title: cli.title,
-                file: cli.file,
-                args: cli.args,
-            };
-
-            // Only check stdin for the default add command
-            let stdin_content = if !io::stdin().is_terminal() {
-                let mut buffer = Vec::new();
-                io::stdin().read_to_end(&mut buffer)?;
-                buffer
-            } else {
-                Vec::new()
-            };
-            commands::add_note(&notes_dir, add_args, stdin_content).map(|_| ())
+        Commands::Mcp(args) => commands::mcp_command(&notes_dir, args),
+        Commands::Last(args) => commands::last_note(&notes_dir, args),
+        Commands::Backup(args) => commands::backup_notes(&notes_dir, args),
+        Commands::Doctor(args) => commands::doctor(&notes_dir, args),
+        Commands::Db(args) => commands::db_command(&notes_dir, args),
+        Commands::Reindex(args) => commands::reindex(&notes_dir, args),
+        Commands::List(args) => commands::list_notes(&notes_dir, args),
+        Commands::Search(args) => commands::search_notes(&notes_dir, args),
+        Commands::Cat(args) => commands::cat(&notes_dir, args),
+        Commands::Due(args) => commands::due(&notes_dir, args),
+        Commands::Tag(args) => commands::tag(&notes_dir, args),
+        Commands::Done(args) => commands::done(&notes_dir, args),
+        Commands::Todos(args) => commands::todos(&notes_dir, args),
+        Commands::Review(args) => commands::review(&notes_dir, args),
+        Commands::Digest(args) => commands::digest(&notes_dir, args),
+        Commands::Exec(args) => commands::exec(&notes_dir, args),
+        Commands::Export(args) => commands::export(&notes_dir, args),
+        Commands::Daemon(args) => commands::daemon_command(&notes_dir, args),
+        Commands::Mv(args) => commands::move_note(&notes_dir, args),
+        Commands::Split(args) => commands::split_note(&notes_dir, args),
+        Commands::Prune(args) => commands::prune(&notes_dir, args),
+        Commands::Profiles
+        | Commands::Config(_)
+        | Commands::Init(_)
+        | Commands::Selftest
+        | Commands::Bench(_) => {
+            unreachable!("handled before resolving the notes directory")
         }
     }
 }
@@ -2,9 +2,21 @@
 
 mod tools;
 
-pub use tools::NotelogMCP;
+pub use tools::{
+    DEFAULT_STORE, NotelogMCP, SERVER_INSTRUCTIONS, TOOL_INSTRUCTIONS, tool_instructions,
+};
+
+use std::path::PathBuf;
+use std::time::Duration;
 
 use tokio::runtime::Runtime;
+use tokio::time::Instant;
+
+use crate::core::retention::RetentionPolicy;
+use crate::db::Database;
+
+/// How long after startup a failure of the indexing stops the server
+const INDEXING_GRACE_PERIOD: Duration = Duration::from_secs(5);
 
 /// Creates a new tokio runtime for MCP operations
 pub fn create_runtime() -> Result<Runtime, std::io::Error> {
@@ -16,33 +28,81 @@ pub fn create_runtime() -> Result<Runtime, std::io::Error> {
 /// Runs the MCP server with database initialization
 ///
 /// This function creates a single Tokio runtime that handles both database initialization
-/// and running the MCP server.
-pub fn run_mcp_server_with_db<P: AsRef<std::path::Path>>(
-    notes_dir: P,
+/// and running the MCP server. Each store gets its own database, indexing, monitoring and
+/// verification, and, if `daily_backups` is given, a daily backup keeping that many copies.
+/// With a `retention` policy, the expired notes of each store are deleted once per day.
+/// With `optimize_on_exit`, `PRAGMA optimize` runs on each database after a clean shutdown.
+///
+/// If indexing the notes of a store fails within `INDEXING_GRACE_PERIOD`, the
+/// server stops with that error instead of serving an index that stays empty.
+pub fn run_mcp_server_with_db(
+    stores: Vec<(String, PathBuf)>,
+    daily_backups: Option<usize>,
+    retention: Option<RetentionPolicy>,
+    optimize_on_exit: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    use crate::db::Database;
     use crate::mcp::NotelogMCP;
 
     let rt = create_runtime()?;
 
     rt.block_on(async {
-        // Initialize the database
-        let db = Database::initialize(notes_dir.as_ref())
-            .await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+        let mut initialized_stores = Vec::with_capacity(stores.len());
+
+        for (name, notes_dir) in stores {
+            // Initialize the database
+            let db = Database::initialize(&notes_dir)
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+
+            // Start the background task to index notes
+            db.start_indexing_task()
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+
+            // Start the background task to monitor the notes directory for changes
+            db.start_monitoring_task()
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+
+            // Start the background task to catch edits that kept the modification time
+            db.start_verification_task()
+                .await
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+
+            // Start the background task to back up the database
+            if let Some(keep) = daily_backups {
+                db.start_daily_backup_task(keep)
+                    .await
+                    .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+            }
 
-        // Start the background task to index notes
-        db.start_indexing_task()
-            .await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+            // Start the background task to delete expired notes
+            if let Some(policy) = &retention {
+                db.start_daily_pruning_task(policy.clone())
+                    .await
+                    .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+            }
 
-        // Start the background task to monitor the notes directory for changes
-        db.start_monitoring_task()
-            .await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
+            initialized_stores.push((name, notes_dir, db));
+        }
 
-        // Create the NotelogMCP handler with the database
-        let handler = NotelogMCP::with_db(notes_dir, db);
+        // Keep the databases around to optimize them after the server stopped
+        let databases: Vec<Database> = if optimize_on_exit {
+            initialized_stores
+                .iter()
+                .map(|(_, _, db)| db.clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let indexing: Vec<(String, Database)> = initialized_stores
+            .iter()
+            .map(|(name, _, db)| (name.clone(), db.clone()))
+            .collect();
+
+        // Create the NotelogMCP handler with the databases
+        let handler = NotelogMCP::with_stores(initialized_stores);
 
         use rmcp::ServiceExt;
         use tokio::io::{stdin, stdout};
@@ -56,8 +116,39 @@ pub fn run_mcp_server_with_db<P: AsRef<std::path::Path>>(
         let server = handler.serve(transport).await?;
 
         // Wait for the server to complete (this will block until STDIN is closed)
-        let _quit_reason = server.waiting().await?;
+        tokio::select! {
+            quit_reason = server.waiting() => {
+                quit_reason?;
+            }
+            Err(e) = check_indexing(indexing, INDEXING_GRACE_PERIOD) => return Err(e),
+        }
+
+        for db in databases {
+            if let Err(e) = db.optimize_queries().await {
+                eprintln!("Error optimizing the database: {}", e);
+            }
+        }
 
         Ok(())
     })
 }
+
+/// Check that the indexing of each store did not fail within `grace_period`
+///
+/// Indexing that is still running at the end of the grace period is not an
+/// error; a later failure is reported by the tools instead.
+async fn check_indexing(
+    stores: Vec<(String, Database)>,
+    grace_period: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let deadline = Instant::now() + grace_period;
+
+    for (name, db) in stores {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if let Err(e) = db.wait_for_indexing(remaining).await {
+            return Err(format!("Indexing the notes of store '{}' failed: {}", name, e).into());
+        }
+    }
+
+    Ok(())
+}
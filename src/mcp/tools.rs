@@ -1,22 +1,72 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone};
 use rmcp::{
-    Error as McpError, ServerHandler,
+    Error as McpError, Peer, RoleServer, ServerHandler,
     model::{CallToolResult, Content, ServerCapabilities, ServerInfo},
-    schemars, serde_json, tool,
+    schemars::{self, JsonSchema, r#gen::SchemaGenerator, schema::Schema},
+    serde_json, tool,
 };
 
-use crate::constants::{DEFAULT_SEARCH_RESULTS, MAX_SEARCH_RESULTS};
-use crate::core::id::Id;
+use crate::config;
+use crate::constants::{
+    DEFAULT_RECENT_NOTES, DEFAULT_SEARCH_RESULTS, MAX_CONTENT_SEARCH_RESULTS,
+    MAX_CREATED_IN_FUTURE_MINUTES, MAX_FETCH_NOTES, MAX_RECENT_NOTES, MAX_SEARCH_CONTENT_KIB,
+    MAX_SEARCH_QUERY_CHARS, MAX_SEARCH_QUERY_DEPTH, MAX_SEARCH_QUERY_TERMS, MAX_SEARCH_RESULTS,
+    MAX_TAGS_PER_NOTE,
+};
+use crate::core::id::{Id, normalize_id_prefix};
+use crate::core::inline_tags::apply_inline_tags;
 use crate::core::note::Note;
 use crate::core::note_builder::NoteBuilder;
 use crate::core::tags::Tag;
-use crate::db::Database;
+use crate::core::todo::{TodoFilter, age_in_days, is_done};
+use crate::db::{
+    Database, MonthCount, PrefixMatch, SearchHit, SearchOrder, SearchQuery, due_until,
+    first_match_line, search_terms,
+};
+use crate::error::{DatabaseError, NotelogError};
+use crate::hooks::{Hook, spawn_hook};
+
+/// Instructions for the server as a whole
+pub const SERVER_INSTRUCTIONS: &str = include_str!("instructions/server.md");
+
+const ADD_NOTE_INSTRUCTIONS: &str = include_str!("instructions/add_note.md");
+const FETCH_NOTE_INSTRUCTIONS: &str = include_str!("instructions/fetch_note.md");
+const FETCH_NOTES_INSTRUCTIONS: &str = include_str!("instructions/fetch_notes.md");
+const EDIT_TAGS_INSTRUCTIONS: &str = include_str!("instructions/edit_tags.md");
+const SEARCH_NOTES_INSTRUCTIONS: &str = include_str!("instructions/search_notes.md");
+const GET_DUE_NOTES_INSTRUCTIONS: &str = include_str!("instructions/get_due_notes.md");
+const LIST_TODOS_INSTRUCTIONS: &str = include_str!("instructions/list_todos.md");
+const GET_TAG_TIMELINE_INSTRUCTIONS: &str = include_str!("instructions/get_tag_timeline.md");
+const GET_STATUS_INSTRUCTIONS: &str = include_str!("instructions/get_status.md");
+const GET_RECENT_NOTES_INSTRUCTIONS: &str = include_str!("instructions/get_recent_notes.md");
+
+/// The instructions of each tool, by tool name
+pub const TOOL_INSTRUCTIONS: &[(&str, &str)] = &[
+    ("add_note", ADD_NOTE_INSTRUCTIONS),
+    ("fetch_note", FETCH_NOTE_INSTRUCTIONS),
+    ("fetch_notes", FETCH_NOTES_INSTRUCTIONS),
+    ("edit_tags", EDIT_TAGS_INSTRUCTIONS),
+    ("search_notes", SEARCH_NOTES_INSTRUCTIONS),
+    ("get_due_notes", GET_DUE_NOTES_INSTRUCTIONS),
+    ("list_todos", LIST_TODOS_INSTRUCTIONS),
+    ("get_tag_timeline", GET_TAG_TIMELINE_INSTRUCTIONS),
+    ("get_status", GET_STATUS_INSTRUCTIONS),
+    ("get_recent_notes", GET_RECENT_NOTES_INSTRUCTIONS),
+];
+
+/// Get the instructions of the tool with the given name
+pub fn tool_instructions(name: &str) -> Option<&'static str> {
+    TOOL_INSTRUCTIONS
+        .iter()
+        .find(|(tool_name, _)| *tool_name == name)
+        .map(|(_, instructions)| *instructions)
+}
 
 /// Request structure for the AddNote tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -25,29 +75,52 @@ pub struct AddNoteRequest {
     #[schemars(description = "The content of the note in Markdown format")]
     pub content: String,
 
-    /// Optional tags for the note (up to 10)
-    #[schemars(
-        description = "Optional tags for the note (up to 10). Tags should start with '+' and can only contain lowercase letters, numbers, and dashes."
-    )]
+    // Optional tags for the note (up to MAX_TAGS_PER_NOTE).
+    #[schemars(schema_with = "add_note_tags_schema")]
     #[serde(default)]
     pub tags: Vec<String>,
+
+    // When the note was created, for notes about the past.
+    #[schemars(schema_with = "add_note_created_schema")]
+    #[serde(default)]
+    pub created: Option<String>,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
 }
 
 /// Request structure for the FetchNote tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct FetchNoteRequest {
     /// The ID prefix of the note to fetch
-    #[schemars(description = "The ID prefix of the note to fetch (string)")]
+    #[schemars(
+        description = "The ID prefix of the note to fetch (string), or 'path:' followed by its path for notes without an ID"
+    )]
     pub id: String,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
+}
+
+/// Request structure for the FetchNotes tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FetchNotesRequest {
+    // The ID prefixes of the notes to fetch (at most MAX_FETCH_NOTES).
+    #[schemars(schema_with = "fetch_notes_ids_schema")]
+    pub ids: Vec<String>,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
 }
 
 /// Request structure for the SearchNotes tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct SearchNotesRequest {
-    /// The search query string
-    #[schemars(
-        description = "Search query string. Can include content terms and/or tags with '+' prefix (e.g., '+project')."
-    )]
+    // The search query string (at most MAX_SEARCH_QUERY_CHARS characters).
+    #[schemars(schema_with = "search_notes_query_schema")]
     pub query: String,
 
     /// Optional date to filter notes created before this time (ISO8601 format)
@@ -64,19 +137,147 @@ pub struct SearchNotesRequest {
     #[serde(default)]
     pub after: Option<String>,
 
-    /// Optional limit on the number of results to return (max MAX_SEARCH_RESULTS, default DEFAULT_SEARCH_RESULTS)
+    // Optional limit on the number of results to return (max MAX_SEARCH_RESULTS, default DEFAULT_SEARCH_RESULTS).
+    #[schemars(schema_with = "search_notes_limit_schema")]
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Optional number of matching notes to skip
     #[schemars(
-        description = "Optional limit on the number of results to return (max 25, default 10). Set to 0 to only return the count of matching notes without their content."
+        description = "Optional number of matching notes to skip, e.g. to get the next page of results (default: 0)"
     )]
     #[serde(default)]
-    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+
+    /// Optional order of the results
+    #[schemars(
+        description = "Optional order of the results: 'relevance' (the default), 'newest' or 'oldest'"
+    )]
+    #[serde(default)]
+    pub order: Option<String>,
+
+    // Whether to include the content of the notes (if at most MAX_CONTENT_SEARCH_RESULTS match).
+    #[schemars(schema_with = "search_notes_include_content_schema")]
+    #[serde(default)]
+    pub include_content: bool,
+
+    /// Whether to pass the query to the fulltext index as-is
+    #[schemars(
+        description = "Optional flag to pass the query to the SQLite FTS5 MATCH operator without preprocessing (default: false). The searchable columns are `content` and `tags`."
+    )]
+    #[serde(default)]
+    pub raw: bool,
+
+    /// Optional grouping of the results
+    #[schemars(
+        description = "Optional grouping of the results: 'month' groups the notes by the month they were created in, newest month first ('oldest' order: oldest month first)"
+    )]
+    #[serde(default)]
+    pub group: Option<String>,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
+}
+
+/// Create the schema for `T` with the given description
+///
+/// schemars only accepts string literals as descriptions, so fields whose
+/// description mentions a limit from `src/constants.rs` use this to format the
+/// description at runtime instead. Such fields have no doc comment, since
+/// schemars would use that as the description.
+fn described_schema<T: JsonSchema>(generator: &mut SchemaGenerator, description: String) -> Schema {
+    let mut schema = generator.subschema_for::<T>().into_object();
+    schema.metadata().description = Some(description);
+    Schema::Object(schema)
+}
+
+/// Schema for the `tags` field of the AddNote tool
+fn add_note_tags_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<Vec<String>>(
+        generator,
+        format!(
+            "Optional tags for the note (up to {MAX_TAGS_PER_NOTE}). Tags should start with '+' and can only contain lowercase letters, numbers, and dashes."
+        ),
+    )
+}
+
+/// Schema for the `created` field of the AddNote tool
+fn add_note_created_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<Option<String>>(
+        generator,
+        format!(
+            "Optional creation time of the note, for notes about the past (RFC3339, e.g. '2025-05-06T14:30:00+02:00', or a date like '2025-05-06'). Defaults to now; at most {MAX_CREATED_IN_FUTURE_MINUTES} minutes in the future."
+        ),
+    )
+}
+
+/// Schema for the `ids` field of the FetchNotes tool
+fn fetch_notes_ids_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<Vec<String>>(
+        generator,
+        format!(
+            "The ID prefixes of the notes to fetch (at most {MAX_FETCH_NOTES}), each as for fetch_note, or 'path:' followed by its path for notes without an ID"
+        ),
+    )
+}
+
+/// Schema for the `query` field of the SearchNotes tool
+fn search_notes_query_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<String>(
+        generator,
+        format!(
+            "Search query string. Can include content terms and/or tags with '+' prefix (e.g., '+project', or 'tag:project'). At most {MAX_SEARCH_QUERY_CHARS} characters and {MAX_SEARCH_QUERY_TERMS} terms, with parentheses nested at most {MAX_SEARCH_QUERY_DEPTH} levels deep."
+        ),
+    )
+}
+
+/// Schema for the `include_content` field of the SearchNotes tool
+fn search_notes_include_content_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<bool>(
+        generator,
+        format!(
+            "Optional flag to include the full content of each note in the results if the query matches at most {MAX_CONTENT_SEARCH_RESULTS} notes (default: false)"
+        ),
+    )
+}
+
+/// Schema for the `limit` field of the SearchNotes tool
+fn search_notes_limit_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<Option<usize>>(
+        generator,
+        format!(
+            "Optional limit on the number of results to return (max {MAX_SEARCH_RESULTS}, default {DEFAULT_SEARCH_RESULTS}). Set to 0 to only return the count of matching notes (per month with `group`), without any notes."
+        ),
+    )
+}
+
+/// Schema for the `limit` field of the GetRecentNotes tool
+fn get_recent_notes_limit_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<Option<usize>>(
+        generator,
+        format!(
+            "Optional number of notes to return (max {MAX_RECENT_NOTES}, default {DEFAULT_RECENT_NOTES})"
+        ),
+    )
+}
+
+/// Description of the `store` field that every tool accepts
+const STORE_DESCRIPTION: &str = "Optional name of the notes store to use (default: \"default\"). The available stores are listed in the server instructions.";
+
+/// Schema for the `store` field of the tools, so that the field is described
+/// once here rather than on every request (see `described_schema`)
+fn store_schema(generator: &mut SchemaGenerator) -> Schema {
+    described_schema::<Option<String>>(generator, STORE_DESCRIPTION.to_string())
 }
 
 /// Request structure for the EditTags tool
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct EditTagsRequest {
     /// The ID prefix of the note to edit
-    #[schemars(description = "A unique prefix of the ID of the note to edit")]
+    #[schemars(
+        description = "A unique prefix of the ID of the note to edit, or 'path:' followed by its path for notes without an ID"
+    )]
     pub id: String,
 
     /// Tags to add to the note
@@ -92,24 +293,196 @@ pub struct EditTagsRequest {
     )]
     #[serde(default)]
     pub remove: Vec<String>,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
 }
 
-/// NotelogMCP tools for interacting with notes via MCP
-#[derive(Debug, Clone)]
-pub struct NotelogMCP {
+/// Request structure for the GetDueNotes tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetDueNotesRequest {
+    /// Only return notes due within this many days
+    #[schemars(
+        description = "Optional number of days from today; only notes due within this time (including overdue notes) are returned"
+    )]
+    #[serde(default)]
+    pub within_days: Option<u32>,
+
+    /// Only return overdue notes
+    #[schemars(description = "Optional flag to only return notes past their due date")]
+    #[serde(default)]
+    pub overdue: bool,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
+}
+
+/// Request structure for the ListTodos tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListTodosRequest {
+    /// List the notes tagged +done instead
+    #[schemars(description = "Optional flag to list the notes tagged +done instead")]
+    #[serde(default)]
+    pub done_only: bool,
+
+    /// List the notes tagged +todo or +done
+    #[schemars(description = "Optional flag to list the notes tagged +todo or +done")]
+    #[serde(default)]
+    pub all: bool,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
+}
+
+/// Request structure for the GetTagTimeline tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetTagTimelineRequest {
+    /// The tag to report on
+    #[schemars(description = "The tag to report on, with or without the leading +")]
+    pub tag: String,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
+}
+
+/// Request structure for the GetRecentNotes tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetRecentNotesRequest {
+    // Optional number of notes to return (max MAX_RECENT_NOTES, default DEFAULT_RECENT_NOTES).
+    #[schemars(schema_with = "get_recent_notes_limit_schema")]
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Only return notes with this tag
+    #[schemars(description = "Optional tag; only notes with this tag are returned")]
+    #[serde(default)]
+    pub tag: Option<String>,
+
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
+}
+
+/// Request structure for the GetStatus tool
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetStatusRequest {
+    #[schemars(schema_with = "store_schema")]
+    #[serde(default)]
+    pub store: Option<String>,
+}
+
+/// The name of the store used when a request does not name one
+pub const DEFAULT_STORE: &str = "default";
+
+/// A notes directory served by the MCP server
+#[derive(Debug)]
+struct Store {
     /// The directory where notes will be stored
     notes_dir: PathBuf,
     /// The database connection (required)
-    db: Arc<Database>,
+    db: Database,
+}
+
+/// NotelogMCP tools for interacting with notes via MCP
+#[derive(Debug, Clone)]
+pub struct NotelogMCP {
+    /// The notes stores, by name
+    stores: Arc<BTreeMap<String, Store>>,
+    /// The connected client, set by rmcp after the initialization handshake
+    peer: Option<Peer<RoleServer>>,
 }
 
 impl NotelogMCP {
     /// Create a new NotelogMCP handler with the specified notes directory and database
-    pub fn with_db<P: AsRef<Path>>(notes_dir: P, db: Database) -> Self {
+    ///
+    /// The notes directory is served as the default store.
+    #[cfg(test)]
+    pub fn with_db<P: AsRef<std::path::Path>>(notes_dir: P, db: Database) -> Self {
+        Self::with_stores(vec![(
+            DEFAULT_STORE.to_string(),
+            notes_dir.as_ref().to_path_buf(),
+            db,
+        )])
+    }
+
+    /// Create a new NotelogMCP handler serving the given named stores
+    pub fn with_stores(stores: Vec<(String, PathBuf, Database)>) -> Self {
+        let stores = stores
+            .into_iter()
+            .map(|(name, notes_dir, db)| (name, Store { notes_dir, db }))
+            .collect();
+
         Self {
-            notes_dir: notes_dir.as_ref().to_path_buf(),
-            db: Arc::new(db),
+            stores: Arc::new(stores),
+            peer: None,
+        }
+    }
+
+    /// Look up a store by name, defaulting to the default store
+    ///
+    /// Returns an error result listing the available stores if there is no
+    /// store with the given name.
+    fn store(&self, name: Option<&str>) -> Result<&Store, CallToolResult> {
+        let name = name.unwrap_or(DEFAULT_STORE);
+
+        self.stores.get(name).ok_or_else(|| {
+            tool_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "Unknown store '{}'. Available stores: {}",
+                    name,
+                    self.store_names().join(", ")
+                ),
+            )
+        })
+    }
+
+    /// Look up a store for a tool that reports on the whole index
+    ///
+    /// Like `store`, but also returns an error result if indexing the notes
+    /// of the store failed, e.g. because of a broken database schema, since
+    /// the index then stays incomplete.
+    fn indexed_store(&self, name: Option<&str>) -> Result<&Store, CallToolResult> {
+        let store = self.store(name)?;
+
+        if let Some(message) = store.db.indexing_failure() {
+            return Err(tool_error(
+                ErrorCode::Internal,
+                format!("Indexing the notes failed: {}", message),
+            ));
+        }
+
+        Ok(store)
+    }
+
+    /// Look up a store for a tool that changes notes
+    ///
+    /// Like `store`, but also returns an error result if the notes directory
+    /// of the store is unavailable, e.g. because its network share was
+    /// unmounted, instead of letting the tool fail with a confusing error.
+    fn writable_store(&self, name: Option<&str>) -> Result<&Store, CallToolResult> {
+        let store = self.store(name)?;
+
+        if !store.db.notes_dir_available() || fs::read_dir(&store.notes_dir).is_err() {
+            return Err(tool_error(
+                ErrorCode::Unavailable,
+                format!(
+                    "The notes directory {} is currently unavailable. Please try again later.",
+                    store.notes_dir.display()
+                ),
+            ));
         }
+
+        Ok(store)
+    }
+
+    /// Get the names of all stores
+    fn store_names(&self) -> Vec<&str> {
+        self.stores.keys().map(String::as_str).collect()
     }
 }
 
@@ -117,80 +490,123 @@ impl NotelogMCP {
 #[tool(tool_box)]
 impl NotelogMCP {
     /// Add a new note with the given content and tags
-    #[tool(description = include_str!("instructions/add_note.md"))]
+    #[tool(description = ADD_NOTE_INSTRUCTIONS)]
     fn add_note(&self, #[tool(aggr)] request: AddNoteRequest) -> Result<CallToolResult, McpError> {
+        let store = match self.writable_store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+
         // Validate the number of tags
-        if request.tags.len() > 10 {
-            return Ok(CallToolResult::error(vec![Content::text(
-                "Too many tags provided. Maximum is 10 tags.",
-            )]));
+        if request.tags.len() > MAX_TAGS_PER_NOTE {
+            return Ok(tool_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "Too many tags provided. Maximum is {} tags.",
+                    MAX_TAGS_PER_NOTE
+                ),
+            ));
         }
 
         // Validate the content
         if request.content.trim().is_empty() {
-            return Ok(CallToolResult::error(vec![Content::text(
+            return Ok(tool_error(
+                ErrorCode::InvalidInput,
                 "Note content cannot be empty.",
-            )]));
+            ));
         }
 
-        // Process tags
-        let mut builder = NoteBuilder::new().content(request.content).validate(true);
+        let now = Local::now();
+        let created = match &request.created {
+            Some(created) => match parse_created(created, now, config::get().min_created_year()) {
+                Ok(created) => created,
+                Err(e) => return Ok(tool_error(ErrorCode::InvalidInput, e)),
+            },
+            None => now,
+        };
+
+        // Process tags. The content is never read as frontmatter, so a pasted
+        // YAML block at its start stays part of the note.
+        let mut builder = NoteBuilder::new()
+            .content(request.content)
+            .created(created)
+            .source(self.source())
+            .validate(true);
 
         // Add tags one by one to catch and report any invalid tags
         for tag_str in &request.tags {
             match Tag::new(tag_str) {
                 Ok(tag) => builder = builder.tag(tag),
-                Err(e) => return Ok(CallToolResult::error(vec![Content::text(e.to_string())])),
+                Err(e) => return Ok(tool_error(ErrorCode::InvalidInput, e)),
             }
         }
 
-        // Build the note
-        let note = match builder.build() {
-            Ok(note) => note,
-            Err(e) => {
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Error: {}",
-                    e
-                ))]));
+        // Build the note, picking up #hashtags from the body if configured
+        let config = config::get();
+        let note = match builder.build().and_then(|note| {
+            if config.inline_tags {
+                apply_inline_tags(note, config.inline_tags_strip)
+            } else {
+                Ok(note)
             }
+        }) {
+            Ok(note) => note,
+            Err(e) => return Ok(tool_error(ErrorCode::of(&e), e)),
         };
 
         // Get the ID before saving
         let id = note.frontmatter().id().expect("Note should have an ID");
 
         // Save the note
-        match note.save(&self.notes_dir, None) {
-            Ok(_) => Ok(CallToolResult::success(vec![Content::text(format!(
-                "Note added successfully. ID: {}",
-                id
-            ))])),
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Error: {}",
-                e
-            ))])),
+        match note.save(&store.notes_dir, None) {
+            Ok(relative_path) => {
+                spawn_hook(&store.notes_dir, Hook::PostAdd, &relative_path, &note);
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Note added successfully. ID: {}, created: {}",
+                    id,
+                    note.frontmatter().created().to_rfc3339()
+                ))]))
+            }
+            Err(e) => Ok(tool_error(
+                ErrorCode::of(&e),
+                format!("Error saving the note: {}", e),
+            )),
         }
     }
 
     /// Fetch a note by its ID prefix
-    #[tool(description = include_str!("instructions/fetch_note.md"))]
+    #[tool(description = FETCH_NOTE_INSTRUCTIONS)]
     async fn fetch_note(
         &self,
         #[tool(aggr)] request: FetchNoteRequest,
     ) -> Result<CallToolResult, McpError> {
-        let db = &self.db;
+        let store = match self.store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+        let db = &store.db;
 
-        // Fetch the note by ID prefix
-        match db.fetch_note_by_id(&request.id).await {
-            Ok(Some(note)) => {
-                // Extract tags from the note using our helper method
-                let tags: Vec<String> = note.tags_as_strings();
+        // Normalize the ID prefix (trim and lowercase)
+        let note_ref = match NoteRef::parse(&request.id) {
+            Ok(note_ref) => note_ref,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid ID prefix: {}", e),
+                ));
+            }
+        };
+
+        // Fetch the note by ID prefix, or by path
+        let result = match &note_ref {
+            NoteRef::IdPrefix(id_prefix) => db.fetch_note_by_id(id_prefix).await,
+            NoteRef::Path(path) => db.fetch_note_by_filepath(path).await,
+        };
 
+        match result {
+            Ok(Some(note)) => {
                 // Create a response object with tags and content
-                let response = serde_json::json!({
-                    "id": note.frontmatter().id().unwrap().as_str(),
-                    "tags": tags,
-                    "content": note.content()
-                });
+                let response = fetched_note_json(&note, &request.id);
 
                 // Convert to string
                 let json = serde_json::to_string_pretty(&response)
@@ -198,46 +614,121 @@ impl NotelogMCP {
 
                 Ok(CallToolResult::success(vec![Content::text(json)]))
             }
-            Ok(None) => {
-                // Note not found
-                Ok(CallToolResult::success(vec![Content::text(
-                    "Note not found.",
-                )]))
-            }
-            Err(e) => {
-                // Check for the specific MultipleMatchesError
-                if let Some(error_message) = e
-                    .to_string()
-                    .strip_prefix("Database error: Multiple notes found with ID prefix ")
-                {
-                    return Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Multiple notes found with ID prefix {}. Please provide a longer prefix.",
-                        error_message
-                    ))]));
-                }
+            Ok(None) => Ok(tool_error(ErrorCode::NotFound, "Note not found.")),
+            Err(e) => Ok(lookup_error(e)),
+        }
+    }
 
-                // Generic error handling
-                Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Error fetching note: {}",
-                    e
-                ))]))
+    /// Fetch several notes by their ID prefixes
+    #[tool(description = FETCH_NOTES_INSTRUCTIONS)]
+    async fn fetch_notes(
+        &self,
+        #[tool(aggr)] request: FetchNotesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let store = match self.store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+        let db = &store.db;
+
+        if request.ids.len() > MAX_FETCH_NOTES {
+            return Ok(tool_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "At most {} notes can be fetched at once, got {}.",
+                    MAX_FETCH_NOTES,
+                    request.ids.len()
+                ),
+            ));
+        }
+
+        let note_refs: Vec<_> = request.ids.iter().map(|id| NoteRef::parse(id)).collect();
+
+        // Look up all ID prefixes at once; notes without an ID are fetched by path below
+        let prefixes: Vec<String> = note_refs
+            .iter()
+            .filter_map(|note_ref| match note_ref {
+                Ok(NoteRef::IdPrefix(prefix)) => Some(prefix.clone()),
+                _ => None,
+            })
+            .collect();
+        let mut prefix_matches = match db.fetch_notes_by_prefixes(&prefixes).await {
+            Ok(matches) => matches.into_iter(),
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::of(&e),
+                    format!("Error fetching notes: {}", e),
+                ));
             }
+        };
+
+        // One bad ID only fails its own entry, not the whole batch
+        let mut results = Vec::with_capacity(request.ids.len());
+        for (requested, note_ref) in request.ids.iter().zip(note_refs) {
+            let error = |code: ErrorCode, message: String| {
+                serde_json::json!({
+                    "id": requested.trim(),
+                    "code": code.as_str(),
+                    "error": message
+                })
+            };
+
+            let entry = match note_ref {
+                Err(e) => error(ErrorCode::InvalidInput, format!("invalid ID prefix: {}", e)),
+                Ok(NoteRef::IdPrefix(_)) => match prefix_matches.next() {
+                    Some(PrefixMatch::Found(note)) => fetched_note_json(&note, requested),
+                    Some(PrefixMatch::Ambiguous(count)) => error(
+                        ErrorCode::Ambiguous,
+                        format!("multiple matches ({})", count),
+                    ),
+                    Some(PrefixMatch::NotFound) | None => {
+                        error(ErrorCode::NotFound, "not found".to_string())
+                    }
+                },
+                Ok(NoteRef::Path(path)) => match db.fetch_note_by_filepath(&path).await {
+                    Ok(Some(note)) => fetched_note_json(&note, requested),
+                    Ok(None) => error(ErrorCode::NotFound, "not found".to_string()),
+                    Err(e) => error(ErrorCode::of(&e), e.to_string()),
+                },
+            };
+            results.push(entry);
         }
+
+        let json = serde_json::to_string_pretty(&results)
+            .unwrap_or_else(|_| "Error serializing response".to_string());
+
+        Ok(CallToolResult::success(vec![Content::text(json)]))
     }
 
     /// Edit the tags of a note
-    #[tool(description = include_str!("instructions/edit_tags.md"))]
+    #[tool(description = EDIT_TAGS_INSTRUCTIONS)]
     async fn edit_tags(
         &self,
         #[tool(aggr)] request: EditTagsRequest,
     ) -> Result<CallToolResult, McpError> {
-        let db = &self.db;
+        let store = match self.writable_store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+        let db = &store.db;
+
+        // Normalize the ID prefix (trim and lowercase)
+        let note_ref = match NoteRef::parse(&request.id) {
+            Ok(note_ref) => note_ref,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid ID prefix: {}", e),
+                ));
+            }
+        };
 
         // Validate that at least one of add or remove has tags
         if request.add.is_empty() && request.remove.is_empty() {
-            return Ok(CallToolResult::error(vec![Content::text(
+            return Ok(tool_error(
+                ErrorCode::InvalidInput,
                 "At least one tag must be specified to add or remove.",
-            )]));
+            ));
         }
 
         // Check for duplicate tags in add and remove arrays
@@ -248,10 +739,13 @@ impl NotelogMCP {
         let duplicates: Vec<String> = add_set.intersection(&remove_set).cloned().collect();
 
         if !duplicates.is_empty() {
-            return Ok(CallToolResult::error(vec![Content::text(format!(
-                "The following tags appear in both add and remove arrays: {}",
-                duplicates.join(", ")
-            ))]));
+            return Ok(tool_error(
+                ErrorCode::InvalidInput,
+                format!(
+                    "The following tags appear in both add and remove arrays: {}",
+                    duplicates.join(", ")
+                ),
+            ));
         }
 
         // Convert add tag strings to Tag objects
@@ -260,10 +754,10 @@ impl NotelogMCP {
             match Tag::new(tag_str) {
                 Ok(tag) => tags_to_add.push(tag),
                 Err(e) => {
-                    return Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Invalid tag to add: {}",
-                        e
-                    ))]));
+                    return Ok(tool_error(
+                        ErrorCode::InvalidInput,
+                        format!("Invalid tag to add: {}", e),
+                    ));
                 }
             }
         }
@@ -274,54 +768,47 @@ impl NotelogMCP {
             match Tag::new(tag_str) {
                 Ok(tag) => tags_to_remove.push(tag),
                 Err(e) => {
-                    return Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Invalid tag to remove: {}",
-                        e
-                    ))]));
+                    return Ok(tool_error(
+                        ErrorCode::InvalidInput,
+                        format!("Invalid tag to remove: {}", e),
+                    ));
                 }
             }
         }
 
-        // Get the filepath for the note
-        let filepath = match db.get_filepath_by_id_prefix(&request.id).await {
+        // Get the filepath for the note; only indexed paths are accepted
+        let lookup = match &note_ref {
+            NoteRef::IdPrefix(id_prefix) => db.get_filepath_by_id_prefix(id_prefix).await,
+            NoteRef::Path(path) => db
+                .fetch_note_by_filepath(path)
+                .await
+                .map(|note| note.map(|_| path.clone())),
+        };
+        let filepath = match lookup {
             Ok(Some(path)) => path,
             Ok(None) => {
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Note with ID prefix '{}' not found.",
-                    request.id
-                ))]));
-            }
-            Err(e) => {
-                // Check for the specific MultipleMatchesError
-                if let Some(error_message) = e
-                    .to_string()
-                    .strip_prefix("Database error: Multiple notes found with ID prefix ")
-                {
-                    return Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Multiple notes found with ID prefix {}. Please provide a longer prefix.",
-                        error_message
-                    ))]));
-                }
-
-                // Generic error handling
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Error fetching note: {}",
-                    e
-                ))]));
+                let message = match &note_ref {
+                    NoteRef::IdPrefix(id_prefix) => {
+                        format!("Note with ID prefix '{}' not found.", id_prefix)
+                    }
+                    NoteRef::Path(path) => format!("Note with path '{}' not found.", path),
+                };
+                return Ok(tool_error(ErrorCode::NotFound, message));
             }
+            Err(e) => return Ok(lookup_error(e)),
         };
 
         // Get the absolute path to the note file
-        let absolute_path = self.notes_dir.join(&filepath);
+        let absolute_path = store.notes_dir.join(&filepath);
 
         // Read the file content
         let content = match fs::read_to_string(&absolute_path) {
             Ok(content) => content,
             Err(e) => {
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Error reading note file: {}",
-                    e
-                ))]));
+                return Ok(tool_error(
+                    ErrorCode::Internal,
+                    format!("Error reading note file: {}", e),
+                ));
             }
         };
 
@@ -329,60 +816,93 @@ impl NotelogMCP {
         let mut note = match Note::from_str(&content) {
             Ok(note) => note,
             Err(e) => {
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Error parsing note: {}",
-                    e
-                ))]));
+                // The file is broken, not the request
+                return Ok(tool_error(
+                    ErrorCode::Internal,
+                    format!("Error parsing note: {}", e),
+                ));
             }
         };
 
-        // If the note doesn't have an ID, generate one
-        if note.frontmatter().id().is_none() {
+        // If the note doesn't have an ID, generate one, unless notes go without
+        let id_newly_assigned = note.frontmatter().id().is_none() && config::get().generate_ids();
+        if id_newly_assigned {
             note.frontmatter_mut().set_id(Id::default());
         }
 
         // Update the tags
-        note.update_tags(tags_to_add, tags_to_remove);
+        if let Err(e) = note.update_tags(tags_to_add, tags_to_remove) {
+            return Ok(tool_error(
+                ErrorCode::of(&e),
+                format!("Error updating tags: {}", e),
+            ));
+        }
 
         // Save the updated note
-        match fs::write(&absolute_path, note.formatted_content()) {
-            Ok(_) => {
-                // Extract tags from the updated note using our helper method
-                let tags: Vec<String> = note.tags_as_strings();
-
-                // Create a success message with the updated tags
-                let message = if tags.is_empty() {
-                    "Tags updated successfully. The note now has no tags.".to_string()
-                } else {
-                    format!(
-                        "Tags updated successfully. The note now has the following tags: {}",
-                        tags.join(", ")
-                    )
-                };
+        if let Err(e) = fs::write(&absolute_path, note.formatted_content()) {
+            return Ok(tool_error(
+                ErrorCode::Internal,
+                format!("Error writing note file: {}", e),
+            ));
+        }
+
+        spawn_hook(
+            &store.notes_dir,
+            Hook::PostEdit,
+            Path::new(&filepath),
+            &note,
+        );
+
+        // Extract tags from the updated note using our helper method
+        let tags: Vec<String> = note.tags_as_strings();
+
+        // Create a success message with the updated tags
+        let mut message = if tags.is_empty() {
+            "Tags updated successfully. The note now has no tags.".to_string()
+        } else {
+            format!(
+                "Tags updated successfully. The note now has the following tags: {}",
+                tags.join(", ")
+            )
+        };
 
-                Ok(CallToolResult::success(vec![Content::text(message)]))
+        // A newly assigned ID is not in the database yet, so report it in full
+        let id_key = match note.frontmatter().id() {
+            Some(id) if id_newly_assigned => {
+                message.push_str(&format!(
+                    "\n\nThe note had no ID and was assigned the new ID {}.",
+                    id
+                ));
+                id.as_str().to_string()
             }
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Error writing note file: {}",
-                e
-            ))])),
-        }
+            _ => note_key(db, &note, &filepath).await,
+        };
+
+        let note_data = serde_json::json!({
+            "id": id_key,
+            "id_newly_assigned": id_newly_assigned,
+            "title": note.extract_title(),
+            "filepath": filepath,
+            "tags": tags,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{}\n\n{}",
+            message, note_data
+        ))]))
     }
 
     /// Search for notes using fulltext search
-    #[tool(description = include_str!("instructions/search_notes.md"))]
+    #[tool(description = SEARCH_NOTES_INSTRUCTIONS)]
     async fn search_notes(
         &self,
         #[tool(aggr)] request: SearchNotesRequest,
     ) -> Result<CallToolResult, McpError> {
-        let db = &self.db;
-
-        // Validate that a query is provided
-        if request.query.trim().is_empty() {
-            return Ok(CallToolResult::error(vec![Content::text(
-                "A search query must be provided.",
-            )]));
-        }
+        let store = match self.indexed_store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+        let db = &store.db;
 
         // Parse before date if provided
         let before = match self.parse_date_string(&request.before, "before") {
@@ -396,140 +916,751 @@ impl NotelogMCP {
             Err(e) => return Ok(e),
         };
 
-        // Check for invalid date range
-        if let (Some(before_date), Some(after_date)) = (&before, &after) {
-            if before_date < after_date {
-                return Ok(CallToolResult::error(vec![Content::text(
-                    "'before' date must be greater than or equal to 'after' date.",
-                )]));
+        let order = match request
+            .order
+            .as_deref()
+            .map(str::parse::<SearchOrder>)
+            .transpose()
+        {
+            Ok(order) => order.unwrap_or_default(),
+            Err(e) => return Ok(tool_error(ErrorCode::InvalidInput, e)),
+        };
+
+        let group_by_month = match request.group.as_deref() {
+            None => false,
+            Some("month") => true,
+            Some(group) => {
+                return Ok(tool_error(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid grouping '{}' (expected 'month')", group),
+                ));
             }
-        }
+        };
 
         // Get the limit parameter, with default of DEFAULT_SEARCH_RESULTS if not specified
         let query_limit = request.limit.unwrap_or(DEFAULT_SEARCH_RESULTS);
 
-        // Validate the limit parameter
-        if query_limit > MAX_SEARCH_RESULTS {
-            return Ok(CallToolResult::error(vec![Content::text(format!(
-                "Limit cannot exceed {}. Please specify a lower limit.",
-                MAX_SEARCH_RESULTS
-            ))]));
+        let query = SearchQuery::new(request.query.as_str())
+            .before(before)
+            .after(after)
+            .limit(query_limit)
+            .max_limit(MAX_SEARCH_RESULTS)
+            .offset(request.offset.unwrap_or(0))
+            .order(order)
+            .include_content(request.include_content)
+            .raw(request.raw);
+
+        if let Err(e) = query.validate() {
+            return Ok(tool_error(ErrorCode::InvalidInput, e));
         }
 
-        // Search for notes with the specified query
-        let result = match db
-            .search_notes(&request.query, before, after, Some(query_limit))
-            .await
-        {
-            Ok((notes, total_count)) => {
-                // If limit is 0, only return the count
-                if query_limit == 0 {
-                    format!("The query matched {total_count} notes.")
-                } else if notes.is_empty() {
-                    // If there are no results, add a message
-                    "The query matched 0 notes.\n\nHint: You may need to try different search terms or a larger date range.".to_string()
-                } else {
-                    // Create a Vec of note data objects
-                    let mut note_results = Vec::with_capacity(notes.len());
-
-                    for note in &notes {
-                        // Get the ID from the note's frontmatter
-                        let id_key = if let Some(id) = note.frontmatter().id() {
-                            // For notes with an ID, find the shortest unique prefix
-                            match db.find_shortest_unique_id_prefix(id).await {
-                                Ok(prefix) => prefix,
-                                Err(_) => {
-                                    // If there's an error finding the prefix, use the full ID
-                                    id.as_str().to_string()
-                                }
-                            }
-                        } else {
-                            // For notes without an ID, use a placeholder value
-                            "_no_id".to_string()
-                        };
-
-                        // Extract tags from the note using our helper method
-                        let tags: Vec<String> = note.tags_as_strings();
-
-                        // Create a note data object
-                        let note_data = serde_json::json!({
-                            "id": id_key,
-                            "title": note.extract_title(),
-                            "tags": tags,
-                            "created": note.frontmatter().created().format("%Y-%m-%d").to_string()
-                        });
-
-                        note_results.push(note_data);
-                    }
-
-                    // Convert the Vec to JSON
-                    let json =
-                        serde_json::to_string(&note_results).unwrap_or_else(|_| "[]".to_string());
-
-                    // Add a message about the number of results
-                    let mut response = format!("The query matched {total_count} notes.\n\n{json}");
+        let (hits, total_count) = match db.search_hits(&query).await {
+            Ok(result) => result,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::of(&e),
+                    format!("Error searching for notes: {}", e),
+                ));
+            }
+        };
 
-                    if total_count > MAX_SEARCH_RESULTS {
-                        response.push_str("\n\nNOTE: The query matches too many notes. Be more specific with your search terms or limit the search using `before` and `after`.");
-                    }
+        let options = SearchResponseOptions {
+            count_only: query_limit == 0,
+            group_by_month,
+            order,
+        };
 
-                    response
+        // Counting by month needs all matches, not just the returned ones
+        let months = if options.count_only && group_by_month {
+            match db.search_month_counts(&query).await {
+                Ok(months) => months,
+                Err(e) => {
+                    return Ok(tool_error(
+                        ErrorCode::of(&e),
+                        format!("Error searching for notes: {}", e),
+                    ));
                 }
             }
-            Err(e) => {
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Error searching for notes: {}",
-                    e
-                ))]));
+        } else {
+            Vec::new()
+        };
+
+        // Create a Vec of note data objects
+        let mut rows = Vec::with_capacity(hits.len());
+        let terms = search_terms(&request.query);
+
+        // Content is only worth including if the query found the note(s) it was after
+        let include_content = query.includes_content() && total_count <= MAX_CONTENT_SEARCH_RESULTS;
+        let mut content_budget = MAX_SEARCH_CONTENT_KIB * 1024;
+        let mut omitted_content = 0;
+
+        for SearchHit { note, filepath, .. } in &hits {
+            // The shortest unique ID prefix, or the path for notes without an ID
+            let id_key = note_key(db, note, filepath).await;
+
+            // Extract tags from the note using our helper method
+            let tags: Vec<String> = note.tags_as_strings();
+
+            // Create a note data object
+            let mut note_data = serde_json::json!({
+                "id": id_key,
+                "title": note.extract_title(),
+                "tag_count": tags.len(),
+                "tags": tags,
+                "created": note.frontmatter().created().format("%Y-%m-%d").to_string(),
+                "line": first_match_line(&note.to_string(), &terms),
+                "word_count": note.word_count()
+            });
+            if include_content {
+                // Notes that no longer fit are left to `fetch_note`
+                let content = note.content();
+                if content.len() <= content_budget {
+                    content_budget -= content.len();
+                    note_data["content"] = content.into();
+                } else {
+                    omitted_content += 1;
+                }
             }
+
+            rows.push(note_data);
+        }
+
+        let omitted = if query.includes_content() && !include_content {
+            OmittedContent::TooManyMatches
+        } else if omitted_content > 0 {
+            OmittedContent::OverBudget(omitted_content)
+        } else {
+            OmittedContent::None
         };
 
+        let result = format_search_response(total_count, &months, rows, options, omitted);
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
-}
 
-// Implement ServerHandler for NotelogMCP
-#[tool(tool_box)]
-impl ServerHandler for NotelogMCP {
-    fn get_info(&self) -> ServerInfo {
-        let instructions = include_str!("instructions/server.md");
+    /// List the notes with a due date
+    #[tool(description = GET_DUE_NOTES_INSTRUCTIONS)]
+    async fn get_due_notes(
+        &self,
+        #[tool(aggr)] request: GetDueNotesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let store = match self.store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+        let db = &store.db;
 
-        ServerInfo {
-            instructions: Some(instructions.into()),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
-            ..Default::default()
-        }
-    }
-}
+        let today = Local::now().date_naive();
+        let until = due_until(today, request.overdue, request.within_days);
 
-impl NotelogMCP {
-    // Helper function to parse date strings.
-    // TODO: This is quite ugly.
-    fn parse_date_string(
-        &self,
-        date_str: &Option<String>,
-        field_name: &str,
-    ) -> Result<Option<DateTime<Local>>, CallToolResult> {
-        if let Some(s) = date_str {
-            match DateTime::parse_from_rfc3339(s) {
-                Ok(dt) => Ok(Some(dt.with_timezone(&Local))),
-                Err(e) => Err(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid '{}' date format: {}",
-                    field_name, e
-                ))])),
+        let notes = match db.due_notes(until).await {
+            Ok(notes) => notes,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::of(&e),
+                    format!("Error listing due notes: {}", e),
+                ));
             }
-        } else {
-            Ok(None)
+        };
+
+        let mut note_results = Vec::with_capacity(notes.len());
+        for note in &notes {
+            let id_key = match note.frontmatter().id() {
+                Some(id) => db
+                    .find_shortest_unique_id_prefix(id)
+                    .await
+                    .unwrap_or_else(|_| id.as_str().to_string()),
+                None => "_no_id".to_string(),
+            };
+            let due = note.frontmatter().due();
+
+            note_results.push(serde_json::json!({
+                "id": id_key,
+                "title": note.extract_title(),
+                "tags": note.tags_as_strings(),
+                "due": due.map(|due| due.to_string()),
+                "overdue": due.is_some_and(|due| due < today),
+            }));
         }
+
+        let json = serde_json::to_string(&note_results).unwrap_or_else(|_| "[]".to_string());
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{} notes are due (today is {}).\n\n{}",
+            notes.len(),
+            today,
+            json
+        ))]))
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// List the notes tagged +todo
+    #[tool(description = LIST_TODOS_INSTRUCTIONS)]
+    async fn list_todos(
+        &self,
+        #[tool(aggr)] request: ListTodosRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let store = match self.store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+        let db = &store.db;
+
+        let filter = TodoFilter::from_flags(request.done_only, request.all);
+        let notes = match db.notes_with_any_tag(&filter.tags()).await {
+            Ok(notes) => notes,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::of(&e),
+                    format!("Error listing todos: {}", e),
+                ));
+            }
+        };
+
+        let now = Local::now();
+        let mut note_results = Vec::with_capacity(notes.len());
+        for note in &notes {
+            let id_key = match note.frontmatter().id() {
+                Some(id) => db
+                    .find_shortest_unique_id_prefix(id)
+                    .await
+                    .unwrap_or_else(|_| id.as_str().to_string()),
+                None => "_no_id".to_string(),
+            };
+            let created = note.frontmatter().created();
+
+            note_results.push(serde_json::json!({
+                "id": id_key,
+                "title": note.extract_title(),
+                "tags": note.tags_as_strings(),
+                "created": created.format("%Y-%m-%d").to_string(),
+                "age_days": age_in_days(created, &now),
+                "done": is_done(note),
+            }));
+        }
+
+        let json = serde_json::to_string(&note_results).unwrap_or_else(|_| "[]".to_string());
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Found {} notes.\n\n{}",
+            notes.len(),
+            json
+        ))]))
+    }
+
+    /// List the most recently created notes, with the content of the newest one
+    #[tool(description = GET_RECENT_NOTES_INSTRUCTIONS)]
+    async fn get_recent_notes(
+        &self,
+        #[tool(aggr)] request: GetRecentNotesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let store = match self.store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+        let db = &store.db;
+
+        let tag = match request.tag.as_deref().map(Tag::new).transpose() {
+            Ok(tag) => tag,
+            Err(e) => return Ok(tool_error(ErrorCode::InvalidInput, e)),
+        };
+
+        let limit = request
+            .limit
+            .unwrap_or(DEFAULT_RECENT_NOTES)
+            .min(MAX_RECENT_NOTES);
+        let notes = match db.recent_notes(limit, tag.as_ref()).await {
+            Ok(notes) => notes,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::of(&e),
+                    format!("Error listing recent notes: {}", e),
+                ));
+            }
+        };
+
+        let mut note_results = Vec::with_capacity(notes.len());
+        for (i, note) in notes.iter().enumerate() {
+            let id_key = match note.frontmatter().id() {
+                Some(id) => db
+                    .find_shortest_unique_id_prefix(id)
+                    .await
+                    .unwrap_or_else(|_| id.as_str().to_string()),
+                None => "_no_id".to_string(),
+            };
+
+            let mut result = serde_json::json!({
+                "id": id_key,
+                "title": note.extract_title(),
+                "tags": note.tags_as_strings(),
+                "created": note.frontmatter().created().to_rfc3339(),
+            });
+
+            // Only the newest note comes with its content
+            if i == 0 {
+                result["content"] = serde_json::json!(note.content());
+            }
+
+            note_results.push(result);
+        }
+
+        let json = serde_json::to_string(&note_results).unwrap_or_else(|_| "[]".to_string());
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Found {} notes, newest first.\n\n{}",
+            notes.len(),
+            json
+        ))]))
+    }
+
+    /// Count the notes with a tag per month
+    #[tool(description = GET_TAG_TIMELINE_INSTRUCTIONS)]
+    async fn get_tag_timeline(
+        &self,
+        #[tool(aggr)] request: GetTagTimelineRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let store = match self.store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+
+        let tag = match Tag::new(&request.tag) {
+            Ok(tag) => tag,
+            Err(e) => return Ok(tool_error(ErrorCode::InvalidInput, e)),
+        };
+
+        let timeline = match store.db.tag_timeline(&tag).await {
+            Ok(timeline) => timeline,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::of(&e),
+                    format!("Error getting the tag timeline: {}", e),
+                ));
+            }
+        };
+
+        let json = serde_json::to_string(&timeline).unwrap_or_else(|_| "{}".to_string());
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{} notes are tagged +{}.\n\n{}",
+            timeline.total(),
+            timeline.tag,
+            json
+        ))]))
+    }
+
+    /// Report the number of indexed notes and the files that could not be read
+    #[tool(description = GET_STATUS_INSTRUCTIONS)]
+    async fn get_status(
+        &self,
+        #[tool(aggr)] request: GetStatusRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let store = match self.indexed_store(request.store.as_deref()) {
+            Ok(store) => store,
+            Err(e) => return Ok(e),
+        };
+
+        let status = async {
+            Ok::<_, NotelogError>((
+                store.db.note_count().await?,
+                store.db.failed_files().await?,
+                store.db.size()?,
+            ))
+        };
+        let (notes, failed_files, database_size) = match status.await {
+            Ok(status) => status,
+            Err(e) => {
+                return Ok(tool_error(
+                    ErrorCode::of(&e),
+                    format!("Error getting the status: {}", e),
+                ));
+            }
+        };
+
+        let available = store.db.notes_dir_available();
+        let json = serde_json::json!({
+            "notes": notes,
+            "failed_files": failed_files,
+            "database_size_bytes": database_size,
+            "notes_dir_available": available,
+            "metrics": store.db.metrics(),
+        });
+
+        let warning = if available {
+            ""
+        } else {
+            " The notes directory is currently unavailable, so notes cannot be added or changed; searches use the index as it was."
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{} notes are indexed; {} note files could not be read.{}\n\n{}",
+            notes,
+            failed_files.len(),
+            warning,
+            json
+        ))]))
+    }
+}
+
+/// The kind of a tool error
+///
+/// Error messages start with the code, e.g. `NOT_FOUND: Note not found.`, so
+/// that clients can tell requests to fix from failures to retry later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorCode {
+    /// The request is invalid, and fails again unless it is changed
+    InvalidInput,
+    /// No note matches the ID prefix or path
+    NotFound,
+    /// Several notes match the ID prefix
+    Ambiguous,
+    /// The notes directory is unavailable for now, e.g. an unmounted share
+    Unavailable,
+    /// The server failed, e.g. to read or write a file
+    Internal,
+}
+
+impl ErrorCode {
+    /// The code as it appears in error messages
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Ambiguous => "AMBIGUOUS",
+            ErrorCode::Unavailable => "UNAVAILABLE",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+
+    /// The code for an error from the rest of notelog
+    fn of(error: &NotelogError) -> Self {
+        match error {
+            NotelogError::TagError(_)
+            | NotelogError::IdError(_)
+            | NotelogError::FrontmatterError(_)
+            | NotelogError::SearchQueryError(_)
+            | NotelogError::DatabaseError(DatabaseError::InvalidSearchQuery(_))
+            | NotelogError::EmptyContent
+            | NotelogError::ContentTooLarge
+            | NotelogError::ContentContainsNullBytes => ErrorCode::InvalidInput,
+            NotelogError::NoteNotFound(_) => ErrorCode::NotFound,
+            NotelogError::DatabaseError(DatabaseError::MultipleMatches(..)) => ErrorCode::Ambiguous,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+/// A tool result for an error, with the message prefixed by its code
+fn tool_error(code: ErrorCode, message: impl std::fmt::Display) -> CallToolResult {
+    CallToolResult::error(vec![Content::text(format!(
+        "{}: {}",
+        code.as_str(),
+        message
+    ))])
+}
+
+/// A tool result for an error looking up a note by ID prefix or path
+fn lookup_error(error: NotelogError) -> CallToolResult {
+    match error {
+        NotelogError::DatabaseError(DatabaseError::MultipleMatches(prefix, count)) => tool_error(
+            ErrorCode::Ambiguous,
+            format!(
+                "Multiple notes found with ID prefix '{}': {} matches. Please provide a longer prefix.",
+                prefix, count
+            ),
+        ),
+        e => tool_error(ErrorCode::of(&e), format!("Error fetching note: {}", e)),
+    }
+}
+
+/// Parse the `created` argument of the AddNote tool
+///
+/// A date without a time is taken as the start of that day in local time.
+/// Times more than a few minutes after `now` or before `min_year` are
+/// rejected, as they are more likely mistakes than notes about the past.
+fn parse_created(
+    input: &str,
+    now: DateTime<Local>,
+    min_year: i32,
+) -> Result<DateTime<Local>, String> {
+    let input = input.trim();
+    let created = match DateTime::parse_from_rfc3339(input) {
+        Ok(created) => created.with_timezone(&Local),
+        Err(_) => NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| Local.from_local_datetime(&date.and_time(Default::default())).earliest())
+            .ok_or_else(|| {
+                format!(
+                    "Invalid 'created' time '{}' (expected RFC3339, e.g. '2025-05-06T14:30:00+02:00', or a date like '2025-05-06')",
+                    input
+                )
+            })?,
+    };
+
+    if created > now + Duration::minutes(MAX_CREATED_IN_FUTURE_MINUTES) {
+        return Err(format!(
+            "'created' cannot be in the future (got {}, now is {})",
+            created.to_rfc3339(),
+            now.to_rfc3339()
+        ));
+    }
+
+    if created.year() < min_year {
+        return Err(format!(
+            "'created' cannot be before the year {} (got {})",
+            min_year,
+            created.to_rfc3339()
+        ));
+    }
+
+    Ok(created)
+}
+
+/// Group search results by the month in their `created` field
+///
+/// The notes keep their order within each month. The months are sorted newest
+/// first, or oldest first for results in the `Oldest` order, so that grouping
+/// a page of results sorted by date does not change the order of the notes.
+fn group_results_by_month(
+    results: Vec<serde_json::Value>,
+    order: SearchOrder,
+) -> Vec<serde_json::Value> {
+    let mut groups: BTreeMap<String, Vec<serde_json::Value>> = BTreeMap::new();
+    for result in results {
+        // `created` is formatted as YYYY-MM-DD in local time
+        let created = result["created"].as_str().unwrap_or_default();
+        let month = created.get(..7).unwrap_or(created).to_string();
+        groups.entry(month).or_default().push(result);
+    }
+
+    let group = |(month, notes): (String, Vec<serde_json::Value>)| {
+        serde_json::json!({
+            "month": month,
+            "count": notes.len(),
+            "notes": notes,
+        })
+    };
+    match order {
+        SearchOrder::Oldest => groups.into_iter().map(group).collect(),
+        SearchOrder::Relevance | SearchOrder::Newest => {
+            groups.into_iter().rev().map(group).collect()
+        }
+    }
+}
+
+/// How `search_notes` presents the notes it found
+#[derive(Debug, Clone, Copy)]
+struct SearchResponseOptions {
+    /// Only report the number of matching notes (`limit: 0`)
+    count_only: bool,
+    group_by_month: bool,
+    order: SearchOrder,
+}
+
+/// Why the content asked for is missing from search results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OmittedContent {
+    None,
+    /// The query matched more than `MAX_CONTENT_SEARCH_RESULTS` notes
+    TooManyMatches,
+    /// The content of this many notes did not fit in `MAX_SEARCH_CONTENT_KIB`
+    OverBudget(usize),
+}
+
+/// Build the text of a `search_notes` response
+///
+/// A count-only search always gets the sentence with the total, followed by
+/// the `months` with their counts when grouped by month; there are no notes to
+/// format then. Any other search gets the sentence and the `rows` of the notes
+/// (grouped by month if asked), followed by notes on the limits that applied.
+fn format_search_response(
+    total: usize,
+    months: &[MonthCount],
+    rows: Vec<serde_json::Value>,
+    options: SearchResponseOptions,
+    omitted: OmittedContent,
+) -> String {
+    let mut response = format!("The query matched {total} notes.");
+
+    if options.count_only {
+        if options.group_by_month {
+            // The months are sorted like the groups of notes would be
+            let months: Vec<&MonthCount> = match options.order {
+                SearchOrder::Oldest => months.iter().collect(),
+                SearchOrder::Relevance | SearchOrder::Newest => months.iter().rev().collect(),
+            };
+            let json = serde_json::to_string(&months).unwrap_or_else(|_| "[]".to_string());
+            response.push_str(&format!("\n\n{json}"));
+        }
+        return response;
+    }
+
+    if total == 0 {
+        response.push_str(
+            "\n\nHint: You may need to try different search terms or a larger date range.",
+        );
+        return response;
+    }
+
+    let rows = if options.group_by_month {
+        group_results_by_month(rows, options.order)
+    } else {
+        rows
+    };
+    let json = serde_json::to_string(&rows).unwrap_or_else(|_| "[]".to_string());
+    response.push_str(&format!("\n\n{json}"));
+
+    if total > MAX_SEARCH_RESULTS {
+        response.push_str("\n\nNOTE: The query matches too many notes. Be more specific with your search terms or limit the search using `before` and `after`.");
+    }
+
+    match omitted {
+        OmittedContent::None => {}
+        OmittedContent::TooManyMatches => response.push_str(&format!("\n\nNOTE: The content was omitted because the query matched more than {MAX_CONTENT_SEARCH_RESULTS} notes. Use `fetch_note` to read a note, or narrow the search.")),
+        OmittedContent::OverBudget(count) => response.push_str(&format!("\n\nNOTE: The content of {count} notes was omitted to keep the response under {MAX_SEARCH_CONTENT_KIB} KiB. Use `fetch_note` to read them.")),
+    }
+
+    response
+}
+
+/// The prefix of note references that are paths instead of ID prefixes
+const PATH_PREFIX: &str = "path:";
+
+/// A note given to a tool, by ID prefix or, for notes without an ID, by path
+enum NoteRef {
+    IdPrefix(String),
+    Path(String),
+}
+
+impl NoteRef {
+    /// Parse the `id` argument of a tool
+    fn parse(input: &str) -> crate::error::Result<Self> {
+        match input.trim().strip_prefix(PATH_PREFIX) {
+            Some(path) => Ok(NoteRef::Path(path.to_string())),
+            None => normalize_id_prefix(input).map(NoteRef::IdPrefix),
+        }
+    }
+}
+
+/// The JSON object of a fetched note, with its ID, tags and content
+///
+/// A note without an ID is identified by the `path:` it was fetched by.
+fn fetched_note_json(note: &Note, requested: &str) -> serde_json::Value {
+    let id = note
+        .frontmatter()
+        .id()
+        .map_or_else(|| requested.trim().to_string(), |id| id.to_string());
+
+    serde_json::json!({
+        "id": id,
+        "tags": note.tags_as_strings(),
+        "content": note.content()
+    })
+}
+
+/// The key that identifies a note in tool results: its ID prefix, or its path
+async fn note_key(db: &Database, note: &Note, filepath: &str) -> String {
+    match note.frontmatter().id() {
+        Some(id) => db
+            .find_shortest_unique_id_prefix(id)
+            .await
+            .unwrap_or_else(|_| id.as_str().to_string()),
+        None => format!("{}{}", PATH_PREFIX, filepath),
+    }
+}
+
+/// Turn a client name like "Claude Desktop" into "claude-desktop"
+fn sanitize_client_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Implement ServerHandler for NotelogMCP
+#[tool(tool_box)]
+impl ServerHandler for NotelogMCP {
+    fn get_info(&self) -> ServerInfo {
+        let mut instructions = SERVER_INSTRUCTIONS.to_string();
+
+        // Tell the assistant where it can file notes if there is more than one store
+        if self.stores.len() > 1 {
+            instructions.push_str(&format!(
+                "\n\n## Stores\n\nNotes are kept in separate stores: {}. Every tool accepts an optional `store` argument to select the store (default: \"{}\"). Choose the store that fits the note.\n",
+                self.store_names().join(", "),
+                DEFAULT_STORE
+            ));
+        }
+
+        ServerInfo {
+            instructions: Some(instructions),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleServer>> {
+        self.peer.clone()
+    }
+
+    fn set_peer(&mut self, peer: Peer<RoleServer>) {
+        self.peer = Some(peer);
+    }
+}
+
+impl NotelogMCP {
+    /// The source recorded for notes added via MCP: `mcp`, followed by the
+    /// client name if the client sent one during the handshake
+    fn source(&self) -> String {
+        let client_name = self
+            .peer
+            .as_ref()
+            .map(|peer| sanitize_client_name(&peer.peer_info().client_info.name))
+            .unwrap_or_default();
+
+        if client_name.is_empty() {
+            "mcp".to_string()
+        } else {
+            format!("mcp:{}", client_name)
+        }
+    }
+
+    // Helper function to parse date strings.
+    // TODO: This is quite ugly.
+    fn parse_date_string(
+        &self,
+        date_str: &Option<String>,
+        field_name: &str,
+    ) -> Result<Option<DateTime<Local>>, CallToolResult> {
+        if let Some(s) = date_str {
+            match DateTime::parse_from_rfc3339(s) {
+                Ok(dt) => Ok(Some(dt.with_timezone(&Local))),
+                Err(e) => Err(tool_error(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid '{}' date format: {}", field_name, e),
+                )),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
+    use crate::core::frontmatter::Frontmatter;
+    use chrono::TimeZone;
     use tempfile::TempDir;
     use tokio::runtime::Runtime;
 
+    /// Extract the text of the first content item of a tool result
+    fn result_text(result: &CallToolResult) -> String {
+        result.content[0]
+            .as_text()
+            .map(|t| t.text.clone())
+            .unwrap_or_default()
+    }
+
     #[test]
     fn test_notelog_mcp_with_db() {
         let temp_dir = TempDir::new().unwrap();
@@ -547,7 +1678,1337 @@ mod tests {
         // Create the NotelogMCP with the database
         let notelog_mcp = NotelogMCP::with_db(temp_dir.path(), db);
 
-        // Verify the notes_dir is set correctly
-        assert_eq!(notelog_mcp.notes_dir, temp_dir.path());
+        // Verify the notes_dir is served as the default store
+        let store = notelog_mcp.store(None).unwrap();
+        assert_eq!(store.notes_dir, temp_dir.path());
+
+        // Without a connected client, notes are attributed to plain "mcp"
+        assert_eq!(notelog_mcp.source(), "mcp");
+    }
+
+    #[test]
+    fn test_multiple_stores() {
+        let work_dir = TempDir::new().unwrap();
+        let personal_dir = TempDir::new().unwrap();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let work_db = Database::initialize(work_dir.path()).await.unwrap();
+            let personal_db = Database::initialize(personal_dir.path()).await.unwrap();
+            let notelog_mcp = NotelogMCP::with_stores(vec![
+                (
+                    DEFAULT_STORE.to_string(),
+                    personal_dir.path().to_path_buf(),
+                    personal_db,
+                ),
+                ("work".to_string(), work_dir.path().to_path_buf(), work_db),
+            ]);
+
+            // The server instructions list the stores
+            let instructions = notelog_mcp.get_info().instructions.unwrap();
+            assert!(instructions.contains("default, work"));
+
+            // Notes are saved to the requested store
+            let result = notelog_mcp
+                .add_note(AddNoteRequest {
+                    content: "# Standup\nAll good.".to_string(),
+                    tags: vec![],
+                    store: Some("work".to_string()),
+                    created: None,
+                })
+                .unwrap();
+            assert_ne!(result.is_error, Some(true));
+
+            let count_notes = |dir: &std::path::Path| {
+                list_files(dir)
+                    .into_iter()
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+                    .count()
+            };
+            assert_eq!(count_notes(work_dir.path()), 1);
+            assert_eq!(count_notes(personal_dir.path()), 0);
+
+            // Unknown stores are reported with the available ones
+            let result = notelog_mcp
+                .search_notes(SearchNotesRequest {
+                    query: "standup".to_string(),
+                    before: None,
+                    after: None,
+                    limit: None,
+                    offset: None,
+                    order: None,
+                    include_content: false,
+                    raw: false,
+                    group: None,
+                    store: Some("hobby".to_string()),
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+            assert_eq!(
+                result_text(&result),
+                "INVALID_INPUT: Unknown store 'hobby'. Available stores: default, work"
+            );
+        });
+    }
+
+    /// List all files below a directory
+    fn list_files(dir: &std::path::Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(list_files(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    #[test]
+    fn test_sanitize_client_name() {
+        assert_eq!(sanitize_client_name("Claude Desktop"), "claude-desktop");
+        assert_eq!(sanitize_client_name("cursor-vscode"), "cursor-vscode");
+        assert_eq!(sanitize_client_name("  My_Client (v2) "), "my-client-v2");
+        assert_eq!(sanitize_client_name("???"), "");
+    }
+
+    #[test]
+    fn test_fetch_note_normalizes_id_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let yaml = "id: abcd1234efgh0000\ncreated: 2025-04-01T12:00:00+00:00";
+            let frontmatter = yaml.parse::<Frontmatter>().unwrap();
+            let note = Note::new(frontmatter, "# Test Note\nSome content.".to_string());
+            note.save(notes_dir, None).unwrap();
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            // Uppercase prefix with surrounding whitespace finds the note
+            let result = notelog_mcp
+                .fetch_note(FetchNoteRequest {
+                    id: "  ABCD ".to_string(),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_ne!(result.is_error, Some(true));
+            assert!(result_text(&result).contains("abcd1234efgh0000"));
+
+            // Invalid characters produce an error instead of "Note not found."
+            let result = notelog_mcp
+                .fetch_note(FetchNoteRequest {
+                    id: "_no_id".to_string(),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+            assert!(result_text(&result).starts_with("INVALID_INPUT: Invalid ID prefix"));
+        });
+    }
+
+    #[test]
+    fn test_fetch_notes_reports_errors_per_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            for id in ["abcd1234efgh0000", "abcd5678efgh0000", "wxyz1234efgh0000"] {
+                let yaml = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id);
+                let frontmatter = yaml.parse::<Frontmatter>().unwrap();
+                let note = Note::new(frontmatter, format!("# Note {}\nSome content.", id));
+                note.save(notes_dir, None).unwrap();
+            }
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let ids = ["WX", "ab", "zz", "_no_id", "abcd5"];
+            let result = notelog_mcp
+                .fetch_notes(FetchNotesRequest {
+                    ids: ids.iter().map(|id| id.to_string()).collect(),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_ne!(result.is_error, Some(true));
+
+            let results: Vec<serde_json::Value> =
+                serde_json::from_str(&result_text(&result)).unwrap();
+            assert_eq!(results.len(), ids.len());
+            assert_eq!(results[0]["id"], "wxyz1234efgh0000");
+            assert!(
+                results[0]["content"]
+                    .as_str()
+                    .unwrap()
+                    .contains("Some content.")
+            );
+            assert_eq!(
+                results[1],
+                serde_json::json!({"id": "ab", "code": "AMBIGUOUS", "error": "multiple matches (2)"})
+            );
+            assert_eq!(
+                results[2],
+                serde_json::json!({"id": "zz", "code": "NOT_FOUND", "error": "not found"})
+            );
+            assert_eq!(results[3]["id"], "_no_id");
+            assert!(
+                results[3]["error"]
+                    .as_str()
+                    .unwrap()
+                    .starts_with("invalid ID prefix")
+            );
+            assert_eq!(results[4]["id"], "abcd5678efgh0000");
+
+            // Too many IDs fail the whole call
+            let result = notelog_mcp
+                .fetch_notes(FetchNotesRequest {
+                    ids: vec!["ab".to_string(); MAX_FETCH_NOTES + 1],
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+        });
+    }
+
+    #[test]
+    fn test_edit_tags_reports_note() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let yaml = "id: abcd1234efgh0000\ncreated: 2025-04-01T12:00:00+00:00";
+            let frontmatter = yaml.parse::<Frontmatter>().unwrap();
+            let note = Note::new(frontmatter, "# Weekly Sync\nSome content.".to_string());
+            let filepath = note.save(notes_dir, None).unwrap();
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let result = notelog_mcp
+                .edit_tags(EditTagsRequest {
+                    id: "abcd".to_string(),
+                    add: vec!["+meeting".to_string()],
+                    remove: vec![],
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_ne!(result.is_error, Some(true));
+
+            let text = result_text(&result);
+            let (sentence, json) = text.split_once("\n\n").unwrap();
+            assert!(sentence.contains("meeting"));
+
+            let data: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(data["id"], "ab");
+            assert_eq!(data["id_newly_assigned"], false);
+            assert_eq!(data["title"], "Weekly Sync");
+            assert_eq!(data["filepath"], filepath.to_string_lossy().as_ref());
+            assert_eq!(data["tags"], serde_json::json!(["meeting"]));
+        });
+    }
+
+    #[test]
+    fn test_edit_tags_keeps_created_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let yaml = "id: abcd1234efgh0000\ncreated: 2025-04-01T12:00:00.250Z\ntags:\n  - draft";
+            let frontmatter = yaml.parse::<Frontmatter>().unwrap();
+            let note = Note::new(frontmatter, "# Weekly Sync\nSome content.".to_string());
+            let path = notes_dir.join(note.save(notes_dir, None).unwrap());
+            let original = fs::read_to_string(&path).unwrap();
+            assert!(original.contains("created: 2025-04-01T12:00:00.250Z\n"));
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            for (add, remove) in [("+meeting", "+draft"), ("+draft", "+meeting")] {
+                let result = notelog_mcp
+                    .edit_tags(EditTagsRequest {
+                        id: "abcd".to_string(),
+                        add: vec![add.to_string()],
+                        remove: vec![remove.to_string()],
+                        store: None,
+                    })
+                    .await
+                    .unwrap();
+                assert_ne!(result.is_error, Some(true));
+            }
+
+            assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        });
+    }
+
+    #[test]
+    fn test_search_notes_reports_line_of_match() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            // The frontmatter takes up four lines, so the match is on line 7
+            let frontmatter = "created: 2025-04-01T12:00:00+00:00"
+                .parse::<Frontmatter>()
+                .unwrap();
+            let note = Note::new(frontmatter, "# Airships\nThe zeppelin landed.".to_string());
+            let filepath = note.save(notes_dir, None).unwrap();
+            let file = fs::read_to_string(notes_dir.join(filepath)).unwrap();
+            assert_eq!(file.lines().nth(6), Some("The zeppelin landed."));
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let search = |query: &str| SearchNotesRequest {
+                query: query.to_string(),
+                before: None,
+                after: None,
+                limit: None,
+                offset: None,
+                order: None,
+                include_content: false,
+                raw: false,
+                group: None,
+                store: None,
+            };
+
+            let result = notelog_mcp.search_notes(search("zeppelin")).await.unwrap();
+            let text = result_text(&result);
+            let (_, json) = text.split_once("\n\n").unwrap();
+            let data: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(data[0]["line"], 7);
+            assert_eq!(data[0]["word_count"], 4);
+            assert_eq!(data[0]["tag_count"], 0);
+
+            // The title is on line 6
+            let result = notelog_mcp.search_notes(search("airships")).await.unwrap();
+            let text = result_text(&result);
+            let (_, json) = text.split_once("\n\n").unwrap();
+            let data: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(data[0]["line"], 6);
+        });
+    }
+
+    #[test]
+    fn test_search_notes_includes_content_of_few_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let save = |day, content: String| {
+                let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+                Note::new(Frontmatter::new(created, vec![]), content)
+                    .save(notes_dir, None)
+                    .unwrap();
+            };
+            save(1, "# Zeppelin\nThe zeppelin landed.".to_string());
+            for day in 2..=5 {
+                save(day, format!("# Balloon {}\nThe balloon took off.", day));
+            }
+            for day in 6..=7 {
+                save(day, format!("# Blimp {}\n{}", day, "blimp ".repeat(7000)));
+            }
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let search = |query: &str| SearchNotesRequest {
+                query: query.to_string(),
+                before: None,
+                after: None,
+                limit: None,
+                offset: None,
+                order: None,
+                include_content: true,
+                raw: false,
+                group: None,
+                store: None,
+            };
+            let results = |text: &str| -> serde_json::Value {
+                let json = text.split("\n\n").nth(1).unwrap();
+                serde_json::from_str(json).unwrap()
+            };
+
+            // A query that identifies a note returns its content
+            let result = notelog_mcp.search_notes(search("zeppelin")).await.unwrap();
+            let text = result_text(&result);
+            assert_eq!(
+                results(&text)[0]["content"].as_str().unwrap().trim_end(),
+                "# Zeppelin\nThe zeppelin landed."
+            );
+            assert!(!text.contains("NOTE:"));
+
+            // Broader queries only list the notes, and say why
+            let result = notelog_mcp.search_notes(search("balloon")).await.unwrap();
+            let text = result_text(&result);
+            let data = results(&text);
+            assert_eq!(data.as_array().unwrap().len(), 4);
+            assert!(data[0].get("content").is_none());
+            assert!(text.contains(&format!(
+                "matched more than {} notes",
+                MAX_CONTENT_SEARCH_RESULTS
+            )));
+
+            // Contents that would make the response too large are left out
+            let result = notelog_mcp.search_notes(search("blimp")).await.unwrap();
+            let text = result_text(&result);
+            let data = results(&text);
+            let with_content = data
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|note| note.get("content").is_some())
+                .count();
+            assert_eq!(with_content, 1);
+            assert!(text.contains("The content of 1 notes was omitted"));
+        });
+    }
+
+    #[test]
+    fn test_search_notes_grouped_by_month() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            // Two notes a minute apart, on either side of the start of May
+            for (month, day, hour, minute) in [(4, 30, 23, 59), (5, 1, 0, 0), (5, 15, 12, 0)] {
+                let created = Local
+                    .with_ymd_and_hms(2025, month, day, hour, minute, 0)
+                    .unwrap();
+                let note = Note::new(Frontmatter::new(created, vec![]), "# Kite".to_string());
+                note.save(notes_dir, None).unwrap();
+            }
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let search = |order: &str, group: &str| SearchNotesRequest {
+                query: "kite".to_string(),
+                before: None,
+                after: None,
+                limit: None,
+                offset: None,
+                order: Some(order.to_string()),
+                include_content: false,
+                raw: false,
+                group: Some(group.to_string()),
+                store: None,
+            };
+            let groups = |text: &str| -> Vec<(String, u64, Vec<String>)> {
+                let (total, json) = text.split_once("\n\n").unwrap();
+                assert_eq!(total, "The query matched 3 notes.");
+                let data: serde_json::Value = serde_json::from_str(json).unwrap();
+                data.as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|group| {
+                        let created = group["notes"]
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|note| note["created"].as_str().unwrap().to_string())
+                            .collect();
+                        (
+                            group["month"].as_str().unwrap().to_string(),
+                            group["count"].as_u64().unwrap(),
+                            created,
+                        )
+                    })
+                    .collect()
+            };
+
+            let result = notelog_mcp
+                .search_notes(search("newest", "month"))
+                .await
+                .unwrap();
+            assert_eq!(
+                groups(&result_text(&result)),
+                vec![
+                    (
+                        "2025-05".to_string(),
+                        2,
+                        vec!["2025-05-15".to_string(), "2025-05-01".to_string()]
+                    ),
+                    ("2025-04".to_string(), 1, vec!["2025-04-30".to_string()]),
+                ]
+            );
+
+            let result = notelog_mcp
+                .search_notes(search("oldest", "month"))
+                .await
+                .unwrap();
+            assert_eq!(
+                groups(&result_text(&result)),
+                vec![
+                    ("2025-04".to_string(), 1, vec!["2025-04-30".to_string()]),
+                    (
+                        "2025-05".to_string(),
+                        2,
+                        vec!["2025-05-01".to_string(), "2025-05-15".to_string()]
+                    ),
+                ]
+            );
+
+            // Count-only searches count the notes of every month
+            let result = notelog_mcp
+                .search_notes(SearchNotesRequest {
+                    limit: Some(0),
+                    ..search("newest", "month")
+                })
+                .await
+                .unwrap();
+            assert_eq!(
+                result_text(&result),
+                "The query matched 3 notes.\n\n\
+                 [{\"month\":\"2025-05\",\"count\":2},{\"month\":\"2025-04\",\"count\":1}]"
+            );
+
+            let result = notelog_mcp
+                .search_notes(search("newest", "week"))
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+        });
+    }
+
+    #[test]
+    fn test_format_search_response() {
+        let months = [
+            MonthCount {
+                month: "2025-04".to_string(),
+                count: 1,
+            },
+            MonthCount {
+                month: "2025-05".to_string(),
+                count: 2,
+            },
+        ];
+        let rows = || {
+            vec![
+                serde_json::json!({"id": "a", "created": "2025-05-15"}),
+                serde_json::json!({"id": "b", "created": "2025-04-30"}),
+            ]
+        };
+        let options = |count_only, group_by_month| SearchResponseOptions {
+            count_only,
+            group_by_month,
+            order: SearchOrder::Newest,
+        };
+        let format = |total, rows, options, omitted| {
+            format_search_response(total, &months, rows, options, omitted)
+        };
+
+        // Count-only searches get the sentence, and the months when grouped
+        assert_eq!(
+            format(3, Vec::new(), options(true, false), OmittedContent::None),
+            "The query matched 3 notes."
+        );
+        assert_eq!(
+            format(3, Vec::new(), options(true, true), OmittedContent::None),
+            "The query matched 3 notes.\n\n\
+             [{\"month\":\"2025-05\",\"count\":2},{\"month\":\"2025-04\",\"count\":1}]"
+        );
+        let oldest = SearchResponseOptions {
+            order: SearchOrder::Oldest,
+            ..options(true, true)
+        };
+        assert_eq!(
+            format(3, Vec::new(), oldest, OmittedContent::None),
+            "The query matched 3 notes.\n\n\
+             [{\"month\":\"2025-04\",\"count\":1},{\"month\":\"2025-05\",\"count\":2}]"
+        );
+        assert_eq!(
+            format_search_response(
+                0,
+                &[],
+                Vec::new(),
+                options(true, true),
+                OmittedContent::None
+            ),
+            "The query matched 0 notes.\n\n[]"
+        );
+
+        // Count-only searches are the same with too many matches
+        let total = MAX_SEARCH_RESULTS + 1;
+        for group_by_month in [false, true] {
+            let text = format(
+                total,
+                Vec::new(),
+                options(true, group_by_month),
+                OmittedContent::None,
+            );
+            assert!(!text.contains("NOTE"), "{}", text);
+        }
+
+        // Searches without matches get a hint, grouped or not
+        for group_by_month in [false, true] {
+            assert_eq!(
+                format(
+                    0,
+                    Vec::new(),
+                    options(false, group_by_month),
+                    OmittedContent::None
+                ),
+                "The query matched 0 notes.\n\nHint: You may need to try different search terms or a larger date range."
+            );
+        }
+
+        // Other searches get the notes, grouped if asked
+        assert_eq!(
+            format(2, rows(), options(false, false), OmittedContent::None),
+            "The query matched 2 notes.\n\n\
+             [{\"created\":\"2025-05-15\",\"id\":\"a\"},{\"created\":\"2025-04-30\",\"id\":\"b\"}]"
+        );
+        let text = format(2, rows(), options(false, true), OmittedContent::None);
+        let (_, json) = text.split_once("\n\n").unwrap();
+        let groups: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(groups[0]["month"], "2025-05");
+        assert_eq!(groups[0]["notes"][0]["id"], "a");
+        assert_eq!(groups[1]["month"], "2025-04");
+
+        // Followed by notes on the limits that applied
+        for group_by_month in [false, true] {
+            let options = options(false, group_by_month);
+            let text = format(total, rows(), options, OmittedContent::None);
+            assert!(text.contains("NOTE: The query matches too many notes."));
+
+            let text = format(2, rows(), options, OmittedContent::TooManyMatches);
+            assert!(text.ends_with("Use `fetch_note` to read a note, or narrow the search."));
+
+            let text = format(2, rows(), options, OmittedContent::OverBudget(1));
+            assert!(text.contains("NOTE: The content of 1 notes was omitted"));
+        }
+    }
+
+    #[test]
+    fn test_notes_without_id_are_found_by_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let frontmatter = "created: 2025-04-01T12:00:00+00:00"
+                .parse::<Frontmatter>()
+                .unwrap();
+            let note = Note::new(frontmatter, "# Airships\nThe zeppelin landed.".to_string());
+            let filepath = note.save(notes_dir, None).unwrap();
+            let filepath = filepath.to_string_lossy().to_string();
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            // The search reports the path in place of an ID
+            let result = notelog_mcp
+                .search_notes(SearchNotesRequest {
+                    query: "zeppelin".to_string(),
+                    before: None,
+                    after: None,
+                    limit: None,
+                    offset: None,
+                    order: None,
+                    include_content: false,
+                    raw: false,
+                    group: None,
+                    store: None,
+                })
+                .await
+                .unwrap();
+            let text = result_text(&result);
+            let (_, json) = text.split_once("\n\n").unwrap();
+            let data: serde_json::Value = serde_json::from_str(json).unwrap();
+            let id = data[0]["id"].as_str().unwrap().to_string();
+            assert_eq!(id, format!("path:{}", filepath));
+
+            // The path fetches the note
+            let result = notelog_mcp
+                .fetch_note(FetchNoteRequest {
+                    id: id.clone(),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            let data: serde_json::Value = serde_json::from_str(&result_text(&result)).unwrap();
+            assert_eq!(data["id"], id.as_str());
+            assert!(data["content"].as_str().unwrap().contains("zeppelin"));
+
+            // ... and edits its tags
+            let result = notelog_mcp
+                .edit_tags(EditTagsRequest {
+                    id: id.clone(),
+                    add: vec!["+airship".to_string()],
+                    remove: vec![],
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_ne!(result.is_error, Some(true), "{}", result_text(&result));
+            let file = fs::read_to_string(notes_dir.join(&filepath)).unwrap();
+            assert!(file.contains("- airship"));
+
+            // Paths that are not in the index are not found
+            for id in ["path:../outside.md", "path:2025/missing.md"] {
+                let result = notelog_mcp
+                    .fetch_note(FetchNoteRequest {
+                        id: id.to_string(),
+                        store: None,
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(result.is_error, Some(true));
+                assert_eq!(result_text(&result), "NOT_FOUND: Note not found.");
+
+                let result = notelog_mcp
+                    .edit_tags(EditTagsRequest {
+                        id: id.to_string(),
+                        add: vec!["+airship".to_string()],
+                        remove: vec![],
+                        store: None,
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(result.is_error, Some(true));
+            }
+        });
+    }
+
+    #[test]
+    fn test_search_notes_rejects_inverted_date_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let db = Database::initialize(temp_dir.path()).await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(temp_dir.path(), db);
+
+            let result = notelog_mcp
+                .search_notes(SearchNotesRequest {
+                    query: "test".to_string(),
+                    before: Some("2025-05-01T00:00:00Z".to_string()),
+                    after: Some("2025-06-01T00:00:00Z".to_string()),
+                    limit: None,
+                    offset: None,
+                    order: None,
+                    include_content: false,
+                    raw: false,
+                    group: None,
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+            assert!(result_text(&result).starts_with("INVALID_INPUT: 'before' date"));
+        });
+    }
+
+    #[test]
+    fn test_get_tag_timeline() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            for (day, tags) in [(3, vec!["deploy", "ops"]), (20, vec!["deploy"])] {
+                let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+                let tags = tags.into_iter().map(|tag| Tag::new(tag).unwrap()).collect();
+                let note = Note::new(Frontmatter::new(created, tags), "# Deploy".to_string());
+                note.save(notes_dir, None).unwrap();
+            }
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let result = notelog_mcp
+                .get_tag_timeline(GetTagTimelineRequest {
+                    tag: "+Deploy".to_string(),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            let text = result_text(&result);
+            let (summary, json) = text.split_once("\n\n").unwrap();
+            assert_eq!(summary, "2 notes are tagged +deploy.");
+
+            let json: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(
+                json["months"],
+                serde_json::json!([{"month": "2025-05", "count": 2}])
+            );
+            assert_eq!(
+                json["co_tags"],
+                serde_json::json!([{"tag": "ops", "count": 1}])
+            );
+
+            let result = notelog_mcp
+                .get_tag_timeline(GetTagTimelineRequest {
+                    tag: "two words".to_string(),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+        });
+    }
+
+    #[test]
+    fn test_get_recent_notes() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            for (title, day, tag) in [("Older", 3, "work"), ("Newer", 20, "home")] {
+                let created = Local.with_ymd_and_hms(2025, 5, day, 12, 0, 0).unwrap();
+                let tags = vec![Tag::new(tag).unwrap()];
+                let note = Note::new(Frontmatter::new(created, tags), format!("# {}", title));
+                note.save(notes_dir, None).unwrap();
+            }
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let result = notelog_mcp
+                .get_recent_notes(GetRecentNotesRequest {
+                    limit: None,
+                    tag: None,
+                    store: None,
+                })
+                .await
+                .unwrap();
+            let text = result_text(&result);
+            let (summary, json) = text.split_once("\n\n").unwrap();
+            assert_eq!(summary, "Found 2 notes, newest first.");
+
+            // Only the newest note includes its content
+            let json: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(json[0]["title"], "Newer");
+            assert_eq!(json[0]["content"].as_str().unwrap().trim_end(), "# Newer");
+            assert_eq!(json[1]["title"], "Older");
+            assert!(json[1].get("content").is_none());
+
+            let result = notelog_mcp
+                .get_recent_notes(GetRecentNotesRequest {
+                    limit: Some(1000),
+                    tag: Some("+work".to_string()),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            let text = result_text(&result);
+            assert!(text.starts_with("Found 1 notes"), "{}", text);
+            assert!(text.contains("\"tags\":[\"work\"]"), "{}", text);
+
+            let result = notelog_mcp
+                .get_recent_notes(GetRecentNotesRequest {
+                    limit: None,
+                    tag: Some("two words".to_string()),
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+        });
+    }
+
+    #[test]
+    fn test_get_status_reports_failed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let created = Local.with_ymd_and_hms(2025, 5, 1, 12, 0, 0).unwrap();
+            let note = Note::new(Frontmatter::new(created, vec![]), "# Fine".to_string());
+            note.save(notes_dir, None).unwrap();
+
+            let month_dir = notes_dir.join("2025/05_May");
+            fs::write(
+                month_dir.join("2025-05-02T12-00 Broken.md"),
+                "---\ncreated: [not a date\n---\n\n# Broken\n",
+            )
+            .unwrap();
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let result = notelog_mcp
+                .get_status(GetStatusRequest { store: None })
+                .await
+                .unwrap();
+            let text = result_text(&result);
+            let (summary, json) = text.split_once("\n\n").unwrap();
+            assert_eq!(
+                summary,
+                "1 notes are indexed; 1 note files could not be read."
+            );
+
+            let json: serde_json::Value = serde_json::from_str(json).unwrap();
+            assert_eq!(
+                json["failed_files"][0]["filepath"],
+                "2025/05_May/2025-05-02T12-00 Broken.md"
+            );
+            assert!(
+                !json["failed_files"][0]["error"]
+                    .as_str()
+                    .unwrap()
+                    .is_empty()
+            );
+            assert!(json["database_size_bytes"].as_u64().unwrap() > 0);
+            assert_eq!(json["notes_dir_available"], true);
+
+            // The broken file took part in the timing as well
+            assert_eq!(json["metrics"]["indexing_pass"]["count"], 1);
+            assert_eq!(json["metrics"]["process_note_file"]["count"], 2);
+            assert_eq!(json["metrics"]["search"]["count"], 0);
+            assert!(
+                json["metrics"]["indexing_pass"]["total_ms"]
+                    .as_f64()
+                    .unwrap()
+                    > 0.0
+            );
+        });
+    }
+
+    #[test]
+    fn test_indexing_failure_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let db = Database::initialize(notes_dir).await.unwrap();
+
+            // Break the schema behind the back of the database
+            let db_url = format!(
+                "sqlite:{}",
+                notes_dir.join(crate::db::DB_FILENAME).display()
+            );
+            let pool = sqlx::SqlitePool::connect(&db_url).await.unwrap();
+            sqlx::query("ALTER TABLE notes RENAME TO broken_notes")
+                .execute(&pool)
+                .await
+                .unwrap();
+            pool.close().await;
+
+            db.start_indexing_task().await.unwrap();
+            let stores = vec![(DEFAULT_STORE.to_string(), db.clone())];
+            let err = crate::mcp::check_indexing(stores, std::time::Duration::from_secs(10))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("no such table: notes"), "{}", err);
+
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+            let result = notelog_mcp
+                .get_status(GetStatusRequest { store: None })
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+            let text = result_text(&result);
+            assert!(
+                text.starts_with("INTERNAL: Indexing the notes failed:"),
+                "{}",
+                text
+            );
+
+            let result = notelog_mcp
+                .search_notes(SearchNotesRequest {
+                    query: "zeppelin".to_string(),
+                    before: None,
+                    after: None,
+                    limit: None,
+                    offset: None,
+                    order: None,
+                    include_content: false,
+                    raw: false,
+                    group: None,
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert!(result_text(&result).contains("no such table: notes"));
+        });
+    }
+
+    #[test]
+    fn test_indexing_within_grace_period() {
+        let temp_dir = TempDir::new().unwrap();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let db = Database::initialize(temp_dir.path()).await.unwrap();
+            db.start_indexing_task().await.unwrap();
+
+            let stores = vec![(DEFAULT_STORE.to_string(), db.clone())];
+            crate::mcp::check_indexing(stores, std::time::Duration::from_secs(10))
+                .await
+                .unwrap();
+            assert_eq!(db.indexing_failure(), None);
+        });
+    }
+
+    #[test]
+    fn test_unavailable_notes_dir_rejects_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path().join("notes");
+        let away_dir = temp_dir.path().join("unmounted");
+        fs::create_dir(&notes_dir).unwrap();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let db = Database::initialize(&notes_dir).await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(&notes_dir, db);
+            let add_note = || {
+                notelog_mcp
+                    .add_note(AddNoteRequest {
+                        content: "# Standup".to_string(),
+                        tags: vec![],
+                        store: None,
+                        created: None,
+                    })
+                    .unwrap()
+            };
+
+            fs::rename(&notes_dir, &away_dir).unwrap();
+            let result = add_note();
+            assert_eq!(result.is_error, Some(true));
+            assert!(result_text(&result).starts_with("UNAVAILABLE: "));
+            assert!(result_text(&result).contains("currently unavailable"));
+
+            let result = notelog_mcp
+                .edit_tags(EditTagsRequest {
+                    id: "abcd".to_string(),
+                    add: vec!["work".to_string()],
+                    remove: vec![],
+                    store: None,
+                })
+                .await
+                .unwrap();
+            assert!(result_text(&result).contains("currently unavailable"));
+
+            fs::rename(&away_dir, &notes_dir).unwrap();
+            assert_ne!(add_note().is_error, Some(true));
+        });
+    }
+
+    #[test]
+    fn test_tool_errors_have_codes() {
+        let temp_dir = TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            for id in ["abcd1234efgh0000", "abcd5678efgh0000"] {
+                let yaml = format!("id: {}\ncreated: 2025-04-01T12:00:00+00:00", id);
+                let frontmatter = yaml.parse::<Frontmatter>().unwrap();
+                let note = Note::new(frontmatter, format!("# Note {}", id));
+                note.save(notes_dir, None).unwrap();
+            }
+
+            let db = Database::initialize(notes_dir).await.unwrap();
+            db.index_notes().await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(notes_dir, db);
+
+            let code = |result: CallToolResult| {
+                assert_eq!(result.is_error, Some(true));
+                let text = result_text(&result);
+                text.split_once(": ")
+                    .map(|(code, _)| code.to_string())
+                    .unwrap()
+            };
+
+            // add_note
+            let add = |content: &str, tags: &[&str]| {
+                notelog_mcp
+                    .add_note(AddNoteRequest {
+                        content: content.to_string(),
+                        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                        store: None,
+                        created: None,
+                    })
+                    .unwrap()
+            };
+            assert_eq!(code(add(" ", &[])), "INVALID_INPUT");
+            assert_eq!(code(add("# Note", &["Not a tag"])), "INVALID_INPUT");
+            assert_eq!(code(add("# Note\0", &[])), "INVALID_INPUT");
+
+            // fetch_note
+            let fetch = |id: &str| {
+                notelog_mcp.fetch_note(FetchNoteRequest {
+                    id: id.to_string(),
+                    store: None,
+                })
+            };
+            assert_eq!(code(fetch("zz").await.unwrap()), "NOT_FOUND");
+            assert_eq!(code(fetch("ab").await.unwrap()), "AMBIGUOUS");
+            assert_eq!(code(fetch("_no_id").await.unwrap()), "INVALID_INPUT");
+
+            // edit_tags
+            let edit = |id: &str, add: &str| {
+                notelog_mcp.edit_tags(EditTagsRequest {
+                    id: id.to_string(),
+                    add: vec![add.to_string()],
+                    remove: vec![],
+                    store: None,
+                })
+            };
+            assert_eq!(code(edit("zz", "work").await.unwrap()), "NOT_FOUND");
+            assert_eq!(code(edit("ab", "work").await.unwrap()), "AMBIGUOUS");
+            assert_eq!(code(edit("abcd1", "Work!").await.unwrap()), "INVALID_INPUT");
+
+            // A note file that became unreadable is not the client's fault
+            let path = list_files(notes_dir)
+                .into_iter()
+                .find(|path| path.to_string_lossy().contains("Note abcd1234"))
+                .unwrap();
+            fs::write(&path, "---\ncreated: [\n---\n# Broken").unwrap();
+            assert_eq!(code(edit("abcd1", "work").await.unwrap()), "INTERNAL");
+
+            // search_notes
+            let search = |order: Option<&str>, after: Option<&str>| SearchNotesRequest {
+                query: "note".to_string(),
+                before: None,
+                after: after.map(str::to_string),
+                limit: None,
+                offset: None,
+                order: order.map(str::to_string),
+                include_content: false,
+                raw: false,
+                group: None,
+                store: None,
+            };
+            let result = notelog_mcp.search_notes(search(Some("random"), None)).await;
+            assert_eq!(code(result.unwrap()), "INVALID_INPUT");
+            let result = notelog_mcp
+                .search_notes(search(None, Some("May 1st")))
+                .await;
+            assert_eq!(code(result.unwrap()), "INVALID_INPUT");
+        });
+    }
+
+    #[test]
+    fn test_parse_created() {
+        let now = Local.with_ymd_and_hms(2025, 5, 10, 12, 0, 0).unwrap();
+
+        let created = parse_created("2025-05-06T14:30:00+02:00", now, 1970).unwrap();
+        assert_eq!(
+            created,
+            DateTime::parse_from_rfc3339("2025-05-06T14:30:00+02:00").unwrap()
+        );
+
+        // Dates are the start of the day in local time
+        let created = parse_created("2025-05-06", now, 1970).unwrap();
+        assert_eq!(
+            created,
+            Local.with_ymd_and_hms(2025, 5, 6, 0, 0, 0).unwrap()
+        );
+
+        // A few minutes in the future are fine, e.g. for clocks that are off
+        let soon = now + Duration::minutes(MAX_CREATED_IN_FUTURE_MINUTES);
+        assert!(parse_created(&soon.to_rfc3339(), now, 1970).is_ok());
+
+        let later = soon + Duration::minutes(1);
+        let error = parse_created(&later.to_rfc3339(), now, 1970).unwrap_err();
+        assert!(error.contains("future"), "{}", error);
+
+        let error = parse_created("0225-05-06", now, 1970).unwrap_err();
+        assert!(error.contains("before the year 1970"), "{}", error);
+
+        let error = parse_created("last tuesday", now, 1970).unwrap_err();
+        assert!(error.starts_with("Invalid 'created' time"), "{}", error);
+    }
+
+    #[test]
+    fn test_add_note_with_created() {
+        let temp_dir = TempDir::new().unwrap();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let db = Database::initialize(temp_dir.path()).await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(temp_dir.path(), db);
+
+            let result = notelog_mcp
+                .add_note(AddNoteRequest {
+                    content: "# Finished the report".to_string(),
+                    tags: vec![],
+                    store: None,
+                    created: Some("2025-05-06".to_string()),
+                })
+                .unwrap();
+            assert_ne!(result.is_error, Some(true));
+
+            // The note is filed under the month it was created in
+            let created = Local.with_ymd_and_hms(2025, 5, 6, 0, 0, 0).unwrap();
+            assert!(result_text(&result).ends_with(&format!("created: {}", created.to_rfc3339())));
+            let files = list_files(&temp_dir.path().join("2025/05_May"));
+            assert_eq!(files.len(), 1);
+            assert!(
+                files[0]
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with("2025-05-06T00-00")
+            );
+
+            let result = notelog_mcp
+                .add_note(AddNoteRequest {
+                    content: "# From the future".to_string(),
+                    tags: vec![],
+                    store: None,
+                    created: Some("2999-01-01".to_string()),
+                })
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+        });
+    }
+
+    #[test]
+    fn test_add_note_with_pasted_yaml() {
+        let temp_dir = TempDir::new().unwrap();
+        let rt = Runtime::new().unwrap();
+
+        rt.block_on(async {
+            let db = Database::initialize(temp_dir.path()).await.unwrap();
+            let notelog_mcp = NotelogMCP::with_db(temp_dir.path(), db);
+
+            // The content is never read as frontmatter, even if it would be valid
+            let content =
+                "---\ncreated: 2020-01-01T00:00:00+00:00\ntags: [ci]\n---\n\nThe deploy workflow";
+            let result = notelog_mcp
+                .add_note(AddNoteRequest {
+                    content: content.to_string(),
+                    tags: vec!["snippet".to_string()],
+                    store: None,
+                    created: None,
+                })
+                .unwrap();
+            assert_ne!(result.is_error, Some(true));
+
+            // The note is filed under the current month, not the pasted `created`
+            let files = list_files(temp_dir.path())
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+                .collect::<Vec<_>>();
+            assert_eq!(files.len(), 1);
+            assert!(!files[0].starts_with(temp_dir.path().join("2020")));
+            let file_name = files[0].file_name().unwrap().to_string_lossy().to_string();
+            assert!(!file_name.contains("---"), "{}", file_name);
+
+            let saved = Note::from_str(&std::fs::read_to_string(&files[0]).unwrap()).unwrap();
+            assert_eq!(saved.content().trim_end(), content);
+            assert_eq!(saved.tags_as_strings(), vec!["snippet"]);
+        });
+    }
+
+    #[test]
+    fn test_schema_descriptions_match_constants() {
+        let schema = serde_json::to_value(schemars::schema_for!(AddNoteRequest)).unwrap();
+        let description = schema["properties"]["tags"]["description"]
+            .as_str()
+            .unwrap();
+        assert!(description.contains(&format!("up to {}", MAX_TAGS_PER_NOTE)));
+        let description = schema["properties"]["created"]["description"]
+            .as_str()
+            .unwrap();
+        assert!(description.contains(&format!("{} minutes", MAX_CREATED_IN_FUTURE_MINUTES)));
+
+        let schema = serde_json::to_value(schemars::schema_for!(FetchNotesRequest)).unwrap();
+        let description = schema["properties"]["ids"]["description"].as_str().unwrap();
+        assert!(description.contains(&format!("at most {}", MAX_FETCH_NOTES)));
+
+        let schema = serde_json::to_value(schemars::schema_for!(GetRecentNotesRequest)).unwrap();
+        let description = schema["properties"]["limit"]["description"]
+            .as_str()
+            .unwrap();
+        assert!(description.contains(&format!("max {}", MAX_RECENT_NOTES)));
+        assert!(description.contains(&format!("default {}", DEFAULT_RECENT_NOTES)));
+
+        let schema = serde_json::to_value(schemars::schema_for!(SearchNotesRequest)).unwrap();
+        let description = schema["properties"]["limit"]["description"]
+            .as_str()
+            .unwrap();
+        assert!(description.contains(&format!("max {}", MAX_SEARCH_RESULTS)));
+        assert!(description.contains(&format!("default {}", DEFAULT_SEARCH_RESULTS)));
+
+        let description = schema["properties"]["include_content"]["description"]
+            .as_str()
+            .unwrap();
+        assert!(description.contains(&format!("at most {} notes", MAX_CONTENT_SEARCH_RESULTS)));
+
+        let description = schema["properties"]["query"]["description"]
+            .as_str()
+            .unwrap();
+        assert!(description.contains(&format!("{} characters", MAX_SEARCH_QUERY_CHARS)));
+        assert!(description.contains(&format!("{} terms", MAX_SEARCH_QUERY_TERMS)));
+        assert!(description.contains(&format!("{} levels", MAX_SEARCH_QUERY_DEPTH)));
+    }
+
+    #[test]
+    fn test_every_tool_has_instructions() {
+        let tools = NotelogMCP::tool_box().list();
+        assert_eq!(tools.len(), TOOL_INSTRUCTIONS.len());
+
+        for tool in tools {
+            let instructions = tool_instructions(&tool.name)
+                .unwrap_or_else(|| panic!("No instructions registered for {}", tool.name));
+            assert!(!instructions.trim().is_empty());
+            assert_eq!(tool.description, instructions);
+        }
+
+        assert!(!SERVER_INSTRUCTIONS.trim().is_empty());
+    }
+
+    #[test]
+    fn test_instructions_match_the_limits() {
+        for (instructions, limit) in [
+            (ADD_NOTE_INSTRUCTIONS, format!("up to {MAX_TAGS_PER_NOTE})")),
+            (
+                FETCH_NOTES_INSTRUCTIONS,
+                format!("at most {MAX_FETCH_NOTES})"),
+            ),
+            (
+                GET_RECENT_NOTES_INSTRUCTIONS,
+                format!("default {DEFAULT_RECENT_NOTES}, at most {MAX_RECENT_NOTES}"),
+            ),
+            (
+                SEARCH_NOTES_INSTRUCTIONS,
+                format!(
+                    "at most {MAX_SEARCH_QUERY_CHARS} characters, {MAX_SEARCH_QUERY_TERMS} terms"
+                ),
+            ),
+            (
+                SEARCH_NOTES_INSTRUCTIONS,
+                format!("nested at most {MAX_SEARCH_QUERY_DEPTH} levels"),
+            ),
+            (
+                SEARCH_NOTES_INSTRUCTIONS,
+                format!("default: {DEFAULT_SEARCH_RESULTS}, max: {MAX_SEARCH_RESULTS}"),
+            ),
+        ] {
+            assert!(instructions.contains(&limit), "missing '{}'", limit);
+        }
     }
 }
@@ -0,0 +1,307 @@
+//! Formatting summaries of the notes for output
+//!
+//! A `Digest` holds the notes of a week grouped by tag; the formatters turn it
+//! into plain text, or into an email that can be piped to `sendmail`. The rows
+//! of the tables that `list` and `search` print are fitted to the terminal.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+
+use chrono::{DateTime, Days, Local, NaiveDate, TimeZone};
+
+use notelog::core::datetime::{format_display_date, week_name};
+use notelog::core::note::Note;
+use notelog::utils::shorten_at_word;
+
+use crate::render::terminal_width;
+
+/// A note in a digest
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestEntry {
+    /// When the note was created
+    pub created: DateTime<Local>,
+    /// The title of the note
+    pub title: String,
+}
+
+/// The notes of a digest with a tag, or without tags
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestSection {
+    /// The tag, without the leading `+`, or `None` for the untagged notes
+    pub tag: Option<String>,
+    /// The notes, oldest first
+    pub entries: Vec<DigestEntry>,
+}
+
+/// The notes of a week, grouped by tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Digest {
+    /// The name of the week, e.g. `2025-W23`
+    pub week: String,
+    /// The first day of the week
+    pub first_day: NaiveDate,
+    /// The last day of the week
+    pub last_day: NaiveDate,
+    /// One section per tag in alphabetical order, then the untagged notes
+    pub sections: Vec<DigestSection>,
+    /// The number of notes, counting notes with several tags once
+    pub total_notes: usize,
+}
+
+impl Digest {
+    /// Group the notes of the week starting on `first_day` by tag
+    ///
+    /// Notes with several tags are listed under each of them.
+    pub fn new(first_day: NaiveDate, notes: &[Note]) -> Self {
+        let mut by_tag: BTreeMap<String, Vec<DigestEntry>> = BTreeMap::new();
+        let mut untagged = Vec::new();
+        for note in notes {
+            let entry = DigestEntry {
+                created: *note.frontmatter().created(),
+                title: note.extract_title(),
+            };
+
+            for tag in note.frontmatter().tags() {
+                by_tag
+                    .entry(tag.as_str().to_string())
+                    .or_default()
+                    .push(entry.clone());
+            }
+            if note.frontmatter().tags().is_empty() {
+                untagged.push(entry);
+            }
+        }
+
+        let mut sections: Vec<DigestSection> = by_tag
+            .into_iter()
+            .map(|(tag, entries)| DigestSection {
+                tag: Some(tag),
+                entries,
+            })
+            .collect();
+        if !untagged.is_empty() {
+            sections.push(DigestSection {
+                tag: None,
+                entries: untagged,
+            });
+        }
+
+        Digest {
+            week: week_name(first_day),
+            first_day,
+            last_day: first_day
+                .checked_add_days(Days::new(6))
+                .unwrap_or(first_day),
+            sections,
+            total_notes: notes.len(),
+        }
+    }
+
+    /// The number of tags, not counting the untagged notes
+    pub fn tag_count(&self) -> usize {
+        self.sections.iter().filter(|s| s.tag.is_some()).count()
+    }
+}
+
+/// Format a digest as plain text
+pub fn format_digest_text(digest: &Digest) -> String {
+    format!(
+        "NoteLog digest {} ({} to {})\n\n{}{}",
+        digest.week,
+        format_display_date(digest.first_day),
+        format_display_date(digest.last_day),
+        format_sections(digest),
+        format_totals(digest)
+    )
+}
+
+/// Format a digest as an email, with a subject line and the date `now`
+///
+/// The headers are followed by a plain-text body that ends with the totals
+/// below a signature separator, which is suitable for `sendmail -t` or `mail`.
+pub fn format_digest_email<Tz>(digest: &Digest, now: DateTime<Tz>) -> String
+where
+    Tz: TimeZone,
+    Tz::Offset: Display,
+{
+    format!(
+        "Subject: NoteLog digest {}\n\
+         Date: {}\n\
+         MIME-Version: 1.0\n\
+         Content-Type: text/plain; charset=utf-8\n\
+         Content-Transfer-Encoding: 8bit\n\
+         \n\
+         Notes from {} to {}\n\n{}-- \n{}",
+        digest.week,
+        now.to_rfc2822(),
+        format_display_date(digest.first_day),
+        format_display_date(digest.last_day),
+        format_sections(digest),
+        format_totals(digest)
+    )
+}
+
+/// The sections of a digest, each followed by an empty line
+fn format_sections(digest: &Digest) -> String {
+    if digest.sections.is_empty() {
+        return "No notes this week.\n\n".to_string();
+    }
+
+    let mut out = String::new();
+    for section in &digest.sections {
+        let heading = match &section.tag {
+            Some(tag) => format!("+{}", tag),
+            None => "Untagged".to_string(),
+        };
+        out.push_str(&format!("{} ({})\n", heading, section.entries.len()));
+        for entry in &section.entries {
+            out.push_str(&format!(
+                "  {} {} {}  {}\n",
+                entry.created.format("%a"),
+                format_display_date(entry.created.date_naive()),
+                entry.created.format("%H:%M"),
+                entry.title
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The width to fit table rows to: the terminal width if the output goes to a
+/// terminal, and `None` for pipes and files, which get the rows in full
+pub fn table_width() -> Option<usize> {
+    io::stdout().is_terminal().then(terminal_width)
+}
+
+/// Fit a table row to `width` characters, shortening its last cell (the title
+/// and tags) at a word boundary so that long titles do not wrap
+pub fn fit_row(row: String, width: Option<usize>) -> String {
+    match width {
+        Some(width) => shorten_at_word(&row, width),
+        None => row,
+    }
+}
+
+/// The line with the totals of a digest
+fn format_totals(digest: &Digest) -> String {
+    let plural = |count: usize, word: &str| {
+        format!("{} {}{}", count, word, if count == 1 { "" } else { "s" })
+    };
+
+    let untagged = digest
+        .sections
+        .iter()
+        .find(|section| section.tag.is_none())
+        .map_or(0, |section| section.entries.len());
+
+    format!(
+        "{}, {}, {} untagged\n",
+        plural(digest.total_notes, "note"),
+        plural(digest.tag_count(), "tag"),
+        untagged
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use notelog::core::frontmatter::Frontmatter;
+    use notelog::core::tags::Tag;
+
+    /// The notes of the week of 2025-06-02, oldest first
+    fn corpus() -> Vec<Note> {
+        let note = |day: u32, hour: u32, title: &str, tags: &[&str]| {
+            let created = Local.with_ymd_and_hms(2025, 6, day, hour, 0, 0).unwrap();
+            let tags = tags.iter().map(|tag| Tag::new(tag).unwrap()).collect();
+            Note::new(
+                Frontmatter::new(created, tags),
+                format!("# {}\n\nBody", title),
+            )
+        };
+
+        vec![
+            note(2, 9, "Planned the sprint", &["work"]),
+            note(3, 14, "Deployed the API", &["work", "ops"]),
+            note(4, 18, "Groceries", &[]),
+            note(6, 11, "Rolled back the API", &["ops"]),
+        ]
+    }
+
+    /// A note of about 40 KB of log output on a single line
+    fn log_dump() -> Note {
+        let created = Local.with_ymd_and_hms(2025, 6, 2, 9, 15, 0).unwrap();
+        let entry = "2025-06-02T09:14:59Z WARN upstream timed out host=10.0.0.7 retry=3 ";
+        Note::new(
+            Frontmatter::new(created, vec![Tag::new("ops").unwrap()]),
+            entry.repeat(40 * 1024 / entry.len()),
+        )
+    }
+
+    fn first_day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, 2).unwrap()
+    }
+
+    #[test]
+    fn test_digest_text_matches_golden_file() {
+        let digest = Digest::new(first_day(), &corpus());
+        assert_eq!(
+            format_digest_text(&digest),
+            include_str!("../tests/golden/digest.txt")
+        );
+    }
+
+    #[test]
+    fn test_digest_email_matches_golden_file() {
+        let digest = Digest::new(first_day(), &corpus());
+        let now = FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 6, 8, 20, 0, 0)
+            .unwrap();
+        assert_eq!(
+            format_digest_email(&digest, now),
+            include_str!("../tests/golden/digest.eml")
+        );
+    }
+
+    #[test]
+    fn test_fit_row() {
+        use crate::cli::ListFormat;
+        use crate::commands::list::format_row;
+
+        // The log dump gets a generated title, so its row is short already
+        let row = format_row("ab12", &log_dump(), ListFormat::Text);
+        assert_eq!(
+            row,
+            "ab12      2025-06-02 09:15  Note 2025-06-02 09:15 +ops"
+        );
+        assert_eq!(fit_row(row.clone(), Some(80)), row);
+
+        // Long titles are shortened at a word boundary
+        let created = Local.with_ymd_and_hms(2025, 6, 2, 9, 15, 0).unwrap();
+        let title = "Notes on the upstream timeouts of the proxy in front of the API servers";
+        let note = Note::new(Frontmatter::new(created, vec![]), format!("# {}", title));
+        let row = format_row("ab12", &note, ListFormat::Text);
+        assert_eq!(
+            fit_row(row.clone(), Some(60)),
+            "ab12      2025-06-02 09:15  Notes on the upstream timeouts…"
+        );
+        assert!(fit_row(row.clone(), Some(60)).chars().count() <= 60);
+
+        // Without a terminal, the rows are kept in full
+        assert_eq!(fit_row(row.clone(), None), row);
+    }
+
+    #[test]
+    fn test_empty_digest() {
+        let digest = Digest::new(first_day(), &[]);
+        assert_eq!(
+            format_digest_text(&digest),
+            "NoteLog digest 2025-W23 (2025-06-02 to 2025-06-08)\n\n\
+             No notes this week.\n\n\
+             0 notes, 0 tags, 0 untagged\n"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Rendering markdown with ANSI styles for the terminal
+//!
+//! This is a minimal renderer for the markdown that notes usually contain:
+//! headings, emphasis, inline code, lists, block quotes and code blocks.
+//! Everything else is shown as it is. Lines are wrapped to the given width.
+
+use std::env;
+
+/// The width to wrap to if the terminal width is unknown
+const DEFAULT_WIDTH: usize = 80;
+
+/// The narrowest width to wrap to
+const MIN_WIDTH: usize = 20;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const CYAN: &str = "\x1b[36m";
+
+/// The width of the terminal, from the `COLUMNS` environment variable
+pub fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(MIN_WIDTH)
+}
+
+/// The inline style of a piece of text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+}
+
+impl Style {
+    /// The escape codes that switch to this style
+    fn codes(self) -> String {
+        let mut codes = String::new();
+        if self.bold {
+            codes.push_str(BOLD);
+        }
+        if self.italic {
+            codes.push_str(ITALIC);
+        }
+        if self.code {
+            codes.push_str(CYAN);
+        }
+        codes
+    }
+}
+
+/// Render markdown with ANSI styles, wrapping lines to `width` characters
+pub fn render_markdown(markdown: &str, width: usize) -> String {
+    let width = width.max(MIN_WIDTH);
+    let mut out = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+
+        if in_code_block {
+            // Code is wrapped at the width, without looking for spaces
+            let chars: Vec<char> = line.chars().collect();
+            for chunk in chars.chunks(width) {
+                let chunk: String = chunk.iter().collect();
+                out.push_str(&format!("{}{}{}\n", CYAN, chunk, RESET));
+            }
+            if chars.is_empty() {
+                out.push('\n');
+            }
+            continue;
+        }
+
+        if let Some((level, heading)) = heading(trimmed) {
+            let base = if level == 1 {
+                format!("{}{}", BOLD, UNDERLINE)
+            } else {
+                BOLD.to_string()
+            };
+            out.push_str(&wrap(heading, &base, "", "", width));
+        } else if let Some((marker, item)) = list_item(trimmed) {
+            let indent = " ".repeat(line.len() - trimmed.len());
+            let first = format!("{}{} ", indent, marker);
+            let rest = " ".repeat(first.chars().count());
+            out.push_str(&wrap(item, "", &first, &rest, width));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            let prefix = format!("{}│{} ", DIM, RESET);
+            out.push_str(&wrap(quote.trim_start(), DIM, &prefix, &prefix, width));
+        } else if trimmed.is_empty() {
+            out.push('\n');
+        } else {
+            out.push_str(&wrap(trimmed, "", "", "", width));
+        }
+    }
+
+    out
+}
+
+/// The level and text of a heading line
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then(|| (level, text.trim()))
+}
+
+/// The marker to show and the text of a list item line
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some(("•".to_string(), item));
+        }
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0
+        && let Some(item) = line[digits..].strip_prefix(". ")
+    {
+        return Some((line[..digits + 1].to_string(), item));
+    }
+
+    None
+}
+
+/// A word, as pieces of text with their inline styles
+type Word = Vec<(String, Style)>;
+
+/// Split text into words with their inline styles
+///
+/// Handles `**bold**`, `__bold__`, `*italic*` and `` `code` ``; markers
+/// without a closing marker on the same line are kept as text. A change of
+/// style within a word starts a new piece of the word.
+fn styled_words(text: &str) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut word: Word = Vec::new();
+    let mut piece = String::new();
+    let mut style = Style::default();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let marker = if c == '`' {
+            Some("`")
+        } else if style.code {
+            None
+        } else if rest.starts_with("**") {
+            Some("**")
+        } else if rest.starts_with("__") {
+            Some("__")
+        } else if c == '*' {
+            Some("*")
+        } else {
+            None
+        };
+
+        if let Some(marker) = marker {
+            let after = &rest[marker.len()..];
+            let open = match marker {
+                "`" => style.code,
+                "*" => style.italic,
+                _ => style.bold,
+            };
+
+            // Only start a style that ends again
+            if open || after.contains(marker) {
+                if !piece.is_empty() {
+                    word.push((std::mem::take(&mut piece), style));
+                }
+                match marker {
+                    "`" => style.code = !style.code,
+                    "*" => style.italic = !style.italic,
+                    _ => style.bold = !style.bold,
+                }
+                rest = after;
+                continue;
+            }
+        }
+
+        if c.is_whitespace() {
+            if !piece.is_empty() {
+                word.push((std::mem::take(&mut piece), style));
+            }
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        } else {
+            piece.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if !piece.is_empty() {
+        word.push((piece, style));
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Wrap a line of text to `width`, with styled words
+///
+/// `first` and `rest` are the prefixes of the first and the following
+/// lines; `base` is a style applied to all of the text.
+fn wrap(text: &str, base: &str, first: &str, rest: &str, width: usize) -> String {
+    let visible_len = |s: &str| strip_ansi(s).chars().count();
+
+    let mut out = String::new();
+    let mut line = first.to_string();
+    let mut line_len = visible_len(first);
+    let mut line_empty = true;
+
+    for word in styled_words(text) {
+        let word_len: usize = word.iter().map(|(piece, _)| piece.chars().count()).sum();
+
+        if !line_empty && line_len + 1 + word_len > width {
+            out.push_str(&line);
+            out.push('\n');
+            line = rest.to_string();
+            line_len = visible_len(rest);
+        } else if !line_empty {
+            line.push(' ');
+            line_len += 1;
+        }
+
+        for (piece, style) in word {
+            // Split words that are longer than a line
+            let mut chars: Vec<char> = piece.chars().collect();
+            while line_len + chars.len() > width && line_len < width {
+                let head: String = chars.drain(..width - line_len).collect();
+                line.push_str(&format!("{}{}{}{}", base, style.codes(), head, RESET));
+                out.push_str(&line);
+                out.push('\n');
+                line = rest.to_string();
+                line_len = visible_len(rest);
+            }
+            let tail: String = chars.iter().collect();
+            line_len += chars.len();
+            line.push_str(&format!("{}{}{}{}", base, style.codes(), tail, RESET));
+        }
+        line_empty = false;
+    }
+
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
+
+/// Remove ANSI escape sequences from a string
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip up to and including the final letter of the sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_markdown() {
+        let rendered = render_markdown(
+            "# Title\n\nSome **bold** and *italic* text with `code`.\n\n- one\n- two\n\n```\nlet x = 1;\n```\n",
+            80,
+        );
+        assert_eq!(
+            strip_ansi(&rendered),
+            "Title\n\nSome bold and italic text with code.\n\n• one\n• two\n\nlet x = 1;\n"
+        );
+        assert!(rendered.contains(&format!("{}bold{}", BOLD, RESET)));
+        assert!(rendered.contains(&format!("{}italic{}", ITALIC, RESET)));
+        assert!(rendered.contains(&format!("{}code{}", CYAN, RESET)));
+    }
+
+    #[test]
+    fn test_long_lines_wrap() {
+        let rendered = strip_ansi(&render_markdown(
+            &format!("- {}\n\n{}\n", "word ".repeat(10), "x".repeat(50)),
+            20,
+        ));
+        for line in rendered.lines() {
+            assert!(line.chars().count() <= 20, "{:?}", line);
+        }
+        assert!(rendered.starts_with("• word word word\n  word word"));
+        assert!(rendered.ends_with(&format!(
+            "{}\n{}\n{}\n",
+            "x".repeat(20),
+            "x".repeat(20),
+            "x".repeat(10)
+        )));
+    }
+
+    #[test]
+    fn test_unclosed_markers_are_text() {
+        let rendered = strip_ansi(&render_markdown("2 * 3 = 6 and snake_case", 80));
+        assert_eq!(rendered, "2 * 3 = 6 and snake_case\n");
+    }
+}
@@ -0,0 +1,122 @@
+//! Synthetic notes for benchmarks
+//!
+//! The generated notes roughly follow a real log: most notes are a few
+//! sentences long and a few are long, a handful of tags are used all the time
+//! and most others rarely, and the notes are spread over three years. The
+//! content depends only on the seed, so numbers can be compared across runs.
+
+use std::path::Path;
+
+use chrono::{Duration, Local, TimeZone};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::core::frontmatter::Frontmatter;
+use crate::core::note::Note;
+use crate::core::tags::Tag;
+use crate::error::Result;
+
+/// Tags, from the most to the least frequently used
+const TAGS: [&str; 24] = [
+    "work", "todo", "done", "idea", "meeting", "til", "project", "personal", "reading", "bug",
+    "release", "health", "travel", "finance", "rust", "sqlite", "recipe", "family", "garden",
+    "music", "review", "draft", "ops", "someday",
+];
+
+/// Words for the content, from the most to the least frequent
+const WORDS: &str = "\
+the and to of a in is that for it with on we was this be not are at from note today \
+meeting should build check fixed plan release team server database index search deploy \
+review notes config editor query tested migration backup weekly garden recipe budget \
+travel reading chapter invoice dentist birthday benchmark latency regression kubernetes \
+postgres flamegraph tokenizer allocation compaction quarterly zeppelin";
+
+/// A word that is used in exactly one note, for narrow searches
+pub const RARE_WORD: &str = "xylophone";
+
+/// Pick an index in `0..len`, preferring small indices (roughly Zipf-distributed)
+fn skewed_index(rng: &mut StdRng, len: usize) -> usize {
+    let x: f64 = rng.random();
+    ((len as f64).powf(x) - 1.0) as usize % len
+}
+
+/// Generate a body of `count` words, in paragraphs of 60 words
+fn generate_body(rng: &mut StdRng, vocabulary: &[&str], count: usize) -> String {
+    let mut body = String::new();
+    for i in 0..count {
+        if i > 0 {
+            body.push_str(if i % 60 == 0 { "\n\n" } else { " " });
+        }
+        body.push_str(vocabulary[skewed_index(rng, vocabulary.len())]);
+    }
+    body
+}
+
+/// Write `count` synthetic notes into the notes directory
+pub fn generate_corpus(notes_dir: &Path, count: usize, seed: u64) -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let vocabulary: Vec<&str> = WORDS.split_whitespace().collect();
+    let start = Local.with_ymd_and_hms(2022, 1, 1, 8, 0, 0).unwrap();
+    let span_minutes = 3 * 365 * 24 * 60;
+
+    for i in 0..count {
+        let created = start + Duration::minutes(rng.random_range(0..span_minutes));
+
+        // Up to four distinct tags, mostly the common ones
+        let mut frontmatter = Frontmatter::new(created, Vec::new());
+        for _ in 0..rng.random_range(0..=4) {
+            frontmatter.add_tag(Tag::new(TAGS[skewed_index(&mut rng, TAGS.len())])?)?;
+        }
+
+        // Nine in ten notes are short, the rest are a few pages long
+        let words = if rng.random_ratio(9, 10) {
+            rng.random_range(5..80)
+        } else {
+            rng.random_range(300..2000)
+        };
+
+        let title = format!("{} {}", generate_body(&mut rng, &vocabulary, 4), i);
+        let mut content = format!(
+            "# {}\n\n{}",
+            title,
+            generate_body(&mut rng, &vocabulary, words)
+        );
+        if i == count / 2 {
+            content.push_str(&format!(" {}", RARE_WORD));
+        }
+
+        Note::new(frontmatter, content).save(notes_dir, None)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_corpus() {
+        let temp_dir = TempDir::new().unwrap();
+        generate_corpus(temp_dir.path(), 20, 7).unwrap();
+
+        let notes: Vec<_> = walk(temp_dir.path());
+        assert_eq!(notes.len(), 20);
+        assert!(notes.iter().any(|content| content.contains(RARE_WORD)));
+    }
+
+    /// Read all note files below a directory
+    fn walk(dir: &Path) -> Vec<String> {
+        let mut notes = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                notes.extend(walk(&path));
+            } else if path.extension().is_some_and(|ext| ext == "md") {
+                notes.push(std::fs::read_to_string(path).unwrap());
+            }
+        }
+        notes
+    }
+}
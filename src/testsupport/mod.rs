@@ -0,0 +1,3 @@
+//! Support code for benchmarks and tests
+
+pub mod corpus;
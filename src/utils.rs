@@ -1,71 +1,279 @@
 use std::env;
+use std::fmt;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::str::FromStr;
 
-use chrono::{DateTime, Datelike, Local};
+use chrono::{DateTime, Datelike, TimeZone};
 use dirs::home_dir;
 use tempfile::NamedTempFile;
 
+use crate::config::{self, MonthDirStyle, Resolved, SettingSource};
 use crate::constants::MAX_FILE_SIZE_BYTES;
+use crate::core::note::Note;
+use crate::db::RESERVED_DIRS;
 use crate::error::{NotelogError, Result};
+use crate::hooks::{Hook, run_hook};
+
+/// Why a file is not indexed as a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file does not have one of the note extensions (.md by default)
+    WrongExtension,
+    /// The filename does not start with a year, e.g. README.md or monthly rollups
+    NotANoteFilename,
+    /// The file is larger than MAX_FILE_SIZE_BYTES
+    TooLarge,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::WrongExtension => write!(f, "wrong extension"),
+            SkipReason::NotANoteFilename => write!(f, "not a note filename"),
+            SkipReason::TooLarge => write!(f, "too large"),
+        }
+    }
+}
+
+/// Determine why a file with the given path and size is not a note file, if it isn't
+///
+/// This only looks at the path and size, not at the file itself. The note
+/// extensions are those of the `note_extensions` setting.
+pub fn note_file_skip_reason(path: &Path, size: u64) -> Option<SkipReason> {
+    note_file_skip_reason_with(path, size, &config::get().note_extensions())
+}
+
+/// Like `note_file_skip_reason`, with the given note extensions
+pub fn note_file_skip_reason_with(
+    path: &Path,
+    size: u64,
+    extensions: &[String],
+) -> Option<SkipReason> {
+    // Check if it has one of the note extensions
+    if path
+        .extension()
+        .is_none_or(|ext| !extensions.iter().any(|allowed| ext == allowed.as_str()))
+    {
+        return Some(SkipReason::WrongExtension);
+    }
+
+    // Only include files that start with '1' or '2' (for year 1xxx or 2xxx)
+    // This assumes the program won't be used for notes in the year 3000
+    let filename = path.file_name()?.to_string_lossy();
+    if !filename.starts_with('1') && !filename.starts_with('2') {
+        return Some(SkipReason::NotANoteFilename);
+    }
+
+    // Check file size (must be less than MAX_FILE_SIZE_BYTES)
+    if size > MAX_FILE_SIZE_BYTES as u64 {
+        return Some(SkipReason::TooLarge);
+    }
+
+    None
+}
 
 /// Check if a file path is a valid note file
 ///
 /// A valid note file must:
-/// - Have a .md extension
+/// - Have one of the note extensions (.md by default)
 /// - Have a filename that starts with '1' or '2' (for year 1xxx or 2xxx)
 ///   to filter out non-note files like README.md or monthly rollups
 /// - Be less than MAX_FILE_SIZE_BYTES in size
 pub fn is_valid_note_file(path: &Path) -> Result<bool> {
-    // Check if it's a markdown file
-    if let Some(ext) = path.extension() {
-        if ext != "md" {
-            return Ok(false);
-        }
-    } else {
+    // If we can't get the metadata, consider it invalid
+    let Ok(metadata) = fs::metadata(path) else {
         return Ok(false);
-    }
+    };
 
-    // Check if the filename starts with a date pattern
-    if let Some(filename) = path.file_name() {
-        let filename_str = filename.to_string_lossy();
-        // Only include files that start with '1' or '2' (for year 1xxx or 2xxx)
-        // This assumes the program won't be used for notes in the year 3000
-        if !filename_str.starts_with('1') && !filename_str.starts_with('2') {
-            return Ok(false);
-        }
-    } else {
-        return Ok(false);
-    }
+    Ok(note_file_skip_reason(path, metadata.len()).is_none())
+}
 
-    // Check file size (must be less than MAX_FILE_SIZE_BYTES)
-    if let Ok(metadata) = fs::metadata(path) {
-        let file_size = metadata.len();
-        if file_size > MAX_FILE_SIZE_BYTES as u64 {
-            return Ok(false);
-        }
-    } else {
-        // If we can't get the metadata, consider it invalid
-        return Ok(false);
-    }
+/// The places the notes directory is looked up, in order of precedence
+pub const NOTES_DIR_RESOLUTION_ORDER: &str =
+    "--notes-dir, --profile/NOTELOG_PROFILE, NOTELOG_DIR, the configuration file, ~/NoteLog";
 
-    Ok(true)
-}
+/// Determine the notes directory
+///
+/// The directory is taken from, in order of precedence: the provided path, an
+/// explicitly selected profile, the NOTELOG_DIR environment variable, the
+/// configuration file, and finally the default of ~/NoteLog.
+pub fn get_notes_dir(notes_dir: Option<PathBuf>) -> Result<Resolved<PathBuf>> {
+    let config = config::get();
 
-/// Determine the notes directory from the provided path, environment variable, or default
-pub fn get_notes_dir(notes_dir: Option<PathBuf>) -> Result<PathBuf> {
     notes_dir
-        .or_else(|| env::var("NOTELOG_DIR").map(PathBuf::from).ok())
-        .or_else(|| home_dir().map(|p| p.join("NoteLog")))
+        .map(|p| Resolved::new(p, SettingSource::Flag))
+        .or_else(|| {
+            config
+                .profile_notes_dir()
+                .map(|p| Resolved::new(p, SettingSource::Profile))
+        })
+        .or_else(|| {
+            env::var("NOTELOG_DIR")
+                .map(|p| Resolved::new(PathBuf::from(p), SettingSource::Environment))
+                .ok()
+        })
+        .or_else(|| {
+            config
+                .file_notes_dir()
+                .map(|p| Resolved::new(p, SettingSource::ConfigFile))
+        })
+        .or_else(|| home_dir().map(|p| Resolved::new(p.join("NoteLog"), SettingSource::Default)))
         .ok_or_else(|| {
             NotelogError::NotesDirectoryNotFound("Could not determine home directory".to_string())
         })
 }
 
+/// Offer to create the default notes directory on first run
+///
+/// Only applies if the notes directory is the default ~/NoteLog and does not
+/// exist yet. If stdin and stderr are terminals, the user is asked whether to
+/// create it; otherwise an error listing where the directory was looked for is
+/// returned.
+pub fn ensure_default_notes_dir(notes_dir: &Path, source: SettingSource) -> Result<()> {
+    if source != SettingSource::Default || notes_dir.exists() {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Err(default_notes_dir_not_found(notes_dir));
+    }
+
+    let mut stderr = io::stderr();
+    if !confirm_create_notes_dir(&mut io::stdin().lock(), &mut stderr, notes_dir)? {
+        writeln!(
+            stderr,
+            "To use a different directory, pass --notes-dir or add this to your shell profile:\n\n    export NOTELOG_DIR=\"/path/to/your/notes\"\n"
+        )?;
+        return Err(default_notes_dir_not_found(notes_dir));
+    }
+
+    fs::create_dir_all(notes_dir)?;
+    writeln!(
+        stderr,
+        "Created {}. To keep using it when other settings change, add this to your shell profile:\n\n    export NOTELOG_DIR=\"{}\"\n",
+        notes_dir.display(),
+        notes_dir.display()
+    )?;
+
+    Ok(())
+}
+
+/// Ask whether the notes directory should be created
+///
+/// Anything but `y` or `yes` (in any case) counts as no.
+fn confirm_create_notes_dir(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    notes_dir: &Path,
+) -> io::Result<bool> {
+    write!(
+        output,
+        "Create {} as your notes directory? [y/N] ",
+        notes_dir.display()
+    )?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The error for a missing default notes directory
+fn default_notes_dir_not_found(notes_dir: &Path) -> NotelogError {
+    NotelogError::NotesDirectoryNotFound(format!(
+        "Directory does not exist: {} (no notes directory was configured; looked in {})",
+        notes_dir.display(),
+        NOTES_DIR_RESOLUTION_ORDER
+    ))
+}
+
+/// Shorten a string to at most `max_chars` characters
+///
+/// Counts characters rather than bytes, so multi-byte characters such as
+/// emoji or CJK text are never cut in half.
+pub fn truncate_chars(s: &str, max_chars: usize) -> &str {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+/// Shorten a string to at most `max_chars` characters at a word boundary,
+/// ending it with `…` if it was shortened
+///
+/// A word that would leave less than half of the space used, like a long
+/// line of pasted output without spaces, is cut in the middle instead.
+pub fn shorten_at_word(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+
+    // Leave room for the ellipsis
+    let cut = truncate_chars(s, max_chars - 1);
+    let kept = if s[cut.len()..].starts_with(char::is_whitespace) {
+        cut
+    } else {
+        match cut.rfind(char::is_whitespace) {
+            Some(end) if cut[..end].chars().count() >= max_chars / 2 => &cut[..end],
+            _ => cut,
+        }
+    };
+
+    format!("{}…", kept.trim_end())
+}
+
+/// Shorten a string to at most `max_bytes` bytes, without splitting a character
+fn truncate_bytes(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+
+    let end = s
+        .char_indices()
+        .map(|(i, _)| i)
+        .take_while(|i| *i <= max_bytes)
+        .last()
+        .unwrap_or(0);
+
+    &s[..end]
+}
+
 /// Generate a valid filename from a title
-pub fn generate_filename(date: &DateTime<Local>, title: &str, counter: Option<usize>) -> String {
+///
+/// The filename is at most `max_filename_bytes` bytes long (200 by default).
+/// The timestamp is written in the time zone of `date`; notes use their
+/// creation time in local time.
+pub fn generate_filename<Tz: TimeZone>(
+    date: &DateTime<Tz>,
+    title: &str,
+    counter: Option<usize>,
+) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    generate_filename_with_limit(date, title, counter, config::get().max_filename_bytes())
+}
+
+/// Generate a valid filename from a title, at most `max_bytes` bytes long
+///
+/// Longer titles are shortened at a character boundary, so that the counter
+/// and the extension still fit.
+pub fn generate_filename_with_limit<Tz: TimeZone>(
+    date: &DateTime<Tz>,
+    title: &str,
+    counter: Option<usize>,
+    max_bytes: usize,
+) -> String
+where
+    Tz::Offset: fmt::Display,
+{
     let date_str = date.format("%Y-%m-%dT%H-%M").to_string();
 
     // Sanitize the title for use in a filename
@@ -77,12 +285,21 @@ pub fn generate_filename(date: &DateTime<Local>, title: &str, counter: Option<us
         })
         .collect::<String>();
 
-    // Add counter if provided
-    if let Some(counter) = counter {
-        format!("{} {} ({}).md", date_str, sanitized_title, counter)
+    let suffix = match counter {
+        Some(counter) => format!(" ({}).md", counter),
+        None => ".md".to_string(),
+    };
+
+    // Leave room for the timestamp, the space after it and the suffix
+    let title_budget = max_bytes.saturating_sub(date_str.len() + 1 + suffix.len());
+    let title = if sanitized_title.len() > title_budget {
+        truncate_bytes(&sanitized_title, title_budget)
+            .trim_end_matches(|c: char| c.is_whitespace() || c == '-')
     } else {
-        format!("{} {}.md", date_str, sanitized_title)
-    }
+        &sanitized_title
+    };
+
+    format!("{} {}{}", date_str, title, suffix)
 }
 
 /// Check if content is valid
@@ -105,36 +322,378 @@ pub fn validate_content(content: &[u8]) -> Result<()> {
     Ok(())
 }
 
-/// Create the year and month directories for the note
-pub fn create_date_directories(notes_dir: &Path, date: &DateTime<Local>) -> Result<PathBuf> {
-    let year = date.year();
-    let month = date.month();
-    let month_name = match month {
-        1 => "01_January",
-        2 => "02_February",
-        3 => "03_March",
-        4 => "04_April",
-        5 => "05_May",
-        6 => "06_June",
-        7 => "07_July",
-        8 => "08_August",
-        9 => "09_September",
-        10 => "10_October",
-        11 => "11_November",
-        12 => "12_December",
-        _ => unreachable!(),
+/// English month names, used for the `english` month directory style
+const ENGLISH_MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Month names for the `locale` month directory style, by language code
+const LOCALIZED_MONTH_NAMES: [(&str, [&str; 12]); 6] = [
+    (
+        "de",
+        [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+    ),
+    (
+        "es",
+        [
+            "Enero",
+            "Febrero",
+            "Marzo",
+            "Abril",
+            "Mayo",
+            "Junio",
+            "Julio",
+            "Agosto",
+            "Septiembre",
+            "Octubre",
+            "Noviembre",
+            "Diciembre",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "Janvier",
+            "Février",
+            "Mars",
+            "Avril",
+            "Mai",
+            "Juin",
+            "Juillet",
+            "Août",
+            "Septembre",
+            "Octobre",
+            "Novembre",
+            "Décembre",
+        ],
+    ),
+    (
+        "it",
+        [
+            "Gennaio",
+            "Febbraio",
+            "Marzo",
+            "Aprile",
+            "Maggio",
+            "Giugno",
+            "Luglio",
+            "Agosto",
+            "Settembre",
+            "Ottobre",
+            "Novembre",
+            "Dicembre",
+        ],
+    ),
+    (
+        "nl",
+        [
+            "Januari",
+            "Februari",
+            "Maart",
+            "April",
+            "Mei",
+            "Juni",
+            "Juli",
+            "Augustus",
+            "September",
+            "Oktober",
+            "November",
+            "December",
+        ],
+    ),
+    (
+        "pt",
+        [
+            "Janeiro",
+            "Fevereiro",
+            "Março",
+            "Abril",
+            "Maio",
+            "Junho",
+            "Julho",
+            "Agosto",
+            "Setembro",
+            "Outubro",
+            "Novembro",
+            "Dezembro",
+        ],
+    ),
+];
+
+/// Get the language code of the current locale from the environment
+///
+/// Uses the first non-empty value of LC_ALL, LC_TIME and LANG, e.g. `de` for
+/// `de_DE.UTF-8`.
+fn locale_language() -> Option<String> {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            value
+                .split(['_', '.', '@', '-'])
+                .next()
+                .map(str::to_lowercase)
+        })
+}
+
+/// Get the name of the directory for the given month (1-12)
+///
+/// The name always starts with the two-digit month number, so that directories
+/// of different styles sort correctly and can be mixed in one notes directory.
+/// The `locale` style falls back to English names for unsupported languages.
+pub fn month_dir_name(month: u32, style: MonthDirStyle, language: Option<&str>) -> String {
+    let index = month as usize - 1;
+
+    let month_name = match style {
+        MonthDirStyle::Numeric => return format!("{:02}", month),
+        MonthDirStyle::English => ENGLISH_MONTH_NAMES[index],
+        MonthDirStyle::Locale => LOCALIZED_MONTH_NAMES
+            .iter()
+            .find(|(code, _)| Some(*code) == language)
+            .map(|(_, names)| names[index])
+            .unwrap_or(ENGLISH_MONTH_NAMES[index]),
     };
 
-    let year_dir = notes_dir.join(year.to_string());
+    format!("{:02}_{}", month, month_name)
+}
+
+/// Create the year and month directories for the note
+///
+/// The month directory is named according to the `month_dir_style` setting.
+/// The year and month are taken in the time zone of `date`; notes use their
+/// creation time in local time.
+pub fn create_date_directories<Tz: TimeZone>(
+    notes_dir: &Path,
+    date: &DateTime<Tz>,
+) -> Result<PathBuf> {
+    create_date_directories_with_style(notes_dir, date, config::get().month_dir_style)
+}
+
+/// Create the year and month directories for the note, using the given style
+pub fn create_date_directories_with_style<Tz: TimeZone>(
+    notes_dir: &Path,
+    date: &DateTime<Tz>,
+    style: MonthDirStyle,
+) -> Result<PathBuf> {
+    let language = locale_language();
+    let month_name = month_dir_name(date.month(), style, language.as_deref());
+
+    let year_dir = notes_dir.join(date.year().to_string());
     let month_dir = year_dir.join(month_name);
 
-    fs::create_dir_all(&month_dir)?;
+    create_dir_all_with_permissions(&month_dir, config::get().restrict_permissions())?;
 
     Ok(month_dir)
 }
 
-/// Ensure the notes directory exists and is writable
-pub fn ensure_notes_dir_exists(notes_dir: &Path) -> Result<()> {
+/// Create a directory and its missing parents
+///
+/// With `restrict`, the new directories are accessible only by their owner
+/// (mode 0700). This has no effect on platforms other than Unix.
+pub fn create_dir_all_with_permissions(path: &Path, restrict: bool) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    if restrict {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    #[cfg(not(unix))]
+    let _ = restrict;
+
+    builder.create(path)
+}
+
+/// Create a new file, failing if it already exists
+///
+/// With `restrict`, the file is readable and writable only by its owner
+/// (mode 0600). This has no effect on platforms other than Unix.
+pub fn create_new_file(path: &Path, restrict: bool) -> io::Result<File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    if restrict {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = restrict;
+
+    options.open(path)
+}
+
+/// Make an existing file readable and writable only by its owner
+///
+/// This has no effect on platforms other than Unix.
+pub fn restrict_file_permissions(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// Ensure the notes directory exists, creating it (with its parents) if `create` is set
+///
+/// Without `create`, a missing directory is an error, so that a typo in the
+/// path does not silently start a new notes directory.
+///
+/// Returns the notes directory with symlinks resolved, which is how file
+/// watchers report the paths of the notes in it.
+pub fn ensure_notes_dir_exists(
+    resolved: &Resolved<PathBuf>,
+    create: bool,
+    allow_large: bool,
+) -> Result<PathBuf> {
+    let notes_dir = resolved.value.as_path();
+    if notes_dir.exists() {
+        let notes_dir = notes_dir.canonicalize()?;
+        if !allow_large {
+            check_notes_dir_scope(&notes_dir)?;
+        }
+        return Ok(notes_dir);
+    }
+
+    if !create {
+        return Err(NotelogError::NotesDirectoryNotFound(format!(
+            "Directory does not exist: {} (set by {}; pass --create-dir to create it)",
+            notes_dir.display(),
+            notes_dir_setting(resolved.source)
+        )));
+    }
+
+    fs::create_dir_all(notes_dir)?;
+    eprintln!("Created the notes directory {}", notes_dir.display());
+
+    Ok(notes_dir.canonicalize()?)
+}
+
+/// The setting that the notes directory was taken from, for error messages
+fn notes_dir_setting(source: SettingSource) -> &'static str {
+    match source {
+        SettingSource::Flag => "--notes-dir",
+        SettingSource::Profile => "the selected profile",
+        SettingSource::Environment => "NOTELOG_DIR",
+        SettingSource::ConfigFile => "the configuration file",
+        SettingSource::Default => "the default",
+    }
+}
+
+/// Refuse a notes directory that would index and watch far more than notes
+///
+/// This is the home directory, a filesystem root, or a directory with more
+/// than `max_foreign_entries` entries at the top level that are neither notes
+/// nor year directories. The path must be canonical.
+pub fn check_notes_dir_scope(notes_dir: &Path) -> Result<()> {
+    let home = home_dir().and_then(|home| home.canonicalize().ok());
+    if let Some(kind) = broad_dir_kind(notes_dir, home.as_deref()) {
+        return Err(NotelogError::NotesDirectoryTooLarge(format!(
+            "{} ({})",
+            notes_dir.display(),
+            kind
+        )));
+    }
+
+    let max = config::get().max_foreign_entries();
+    let foreign = count_foreign_entries(notes_dir, &config::get().note_extensions())?;
+    if foreign > max {
+        return Err(NotelogError::NotesDirectoryTooLarge(format!(
+            "{} ({} entries that are not notes, more than max_foreign_entries = {})",
+            notes_dir.display(),
+            foreign,
+            max
+        )));
+    }
+
+    Ok(())
+}
+
+/// Whether a path is the home directory or a filesystem root
+fn broad_dir_kind(path: &Path, home: Option<&Path>) -> Option<&'static str> {
+    if path.parent().is_none() {
+        Some("a filesystem root")
+    } else if home == Some(path) {
+        Some("the home directory")
+    } else {
+        None
+    }
+}
+
+/// Count the entries at the top level of a directory that notelog did not put there
+///
+/// Notes, year directories, hidden entries (like the database or `.git`) and
+/// the reserved directories are not counted.
+fn count_foreign_entries(dir: &Path, extensions: &[String]) -> io::Result<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            count += 1;
+            continue;
+        };
+
+        let is_year_dir = name.len() == 4 && name.bytes().all(|b| b.is_ascii_digit());
+        let is_note = path
+            .extension()
+            .is_some_and(|ext| extensions.iter().any(|allowed| ext == allowed.as_str()));
+        if !(is_year_dir || is_note || name.starts_with('.') || RESERVED_DIRS.contains(&name)) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Get the path of a file relative to the notes directory
+///
+/// If the paths do not share a prefix as given, e.g. because only one of them
+/// has a symlink resolved, they are compared again with symlinks resolved.
+/// Only the directory of the file is resolved, so that this also works for
+/// files that were just removed.
+pub fn relative_to_notes_dir(path: &Path, notes_dir: &Path) -> Option<PathBuf> {
+    if let Ok(relative_path) = path.strip_prefix(notes_dir) {
+        return Some(relative_path.to_path_buf());
+    }
+
+    let notes_dir = notes_dir.canonicalize().ok()?;
+    let path = path.parent()?.canonicalize().ok()?.join(path.file_name()?);
+    path.strip_prefix(&notes_dir).ok().map(Path::to_path_buf)
+}
+
+/// Ensure the notes directory exists and can be read
+///
+/// This is enough for commands that only query the notes.
+pub fn ensure_notes_dir_readable(notes_dir: &Path) -> Result<()> {
     if !notes_dir.exists() {
         return Err(NotelogError::NotesDirectoryNotFound(format!(
             "Directory does not exist: {}",
@@ -147,24 +706,88 @@ pub fn ensure_notes_dir_exists(notes_dir: &Path) -> Result<()> {
         )));
     }
 
-    // Check if the directory is writable by attempting to create a temporary file
-    let temp_file_path = notes_dir.join(".notelog_write_test");
-    match File::create(&temp_file_path) {
-        Ok(_) => {
-            // Clean up the test file
-            let _ = fs::remove_file(temp_file_path);
-            Ok(())
-        }
-        Err(e) => Err(NotelogError::NotesDirectoryNotWritable(format!(
-            "{}: {}",
-            notes_dir.display(),
-            e
-        ))),
+    fs::read_dir(notes_dir).map_err(|e| {
+        NotelogError::NotesDirectoryNotReadable(format!("{}: {}", notes_dir.display(), e))
+    })?;
+
+    Ok(())
+}
+
+/// Ensure the notes directory exists and is writable
+///
+/// This is required for commands that create or modify notes.
+pub fn ensure_notes_dir_writable(notes_dir: &Path) -> Result<()> {
+    ensure_notes_dir_readable(notes_dir)?;
+
+    check_dir_writable(notes_dir).map_err(|e| {
+        NotelogError::NotesDirectoryNotWritable(format!("{}: {}", notes_dir.display(), e))
+    })
+}
+
+/// Check whether a directory is writable by creating and removing a temporary file
+pub fn check_dir_writable(dir: &Path) -> io::Result<()> {
+    let temp_file_path = dir.join(".notelog_write_test");
+    File::create(&temp_file_path)?;
+
+    // Clean up the test file
+    let _ = fs::remove_file(temp_file_path);
+    Ok(())
+}
+
+/// Open a note file in the editor and save the changes
+///
+/// The note is validated before the editor is opened, and the post-edit hook
+/// runs if the content was changed. Returns whether the content was changed.
+pub fn edit_note_file(notes_dir: &Path, path: &Path) -> Result<bool> {
+    edit_note_file_at(notes_dir, path, None)
+}
+
+/// Open a note file in the editor with the cursor on the given line
+///
+/// Works like `edit_note_file`; see `open_editor_at` for which editors support
+/// the line.
+pub fn edit_note_file_at(notes_dir: &Path, path: &Path, line: Option<usize>) -> Result<bool> {
+    // Read the note content
+    let content = read_file_content(path)?;
+
+    // Parse the note to validate it
+    let _note = Note::from_str(&content)?;
+
+    // Open the note in the editor
+    let new_content = open_editor_at(Some(&content), line)?;
+
+    // If the content has changed, save it back to the file
+    if new_content == content {
+        return Ok(false);
     }
+
+    fs::write(path, &new_content)?;
+    println!("Note updated: {}", path.display());
+
+    // The hook gets the note as saved, which may no longer parse
+    if let Ok(note) = Note::from_str(&new_content) {
+        let relative_path = relative_to_notes_dir(path, notes_dir);
+        run_hook(
+            notes_dir,
+            Hook::PostEdit,
+            relative_path.as_deref().unwrap_or(path),
+            &note,
+        );
+    }
+
+    Ok(true)
 }
 
 /// Open an editor for the user to write a note
 pub fn open_editor(initial_content: Option<&str>) -> Result<String> {
+    open_editor_at(initial_content, None)
+}
+
+/// Open an editor for the user to write a note, with the cursor on the given line
+///
+/// The line is only passed to editors known to support `+<line>`; others open
+/// the file as usual.
+pub fn open_editor_at(initial_content: Option<&str>, line: Option<usize>) -> Result<String> {
     // Create a temporary file with .md extension
     let mut temp_file = NamedTempFile::with_suffix(".md")?;
     let temp_path = temp_file.path().to_path_buf();
@@ -176,12 +799,14 @@ pub fn open_editor(initial_content: Option<&str>) -> Result<String> {
     }
 
     // Get the editor command
-    let editor = env::var("VISUAL")
-        .or_else(|_| env::var("EDITOR"))
-        .unwrap_or_else(|_| "nano".to_string());
+    let editor = config::get().resolved_editor().value;
 
     // Launch the editor
-    let status = Command::new(&editor)
+    let mut command = Command::new(&editor);
+    if let Some(line_arg) = line.and_then(|line| editor_line_arg(&editor, line)) {
+        command.arg(line_arg);
+    }
+    let status = command
         .arg(&temp_path)
         .status()
         .map_err(|e| NotelogError::EditorLaunchFailed(format!("{}: {}", editor, e)))?;
@@ -204,6 +829,48 @@ pub fn open_editor(initial_content: Option<&str>) -> Result<String> {
     Ok(content)
 }
 
+/// Get the argument that makes the editor start on the given line
+///
+/// Returns `None` for editors that are not known to support `+<line>`.
+fn editor_line_arg(editor: &str, line: usize) -> Option<String> {
+    const LINE_FLAG_EDITORS: [&str; 7] =
+        ["vi", "vim", "nvim", "gvim", "nano", "emacs", "emacsclient"];
+
+    let name = Path::new(editor).file_name()?.to_str()?;
+    LINE_FLAG_EDITORS
+        .contains(&name)
+        .then(|| format!("+{}", line))
+}
+
+/// Read at most MAX_FILE_SIZE_BYTES from a reader
+///
+/// Stops reading as soon as the limit is exceeded instead of buffering the
+/// entire input, and returns `ContentTooLarge` in that case.
+pub fn read_bounded<R: Read>(reader: R) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    reader
+        .take(MAX_FILE_SIZE_BYTES as u64 + 1)
+        .read_to_end(&mut buffer)?;
+
+    if buffer.len() > MAX_FILE_SIZE_BYTES {
+        return Err(NotelogError::ContentTooLarge);
+    }
+
+    Ok(buffer)
+}
+
+/// Read the note content piped to stdin
+///
+/// Returns an empty buffer if stdin is a terminal.
+pub fn read_piped_stdin() -> Result<Vec<u8>> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(Vec::new());
+    }
+
+    read_bounded(stdin.lock())
+}
+
 /// Read content from a file
 pub fn read_file_content(path: &Path) -> Result<String> {
     let mut file = File::open(path)?;
@@ -212,7 +879,68 @@ pub fn read_file_content(path: &Path) -> Result<String> {
 
     validate_content(&content)?;
 
-    String::from_utf8(content).map_err(|_| NotelogError::InvalidUtf8Content)
+    decode_utf8(&content, &path.display().to_string()).map(str::to_string)
+}
+
+/// Replace the content of an existing file without ever leaving it half-written
+///
+/// The content is written to a temporary file in the same directory, which
+/// then replaces the file. The file keeps its permissions.
+pub fn write_file_atomically(path: &Path, content: &str) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut temp_file = NamedTempFile::new_in(dir)?;
+    temp_file.write_all(content.as_bytes())?;
+    temp_file.flush()?;
+    temp_file
+        .as_file()
+        .set_permissions(fs::metadata(path)?.permissions())?;
+
+    temp_file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// The number of bytes on each side of an invalid byte that errors show
+const UTF8_CONTEXT_BYTES: usize = 8;
+
+/// Decode UTF-8, reporting where the first invalid byte is
+///
+/// `path` names the source of the bytes in the error, e.g. a file or `stdin`.
+pub fn decode_utf8<'a>(bytes: &'a [u8], path: &str) -> Result<&'a str> {
+    std::str::from_utf8(bytes).map_err(|e| {
+        let offset = e.valid_up_to();
+        let invalid_len = e.error_len().unwrap_or(bytes.len() - offset);
+        NotelogError::InvalidUtf8Content {
+            path: path.to_string(),
+            offset,
+            context: hex_context(bytes, offset, invalid_len),
+        }
+    })
+}
+
+/// The bytes around `offset` in hex, with the `len` bytes at `offset` in brackets
+fn hex_context(bytes: &[u8], offset: usize, len: usize) -> String {
+    let start = offset.saturating_sub(UTF8_CONTEXT_BYTES);
+    let end = (offset + len + UTF8_CONTEXT_BYTES).min(bytes.len());
+
+    let hex = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let parts = [
+        hex(&bytes[start..offset]),
+        format!("[{}]", hex(&bytes[offset..offset + len])),
+        hex(&bytes[offset + len..end]),
+    ];
+    parts
+        .iter()
+        .filter(|part| !part.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 /// Wait for user to press Enter or Ctrl+C
@@ -221,6 +949,7 @@ pub fn wait_for_user_input() -> Result<bool> {
 
     let mut input = String::new();
     match io::stdin().read_line(&mut input) {
+        Ok(0) => Ok(false),                 // End of input, nobody can fix the note
         Ok(_) => Ok(true),                  // User pressed Enter
         Err(e) => Err(NotelogError::Io(e)), // Error reading input
     }
@@ -230,7 +959,128 @@ pub fn wait_for_user_input() -> Result<bool> {
 mod tests {
     use super::*;
     use crate::error::NotelogError;
-    use chrono::TimeZone;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_confirm_create_notes_dir() {
+        let dir = Path::new("/home/me/NoteLog");
+
+        for (answer, expected) in [
+            ("y\n", true),
+            ("YES\n", true),
+            ("n\n", false),
+            ("\n", false),
+            ("", false),
+        ] {
+            let mut output = Vec::new();
+            let confirmed =
+                confirm_create_notes_dir(&mut io::Cursor::new(answer), &mut output, dir).unwrap();
+
+            assert_eq!(confirmed, expected, "answer {:?}", answer);
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                "Create /home/me/NoteLog as your notes directory? [y/N] "
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_notes_dir_not_found_lists_resolution_order() {
+        let message = default_notes_dir_not_found(Path::new("/home/me/NoteLog")).to_string();
+
+        assert!(message.contains("/home/me/NoteLog"));
+        assert!(message.contains("--notes-dir"));
+        assert!(message.contains("NOTELOG_DIR"));
+    }
+
+    #[test]
+    fn test_explicit_notes_dir_is_not_created() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing");
+
+        ensure_default_notes_dir(&missing, SettingSource::Flag).unwrap();
+        assert!(!missing.exists());
+    }
+
+    #[test]
+    fn test_ensure_notes_dir_exists() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing").join("notes");
+        let resolved = Resolved::new(missing.clone(), SettingSource::Environment);
+
+        let err = ensure_notes_dir_exists(&resolved, false, false).unwrap_err();
+        assert!(matches!(err, NotelogError::NotesDirectoryNotFound(_)));
+        assert!(err.to_string().contains("set by NOTELOG_DIR"));
+        assert!(!missing.exists());
+
+        let notes_dir = ensure_notes_dir_exists(&resolved, true, false).unwrap();
+        assert!(missing.is_dir());
+        assert_eq!(notes_dir, missing.canonicalize().unwrap());
+
+        // An existing directory is fine either way
+        ensure_notes_dir_exists(&resolved, false, false).unwrap();
+    }
+
+    #[test]
+    fn test_broad_dir_kind() {
+        let home = Path::new("/home/user");
+        assert_eq!(
+            broad_dir_kind(Path::new("/"), Some(home)),
+            Some("a filesystem root")
+        );
+        assert_eq!(broad_dir_kind(home, Some(home)), Some("the home directory"));
+        assert_eq!(broad_dir_kind(&home.join("NoteLog"), Some(home)), None);
+        assert_eq!(broad_dir_kind(Path::new("/home"), Some(home)), None);
+        assert_eq!(broad_dir_kind(home, None), None);
+    }
+
+    #[test]
+    fn test_count_foreign_entries() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        for name in ["2025", ".git", ".trash", "attachments"] {
+            fs::create_dir(dir.join(name)).unwrap();
+        }
+        for name in ["README.md", ".notes.db"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let extensions = vec!["md".to_string()];
+        assert_eq!(count_foreign_entries(dir, &extensions).unwrap(), 0);
+
+        fs::create_dir(dir.join("Downloads")).unwrap();
+        fs::write(dir.join("photo.jpg"), "").unwrap();
+        assert_eq!(count_foreign_entries(dir, &extensions).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("exactly", 7), "exactly");
+        assert_eq!(truncate_chars("truncated", 5), "trunc");
+        assert_eq!(truncate_chars("日本語のノート", 3), "日本語");
+        assert_eq!(truncate_chars("👍🏽👍🏽", 1), "👍");
+        assert_eq!(truncate_chars("", 0), "");
+    }
+
+    #[test]
+    fn test_shorten_at_word() {
+        assert_eq!(shorten_at_word("short", 10), "short");
+        assert_eq!(shorten_at_word("exactly ten", 11), "exactly ten");
+        assert_eq!(
+            shorten_at_word("the airship was late", 16),
+            "the airship was…"
+        );
+        assert_eq!(shorten_at_word("the airship was late", 15), "the airship…");
+        assert_eq!(shorten_at_word("日本語 のノート", 6), "日本語…");
+
+        // Words that are too long are cut in the middle
+        assert_eq!(
+            shorten_at_word("see https://example.com/a/very/long/path", 20),
+            "see https://example…"
+        );
+        assert_eq!(shorten_at_word(&"x".repeat(50), 5), "xxxx…");
+        assert_eq!(shorten_at_word("abc", 0), "");
+    }
 
     #[test]
     fn test_generate_filename() {
@@ -252,6 +1102,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_generate_filename_caps_long_ascii_title() {
+        let date = Local.with_ymd_and_hms(2025, 4, 1, 12, 0, 0).unwrap();
+        let title = "word ".repeat(60);
+
+        let filename = generate_filename_with_limit(&date, &title, None, 200);
+        assert!(filename.len() <= 200);
+        assert!(filename.starts_with("2025-04-01T12-00 word word"));
+        // The cut does not leave a trailing space before the extension
+        assert!(filename.ends_with("word.md"));
+
+        let filename = generate_filename_with_limit(&date, &title, Some(12), 200);
+        assert!(filename.len() <= 200);
+        assert!(filename.ends_with("word (12).md"));
+
+        // Short titles are not touched
+        assert_eq!(
+            generate_filename_with_limit(&date, "Short", Some(1), 200),
+            "2025-04-01T12-00 Short (1).md"
+        );
+    }
+
+    #[test]
+    fn test_generate_filename_caps_long_multibyte_title() {
+        let date = Local.with_ymd_and_hms(2025, 4, 1, 12, 0, 0).unwrap();
+
+        for title in [
+            "日本語".repeat(34),
+            "🦀-".repeat(40),
+            format!("a{}", "é".repeat(99)),
+        ] {
+            let filename = generate_filename_with_limit(&date, &title, Some(3), 200);
+            assert!(filename.len() <= 200, "{} is too long", filename);
+            assert!(std::str::from_utf8(filename.as_bytes()).is_ok());
+
+            let kept = filename
+                .strip_prefix("2025-04-01T12-00 ")
+                .and_then(|rest| rest.strip_suffix(" (3).md"))
+                .unwrap();
+            assert!(title.starts_with(kept));
+            assert!(!kept.ends_with('-'));
+        }
+    }
+
     #[test]
     fn test_generate_filename_sanitizes_title() {
         let date = Local.with_ymd_and_hms(2025, 4, 1, 12, 0, 0).unwrap();
@@ -262,6 +1156,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_note_paths_use_the_time_zone_of_the_date() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        // The same moment, written with different offsets
+        let timestamps = [
+            "2025-05-31T12:30:00+00:00",
+            "2025-05-31T07:30:00-05:00",
+            "2025-06-01T00:30:00+12:00",
+        ];
+
+        // Just after midnight in UTC+12, still the previous morning in UTC-10
+        for (offset_hours, month, filename) in [
+            (12, "06", "2025-06-01T00-30 Note.md"),
+            (-10, "05", "2025-05-31T02-30 Note.md"),
+        ] {
+            let local = chrono::FixedOffset::east_opt(offset_hours * 3600).unwrap();
+
+            for timestamp in timestamps {
+                let date = DateTime::parse_from_rfc3339(timestamp)
+                    .unwrap()
+                    .with_timezone(&local);
+
+                let month_dir =
+                    create_date_directories_with_style(notes_dir, &date, MonthDirStyle::Numeric)
+                        .unwrap();
+                assert_eq!(
+                    month_dir,
+                    notes_dir.join("2025").join(month),
+                    "{} in UTC{:+}",
+                    timestamp,
+                    offset_hours
+                );
+                assert_eq!(
+                    generate_filename_with_limit(&date, "Note", None, 200),
+                    filename,
+                    "{} in UTC{:+}",
+                    timestamp,
+                    offset_hours
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_month_dir_name() {
+        assert_eq!(month_dir_name(1, MonthDirStyle::Numeric, None), "01");
+        assert_eq!(
+            month_dir_name(10, MonthDirStyle::English, Some("de")),
+            "10_October"
+        );
+        assert_eq!(
+            month_dir_name(3, MonthDirStyle::Locale, Some("de")),
+            "03_März"
+        );
+
+        // Unsupported languages fall back to English
+        assert_eq!(
+            month_dir_name(12, MonthDirStyle::Locale, Some("xx")),
+            "12_December"
+        );
+        assert_eq!(
+            month_dir_name(12, MonthDirStyle::Locale, None),
+            "12_December"
+        );
+    }
+
     #[test]
     fn test_validate_content_empty() {
         let content = b"";
@@ -302,14 +1264,59 @@ mod tests {
     }
 
     #[test]
-    fn test_invalid_utf8_conversion() {
-        // Create invalid UTF-8 sequence
-        let invalid_utf8 = vec![0xFF, 0xFF, 0xFF, 0xFF];
+    fn test_read_bounded() {
+        let content = vec![b'a'; MAX_FILE_SIZE_BYTES];
+        assert_eq!(
+            read_bounded(content.as_slice()).unwrap().len(),
+            MAX_FILE_SIZE_BYTES
+        );
+
+        // An endless reader would never finish if the read was not bounded
+        let result = read_bounded(io::repeat(b'a'));
+        assert!(matches!(result, Err(NotelogError::ContentTooLarge)));
+    }
+
+    #[test]
+    fn test_decode_utf8_reports_offset() {
+        let bytes = b"# Caf\xe9 notes\n";
+        let error = decode_utf8(bytes, "note.md").unwrap_err();
+        match &error {
+            NotelogError::InvalidUtf8Content {
+                path,
+                offset,
+                context,
+            } => {
+                assert_eq!(path, "note.md");
+                assert_eq!(*offset, 5);
+                assert_eq!(context, "23 20 43 61 66 [e9] 20 6e 6f 74 65 73 0a");
+            }
+            _ => panic!("unexpected error: {}", error),
+        }
+        assert!(
+            error
+                .to_string()
+                .starts_with("note.md contains invalid UTF-8 at byte 5")
+        );
 
-        // Test with String::from_utf8 conversion
-        let result = String::from_utf8(invalid_utf8).map_err(|_| NotelogError::InvalidUtf8Content);
+        // Only a few bytes around the invalid one are shown
+        let mut bytes = vec![b'a'; 100];
+        bytes[50] = 0xff;
+        let Err(NotelogError::InvalidUtf8Content { context, .. }) = decode_utf8(&bytes, "x") else {
+            panic!("expected an error");
+        };
+        assert_eq!(context, format!("{0} [ff] {0}", ["61"; 8].join(" ")));
 
-        assert!(matches!(result, Err(NotelogError::InvalidUtf8Content)));
+        // A truncated sequence at the end
+        let Err(NotelogError::InvalidUtf8Content {
+            offset, context, ..
+        }) = decode_utf8("ab€".as_bytes().split_last().unwrap().1, "x")
+        else {
+            panic!("expected an error");
+        };
+        assert_eq!(offset, 2);
+        assert_eq!(context, "61 62 [e2 82]");
+
+        assert_eq!(decode_utf8("Café".as_bytes(), "x").unwrap(), "Café");
     }
 
     #[test]
@@ -333,4 +1340,109 @@ mod tests {
         let path = PathBuf::from("2023-01-01T12-00 Test Note");
         assert!(!is_valid_note_file(&path).unwrap_or(true));
     }
+
+    #[test]
+    fn test_note_file_skip_reason() {
+        let path = Path::new("2023-01-01T12-00 Test Note.md");
+        assert_eq!(note_file_skip_reason(path, 100), None);
+        assert_eq!(
+            note_file_skip_reason(path, MAX_FILE_SIZE_BYTES as u64 + 1),
+            Some(SkipReason::TooLarge)
+        );
+
+        assert_eq!(
+            note_file_skip_reason(Path::new("2023-01-01T12-00 Test Note.txt"), 100),
+            Some(SkipReason::WrongExtension)
+        );
+        assert_eq!(
+            note_file_skip_reason(Path::new("2023-01-01T12-00 Test Note"), 100),
+            Some(SkipReason::WrongExtension)
+        );
+        assert_eq!(
+            note_file_skip_reason(Path::new("README.md"), 100),
+            Some(SkipReason::NotANoteFilename)
+        );
+    }
+
+    #[test]
+    fn test_note_file_skip_reason_with_extensions() {
+        let extensions = vec!["md".to_string(), "markdown".to_string()];
+        for name in ["2023-01-01T12-00 Note.md", "2023-01-01T12-00 Note.markdown"] {
+            assert_eq!(
+                note_file_skip_reason_with(Path::new(name), 100, &extensions),
+                None
+            );
+        }
+        assert_eq!(
+            note_file_skip_reason_with(Path::new("2023-01-01T12-00 Note.txt"), 100, &extensions),
+            Some(SkipReason::WrongExtension)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_notes_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let notes_dir = temp_dir.path();
+
+        fs::set_permissions(notes_dir, fs::Permissions::from_mode(0o555)).unwrap();
+        // Permissions do not apply to root, so only check the result where they do
+        let enforced = check_dir_writable(notes_dir).is_err();
+        let readable = ensure_notes_dir_readable(notes_dir);
+        let writable = ensure_notes_dir_writable(notes_dir);
+        fs::set_permissions(notes_dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(readable.is_ok());
+        if enforced {
+            assert!(matches!(
+                writable,
+                Err(NotelogError::NotesDirectoryNotWritable(_))
+            ));
+        } else {
+            assert!(writable.is_ok());
+        }
+
+        assert!(matches!(
+            ensure_notes_dir_readable(&notes_dir.join("missing")),
+            Err(NotelogError::NotesDirectoryNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_editor_line_arg() {
+        assert_eq!(editor_line_arg("vim", 4), Some("+4".to_string()));
+        assert_eq!(
+            editor_line_arg("/usr/bin/nano", 12),
+            Some("+12".to_string())
+        );
+        assert_eq!(editor_line_arg("emacs", 1), Some("+1".to_string()));
+        assert_eq!(editor_line_arg("code", 4), None);
+        assert_eq!(editor_line_arg("vimdiff", 4), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restricted_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let month_dir = temp_dir.path().join("2025").join("05_May");
+        create_dir_all_with_permissions(&month_dir, true).unwrap();
+        assert_eq!(mode(&temp_dir.path().join("2025")), 0o700);
+        assert_eq!(mode(&month_dir), 0o700);
+
+        let note_path = month_dir.join("note.md");
+        create_new_file(&note_path, true).unwrap();
+        assert_eq!(mode(&note_path), 0o600);
+        assert!(create_new_file(&note_path, true).is_err());
+
+        let other_path = month_dir.join("other.md");
+        fs::write(&other_path, "").unwrap();
+        restrict_file_permissions(&other_path).unwrap();
+        assert_eq!(mode(&other_path), 0o600);
+    }
 }
@@ -0,0 +1,1026 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use tempfile::TempDir;
+
+/// Build a `notelog` command for the given notes directory, isolated from the
+/// user's configuration and environment
+fn notelog(notes_dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_notelog"));
+    command
+        .arg("-d")
+        .arg(notes_dir)
+        .env("NOTELOG_CONFIG", notes_dir.join("config.toml"))
+        .env("NOTELOG_SEARCH_HISTORY", notes_dir.join("search_history"))
+        .env("NOTELOG_DAEMON_DIR", notes_dir.join(".daemon"))
+        .env_remove("NOTELOG_DIR")
+        .env_remove("NOTELOG_PROFILE")
+        .env_remove("NOTELOG_NOTE_EXTENSIONS")
+        .env_remove("VISUAL")
+        .env_remove("EDITOR")
+        .stdin(Stdio::null());
+    command
+}
+
+/// Run a command with the given stdin
+fn run_with_stdin(mut command: Command, stdin: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Get the path of the note from the output of `notelog add`
+fn saved_path(output: &Output) -> PathBuf {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let path = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Note saved to: "))
+        .unwrap_or_else(|| panic!("no saved note in {:?}", output));
+    PathBuf::from(path)
+}
+
+#[test]
+fn test_add_from_args() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Fixed", "the", "build", "+work"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let path = saved_path(&output);
+    assert!(path.to_string_lossy().ends_with(" Fixed the build.md"));
+    let note = fs::read_to_string(path).unwrap();
+    assert!(note.contains("  - work\n"));
+    assert!(note.contains("source: cli"));
+    assert!(note.trim_end().ends_with("Fixed the build"));
+}
+
+#[test]
+fn test_add_from_stdin() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let mut command = notelog(notes_dir);
+    command.args(["+idea"]);
+    let output = run_with_stdin(command, "# Piped\n\nLorem ipsum\n");
+    assert!(output.status.success(), "{:?}", output);
+
+    let note = fs::read_to_string(saved_path(&output)).unwrap();
+    assert!(note.contains("  - idea\n"));
+    assert!(note.contains("# Piped\n\nLorem ipsum"));
+}
+
+#[test]
+fn test_add_without_id() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "--no-id", "Minimalist"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        !fs::read_to_string(saved_path(&output))
+            .unwrap()
+            .contains("id:")
+    );
+
+    // The same from stdin, with the setting in the configuration file
+    fs::write(notes_dir.join("config.toml"), "generate_ids = false\n").unwrap();
+    let output = run_with_stdin(notelog(notes_dir), "Piped minimalist\n");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        !fs::read_to_string(saved_path(&output))
+            .unwrap()
+            .contains("id:")
+    );
+}
+
+#[test]
+fn test_add_multi_word_tag() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // As the shell passes `+"code review"`
+    let output = notelog(notes_dir)
+        .args(["add", "Reviewed the parser", "+code review"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot contain spaces"), "{}", stderr);
+    assert!(stderr.contains("+code-review"), "{}", stderr);
+
+    let output = notelog(notes_dir)
+        .args(["add", "--fix-tags", "Reviewed the parser", "+code review"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let note = fs::read_to_string(saved_path(&output)).unwrap();
+    assert!(note.contains("- code-review"), "{}", note);
+
+    let output = notelog(notes_dir)
+        .args(["search", "+\"code review\""])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("+code-review"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_add_from_editor() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // An "editor" that appends a line to the note template
+    let editor = notes_dir.join("editor.sh");
+    fs::write(
+        &editor,
+        "#!/bin/sh\necho 'Written in the editor' >> \"$1\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = notelog(notes_dir)
+        .args(["add", "+journal"])
+        .env("EDITOR", &editor)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let note = fs::read_to_string(saved_path(&output)).unwrap();
+    assert!(note.contains("  - journal\n"));
+    assert!(note.contains("Written in the editor"));
+}
+
+#[test]
+fn test_review_week() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Deployed", "the", "API", "+ops"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = notelog(notes_dir)
+        .args(["review", "--week", "--no-edit"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let review = fs::read_to_string(saved_path(&output)).unwrap();
+    assert!(review.contains("  - weekly-review\n"));
+    assert!(review.contains("### +ops\n\n- [Deployed the API](notelog://note/"));
+    assert!(review.contains("## Highlights"));
+
+    // Earlier reviews are not part of the next one
+    let output = notelog(notes_dir)
+        .args(["review", "--week", "--no-edit"])
+        .output()
+        .unwrap();
+    let review = fs::read_to_string(saved_path(&output)).unwrap();
+    assert!(review.contains("### +ops\n"));
+    assert!(!review.contains("### +weekly-review"));
+}
+
+#[test]
+fn test_mv() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Late", "night", "deploy"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let old_path = saved_path(&output);
+    let content = fs::read_to_string(&old_path).unwrap();
+    let id = content
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap()
+        .to_string();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Unrelated"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = notelog(notes_dir)
+        .args([
+            "mv",
+            &id,
+            "--created",
+            "2024-12-31 23:30",
+            "--title",
+            "Deploy",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!old_path.exists());
+
+    let new_path = notes_dir.join("2024/12_December/2024-12-31T23-30 Deploy.md");
+    let note = fs::read_to_string(&new_path).unwrap();
+    assert!(note.contains("created: 2024-12-31T23:30:00"));
+    assert!(note.contains("Late night deploy"));
+
+    // The index knows the new path
+    let output = notelog(notes_dir)
+        .args(["search", "--path", "2024/12_December", "deploy"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Late night deploy"));
+}
+
+#[test]
+fn test_split() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let mut command = notelog(notes_dir);
+    command.args(["add", "+meeting"]);
+    let output = run_with_stdin(
+        command,
+        "# Planning\n\n## Follow-ups\n\n- Call Bob\n\n### Later\n\n```sh\n# Attendees\necho hi\n```\n\n## Attendees\n\nAlice\n",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let original_path = saved_path(&output);
+
+    let output = notelog(notes_dir)
+        .args(["add", "Unrelated"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let content = fs::read_to_string(&original_path).unwrap();
+    let id = content
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap()
+        .to_string();
+
+    let output = notelog(notes_dir)
+        .args([
+            "split",
+            &id,
+            "--heading",
+            "Follow-ups",
+            "+todo",
+            "--created",
+            "2025-05-07 09:00",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // The section, with its subsection and code, is a note of its own
+    let new_path = notes_dir.join("2025/05_May/2025-05-07T09-00 Follow-ups.md");
+    let new_note = fs::read_to_string(&new_path).unwrap();
+    assert!(new_note.contains("  - meeting\n  - todo\n"), "{}", new_note);
+    assert!(new_note.contains("# Follow-ups\n\n- Call Bob\n\n## Later\n\n```sh\n# Attendees\necho hi\n```\n\nSplit from:\n\n- [Planning](notelog://note/"));
+    let new_id = new_note
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap();
+
+    // The original note keeps its other sections and links to the new one
+    let original = fs::read_to_string(&original_path).unwrap();
+    assert!(original.contains(&format!(
+        "# Planning\n\n- [Follow-ups](notelog://note/{})\n\n## Attendees\n\nAlice\n",
+        new_id
+    )));
+    assert!(!original.contains("Call Bob"));
+
+    // Both notes are indexed right away
+    let output = notelog(notes_dir)
+        .args(["search", "+todo", "Bob"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Follow-ups"));
+
+    let output = notelog(notes_dir)
+        .args(["split", &id, "--heading", "Missing"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("No section with the heading 'Missing'")
+    );
+}
+
+#[test]
+fn test_last_print() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "-t", "Newest", "The newest note"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = notelog(notes_dir)
+        .args(["last", "--print"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("# Newest"));
+    assert!(stdout.contains("The newest note"));
+}
+
+#[test]
+fn test_errors_exit_with_status_1() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let check = |output: Output, message: &str| {
+        assert_eq!(output.status.code(), Some(1), "{:?}", output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.starts_with("Error: "), "{}", stderr);
+        assert!(stderr.contains(message), "{}", stderr);
+    };
+
+    // The notes directory does not exist
+    let output = notelog(&notes_dir.join("missing"))
+        .args(["add", "Lost"])
+        .output()
+        .unwrap();
+    check(output, "does not exist");
+
+    // Stdin and arguments cannot be combined
+    let mut command = notelog(notes_dir);
+    command.args(["add", "From", "args"]);
+    check(run_with_stdin(command, "From stdin"), "stdin");
+
+    // No note with this ID
+    let output = notelog(notes_dir)
+        .args(["done", "zzzzzz"])
+        .output()
+        .unwrap();
+    check(output, "zzzzzz");
+}
+
+#[test]
+fn test_create_missing_notes_dir() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // With --create-dir
+    let notes_dir = temp_dir.path().join("new").join("notes");
+    let output = notelog(&notes_dir)
+        .args(["--create-dir", "add", "First"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Created the notes directory"));
+    assert!(saved_path(&output).starts_with(&notes_dir));
+
+    // With the setting in the configuration file
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, "create_missing_dir = true\n").unwrap();
+    let notes_dir = temp_dir.path().join("other");
+    let output = notelog(&notes_dir)
+        .env("NOTELOG_CONFIG", &config_path)
+        .args(["add", "First"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(notes_dir.is_dir());
+}
+
+#[test]
+fn test_selftest() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // The self test does not need a notes directory
+    let output = Command::new(env!("CARGO_BIN_EXE_notelog"))
+        .arg("selftest")
+        .env("NOTELOG_CONFIG", temp_dir.path().join("config.toml"))
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("  ok  ").count(), 5, "{}", stdout);
+    assert!(stdout.contains("Self test passed"));
+}
+
+#[test]
+fn test_search_shows_line_of_match() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let mut command = notelog(notes_dir);
+    command.args(["add"]);
+    let output = run_with_stdin(command, "# Airships\n\nThe zeppelin landed.\n");
+    assert!(output.status.success(), "{:?}", output);
+
+    // The line number is the line in the note file, including the frontmatter
+    let note = fs::read_to_string(saved_path(&output)).unwrap();
+    let line = note.lines().position(|l| l.contains("zeppelin")).unwrap() + 1;
+
+    let output = notelog(notes_dir)
+        .args(["search", "zeppelin"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    assert!(lines.next().unwrap().contains("  LINE  "), "{}", stdout);
+    let row: Vec<&str> = lines.next().unwrap().split_whitespace().collect();
+    assert_eq!(row[1], line.to_string(), "{}", stdout);
+
+    // The long output adds the number of words and tags
+    let output = notelog(notes_dir)
+        .args(["search", "--long", "zeppelin"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    assert!(
+        lines.next().unwrap().contains("  WORDS  TAGS  "),
+        "{}",
+        stdout
+    );
+    let row: Vec<&str> = lines.next().unwrap().split_whitespace().collect();
+    assert_eq!(
+        row[1..4],
+        [line.to_string(), "4".into(), "0".into()],
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_search_query_from_stdin_and_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "The zeppelin landed", "+airships"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut command = notelog(notes_dir);
+    command.args(["search", "-"]);
+    let output = run_with_stdin(command, "zeppelin +airships\n");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("The zeppelin landed"));
+
+    let query_file = temp_dir.path().join("query.txt");
+    fs::write(&query_file, "blimp\n").unwrap();
+    let output = notelog(notes_dir)
+        .args(["search", "--query-file"])
+        .arg(&query_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("The zeppelin landed"));
+}
+
+#[test]
+fn test_unicode_tags() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Call with the customer", "+kundengespr\u{e4}ch"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{:?}", output);
+
+    fs::write(notes_dir.join("config.toml"), "unicode_tags = true\n").unwrap();
+    let output = notelog(notes_dir)
+        .args(["add", "Call with the customer", "+kundengespr\u{e4}ch"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // The decomposed form of the tag finds the note
+    let output = notelog(notes_dir)
+        .args(["search", "+kundengespra\u{308}ch"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Call with the customer"), "{}", stdout);
+}
+
+#[test]
+fn test_note_extensions() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let month_dir = notes_dir.join("2025").join("05_May");
+    fs::create_dir_all(&month_dir).unwrap();
+    fs::write(
+        month_dir.join("2025-05-01T12-00 Export.markdown"),
+        "---\ncreated: 2025-05-01T12:00:00+00:00\n---\n\n# Export\n\nThe walrus report.\n",
+    )
+    .unwrap();
+
+    let search = || {
+        let output = notelog(notes_dir)
+            .args(["search", "walrus"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert!(!search().contains("Export"));
+
+    fs::write(
+        notes_dir.join("config.toml"),
+        "note_extensions = [\"md\", \"markdown\"]\n",
+    )
+    .unwrap();
+    let stdout = search();
+    assert!(stdout.contains("Export"), "{}", stdout);
+
+    // New notes are still saved as .md
+    let output = notelog(notes_dir)
+        .args(["add", "Another note"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(saved_path(&output).extension().unwrap(), "md");
+}
+
+#[test]
+fn test_prune() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    fs::write(notes_dir.join("config.toml"), "[retention]\nscratch = 30\n").unwrap();
+
+    let month_dir = notes_dir.join("2020/01_January");
+    fs::create_dir_all(&month_dir).unwrap();
+    let mut paths = Vec::new();
+    for (title, tags) in [("Old", "[scratch]"), ("Kept", "[scratch, work]")] {
+        let path = month_dir.join(format!("2020-01-01T12-00 {}.md", title));
+        let note = format!(
+            "---\ncreated: 2020-01-01T12:00:00+00:00\ntags: {}\n---\n\n# {}\n",
+            tags, title
+        );
+        fs::write(&path, note).unwrap();
+        paths.push(path);
+    }
+    let output = notelog(notes_dir)
+        .args(["add", "New", "+scratch"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    paths.push(saved_path(&output));
+
+    // Without a terminal or --yes, the expired notes are only listed
+    let output = notelog(notes_dir).arg("prune").output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("1 notes expired; run with --yes"),
+        "{}",
+        stdout
+    );
+    assert!(paths[0].exists());
+
+    let output = notelog(notes_dir)
+        .args(["prune", "--yes"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Deleted 1 expired notes."));
+    assert!(!paths[0].exists());
+    assert!(paths[1].exists());
+    assert!(paths[2].exists());
+}
+
+#[test]
+fn test_cat_prints_plain_markdown_when_piped() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Deploy **done**", "+ops"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let content = fs::read_to_string(saved_path(&output)).unwrap();
+    let id = content
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap()
+        .to_string();
+
+    let output = notelog(notes_dir).args(["cat", &id]).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // A header line instead of the frontmatter, and no escape codes
+    let (header, body) = stdout.split_once("\n\n").unwrap();
+    assert!(header.ends_with("  +ops"), "{}", header);
+    assert!(
+        !stdout.contains("---") && !stdout.contains('\x1b'),
+        "{}",
+        stdout
+    );
+    assert!(body.contains("Deploy **done**"), "{}", body);
+}
+
+#[test]
+fn test_db_optimize() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Something", "to", "index"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = notelog(notes_dir)
+        .args(["db", "optimize"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("Optimized the database in "),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(" before, ") && stdout.contains("KiB after"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_date_format() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let month_dir = notes_dir.join("2025").join("05_May");
+    fs::create_dir_all(&month_dir).unwrap();
+    fs::write(
+        month_dir.join("2025-05-15T12-00 Standup.md"),
+        "---\ncreated: 2025-05-15T12:00:00+00:00\n---\n\n# Standup\n",
+    )
+    .unwrap();
+
+    fs::write(
+        notes_dir.join("config.toml"),
+        "date_format = \"%d.%m.%Y\"\n",
+    )
+    .unwrap();
+    let output = notelog(notes_dir).arg("list").output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("15.05.2025"), "{}", stdout);
+
+    // Dates in the TSV listing stay ISO 8601
+    let output = notelog(notes_dir)
+        .args(["list", "--format", "tsv"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\t2025-05-15T"), "{}", stdout);
+
+    fs::write(
+        notes_dir.join("config.toml"),
+        "date_format = \"%d.%m.%Q\"\n",
+    )
+    .unwrap();
+    let output = notelog(notes_dir).arg("list").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown strftime specifier"), "{}", stderr);
+}
+
+#[test]
+fn test_last_falls_back_to_the_index() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    // A note outside of the year and month directories
+    let project_dir = notes_dir.join("project");
+    fs::create_dir_all(&project_dir).unwrap();
+    fs::write(
+        project_dir.join("2025-05-15T12-00 Kickoff.md"),
+        "---\ncreated: 2025-05-15T12:00:00+00:00\n---\n\n# Kickoff\n",
+    )
+    .unwrap();
+
+    let last = |args: &[&str]| {
+        let output = notelog(notes_dir).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    // Found by the fallback, as there are no year directories
+    assert!(last(&["last", "--print"]).contains("# Kickoff"));
+
+    // A year directory with an older note hides it from the directory walk,
+    // but not from the index
+    let month_dir = notes_dir.join("2025").join("04_April");
+    fs::create_dir_all(&month_dir).unwrap();
+    fs::write(
+        month_dir.join("2025-04-01T12-00 Older.md"),
+        "---\ncreated: 2025-04-01T12:00:00+00:00\n---\n\n# Older\n",
+    )
+    .unwrap();
+    assert!(last(&["last", "--print"]).contains("# Older"));
+    assert!(last(&["last", "--print", "--from-index"]).contains("# Kickoff"));
+}
+
+#[test]
+fn test_tag_suggest_cleanup() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    for (text, tag) in [
+        ("Monday standup", "+stand-up"),
+        ("Tuesday standup", "+stand-up"),
+        ("Wednesday standup", "+standup"),
+        ("Unrelated", "+rust"),
+    ] {
+        let output = notelog(notes_dir)
+            .args(["add", text, tag])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    let output = notelog(notes_dir)
+        .args(["tag", "suggest-cleanup"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "  1) +stand-up (2)  <-  +standup (1)\n");
+
+    let mut command = notelog(notes_dir);
+    command.args(["tag", "suggest-cleanup", "--apply"]);
+    let output = run_with_stdin(command, "1\n");
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Retagged 1 notes with +stand-up"),
+        "{}",
+        stdout
+    );
+
+    let output = notelog(notes_dir)
+        .args(["search", "+stand-up"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 4, "{}", stdout);
+    let output = notelog(notes_dir)
+        .args(["tag", "suggest-cleanup"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "No near-duplicate tags found.\n"
+    );
+}
+
+#[test]
+fn test_export_html() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let output_dir = TempDir::new().unwrap();
+
+    let output = notelog(notes_dir)
+        .args(["add", "Shipped the <release>", "+project"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let note = fs::read_to_string(saved_path(&output)).unwrap();
+    let id = note
+        .lines()
+        .find_map(|line| line.strip_prefix("id: "))
+        .unwrap();
+
+    let output = notelog(notes_dir)
+        .args(["export", "--format", "html", "--output"])
+        .arg(output_dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Exported 1 notes"));
+
+    let index = fs::read_to_string(output_dir.path().join("index.html")).unwrap();
+    assert!(
+        index.contains(&format!("href=\"{}.html\"", id)),
+        "{}",
+        index
+    );
+    let page = fs::read_to_string(output_dir.path().join(format!("{}.html", id))).unwrap();
+    assert!(page.contains("Shipped the &lt;release&gt;"), "{}", page);
+    assert!(output_dir.path().join("tag-project.html").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_saves_the_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog(notes_dir)
+        .args([
+            "exec",
+            "+ci",
+            "--",
+            "sh",
+            "-c",
+            "echo hello; echo oops >&2; exit 3",
+        ])
+        .output()
+        .unwrap();
+
+    // The command failed, so notelog does too, but only after saving the note
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("hello\n"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("oops\n"), "{}", stderr);
+    assert!(stderr.contains("exit code 3"), "{}", stderr);
+
+    let note = fs::read_to_string(saved_path(&output)).unwrap();
+    assert!(note.contains("  - exec\n  - ci\n"), "{}", note);
+    assert!(
+        note.contains("# sh -c 'echo hello; echo oops >&2; exit 3'\n\n- Result: exit code 3\n"),
+        "{}",
+        note
+    );
+    assert!(
+        note.contains("## Output\n\n```text\nhello\n```\n"),
+        "{}",
+        note
+    );
+    assert!(
+        note.contains("## Errors\n\n```text\noops\n```\n"),
+        "{}",
+        note
+    );
+
+    // Without `--`, a command is taken for a tag
+    let output = notelog(notes_dir).args(["exec", "true"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_daemon() {
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    let output = notelog(notes_dir)
+        .args(["add", "The zeppelin landed", "+airships"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let run = |args: &[&str]| {
+        let output = notelog(notes_dir).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    assert!(run(&["daemon", "status"]).contains("No daemon is running"));
+
+    // Killed if the test fails, so that the daemon does not outlive it
+    struct Daemon(std::process::Child);
+    impl Drop for Daemon {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+    let mut daemon = Daemon(
+        notelog(notes_dir)
+            .arg("daemon")
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let started = Instant::now();
+    while !run(&["daemon", "status"]).contains("A daemon is running") {
+        assert!(started.elapsed() < Duration::from_secs(30), "no daemon");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // Notes added while the daemon runs are found once it indexed them
+    let output = notelog(notes_dir)
+        .args(["add", "The airship was late", "+airships"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    while !run(&["search", "airship"]).contains("late") {
+        assert!(started.elapsed() < Duration::from_secs(30), "not indexed");
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(run(&["search", "zeppelin"]).contains("The zeppelin landed"));
+    assert_eq!(run(&["list", "--plain"]).lines().count(), 2);
+
+    // The daemon times the searches it answered
+    let status = run(&["daemon", "status", "--metrics"]);
+    assert!(
+        status
+            .lines()
+            .any(|line| line.starts_with("search ") && line.contains(" runs, mean ")),
+        "{}",
+        status
+    );
+    assert!(status.contains("indexing_pass"), "{}", status);
+
+    // Errors are reported as without the daemon
+    let output = notelog(notes_dir).args(["cat", "zz"]).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No note found with ID prefix 'zz'"),
+        "{}",
+        stderr
+    );
+
+    assert!(run(&["daemon", "stop"]).contains("Stopped the daemon"));
+    assert!(daemon.0.wait().unwrap().success());
+    assert!(run(&["daemon", "status"]).contains("No daemon is running"));
+}
+
+#[test]
+fn test_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    fs::write(notes_dir.join("config.toml"), "default_tags = [\"log\"]\n").unwrap();
+
+    let output = notelog(notes_dir)
+        .args(["config", "--json"])
+        .env("EDITOR", "vim")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["notes_dir"]["value"], notes_dir.to_str().unwrap());
+    assert_eq!(json["notes_dir"]["source"], "flag");
+    assert_eq!(json["config_file"]["source"], "env");
+    assert_eq!(json["editor"]["value"], "vim");
+    assert_eq!(json["editor"]["source"], "env");
+    assert_eq!(json["default_tags"]["value"][0], "log");
+    assert_eq!(json["default_tags"]["source"], "config");
+
+    // A missing notes directory is reported with where it was set
+    let missing = notes_dir.join("missing");
+    let without_flag = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_notelog"))
+            .args(args)
+            .env("NOTELOG_CONFIG", notes_dir.join("config.toml"))
+            .env("NOTELOG_DIR", &missing)
+            .env_remove("NOTELOG_PROFILE")
+            .output()
+            .unwrap()
+    };
+    let output = without_flag(&["config"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!("notes_dir            {} (env)", missing.display());
+    assert!(stdout.contains(&expected), "{}", stdout);
+
+    let output = without_flag(&["list"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("set by NOTELOG_DIR"), "{}", stderr);
+}
@@ -0,0 +1,82 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+use tempfile::TempDir;
+
+/// Install a post-add hook that writes its arguments to a marker file
+fn write_post_add_hook(notes_dir: &Path) {
+    let hooks_dir = notes_dir.join(".notelog").join("hooks");
+    fs::create_dir_all(&hooks_dir).unwrap();
+
+    let path = hooks_dir.join("post-add");
+    fs::write(
+        &path,
+        "#!/bin/sh\necho \"$1|$3\" > \"$NOTELOG_DIR/marker\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Run `notelog add` in the given notes directory
+fn notelog_add(notes_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_notelog"))
+        .arg("-d")
+        .arg(notes_dir)
+        .args(args)
+        .env("NOTELOG_CONFIG", notes_dir.join("config.toml"))
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_post_add_hook_runs_after_add() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    write_post_add_hook(notes_dir);
+
+    let output = notelog_add(notes_dir, &["add", "-t", "Hooked", "Some content"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let marker = fs::read_to_string(notes_dir.join("marker")).unwrap();
+    let (path, title) = marker.trim_end().split_once('|').unwrap();
+    assert!(path.ends_with("Hooked.md"));
+    assert!(notes_dir.join(path).is_file());
+    assert_eq!(title, "Hooked");
+}
+
+#[test]
+fn test_hooks_can_be_disabled() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+    write_post_add_hook(notes_dir);
+
+    let output = notelog_add(notes_dir, &["--no-hooks", "add", "Some content"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!notes_dir.join("marker").exists());
+
+    fs::write(notes_dir.join("config.toml"), "disable_hooks = true\n").unwrap();
+    let output = notelog_add(notes_dir, &["add", "More content"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!notes_dir.join("marker").exists());
+}
+
+#[test]
+fn test_failing_hook_does_not_fail_add() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let hooks_dir = notes_dir.join(".notelog").join("hooks");
+    fs::create_dir_all(&hooks_dir).unwrap();
+    let path = hooks_dir.join("post-add");
+    fs::write(&path, "#!/bin/sh\nexit 1\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = notelog_add(notes_dir, &["add", "Some content"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("post-add hook failed"));
+}
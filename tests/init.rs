@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use chrono::{Datelike, Local};
+use tempfile::TempDir;
+
+/// Run `notelog init` with the configuration file in the given directory
+fn notelog_init(config_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_notelog"))
+        .arg("init")
+        .args(args)
+        .env("NOTELOG_CONFIG", config_dir.join("config.toml"))
+        .env_remove("NOTELOG_DIR")
+        .env_remove("NOTELOG_PROFILE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_init_creates_notes_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path().join("NoteLog");
+
+    let output = notelog_init(temp_dir.path(), &[notes_dir.to_str().unwrap(), "--git"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("What next?"));
+
+    let now = Local::now();
+    let year_dir = notes_dir.join(now.year().to_string());
+    let month_dirs = fs::read_dir(&year_dir).unwrap().collect::<Vec<_>>();
+    assert_eq!(month_dirs.len(), 1);
+    let month_dir = month_dirs[0].as_ref().unwrap().file_name();
+    assert!(
+        month_dir
+            .to_string_lossy()
+            .starts_with(&format!("{:02}", now.month()))
+    );
+
+    assert!(notes_dir.join(".notelogignore").is_file());
+    assert!(notes_dir.join(".notes.db").is_file());
+    assert!(notes_dir.join(".git").is_dir());
+    assert!(
+        fs::read_to_string(notes_dir.join(".gitignore"))
+            .unwrap()
+            .contains(".notes.db")
+    );
+
+    let config = fs::read_to_string(temp_dir.path().join("config.toml")).unwrap();
+    let notes_dir = notes_dir.canonicalize().unwrap();
+    assert!(config.contains(&format!("notes_dir = \"{}\"", notes_dir.display())));
+}
+
+#[test]
+fn test_init_keeps_existing_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path().join("NoteLog");
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, "editor = \"vim\"\n").unwrap();
+
+    let output = notelog_init(temp_dir.path(), &[notes_dir.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+
+    assert_eq!(
+        fs::read_to_string(config_path).unwrap(),
+        "editor = \"vim\"\n"
+    );
+    assert!(!notes_dir.join(".git").exists());
+}
+
+#[test]
+fn test_init_refuses_non_empty_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path().join("NoteLog");
+    fs::create_dir(&notes_dir).unwrap();
+    fs::write(notes_dir.join("todo.txt"), "buy milk").unwrap();
+
+    let output = notelog_init(temp_dir.path(), &[notes_dir.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    assert!(!notes_dir.join(".notes.db").exists());
+
+    let output = notelog_init(temp_dir.path(), &[notes_dir.to_str().unwrap(), "--force"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(notes_dir.join(".notes.db").is_file());
+    assert!(notes_dir.join("todo.txt").is_file());
+}
@@ -0,0 +1,47 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+use tempfile::TempDir;
+
+/// Run notelog in the given notes directory with restricted permissions
+fn notelog_restricted(notes_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_notelog"))
+        .arg("-d")
+        .arg(notes_dir)
+        .args(args)
+        .env("NOTELOG_CONFIG", notes_dir.join("config.toml"))
+        .env("NOTELOG_RESTRICT_PERMISSIONS", "1")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn mode(path: &Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[test]
+fn test_restricted_permissions() {
+    let temp_dir = TempDir::new().unwrap();
+    let notes_dir = temp_dir.path();
+
+    let output = notelog_restricted(notes_dir, &["add", "-t", "Secret", "Some content"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // The note and the year and month directories it was saved in
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let note_path = Path::new(stdout.trim().strip_prefix("Note saved to: ").unwrap());
+    assert_eq!(mode(note_path), 0o600);
+    let month_dir = note_path.parent().unwrap();
+    assert_eq!(mode(month_dir), 0o700);
+    assert_eq!(mode(month_dir.parent().unwrap()), 0o700);
+
+    // The database is created by the first command that searches
+    let output = notelog_restricted(notes_dir, &["list"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mode(&notes_dir.join(".notes.db")), 0o600);
+}